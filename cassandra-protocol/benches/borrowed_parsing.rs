@@ -0,0 +1,136 @@
+//! Compares allocations and time spent parsing hot frame bodies with owned and borrowed APIs.
+//!
+//! Run with `cargo bench -p cassandra-protocol --bench borrowed_parsing`.
+
+mod common;
+
+use cassandra_protocol::consistency::Consistency;
+use cassandra_protocol::frame::message_error::{
+    ErrorBody, ErrorBodyBorrowed, ErrorType, UnavailableError,
+};
+use cassandra_protocol::frame::message_execute::{BodyReqExecuteBorrowed, BodyReqExecuteOwned};
+use cassandra_protocol::frame::message_query::{BodyReqQuery, BodyReqQueryBorrowed};
+use cassandra_protocol::frame::message_result::{
+    BodyResResultRows, BodyResResultRowsBorrowed, ColSpec, ColType, ColTypeOption, RowsMetadata,
+    RowsMetadataFlags, TableSpec,
+};
+use cassandra_protocol::frame::{FromCursor, FromCursorBorrowed, Serialize, Version};
+use cassandra_protocol::query::{QueryParams, QueryValues};
+use cassandra_protocol::types::value::Value;
+use cassandra_protocol::types::{CBytes, CBytesShort};
+use std::hint::black_box;
+use std::io::Cursor;
+
+const ITERATIONS: usize = 100_000;
+
+fn measure(name: &str, data: &[u8], parse: impl Fn(&[u8])) {
+    common::measure(name, "parse", ITERATIONS, || parse(black_box(data)));
+}
+
+macro_rules! compare {
+    ($name:expr, $owned:ty, $borrowed:ty, $data:expr $(,)?) => {{
+        let data: &[u8] = $data;
+        measure(concat!($name, " owned"), data, |data| {
+            black_box(<$owned>::from_cursor(&mut Cursor::new(data), Version::V4).unwrap());
+        });
+        measure(concat!($name, " borrowed"), data, |data| {
+            black_box(
+                <$borrowed>::from_cursor_borrowed(&mut Cursor::new(data), Version::V4).unwrap(),
+            );
+        });
+    }};
+}
+
+fn query_params() -> QueryParams {
+    QueryParams {
+        consistency: Consistency::LocalQuorum,
+        values: Some(QueryValues::SimpleValues(vec![
+            Value::new(1),
+            Value::new("name"),
+            Value::new(vec![0u8; 64]),
+        ])),
+        page_size: Some(5000),
+        paging_state: Some(CBytes::new(vec![0; 32])),
+        ..Default::default()
+    }
+}
+
+fn rows() -> BodyResResultRows {
+    let col_spec = |name: &str, id| ColSpec {
+        table_spec: None,
+        name: name.into(),
+        col_type: ColTypeOption { id, value: None },
+    };
+
+    BodyResResultRows {
+        metadata: RowsMetadata {
+            flags: RowsMetadataFlags::GLOBAL_TABLE_SPACE,
+            columns_count: 3,
+            paging_state: None,
+            new_metadata_id: None,
+            global_table_spec: Some(TableSpec {
+                ks_name: "ks".into(),
+                table_name: "users".into(),
+            }),
+            col_specs: vec![
+                col_spec("id", ColType::Int),
+                col_spec("name", ColType::Varchar),
+                col_spec("avatar", ColType::Blob),
+            ],
+        },
+        rows_count: 100,
+        rows_content: (0..100)
+            .map(|id: i32| {
+                vec![
+                    CBytes::new(id.to_be_bytes().to_vec()),
+                    CBytes::new(format!("user {id}").into_bytes()),
+                    CBytes::new(vec![0; 64]),
+                ]
+            })
+            .collect(),
+        protocol_version: Version::V4,
+    }
+}
+
+fn main() {
+    let query = BodyReqQuery {
+        query: "SELECT * FROM ks.users WHERE id = ? AND name = ? AND avatar = ?".into(),
+        query_params: query_params(),
+    };
+    compare!(
+        "QUERY",
+        BodyReqQuery,
+        BodyReqQueryBorrowed,
+        &query.serialize_to_vec(Version::V4)
+    );
+
+    let execute = BodyReqExecuteOwned::new(CBytesShort::new(vec![0; 16]), None, query_params());
+    compare!(
+        "EXECUTE",
+        BodyReqExecuteOwned,
+        BodyReqExecuteBorrowed,
+        &execute.serialize_to_vec(Version::V4),
+    );
+
+    compare!(
+        "ROWS",
+        BodyResResultRows,
+        BodyResResultRowsBorrowed,
+        &rows().serialize_to_vec(Version::V4),
+    );
+
+    let error = ErrorBody {
+        message: "Cannot achieve consistency level QUORUM".into(),
+        ty: ErrorType::Unavailable(UnavailableError {
+            cl: Consistency::Quorum,
+            required: 2,
+            alive: 1,
+        }),
+    };
+    compare!(
+        "ERROR",
+        ErrorBody,
+        ErrorBodyBorrowed,
+        &error.serialize_to_vec(Version::V4)
+    );
+}
@@ -0,0 +1,71 @@
+//! Heap allocation counting and timing shared by benches. Declaring this module installs a global
+//! allocator counting allocations, so measurements can report them along with elapsed time.
+
+// not every bench uses every helper
+#![allow(dead_code)]
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+struct CountingAllocator;
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+/// Allocations and time counted from the moment of starting a measurement.
+pub struct Measurement {
+    allocations: usize,
+    start: Instant,
+}
+
+impl Measurement {
+    pub fn start() -> Self {
+        Measurement {
+            allocations: ALLOCATIONS.load(Ordering::Relaxed),
+            start: Instant::now(),
+        }
+    }
+
+    /// Returns the number of allocations and time elapsed since the start.
+    pub fn finish(self) -> (usize, Duration) {
+        let elapsed = self.start.elapsed();
+        let allocations = ALLOCATIONS.load(Ordering::Relaxed) - self.allocations;
+
+        (allocations, elapsed)
+    }
+}
+
+/// Runs given closure `iterations` times and prints allocations and time spent per `unit`, which
+/// is a single run.
+pub fn measure(name: &str, unit: &str, iterations: usize, mut run: impl FnMut()) {
+    let measurement = Measurement::start();
+
+    for _ in 0..iterations {
+        run();
+    }
+
+    let (allocations, elapsed) = measurement.finish();
+
+    println!(
+        "{:<16} {:>10.2} allocations/{} {:>12.1} ns/{}",
+        name,
+        allocations as f64 / iterations as f64,
+        unit,
+        elapsed.as_nanos() as f64 / iterations as f64,
+        unit
+    );
+}
@@ -0,0 +1,115 @@
+//! Compares allocations and time spent building QUERY, PREPARE and BATCH envelopes from static
+//! statement text, by copying the text into owned bodies and by borrowing it. Reused bodies are
+//! compared with cloning them for each execution.
+//!
+//! Run with `cargo bench -p cassandra-protocol --bench query_construction`.
+
+mod common;
+
+use cassandra_protocol::consistency::Consistency;
+use cassandra_protocol::frame::message_batch::{BatchQuery, BatchQuerySubj, BatchType};
+use cassandra_protocol::frame::message_query::BodyReqQuery;
+use cassandra_protocol::frame::{Envelope, Flags, Version};
+use cassandra_protocol::query::{QueryBatch, QueryParams, QueryValues};
+use cassandra_protocol::types::value::Value;
+use std::hint::black_box;
+
+const ITERATIONS: usize = 100_000;
+
+const QUERY: &str = "SELECT id, name, email, created_at FROM ks.users WHERE id = ? AND bucket = ?";
+
+fn measure(name: &str, build: impl Fn() -> Envelope) {
+    common::measure(name, "envelope", ITERATIONS, || {
+        black_box(build());
+    });
+}
+
+// created for each envelope in both cases, like parameters of each executed statement
+fn query_params() -> QueryParams {
+    QueryParams {
+        consistency: Consistency::LocalQuorum,
+        values: Some(QueryValues::SimpleValues(vec![
+            Value::new(1i64),
+            Value::new(2i32),
+        ])),
+        ..Default::default()
+    }
+}
+
+fn batch() -> QueryBatch {
+    QueryBatch::new(
+        BatchType::Unlogged,
+        (0..8)
+            .map(|index| BatchQuery {
+                subject: BatchQuerySubj::QueryString(QUERY.into()),
+                values: QueryValues::SimpleValues(vec![Value::new(index), Value::new(2i32)]),
+            })
+            .collect(),
+        Consistency::LocalQuorum,
+        None,
+        None,
+        Some("ks".into()),
+        None,
+    )
+}
+
+fn main() {
+    // what executing a query did before borrowing the statement text
+    measure("query owned", || {
+        Envelope::new_query(
+            BodyReqQuery {
+                query: black_box(QUERY).to_string(),
+                query_params: query_params(),
+            },
+            Flags::empty(),
+            Version::V4,
+        )
+        .unwrap()
+    });
+    measure("query borrowed", || {
+        Envelope::new_query_borrowed(
+            black_box(QUERY),
+            &query_params(),
+            Flags::empty(),
+            Version::V4,
+        )
+        .unwrap()
+    });
+
+    // re-executing the same statement
+    let query = BodyReqQuery {
+        query: QUERY.to_string(),
+        query_params: query_params(),
+    };
+    measure("query cloned", || {
+        Envelope::new_query(black_box(&query).clone(), Flags::empty(), Version::V5).unwrap()
+    });
+    measure("query reused", || {
+        Envelope::new_query_ref(black_box(&query), Flags::empty(), Version::V5).unwrap()
+    });
+
+    measure("prepare owned", || {
+        Envelope::new_req_prepare(
+            black_box(QUERY).to_string(),
+            Some("ks".to_string()),
+            Flags::empty(),
+            Version::V5,
+        )
+    });
+    measure("prepare borrowed", || {
+        Envelope::new_req_prepare_borrowed(
+            black_box(QUERY),
+            Some("ks"),
+            Flags::empty(),
+            Version::V5,
+        )
+    });
+
+    let batch = batch();
+    measure("batch cloned", || {
+        Envelope::new_req_batch(black_box(&batch).clone(), Flags::empty(), Version::V5).unwrap()
+    });
+    measure("batch reused", || {
+        Envelope::new_req_batch_ref(black_box(&batch), Flags::empty(), Version::V5).unwrap()
+    });
+}
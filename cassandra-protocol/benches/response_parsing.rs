@@ -0,0 +1,92 @@
+//! Compares allocations and time spent reading paging metadata and rows from a large RESULT
+//! envelope when parsing its body for each access and when parsing it once.
+//!
+//! Run with `cargo bench -p cassandra-protocol --bench response_parsing`.
+
+mod common;
+
+use cassandra_protocol::frame::message_result::{
+    BodyResResultRows, ColSpec, ColType, ColTypeOption, ResResultBody, RowsMetadata,
+    RowsMetadataFlags, TableSpec,
+};
+use cassandra_protocol::frame::{Direction, Envelope, Flags, Opcode, Serialize, Version};
+use cassandra_protocol::types::CBytes;
+use std::hint::black_box;
+
+const ITERATIONS: usize = 1_000;
+
+fn measure(name: &str, envelope: &Envelope, read: impl Fn(Envelope)) {
+    common::measure(name, "response", ITERATIONS, || {
+        read(black_box(envelope.clone()))
+    });
+}
+
+fn envelope() -> Envelope {
+    let col_spec = |name: &str, id| ColSpec {
+        table_spec: None,
+        name: name.into(),
+        col_type: ColTypeOption { id, value: None },
+    };
+
+    let rows = BodyResResultRows {
+        metadata: RowsMetadata {
+            flags: RowsMetadataFlags::GLOBAL_TABLE_SPACE | RowsMetadataFlags::HAS_MORE_PAGES,
+            columns_count: 3,
+            paging_state: Some(CBytes::new(vec![0; 32])),
+            new_metadata_id: None,
+            global_table_spec: Some(TableSpec {
+                ks_name: "ks".into(),
+                table_name: "users".into(),
+            }),
+            col_specs: vec![
+                col_spec("id", ColType::Int),
+                col_spec("name", ColType::Varchar),
+                col_spec("avatar", ColType::Blob),
+            ],
+        },
+        rows_count: 5000,
+        rows_content: (0..5000)
+            .map(|id: i32| {
+                vec![
+                    CBytes::new(id.to_be_bytes().to_vec()),
+                    CBytes::new(format!("user {id}").into_bytes()),
+                    CBytes::new(vec![0; 64]),
+                ]
+            })
+            .collect(),
+        protocol_version: Version::V4,
+    };
+
+    Envelope::new(
+        Version::V4,
+        Direction::Response,
+        Flags::empty(),
+        Opcode::Result,
+        1,
+        ResResultBody::Rows(rows).serialize_to_vec(Version::V4),
+        None,
+        vec![],
+    )
+}
+
+fn main() {
+    let envelope = envelope();
+
+    // reading paging metadata from one body and rows from another, as done before responses were
+    // parsed along with their envelopes
+    measure("parsed twice", &envelope, |envelope| {
+        let paging_state = envelope
+            .response_body()
+            .unwrap()
+            .as_rows_metadata()
+            .and_then(|metadata| metadata.paging_state.clone());
+        black_box(paging_state);
+        black_box(envelope.response_body().unwrap().into_rows());
+    });
+
+    measure("parsed once", &envelope, |envelope| {
+        let response = envelope.into_parsed_response().unwrap();
+        black_box(response.paging_state().cloned());
+        black_box(response.into_rows());
+    });
+}
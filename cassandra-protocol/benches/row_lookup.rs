@@ -0,0 +1,88 @@
+//! Measures column lookups by name in wide rows, compared with lookups by index and a linear scan
+//! over column metadata.
+//!
+//! Run with `cargo bench -p cassandra-protocol --bench row_lookup`.
+
+use cassandra_protocol::frame::message_result::{
+    BodyResResultRows, ColSpec, ColType, ColTypeOption, RowsMetadata, RowsMetadataFlags,
+};
+use cassandra_protocol::frame::Version;
+use cassandra_protocol::types::rows::Row;
+use cassandra_protocol::types::{CBytes, IntoRustByIndex, IntoRustByName};
+use std::hint::black_box;
+use std::time::Instant;
+
+const COLUMNS: usize = 100;
+const ROWS: usize = 1000;
+
+fn measure(name: &str, rows: &[Row], lookup: impl Fn(&Row, usize) -> i32) {
+    let start = Instant::now();
+    let mut sum = 0i64;
+
+    for row in rows {
+        for column in 0..COLUMNS {
+            sum += lookup(black_box(row), column) as i64;
+        }
+    }
+
+    let elapsed = start.elapsed();
+    black_box(sum);
+
+    println!(
+        "{:<12} {:>8.1} ns/column",
+        name,
+        elapsed.as_nanos() as f64 / (ROWS * COLUMNS) as f64
+    );
+}
+
+fn main() {
+    let names: Vec<String> = (0..COLUMNS).map(|i| format!("column_{i}")).collect();
+    let metadata = RowsMetadata {
+        flags: RowsMetadataFlags::empty(),
+        columns_count: COLUMNS as i32,
+        paging_state: None,
+        new_metadata_id: None,
+        global_table_spec: None,
+        col_specs: names
+            .iter()
+            .map(|name| ColSpec {
+                table_spec: None,
+                name: name.clone(),
+                col_type: ColTypeOption {
+                    id: ColType::Int,
+                    value: None,
+                },
+            })
+            .collect(),
+    };
+
+    let rows = Row::from_body(BodyResResultRows {
+        metadata: metadata.clone(),
+        rows_count: ROWS as i32,
+        rows_content: (0..ROWS)
+            .map(|_| {
+                (0..COLUMNS as i32)
+                    .map(|i| CBytes::new(i.to_be_bytes().to_vec()))
+                    .collect()
+            })
+            .collect(),
+        protocol_version: Version::V4,
+    });
+
+    measure("by index", &rows, |row, column| {
+        row.get_r_by_index(column).unwrap()
+    });
+    measure("by name", &rows, |row, column| {
+        row.get_r_by_name(&names[column]).unwrap()
+    });
+
+    // resolves the name the way rows did before sharing a column index
+    measure("linear scan", &rows, |row, column| {
+        let index = metadata
+            .col_specs
+            .iter()
+            .position(|spec| spec.name == names[column])
+            .unwrap();
+        row.get_r_by_index(index).unwrap()
+    });
+}
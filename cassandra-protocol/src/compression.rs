@@ -7,34 +7,46 @@
 /// must never be compressed.  However, once the STARTUP envelope has been received
 /// by the server, messages can be compressed (including the response to the STARTUP
 /// request).
+use crate::frame::Version;
+use crate::stopwatch::Stopwatch;
 use derive_more::Display;
+#[cfg(feature = "snappy")]
 use snap::raw::{Decoder, Encoder};
 use std::convert::{From, TryInto};
 use std::error::Error;
 use std::fmt;
 use std::io;
 use std::result;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
 
 type Result<T> = result::Result<T, CompressionError>;
 
 pub const LZ4: &str = "lz4";
 pub const SNAPPY: &str = "snappy";
 
-/// An error which may occur during encoding or decoding frame body. As there are only two types
-/// of compressors it contains two related enum options.
+/// An error which may occur during encoding or decoding frame body.
 #[derive(Debug)]
 pub enum CompressionError {
     /// Snappy error.
+    #[cfg(feature = "snappy")]
     Snappy(snap::Error),
     /// Lz4 error.
     Lz4(io::Error),
+    /// The compression algorithm is not enabled in this build.
+    Unsupported(Compression),
 }
 
 impl fmt::Display for CompressionError {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match *self {
+            #[cfg(feature = "snappy")]
             CompressionError::Snappy(ref err) => write!(f, "Snappy Error: {err:?}"),
             CompressionError::Lz4(ref err) => write!(f, "Lz4 Error: {err:?}"),
+            CompressionError::Unsupported(compression) => {
+                write!(f, "Unsupported compression: {compression}")
+            }
         }
     }
 }
@@ -42,8 +54,10 @@ impl fmt::Display for CompressionError {
 impl Error for CompressionError {
     fn source(&self) -> Option<&(dyn Error + 'static)> {
         match *self {
+            #[cfg(feature = "snappy")]
             CompressionError::Snappy(ref err) => Some(err),
             CompressionError::Lz4(ref err) => Some(err),
+            CompressionError::Unsupported(_) => None,
         }
     }
 }
@@ -51,6 +65,7 @@ impl Error for CompressionError {
 impl Clone for CompressionError {
     fn clone(&self) -> Self {
         match self {
+            #[cfg(feature = "snappy")]
             CompressionError::Snappy(error) => CompressionError::Snappy(error.clone()),
             CompressionError::Lz4(error) => CompressionError::Lz4(io::Error::new(
                 error.kind(),
@@ -59,6 +74,9 @@ impl Clone for CompressionError {
                     .map(|error| error.to_string())
                     .unwrap_or_default(),
             )),
+            CompressionError::Unsupported(compression) => {
+                CompressionError::Unsupported(*compression)
+            }
         }
     }
 }
@@ -68,7 +86,8 @@ impl Clone for CompressionError {
 pub enum Compression {
     /// [lz4](https://code.google.com/p/lz4/) compression
     Lz4,
-    /// [snappy](https://code.google.com/p/snappy/) compression
+    /// [snappy](https://code.google.com/p/snappy/) compression, available with the `snappy`
+    /// feature
     Snappy,
     /// No compression
     None,
@@ -96,6 +115,24 @@ impl Compression {
         }
     }
 
+    /// Encodes `bytes` and records sizes and time taken in given statistics.
+    pub fn encode_with_stats(&self, bytes: &[u8], stats: &CompressionStats) -> Result<Vec<u8>> {
+        let start = Stopwatch::start();
+        let encoded = self.encode(bytes)?;
+        stats.record_compression(bytes.len(), encoded.len(), start.elapsed());
+
+        #[cfg(debug_assertions)]
+        if stats.verifies_round_trip() {
+            assert_eq!(
+                self.decode(encoded.clone()).ok().as_deref(),
+                Some(bytes),
+                "{self} compression round trip failed!"
+            );
+        }
+
+        Ok(encoded)
+    }
+
     /// Checks if current compression actually compresses data.
     #[inline]
     pub fn is_compressed(self) -> bool {
@@ -111,6 +148,16 @@ impl Compression {
         }
     }
 
+    /// Decodes `bytes` and records sizes and time taken in given statistics.
+    pub fn decode_with_stats(&self, bytes: Vec<u8>, stats: &CompressionStats) -> Result<Vec<u8>> {
+        let start = Stopwatch::start();
+        let compressed_len = bytes.len();
+        let decoded = self.decode(bytes)?;
+        stats.record_decompression(compressed_len, decoded.len(), start.elapsed());
+
+        Ok(decoded)
+    }
+
     /// It transforms compression method into a `&str`.
     pub fn as_str(&self) -> Option<&'static str> {
         match *self {
@@ -120,6 +167,7 @@ impl Compression {
         }
     }
 
+    #[cfg(feature = "snappy")]
     fn encode_snappy(bytes: &[u8]) -> Result<Vec<u8>> {
         let mut encoder = Encoder::new();
         encoder
@@ -127,6 +175,12 @@ impl Compression {
             .map_err(CompressionError::Snappy)
     }
 
+    #[cfg(not(feature = "snappy"))]
+    fn encode_snappy(_bytes: &[u8]) -> Result<Vec<u8>> {
+        Err(CompressionError::Unsupported(Compression::Snappy))
+    }
+
+    #[cfg(feature = "snappy")]
     fn decode_snappy(bytes: Vec<u8>) -> Result<Vec<u8>> {
         let mut decoder = Decoder::new();
         decoder
@@ -134,6 +188,11 @@ impl Compression {
             .map_err(CompressionError::Snappy)
     }
 
+    #[cfg(not(feature = "snappy"))]
+    fn decode_snappy(_bytes: Vec<u8>) -> Result<Vec<u8>> {
+        Err(CompressionError::Unsupported(Compression::Snappy))
+    }
+
     fn encode_lz4(bytes: &[u8]) -> Result<Vec<u8>> {
         let len = 4 + lz4_flex::block::get_maximum_output_size(bytes.len());
         assert!(len <= i32::MAX as usize);
@@ -202,6 +261,181 @@ impl<'a> From<&'a str> for Compression {
     }
 }
 
+/// Decides which directions of traffic are compressed. The algorithm is negotiated once per
+/// connection, but before protocol V5 the compression flag is set per envelope, so the client can
+/// send requests as they are and still receive compressed responses.
+#[derive(Debug, PartialEq, Clone, Copy, Eq, Ord, PartialOrd, Hash, Display, Default)]
+pub enum CompressionMode {
+    /// Compress both requests and responses.
+    #[default]
+    Both,
+    /// Negotiate compression in STARTUP, but never compress requests. Useful when requests are
+    /// small and responses are big.
+    ResponsesOnly,
+    /// Compress requests. Servers compress all responses once compression is negotiated, so
+    /// responses are compressed as well in practice.
+    RequestsOnly,
+}
+
+impl CompressionMode {
+    /// Returns the minimum body size of compressed requests in this mode, given the configured
+    /// threshold.
+    #[inline]
+    pub fn request_compression_threshold(self, compression_threshold: usize) -> usize {
+        match self {
+            CompressionMode::ResponsesOnly => usize::MAX,
+            _ => compression_threshold,
+        }
+    }
+
+    /// Returns the mode actually in effect for given compression and protocol version, or `None`
+    /// if nothing is compressed. Since V5, compression is applied to whole frames in both
+    /// directions.
+    pub fn effective(self, compression: Compression, version: Version) -> Option<CompressionMode> {
+        if !compression.is_compressed() {
+            return None;
+        }
+
+        if version >= Version::V5 || self == CompressionMode::RequestsOnly {
+            Some(CompressionMode::Both)
+        } else {
+            Some(self)
+        }
+    }
+}
+
+/// Compression statistics, e.g. of a single connection. Counters are updated with relaxed
+/// atomics, so they are cheap to maintain, but a snapshot taken while traffic flows might mix
+/// values from different moments. Statistics can have a parent, which gets updated as well,
+/// allowing cheap aggregation. Time is not measured on `wasm32-unknown-unknown`, which has no
+/// clock.
+#[derive(Debug, Default)]
+pub struct CompressionStats {
+    uncompressed_bytes_sent: AtomicU64,
+    compressed_bytes_sent: AtomicU64,
+    compression_nanos: AtomicU64,
+    compressed_bytes_received: AtomicU64,
+    decompressed_bytes_received: AtomicU64,
+    decompression_nanos: AtomicU64,
+    verify_round_trip: bool,
+    parent: Option<Arc<CompressionStats>>,
+}
+
+impl CompressionStats {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Creates new statistics, which also update given parent.
+    pub fn with_parent(parent: Arc<CompressionStats>) -> Self {
+        CompressionStats {
+            parent: Some(parent),
+            ..Default::default()
+        }
+    }
+
+    /// Enables verification that compressed data decompresses back to the original. Useful in
+    /// tests, since it's expensive and only applies to debug builds.
+    #[must_use]
+    pub fn with_round_trip_verification(mut self) -> Self {
+        self.verify_round_trip = true;
+        self
+    }
+
+    /// Checks if compressed data should be verified by decompressing it.
+    #[inline]
+    pub fn verifies_round_trip(&self) -> bool {
+        cfg!(debug_assertions) && self.verify_round_trip
+    }
+
+    /// Records compressing outgoing data.
+    pub fn record_compression(
+        &self,
+        uncompressed_len: usize,
+        compressed_len: usize,
+        elapsed: Duration,
+    ) {
+        self.uncompressed_bytes_sent
+            .fetch_add(uncompressed_len as u64, Ordering::Relaxed);
+        self.compressed_bytes_sent
+            .fetch_add(compressed_len as u64, Ordering::Relaxed);
+        self.compression_nanos
+            .fetch_add(elapsed.as_nanos() as u64, Ordering::Relaxed);
+
+        if let Some(parent) = &self.parent {
+            parent.record_compression(uncompressed_len, compressed_len, elapsed);
+        }
+    }
+
+    /// Records decompressing incoming data.
+    pub fn record_decompression(
+        &self,
+        compressed_len: usize,
+        decompressed_len: usize,
+        elapsed: Duration,
+    ) {
+        self.compressed_bytes_received
+            .fetch_add(compressed_len as u64, Ordering::Relaxed);
+        self.decompressed_bytes_received
+            .fetch_add(decompressed_len as u64, Ordering::Relaxed);
+        self.decompression_nanos
+            .fetch_add(elapsed.as_nanos() as u64, Ordering::Relaxed);
+
+        if let Some(parent) = &self.parent {
+            parent.record_decompression(compressed_len, decompressed_len, elapsed);
+        }
+    }
+
+    /// Returns current values of the statistics.
+    pub fn snapshot(&self) -> CompressionStatsSnapshot {
+        CompressionStatsSnapshot {
+            uncompressed_bytes_sent: self.uncompressed_bytes_sent.load(Ordering::Relaxed),
+            compressed_bytes_sent: self.compressed_bytes_sent.load(Ordering::Relaxed),
+            compression_time: Duration::from_nanos(self.compression_nanos.load(Ordering::Relaxed)),
+            compressed_bytes_received: self.compressed_bytes_received.load(Ordering::Relaxed),
+            decompressed_bytes_received: self.decompressed_bytes_received.load(Ordering::Relaxed),
+            decompression_time: Duration::from_nanos(
+                self.decompression_nanos.load(Ordering::Relaxed),
+            ),
+        }
+    }
+}
+
+/// Point-in-time values of [`CompressionStats`]. Only data which went through compression is
+/// counted, i.e. envelopes below the compression threshold are not included.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct CompressionStatsSnapshot {
+    /// Size of outgoing data before compression.
+    pub uncompressed_bytes_sent: u64,
+    /// Size of outgoing data after compression.
+    pub compressed_bytes_sent: u64,
+    /// Total time spent compressing.
+    pub compression_time: Duration,
+    /// Size of incoming data before decompression.
+    pub compressed_bytes_received: u64,
+    /// Size of incoming data after decompression.
+    pub decompressed_bytes_received: u64,
+    /// Total time spent decompressing.
+    pub decompression_time: Duration,
+}
+
+impl CompressionStatsSnapshot {
+    /// Returns compressed to uncompressed size ratio of outgoing data, if anything was compressed.
+    /// Values above 1 mean compression makes data bigger.
+    pub fn sent_compression_ratio(&self) -> Option<f64> {
+        (self.uncompressed_bytes_sent > 0)
+            .then(|| self.compressed_bytes_sent as f64 / self.uncompressed_bytes_sent as f64)
+    }
+
+    /// Returns compressed to decompressed size ratio of incoming data, if anything was
+    /// decompressed. Values above 1 mean compression makes data bigger.
+    pub fn received_compression_ratio(&self) -> Option<f64> {
+        (self.decompressed_bytes_received > 0).then(|| {
+            self.compressed_bytes_received as f64 / self.decompressed_bytes_received as f64
+        })
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -267,6 +501,7 @@ mod tests {
         assert_eq!(Compression::from(none), Compression::None);
     }
 
+    #[cfg(feature = "snappy")]
     #[test]
     fn test_compression_encode_snappy() {
         let snappy_compression = Compression::Snappy;
@@ -276,6 +511,7 @@ mod tests {
             .expect("Should work without exceptions");
     }
 
+    #[cfg(feature = "snappy")]
     #[test]
     fn test_compression_decode_snappy() {
         let snappy_compression = Compression::Snappy;
@@ -284,6 +520,15 @@ mod tests {
         assert_eq!(snappy_compression.decode(encoded).unwrap(), bytes);
     }
 
+    #[cfg(not(feature = "snappy"))]
+    #[test]
+    fn test_compression_snappy_unsupported() {
+        assert!(matches!(
+            Compression::Snappy.encode(&[1, 2, 3]),
+            Err(CompressionError::Unsupported(Compression::Snappy))
+        ));
+    }
+
     #[test]
     fn test_compression_encode_lz4() {
         let snappy_compression = Compression::Lz4;
@@ -325,6 +570,7 @@ mod tests {
         assert!(decode.is_err());
     }
 
+    #[cfg(feature = "snappy")]
     #[test]
     fn test_compression_encode_snappy_with_non_utf8() {
         let snappy_compression = Compression::Snappy;
@@ -334,4 +580,61 @@ mod tests {
             .expect("Should work without exceptions");
         assert_eq!(snappy_compression.decode(encoded).unwrap(), v);
     }
+
+    #[cfg(feature = "snappy")]
+    #[test]
+    fn test_compression_stats() {
+        let parent = Arc::new(CompressionStats::new());
+        let stats = CompressionStats::with_parent(parent.clone()).with_round_trip_verification();
+        let bytes = vec![7; 1000];
+
+        for compression in [Compression::Lz4, Compression::Snappy] {
+            let encoded = compression.encode_with_stats(&bytes, &stats).unwrap();
+            assert_eq!(
+                compression.decode_with_stats(encoded, &stats).unwrap(),
+                bytes
+            );
+        }
+
+        let snapshot = stats.snapshot();
+        assert_eq!(snapshot.uncompressed_bytes_sent, 2000);
+        assert_eq!(snapshot.decompressed_bytes_received, 2000);
+        assert_eq!(
+            snapshot.compressed_bytes_sent,
+            snapshot.compressed_bytes_received
+        );
+        assert!(snapshot.sent_compression_ratio().unwrap() < 0.1);
+        assert_eq!(parent.snapshot(), snapshot);
+
+        assert_eq!(
+            CompressionStatsSnapshot::default().sent_compression_ratio(),
+            None
+        );
+    }
+
+    #[test]
+    fn test_compression_mode() {
+        assert_eq!(CompressionMode::Both.request_compression_threshold(64), 64);
+        assert_eq!(
+            CompressionMode::ResponsesOnly.request_compression_threshold(64),
+            usize::MAX
+        );
+
+        assert_eq!(
+            CompressionMode::ResponsesOnly.effective(Compression::Lz4, Version::V4),
+            Some(CompressionMode::ResponsesOnly)
+        );
+        assert_eq!(
+            CompressionMode::RequestsOnly.effective(Compression::Snappy, Version::V4),
+            Some(CompressionMode::Both)
+        );
+        assert_eq!(
+            CompressionMode::ResponsesOnly.effective(Compression::Lz4, Version::V5),
+            Some(CompressionMode::Both)
+        );
+        assert_eq!(
+            CompressionMode::ResponsesOnly.effective(Compression::None, Version::V4),
+            None
+        );
+    }
 }
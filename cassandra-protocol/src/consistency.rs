@@ -3,16 +3,21 @@
 use crate::error;
 use crate::frame::{FromBytes, FromCursor, Serialize, Version};
 use crate::types::*;
-use derive_more::Display;
 use std::convert::{From, TryFrom, TryInto};
 use std::default::Default;
+use std::fmt::{self, Display, Formatter};
 use std::io;
 use std::str::FromStr;
 
 /// `Consistency` is an enum which represents Cassandra's consistency levels.
 /// To find more details about each consistency level please refer to the following documentation:
 /// <https://docs.datastax.com/en/cql-oss/3.x/cql/cql_reference/cqlshConsistency.html>
-#[derive(Debug, PartialEq, Clone, Copy, Display, Ord, PartialOrd, Eq, Hash, Default)]
+///
+/// Consistency levels are displayed with their CQL names, e.g. `LOCAL_QUORUM`, and can be parsed
+/// from names in any case, with or without underscores, or from numeric protocol codes. With the
+/// `serde` feature, they are serialized as CQL names and deserialized from names or codes, which
+/// makes them usable directly in configuration files.
+#[derive(Debug, PartialEq, Clone, Copy, Ord, PartialOrd, Eq, Hash, Default)]
 #[non_exhaustive]
 pub enum Consistency {
     /// Closest replica, as determined by the snitch.
@@ -74,30 +79,95 @@ pub enum Consistency {
     LocalOne,
 }
 
+const ALL_CONSISTENCIES: [Consistency; 11] = [
+    Consistency::Any,
+    Consistency::One,
+    Consistency::Two,
+    Consistency::Three,
+    Consistency::Quorum,
+    Consistency::All,
+    Consistency::LocalQuorum,
+    Consistency::EachQuorum,
+    Consistency::Serial,
+    Consistency::LocalSerial,
+    Consistency::LocalOne,
+];
+
+// compares names ignoring case and underscores, so both LOCAL_QUORUM and LocalQuorum match
+fn names_match(name: &str, candidate: &str) -> bool {
+    let normalize = |name: &str| {
+        name.bytes()
+            .filter(|byte| *byte != b'_')
+            .map(|byte| byte.to_ascii_uppercase())
+            .collect::<Vec<_>>()
+    };
+
+    normalize(name) == normalize(candidate)
+}
+
+impl Display for Consistency {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.pad(self.name())
+    }
+}
+
 impl FromStr for Consistency {
     type Err = error::Error;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let consistency = match s {
-            "Any" => Consistency::Any,
-            "One" => Consistency::One,
-            "Two" => Consistency::Two,
-            "Three" => Consistency::Three,
-            "Quorum" => Consistency::Quorum,
-            "All" => Consistency::All,
-            "LocalQuorum" => Consistency::LocalQuorum,
-            "EachQuorum" => Consistency::EachQuorum,
-            "Serial" => Consistency::Serial,
-            "LocalSerial" => Consistency::LocalSerial,
-            "LocalOne" => Consistency::LocalOne,
-            _ => {
-                return Err(error::Error::General(format!(
-                    "Invalid consistency provided: {s}"
-                )))
-            }
+        let s = s.trim();
+        let consistency = match s.parse::<CIntShort>() {
+            Ok(code) => Consistency::try_from(code).ok(),
+            Err(_) => ALL_CONSISTENCIES
+                .iter()
+                .find(|consistency| names_match(consistency.name(), s))
+                .copied(),
         };
 
-        Ok(consistency)
+        consistency.ok_or_else(|| {
+            let names: Vec<_> = ALL_CONSISTENCIES.iter().map(|c| c.name()).collect();
+            error::Error::General(format!(
+                "Invalid consistency provided: {s}, expected one of {} or a code from 0 to {}",
+                names.join(", "),
+                ALL_CONSISTENCIES.len() - 1
+            ))
+        })
+    }
+}
+
+#[cfg(feature = "serde")]
+impl serde::Serialize for Consistency {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.name())
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for Consistency {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct ConsistencyVisitor;
+
+        impl serde::de::Visitor<'_> for ConsistencyVisitor {
+            type Value = Consistency;
+
+            fn expecting(&self, formatter: &mut Formatter) -> fmt::Result {
+                formatter.write_str("a consistency level name or code")
+            }
+
+            fn visit_str<E: serde::de::Error>(self, value: &str) -> Result<Self::Value, E> {
+                value.parse().map_err(E::custom)
+            }
+
+            fn visit_i64<E: serde::de::Error>(self, value: i64) -> Result<Self::Value, E> {
+                self.visit_str(&value.to_string())
+            }
+
+            fn visit_u64<E: serde::de::Error>(self, value: u64) -> Result<Self::Value, E> {
+                self.visit_str(&value.to_string())
+            }
+        }
+
+        deserializer.deserialize_any(ConsistencyVisitor)
     }
 }
 
@@ -162,14 +232,46 @@ impl FromCursor for Consistency {
 }
 
 impl Consistency {
+    /// CQL name of this consistency, e.g. `LOCAL_QUORUM`.
+    pub fn name(self) -> &'static str {
+        match self {
+            Consistency::Any => "ANY",
+            Consistency::One => "ONE",
+            Consistency::Two => "TWO",
+            Consistency::Three => "THREE",
+            Consistency::Quorum => "QUORUM",
+            Consistency::All => "ALL",
+            Consistency::LocalQuorum => "LOCAL_QUORUM",
+            Consistency::EachQuorum => "EACH_QUORUM",
+            Consistency::Serial => "SERIAL",
+            Consistency::LocalSerial => "LOCAL_SERIAL",
+            Consistency::LocalOne => "LOCAL_ONE",
+        }
+    }
+
     /// Does this consistency require local dc.
     #[inline]
     pub fn is_dc_local(self) -> bool {
+        self.is_local()
+    }
+
+    /// Is this consistency confined to the local data center. Same as
+    /// [`is_dc_local`](Self::is_dc_local).
+    #[inline]
+    pub fn is_local(self) -> bool {
         matches!(
             self,
             Consistency::LocalOne | Consistency::LocalQuorum | Consistency::LocalSerial
         )
     }
+
+    /// Is this a serial consistency, i.e. one which can be used as serial consistency of
+    /// lightweight transactions. Serial consistencies can't be used as the consistency of writes,
+    /// which servers reject.
+    #[inline]
+    pub fn is_serial(self) -> bool {
+        matches!(self, Consistency::Serial | Consistency::LocalSerial)
+    }
 }
 
 #[cfg(test)]
@@ -304,4 +406,91 @@ mod tests {
             Consistency::LocalOne
         );
     }
+
+    #[test]
+    fn test_consistency_from_str() {
+        for consistency in ALL_CONSISTENCIES {
+            assert_eq!(
+                consistency.to_string().parse::<Consistency>().unwrap(),
+                consistency
+            );
+            assert_eq!(
+                format!("{:?}", consistency).parse::<Consistency>().unwrap(),
+                consistency
+            );
+            assert_eq!(
+                CIntShort::from(consistency)
+                    .to_string()
+                    .parse::<Consistency>()
+                    .unwrap(),
+                consistency
+            );
+        }
+
+        assert_eq!(
+            "local_quorum".parse::<Consistency>().unwrap(),
+            Consistency::LocalQuorum
+        );
+        assert_eq!(
+            " LocalOne ".parse::<Consistency>().unwrap(),
+            Consistency::LocalOne
+        );
+
+        for invalid in ["", "LOCAL-QUORUM", "LOCAL_QUORUMS", "11", "-1"] {
+            let error = invalid.parse::<Consistency>().unwrap_err().to_string();
+            assert!(error.contains("LOCAL_QUORUM, EACH_QUORUM"), "{}", error);
+        }
+    }
+
+    #[test]
+    fn test_consistency_display() {
+        assert_eq!(Consistency::LocalQuorum.to_string(), "LOCAL_QUORUM");
+        assert_eq!(format!("{:>5}", Consistency::One), "  ONE");
+    }
+
+    #[test]
+    fn test_consistency_kinds() {
+        let serial: Vec<_> = ALL_CONSISTENCIES
+            .iter()
+            .copied()
+            .filter(|consistency| consistency.is_serial())
+            .collect();
+        assert_eq!(serial, [Consistency::Serial, Consistency::LocalSerial]);
+
+        let local: Vec<_> = ALL_CONSISTENCIES
+            .iter()
+            .copied()
+            .filter(|consistency| consistency.is_local())
+            .collect();
+        assert_eq!(
+            local,
+            [
+                Consistency::LocalQuorum,
+                Consistency::LocalSerial,
+                Consistency::LocalOne
+            ]
+        );
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_consistency_serde() {
+        assert_eq!(
+            serde_json::to_string(&Consistency::LocalQuorum).unwrap(),
+            "\"LOCAL_QUORUM\""
+        );
+        assert_eq!(
+            serde_json::from_str::<Consistency>("\"local_serial\"").unwrap(),
+            Consistency::LocalSerial
+        );
+        assert_eq!(
+            serde_json::from_str::<Consistency>("6").unwrap(),
+            Consistency::LocalQuorum
+        );
+        assert!(serde_json::from_str::<Consistency>("\"quorums\"")
+            .unwrap_err()
+            .to_string()
+            .contains("expected one of ANY"));
+        assert!(serde_json::from_str::<Consistency>("70000").is_err());
+    }
 }
@@ -1,7 +1,8 @@
 use crate::compression::CompressionError;
 use crate::frame::message_error::ErrorBody;
-use crate::frame::Opcode;
+use crate::frame::{Opcode, StreamId, Version};
 use crate::types::{CInt, CIntShort};
+use derive_more::Display;
 use std::fmt::{Debug, Display};
 use std::io;
 use std::net::SocketAddr;
@@ -42,9 +43,14 @@ pub enum Error {
     /// Server error.
     #[error("Server {addr} error: {body:?}")]
     Server { body: ErrorBody, addr: SocketAddr },
-    /// Timed out waiting for an operation to complete.
-    #[error("Timeout: {0}")]
-    Timeout(String),
+    /// Timed out waiting for an operation to complete. `possibly_applied` is `true` if the request
+    /// might have been written to a connection before the timeout expired, so the server might
+    /// have executed it anyway.
+    #[error("Timeout: {message}")]
+    Timeout {
+        message: String,
+        possibly_applied: bool,
+    },
     /// Unknown consistency.
     #[error("Unknown consistency: {0}")]
     UnknownConsistency(CIntShort),
@@ -95,6 +101,114 @@ pub enum Error {
     /// returned error response.
     #[error("Invalid protocol used when communicating with a node: {0}")]
     InvalidProtocol(SocketAddr),
+    /// Data received from a node cannot be parsed, so the connection is no longer in sync with the
+    /// protocol and all pending requests on it are failed.
+    #[error("Protocol desynchronization: {0}")]
+    ProtocolDesync(String),
+    /// The session keyspace is locked, so a statement changing it has been rejected. Contains the
+    /// rejected statement.
+    #[error("Keyspace is locked, rejecting statement: {0}")]
+    KeyspaceLocked(String),
+    /// A frame received from a node has a version byte not matching the protocol version and
+    /// response direction negotiated for the connection, e.g. due to a misbehaving proxy. The
+    /// connection is no longer used.
+    #[error(
+        "Unexpected frame version byte: expected {expected:#04x}, got {actual:#04x} (opcode: {opcode}, stream: {stream})"
+    )]
+    UnexpectedFrame {
+        expected: u8,
+        actual: u8,
+        opcode: Opcode,
+        stream: StreamId,
+    },
+    /// A node accepted the connection, but did not finish a step of the startup handshake, other
+    /// than authentication, in time.
+    #[error("Timeout waiting for startup handshake with: {0}")]
+    HandshakeTimeout(SocketAddr),
+    /// A node did not finish authenticating a connection in time, e.g. due to a slow external
+    /// authentication service.
+    #[error("Timeout waiting for authentication with: {0}")]
+    AuthenticationTimeout(SocketAddr),
+    /// The connection to a node failed before the request could be written, so it never reached
+    /// a coordinator and can be safely sent to another node.
+    #[error("Connection to {0} failed before the request was sent")]
+    RequestNotSent(SocketAddr),
+    /// The retry policy decided to retry a failed request, but the session retry budget was
+    /// exhausted. Contains the error the request failed with.
+    #[error("Retry suppressed by exhausted retry budget: {0}")]
+    RetrySuppressed(Box<Error>),
+    /// A bound value cannot be converted to the type of its parameter without truncation or loss
+    /// of precision.
+    #[error("Invalid value bound to {name}: {reason}")]
+    InvalidBoundValue { name: String, reason: String },
+    /// Names of values bound to a query don't match its named bind markers. Contains marker names
+    /// without values and value names without markers.
+    #[error("Named values don't match bind markers - missing: {missing:?}, extra: {extra:?}")]
+    NamedValuesMismatch {
+        missing: Vec<String>,
+        extra: Vec<String>,
+    },
+    /// A request succeeded, but the server reported a warning which the configured warning
+    /// policy treats as an error.
+    #[error("Warning policy violation for query \"{query}\": {warning}")]
+    PolicyViolation { warning: String, query: String },
+    /// Establishing a TLS session with a node failed, e.g. because its certificate was rejected.
+    #[error("TLS {kind} failed for node {node}: {details}")]
+    Tls {
+        node: SocketAddr,
+        kind: TlsErrorKind,
+        details: String,
+    },
+    /// The request was cancelled before completing. It might have been already sent, in which
+    /// case the server might still process it.
+    #[error("Request cancelled")]
+    Cancelled,
+    /// A row couldn't be converted to a Rust type. Contains the index of the row within the
+    /// converted rows, e.g. within the current page, and the conversion error.
+    #[error("Cannot convert row {index}: {error}")]
+    RowConversion { index: usize, error: Box<Error> },
+    /// A request field is too large to be encoded, e.g. a query has more bound values than the
+    /// protocol can express. Detected before sending the request.
+    #[error("Too large {what}: {actual} exceeds maximum of {max}")]
+    ValueTooLarge {
+        what: &'static str,
+        max: usize,
+        actual: usize,
+    },
+    /// No node had a live connection when the request was about to be sent, so it failed without
+    /// waiting for reconnection. Returned only with fail fast mode enabled in the session.
+    #[error("No nodes available with live connections")]
+    NoNodesAvailable,
+    /// Bound variables of a prepared statement changed after re-preparing it, e.g. after an
+    /// `ALTER TABLE`, so positional values might no longer match them. Returned only with strict
+    /// prepared metadata checks enabled in the session. The statement needs to be prepared again.
+    #[error("Bound variables of prepared statement changed: {query}")]
+    PreparedMetadataChanged { query: String },
+    /// A request field is not supported by the protocol version in use, e.g. a per-statement
+    /// keyspace before V5. Detected before sending the request, instead of dropping the field.
+    #[error("{what} is not supported by protocol {version}")]
+    UnsupportedByVersion {
+        what: &'static str,
+        version: Version,
+    },
+}
+
+/// Kind of a TLS failure.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Display)]
+#[non_exhaustive]
+pub enum TlsErrorKind {
+    /// The certificate presented by the node was rejected, e.g. because it's self-signed, expired
+    /// or issued by an unknown authority.
+    #[display("certificate verification")]
+    CertificateVerification,
+    /// The certificate presented by the node is valid, but not for the server name used to
+    /// connect.
+    #[display("server name verification")]
+    ServerNameMismatch,
+    /// The node violated the TLS protocol, doesn't support required parameters or aborted the
+    /// handshake.
+    #[display("handshake")]
+    HandshakeProtocol,
 }
 
 pub fn column_is_empty_err<T: Display>(column_name: T) -> Error {
@@ -132,7 +246,13 @@ impl Clone for Error {
                 body: body.clone(),
                 addr: *addr,
             },
-            Error::Timeout(error) => Error::Timeout(error.clone()),
+            Error::Timeout {
+                message,
+                possibly_applied,
+            } => Error::Timeout {
+                message: message.clone(),
+                possibly_applied: *possibly_applied,
+            },
             Error::UnknownConsistency(value) => Error::UnknownConsistency(*value),
             Error::UnknownServerEvent(value) => Error::UnknownServerEvent(value.clone()),
             Error::UnexpectedTopologyChangeType(value) => {
@@ -159,6 +279,62 @@ impl Clone for Error {
             Error::UnexpectedAuthResponse(value) => Error::UnexpectedAuthResponse(*value),
             Error::UnexpectedStartupResponse(value) => Error::UnexpectedStartupResponse(*value),
             Error::InvalidProtocol(addr) => Error::InvalidProtocol(*addr),
+            Error::ProtocolDesync(error) => Error::ProtocolDesync(error.clone()),
+            Error::KeyspaceLocked(query) => Error::KeyspaceLocked(query.clone()),
+            Error::UnexpectedFrame {
+                expected,
+                actual,
+                opcode,
+                stream,
+            } => Error::UnexpectedFrame {
+                expected: *expected,
+                actual: *actual,
+                opcode: *opcode,
+                stream: *stream,
+            },
+            Error::HandshakeTimeout(addr) => Error::HandshakeTimeout(*addr),
+            Error::AuthenticationTimeout(addr) => Error::AuthenticationTimeout(*addr),
+            Error::RequestNotSent(addr) => Error::RequestNotSent(*addr),
+            Error::RetrySuppressed(error) => Error::RetrySuppressed(error.clone()),
+            Error::InvalidBoundValue { name, reason } => Error::InvalidBoundValue {
+                name: name.clone(),
+                reason: reason.clone(),
+            },
+            Error::NamedValuesMismatch { missing, extra } => Error::NamedValuesMismatch {
+                missing: missing.clone(),
+                extra: extra.clone(),
+            },
+            Error::PolicyViolation { warning, query } => Error::PolicyViolation {
+                warning: warning.clone(),
+                query: query.clone(),
+            },
+            Error::Tls {
+                node,
+                kind,
+                details,
+            } => Error::Tls {
+                node: *node,
+                kind: *kind,
+                details: details.clone(),
+            },
+            Error::Cancelled => Error::Cancelled,
+            Error::RowConversion { index, error } => Error::RowConversion {
+                index: *index,
+                error: error.clone(),
+            },
+            Error::ValueTooLarge { what, max, actual } => Error::ValueTooLarge {
+                what,
+                max: *max,
+                actual: *actual,
+            },
+            Error::NoNodesAvailable => Error::NoNodesAvailable,
+            Error::PreparedMetadataChanged { query } => Error::PreparedMetadataChanged {
+                query: query.clone(),
+            },
+            Error::UnsupportedByVersion { what, version } => Error::UnsupportedByVersion {
+                what,
+                version: *version,
+            },
         }
     }
 }
@@ -1,11 +1,15 @@
-use crate::compression::{Compression, CompressionError};
+use crate::compression::{Compression, CompressionError, CompressionStats};
 use crate::frame::message_request::RequestBody;
-use crate::frame::message_response::ResponseBody;
+use crate::frame::message_response::{ParsedResponse, ResponseBody};
 use crate::types::data_serialization_types::decode_timeuuid;
-use crate::types::{from_cursor_string_list, try_i16_from_bytes, try_i32_from_bytes, UUID_LEN};
+use crate::types::{
+    from_cursor_bytes_map, from_cursor_string_list, serialize_bytes_map, to_short_len,
+    try_i16_from_bytes, try_i32_from_bytes, CBytes, UUID_LEN,
+};
 use bitflags::bitflags;
 use derivative::Derivative;
 use derive_more::{Constructor, Display};
+use std::collections::BTreeMap;
 use std::convert::TryFrom;
 use std::io::Cursor;
 use thiserror::Error;
@@ -23,6 +27,8 @@ pub const LENGTH_LEN: usize = 4;
 pub mod events;
 pub mod frame_decoder;
 pub mod frame_encoder;
+#[cfg(test)]
+mod fuzz_tests;
 pub mod message_auth_challenge;
 pub mod message_auth_response;
 pub mod message_auth_success;
@@ -81,6 +87,35 @@ pub struct ParsedEnvelope {
     pub envelope: Envelope,
 }
 
+/// Envelope header, which can be parsed without touching the body.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct EnvelopeHeader {
+    pub version: Version,
+    pub direction: Direction,
+    pub flags: Flags,
+    pub opcode: Opcode,
+    pub stream_id: StreamId,
+    /// Length of the body, which might be compressed.
+    pub body_len: usize,
+}
+
+impl EnvelopeHeader {
+    /// Total length of the envelope, including the header.
+    #[inline]
+    pub fn envelope_len(&self) -> usize {
+        ENVELOPE_HEADER_LEN + self.body_len
+    }
+
+    /// Returns the raw body of the envelope starting at the beginning of given buffer, or `None`
+    /// if the buffer is too short. The body is compressed if the [`Flags::COMPRESSION`] flag is
+    /// set, and starts with tracing id, warnings and custom payload, if the respective flags are
+    /// set.
+    #[inline]
+    pub fn body<'a>(&self, data: &'a [u8]) -> Option<&'a [u8]> {
+        data.get(ENVELOPE_HEADER_LEN..self.envelope_len())
+    }
+}
+
 #[derive(Derivative, Clone, PartialEq, Eq, Hash)]
 #[derivative(Debug)]
 pub struct Envelope {
@@ -93,6 +128,8 @@ pub struct Envelope {
     pub body: Vec<u8>,
     pub tracing_id: Option<Uuid>,
     pub warnings: Vec<String>,
+    /// Custom payload, sent when the [`Flags::CUSTOM_PAYLOAD`] flag is set.
+    pub custom_payload: BTreeMap<String, CBytes>,
 }
 
 impl Envelope {
@@ -117,6 +154,7 @@ impl Envelope {
             body,
             tracing_id,
             warnings,
+            custom_payload: Default::default(),
         }
     }
 
@@ -130,9 +168,18 @@ impl Envelope {
         ResponseBody::try_from(self.body.as_slice(), self.opcode, self.version)
     }
 
+    /// Parses the response body, keeping it along with the envelope, so it doesn't need to be
+    /// parsed again.
     #[inline]
-    pub fn tracing_id(&self) -> &Option<Uuid> {
-        &self.tracing_id
+    pub fn into_parsed_response(self) -> error::Result<ParsedResponse> {
+        ParsedResponse::new(self)
+    }
+
+    /// Returns the id of the server-side trace, if tracing was requested for this envelope's
+    /// request. The trace can be read from `system_traces.sessions` and `system_traces.events`.
+    #[inline]
+    pub fn tracing_id(&self) -> Option<Uuid> {
+        self.tracing_id
     }
 
     #[inline]
@@ -140,6 +187,21 @@ impl Envelope {
         &self.warnings
     }
 
+    #[inline]
+    pub fn custom_payload(&self) -> &BTreeMap<String, CBytes> {
+        &self.custom_payload
+    }
+
+    /// Sets custom payload sent with the envelope, along with the corresponding flag. Custom
+    /// payloads are supported since protocol V4.
+    #[must_use]
+    pub fn with_custom_payload(mut self, custom_payload: BTreeMap<String, CBytes>) -> Self {
+        self.flags
+            .set(Flags::CUSTOM_PAYLOAD, !custom_payload.is_empty());
+        self.custom_payload = custom_payload;
+        self
+    }
+
     /// Parses the raw bytes of a cassandra envelope returning a [`ParsedEnvelope`] struct.
     /// The typical use case is reading from a buffer that may contain 0 or more envelopes and where
     /// the last envelope may be incomplete. The possible return values are:
@@ -150,11 +212,43 @@ impl Envelope {
         data: &[u8],
         compression: Compression,
     ) -> Result<ParsedEnvelope, ParseEnvelopeError> {
+        Self::from_buffer_with_stats(data, compression, None)
+    }
+
+    /// Parses only the header of an envelope at the beginning of given buffer, which is enough to
+    /// route it by opcode and stream id without allocating. The body doesn't need to be present.
+    pub fn parse_header(data: &[u8]) -> Result<EnvelopeHeader, ParseEnvelopeError> {
         if data.len() < ENVELOPE_HEADER_LEN {
             return Err(ParseEnvelopeError::NotEnoughBytes);
         }
 
-        let body_len = try_i32_from_bytes(&data[5..9]).unwrap() as usize;
+        let version = Version::try_from(data[0])
+            .map_err(|_| ParseEnvelopeError::UnsupportedVersion(data[0] & 0x7f))?;
+        let opcode = Opcode::try_from(data[4])
+            .map_err(|_| ParseEnvelopeError::UnsupportedOpcode(data[4]))?;
+
+        Ok(EnvelopeHeader {
+            version,
+            direction: Direction::from(data[0]),
+            flags: Flags::from_bits_truncate(data[1]),
+            opcode,
+            stream_id: try_i16_from_bytes(&data[2..4]).unwrap(),
+            body_len: parse_body_len(data).map_err(ParseEnvelopeError::InvalidBodyLength)?,
+        })
+    }
+
+    /// Parses an envelope like [`Envelope::from_buffer`], recording body decompression in given
+    /// statistics.
+    pub fn from_buffer_with_stats(
+        data: &[u8],
+        compression: Compression,
+        stats: Option<&CompressionStats>,
+    ) -> Result<ParsedEnvelope, ParseEnvelopeError> {
+        if data.len() < ENVELOPE_HEADER_LEN {
+            return Err(ParseEnvelopeError::NotEnoughBytes);
+        }
+
+        let body_len = parse_body_len(data).map_err(ParseEnvelopeError::InvalidBodyLength)?;
         let envelope_len = ENVELOPE_HEADER_LEN + body_len;
         if data.len() < envelope_len {
             return Err(ParseEnvelopeError::NotEnoughBytes);
@@ -171,7 +265,10 @@ impl Envelope {
         let body_bytes = &data[ENVELOPE_HEADER_LEN..envelope_len];
 
         let full_body = if flags.contains(Flags::COMPRESSION) {
-            compression.decode(body_bytes.to_vec())
+            match stats {
+                Some(stats) => compression.decode_with_stats(body_bytes.to_vec(), stats),
+                None => compression.decode(body_bytes.to_vec()),
+            }
         } else {
             Compression::None.decode(body_bytes.to_vec())
         }
@@ -183,10 +280,14 @@ impl Envelope {
         let mut body_cursor = Cursor::new(full_body.as_slice());
 
         let tracing_id = if flags.contains(Flags::TRACING) && direction == Direction::Response {
-            let mut tracing_bytes = [0; UUID_LEN];
-            std::io::Read::read_exact(&mut body_cursor, &mut tracing_bytes).unwrap();
-
-            Some(decode_timeuuid(&tracing_bytes).map_err(ParseEnvelopeError::InvalidUuid)?)
+            // a truncated id is rejected when decoding
+            let tracing_len = UUID_LEN.min(body_len);
+            body_cursor.set_position(tracing_len as u64);
+
+            Some(
+                decode_timeuuid(&full_body[..tracing_len])
+                    .map_err(ParseEnvelopeError::InvalidUuid)?,
+            )
         } else {
             None
         };
@@ -198,6 +299,13 @@ impl Envelope {
             vec![]
         };
 
+        let custom_payload = if flags.contains(Flags::CUSTOM_PAYLOAD) {
+            from_cursor_bytes_map(&mut body_cursor, version)
+                .map_err(ParseEnvelopeError::InvalidCustomPayload)?
+        } else {
+            Default::default()
+        };
+
         let mut body = Vec::with_capacity(body_len - body_cursor.position() as usize);
 
         std::io::Read::read_to_end(&mut body_cursor, &mut body)
@@ -214,6 +322,7 @@ impl Envelope {
                 body,
                 tracing_id,
                 warnings,
+                custom_payload,
             },
         ))
     }
@@ -223,7 +332,7 @@ impl Envelope {
             return Err(CheckEnvelopeSizeError::NotEnoughBytes);
         }
 
-        let body_len = try_i32_from_bytes(&data[5..9]).unwrap() as usize;
+        let body_len = parse_body_len(data).map_err(CheckEnvelopeSizeError::InvalidBodyLength)?;
         let envelope_len = ENVELOPE_HEADER_LEN + body_len;
         if data.len() < envelope_len {
             return Err(CheckEnvelopeSizeError::NotEnoughBytes);
@@ -234,15 +343,53 @@ impl Envelope {
         Ok(envelope_len)
     }
 
+    #[inline]
     pub fn encode_with(&self, compressor: Compression) -> error::Result<Vec<u8>> {
+        self.encode_with_threshold(compressor, 0)
+    }
+
+    /// Encodes the envelope, compressing the body only if it's at least `compression_threshold`
+    /// bytes long. Compressing tiny bodies costs CPU time and usually makes them bigger, so it's
+    /// better to send them as they are - the compression flag is set per envelope.
+    #[inline]
+    pub fn encode_with_threshold(
+        &self,
+        compressor: Compression,
+        compression_threshold: usize,
+    ) -> error::Result<Vec<u8>> {
+        self.encode_with_stats(compressor, compression_threshold, None)
+    }
+
+    /// Encodes the envelope like [`Envelope::encode_with_threshold`], recording body compression
+    /// in given statistics.
+    pub fn encode_with_stats(
+        &self,
+        compressor: Compression,
+        compression_threshold: usize,
+        stats: Option<&CompressionStats>,
+    ) -> error::Result<Vec<u8>> {
         // compression is ignored since v5
-        let is_compressed = self.version < Version::V5 && compressor.is_compressed();
+        let is_compressed = self.version < Version::V5
+            && compressor.is_compressed()
+            && self.body.len() >= compression_threshold;
 
         let combined_version_byte = u8::from(self.version) | u8::from(self.direction);
+
+        // custom payloads and warnings are not supported before V4, while beta versions are
+        // rejected by servers without the beta flag
+        let flags = if self.version < Version::V4 {
+            self.flags
+                .difference(Flags::CUSTOM_PAYLOAD | Flags::WARNING)
+        } else if self.version.is_beta() {
+            self.flags | Flags::BETA
+        } else {
+            self.flags
+        };
+
         let flag_byte = (if is_compressed {
-            self.flags | Flags::COMPRESSION
+            flags | Flags::COMPRESSION
         } else {
-            self.flags.difference(Flags::COMPRESSION)
+            flags.difference(Flags::COMPRESSION)
         })
         .bits();
 
@@ -272,24 +419,34 @@ impl Envelope {
             flags_buffer.append(&mut tracing_id);
         };
 
-        if self.flags.contains(Flags::WARNING) && self.direction == Direction::Response {
-            let warnings_len = self.warnings.len() as i16;
-            flags_buffer.extend_from_slice(&warnings_len.to_be_bytes());
+        if flags.contains(Flags::WARNING) && self.direction == Direction::Response {
+            flags_buffer
+                .extend_from_slice(&to_short_len("number of warnings", self.warnings.len())?);
 
             for warning in &self.warnings {
-                let warning_len = warning.len() as i16;
-                flags_buffer.extend_from_slice(&warning_len.to_be_bytes());
+                flags_buffer.extend_from_slice(&to_short_len("warning", warning.len())?);
                 flags_buffer.append(&mut warning.as_bytes().to_vec());
             }
         }
 
+        if flags.contains(Flags::CUSTOM_PAYLOAD) {
+            let mut cursor = Cursor::new(&mut flags_buffer);
+            cursor.set_position(cursor.get_ref().len() as u64);
+            serialize_bytes_map(&mut cursor, &self.custom_payload, self.version)?;
+        }
+
         if is_compressed {
             // avoid having to copy the body if there is nothing in flags_buffer
+            let encode = |bytes: &[u8]| match stats {
+                Some(stats) => compressor.encode_with_stats(bytes, stats),
+                None => compressor.encode(bytes),
+            };
+
             let encoded_body = if flags_buffer.is_empty() {
-                compressor.encode(&self.body)?
+                encode(&self.body)?
             } else {
                 flags_buffer.extend_from_slice(&self.body);
-                compressor.encode(&flags_buffer)?
+                encode(&flags_buffer)?
             };
 
             let body_len = encoded_body.len() as i32;
@@ -322,6 +479,8 @@ pub enum CheckEnvelopeSizeError {
     UnsupportedVersion(u8),
     #[error("Unsupported opcode: {0}")]
     UnsupportedOpcode(u8),
+    #[error("Invalid body length: {0}")]
+    InvalidBodyLength(i32),
 }
 
 #[derive(Debug, Error)]
@@ -341,6 +500,18 @@ pub enum ParseEnvelopeError {
     InvalidUuid(uuid::Error),
     #[error("Invalid warnings: {0}")]
     InvalidWarnings(error::Error),
+    #[error("Invalid custom payload: {0}")]
+    InvalidCustomPayload(error::Error),
+    /// The header declares a negative body length.
+    #[error("Invalid body length: {0}")]
+    InvalidBodyLength(i32),
+}
+
+// returns the body length declared in the header at the beginning of given buffer, or the raw
+// value if it's negative
+fn parse_body_len(data: &[u8]) -> Result<usize, i32> {
+    let body_len = try_i32_from_bytes(&data[5..9]).unwrap();
+    usize::try_from(body_len).map_err(|_| body_len)
 }
 
 /// Protocol version.
@@ -350,6 +521,8 @@ pub enum Version {
     V3,
     V4,
     V5,
+    /// Currently only available as beta on pre-release servers.
+    V6,
 }
 
 impl From<Version> for u8 {
@@ -358,6 +531,7 @@ impl From<Version> for u8 {
             Version::V3 => 3,
             Version::V4 => 4,
             Version::V5 => 5,
+            Version::V6 => 6,
         }
     }
 }
@@ -370,6 +544,7 @@ impl TryFrom<u8> for Version {
             3 => Ok(Version::V3),
             4 => Ok(Version::V4),
             5 => Ok(Version::V5),
+            6 => Ok(Version::V6),
             v => Err(error::Error::General(format!(
                 "Unknown cassandra version: {v}"
             ))),
@@ -380,6 +555,13 @@ impl TryFrom<u8> for Version {
 impl Version {
     /// Number of bytes that represent Cassandra frame's version.
     pub const BYTE_LENGTH: usize = 1;
+
+    /// Checks if the version is only available as beta, which requires setting the beta flag in
+    /// every envelope.
+    #[inline]
+    pub fn is_beta(self) -> bool {
+        self == Version::V6
+    }
 }
 
 #[derive(Debug, PartialEq, Copy, Clone, Ord, PartialOrd, Eq, Hash, Display)]
@@ -449,6 +631,9 @@ pub enum Opcode {
     AuthChallenge,
     AuthResponse,
     AuthSuccess,
+    /// Opcode unknown to this driver, e.g. introduced by a newer protocol version.
+    #[display("Other({_0})")]
+    Other(u8),
 }
 
 impl Opcode {
@@ -475,6 +660,7 @@ impl From<Opcode> for u8 {
             Opcode::AuthChallenge => 0x0E,
             Opcode::AuthResponse => 0x0F,
             Opcode::AuthSuccess => 0x10,
+            Opcode::Other(value) => value,
         }
     }
 }
@@ -500,7 +686,7 @@ impl TryFrom<u8> for Opcode {
             0x0E => Ok(Opcode::AuthChallenge),
             0x0F => Ok(Opcode::AuthResponse),
             0x10 => Ok(Opcode::AuthSuccess),
-            _ => Err(error::Error::General(format!("Unknown opcode: {value}"))),
+            _ => Ok(Opcode::Other(value)),
         }
     }
 }
@@ -604,12 +790,14 @@ mod tests {
     use crate::query::query_values::QueryValues;
     use crate::types::value::Value;
     use crate::types::CBytes;
+    use std::sync::Arc;
 
     #[test]
     fn test_frame_version_as_byte() {
         assert_eq!(u8::from(Version::V3), 0x03);
         assert_eq!(u8::from(Version::V4), 0x04);
         assert_eq!(u8::from(Version::V5), 0x05);
+        assert_eq!(u8::from(Version::V6), 0x06);
 
         assert_eq!(u8::from(Direction::Request), 0x00);
         assert_eq!(u8::from(Direction::Response), 0x80);
@@ -623,6 +811,8 @@ mod tests {
         assert_eq!(Version::try_from(0x84).unwrap(), Version::V4);
         assert_eq!(Version::try_from(0x05).unwrap(), Version::V5);
         assert_eq!(Version::try_from(0x85).unwrap(), Version::V5);
+        assert_eq!(Version::try_from(0x06).unwrap(), Version::V6);
+        assert_eq!(Version::try_from(0x86).unwrap(), Version::V6);
 
         assert_eq!(Direction::from(0x03), Direction::Request);
         assert_eq!(Direction::from(0x04), Direction::Request);
@@ -650,6 +840,7 @@ mod tests {
         assert_eq!(u8::from(Opcode::AuthChallenge), 0x0E);
         assert_eq!(u8::from(Opcode::AuthResponse), 0x0F);
         assert_eq!(u8::from(Opcode::AuthSuccess), 0x10);
+        assert_eq!(u8::from(Opcode::Other(0x42)), 0x42);
     }
 
     #[test]
@@ -670,6 +861,22 @@ mod tests {
         assert_eq!(Opcode::try_from(0x0E).unwrap(), Opcode::AuthChallenge);
         assert_eq!(Opcode::try_from(0x0F).unwrap(), Opcode::AuthResponse);
         assert_eq!(Opcode::try_from(0x10).unwrap(), Opcode::AuthSuccess);
+        assert_eq!(Opcode::try_from(0x42).unwrap(), Opcode::Other(0x42));
+    }
+
+    #[test]
+    fn test_unknown_opcode_response() {
+        let raw_envelope = [4, 0, 0, 3, 0x42, 0, 0, 0, 1, 7];
+
+        let envelope = Envelope::from_buffer(&raw_envelope, Compression::None)
+            .unwrap()
+            .envelope;
+        assert_eq!(envelope.opcode, Opcode::Other(0x42));
+        assert_eq!(envelope.body, vec![7]);
+        assert!(matches!(
+            envelope.response_body(),
+            Err(error::Error::NonResponseOpcode(Opcode::Other(0x42)))
+        ));
     }
 
     #[test]
@@ -684,11 +891,56 @@ mod tests {
             body: vec![],
             tracing_id: None,
             warnings: vec![],
+            custom_payload: Default::default(),
         };
         let body = ResponseBody::Ready;
         helpers::test_encode_decode_roundtrip_response(&raw_envelope, envelope, body);
     }
 
+    #[test]
+    fn test_parse_header() {
+        // the body is not required
+        let raw_envelope = [132, 2, 5, 64, 7, 0, 0, 0, 11, 0, 0];
+        let header = Envelope::parse_header(&raw_envelope).unwrap();
+
+        assert_eq!(
+            header,
+            EnvelopeHeader {
+                version: Version::V4,
+                direction: Direction::Response,
+                flags: Flags::TRACING,
+                opcode: Opcode::Query,
+                stream_id: 1344,
+                body_len: 11,
+            }
+        );
+        assert_eq!(header.envelope_len(), 20);
+        assert_eq!(header.body(&raw_envelope), None);
+
+        assert!(matches!(
+            Envelope::parse_header(&raw_envelope[..8]),
+            Err(ParseEnvelopeError::NotEnoughBytes)
+        ));
+    }
+
+    #[test]
+    fn test_negative_body_len() {
+        let raw_envelope = [132, 0, 0, 1, 2, 255, 255, 255, 254];
+
+        assert!(matches!(
+            Envelope::parse_header(&raw_envelope),
+            Err(ParseEnvelopeError::InvalidBodyLength(-2))
+        ));
+        assert!(matches!(
+            Envelope::from_buffer(&raw_envelope, Compression::None),
+            Err(ParseEnvelopeError::InvalidBodyLength(-2))
+        ));
+        assert!(matches!(
+            Envelope::check_envelope_size(&raw_envelope),
+            Err(CheckEnvelopeSizeError::InvalidBodyLength(-2))
+        ));
+    }
+
     #[test]
     fn test_query_minimal() {
         let raw_envelope = [
@@ -703,6 +955,7 @@ mod tests {
             body: vec![0, 0, 0, 4, 98, 108, 97, 104, 0, 0, 64],
             tracing_id: None,
             warnings: vec![],
+            custom_payload: Default::default(),
         };
         let body = RequestBody::Query(BodyReqQuery {
             query: "blah".into(),
@@ -739,6 +992,7 @@ mod tests {
             ],
             tracing_id: None,
             warnings: vec![],
+            custom_payload: Default::default(),
         };
         let body = RequestBody::Query(BodyReqQuery {
             query: "some query".into(),
@@ -771,6 +1025,7 @@ mod tests {
             body: vec![],
             tracing_id: None,
             warnings: vec![],
+            custom_payload: Default::default(),
         };
         let body = RequestBody::Query(BodyReqQuery {
             query: "another query".into(),
@@ -856,6 +1111,7 @@ mod tests {
             ],
             tracing_id: None,
             warnings: vec![],
+            custom_payload: Default::default(),
         };
         let body = ResponseBody::Result(ResResultBody::Prepared(BodyResResultPrepared {
             id: CBytesShort::new(vec![
@@ -925,6 +1181,7 @@ mod tests {
             ],
             tracing_id: None,
             warnings: vec![],
+            custom_payload: Default::default(),
         };
 
         (envelope, raw_envelope)
@@ -945,6 +1202,7 @@ mod tests {
             body,
             tracing_id: None,
             warnings: vec![],
+            custom_payload: Default::default(),
         };
 
         (envelope, raw_envelope)
@@ -1100,6 +1358,56 @@ mod tests {
         assert_eq!(envelopes.len(), 1);
         assert_eq!(envelopes[0], envelope);
     }
+
+    #[test]
+    fn should_record_compression_stats() {
+        let (envelope, raw_envelope) = create_small_envelope_data();
+
+        let stats = Arc::new(CompressionStats::new().with_round_trip_verification());
+
+        let mut encoder = Lz4FrameEncoder::default();
+        encoder.set_compression_stats(stats.clone());
+        encoder.add_envelope(raw_envelope.clone());
+
+        let mut buffer = encoder.finalize_self_contained().to_vec();
+
+        let mut decoder = Lz4FrameDecoder::default();
+        decoder.set_compression_stats(stats.clone());
+
+        let envelopes = decoder.consume(&mut buffer, Compression::None).unwrap();
+        assert_eq!(envelopes, vec![envelope.clone()]);
+
+        let snapshot = stats.snapshot();
+        assert_eq!(snapshot.uncompressed_bytes_sent, raw_envelope.len() as u64);
+        assert_eq!(
+            snapshot.decompressed_bytes_received,
+            raw_envelope.len() as u64
+        );
+        assert_eq!(
+            snapshot.compressed_bytes_sent,
+            snapshot.compressed_bytes_received
+        );
+
+        // legacy envelopes only count compressed bodies
+        let legacy_stats = Arc::new(CompressionStats::new().with_round_trip_verification());
+        let mut buffer = envelope
+            .encode_with_stats(Compression::Lz4, 0, Some(&legacy_stats))
+            .unwrap();
+
+        let mut decoder = LegacyFrameDecoder::default();
+        decoder.set_compression_stats(legacy_stats.clone());
+
+        let envelopes = decoder.consume(&mut buffer, Compression::Lz4).unwrap();
+        assert_eq!(envelopes.len(), 1);
+        assert_eq!(envelopes[0].body, envelope.body);
+
+        let snapshot = legacy_stats.snapshot();
+        assert_eq!(snapshot.uncompressed_bytes_sent, envelope.body.len() as u64);
+        assert_eq!(
+            snapshot.decompressed_bytes_received,
+            envelope.body.len() as u64
+        );
+    }
 }
 
 #[cfg(test)]
@@ -1109,6 +1417,7 @@ mod flags {
     use crate::frame::message_query::BodyReqQuery;
     use crate::frame::message_result::ResResultBody;
     use crate::query::query_params::QueryParams;
+    use crate::types::MAX_STRING_LEN;
 
     #[test]
     fn test_tracing_id_request() {
@@ -1129,6 +1438,7 @@ mod flags {
             body: vec![0, 0, 0, 4, 98, 108, 97, 104, 0, 0, 64],
             tracing_id: None,
             warnings: vec![],
+            custom_payload: Default::default(),
         };
 
         let body = RequestBody::Query(BodyReqQuery {
@@ -1170,8 +1480,16 @@ mod flags {
                 4, 54, 67, 12, 43, 2, 98, 76, 32, 50, 87, 5, 1, 33, 43, 87,
             ])),
             warnings: vec![],
+            custom_payload: Default::default(),
         };
 
+        assert_eq!(
+            envelope.tracing_id(),
+            Some(uuid::Uuid::from_bytes([
+                4, 54, 67, 12, 43, 2, 98, 76, 32, 50, 87, 5, 1, 33, 43, 87,
+            ]))
+        );
+
         let body = ResponseBody::Result(ResResultBody::Void);
 
         helpers::test_encode_decode_roundtrip_response(&raw_envelope, envelope, body);
@@ -1201,8 +1519,131 @@ mod flags {
             tracing_id: None,
             body: vec![0, 0, 0, 1],
             warnings: vec!["Hello World".into()],
+            custom_payload: Default::default(),
+        };
+
+        helpers::test_encode_decode_roundtrip_response(&raw_envelope, envelope, body);
+    }
+
+    #[test]
+    fn test_custom_payload_response() {
+        let raw_envelope = [
+            132, // version
+            12,  // flags
+            0, 7, // stream id
+            8, // opcode
+            0, 0, 0, 27, // length
+            0, 1, 0, 2, 104, 105, // warnings
+            0, 2, // custom payload
+            0, 1, 97, 0, 0, 0, 1, 1, // "a" => [1]
+            0, 1, 98, 255, 255, 255, 255, // "b" => null
+            0, 0, 0, 1, // body
+        ];
+
+        let body = ResponseBody::Result(ResResultBody::Void);
+
+        let envelope = Envelope {
+            version: Version::V4,
+            opcode: Opcode::Result,
+            flags: Flags::WARNING | Flags::CUSTOM_PAYLOAD,
+            direction: Direction::Response,
+            stream_id: 7,
+            tracing_id: None,
+            body: vec![0, 0, 0, 1],
+            warnings: vec!["hi".into()],
+            custom_payload: [
+                ("a".to_string(), CBytes::new(vec![1])),
+                ("b".to_string(), CBytes::new_null()),
+            ]
+            .iter()
+            .cloned()
+            .collect(),
         };
 
         helpers::test_encode_decode_roundtrip_response(&raw_envelope, envelope, body);
     }
+
+    #[test]
+    fn should_reject_too_long_warnings_and_payload_keys() {
+        let envelope = Envelope {
+            version: Version::V4,
+            opcode: Opcode::Result,
+            flags: Flags::WARNING,
+            direction: Direction::Response,
+            stream_id: 7,
+            tracing_id: None,
+            body: vec![0, 0, 0, 1],
+            warnings: vec!["a".repeat(MAX_STRING_LEN + 1)],
+            custom_payload: Default::default(),
+        };
+
+        assert!(matches!(
+            envelope.encode_with(Compression::None),
+            Err(error::Error::ValueTooLarge {
+                what: "warning",
+                ..
+            })
+        ));
+
+        let envelope = Envelope {
+            flags: Flags::CUSTOM_PAYLOAD,
+            warnings: vec![],
+            custom_payload: [("a".repeat(MAX_STRING_LEN + 1), CBytes::new(vec![1]))]
+                .iter()
+                .cloned()
+                .collect(),
+            ..envelope
+        };
+
+        assert!(matches!(
+            envelope.encode_with(Compression::None),
+            Err(error::Error::ValueTooLarge {
+                what: "map key",
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn test_compression_threshold() {
+        let envelope = Envelope {
+            version: Version::V4,
+            direction: Direction::Request,
+            flags: Flags::empty(),
+            opcode: Opcode::Query,
+            stream_id: 12,
+            body: vec![0, 0, 0, 4, 98, 108, 97, 104, 0, 0, 64],
+            tracing_id: None,
+            warnings: vec![],
+            custom_payload: Default::default(),
+        };
+
+        let small = envelope
+            .encode_with_threshold(Compression::Lz4, 512)
+            .unwrap();
+        assert!(!Flags::from_bits_truncate(small[1]).contains(Flags::COMPRESSION));
+        assert_eq!(small, envelope.encode_with(Compression::None).unwrap());
+
+        let compressed = envelope
+            .encode_with_threshold(Compression::Lz4, envelope.body.len())
+            .unwrap();
+        assert!(Flags::from_bits_truncate(compressed[1]).contains(Flags::COMPRESSION));
+
+        let decoded = Envelope::from_buffer(&compressed, Compression::Lz4)
+            .unwrap()
+            .envelope;
+        assert_eq!(decoded.body, envelope.body);
+    }
+
+    #[test]
+    fn test_beta_flag() {
+        let envelope = Envelope::new_req_options(Version::V6);
+        let data = envelope.encode_with(Compression::None).unwrap();
+        assert_eq!(data[0], 6);
+        assert!(Flags::from_bits_truncate(data[1]).contains(Flags::BETA));
+
+        let envelope = Envelope::new_req_options(Version::V5);
+        let data = envelope.encode_with(Compression::None).unwrap();
+        assert!(!Flags::from_bits_truncate(data[1]).contains(Flags::BETA));
+    }
 }
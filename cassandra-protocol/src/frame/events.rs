@@ -1,6 +1,6 @@
 use crate::frame::traits::FromCursor;
 use crate::frame::{Serialize, Version};
-use crate::types::{from_cursor_str, from_cursor_string_list, serialize_str, CIntShort};
+use crate::types::{from_cursor_str, from_cursor_string_list, serialize_short_len, serialize_str};
 use crate::{error, Error};
 use derive_more::Display;
 use std::cmp::PartialEq;
@@ -262,6 +262,27 @@ pub struct SchemaChange {
     pub options: SchemaChangeOptions,
 }
 
+impl SchemaChange {
+    /// Returns the name of the keyspace affected by the change.
+    pub fn keyspace(&self) -> &str {
+        match &self.options {
+            SchemaChangeOptions::Keyspace(keyspace)
+            | SchemaChangeOptions::TableType(keyspace, _)
+            | SchemaChangeOptions::FunctionAggregate(keyspace, _, _) => keyspace,
+        }
+    }
+
+    /// Returns the name of the changed table, type, function or aggregate, or `None` if the
+    /// keyspace itself has been changed.
+    pub fn name(&self) -> Option<&str> {
+        match &self.options {
+            SchemaChangeOptions::Keyspace(_) => None,
+            SchemaChangeOptions::TableType(_, name)
+            | SchemaChangeOptions::FunctionAggregate(_, name, _) => Some(name),
+        }
+    }
+}
+
 impl Serialize for SchemaChange {
     fn serialize(&self, cursor: &mut Cursor<&mut Vec<u8>>, version: Version) {
         self.change_type.serialize(cursor, version);
@@ -385,8 +406,7 @@ impl Serialize for SchemaChangeOptions {
                 serialize_str(cursor, ks, version);
                 serialize_str(cursor, fa_name, version);
 
-                let len = list.len() as CIntShort;
-                len.serialize(cursor, version);
+                serialize_short_len(cursor, list.len());
                 list.iter().for_each(|x| serialize_str(cursor, x, version));
             }
         }
@@ -1121,4 +1141,18 @@ mod server_event {
             test_encode_decode(bytes, expected);
         }
     }
+
+    #[test]
+    fn unknown_event_type() {
+        let bytes = &[
+            0, 13, 67, 76, 73, 69, 78, 84, 95, 67, 72, 65, 78, 71, 69, // client change
+            0, 3, 78, 69, 87, // new
+        ];
+
+        let mut cursor: Cursor<&[u8]> = Cursor::new(bytes);
+        assert!(matches!(
+            ServerEvent::from_cursor(&mut cursor, Version::V4),
+            Err(Error::UnknownServerEvent(event_type)) if event_type == "CLIENT_CHANGE"
+        ));
+    }
 }
@@ -1,13 +1,15 @@
-use crate::compression::{Compression, CompressionError};
+use crate::compression::{Compression, CompressionError, CompressionStats};
 use crate::crc::{crc24, crc32};
 use crate::error::{Error, Result};
 use crate::frame::{
     Envelope, ParseEnvelopeError, COMPRESSED_FRAME_HEADER_LENGTH, ENVELOPE_HEADER_LEN,
     FRAME_TRAILER_LENGTH, MAX_FRAME_SIZE, PAYLOAD_SIZE_LIMIT, UNCOMPRESSED_FRAME_HEADER_LENGTH,
 };
+use crate::stopwatch::Stopwatch;
 use lz4_flex::decompress;
 use std::convert::TryInto;
 use std::io;
+use std::sync::Arc;
 
 #[inline]
 fn create_unexpected_self_contained_error() -> Error {
@@ -24,12 +26,16 @@ fn create_payload_crc_mismatch_error(computed_crc: u32, payload_crc32: u32) -> E
     format!("Payload CRC mismatch - read {payload_crc32}, computed {computed_crc}.",).into()
 }
 
-fn extract_envelopes(buffer: &[u8], compression: Compression) -> Result<(usize, Vec<Envelope>)> {
+fn extract_envelopes(
+    buffer: &[u8],
+    compression: Compression,
+    stats: Option<&CompressionStats>,
+) -> Result<(usize, Vec<Envelope>)> {
     let mut current_pos = 0;
     let mut envelopes = vec![];
 
     loop {
-        match Envelope::from_buffer(&buffer[current_pos..], compression) {
+        match Envelope::from_buffer_with_stats(&buffer[current_pos..], compression, stats) {
             Ok(envelope) => {
                 envelopes.push(envelope.envelope);
                 current_pos += envelope.envelope_len;
@@ -45,13 +51,14 @@ fn extract_envelopes(buffer: &[u8], compression: Compression) -> Result<(usize,
 fn try_decode_envelopes_with_spare_data(
     buffer: &mut Vec<u8>,
     compression: Compression,
+    stats: Option<&CompressionStats>,
 ) -> Result<(Vec<Envelope>, Vec<u8>)> {
-    let (current_pos, envelopes) = extract_envelopes(buffer.as_slice(), compression)?;
+    let (current_pos, envelopes) = extract_envelopes(buffer.as_slice(), compression, stats)?;
     Ok((envelopes, buffer.split_off(current_pos)))
 }
 
 fn try_decode_envelopes_without_spare_data(buffer: &[u8]) -> Result<Vec<Envelope>> {
-    let (_, envelopes) = extract_envelopes(buffer, Compression::None)?;
+    let (_, envelopes) = extract_envelopes(buffer, Compression::None, None)?;
     Ok(envelopes)
 }
 
@@ -62,18 +69,24 @@ pub trait FrameDecoder {
     /// buffered until envelopes can be parsed.
     /// The buffer passed in should be cleared of consumed data by the decoder.
     fn consume(&mut self, data: &mut Vec<u8>, compression: Compression) -> Result<Vec<Envelope>>;
+
+    /// Sets statistics to record decompression in. Decoders which don't decompress data can
+    /// ignore them.
+    fn set_compression_stats(&mut self, _stats: Arc<CompressionStats>) {}
 }
 
 /// Pre-V5 frame decoder which simply decodes one envelope directly into a buffer.
 #[derive(Clone, Debug)]
 pub struct LegacyFrameDecoder {
     buffer: Vec<u8>,
+    compression_stats: Option<Arc<CompressionStats>>,
 }
 
 impl Default for LegacyFrameDecoder {
     fn default() -> Self {
         Self {
             buffer: Vec::with_capacity(MAX_FRAME_SIZE),
+            compression_stats: None,
         }
     }
 }
@@ -82,7 +95,11 @@ impl FrameDecoder for LegacyFrameDecoder {
     fn consume(&mut self, data: &mut Vec<u8>, compression: Compression) -> Result<Vec<Envelope>> {
         if self.buffer.is_empty() {
             // optimistic case
-            let (envelopes, buffer) = try_decode_envelopes_with_spare_data(data, compression)?;
+            let (envelopes, buffer) = try_decode_envelopes_with_spare_data(
+                data,
+                compression,
+                self.compression_stats.as_deref(),
+            )?;
 
             self.buffer = buffer;
             data.clear();
@@ -92,30 +109,48 @@ impl FrameDecoder for LegacyFrameDecoder {
 
         self.buffer.append(data);
 
-        let (envelopes, buffer) =
-            try_decode_envelopes_with_spare_data(&mut self.buffer, compression)?;
+        let (envelopes, buffer) = try_decode_envelopes_with_spare_data(
+            &mut self.buffer,
+            compression,
+            self.compression_stats.as_deref(),
+        )?;
 
         self.buffer = buffer;
         Ok(envelopes)
     }
+
+    #[inline]
+    fn set_compression_stats(&mut self, stats: Arc<CompressionStats>) {
+        self.compression_stats = Some(stats);
+    }
 }
 
 /// Post-V5 Lz4 decoder with support for envelope frames with CRC checksum.
 #[derive(Clone, Debug, Default)]
 pub struct Lz4FrameDecoder {
     inner_decoder: GenericFrameDecoder,
+    compression_stats: Option<Arc<CompressionStats>>,
 }
 
 impl FrameDecoder for Lz4FrameDecoder {
-    //noinspection DuplicatedCode
     #[inline]
     fn consume(&mut self, data: &mut Vec<u8>, _compression: Compression) -> Result<Vec<Envelope>> {
-        self.inner_decoder.consume(data, Self::try_decode_frame)
+        let stats = self.compression_stats.as_deref();
+        self.inner_decoder
+            .consume(data, |buffer| Self::try_decode_frame(buffer, stats))
+    }
+
+    #[inline]
+    fn set_compression_stats(&mut self, stats: Arc<CompressionStats>) {
+        self.compression_stats = Some(stats);
     }
 }
 
 impl Lz4FrameDecoder {
-    fn try_decode_frame(buffer: &mut Vec<u8>) -> Result<Option<(bool, Vec<u8>)>> {
+    fn try_decode_frame(
+        buffer: &mut Vec<u8>,
+        stats: Option<&CompressionStats>,
+    ) -> Result<Option<(bool, Vec<u8>)>> {
         let buffer_len = buffer.len();
         if buffer_len < COMPRESSED_FRAME_HEADER_LENGTH {
             return Ok(None);
@@ -167,12 +202,17 @@ impl Lz4FrameDecoder {
             return Ok(Some((self_contained, payload)));
         }
 
+        let start = Stopwatch::start();
         decompress(
             &buffer[COMPRESSED_FRAME_HEADER_LENGTH..compressed_payload_end],
             uncompressed_length,
         )
         .map_err(|error| CompressionError::Lz4(io::Error::new(io::ErrorKind::Other, error)).into())
         .map(|payload| {
+            if let Some(stats) = stats {
+                stats.record_decompression(compressed_length, payload.len(), start.elapsed());
+            }
+
             *buffer = buffer.split_off(frame_end);
             Some((self_contained, payload))
         })
@@ -1,10 +1,13 @@
+use crate::compression::CompressionStats;
 use crate::crc::{crc24, crc32};
 use crate::frame::{
     COMPRESSED_FRAME_HEADER_LENGTH, FRAME_TRAILER_LENGTH, PAYLOAD_SIZE_LIMIT,
     UNCOMPRESSED_FRAME_HEADER_LENGTH,
 };
+use crate::stopwatch::Stopwatch;
 use lz4_flex::block::get_maximum_output_size;
 use lz4_flex::{compress, compress_into};
+use std::sync::Arc;
 
 #[inline]
 fn put3b(buffer: &mut [u8], value: i32) {
@@ -56,6 +59,10 @@ pub trait FrameEncoder {
 
     /// Checks if current frame contains any envelopes.
     fn has_envelopes(&self) -> bool;
+
+    /// Sets statistics to record compression in. Encoders which don't compress data can ignore
+    /// them.
+    fn set_compression_stats(&mut self, _stats: Arc<CompressionStats>) {}
 }
 
 /// Pre-V5 frame encoder which simply encodes one envelope directly in the buffer.
@@ -183,6 +190,7 @@ impl UncompressedFrameEncoder {
 #[derive(Clone, Debug)]
 pub struct Lz4FrameEncoder {
     buffer: Vec<u8>,
+    compression_stats: Option<Arc<CompressionStats>>,
 }
 
 impl FrameEncoder for Lz4FrameEncoder {
@@ -207,7 +215,14 @@ impl FrameEncoder for Lz4FrameEncoder {
 
     fn finalize_self_contained(&mut self) -> &[u8] {
         let uncompressed_size = self.buffer.len() - COMPRESSED_FRAME_HEADER_LENGTH;
+
+        let start = Stopwatch::start();
         let mut compressed_payload = compress(&self.buffer[COMPRESSED_FRAME_HEADER_LENGTH..]);
+        self.record_compression(
+            &self.buffer[COMPRESSED_FRAME_HEADER_LENGTH..],
+            &compressed_payload,
+            start,
+        );
 
         self.buffer.truncate(COMPRESSED_FRAME_HEADER_LENGTH);
         self.buffer.append(&mut compressed_payload);
@@ -229,12 +244,20 @@ impl FrameEncoder for Lz4FrameEncoder {
             0,
         );
 
+        let start = Stopwatch::start();
         let mut compressed_size = compress_into(
             &envelope[..uncompressed_size],
             &mut self.buffer[COMPRESSED_FRAME_HEADER_LENGTH..],
         )
         .unwrap(); // we can safely unwrap, since we have at least the amount of space needed
 
+        self.record_compression(
+            &envelope[..uncompressed_size],
+            &self.buffer[COMPRESSED_FRAME_HEADER_LENGTH
+                ..(COMPRESSED_FRAME_HEADER_LENGTH + compressed_size)],
+            start,
+        );
+
         if compressed_size >= PAYLOAD_SIZE_LIMIT {
             // compressed size can exceed source size, therefore can exceed max payload size
             // Java driver simply ignores compression at this point, so ¯\_(ツ)_/¯
@@ -259,16 +282,41 @@ impl FrameEncoder for Lz4FrameEncoder {
     fn has_envelopes(&self) -> bool {
         self.buffer.len() > COMPRESSED_FRAME_HEADER_LENGTH
     }
+
+    #[inline]
+    fn set_compression_stats(&mut self, stats: Arc<CompressionStats>) {
+        self.compression_stats = Some(stats);
+    }
 }
 
 impl Default for Lz4FrameEncoder {
     fn default() -> Self {
         let buffer = vec![0; COMPRESSED_FRAME_HEADER_LENGTH];
-        Self { buffer }
+        Self {
+            buffer,
+            compression_stats: None,
+        }
     }
 }
 
 impl Lz4FrameEncoder {
+    fn record_compression(&self, uncompressed: &[u8], compressed: &[u8], start: Stopwatch) {
+        if let Some(stats) = &self.compression_stats {
+            stats.record_compression(uncompressed.len(), compressed.len(), start.elapsed());
+
+            #[cfg(debug_assertions)]
+            if stats.verifies_round_trip() {
+                assert_eq!(
+                    lz4_flex::decompress(compressed, uncompressed.len())
+                        .ok()
+                        .as_deref(),
+                    Some(uncompressed),
+                    "Lz4 frame compression round trip failed!"
+                );
+            }
+        }
+    }
+
     fn write_header(&mut self, uncompressed_size: usize, self_contained: bool) {
         let len = self.buffer.len();
         debug_assert!(len < (PAYLOAD_SIZE_LIMIT + COMPRESSED_FRAME_HEADER_LENGTH));
@@ -0,0 +1,140 @@
+//! Feeds malformed input to all parsers reachable from received bytes, making sure they fail with
+//! errors instead of panicking.
+
+use std::collections::BTreeMap;
+use std::convert::TryFrom;
+use std::panic::{catch_unwind, AssertUnwindSafe};
+
+use crate::compression::Compression;
+use crate::frame::message_request::RequestBody;
+use crate::frame::message_response::ResponseBody;
+use crate::frame::{Envelope, Opcode, Version};
+use crate::types::data_serialization_types::*;
+
+const VERSIONS: [Version; 4] = [Version::V3, Version::V4, Version::V5, Version::V6];
+
+// xorshift, so failures are reproducible without external dependencies
+struct Rng(u64);
+
+impl Rng {
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    fn below(&mut self, bound: usize) -> usize {
+        (self.next() % bound as u64) as usize
+    }
+
+    // random bytes biased towards small lengths, counts and type ids, so parsing gets past the
+    // first length prefix often enough to reach nested structures
+    fn bytes(&mut self, max_len: usize) -> Vec<u8> {
+        let len = self.below(max_len + 1);
+        let mut bytes = Vec::with_capacity(len);
+        while bytes.len() < len {
+            match self.below(6) {
+                0 => bytes.extend_from_slice(&(self.below(4) as i32).to_be_bytes()),
+                1 => bytes.extend_from_slice(&(self.below(4) as i16).to_be_bytes()),
+                2 => bytes.extend_from_slice(&(-(self.below(3) as i32) - 1).to_be_bytes()),
+                3 => bytes.push(self.below(0x31) as u8),
+                _ => bytes.push(self.next() as u8),
+            }
+        }
+
+        bytes.truncate(len);
+        bytes
+    }
+}
+
+fn opcodes() -> impl Iterator<Item = Opcode> {
+    (0..=0x10).filter_map(|opcode| Opcode::try_from(opcode).ok())
+}
+
+// the panic hook is left alone, since tests run in parallel - panics are still printed, but the
+// test harness captures the output
+fn check(failures: &mut BTreeMap<String, Vec<u8>>, input: &[u8], parse: impl FnOnce()) {
+    if let Err(panic) = catch_unwind(AssertUnwindSafe(parse)) {
+        let message = panic
+            .downcast_ref::<String>()
+            .cloned()
+            .or_else(|| {
+                panic
+                    .downcast_ref::<&str>()
+                    .map(|message| message.to_string())
+            })
+            .unwrap_or_default();
+
+        failures.entry(message).or_insert_with(|| input.to_vec());
+    }
+}
+
+#[test]
+fn should_not_panic_on_malformed_bodies() {
+    let mut rng = Rng(0x2545_f491_4f6c_dd1d);
+    let mut failures = BTreeMap::new();
+
+    for _ in 0..20_000 {
+        let bytes = rng.bytes(96);
+        for version in VERSIONS.iter() {
+            for opcode in opcodes() {
+                check(&mut failures, &bytes, || {
+                    let _ = ResponseBody::try_from(&bytes, opcode, *version);
+                });
+                check(&mut failures, &bytes, || {
+                    let _ = RequestBody::try_from(&bytes, opcode, *version);
+                });
+            }
+        }
+
+        check(&mut failures, &bytes, || {
+            let _ = Envelope::from_buffer(&bytes, Compression::None);
+        });
+    }
+
+    assert!(failures.is_empty(), "{:#?}", failures);
+}
+
+#[test]
+fn should_not_panic_on_malformed_values() {
+    let mut rng = Rng(0x9e37_79b9_7f4a_7c15);
+    let mut failures = BTreeMap::new();
+
+    for _ in 0..20_000 {
+        let bytes = rng.bytes(32);
+        let count = rng.below(16);
+
+        check(&mut failures, &bytes, || {
+            let _ = decode_bigint(&bytes);
+            let _ = decode_blob(&bytes);
+            let _ = decode_boolean(&bytes);
+            let _ = decode_int(&bytes);
+            let _ = decode_date(&bytes);
+            let _ = decode_cql_date(&bytes);
+            let _ = decode_decimal(&bytes);
+            let _ = decode_double(&bytes);
+            let _ = decode_float(&bytes);
+            let _ = decode_inet(&bytes);
+            let _ = decode_timestamp(&bytes);
+            let _ = decode_smallint(&bytes);
+            let _ = decode_tinyint(&bytes);
+            let _ = decode_time(&bytes);
+            let _ = decode_timeuuid(&bytes);
+            let _ = decode_varint(&bytes);
+            let _ = decode_duration(&bytes);
+        });
+
+        for version in VERSIONS.iter() {
+            check(&mut failures, &bytes, || {
+                let _ = decode_list(&bytes, *version);
+                let _ = decode_float_vector(&bytes, *version, count);
+                let _ = decode_map(&bytes, *version);
+                let _ = decode_udt(&bytes, count, *version);
+                let _ = decode_tuple(&bytes, count, *version);
+            });
+        }
+    }
+
+    assert!(failures.is_empty(), "{:#?}", failures);
+}
@@ -1,11 +1,13 @@
 use crate::consistency::Consistency;
 use crate::frame::{Direction, Envelope, Flags, FromCursor, Opcode, Serialize, Version};
+use crate::query::query_params::check_v5_fields;
 use crate::query::QueryFlags;
 use crate::query::QueryValues;
 use crate::types::value::Value;
 use crate::types::{
-    from_cursor_str, from_cursor_str_long, serialize_str, serialize_str_long, CBytesShort, CInt,
-    CIntShort, CLong,
+    bounded_capacity, check_len, from_cursor_short_len, from_cursor_str, from_cursor_str_long,
+    serialize_short_len, serialize_str, serialize_str_long, CBytesShort, CInt, CIntShort, CLong,
+    MAX_SHORT_COUNT, MAX_STRING_LEN,
 };
 use crate::{error, Error};
 use derive_more::{Constructor, Display};
@@ -23,13 +25,44 @@ pub struct BodyReqBatch {
     pub now_in_seconds: Option<CInt>,
 }
 
+impl BodyReqBatch {
+    /// Checks if the number of statements, their values and the keyspace fit into their protocol
+    /// fields, so the batch can be sent.
+    pub fn check_lengths(&self) -> error::Result<()> {
+        check_len(
+            "number of batch statements",
+            self.queries.len(),
+            MAX_SHORT_COUNT,
+        )?;
+
+        for query in &self.queries {
+            query.values.check_lengths()?;
+        }
+
+        if let Some(keyspace) = &self.keyspace {
+            check_len("keyspace name", keyspace.len(), MAX_STRING_LEN)?;
+        }
+
+        Ok(())
+    }
+
+    /// Checks if the batch can be sent with given protocol version. The per-batch keyspace and
+    /// current time are only supported since V5.
+    pub fn check_version(&self, version: Version) -> error::Result<()> {
+        check_v5_fields(
+            self.keyspace.is_some(),
+            self.now_in_seconds.is_some(),
+            version,
+        )
+    }
+}
+
 impl Serialize for BodyReqBatch {
     fn serialize(&self, cursor: &mut Cursor<&mut Vec<u8>>, version: Version) {
         let batch_type = u8::from(self.batch_type);
         batch_type.serialize(cursor, version);
 
-        let len = self.queries.len() as CIntShort;
-        len.serialize(cursor, version);
+        serialize_short_len(cursor, self.queries.len());
 
         for query in &self.queries {
             query.serialize(cursor, version);
@@ -47,12 +80,15 @@ impl Serialize for BodyReqBatch {
             flags.insert(QueryFlags::WITH_DEFAULT_TIMESTAMP)
         }
 
-        if self.keyspace.is_some() {
-            flags.insert(QueryFlags::WITH_KEYSPACE)
-        }
+        // keyspace and now are not supported before V5
+        if version >= Version::V5 {
+            if self.keyspace.is_some() {
+                flags.insert(QueryFlags::WITH_KEYSPACE)
+            }
 
-        if self.now_in_seconds.is_some() {
-            flags.insert(QueryFlags::WITH_NOW_IN_SECONDS)
+            if self.now_in_seconds.is_some() {
+                flags.insert(QueryFlags::WITH_NOW_IN_SECONDS)
+            }
         }
 
         flags.serialize(cursor, version);
@@ -66,12 +102,16 @@ impl Serialize for BodyReqBatch {
             timestamp.serialize(cursor, version);
         }
 
-        if let Some(keyspace) = &self.keyspace {
-            serialize_str(cursor, keyspace.as_str(), version);
+        if flags.contains(QueryFlags::WITH_KEYSPACE) {
+            if let Some(keyspace) = &self.keyspace {
+                serialize_str(cursor, keyspace.as_str(), version);
+            }
         }
 
-        if let Some(now_in_seconds) = self.now_in_seconds {
-            now_in_seconds.serialize(cursor, version);
+        if flags.contains(QueryFlags::WITH_NOW_IN_SECONDS) {
+            if let Some(now_in_seconds) = self.now_in_seconds {
+                now_in_seconds.serialize(cursor, version);
+            }
         }
     }
 }
@@ -82,9 +122,9 @@ impl FromCursor for BodyReqBatch {
         cursor.read_exact(&mut batch_type)?;
 
         let batch_type = BatchType::try_from(batch_type[0])?;
-        let len = CIntShort::from_cursor(cursor, version)?;
+        let len = from_cursor_short_len(cursor)?;
 
-        let mut queries = Vec::with_capacity(len as usize);
+        let mut queries = Vec::with_capacity(bounded_capacity(len, cursor));
         for _ in 0..len {
             queries.push(BatchQuery::from_cursor(cursor, version)?);
         }
@@ -198,8 +238,7 @@ impl Serialize for BatchQuery {
             }
         }
 
-        let len = self.values.len() as CIntShort;
-        len.serialize(cursor, version);
+        serialize_short_len(cursor, self.values.len());
 
         self.values.serialize(cursor, version);
     }
@@ -218,11 +257,11 @@ impl FromCursor for BatchQuery {
             BatchQuerySubj::QueryString(from_cursor_str_long(cursor).map(Into::into)?)
         };
 
-        let len = CIntShort::from_cursor(cursor, version)?;
+        let len = from_cursor_short_len(cursor)?;
 
         // assuming names are not present due to
         // https://issues.apache.org/jira/browse/CASSANDRA-10246
-        let mut values = Vec::with_capacity(len as usize);
+        let mut values = Vec::with_capacity(bounded_capacity(len, cursor));
         for _ in 0..len {
             values.push(Value::from_cursor(cursor, version)?);
         }
@@ -232,11 +271,21 @@ impl FromCursor for BatchQuery {
 }
 
 impl Envelope {
-    pub fn new_req_batch(query: BodyReqBatch, flags: Flags, version: Version) -> Envelope {
+    /// Creates a BATCH request from a batch which can be reused for subsequent executions. Fails if
+    /// the batch does not fit into its protocol fields, or uses fields not supported by given
+    /// protocol version.
+    pub fn new_req_batch_ref(
+        query: &BodyReqBatch,
+        flags: Flags,
+        version: Version,
+    ) -> error::Result<Envelope> {
         let direction = Direction::Request;
         let opcode = Opcode::Batch;
 
-        Envelope::new(
+        query.check_lengths()?;
+        query.check_version(version)?;
+
+        Ok(Envelope::new(
             version,
             direction,
             flags,
@@ -245,7 +294,16 @@ impl Envelope {
             query.serialize_to_vec(version),
             None,
             vec![],
-        )
+        ))
+    }
+
+    #[inline]
+    pub fn new_req_batch(
+        query: BodyReqBatch,
+        flags: Flags,
+        version: Version,
+    ) -> error::Result<Envelope> {
+        Self::new_req_batch_ref(&query, flags, version)
     }
 }
 
@@ -254,9 +312,11 @@ mod tests {
     use crate::consistency::Consistency;
     use crate::frame::message_batch::{BatchQuery, BatchQuerySubj, BatchType, BodyReqBatch};
     use crate::frame::traits::Serialize;
-    use crate::frame::{FromCursor, Version};
+    use crate::frame::{Envelope, Flags, FromCursor, Version};
     use crate::query::QueryValues;
     use crate::types::prelude::Value;
+    use crate::types::{MAX_SHORT_COUNT, MAX_STRING_LEN};
+    use crate::Error;
     use std::io::Cursor;
 
     #[test]
@@ -319,4 +379,97 @@ mod tests {
             BodyReqBatch::from_cursor(&mut Cursor::new(data.as_slice()), Version::V5).unwrap();
         assert_eq!(body.now_in_seconds, Some(now_in_seconds));
     }
+
+    #[test]
+    fn should_round_trip_legacy_versions() {
+        let body = BodyReqBatch::new(
+            BatchType::Unlogged,
+            vec![BatchQuery {
+                subject: BatchQuerySubj::QueryString("A".into()),
+                values: QueryValues::SimpleValues(vec![Value::new(1)]),
+            }],
+            Consistency::Quorum,
+            Some(Consistency::Serial),
+            Some(10),
+            None,
+            None,
+        );
+
+        for version in [Version::V3, Version::V4] {
+            let data = body.serialize_to_vec(version);
+            let result =
+                BodyReqBatch::from_cursor(&mut Cursor::new(data.as_slice()), version).unwrap();
+
+            assert_eq!(result.queries, body.queries);
+            assert_eq!(result.serial_consistency, body.serial_consistency);
+            assert_eq!(result.timestamp, body.timestamp);
+        }
+    }
+
+    #[test]
+    fn should_reject_now_in_seconds_in_v4() {
+        let mut body = batch(vec![]);
+        body.now_in_seconds = Some(4);
+
+        assert!(matches!(
+            Envelope::new_req_batch_ref(&body, Flags::empty(), Version::V4),
+            Err(Error::UnsupportedByVersion {
+                what: "Per-statement current time",
+                version: Version::V4,
+            })
+        ));
+        assert!(Envelope::new_req_batch_ref(&body, Flags::empty(), Version::V5).is_ok());
+    }
+
+    fn batch(queries: Vec<BatchQuery>) -> BodyReqBatch {
+        BodyReqBatch::new(
+            BatchType::Logged,
+            queries,
+            Consistency::One,
+            None,
+            None,
+            None,
+            None,
+        )
+    }
+
+    fn query(values: Vec<Value>) -> BatchQuery {
+        BatchQuery {
+            subject: BatchQuerySubj::QueryString("INSERT".into()),
+            values: QueryValues::SimpleValues(values),
+        }
+    }
+
+    #[test]
+    fn should_check_batch_lengths() {
+        assert!(batch(vec![query(vec![]); MAX_SHORT_COUNT])
+            .check_lengths()
+            .is_ok());
+
+        assert!(matches!(
+            batch(vec![query(vec![]); MAX_SHORT_COUNT + 1]).check_lengths(),
+            Err(Error::ValueTooLarge {
+                what: "number of batch statements",
+                ..
+            })
+        ));
+
+        assert!(matches!(
+            batch(vec![query(vec![Value::Null; MAX_SHORT_COUNT + 1])]).check_lengths(),
+            Err(Error::ValueTooLarge {
+                what: "number of bound values",
+                ..
+            })
+        ));
+
+        let mut body = batch(vec![]);
+        body.keyspace = Some("k".repeat(MAX_STRING_LEN + 1));
+        assert!(matches!(
+            body.check_lengths(),
+            Err(Error::ValueTooLarge {
+                what: "keyspace name",
+                ..
+            })
+        ));
+    }
 }
@@ -1,6 +1,6 @@
 use super::Serialize;
 use crate::consistency::Consistency;
-use crate::frame::traits::FromCursor;
+use crate::frame::traits::{FromCursor, FromCursorBorrowed};
 use crate::frame::Version;
 use crate::types::*;
 use crate::{error, Error};
@@ -40,6 +40,45 @@ impl FromCursor for ErrorBody {
     }
 }
 
+/// Borrowed counterpart of [`ErrorBody`], referencing the parsed buffer. Type specific additional
+/// information is kept as raw bytes and parsed on demand with [`ErrorBodyBorrowed::ty`].
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct ErrorBodyBorrowed<'a> {
+    /// Error code.
+    pub code: CInt,
+    /// Error message.
+    pub message: &'a str,
+    /// Raw additional information, depending on the error code.
+    pub additional_info: &'a [u8],
+}
+
+impl<'a> FromCursorBorrowed<'a> for ErrorBodyBorrowed<'a> {
+    fn from_cursor_borrowed(
+        cursor: &mut Cursor<&'a [u8]>,
+        version: Version,
+    ) -> error::Result<Self> {
+        let code = CInt::from_cursor(cursor, version)?;
+        let message = from_cursor_str(cursor)?;
+
+        let position = cursor.position() as usize;
+        let additional_info = cursor.get_ref().get(position..).unwrap_or_default();
+        cursor.set_position(cursor.get_ref().len() as u64);
+
+        Ok(ErrorBodyBorrowed {
+            code,
+            message,
+            additional_info,
+        })
+    }
+}
+
+impl ErrorBodyBorrowed<'_> {
+    /// Parses the type of error along with its additional information.
+    pub fn ty(&self, version: Version) -> error::Result<ErrorType> {
+        ErrorType::from_cursor_with_code(&mut Cursor::new(self.additional_info), self.code, version)
+    }
+}
+
 impl ErrorBody {
     /// Is the error related to bad protocol used. This is a special case which is used in some
     /// situations to detect when a node should not be contacted.
@@ -61,7 +100,73 @@ pub enum FailureInfo {
     /// Represents the number of nodes that experience a failure while executing the request.
     NumFailures(CInt),
     /// Error code map for affected nodes.
-    ReasonMap(HashMap<SocketAddr, CIntShort>),
+    ReasonMap(HashMap<SocketAddr, FailureCode>),
+}
+
+impl FailureInfo {
+    /// Returns the number of nodes that experienced a failure, regardless of protocol version.
+    pub fn num_failures(&self) -> CInt {
+        match self {
+            FailureInfo::NumFailures(count) => *count,
+            FailureInfo::ReasonMap(map) => map.len() as CInt,
+        }
+    }
+
+    /// Returns failure codes of affected nodes, which are available since protocol V5.
+    pub fn reason_map(&self) -> Option<&HashMap<SocketAddr, FailureCode>> {
+        match self {
+            FailureInfo::NumFailures(_) => None,
+            FailureInfo::ReasonMap(map) => Some(map),
+        }
+    }
+}
+
+/// Reason of a failure on a single node, as reported in [`FailureInfo::ReasonMap`].
+#[derive(Debug, Ord, PartialOrd, Eq, PartialEq, Hash, Clone, Copy, Display)]
+#[non_exhaustive]
+pub enum FailureCode {
+    /// The read scanned more tombstones than allowed by `tombstone_failure_threshold`.
+    ReadTooManyTombstones,
+    /// The node timed out.
+    Timeout,
+    /// The node has incompatible schema.
+    IncompatibleSchema,
+    /// The read exceeded the configured size limit.
+    ReadSize,
+    /// The node is down.
+    NodeDown,
+    /// A required secondary index is not available on the node.
+    IndexNotAvailable,
+    /// Unknown failure, either reported as such by the server or with an unrecognized code.
+    Unknown(CIntShort),
+}
+
+impl From<CIntShort> for FailureCode {
+    fn from(code: CIntShort) -> Self {
+        match code {
+            0x0001 => FailureCode::ReadTooManyTombstones,
+            0x0002 => FailureCode::Timeout,
+            0x0003 => FailureCode::IncompatibleSchema,
+            0x0004 => FailureCode::ReadSize,
+            0x0005 => FailureCode::NodeDown,
+            0x0006 => FailureCode::IndexNotAvailable,
+            code => FailureCode::Unknown(code),
+        }
+    }
+}
+
+impl From<FailureCode> for CIntShort {
+    fn from(code: FailureCode) -> Self {
+        match code {
+            FailureCode::ReadTooManyTombstones => 0x0001,
+            FailureCode::Timeout => 0x0002,
+            FailureCode::IncompatibleSchema => 0x0003,
+            FailureCode::ReadSize => 0x0004,
+            FailureCode::NodeDown => 0x0005,
+            FailureCode::IndexNotAvailable => 0x0006,
+            FailureCode::Unknown(code) => code,
+        }
+    }
 }
 
 impl Serialize for FailureInfo {
@@ -74,7 +179,7 @@ impl Serialize for FailureInfo {
 
                 for (endpoint, error_code) in map {
                     endpoint.serialize(cursor, version);
-                    error_code.serialize(cursor, version);
+                    CIntShort::from(*error_code).serialize(cursor, version);
                 }
             }
         }
@@ -85,14 +190,15 @@ impl FromCursor for FailureInfo {
     fn from_cursor(cursor: &mut Cursor<&[u8]>, version: Version) -> error::Result<Self> {
         Ok(match version {
             Version::V3 | Version::V4 => Self::NumFailures(CInt::from_cursor(cursor, version)?),
-            Version::V5 => {
+            Version::V5 | Version::V6 => {
                 let num_failures = CInt::from_cursor(cursor, version)?;
-                let mut map = HashMap::with_capacity(num_failures as usize);
+                let mut map =
+                    HashMap::with_capacity(bounded_capacity(num_failures as usize, cursor));
 
                 for _ in 0..num_failures {
                     let endpoint = SocketAddr::from_cursor(cursor, version)?;
                     let error_code = CIntShort::from_cursor(cursor, version)?;
-                    map.insert(endpoint, error_code);
+                    map.insert(endpoint, error_code.into());
                 }
 
                 Self::ReasonMap(map)
@@ -374,6 +480,12 @@ impl ReadFailureError {
     pub fn replica_has_responded(&self) -> bool {
         self.data_present != 0
     }
+
+    /// Returns failure codes of affected nodes, which are available since protocol V5.
+    #[inline]
+    pub fn reason_map(&self) -> Option<&HashMap<SocketAddr, FailureCode>> {
+        self.failure_info.reason_map()
+    }
 }
 
 impl FromCursor for ReadFailureError {
@@ -462,6 +574,14 @@ impl Serialize for WriteFailureError {
     }
 }
 
+impl WriteFailureError {
+    /// Returns failure codes of affected nodes, which are available since protocol V5.
+    #[inline]
+    pub fn reason_map(&self) -> Option<&HashMap<SocketAddr, FailureCode>> {
+        self.failure_info.reason_map()
+    }
+}
+
 impl FromCursor for WriteFailureError {
     fn from_cursor(
         cursor: &mut Cursor<&[u8]>,
@@ -792,6 +912,65 @@ mod error_tests {
         test_encode_decode(bytes, expected);
     }
 
+    #[test]
+    fn write_failure_with_reason_map() {
+        let bytes = &[
+            0, 0, 21, 0, // write failure
+            0, 3, 102, 111, 111, // message - foo
+            //
+            // write failure
+            0, 0, // consistency any
+            0, 0, 0, 1, // received
+            0, 0, 0, 2, // block_for
+            0, 0, 0, 1, // num failures
+            4, 127, 0, 0, 1, 0, 0, 35, 82, // endpoint - 127.0.0.1:9042
+            0, 1, // failure code - read too many tombstones
+            0, 6, 83, 73, 77, 80, 76, 69, // write type - SIMPLE
+        ];
+
+        let endpoint = "127.0.0.1:9042".parse().unwrap();
+        let mut reason_map = HashMap::new();
+        reason_map.insert(endpoint, FailureCode::ReadTooManyTombstones);
+
+        let expected = ErrorBody {
+            message: "foo".into(),
+            ty: ErrorType::WriteFailure(WriteFailureError {
+                cl: Consistency::Any,
+                received: 1,
+                block_for: 2,
+                failure_info: FailureInfo::ReasonMap(reason_map),
+                write_type: WriteType::Simple,
+            }),
+        };
+
+        let mut cursor: Cursor<&[u8]> = Cursor::new(bytes);
+        let result = ErrorBody::from_cursor(&mut cursor, Version::V5).unwrap();
+        assert_eq!(result, expected);
+
+        match &result.ty {
+            ErrorType::WriteFailure(error) => {
+                assert_eq!(error.failure_info.num_failures(), 1);
+                assert_eq!(
+                    error.reason_map().unwrap()[&endpoint],
+                    FailureCode::ReadTooManyTombstones
+                );
+            }
+            ty => panic!("Unexpected error type: {:?}", ty),
+        }
+
+        let mut buffer = Vec::new();
+        expected.serialize(&mut Cursor::new(&mut buffer), Version::V5);
+        assert_eq!(buffer, bytes);
+    }
+
+    #[test]
+    fn failure_code_round_trip() {
+        assert_eq!(FailureCode::from(5), FailureCode::NodeDown);
+        assert_eq!(FailureCode::from(0), FailureCode::Unknown(0));
+        assert_eq!(FailureCode::from(100), FailureCode::Unknown(100));
+        assert_eq!(CIntShort::from(FailureCode::Unknown(100)), 100);
+    }
+
     #[test]
     fn syntax() {
         let bytes = &[
@@ -844,3 +1023,31 @@ mod error_tests {
         test_encode_decode(bytes, expected);
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::consistency::Consistency;
+    use crate::frame::message_error::{ErrorBody, ErrorBodyBorrowed, ErrorType, UnavailableError};
+    use crate::frame::{FromCursorBorrowed, Serialize, Version};
+    use std::io::Cursor;
+
+    #[test]
+    fn should_parse_borrowed() {
+        let body = ErrorBody {
+            message: "Not enough replicas".into(),
+            ty: ErrorType::Unavailable(UnavailableError {
+                cl: Consistency::Quorum,
+                required: 2,
+                alive: 1,
+            }),
+        };
+
+        let data = body.serialize_to_vec(Version::V4);
+        let result =
+            ErrorBodyBorrowed::from_cursor_borrowed(&mut Cursor::new(&data), Version::V4).unwrap();
+
+        assert_eq!(result.code, 0x1000);
+        assert_eq!(result.message, body.message);
+        assert_eq!(result.ty(Version::V4).unwrap(), body.ty);
+    }
+}
@@ -1,7 +1,9 @@
 use crate::error;
-use crate::frame::{Direction, Envelope, Flags, FromCursor, Opcode, Serialize, Version};
-use crate::query::QueryParams;
-use crate::types::CBytesShort;
+use crate::frame::{
+    Direction, Envelope, Flags, FromCursor, FromCursorBorrowed, Opcode, Serialize, Version,
+};
+use crate::query::{QueryParams, QueryParamsBorrowed};
+use crate::types::{from_cursor_short_bytes_borrowed, CBytesShort};
 use derive_more::Constructor;
 use std::io::Cursor;
 
@@ -17,8 +19,10 @@ impl<'a> Serialize for BodyReqExecute<'a> {
     fn serialize(&self, cursor: &mut Cursor<&mut Vec<u8>>, version: Version) {
         self.id.serialize(cursor, version);
 
-        if let Some(result_metadata_id) = self.result_metadata_id {
-            result_metadata_id.serialize(cursor, version);
+        if version >= Version::V5 {
+            if let Some(result_metadata_id) = self.result_metadata_id {
+                result_metadata_id.serialize(cursor, version);
+            }
         }
 
         self.query_parameters.serialize(cursor, version);
@@ -67,6 +71,37 @@ impl FromCursor for BodyReqExecuteOwned {
     }
 }
 
+/// Borrowed counterpart of [`BodyReqExecuteOwned`], referencing the parsed buffer.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct BodyReqExecuteBorrowed<'a> {
+    pub id: &'a [u8],
+    pub result_metadata_id: Option<&'a [u8]>,
+    pub query_parameters: QueryParamsBorrowed<'a>,
+}
+
+impl<'a> FromCursorBorrowed<'a> for BodyReqExecuteBorrowed<'a> {
+    fn from_cursor_borrowed(
+        cursor: &mut Cursor<&'a [u8]>,
+        version: Version,
+    ) -> error::Result<Self> {
+        let id = from_cursor_short_bytes_borrowed(cursor)?.unwrap_or_default();
+
+        let result_metadata_id = if version >= Version::V5 {
+            from_cursor_short_bytes_borrowed(cursor)?
+        } else {
+            None
+        };
+
+        let query_parameters = QueryParamsBorrowed::from_cursor_borrowed(cursor, version)?;
+
+        Ok(BodyReqExecuteBorrowed {
+            id,
+            result_metadata_id,
+            query_parameters,
+        })
+    }
+}
+
 impl Serialize for BodyReqExecuteOwned {
     fn serialize(&self, cursor: &mut Cursor<&mut Vec<u8>>, version: Version) {
         BodyReqExecute::new(
@@ -79,19 +114,24 @@ impl Serialize for BodyReqExecuteOwned {
 }
 
 impl Envelope {
+    /// Creates an EXECUTE request. Fails if bound values or the keyspace do not fit into their
+    /// protocol fields, or if fields are not supported by given protocol version.
     pub fn new_req_execute(
         id: &CBytesShort,
         result_metadata_id: Option<&CBytesShort>, // only required for protocol >= V5
         query_parameters: &QueryParams,
         flags: Flags,
         version: Version,
-    ) -> Envelope {
+    ) -> error::Result<Envelope> {
         let direction = Direction::Request;
         let opcode = Opcode::Execute;
 
+        query_parameters.check_lengths()?;
+        query_parameters.check_version(version)?;
+
         let body = BodyReqExecute::new(id, result_metadata_id, query_parameters);
 
-        Envelope::new(
+        Ok(Envelope::new(
             version,
             direction,
             flags,
@@ -100,18 +140,21 @@ impl Envelope {
             body.serialize_to_vec(version),
             None,
             vec![],
-        )
+        ))
     }
 }
 
 #[cfg(test)]
 mod tests {
     use crate::consistency::Consistency;
-    use crate::frame::message_execute::BodyReqExecuteOwned;
+    use crate::frame::message_execute::{BodyReqExecuteBorrowed, BodyReqExecuteOwned};
     use crate::frame::traits::Serialize;
-    use crate::frame::{FromCursor, Version};
-    use crate::query::QueryParams;
-    use crate::types::CBytesShort;
+    use crate::frame::{Envelope, Flags, FromCursor, FromCursorBorrowed, Version};
+    use crate::query::{QueryParams, QueryValues};
+    use crate::types::value::{Value, ValueBorrowed};
+    use crate::types::{CBytes, CBytesShort};
+    use crate::Error;
+    use std::collections::HashMap;
     use std::io::Cursor;
 
     #[test]
@@ -137,4 +180,114 @@ mod tests {
             body
         );
     }
+
+    #[test]
+    fn should_round_trip_legacy_versions() {
+        let body = BodyReqExecuteOwned::new(
+            CBytesShort::new(vec![1]),
+            Some(CBytesShort::new(vec![2])),
+            QueryParams {
+                consistency: Consistency::One,
+                values: Some(QueryValues::SimpleValues(vec![Value::new(1)])),
+                page_size: Some(100),
+                timestamp: Some(10),
+                ..Default::default()
+            },
+        );
+
+        for version in [Version::V3, Version::V4] {
+            let data = body.serialize_to_vec(version);
+            let result =
+                BodyReqExecuteOwned::from_cursor(&mut Cursor::new(&data), version).unwrap();
+
+            assert_eq!(result.id, body.id);
+            assert_eq!(result.result_metadata_id, None);
+            assert_eq!(result.query_parameters, body.query_parameters);
+        }
+    }
+
+    #[test]
+    fn should_reject_keyspace_in_v4() {
+        let query_parameters = QueryParams {
+            keyspace: Some("abc".into()),
+            ..Default::default()
+        };
+
+        assert!(matches!(
+            Envelope::new_req_execute(
+                &CBytesShort::new(vec![1]),
+                None,
+                &query_parameters,
+                Flags::empty(),
+                Version::V4
+            ),
+            Err(Error::UnsupportedByVersion {
+                version: Version::V4,
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn should_locate_values_with_trailing_len() {
+        let query_parameters = QueryParams {
+            consistency: Consistency::One,
+            values: Some(QueryValues::SimpleValues(vec![Value::Some(vec![7, 8])])),
+            page_size: Some(100),
+            paging_state: Some(CBytes::new(vec![1, 2, 3])),
+            timestamp: Some(10),
+            keyspace: Some("abc".into()),
+            now_in_seconds: Some(5),
+            ..Default::default()
+        };
+
+        for version in [Version::V4, Version::V5] {
+            let body = BodyReqExecuteOwned::new(
+                CBytesShort::new(vec![1]),
+                Some(CBytesShort::new(vec![2])),
+                query_parameters.clone(),
+            );
+
+            let data = body.serialize_to_vec(version);
+            let values_end = data.len() - query_parameters.trailing_len(version);
+            assert_eq!(&data[(values_end - 6)..values_end], &[0, 0, 0, 2, 7, 8]);
+        }
+    }
+
+    #[test]
+    fn should_parse_borrowed() {
+        let body = BodyReqExecuteOwned::new(
+            CBytesShort::new(vec![1, 2]),
+            Some(CBytesShort::new(vec![3])),
+            QueryParams {
+                consistency: Consistency::Quorum,
+                with_names: true,
+                values: Some(QueryValues::NamedValues(HashMap::from([(
+                    "id".to_string(),
+                    Value::Null,
+                )]))),
+                paging_state: Some(CBytes::new(vec![4])),
+                ..Default::default()
+            },
+        );
+
+        let data = body.serialize_to_vec(Version::V5);
+        let result =
+            BodyReqExecuteBorrowed::from_cursor_borrowed(&mut Cursor::new(&data), Version::V5)
+                .unwrap();
+
+        assert_eq!(result.id, [1, 2]);
+        assert_eq!(result.result_metadata_id, Some([3].as_slice()));
+        assert_eq!(result.query_parameters.consistency, Consistency::Quorum);
+        assert_eq!(result.query_parameters.paging_state, Some([4].as_slice()));
+        assert_eq!(
+            result
+                .query_parameters
+                .values
+                .unwrap()
+                .iter()
+                .collect::<Vec<_>>(),
+            vec![(Some("id"), ValueBorrowed::Null)]
+        );
+    }
 }
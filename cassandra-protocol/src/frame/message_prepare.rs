@@ -24,39 +24,51 @@ impl BodyReqPrepare {
 impl Serialize for BodyReqPrepare {
     #[inline]
     fn serialize(&self, cursor: &mut Cursor<&mut Vec<u8>>, version: Version) {
-        serialize_str_long(cursor, &self.query, version);
-
-        if version >= Version::V5 {
-            if let Some(keyspace) = &self.keyspace {
-                PrepareFlags::WITH_KEYSPACE.serialize(cursor, version);
-                serialize_str(cursor, keyspace.as_str(), version);
-            } else {
-                PrepareFlags::empty().serialize(cursor, version);
-            }
-        }
+        serialize_prepare(cursor, &self.query, self.keyspace.as_deref(), version);
     }
 
     #[inline]
     fn serialize_to_vec(&self, version: Version) -> Vec<u8> {
-        let mut buf = if version >= Version::V5 {
-            Vec::with_capacity(
-                INT_LEN * 2
-                    + self.query.len()
-                    + self
-                        .keyspace
-                        .as_ref()
-                        .map(|keyspace| SHORT_LEN + keyspace.len())
-                        .unwrap_or(0),
-            )
-        } else {
-            Vec::with_capacity(INT_LEN + self.query.len())
-        };
+        serialize_prepare_to_vec(&self.query, self.keyspace.as_deref(), version)
+    }
+}
 
-        self.serialize(&mut Cursor::new(&mut buf), version);
-        buf
+#[inline]
+fn serialize_prepare(
+    cursor: &mut Cursor<&mut Vec<u8>>,
+    query: &str,
+    keyspace: Option<&str>,
+    version: Version,
+) {
+    serialize_str_long(cursor, query, version);
+
+    if version >= Version::V5 {
+        if let Some(keyspace) = keyspace {
+            PrepareFlags::WITH_KEYSPACE.serialize(cursor, version);
+            serialize_str(cursor, keyspace, version);
+        } else {
+            PrepareFlags::empty().serialize(cursor, version);
+        }
     }
 }
 
+fn serialize_prepare_to_vec(query: &str, keyspace: Option<&str>, version: Version) -> Vec<u8> {
+    let mut buf = if version >= Version::V5 {
+        Vec::with_capacity(
+            INT_LEN * 2
+                + query.len()
+                + keyspace
+                    .map(|keyspace| SHORT_LEN + keyspace.len())
+                    .unwrap_or(0),
+        )
+    } else {
+        Vec::with_capacity(INT_LEN + query.len())
+    };
+
+    serialize_prepare(&mut Cursor::new(&mut buf), query, keyspace, version);
+    buf
+}
+
 impl FromCursor for BodyReqPrepare {
     #[inline]
     fn from_cursor(cursor: &mut Cursor<&[u8]>, version: Version) -> error::Result<Self> {
@@ -102,6 +114,26 @@ impl Envelope {
             vec![],
         )
     }
+
+    /// Creates a PREPARE request from borrowed statement text, without copying it into a
+    /// [`BodyReqPrepare`] first.
+    pub fn new_req_prepare_borrowed(
+        query: &str,
+        keyspace: Option<&str>,
+        flags: Flags,
+        version: Version,
+    ) -> Envelope {
+        Envelope::new(
+            version,
+            Direction::Request,
+            flags,
+            Opcode::Prepare,
+            0,
+            serialize_prepare_to_vec(query, keyspace, version),
+            None,
+            vec![],
+        )
+    }
 }
 
 #[cfg(test)]
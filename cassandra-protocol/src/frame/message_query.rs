@@ -1,8 +1,8 @@
 use crate::consistency::Consistency;
 use crate::error;
-use crate::frame::traits::FromCursor;
+use crate::frame::traits::{FromCursor, FromCursorBorrowed};
 use crate::frame::{Direction, Envelope, Flags, Opcode, Serialize, Version};
-use crate::query::{QueryParams, QueryValues};
+use crate::query::{QueryParams, QueryParamsBorrowed, QueryValues};
 use crate::types::{from_cursor_str_long, serialize_str_long, CBytes, CInt, CLong, INT_LEN};
 use std::io::Cursor;
 
@@ -58,23 +58,64 @@ impl FromCursor for BodyReqQuery {
     }
 }
 
+/// Borrowed counterpart of [`BodyReqQuery`], referencing the parsed buffer.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct BodyReqQueryBorrowed<'a> {
+    /// Query string.
+    pub query: &'a str,
+    /// Query parameters.
+    pub query_params: QueryParamsBorrowed<'a>,
+}
+
+impl<'a> FromCursorBorrowed<'a> for BodyReqQueryBorrowed<'a> {
+    fn from_cursor_borrowed(
+        cursor: &mut Cursor<&'a [u8]>,
+        version: Version,
+    ) -> error::Result<Self> {
+        let query = from_cursor_str_long(cursor)?;
+        let query_params = QueryParamsBorrowed::from_cursor_borrowed(cursor, version)?;
+
+        Ok(BodyReqQueryBorrowed {
+            query,
+            query_params,
+        })
+    }
+}
+
 impl Serialize for BodyReqQuery {
     #[inline]
     fn serialize(&self, cursor: &mut Cursor<&mut Vec<u8>>, version: Version) {
-        serialize_str_long(cursor, &self.query, version);
-        self.query_params.serialize(cursor, version);
+        serialize_query(cursor, &self.query, &self.query_params, version);
     }
 
     #[inline]
     fn serialize_to_vec(&self, version: Version) -> Vec<u8> {
-        let mut buf = Vec::with_capacity(INT_LEN + self.query.len());
-
-        self.serialize(&mut Cursor::new(&mut buf), version);
-        buf
+        serialize_query_to_vec(&self.query, &self.query_params, version)
     }
 }
 
+#[inline]
+fn serialize_query(
+    cursor: &mut Cursor<&mut Vec<u8>>,
+    query: &str,
+    query_params: &QueryParams,
+    version: Version,
+) {
+    serialize_str_long(cursor, query, version);
+    query_params.serialize(cursor, version);
+}
+
+#[inline]
+fn serialize_query_to_vec(query: &str, query_params: &QueryParams, version: Version) -> Vec<u8> {
+    let mut buf = Vec::with_capacity(INT_LEN + query.len());
+
+    serialize_query(&mut Cursor::new(&mut buf), query, query_params, version);
+    buf
+}
+
 impl Envelope {
+    /// Creates a QUERY request. Fails if bound values or the keyspace do not fit into their
+    /// protocol fields, or if fields are not supported by given protocol version.
     #[allow(clippy::too_many_arguments)]
     pub fn new_req_query(
         query: String,
@@ -89,7 +130,7 @@ impl Envelope {
         now_in_seconds: Option<CInt>,
         flags: Flags,
         version: Version,
-    ) -> Envelope {
+    ) -> error::Result<Envelope> {
         let direction = Direction::Request;
         let opcode = Opcode::Query;
         let body = BodyReqQuery::new(
@@ -104,8 +145,10 @@ impl Envelope {
             keyspace,
             now_in_seconds,
         );
+        body.query_params.check_lengths()?;
+        body.query_params.check_version(version)?;
 
-        Envelope::new(
+        Ok(Envelope::new(
             version,
             direction,
             flags,
@@ -114,24 +157,209 @@ impl Envelope {
             body.serialize_to_vec(version),
             None,
             vec![],
-        )
+        ))
     }
 
-    #[inline]
-    pub fn new_query(query: BodyReqQuery, flags: Flags, version: Version) -> Envelope {
-        Envelope::new_req_query(
-            query.query,
-            query.query_params.consistency,
-            query.query_params.values,
-            query.query_params.with_names,
-            query.query_params.page_size,
-            query.query_params.paging_state,
-            query.query_params.serial_consistency,
-            query.query_params.timestamp,
-            query.query_params.keyspace,
-            query.query_params.now_in_seconds,
-            flags,
+    /// Creates a QUERY request from borrowed statement text and parameters, without copying them
+    /// into a [`BodyReqQuery`] first. Fails if bound values or the keyspace do not fit into their
+    /// protocol fields, or if fields are not supported by given protocol version.
+    pub fn new_query_borrowed(
+        query: &str,
+        query_params: &QueryParams,
+        flags: Flags,
+        version: Version,
+    ) -> error::Result<Envelope> {
+        query_params.check_lengths()?;
+        query_params.check_version(version)?;
+
+        Ok(Envelope::new(
             version,
-        )
+            Direction::Request,
+            flags,
+            Opcode::Query,
+            0,
+            serialize_query_to_vec(query, query_params, version),
+            None,
+            vec![],
+        ))
+    }
+
+    /// Creates a QUERY request from a body which can be reused for subsequent executions.
+    #[inline]
+    pub fn new_query_ref(
+        query: &BodyReqQuery,
+        flags: Flags,
+        version: Version,
+    ) -> error::Result<Envelope> {
+        Self::new_query_borrowed(&query.query, &query.query_params, flags, version)
+    }
+
+    #[inline]
+    pub fn new_query(
+        query: BodyReqQuery,
+        flags: Flags,
+        version: Version,
+    ) -> error::Result<Envelope> {
+        Self::new_query_ref(&query, flags, version)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::consistency::Consistency;
+    use crate::frame::message_query::{BodyReqQuery, BodyReqQueryBorrowed};
+    use crate::frame::traits::Serialize;
+    use crate::frame::{Envelope, Flags, FromCursor, FromCursorBorrowed, Version};
+    use crate::query::{QueryParams, QueryValues};
+    use crate::types::value::{Value, ValueBorrowed};
+    use crate::types::MAX_SHORT_COUNT;
+    use crate::Error;
+    use std::io::Cursor;
+
+    fn body() -> BodyReqQuery {
+        BodyReqQuery {
+            query: "SELECT * FROM t WHERE a = ?".into(),
+            query_params: QueryParams {
+                consistency: Consistency::LocalQuorum,
+                values: Some(QueryValues::SimpleValues(vec![Value::new("a")])),
+                page_size: Some(10),
+                serial_consistency: Some(Consistency::LocalSerial),
+                timestamp: Some(20),
+                keyspace: Some("abc".into()),
+                now_in_seconds: Some(30),
+                ..Default::default()
+            },
+        }
+    }
+
+    // keyspace and now are not supported before V5
+    fn legacy_body() -> BodyReqQuery {
+        let mut body = body();
+        body.query_params.keyspace = None;
+        body.query_params.now_in_seconds = None;
+        body
+    }
+
+    #[test]
+    fn should_round_trip_legacy_versions() {
+        let body = legacy_body();
+
+        for version in [Version::V3, Version::V4] {
+            let data = body.serialize_to_vec(version);
+            let result = BodyReqQuery::from_cursor(&mut Cursor::new(&data), version).unwrap();
+
+            assert_eq!(result.query_params, body.query_params);
+            assert_eq!(result.query, body.query);
+        }
+    }
+
+    #[test]
+    fn should_reject_v5_fields_in_legacy_versions() {
+        let mut body = body();
+
+        for version in [Version::V3, Version::V4] {
+            assert!(matches!(
+                Envelope::new_query_ref(&body, Flags::empty(), version),
+                Err(Error::UnsupportedByVersion {
+                    what: "Per-statement keyspace",
+                    version: error_version,
+                }) if error_version == version
+            ));
+        }
+
+        body.query_params.keyspace = None;
+        assert!(matches!(
+            Envelope::new_query_ref(&body, Flags::empty(), Version::V4),
+            Err(Error::UnsupportedByVersion {
+                what: "Per-statement current time",
+                ..
+            })
+        ));
+
+        assert!(Envelope::new_query_ref(&body, Flags::empty(), Version::V5).is_ok());
+    }
+
+    #[test]
+    fn should_create_same_envelope_from_borrowed_query() {
+        for (version, body) in [(Version::V4, legacy_body()), (Version::V5, body())] {
+            assert_eq!(
+                Envelope::new_query_borrowed(
+                    &body.query,
+                    &body.query_params,
+                    Flags::empty(),
+                    version
+                )
+                .unwrap(),
+                Envelope::new_query(body.clone(), Flags::empty(), version).unwrap()
+            );
+        }
+    }
+
+    #[test]
+    fn should_round_trip_v5() {
+        let body = body();
+        let data = body.serialize_to_vec(Version::V5);
+        assert_eq!(
+            BodyReqQuery::from_cursor(&mut Cursor::new(&data), Version::V5).unwrap(),
+            body
+        );
+    }
+
+    #[test]
+    fn should_parse_borrowed() {
+        let body = body();
+        let data = body.serialize_to_vec(Version::V5);
+        let result =
+            BodyReqQueryBorrowed::from_cursor_borrowed(&mut Cursor::new(&data), Version::V5)
+                .unwrap();
+
+        assert_eq!(result.query, body.query);
+
+        let params = result.query_params;
+        assert_eq!(params.consistency, Consistency::LocalQuorum);
+        assert_eq!(params.page_size, Some(10));
+        assert_eq!(params.serial_consistency, Some(Consistency::LocalSerial));
+        assert_eq!(params.timestamp, Some(20));
+        assert_eq!(params.keyspace, Some("abc"));
+        assert_eq!(params.now_in_seconds, Some(30));
+
+        let values = params.values.unwrap();
+        assert!(!values.with_names());
+        assert_eq!(
+            values.iter().collect::<Vec<_>>(),
+            vec![(None, ValueBorrowed::Some(b"a"))]
+        );
+    }
+
+    #[test]
+    fn should_round_trip_max_number_of_values() {
+        let mut body = legacy_body();
+        body.query_params.values = Some(QueryValues::SimpleValues(vec![
+            Value::Null;
+            MAX_SHORT_COUNT
+        ]));
+
+        let envelope = Envelope::new_query_ref(&body, Flags::empty(), Version::V4).unwrap();
+        assert_eq!(
+            BodyReqQuery::from_cursor(&mut Cursor::new(&envelope.body), Version::V4).unwrap(),
+            body
+        );
+    }
+
+    #[test]
+    fn should_reject_too_many_values() {
+        let mut body = body();
+        body.query_params.values = Some(QueryValues::SimpleValues(vec![
+            Value::Null;
+            MAX_SHORT_COUNT + 1
+        ]));
+
+        assert!(matches!(
+            Envelope::new_query(body, Flags::empty(), Version::V4),
+            Err(Error::ValueTooLarge {
+                what: "number of bound values",
+                ..
+            })
+        ));
     }
 }
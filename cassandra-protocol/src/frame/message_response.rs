@@ -1,16 +1,20 @@
 use std::io::Cursor;
+use uuid::Uuid;
 
 use crate::frame::message_auth_challenge::BodyResAuthChallenge;
 use crate::frame::message_auth_success::BodyReqAuthSuccess;
 use crate::frame::message_authenticate::BodyResAuthenticate;
 use crate::frame::message_error::ErrorBody;
 use crate::frame::message_event::BodyResEvent;
+use crate::frame::message_result::RowsMetadataFlags;
 use crate::frame::message_result::{
-    BodyResResultPrepared, BodyResResultRows, BodyResResultSetKeyspace, ResResultBody, RowsMetadata,
+    BodyResResultPrepared, BodyResResultRows, BodyResResultSetKeyspace, ResResultBody,
+    RowsMetadata, SchemaChange,
 };
 use crate::frame::message_supported::BodyResSupported;
-use crate::frame::{FromCursor, Opcode, Version};
+use crate::frame::{Envelope, FromCursor, Opcode, Version};
 use crate::types::rows::Row;
+use crate::types::{CBytes, IntoRustByIndex};
 use crate::{error, Error};
 
 #[derive(Debug, PartialEq, Eq, Clone)]
@@ -110,6 +114,37 @@ impl ResponseBody {
         }
     }
 
+    /// Consumes the body, returning the only value of a result containing exactly one row with
+    /// exactly one column, e.g. of `SELECT count(*) ...` or other single-value aggregates. Fails if
+    /// the result has a different shape, or if the value is null.
+    pub fn into_scalar<T>(self) -> error::Result<T>
+    where
+        Row: IntoRustByIndex<T>,
+    {
+        let mut rows = self
+            .into_rows()
+            .ok_or("Scalar query should yield a vector of rows")?;
+
+        if rows.len() != 1 {
+            return Err(format!(
+                "Scalar query should yield exactly one row, got {}",
+                rows.len()
+            )
+            .into());
+        }
+
+        let row = rows.remove(0);
+        let column_count = row.column_count();
+        if column_count != 1 {
+            return Err(format!(
+                "Scalar query should yield exactly one column, got {column_count}"
+            )
+            .into());
+        }
+
+        row.get_r_by_index(0)
+    }
+
     /// Unwraps body and returns BodyResResultPrepared which contains an exact result of
     /// PREPARE query.
     pub fn into_prepared(self) -> Option<BodyResResultPrepared> {
@@ -128,6 +163,15 @@ impl ResponseBody {
         }
     }
 
+    /// Unwraps body and returns SchemaChange which describes the result of a schema altering
+    /// query.
+    pub fn into_schema_change(self) -> Option<SchemaChange> {
+        match self {
+            ResponseBody::Result(res) => res.into_schema_change(),
+            _ => None,
+        }
+    }
+
     /// Unwraps body and returns BodyResEvent.
     pub fn into_server_event(self) -> Option<BodyResEvent> {
         match self {
@@ -150,3 +194,186 @@ impl ResponseBody {
         }
     }
 }
+
+/// Response envelope along with its body, parsed once, so both can be accessed without parsing
+/// the body again, e.g. rows along with the paging state and warnings.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct ParsedResponse {
+    envelope: Envelope,
+    body: ResponseBody,
+}
+
+impl ParsedResponse {
+    /// Parses the body of given response envelope.
+    pub fn new(envelope: Envelope) -> error::Result<Self> {
+        let body = envelope.response_body()?;
+        Ok(ParsedResponse { envelope, body })
+    }
+
+    #[inline]
+    pub fn envelope(&self) -> &Envelope {
+        &self.envelope
+    }
+
+    #[inline]
+    pub fn body(&self) -> &ResponseBody {
+        &self.body
+    }
+
+    #[inline]
+    pub fn into_envelope(self) -> Envelope {
+        self.envelope
+    }
+
+    #[inline]
+    pub fn into_body(self) -> ResponseBody {
+        self.body
+    }
+
+    /// Returns rows metadata, if the response contains rows.
+    #[inline]
+    pub fn rows_metadata(&self) -> Option<&RowsMetadata> {
+        self.body.as_rows_metadata()
+    }
+
+    /// Returns the paging state for fetching the next page, if the response contains rows and
+    /// there are more pages.
+    #[inline]
+    pub fn paging_state(&self) -> Option<&CBytes> {
+        self.rows_metadata()
+            .and_then(|metadata| metadata.paging_state.as_ref())
+    }
+
+    /// Checks if the response contains rows and there are more pages to fetch.
+    #[inline]
+    pub fn has_more_pages(&self) -> bool {
+        self.rows_metadata()
+            .map(|metadata| metadata.flags.contains(RowsMetadataFlags::HAS_MORE_PAGES))
+            .unwrap_or(false)
+    }
+
+    #[inline]
+    pub fn tracing_id(&self) -> Option<Uuid> {
+        self.envelope.tracing_id()
+    }
+
+    #[inline]
+    pub fn warnings(&self) -> &[String] {
+        self.envelope.warnings()
+    }
+
+    /// Consumes the response, returning its rows, if it contains any.
+    #[inline]
+    pub fn into_rows(self) -> Option<Vec<Row>> {
+        self.body.into_rows()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use uuid::Uuid;
+
+    use super::ResponseBody;
+    use crate::frame::message_result::{
+        BodyResResultRows, ColSpec, ColType, ColTypeOption, ResResultBody, RowsMetadata,
+        RowsMetadataFlags, TableSpec,
+    };
+    use crate::frame::{Direction, Envelope, Flags, Opcode, Serialize, Version};
+    use crate::types::CBytes;
+
+    fn rows_body(columns: usize, rows_content: Vec<Vec<CBytes>>) -> ResponseBody {
+        let col_specs = (0..columns)
+            .map(|index| ColSpec {
+                table_spec: None,
+                name: format!("column{index}"),
+                col_type: ColTypeOption {
+                    id: ColType::Bigint,
+                    value: None,
+                },
+            })
+            .collect();
+
+        ResponseBody::Result(ResResultBody::Rows(BodyResResultRows {
+            metadata: RowsMetadata {
+                flags: RowsMetadataFlags::empty(),
+                columns_count: columns as i32,
+                paging_state: None,
+                new_metadata_id: None,
+                global_table_spec: None,
+                col_specs,
+            },
+            rows_count: rows_content.len() as i32,
+            rows_content,
+            protocol_version: Version::V4,
+        }))
+    }
+
+    fn value(value: i64) -> CBytes {
+        CBytes::new(value.to_be_bytes().to_vec())
+    }
+
+    #[test]
+    fn should_extract_scalar() {
+        let body = rows_body(1, vec![vec![value(42)]]);
+        assert_eq!(body.into_scalar::<i64>().unwrap(), 42);
+    }
+
+    #[test]
+    fn should_reject_non_scalar_results() {
+        let error = rows_body(1, vec![]).into_scalar::<i64>().unwrap_err();
+        assert!(error.to_string().contains("exactly one row, got 0"));
+
+        let error = rows_body(1, vec![vec![value(1)], vec![value(2)]])
+            .into_scalar::<i64>()
+            .unwrap_err();
+        assert!(error.to_string().contains("exactly one row, got 2"));
+
+        let error = rows_body(2, vec![vec![value(1), value(2)]])
+            .into_scalar::<i64>()
+            .unwrap_err();
+        assert!(error.to_string().contains("exactly one column, got 2"));
+
+        assert!(rows_body(1, vec![vec![CBytes::new_null()]])
+            .into_scalar::<i64>()
+            .is_err());
+        assert!(ResponseBody::Ready.into_scalar::<i64>().is_err());
+    }
+
+    #[test]
+    fn should_parse_response_once() {
+        let mut body = rows_body(1, vec![vec![value(1)], vec![value(2)]]);
+        if let ResponseBody::Result(ResResultBody::Rows(rows)) = &mut body {
+            rows.metadata.flags =
+                RowsMetadataFlags::GLOBAL_TABLE_SPACE | RowsMetadataFlags::HAS_MORE_PAGES;
+            rows.metadata.global_table_spec = Some(TableSpec {
+                ks_name: "ks".into(),
+                table_name: "table".into(),
+            });
+            rows.metadata.paging_state = Some(CBytes::new(vec![1, 2]));
+        }
+
+        let tracing_id = Uuid::from_u128(1);
+        let envelope = Envelope::new(
+            Version::V4,
+            Direction::Response,
+            Flags::TRACING | Flags::WARNING,
+            Opcode::Result,
+            1,
+            body.serialize_to_vec(Version::V4),
+            Some(tracing_id),
+            vec!["warning".into()],
+        );
+
+        let response = envelope.clone().into_parsed_response().unwrap();
+        assert_eq!(response.body(), &body);
+        assert_eq!(response.envelope(), &envelope);
+        assert!(response.has_more_pages());
+        assert_eq!(response.paging_state(), Some(&CBytes::new(vec![1, 2])));
+        assert_eq!(response.tracing_id(), Some(tracing_id));
+        assert_eq!(response.warnings(), ["warning"]);
+        assert_eq!(response.into_rows().unwrap().len(), 2);
+
+        let envelope = Envelope::new_req_options(Version::V4);
+        assert!(envelope.into_parsed_response().is_err());
+    }
+}
@@ -1,11 +1,13 @@
 use crate::error;
 use crate::error::Error;
-use crate::frame::events::SchemaChange;
-use crate::frame::{FromBytes, FromCursor, Serialize, Version};
+pub use crate::frame::events::SchemaChange;
+use crate::frame::{FromBytes, FromCursor, FromCursorBorrowed, Serialize, Version};
 use crate::types::rows::Row;
 use crate::types::{
-    from_cursor_str, serialize_str, try_i16_from_bytes, try_i32_from_bytes, try_u64_from_bytes,
-    CBytes, CBytesShort, CInt, CIntShort, INT_LEN, SHORT_LEN,
+    bounded_capacity, from_cursor_bytes_borrowed, from_cursor_short_bytes_borrowed,
+    from_cursor_short_len, from_cursor_str, serialize_short_len, serialize_str, try_i16_from_bytes,
+    try_i32_from_bytes, try_u64_from_bytes, CBytes, CBytesShort, CInt, CIntShort, INT_LEN,
+    SHORT_LEN,
 };
 use bitflags::bitflags;
 use derive_more::{Constructor, Display};
@@ -26,6 +28,9 @@ pub enum ResultKind {
     Prepared,
     /// Schema change result.
     SchemaChange,
+    /// Result kind unknown to this driver, e.g. introduced by a newer protocol version.
+    #[display("Other({_0})")]
+    Other(CInt),
 }
 
 impl Serialize for ResultKind {
@@ -51,6 +56,7 @@ impl From<ResultKind> for CInt {
             ResultKind::SetKeyspace => 0x0003,
             ResultKind::Prepared => 0x0004,
             ResultKind::SchemaChange => 0x0005,
+            ResultKind::Other(value) => value,
         }
     }
 }
@@ -65,7 +71,7 @@ impl TryFrom<CInt> for ResultKind {
             0x0003 => Ok(ResultKind::SetKeyspace),
             0x0004 => Ok(ResultKind::Prepared),
             0x0005 => Ok(ResultKind::SchemaChange),
-            _ => Err(Error::UnexpectedResultKind(value)),
+            _ => Ok(ResultKind::Other(value)),
         }
     }
 }
@@ -145,6 +151,7 @@ impl ResResultBody {
             ResultKind::SchemaChange => {
                 ResResultBody::SchemaChange(SchemaChange::from_cursor(cursor, version)?)
             }
+            ResultKind::Other(value) => return Err(Error::UnexpectedResultKind(value)),
         })
     }
 
@@ -181,6 +188,15 @@ impl ResResultBody {
             _ => None,
         }
     }
+
+    /// Unwraps body and returns SchemaChange which describes the result of a schema altering
+    /// query.
+    pub fn into_schema_change(self) -> Option<SchemaChange> {
+        match self {
+            ResResultBody::SchemaChange(schema_change) => Some(schema_change),
+            _ => None,
+        }
+    }
 }
 
 impl ResResultBody {
@@ -249,6 +265,14 @@ impl BodyResResultRows {
         columns_count: i32,
         version: Version,
     ) -> error::Result<Vec<Vec<CBytes>>> {
+        // rows without columns take no space, so their count can't be checked against the
+        // remaining bytes
+        if rows_count < 0 || columns_count < 0 || (columns_count == 0 && rows_count > 0) {
+            return Err(Error::General(format!(
+                "Invalid rows result with {rows_count} rows and {columns_count} columns"
+            )));
+        }
+
         (0..rows_count)
             .map(|_| {
                 (0..columns_count)
@@ -533,6 +557,10 @@ pub enum ColType {
     Set,
     Udt,
     Tuple,
+    /// Type unknown to this driver, e.g. introduced by a newer protocol version. Values of such
+    /// columns can only be read as raw bytes.
+    #[display("Other({_0})")]
+    Other(CIntShort),
 }
 
 impl TryFrom<CIntShort> for ColType {
@@ -567,7 +595,7 @@ impl TryFrom<CIntShort> for ColType {
             0x0030 => Ok(ColType::Udt),
             0x0031 => Ok(ColType::Tuple),
             0x0080 => Ok(ColType::Varchar),
-            _ => Err(Error::UnexpectedColumnType(value)),
+            _ => Ok(ColType::Other(value)),
         }
     }
 }
@@ -610,6 +638,7 @@ impl Serialize for ColType {
             ColType::Set => 0x0022,
             ColType::Udt => 0x0030,
             ColType::Tuple => 0x0031,
+            ColType::Other(value) => *value,
         } as CIntShort)
             .serialize(cursor, version);
     }
@@ -727,7 +756,7 @@ impl Serialize for CUdt {
     fn serialize(&self, cursor: &mut Cursor<&mut Vec<u8>>, version: Version) {
         serialize_str(cursor, &self.ks, version);
         serialize_str(cursor, &self.udt_name, version);
-        (self.descriptions.len() as i16).serialize(cursor, version);
+        serialize_short_len(cursor, self.descriptions.len());
         self.descriptions.iter().for_each(|(name, col_type)| {
             serialize_str(cursor, name, version);
             col_type.serialize(cursor, version);
@@ -740,11 +769,8 @@ impl FromCursor for CUdt {
         let ks = from_cursor_str(cursor)?.to_string();
         let udt_name = from_cursor_str(cursor)?.to_string();
 
-        let mut buff = [0; SHORT_LEN];
-        cursor.read_exact(&mut buff)?;
-
-        let n = i16::from_be_bytes(buff);
-        let mut descriptions = Vec::with_capacity(n as usize);
+        let n = from_cursor_short_len(cursor)?;
+        let mut descriptions = Vec::with_capacity(bounded_capacity(n, cursor));
         for _ in 0..n {
             let name = from_cursor_str(cursor)?.to_string();
             let col_type = ColTypeOption::from_cursor(cursor, version)?;
@@ -770,18 +796,15 @@ pub struct CTuple {
 impl Serialize for CTuple {
     #[inline]
     fn serialize(&self, cursor: &mut Cursor<&mut Vec<u8>>, version: Version) {
-        (self.types.len() as i16).serialize(cursor, version);
+        serialize_short_len(cursor, self.types.len());
         self.types.iter().for_each(|f| f.serialize(cursor, version));
     }
 }
 
 impl FromCursor for CTuple {
     fn from_cursor(cursor: &mut Cursor<&[u8]>, version: Version) -> error::Result<CTuple> {
-        let mut buff = [0; SHORT_LEN];
-        cursor.read_exact(&mut buff)?;
-
-        let n = i16::from_be_bytes(buff);
-        let mut types = Vec::with_capacity(n as usize);
+        let n = from_cursor_short_len(cursor)?;
+        let mut types = Vec::with_capacity(bounded_capacity(n, cursor));
         for _ in 0..n {
             let col_type = ColTypeOption::from_cursor(cursor, version)?;
             types.push(col_type);
@@ -826,7 +849,7 @@ impl BodyResResultPrepared {
     ) -> error::Result<BodyResResultPrepared> {
         let id = CBytesShort::from_cursor(cursor, version)?;
 
-        let result_metadata_id = if version == Version::V5 {
+        let result_metadata_id = if version >= Version::V5 {
             Some(CBytesShort::from_cursor(cursor, version)?)
         } else {
             None
@@ -895,7 +918,7 @@ impl Serialize for PreparedMetadata {
     }
 }
 
-impl PreparedMetadata {
+impl FromCursor for PreparedMetadata {
     fn from_cursor(
         cursor: &mut Cursor<&[u8]>,
         version: Version,
@@ -933,6 +956,281 @@ impl PreparedMetadata {
     }
 }
 
+/// Borrowed counterpart of [`BodyResResultRows`], referencing the parsed buffer. Rows are kept as
+/// raw bytes and parsed on iteration.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct BodyResResultRowsBorrowed<'a> {
+    /// Rows metadata.
+    pub metadata: RowsMetadataBorrowed<'a>,
+    /// Number of rows.
+    pub rows_count: CInt,
+    rows_content: &'a [u8],
+}
+
+impl<'a> FromCursorBorrowed<'a> for BodyResResultRowsBorrowed<'a> {
+    fn from_cursor_borrowed(
+        cursor: &mut Cursor<&'a [u8]>,
+        version: Version,
+    ) -> error::Result<Self> {
+        let metadata = RowsMetadataBorrowed::from_cursor_borrowed(cursor, version)?;
+        let rows_count = CInt::from_cursor(cursor, version)?;
+
+        let cell_count = rows_count.max(0) as usize * metadata.columns_count.max(0) as usize;
+        let rows_content = borrow_walked(cursor, |cursor| {
+            for _ in 0..cell_count {
+                from_cursor_bytes_borrowed(cursor)?;
+            }
+
+            Ok(())
+        })?;
+
+        Ok(BodyResResultRowsBorrowed {
+            metadata,
+            rows_count,
+            rows_content,
+        })
+    }
+}
+
+impl<'a> BodyResResultRowsBorrowed<'a> {
+    /// Returns rows in the order they were sent.
+    pub fn rows(&self) -> impl Iterator<Item = RowBorrowed<'a>> + '_ {
+        let mut cursor = Cursor::new(self.rows_content);
+        let columns_count = self.metadata.columns_count.max(0) as usize;
+
+        (0..self.rows_count.max(0)).map(move |_| {
+            let cells = borrow_walked(&mut cursor, |cursor| {
+                for _ in 0..columns_count {
+                    from_cursor_bytes_borrowed(cursor)?;
+                }
+
+                Ok(())
+            })
+            .expect("Rows have been validated when parsing");
+
+            RowBorrowed {
+                columns_count,
+                cells,
+            }
+        })
+    }
+}
+
+/// Single row of [`BodyResResultRowsBorrowed`].
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct RowBorrowed<'a> {
+    columns_count: usize,
+    cells: &'a [u8],
+}
+
+impl<'a> RowBorrowed<'a> {
+    /// Returns raw cell values in column order, with `None` for nulls.
+    pub fn cells(&self) -> impl Iterator<Item = Option<&'a [u8]>> + '_ {
+        let mut cursor = Cursor::new(self.cells);
+        (0..self.columns_count).map(move |_| {
+            from_cursor_bytes_borrowed(&mut cursor).expect("Rows have been validated when parsing")
+        })
+    }
+}
+
+/// Borrowed counterpart of [`RowsMetadata`], referencing the parsed buffer. Column specifications
+/// are kept as raw bytes and parsed on iteration.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RowsMetadataBorrowed<'a> {
+    /// Flags.
+    pub flags: RowsMetadataFlags,
+    /// Number of columns.
+    pub columns_count: i32,
+    /// Paging state.
+    pub paging_state: Option<&'a [u8]>,
+    /// New, changed result set metadata id.
+    pub new_metadata_id: Option<&'a [u8]>,
+    /// Global table space, if present.
+    pub global_table_spec: Option<TableSpecBorrowed<'a>>,
+    col_specs: &'a [u8],
+}
+
+impl<'a> FromCursorBorrowed<'a> for RowsMetadataBorrowed<'a> {
+    fn from_cursor_borrowed(
+        cursor: &mut Cursor<&'a [u8]>,
+        version: Version,
+    ) -> error::Result<Self> {
+        let flags = RowsMetadataFlags::from_bits_truncate(CInt::from_cursor(cursor, version)?);
+        let columns_count = CInt::from_cursor(cursor, version)?;
+
+        let paging_state = if flags.contains(RowsMetadataFlags::HAS_MORE_PAGES) {
+            from_cursor_bytes_borrowed(cursor)?
+        } else {
+            None
+        };
+
+        if flags.contains(RowsMetadataFlags::NO_METADATA) {
+            return Ok(RowsMetadataBorrowed {
+                flags,
+                columns_count,
+                paging_state,
+                new_metadata_id: None,
+                global_table_spec: None,
+                col_specs: &[],
+            });
+        }
+
+        let new_metadata_id = if flags.contains(RowsMetadataFlags::METADATA_CHANGED) {
+            from_cursor_short_bytes_borrowed(cursor)?
+        } else {
+            None
+        };
+
+        let has_global_table_space = flags.contains(RowsMetadataFlags::GLOBAL_TABLE_SPACE);
+        let global_table_spec = if has_global_table_space {
+            Some(TableSpecBorrowed::from_cursor_borrowed(cursor, version)?)
+        } else {
+            None
+        };
+
+        let col_specs = borrow_walked(cursor, |cursor| {
+            for _ in 0..columns_count {
+                ColSpecBorrowed::from_cursor(cursor, has_global_table_space)?;
+            }
+
+            Ok(())
+        })?;
+
+        Ok(RowsMetadataBorrowed {
+            flags,
+            columns_count,
+            paging_state,
+            new_metadata_id,
+            global_table_spec,
+            col_specs,
+        })
+    }
+}
+
+impl<'a> RowsMetadataBorrowed<'a> {
+    /// Returns column specifications. Empty if metadata was skipped.
+    pub fn col_specs(&self) -> impl Iterator<Item = ColSpecBorrowed<'a>> + '_ {
+        let mut cursor = Cursor::new(self.col_specs);
+        let has_global_table_space = self.flags.contains(RowsMetadataFlags::GLOBAL_TABLE_SPACE);
+        let count = if self.col_specs.is_empty() {
+            0
+        } else {
+            self.columns_count.max(0)
+        };
+
+        (0..count).map(move |_| {
+            ColSpecBorrowed::from_cursor(&mut cursor, has_global_table_space)
+                .expect("Column specifications have been validated when parsing")
+        })
+    }
+}
+
+/// Borrowed counterpart of [`TableSpec`].
+#[derive(Debug, Clone, Copy, PartialEq, Ord, PartialOrd, Eq, Hash)]
+pub struct TableSpecBorrowed<'a> {
+    pub ks_name: &'a str,
+    pub table_name: &'a str,
+}
+
+impl<'a> FromCursorBorrowed<'a> for TableSpecBorrowed<'a> {
+    fn from_cursor_borrowed(
+        cursor: &mut Cursor<&'a [u8]>,
+        _version: Version,
+    ) -> error::Result<Self> {
+        let ks_name = from_cursor_str(cursor)?;
+        let table_name = from_cursor_str(cursor)?;
+        Ok(TableSpecBorrowed {
+            ks_name,
+            table_name,
+        })
+    }
+}
+
+/// Borrowed counterpart of [`ColSpec`]. The column type is kept as raw bytes, since parsing it
+/// requires allocations, and can be parsed with [`ColSpecBorrowed::col_type`].
+#[derive(Debug, Clone, Copy, PartialEq, Ord, PartialOrd, Eq, Hash)]
+pub struct ColSpecBorrowed<'a> {
+    /// Only present if the global table spec flag is not set.
+    pub table_spec: Option<TableSpecBorrowed<'a>>,
+    /// Column name.
+    pub name: &'a str,
+    /// Raw column type option.
+    pub raw_col_type: &'a [u8],
+}
+
+impl<'a> ColSpecBorrowed<'a> {
+    fn from_cursor(
+        cursor: &mut Cursor<&'a [u8]>,
+        has_global_table_space: bool,
+    ) -> error::Result<Self> {
+        let table_spec = if !has_global_table_space {
+            // table specs don't depend on protocol version
+            Some(TableSpecBorrowed::from_cursor_borrowed(
+                cursor,
+                Version::V4,
+            )?)
+        } else {
+            None
+        };
+
+        let name = from_cursor_str(cursor)?;
+        let raw_col_type = borrow_walked(cursor, skip_col_type_option)?;
+
+        Ok(ColSpecBorrowed {
+            table_spec,
+            name,
+            raw_col_type,
+        })
+    }
+
+    /// Parses the column type.
+    pub fn col_type(&self, version: Version) -> error::Result<ColTypeOption> {
+        ColTypeOption::from_cursor(&mut Cursor::new(self.raw_col_type), version)
+    }
+}
+
+// advances the cursor past a column type option without allocating
+fn skip_col_type_option(cursor: &mut Cursor<&[u8]>) -> error::Result<()> {
+    match ColType::from_cursor(cursor, Version::V4)? {
+        ColType::Custom => {
+            from_cursor_str(cursor)?;
+        }
+        ColType::Set | ColType::List => skip_col_type_option(cursor)?,
+        ColType::Map => {
+            skip_col_type_option(cursor)?;
+            skip_col_type_option(cursor)?;
+        }
+        ColType::Udt => {
+            from_cursor_str(cursor)?;
+            from_cursor_str(cursor)?;
+
+            for _ in 0..from_cursor_short_len(cursor)? {
+                from_cursor_str(cursor)?;
+                skip_col_type_option(cursor)?;
+            }
+        }
+        ColType::Tuple => {
+            for _ in 0..from_cursor_short_len(cursor)? {
+                skip_col_type_option(cursor)?;
+            }
+        }
+        _ => {}
+    }
+
+    Ok(())
+}
+
+// runs given parser and returns the bytes it has consumed
+fn borrow_walked<'a>(
+    cursor: &mut Cursor<&'a [u8]>,
+    walk: impl FnOnce(&mut Cursor<&'a [u8]>) -> error::Result<()>,
+) -> error::Result<&'a [u8]> {
+    let start = cursor.position() as usize;
+    walk(cursor)?;
+
+    Ok(&cursor.get_ref()[start..cursor.position() as usize])
+}
+
 fn extract_global_table_space(
     cursor: &mut Cursor<&[u8]>,
     has_global_table_space: bool,
@@ -1151,6 +1449,20 @@ mod col_type_option {
         }
     }
 
+    #[test]
+    fn col_type_options_unknown() {
+        let bytes = &[0, 64];
+        let expected = ColTypeOption {
+            id: ColType::Other(64),
+            value: None,
+        };
+
+        let mut cursor: Cursor<&[u8]> = Cursor::new(bytes);
+        let col_type_option = ColTypeOption::from_cursor(&mut cursor, Version::V4).unwrap();
+        assert_eq!(col_type_option, expected);
+        assert_eq!(expected.serialize_to_vec(Version::V4), bytes);
+    }
+
     #[test]
     fn col_type_options_map() {
         let bytes = &[0, 33, 0, 9, 0, 9];
@@ -1224,6 +1536,21 @@ mod void {
         let expected = ResResultBody::Void;
         test_encode_decode(bytes, expected);
     }
+
+    #[test]
+    fn test_unknown_result_kind() {
+        let bytes = &[0, 0, 0, 42];
+
+        let result_kind = ResultKind::from_bytes(bytes).unwrap();
+        assert_eq!(result_kind, ResultKind::Other(42));
+        assert_eq!(CInt::from(result_kind), 42);
+
+        let mut cursor: Cursor<&[u8]> = Cursor::new(bytes);
+        assert!(matches!(
+            ResResultBody::from_cursor(&mut cursor, Version::V4),
+            Err(Error::UnexpectedResultKind(42))
+        ));
+    }
 }
 
 #[cfg(test)]
@@ -1390,6 +1717,102 @@ mod rows {
 
         test_encode_decode(bytes, expected);
     }
+
+    #[test]
+    fn test_rows_borrowed() {
+        let map_type = ColTypeOption {
+            id: ColType::Map,
+            value: Some(ColTypeOptionValue::CMap(
+                Box::new(ColTypeOption {
+                    id: ColType::Varchar,
+                    value: None,
+                }),
+                Box::new(ColTypeOption {
+                    id: ColType::Int,
+                    value: None,
+                }),
+            )),
+        };
+
+        let rows = BodyResResultRows {
+            metadata: RowsMetadata {
+                flags: RowsMetadataFlags::GLOBAL_TABLE_SPACE | RowsMetadataFlags::HAS_MORE_PAGES,
+                columns_count: 2,
+                paging_state: Some(CBytes::new(vec![9])),
+                new_metadata_id: None,
+                global_table_spec: Some(TableSpec {
+                    ks_name: "ks".into(),
+                    table_name: "t".into(),
+                }),
+                col_specs: vec![
+                    ColSpec {
+                        table_spec: None,
+                        name: "id".into(),
+                        col_type: ColTypeOption {
+                            id: ColType::Int,
+                            value: None,
+                        },
+                    },
+                    ColSpec {
+                        table_spec: None,
+                        name: "tags".into(),
+                        col_type: map_type.clone(),
+                    },
+                ],
+            },
+            rows_count: 2,
+            rows_content: vec![
+                vec![CBytes::new(vec![0, 0, 0, 1]), CBytes::new_null()],
+                vec![CBytes::new(vec![0, 0, 0, 2]), CBytes::new(vec![])],
+            ],
+            protocol_version: Version::V4,
+        };
+
+        let data = rows.serialize_to_vec(Version::V4);
+        let result =
+            BodyResResultRowsBorrowed::from_cursor_borrowed(&mut Cursor::new(&data), Version::V4)
+                .unwrap();
+
+        assert_eq!(result.rows_count, 2);
+        assert_eq!(result.metadata.paging_state, Some([9].as_slice()));
+        assert_eq!(
+            result.metadata.global_table_spec,
+            Some(TableSpecBorrowed {
+                ks_name: "ks",
+                table_name: "t",
+            })
+        );
+
+        let col_specs: Vec<_> = result.metadata.col_specs().collect();
+        assert_eq!(col_specs.len(), 2);
+        assert_eq!(col_specs[1].name, "tags");
+        assert_eq!(col_specs[1].col_type(Version::V4).unwrap(), map_type);
+
+        let cells: Vec<Vec<_>> = result.rows().map(|row| row.cells().collect()).collect();
+        assert_eq!(
+            cells,
+            vec![
+                vec![Some([0, 0, 0, 1].as_slice()), None],
+                vec![Some([0, 0, 0, 2].as_slice()), Some([].as_slice())],
+            ]
+        );
+    }
+
+    #[test]
+    fn test_rows_borrowed_truncated() {
+        let bytes = &[
+            0, 0, 0, 4, // rows metadata flag
+            0, 0, 0, 1, // columns count
+            0, 0, 0, 1, // rows count
+            0, 0, 0, 4, 1, // truncated cell
+        ];
+
+        assert!(BodyResResultRowsBorrowed::from_cursor_borrowed(
+            &mut Cursor::new(bytes.as_slice()),
+            Version::V4
+        )
+        .is_err());
+    }
 }
 
 #[cfg(test)]
@@ -1592,6 +2015,43 @@ mod prepared {
 
         test_encode_decode(bytes, expected);
     }
+
+    #[test]
+    fn test_prepared_with_result_metadata_id() {
+        for version in [Version::V5, Version::V6] {
+            let expected = ResResultBody::Prepared(BodyResResultPrepared {
+                id: CBytesShort::new(to_short(1)),
+                result_metadata_id: Some(CBytesShort::new(vec![1, 2, 3])),
+                metadata: PreparedMetadata {
+                    pk_indexes: vec![0],
+                    global_table_spec: Some(TableSpec {
+                        ks_name: "ks".into(),
+                        table_name: "t".into(),
+                    }),
+                    col_specs: vec![ColSpec {
+                        table_spec: None,
+                        name: "foo".into(),
+                        col_type: ColTypeOption {
+                            id: ColType::Int,
+                            value: None,
+                        },
+                    }],
+                },
+                result_metadata: RowsMetadata {
+                    flags: RowsMetadataFlags::NO_METADATA,
+                    columns_count: 1,
+                    paging_state: None,
+                    new_metadata_id: None,
+                    global_table_spec: None,
+                    col_specs: vec![],
+                },
+            });
+
+            let bytes = expected.serialize_to_vec(version);
+            let result = ResResultBody::from_cursor(&mut Cursor::new(&bytes), version).unwrap();
+            assert_eq!(result, expected, "{:?}", version);
+        }
+    }
 }
 
 #[cfg(test)]
@@ -1616,4 +2076,21 @@ mod schema_change {
 
         test_encode_decode(bytes, expected);
     }
+
+    #[test]
+    fn should_unwrap_schema_change() {
+        let body = ResResultBody::SchemaChange(SchemaChange {
+            change_type: SchemaChangeType::Updated,
+            target: SchemaChangeTarget::Table,
+            options: SchemaChangeOptions::TableType("ks".into(), "users".into()),
+        });
+
+        let schema_change = body.into_schema_change().unwrap();
+        assert_eq!(schema_change.change_type, SchemaChangeType::Updated);
+        assert_eq!(schema_change.target, SchemaChangeTarget::Table);
+        assert_eq!(schema_change.keyspace(), "ks");
+        assert_eq!(schema_change.name(), Some("users"));
+
+        assert!(ResResultBody::Void.into_schema_change().is_none());
+    }
 }
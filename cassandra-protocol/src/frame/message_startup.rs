@@ -1,6 +1,9 @@
 use crate::error;
 use crate::frame::{Direction, Envelope, Flags, FromCursor, Opcode, Serialize, Version};
-use crate::types::{from_cursor_str, serialize_str, CIntShort};
+use crate::types::{
+    bounded_capacity, check_len, from_cursor_short_len, from_cursor_str, serialize_short_len,
+    serialize_str, MAX_SHORT_COUNT, MAX_STRING_LEN,
+};
 use std::collections::HashMap;
 use std::io::Cursor;
 
@@ -32,12 +35,23 @@ impl BodyReqStartup {
 
         BodyReqStartup { map }
     }
+
+    /// Checks if the number of options and their keys and values fit into their protocol fields.
+    pub fn check_lengths(&self) -> error::Result<()> {
+        check_len("number of startup options", self.map.len(), MAX_SHORT_COUNT)?;
+
+        for (key, value) in &self.map {
+            check_len("startup option", key.len(), MAX_STRING_LEN)?;
+            check_len("startup option", value.len(), MAX_STRING_LEN)?;
+        }
+
+        Ok(())
+    }
 }
 
 impl Serialize for BodyReqStartup {
     fn serialize(&self, cursor: &mut Cursor<&mut Vec<u8>>, version: Version) {
-        let num = self.map.len() as CIntShort;
-        num.serialize(cursor, version);
+        serialize_short_len(cursor, self.map.len());
 
         for (key, val) in &self.map {
             serialize_str(cursor, key, version);
@@ -47,10 +61,10 @@ impl Serialize for BodyReqStartup {
 }
 
 impl FromCursor for BodyReqStartup {
-    fn from_cursor(cursor: &mut Cursor<&[u8]>, version: Version) -> error::Result<Self> {
-        let num = CIntShort::from_cursor(cursor, version)?;
+    fn from_cursor(cursor: &mut Cursor<&[u8]>, _version: Version) -> error::Result<Self> {
+        let num = from_cursor_short_len(cursor)?;
 
-        let mut map = HashMap::with_capacity(num as usize);
+        let mut map = HashMap::with_capacity(bounded_capacity(num, cursor));
         for _ in 0..num {
             map.insert(
                 from_cursor_str(cursor)?.to_string(),
@@ -63,13 +77,18 @@ impl FromCursor for BodyReqStartup {
 }
 
 impl Envelope {
-    /// Creates new envelope of type `startup`.
-    pub fn new_req_startup(compression: Option<String>, version: Version) -> Envelope {
+    /// Creates new envelope of type `startup`. Fails if the options do not fit into their protocol
+    /// fields.
+    pub fn new_req_startup(
+        compression: Option<String>,
+        version: Version,
+    ) -> error::Result<Envelope> {
         let direction = Direction::Request;
         let opcode = Opcode::Startup;
         let body = BodyReqStartup::new(compression, version);
+        body.check_lengths()?;
 
-        Envelope::new(
+        Ok(Envelope::new(
             version,
             direction,
             Flags::empty(),
@@ -78,7 +97,7 @@ impl Envelope {
             body.serialize_to_vec(version),
             None,
             vec![],
-        )
+        ))
     }
 }
 
@@ -115,7 +134,7 @@ mod test {
     #[test]
     fn new_req_startup() {
         let compression = Some("test_compression".to_string());
-        let frame = Envelope::new_req_startup(compression, Version::V4);
+        let frame = Envelope::new_req_startup(compression, Version::V4).unwrap();
         assert_eq!(frame.version, Version::V4);
         assert_eq!(frame.flags, Flags::empty());
         assert_eq!(frame.opcode, Opcode::Startup);
@@ -135,4 +154,34 @@ mod test {
         let mut cursor = Cursor::new(bytes.as_slice());
         BodyReqStartup::from_cursor(&mut cursor, Version::V4).unwrap();
     }
+
+    #[test]
+    fn should_check_startup_lengths() {
+        let body = BodyReqStartup::new(None, Version::V5);
+        assert!(body.check_lengths().is_ok());
+
+        let mut body = BodyReqStartup::default();
+        body.map
+            .insert("DRIVER_NAME".into(), "a".repeat(MAX_STRING_LEN + 1));
+        assert!(matches!(
+            body.check_lengths(),
+            Err(crate::Error::ValueTooLarge {
+                what: "startup option",
+                ..
+            })
+        ));
+
+        let body = BodyReqStartup {
+            map: (0..=MAX_SHORT_COUNT)
+                .map(|index| (index.to_string(), String::new()))
+                .collect(),
+        };
+        assert!(matches!(
+            body.check_lengths(),
+            Err(crate::Error::ValueTooLarge {
+                what: "number of startup options",
+                ..
+            })
+        ));
+    }
 }
@@ -1,9 +1,12 @@
 use super::Serialize;
 use crate::error;
 use crate::frame::{FromCursor, Version};
-use crate::types::{from_cursor_str, from_cursor_string_list, serialize_str, CIntShort, SHORT_LEN};
+use crate::types::{
+    bounded_capacity, from_cursor_short_len, from_cursor_str, from_cursor_string_list,
+    serialize_short_len, serialize_str,
+};
 use std::collections::HashMap;
-use std::io::{Cursor, Read};
+use std::io::Cursor;
 
 #[derive(Debug, PartialEq, Eq, Clone, Default)]
 pub struct BodyResSupported {
@@ -12,10 +15,10 @@ pub struct BodyResSupported {
 
 impl Serialize for BodyResSupported {
     fn serialize(&self, cursor: &mut Cursor<&mut Vec<u8>>, version: Version) {
-        (self.data.len() as CIntShort).serialize(cursor, version);
+        serialize_short_len(cursor, self.data.len());
         self.data.iter().for_each(|(key, value)| {
             serialize_str(cursor, key.as_str(), version);
-            (value.len() as CIntShort).serialize(cursor, version);
+            serialize_short_len(cursor, value.len());
             value
                 .iter()
                 .for_each(|s| serialize_str(cursor, s.as_str(), version));
@@ -28,11 +31,9 @@ impl FromCursor for BodyResSupported {
         cursor: &mut Cursor<&[u8]>,
         _version: Version,
     ) -> error::Result<BodyResSupported> {
-        let mut buff = [0; SHORT_LEN];
-        cursor.read_exact(&mut buff)?;
-
-        let l = i16::from_be_bytes(buff) as usize;
-        let mut data: HashMap<String, Vec<String>> = HashMap::with_capacity(l);
+        let l = from_cursor_short_len(cursor)?;
+        let mut data: HashMap<String, Vec<String>> =
+            HashMap::with_capacity(bounded_capacity(l, cursor));
         for _ in 0..l {
             let name = from_cursor_str(cursor)?.to_string();
             let val = from_cursor_string_list(cursor)?;
@@ -35,6 +35,15 @@ pub trait FromCursor {
         Self: Sized;
 }
 
+/// Borrowing counterpart of [`FromCursor`]. Parsed structures reference strings and bytes in the
+/// underlying buffer instead of copying them, which avoids allocations when only a part of a
+/// message needs to be inspected, e.g. in proxies.
+pub trait FromCursorBorrowed<'a>: Sized {
+    /// Tries to parse Self from a cursor of bytes, borrowing from the underlying buffer.
+    fn from_cursor_borrowed(cursor: &mut Cursor<&'a [u8]>, version: Version)
+        -> error::Result<Self>;
+}
+
 /// The trait that allows transformation of `Self` to CDRS query values.
 pub trait IntoQueryValues {
     fn into_query_values(self) -> query::QueryValues;
@@ -42,6 +51,22 @@ pub trait IntoQueryValues {
 
 pub trait TryFromRow: Sized {
     fn try_from_row(row: crate::types::rows::Row) -> error::Result<Self>;
+
+    /// Converts all given rows. Fails with [`Error::RowConversion`](error::Error::RowConversion)
+    /// indicating the index of the first row which couldn't be converted.
+    fn try_from_rows(
+        rows: impl IntoIterator<Item = crate::types::rows::Row>,
+    ) -> error::Result<Vec<Self>> {
+        rows.into_iter()
+            .enumerate()
+            .map(|(index, row)| {
+                Self::try_from_row(row).map_err(|error| error::Error::RowConversion {
+                    index,
+                    error: Box::new(error),
+                })
+            })
+            .collect()
+    }
 }
 
 pub trait TryFromUdt: Sized {
@@ -18,5 +18,7 @@ pub mod error;
 pub mod events;
 pub mod token;
 
+mod stopwatch;
+
 pub type Error = error::Error;
 pub type Result<T> = error::Result<T>;
@@ -1,19 +1,18 @@
 #[macro_export]
 
-/// Transforms arguments to values consumed by queries.
+/// Transforms arguments to values consumed by queries. Accepts anything convertible into
+/// [`Value`](crate::types::value::Value), including borrowed strings, byte slices (as blobs),
+/// references to other values and optional values, with `None` sent as `NULL`.
 macro_rules! query_values {
-    ($($value:expr),*) => {
+    ($($value:expr),* $(,)?) => {
         {
             use cassandra_protocol::types::value::Value;
             use cassandra_protocol::query::QueryValues;
-            let mut values: Vec<Value> = Vec::new();
-            $(
-                values.push($value.into());
-            )*
+            let values: Vec<Value> = vec![$($value.into()),*];
             QueryValues::SimpleValues(values)
         }
     };
-    ($($name:expr => $value:expr),*) => {
+    ($($name:expr => $value:expr),* $(,)?) => {
         {
             use cassandra_protocol::types::value::Value;
             use cassandra_protocol::query::QueryValues;
@@ -921,7 +920,7 @@ macro_rules! as_rust_type {
     ($data_type_option:ident, $data_value:ident, Blob) => {
 
         match $data_type_option.id {
-            ColType::Blob => as_res_opt!($data_value, decode_blob),
+            ColType::Blob | ColType::Other(_) => as_res_opt!($data_value, decode_blob),
             ColType::Custom => {
                 let unmarshal = || {
                     if let Some(crate::frame::message_result::ColTypeOptionValue::CString(value)) = &$data_type_option.value {
@@ -1453,6 +1452,33 @@ macro_rules! as_rust_type {
             ))),
         }
     };
+    ($data_type_option:ident, $data_value:ident, CqlDate) => {
+        match $data_type_option.id {
+            ColType::Date => as_res_opt!($data_value, decode_cql_date),
+            ColType::Custom => {
+                let unmarshal = || {
+                    if let Some(ColTypeOptionValue::CString(value)) = &$data_type_option.value {
+                        if value.as_str() == "org.apache.cassandra.db.marshal.SimpleDateType" {
+                            return as_res_opt!($data_value, decode_cql_date);
+                        }
+                    }
+
+                    Err(crate::error::Error::General(format!(
+                        "Invalid conversion. \
+                         Cannot convert marshalled type {:?} into CqlDate (valid types: org.apache.cassandra.db.marshal.SimpleDateType).",
+                        $data_type_option
+                    )))
+                };
+
+                unmarshal()
+            }
+            _ => Err(crate::error::Error::General(format!(
+                "Invalid conversion. \
+                 Cannot convert {:?} into CqlDate (valid types: Date, Custom).",
+                $data_type_option.id
+            ))),
+        }
+    };
     ($data_type_option:ident, $data_value:ident, Decimal) => {
         match $data_type_option.id {
             ColType::Decimal => match $data_value.as_slice() {
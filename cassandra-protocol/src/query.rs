@@ -1,16 +1,22 @@
 pub mod batch_query_builder;
+pub mod bound_values;
 pub mod prepare_flags;
 pub mod prepared_query;
 pub mod query_flags;
 pub mod query_params;
 pub mod query_params_builder;
 pub mod query_values;
+pub mod query_values_display;
 pub mod utils;
 
-pub use crate::query::batch_query_builder::{BatchQueryBuilder, QueryBatch};
+pub use crate::query::batch_query_builder::{BatchQueryBuilder, QueryBatch, MAX_BATCH_STATEMENTS};
+pub use crate::query::bound_values::convert_bound_values;
 pub use crate::query::prepare_flags::PrepareFlags;
 pub use crate::query::prepared_query::PreparedQuery;
 pub use crate::query::query_flags::QueryFlags;
-pub use crate::query::query_params::QueryParams;
+pub use crate::query::query_params::{QueryParams, QueryParamsBorrowed, QueryValuesBorrowed};
 pub use crate::query::query_params_builder::QueryParamsBuilder;
 pub use crate::query::query_values::QueryValues;
+pub use crate::query::query_values_display::{
+    QueryValuesDisplay, Redactor, DEFAULT_MAX_COLLECTION_ELEMENTS,
+};
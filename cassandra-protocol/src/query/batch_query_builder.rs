@@ -2,10 +2,13 @@ use crate::consistency::Consistency;
 use crate::error::{Error as CError, Result as CResult};
 use crate::frame::message_batch::{BatchQuery, BatchQuerySubj, BatchType, BodyReqBatch};
 use crate::query::{PreparedQuery, QueryValues};
-use crate::types::{CInt, CLong};
+use crate::types::{CInt, CLong, MAX_SHORT_COUNT};
 
 pub type QueryBatch = BodyReqBatch;
 
+/// Maximum number of statements in a batch, as the count is sent as an unsigned short.
+pub const MAX_BATCH_STATEMENTS: usize = MAX_SHORT_COUNT;
+
 #[derive(Debug)]
 pub struct BatchQueryBuilder {
     batch_type: BatchType,
@@ -62,6 +65,37 @@ impl BatchQueryBuilder {
         self
     }
 
+    /// Add queries (non-prepared ones) along with their values.
+    #[must_use]
+    pub fn add_queries<I, Q, V>(mut self, queries: I) -> Self
+    where
+        I: IntoIterator<Item = (Q, V)>,
+        Q: ToString,
+        V: Into<QueryValues>,
+    {
+        self.queries
+            .extend(queries.into_iter().map(|(query, values)| BatchQuery {
+                subject: BatchQuerySubj::QueryString(query.to_string()),
+                values: values.into(),
+            }));
+        self
+    }
+
+    /// Add queries (prepared ones) along with their values.
+    #[must_use]
+    pub fn add_queries_prepared<'a, I, V>(mut self, queries: I) -> Self
+    where
+        I: IntoIterator<Item = (&'a PreparedQuery, V)>,
+        V: Into<QueryValues>,
+    {
+        self.queries
+            .extend(queries.into_iter().map(|(query, values)| BatchQuery {
+                subject: BatchQuerySubj::PreparedId(query.id.clone()),
+                values: values.into(),
+            }));
+        self
+    }
+
     #[must_use]
     pub fn clear_queries(mut self) -> Self {
         self.queries = vec![];
@@ -99,6 +133,14 @@ impl BatchQueryBuilder {
     }
 
     pub fn build(self) -> CResult<BodyReqBatch> {
+        if self.queries.len() > MAX_BATCH_STATEMENTS {
+            return Err(CError::General(format!(
+                "Too many statements in a batch: {}, while the limit is {}",
+                self.queries.len(),
+                MAX_BATCH_STATEMENTS
+            )));
+        }
+
         let with_names_for_values = self.queries.iter().all(|q| q.values.has_names());
 
         if !with_names_for_values {
@@ -123,3 +165,85 @@ impl BatchQueryBuilder {
         })
     }
 }
+
+impl From<Vec<(String, QueryValues)>> for BatchQueryBuilder {
+    fn from(queries: Vec<(String, QueryValues)>) -> Self {
+        BatchQueryBuilder::new().add_queries(queries)
+    }
+}
+
+impl<'a> From<Vec<(&'a PreparedQuery, QueryValues)>> for BatchQueryBuilder {
+    fn from(queries: Vec<(&'a PreparedQuery, QueryValues)>) -> Self {
+        BatchQueryBuilder::new().add_queries_prepared(queries)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::frame::message_batch::BatchQuerySubj;
+    use crate::query::{PreparedQuery, QueryValues};
+    use crate::types::value::Value;
+    use crate::types::CBytesShort;
+
+    use super::{BatchQueryBuilder, MAX_BATCH_STATEMENTS};
+
+    fn prepared() -> PreparedQuery {
+        PreparedQuery::new(
+            CBytesShort::new(vec![1]),
+            "INSERT INTO ks.t (a) VALUES (?)".into(),
+            None,
+            vec![],
+            Default::default(),
+        )
+    }
+
+    #[test]
+    fn should_add_queries_from_iterator() {
+        let prepared = prepared();
+        let batch = BatchQueryBuilder::new()
+            .add_queries((0..3).map(|index| {
+                (
+                    format!("INSERT INTO ks.t (a) VALUES ({index})"),
+                    QueryValues::SimpleValues(vec![]),
+                )
+            }))
+            .add_queries_prepared(vec![(&prepared, vec![Value::new(1)])])
+            .build()
+            .unwrap();
+
+        assert_eq!(batch.queries.len(), 4);
+        assert!(matches!(
+            &batch.queries[2].subject,
+            BatchQuerySubj::QueryString(query) if query == "INSERT INTO ks.t (a) VALUES (2)"
+        ));
+        assert!(matches!(
+            &batch.queries[3].subject,
+            BatchQuerySubj::PreparedId(id) if *id == prepared.id
+        ));
+
+        let batch = BatchQueryBuilder::from(vec![(
+            "TRUNCATE ks.t".to_string(),
+            QueryValues::SimpleValues(vec![]),
+        )])
+        .build()
+        .unwrap();
+        assert_eq!(batch.queries.len(), 1);
+    }
+
+    #[test]
+    fn should_reject_too_many_statements() {
+        let prepared = prepared();
+        let queries =
+            || (0..MAX_BATCH_STATEMENTS).map(|_| (&prepared, QueryValues::SimpleValues(vec![])));
+
+        assert!(BatchQueryBuilder::new()
+            .add_queries_prepared(queries())
+            .build()
+            .is_ok());
+        assert!(BatchQueryBuilder::new()
+            .add_queries_prepared(queries())
+            .add_query_prepared(&prepared, QueryValues::SimpleValues(vec![]))
+            .build()
+            .is_err());
+    }
+}
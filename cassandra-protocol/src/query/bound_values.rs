@@ -0,0 +1,341 @@
+use num_bigint::BigInt;
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::convert::{TryFrom, TryInto};
+
+use crate::error::{Error, Result};
+use crate::frame::message_result::{ColSpec, ColType};
+use crate::query::QueryValues;
+use crate::types::value::Value;
+
+/// Checks bound values against prepared statement metadata and converts numeric values to the
+/// representation of their target column types. Integer values are accepted if they fit the
+/// target type, while `double` values bound to `float` columns are accepted only if they can be
+/// represented exactly. Values which would be truncated or lose precision result in
+/// [`Error::InvalidBoundValue`], as do malformed `decimal` values. Returns borrowed values if no
+/// conversion was necessary.
+pub fn convert_bound_values<'a>(
+    values: &'a QueryValues,
+    col_specs: &[ColSpec],
+) -> Result<Cow<'a, QueryValues>> {
+    let converted = match values {
+        QueryValues::SimpleValues(simple_values) => {
+            let mut converted: Option<Vec<Value>> = None;
+
+            for (index, (value, col_spec)) in simple_values.iter().zip(col_specs).enumerate() {
+                if let Some(bytes) = convert_value(value, col_spec)? {
+                    converted.get_or_insert_with(|| simple_values.clone())[index] =
+                        Value::Some(bytes);
+                }
+            }
+
+            converted.map(QueryValues::SimpleValues)
+        }
+        QueryValues::NamedValues(named_values) => {
+            let mut converted: Option<HashMap<String, Value>> = None;
+
+            for col_spec in col_specs {
+                if let Some(value) = named_values.get(&col_spec.name) {
+                    if let Some(bytes) = convert_value(value, col_spec)? {
+                        converted
+                            .get_or_insert_with(|| named_values.clone())
+                            .insert(col_spec.name.clone(), Value::Some(bytes));
+                    }
+                }
+            }
+
+            converted.map(QueryValues::NamedValues)
+        }
+    };
+
+    Ok(converted.map_or(Cow::Borrowed(values), Cow::Owned))
+}
+
+fn convert_value(value: &Value, col_spec: &ColSpec) -> Result<Option<Vec<u8>>> {
+    let bytes = match value {
+        Value::Some(bytes) if !bytes.is_empty() => bytes,
+        _ => return Ok(None),
+    };
+
+    convert_bytes(bytes, col_spec.col_type.id).map_err(|reason| Error::InvalidBoundValue {
+        name: col_spec.name.clone(),
+        reason,
+    })
+}
+
+fn convert_bytes(bytes: &[u8], col_type: ColType) -> std::result::Result<Option<Vec<u8>>, String> {
+    match col_type {
+        ColType::Tinyint => {
+            convert_integer::<i8>(bytes, col_type, |value| value.to_be_bytes().into())
+        }
+        ColType::Smallint => {
+            convert_integer::<i16>(bytes, col_type, |value| value.to_be_bytes().into())
+        }
+        ColType::Int => convert_integer::<i32>(bytes, col_type, |value| value.to_be_bytes().into()),
+        ColType::Bigint | ColType::Counter => {
+            convert_integer::<i64>(bytes, col_type, |value| value.to_be_bytes().into())
+        }
+        ColType::Float => match bytes.len() {
+            4 => Ok(None),
+            8 => {
+                let value = f64::from_be_bytes(bytes.try_into().unwrap());
+                let narrowed = value as f32;
+
+                if value.is_finite() && f64::from(narrowed) != value {
+                    Err(format!(
+                        "{value} cannot be represented as {col_type} without losing precision"
+                    ))
+                } else {
+                    Ok(Some(narrowed.to_be_bytes().into()))
+                }
+            }
+            len => Err(invalid_length(len, col_type)),
+        },
+        ColType::Double => match bytes.len() {
+            8 => Ok(None),
+            len => Err(invalid_length(len, col_type)),
+        },
+        ColType::Decimal => validate_decimal(bytes).map(|_| None),
+        _ => Ok(None),
+    }
+}
+
+/// Decimals are encoded as a 4 byte scale followed by a minimally encoded two's complement
+/// unscaled value.
+fn validate_decimal(bytes: &[u8]) -> std::result::Result<(), String> {
+    if bytes.len() < 4 {
+        return Err(format!(
+            "{} byte value is missing the 4 byte {} scale",
+            bytes.len(),
+            ColType::Decimal
+        ));
+    }
+
+    match &bytes[4..] {
+        [] => Err(format!(
+            "{} value is missing the unscaled value",
+            ColType::Decimal
+        )),
+        [0x00, next, ..] if *next & 0x80 == 0 => Err(redundant_sign_byte()),
+        [0xff, next, ..] if *next & 0x80 != 0 => Err(redundant_sign_byte()),
+        _ => Ok(()),
+    }
+}
+
+#[inline]
+fn redundant_sign_byte() -> String {
+    format!(
+        "{} unscaled value is not minimally encoded: redundant leading sign byte",
+        ColType::Decimal
+    )
+}
+
+fn convert_integer<T: for<'a> TryFrom<&'a BigInt>>(
+    bytes: &[u8],
+    col_type: ColType,
+    encode: impl FnOnce(T) -> Vec<u8>,
+) -> std::result::Result<Option<Vec<u8>>, String> {
+    if bytes.len() == std::mem::size_of::<T>() {
+        return Ok(None);
+    }
+
+    let value = BigInt::from_signed_bytes_be(bytes);
+    T::try_from(&value)
+        .map(|value| Some(encode(value)))
+        .map_err(|_| format!("{value} is out of range for {col_type}"))
+}
+
+#[inline]
+fn invalid_length(len: usize, col_type: ColType) -> String {
+    format!("{len} byte value cannot be bound to {col_type}")
+}
+
+#[cfg(test)]
+mod tests {
+    use num_bigint::BigInt;
+    use std::borrow::Cow;
+    use std::collections::HashMap;
+
+    use crate::error::Error;
+    use crate::frame::message_result::{ColSpec, ColType, ColTypeOption};
+    use crate::query::bound_values::convert_bound_values;
+    use crate::query::QueryValues;
+    use crate::types::decimal::Decimal;
+    use crate::types::value::{Bytes, Value};
+
+    fn col_spec(name: &str, id: ColType) -> ColSpec {
+        ColSpec {
+            table_spec: None,
+            name: name.into(),
+            col_type: ColTypeOption { id, value: None },
+        }
+    }
+
+    fn convert_single(value: Value, id: ColType) -> Result<Value, Error> {
+        let values = QueryValues::SimpleValues(vec![value]);
+        match convert_bound_values(&values, &[col_spec("column", id)])?.into_owned() {
+            QueryValues::SimpleValues(mut values) => Ok(values.remove(0)),
+            QueryValues::NamedValues(_) => unreachable!(),
+        }
+    }
+
+    fn assert_out_of_range(value: Value, id: ColType) {
+        assert!(matches!(
+            convert_single(value, id),
+            Err(Error::InvalidBoundValue { name, .. }) if name == "column"
+        ));
+    }
+
+    #[test]
+    fn should_not_convert_matching_values() {
+        let values =
+            QueryValues::SimpleValues(vec![1i32.into(), 2i64.into(), Value::Null, Value::NotSet]);
+        let col_specs = [
+            col_spec("a", ColType::Int),
+            col_spec("b", ColType::Bigint),
+            col_spec("c", ColType::Int),
+            col_spec("d", ColType::Int),
+        ];
+
+        assert!(matches!(
+            convert_bound_values(&values, &col_specs).unwrap(),
+            Cow::Borrowed(_)
+        ));
+    }
+
+    #[test]
+    fn should_convert_integers_in_range() {
+        assert_eq!(
+            convert_single(100i64.into(), ColType::Tinyint).unwrap(),
+            100i8.into()
+        );
+        assert_eq!(
+            convert_single((-300i32).into(), ColType::Smallint).unwrap(),
+            (-300i16).into()
+        );
+        assert_eq!(
+            convert_single(i64::from(i32::MIN).into(), ColType::Int).unwrap(),
+            i32::MIN.into()
+        );
+        assert_eq!(
+            convert_single(5i8.into(), ColType::Bigint).unwrap(),
+            5i64.into()
+        );
+        assert_eq!(
+            convert_single(Value::new(Bytes::from(BigInt::from(70000))), ColType::Int).unwrap(),
+            70000i32.into()
+        );
+    }
+
+    #[test]
+    fn should_reject_integers_out_of_range() {
+        assert_out_of_range(128i16.into(), ColType::Tinyint);
+        assert_out_of_range(40000i32.into(), ColType::Smallint);
+        assert_out_of_range((i64::from(i32::MAX) + 1).into(), ColType::Int);
+        assert_out_of_range(
+            Value::new(Bytes::from(BigInt::from(i64::MAX) + 1)),
+            ColType::Bigint,
+        );
+    }
+
+    #[test]
+    fn should_accept_any_integer_as_varint() {
+        assert_eq!(
+            convert_single(i64::MAX.into(), ColType::Varint).unwrap(),
+            i64::MAX.into()
+        );
+        assert_eq!(
+            convert_single(1i8.into(), ColType::Varint).unwrap(),
+            1i8.into()
+        );
+    }
+
+    #[test]
+    fn should_convert_exact_floats() {
+        assert_eq!(
+            convert_single(1.5f64.into(), ColType::Float).unwrap(),
+            1.5f32.into()
+        );
+        assert_eq!(
+            convert_single(f64::INFINITY.into(), ColType::Float).unwrap(),
+            f32::INFINITY.into()
+        );
+        assert_out_of_range(0.1f64.into(), ColType::Float);
+    }
+
+    #[test]
+    fn should_reject_mismatched_doubles() {
+        assert_eq!(
+            convert_single(0.1f64.into(), ColType::Double).unwrap(),
+            0.1f64.into()
+        );
+        assert_out_of_range(1.5f32.into(), ColType::Double);
+    }
+
+    #[test]
+    fn should_validate_decimals() {
+        let decimal: Value = Decimal::new(12345.into(), 2).into();
+        assert_eq!(
+            convert_single(decimal.clone(), ColType::Decimal).unwrap(),
+            decimal
+        );
+        assert_out_of_range(1i32.into(), ColType::Decimal);
+
+        let negative_scale: Value = Decimal::new((-12345).into(), -2).into();
+        assert_eq!(
+            convert_single(negative_scale.clone(), ColType::Decimal).unwrap(),
+            negative_scale
+        );
+    }
+
+    #[test]
+    fn should_reject_malformed_decimals() {
+        // missing scale
+        assert_out_of_range(Value::Some(vec![0u8, 0, 1]), ColType::Decimal);
+        // missing unscaled value
+        assert_out_of_range(Value::Some(vec![0u8, 0, 0, 2]), ColType::Decimal);
+        // redundant leading sign bytes
+        assert_out_of_range(
+            Value::Some(vec![0u8, 0, 0, 2, 0x00, 0x7f]),
+            ColType::Decimal,
+        );
+        assert_out_of_range(
+            Value::Some(vec![0u8, 0, 0, 2, 0xff, 0x80]),
+            ColType::Decimal,
+        );
+
+        // sign bytes which are required
+        for unscaled in [[0x00, 0x80], [0xff, 0x7f]] {
+            let mut bytes = vec![0u8, 0, 0, 2];
+            bytes.extend_from_slice(&unscaled);
+            let value = Value::Some(bytes);
+            assert_eq!(
+                convert_single(value.clone(), ColType::Decimal).unwrap(),
+                value
+            );
+        }
+    }
+
+    #[test]
+    fn should_convert_named_values() {
+        let mut values = HashMap::new();
+        values.insert("a".to_string(), Value::from(1i64));
+        values.insert("b".to_string(), Value::from(i64::MAX));
+
+        let values = QueryValues::NamedValues(values);
+
+        let result = convert_bound_values(&values, &[col_spec("a", ColType::Int)]).unwrap();
+        match result.into_owned() {
+            QueryValues::NamedValues(values) => {
+                assert_eq!(values["a"], 1i32.into());
+                assert_eq!(values["b"], i64::MAX.into());
+            }
+            QueryValues::SimpleValues(_) => unreachable!(),
+        }
+
+        assert!(matches!(
+            convert_bound_values(&values, &[col_spec("b", ColType::Int)]),
+            Err(Error::InvalidBoundValue { name, .. }) if name == "b"
+        ));
+    }
+}
@@ -2,6 +2,7 @@ use arc_swap::ArcSwapOption;
 use std::cmp::Ordering;
 use std::hash::{Hash, Hasher};
 
+use crate::frame::message_result::ColSpec;
 use crate::types::CBytesShort;
 
 #[derive(Debug)]
@@ -11,6 +12,40 @@ pub struct PreparedQuery {
     pub keyspace: Option<String>,
     pub pk_indexes: Vec<i16>,
     pub result_metadata_id: ArcSwapOption<CBytesShort>,
+    col_specs: Vec<ColSpec>,
+}
+
+impl PreparedQuery {
+    pub fn new(
+        id: CBytesShort,
+        query: String,
+        keyspace: Option<String>,
+        pk_indexes: Vec<i16>,
+        result_metadata_id: ArcSwapOption<CBytesShort>,
+    ) -> Self {
+        Self {
+            id,
+            query,
+            keyspace,
+            pk_indexes,
+            result_metadata_id,
+            col_specs: vec![],
+        }
+    }
+
+    /// Sets metadata of bound variables, used to check bound values before sending.
+    #[must_use]
+    pub fn with_col_specs(mut self, col_specs: Vec<ColSpec>) -> Self {
+        self.col_specs = col_specs;
+        self
+    }
+
+    /// Metadata of bound variables, used to check bound values before sending. Empty if the
+    /// metadata is unknown, in which case bound values are sent unchecked.
+    #[inline]
+    pub fn col_specs(&self) -> &[ColSpec] {
+        &self.col_specs
+    }
 }
 
 impl Clone for PreparedQuery {
@@ -21,6 +56,7 @@ impl Clone for PreparedQuery {
             keyspace: self.keyspace.clone(),
             pk_indexes: self.pk_indexes.clone(),
             result_metadata_id: ArcSwapOption::new(self.result_metadata_id.load().clone()),
+            col_specs: self.col_specs.clone(),
         }
     }
 }
@@ -2,10 +2,13 @@ use std::collections::HashMap;
 use std::io::Cursor;
 
 use crate::consistency::Consistency;
-use crate::frame::traits::FromCursor;
+use crate::frame::traits::{FromCursor, FromCursorBorrowed};
 use crate::frame::{Serialize, Version};
 use crate::query::query_flags::QueryFlags;
 use crate::query::query_values::QueryValues;
+use crate::types::value::ValueBorrowed;
+use crate::types::{bounded_capacity, cursor_next_value_ref, from_cursor_bytes_borrowed};
+use crate::types::{check_len, from_cursor_short_len, serialize_short_len, MAX_STRING_LEN};
 use crate::types::{from_cursor_str, serialize_str, value::Value, CInt, CIntShort};
 use crate::types::{CBytes, CLong};
 use crate::Error;
@@ -36,7 +39,7 @@ pub struct QueryParams {
 }
 
 impl QueryParams {
-    fn flags(&self) -> QueryFlags {
+    fn flags(&self, version: Version) -> QueryFlags {
         let mut flags = QueryFlags::empty();
 
         if self.values.is_some() {
@@ -63,36 +66,59 @@ impl QueryParams {
             flags.insert(QueryFlags::WITH_DEFAULT_TIMESTAMP);
         }
 
-        if self.keyspace.is_some() {
-            flags.insert(QueryFlags::WITH_KEYSPACE);
-        }
+        // keyspace and now are not supported before V5
+        if version >= Version::V5 {
+            if self.keyspace.is_some() {
+                flags.insert(QueryFlags::WITH_KEYSPACE);
+            }
 
-        if self.now_in_seconds.is_some() {
-            flags.insert(QueryFlags::WITH_NOW_IN_SECONDS);
+            if self.now_in_seconds.is_some() {
+                flags.insert(QueryFlags::WITH_NOW_IN_SECONDS);
+            }
         }
 
         flags
     }
-}
-
-impl Serialize for QueryParams {
-    fn serialize(&self, cursor: &mut Cursor<&mut Vec<u8>>, version: Version) {
-        let consistency: CIntShort = self.consistency.into();
-        consistency.serialize(cursor, version);
-
-        let flag_bits = self.flags().bits();
-        if version >= Version::V5 {
-            flag_bits.serialize(cursor, version);
-        } else {
-            (flag_bits as u8).serialize(cursor, version);
-        };
 
+    /// Checks if bound values and the keyspace fit into their protocol fields, so the parameters
+    /// can be sent in a request.
+    pub fn check_lengths(&self) -> Result<(), Error> {
         if let Some(values) = &self.values {
-            let len = values.len() as CIntShort;
-            len.serialize(cursor, version);
-            values.serialize(cursor, version);
+            values.check_lengths()?;
+        }
+
+        if let Some(keyspace) = &self.keyspace {
+            check_len("keyspace name", keyspace.len(), MAX_STRING_LEN)?;
         }
 
+        Ok(())
+    }
+
+    /// Checks if the parameters can be sent with given protocol version. The per-statement
+    /// keyspace and current time are only supported since V5.
+    pub fn check_version(&self, version: Version) -> Result<(), Error> {
+        check_v5_fields(
+            self.keyspace.is_some(),
+            self.now_in_seconds.is_some(),
+            version,
+        )
+    }
+
+    /// Returns the length of parameters serialized after bound values. Since parameters are
+    /// always serialized at the end of request bodies, this allows locating values in serialized
+    /// requests.
+    pub fn trailing_len(&self, version: Version) -> usize {
+        let mut buffer = vec![];
+        self.serialize_trailing(self.flags(version), &mut Cursor::new(&mut buffer), version);
+        buffer.len()
+    }
+
+    fn serialize_trailing(
+        &self,
+        flags: QueryFlags,
+        cursor: &mut Cursor<&mut Vec<u8>>,
+        version: Version,
+    ) {
         if let Some(page_size) = self.page_size {
             page_size.serialize(cursor, version);
         }
@@ -110,26 +136,75 @@ impl Serialize for QueryParams {
             timestamp.serialize(cursor, version);
         }
 
-        if let Some(keyspace) = &self.keyspace {
-            serialize_str(cursor, keyspace.as_str(), version);
+        if flags.contains(QueryFlags::WITH_KEYSPACE) {
+            if let Some(keyspace) = &self.keyspace {
+                serialize_str(cursor, keyspace.as_str(), version);
+            }
         }
 
-        if let Some(now_in_seconds) = self.now_in_seconds {
-            now_in_seconds.serialize(cursor, version);
+        if flags.contains(QueryFlags::WITH_NOW_IN_SECONDS) {
+            if let Some(now_in_seconds) = self.now_in_seconds {
+                now_in_seconds.serialize(cursor, version);
+            }
         }
     }
 }
 
+// the fields would otherwise need to be dropped, silently changing how the statement is executed
+pub(crate) fn check_v5_fields(
+    keyspace: bool,
+    now_in_seconds: bool,
+    version: Version,
+) -> Result<(), Error> {
+    if version >= Version::V5 {
+        return Ok(());
+    }
+
+    if keyspace {
+        return Err(Error::UnsupportedByVersion {
+            what: "Per-statement keyspace",
+            version,
+        });
+    }
+
+    if now_in_seconds {
+        return Err(Error::UnsupportedByVersion {
+            what: "Per-statement current time",
+            version,
+        });
+    }
+
+    Ok(())
+}
+
+impl Serialize for QueryParams {
+    fn serialize(&self, cursor: &mut Cursor<&mut Vec<u8>>, version: Version) {
+        let consistency: CIntShort = self.consistency.into();
+        consistency.serialize(cursor, version);
+
+        let flags = self.flags(version);
+        flags.serialize(cursor, version);
+
+        if let Some(values) = &self.values {
+            serialize_short_len(cursor, values.len());
+            values.serialize(cursor, version);
+        }
+
+        self.serialize_trailing(flags, cursor, version);
+    }
+}
+
 impl FromCursor for QueryParams {
     fn from_cursor(cursor: &mut Cursor<&[u8]>, version: Version) -> Result<QueryParams, Error> {
         let consistency = Consistency::from_cursor(cursor, version)?;
         let flags = QueryFlags::from_cursor(cursor, version)?;
 
         let values = if flags.contains(QueryFlags::VALUE) {
-            let number_of_values = CIntShort::from_cursor(cursor, version)?;
+            let number_of_values = from_cursor_short_len(cursor)?;
 
             if flags.contains(QueryFlags::WITH_NAMES_FOR_VALUES) {
-                let mut map = HashMap::with_capacity(number_of_values as usize);
+                let mut map =
+                    HashMap::with_capacity(bounded_capacity(number_of_values as usize, cursor));
                 for _ in 0..number_of_values {
                     map.insert(
                         from_cursor_str(cursor)?.to_string(),
@@ -138,7 +213,8 @@ impl FromCursor for QueryParams {
                 }
                 Some(QueryValues::NamedValues(map))
             } else {
-                let mut vec = Vec::with_capacity(number_of_values as usize);
+                let mut vec =
+                    Vec::with_capacity(bounded_capacity(number_of_values as usize, cursor));
                 for _ in 0..number_of_values {
                     vec.push(Value::from_cursor(cursor, version)?);
                 }
@@ -199,3 +275,166 @@ impl FromCursor for QueryParams {
         })
     }
 }
+
+/// Borrowed counterpart of [`QueryParams`], referencing strings and bytes in the parsed buffer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct QueryParamsBorrowed<'a> {
+    /// Cassandra consistency level.
+    pub consistency: Consistency,
+    /// Values, which are parsed lazily.
+    pub values: Option<QueryValuesBorrowed<'a>>,
+    /// Page size.
+    pub page_size: Option<CInt>,
+    /// Paging state. Null paging state is represented as `None`.
+    pub paging_state: Option<&'a [u8]>,
+    /// Serial `Consistency`.
+    pub serial_consistency: Option<Consistency>,
+    /// Timestamp.
+    pub timestamp: Option<CLong>,
+    /// Keyspace indicating the keyspace that the query should be executed in.
+    pub keyspace: Option<&'a str>,
+    /// Represents the current time (now) for the query.
+    pub now_in_seconds: Option<CInt>,
+}
+
+impl<'a> FromCursorBorrowed<'a> for QueryParamsBorrowed<'a> {
+    fn from_cursor_borrowed(
+        cursor: &mut Cursor<&'a [u8]>,
+        version: Version,
+    ) -> Result<Self, Error> {
+        let consistency = Consistency::from_cursor(cursor, version)?;
+        let flags = QueryFlags::from_cursor(cursor, version)?;
+
+        let values = if flags.contains(QueryFlags::VALUE) {
+            Some(QueryValuesBorrowed::from_cursor(
+                cursor,
+                flags.contains(QueryFlags::WITH_NAMES_FOR_VALUES),
+                version,
+            )?)
+        } else {
+            None
+        };
+
+        let page_size = if flags.contains(QueryFlags::PAGE_SIZE) {
+            Some(CInt::from_cursor(cursor, version)?)
+        } else {
+            None
+        };
+
+        let paging_state = if flags.contains(QueryFlags::WITH_PAGING_STATE) {
+            from_cursor_bytes_borrowed(cursor)?
+        } else {
+            None
+        };
+
+        let serial_consistency = if flags.contains(QueryFlags::WITH_SERIAL_CONSISTENCY) {
+            Some(Consistency::from_cursor(cursor, version)?)
+        } else {
+            None
+        };
+
+        let timestamp = if flags.contains(QueryFlags::WITH_DEFAULT_TIMESTAMP) {
+            Some(CLong::from_cursor(cursor, version)?)
+        } else {
+            None
+        };
+
+        let keyspace = if flags.contains(QueryFlags::WITH_KEYSPACE) {
+            Some(from_cursor_str(cursor)?)
+        } else {
+            None
+        };
+
+        let now_in_seconds = if flags.contains(QueryFlags::WITH_NOW_IN_SECONDS) {
+            Some(CInt::from_cursor(cursor, version)?)
+        } else {
+            None
+        };
+
+        Ok(QueryParamsBorrowed {
+            consistency,
+            values,
+            page_size,
+            paging_state,
+            serial_consistency,
+            timestamp,
+            keyspace,
+            now_in_seconds,
+        })
+    }
+}
+
+/// Values of a query, kept as raw bytes and parsed on iteration.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct QueryValuesBorrowed<'a> {
+    with_names: bool,
+    len: usize,
+    data: &'a [u8],
+    version: Version,
+}
+
+impl<'a> QueryValuesBorrowed<'a> {
+    // walks the values once, so iteration can't run past them
+    fn from_cursor(
+        cursor: &mut Cursor<&'a [u8]>,
+        with_names: bool,
+        version: Version,
+    ) -> Result<Self, Error> {
+        let len = from_cursor_short_len(cursor)?;
+        let start = cursor.position() as usize;
+
+        for _ in 0..len {
+            Self::next_value(cursor, with_names, version)?;
+        }
+
+        let end = cursor.position() as usize;
+        cursor.set_position(start as u64);
+
+        Ok(QueryValuesBorrowed {
+            with_names,
+            len,
+            data: cursor_next_value_ref(cursor, end - start)?,
+            version,
+        })
+    }
+
+    fn next_value(
+        cursor: &mut Cursor<&'a [u8]>,
+        with_names: bool,
+        version: Version,
+    ) -> Result<(Option<&'a str>, ValueBorrowed<'a>), Error> {
+        let name = if with_names {
+            Some(from_cursor_str(cursor)?)
+        } else {
+            None
+        };
+
+        Ok((name, ValueBorrowed::from_cursor_borrowed(cursor, version)?))
+    }
+
+    /// Are values named.
+    #[inline]
+    pub fn with_names(&self) -> bool {
+        self.with_names
+    }
+
+    /// Number of values.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Returns values along with their names, if values are named.
+    pub fn iter(&self) -> impl Iterator<Item = (Option<&'a str>, ValueBorrowed<'a>)> + '_ {
+        let mut cursor = Cursor::new(self.data);
+        (0..self.len).map(move |_| {
+            Self::next_value(&mut cursor, self.with_names, self.version)
+                .expect("Values have been validated when parsing")
+        })
+    }
+}
@@ -1,15 +1,22 @@
 use itertools::Itertools;
 use std::collections::HashMap;
+use std::fmt::{self, Debug, Display, Formatter};
 use std::io::Cursor;
 
+use crate::error;
+use crate::frame::message_result::ColSpec;
 use crate::frame::{Serialize, Version};
-use crate::types::serialize_str;
+use crate::query::{QueryValuesDisplay, Redactor};
 use crate::types::value::Value;
+use crate::types::{check_len, serialize_str, MAX_SHORT_COUNT, MAX_STRING_LEN};
 
 /// Enum that represents two types of query values:
 /// * values without name
 /// * values with names
-#[derive(Debug, Clone, PartialEq, Eq)]
+///
+/// Values are displayed without their contents, since their types are unknown. Use
+/// [`QueryValues::display`] to display them along with prepared statement metadata.
+#[derive(Clone, PartialEq, Eq)]
 pub enum QueryValues {
     SimpleValues(Vec<Value>),
     NamedValues(HashMap<String, Value>),
@@ -34,6 +41,43 @@ impl QueryValues {
     pub fn is_empty(&self) -> bool {
         self.len() == 0
     }
+
+    /// Checks if the number of values and their names fit into their protocol fields, so the
+    /// values can be sent in a request.
+    pub fn check_lengths(&self) -> error::Result<()> {
+        check_len("number of bound values", self.len(), MAX_SHORT_COUNT)?;
+
+        if let QueryValues::NamedValues(values) = self {
+            for name in values.keys() {
+                check_len("bound value name", name.len(), MAX_STRING_LEN)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Displays values as CQL-like literals, decoded using types from given prepared statement
+    /// metadata and hiding values selected by the redactor.
+    #[inline]
+    pub fn display<'a>(
+        &'a self,
+        col_specs: Option<&'a [ColSpec]>,
+        redactor: Option<&'a Redactor>,
+    ) -> QueryValuesDisplay<'a> {
+        QueryValuesDisplay::new(self, col_specs, redactor)
+    }
+}
+
+impl Display for QueryValues {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        Display::fmt(&self.display(None, None), f)
+    }
+}
+
+impl Debug for QueryValues {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        Display::fmt(self, f)
+    }
 }
 
 impl<T: Into<Value>> From<Vec<T>> for QueryValues {
@@ -81,3 +125,44 @@ impl Serialize for QueryValues {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use crate::error::Error;
+    use crate::query::QueryValues;
+    use crate::types::value::Value;
+    use crate::types::{MAX_SHORT_COUNT, MAX_STRING_LEN};
+
+    #[test]
+    fn should_check_number_of_values() {
+        let values = QueryValues::SimpleValues(vec![Value::Null; MAX_SHORT_COUNT]);
+        assert!(values.check_lengths().is_ok());
+
+        let values = QueryValues::SimpleValues(vec![Value::Null; MAX_SHORT_COUNT + 1]);
+        assert!(matches!(
+            values.check_lengths(),
+            Err(Error::ValueTooLarge {
+                what: "number of bound values",
+                max: MAX_SHORT_COUNT,
+                actual,
+            }) if actual == MAX_SHORT_COUNT + 1
+        ));
+    }
+
+    #[test]
+    fn should_check_value_names() {
+        let mut values = HashMap::new();
+        values.insert("a".repeat(MAX_STRING_LEN + 1), Value::Null);
+
+        assert!(matches!(
+            QueryValues::NamedValues(values).check_lengths(),
+            Err(Error::ValueTooLarge {
+                what: "bound value name",
+                max: MAX_STRING_LEN,
+                ..
+            })
+        ));
+    }
+}
@@ -0,0 +1,433 @@
+use std::collections::HashSet;
+use std::fmt::{self, Display, Formatter};
+
+use crate::frame::message_result::{ColSpec, ColType, ColTypeOption, ColTypeOptionValue};
+use crate::frame::Version;
+use crate::query::QueryValues;
+use crate::types::data_serialization_types::*;
+use crate::types::value::Value;
+use crate::types::CBytes;
+
+/// Default number of displayed collection elements.
+pub const DEFAULT_MAX_COLLECTION_ELEMENTS: usize = 10;
+
+const REDACTED: &str = "<redacted>";
+
+// collections are encoded the same way in all supported protocol versions
+const COLLECTION_VERSION: Version = Version::V4;
+
+/// Decides which bound values are hidden when query values are displayed, e.g. when logging
+/// failed statements. Values can be redacted by parameter name, which is case-insensitive, or by
+/// index. Named values only have an index when displayed along with prepared statement metadata.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Redactor {
+    names: HashSet<String>,
+    indexes: HashSet<usize>,
+    max_collection_elements: usize,
+}
+
+impl Default for Redactor {
+    fn default() -> Self {
+        Redactor {
+            names: Default::default(),
+            indexes: Default::default(),
+            max_collection_elements: DEFAULT_MAX_COLLECTION_ELEMENTS,
+        }
+    }
+}
+
+impl Redactor {
+    /// Redacts values bound to parameters with given name.
+    #[must_use]
+    pub fn with_name(mut self, name: impl Into<String>) -> Self {
+        self.names.insert(name.into().to_lowercase());
+        self
+    }
+
+    /// Redacts values bound to parameters with given index.
+    #[must_use]
+    pub fn with_index(mut self, index: usize) -> Self {
+        self.indexes.insert(index);
+        self
+    }
+
+    /// Sets how many elements of collections are displayed before the rest is skipped.
+    #[must_use]
+    pub fn with_max_collection_elements(mut self, max_collection_elements: usize) -> Self {
+        self.max_collection_elements = max_collection_elements;
+        self
+    }
+
+    /// Checks if a value bound to given parameter should be redacted.
+    pub fn is_redacted(&self, name: Option<&str>, index: Option<usize>) -> bool {
+        index.is_some_and(|index| self.indexes.contains(&index))
+            || name.is_some_and(|name| self.names.contains(&name.to_lowercase()))
+    }
+}
+
+/// Displays query values as CQL-like literals, hiding values selected by a [`Redactor`]. Values
+/// can only be decoded when their types are known from prepared statement metadata - otherwise
+/// they are displayed as their length in bytes, the same as blobs.
+#[derive(Clone, Copy, Debug)]
+pub struct QueryValuesDisplay<'a> {
+    values: &'a QueryValues,
+    col_specs: Option<&'a [ColSpec]>,
+    redactor: Option<&'a Redactor>,
+}
+
+impl<'a> QueryValuesDisplay<'a> {
+    pub fn new(
+        values: &'a QueryValues,
+        col_specs: Option<&'a [ColSpec]>,
+        redactor: Option<&'a Redactor>,
+    ) -> Self {
+        QueryValuesDisplay {
+            values,
+            col_specs,
+            redactor,
+        }
+    }
+
+    fn max_collection_elements(&self) -> usize {
+        self.redactor
+            .map(|redactor| redactor.max_collection_elements)
+            .unwrap_or(DEFAULT_MAX_COLLECTION_ELEMENTS)
+    }
+
+    fn fmt_param(
+        &self,
+        f: &mut Formatter<'_>,
+        value: &Value,
+        name: Option<&str>,
+        index: Option<usize>,
+        col_type: Option<&ColTypeOption>,
+    ) -> fmt::Result {
+        if self
+            .redactor
+            .is_some_and(|redactor| redactor.is_redacted(name, index))
+        {
+            return f.write_str(REDACTED);
+        }
+
+        match value {
+            Value::Some(bytes) => fmt_bytes(f, bytes, col_type, self.max_collection_elements()),
+            Value::Null => f.write_str("null"),
+            Value::NotSet => f.write_str("unset"),
+        }
+    }
+}
+
+impl Display for QueryValuesDisplay<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self.values {
+            QueryValues::SimpleValues(values) => {
+                f.write_str("[")?;
+
+                for (index, value) in values.iter().enumerate() {
+                    if index > 0 {
+                        f.write_str(", ")?;
+                    }
+
+                    let col_spec = self.col_specs.and_then(|col_specs| col_specs.get(index));
+                    self.fmt_param(
+                        f,
+                        value,
+                        col_spec.map(|col_spec| col_spec.name.as_str()),
+                        Some(index),
+                        col_spec.map(|col_spec| &col_spec.col_type),
+                    )?;
+                }
+
+                f.write_str("]")
+            }
+            QueryValues::NamedValues(values) => {
+                // sorted for stable output
+                let mut names: Vec<_> = values.keys().collect();
+                names.sort_unstable();
+
+                f.write_str("{")?;
+
+                for (position, name) in names.into_iter().enumerate() {
+                    if position > 0 {
+                        f.write_str(", ")?;
+                    }
+
+                    let col_spec = self.col_specs.and_then(|col_specs| {
+                        col_specs
+                            .iter()
+                            .enumerate()
+                            .find(|(_, col_spec)| col_spec.name.eq_ignore_ascii_case(name))
+                    });
+
+                    write!(f, "{name}: ")?;
+                    self.fmt_param(
+                        f,
+                        &values[name],
+                        Some(name),
+                        col_spec.map(|(index, _)| index),
+                        col_spec.map(|(_, col_spec)| &col_spec.col_type),
+                    )?;
+                }
+
+                f.write_str("}")
+            }
+        }
+    }
+}
+
+fn fmt_blob(f: &mut Formatter<'_>, bytes: &[u8]) -> fmt::Result {
+    write!(f, "0x<{} bytes>", bytes.len())
+}
+
+fn fmt_element(
+    f: &mut Formatter<'_>,
+    bytes: &CBytes,
+    col_type: Option<&ColTypeOption>,
+    max_elements: usize,
+) -> fmt::Result {
+    match bytes.as_slice() {
+        Some(bytes) => fmt_bytes(f, bytes, col_type, max_elements),
+        None => f.write_str("null"),
+    }
+}
+
+fn fmt_elements<T>(
+    f: &mut Formatter<'_>,
+    elements: &[T],
+    max_elements: usize,
+    mut fmt: impl FnMut(&mut Formatter<'_>, &T) -> fmt::Result,
+) -> fmt::Result {
+    for (index, element) in elements.iter().take(max_elements).enumerate() {
+        if index > 0 {
+            f.write_str(", ")?;
+        }
+
+        fmt(f, element)?;
+    }
+
+    if elements.len() > max_elements {
+        if max_elements > 0 {
+            f.write_str(", ")?;
+        }
+
+        write!(f, "...{} more", elements.len() - max_elements)?;
+    }
+
+    Ok(())
+}
+
+fn fmt_bytes(
+    f: &mut Formatter<'_>,
+    bytes: &[u8],
+    col_type: Option<&ColTypeOption>,
+    max_elements: usize,
+) -> fmt::Result {
+    let col_type = match col_type {
+        Some(col_type) => col_type,
+        None => return fmt_blob(f, bytes),
+    };
+
+    let result = match col_type.id {
+        ColType::Ascii | ColType::Varchar => decode_varchar(bytes)
+            .ok()
+            .map(|value| write!(f, "'{}'", value.replace('\'', "''"))),
+        ColType::Bigint | ColType::Counter | ColType::Timestamp | ColType::Time => {
+            decode_bigint(bytes).ok().map(|value| write!(f, "{value}"))
+        }
+        ColType::Int => decode_int(bytes).ok().map(|value| write!(f, "{value}")),
+        ColType::Smallint => decode_smallint(bytes)
+            .ok()
+            .map(|value| write!(f, "{value}")),
+        ColType::Tinyint => decode_tinyint(bytes).ok().map(|value| write!(f, "{value}")),
+        ColType::Boolean => decode_boolean(bytes).ok().map(|value| write!(f, "{value}")),
+        ColType::Double => decode_double(bytes).ok().map(|value| write!(f, "{value}")),
+        ColType::Float => decode_float(bytes).ok().map(|value| write!(f, "{value}")),
+        ColType::Varint => decode_varint(bytes).ok().map(|value| write!(f, "{value}")),
+        ColType::Decimal => decode_decimal(bytes)
+            .ok()
+            .map(|value| write!(f, "{}E{}", value.unscaled, -value.scale)),
+        ColType::Uuid | ColType::Timeuuid => decode_timeuuid(bytes)
+            .ok()
+            .map(|value| write!(f, "{value}")),
+        ColType::Inet => decode_inet(bytes).ok().map(|value| write!(f, "'{value}'")),
+        ColType::Date => decode_cql_date(bytes)
+            .ok()
+            .map(|value| write!(f, "'{value}'")),
+        ColType::Duration => decode_duration(bytes).ok().map(|value| {
+            write!(
+                f,
+                "{}mo{}d{}ns",
+                value.months(),
+                value.days(),
+                value.nanoseconds()
+            )
+        }),
+        ColType::List | ColType::Set => {
+            let element_type = match &col_type.value {
+                Some(ColTypeOptionValue::CList(element_type))
+                | Some(ColTypeOptionValue::CSet(element_type)) => Some(element_type.as_ref()),
+                _ => None,
+            };
+            let (open, close) = if col_type.id == ColType::List {
+                ("[", "]")
+            } else {
+                ("{", "}")
+            };
+
+            decode_list(bytes, COLLECTION_VERSION).ok().map(|elements| {
+                f.write_str(open)?;
+                fmt_elements(f, &elements, max_elements, |f, element| {
+                    fmt_element(f, element, element_type, max_elements)
+                })?;
+                f.write_str(close)
+            })
+        }
+        ColType::Map => {
+            let (key_type, value_type) = match &col_type.value {
+                Some(ColTypeOptionValue::CMap(key_type, value_type)) => {
+                    (Some(key_type.as_ref()), Some(value_type.as_ref()))
+                }
+                _ => (None, None),
+            };
+
+            decode_map(bytes, COLLECTION_VERSION).ok().map(|entries| {
+                f.write_str("{")?;
+                fmt_elements(f, &entries, max_elements, |f, (key, value)| {
+                    fmt_element(f, key, key_type, max_elements)?;
+                    f.write_str(": ")?;
+                    fmt_element(f, value, value_type, max_elements)
+                })?;
+                f.write_str("}")
+            })
+        }
+        ColType::Tuple => match &col_type.value {
+            Some(ColTypeOptionValue::TupleType(tuple)) => {
+                decode_tuple(bytes, tuple.types.len(), COLLECTION_VERSION)
+                    .ok()
+                    .map(|elements| {
+                        f.write_str("(")?;
+                        fmt_elements(
+                            f,
+                            &elements.iter().zip(&tuple.types).collect::<Vec<_>>(),
+                            usize::MAX,
+                            |f, (element, element_type)| {
+                                fmt_element(f, element, Some(element_type), max_elements)
+                            },
+                        )?;
+                        f.write_str(")")
+                    })
+            }
+            _ => None,
+        },
+        ColType::Udt => match &col_type.value {
+            Some(ColTypeOptionValue::UdtType(udt)) => {
+                decode_udt(bytes, udt.descriptions.len(), COLLECTION_VERSION)
+                    .ok()
+                    .map(|fields| {
+                        f.write_str("{")?;
+                        fmt_elements(
+                            f,
+                            &fields.iter().zip(&udt.descriptions).collect::<Vec<_>>(),
+                            usize::MAX,
+                            |f, (field, (name, field_type))| {
+                                write!(f, "{name}: ")?;
+                                fmt_element(f, field, Some(field_type), max_elements)
+                            },
+                        )?;
+                        f.write_str("}")
+                    })
+            }
+            _ => None,
+        },
+        ColType::Blob | ColType::Custom | ColType::Other(_) => None,
+    };
+
+    // undecodable values are displayed as blobs
+    result.unwrap_or_else(|| fmt_blob(f, bytes))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+    use crate::types::value::Bytes;
+
+    fn col_spec(name: &str, id: ColType, value: Option<ColTypeOptionValue>) -> ColSpec {
+        ColSpec {
+            table_spec: None,
+            name: name.into(),
+            col_type: ColTypeOption { id, value },
+        }
+    }
+
+    #[test]
+    fn should_display_untyped_values_as_blobs() {
+        let values = QueryValues::SimpleValues(vec![Value::new(1i32), Value::Null, Value::NotSet]);
+
+        assert_eq!(
+            QueryValuesDisplay::new(&values, None, None).to_string(),
+            "[0x<4 bytes>, null, unset]"
+        );
+    }
+
+    #[test]
+    fn should_display_typed_values() {
+        let values = QueryValues::SimpleValues(vec![
+            Value::new("it's"),
+            Value::new(5i64),
+            Value::Some(vec![1, 2, 3]),
+            Value::new(vec![1i32, 2, 3]),
+        ]);
+        let col_specs = [
+            col_spec("name", ColType::Varchar, None),
+            col_spec("id", ColType::Bigint, None),
+            col_spec("data", ColType::Blob, None),
+            col_spec(
+                "list",
+                ColType::List,
+                Some(ColTypeOptionValue::CList(Box::new(ColTypeOption {
+                    id: ColType::Int,
+                    value: None,
+                }))),
+            ),
+        ];
+        let redactor = Redactor::default().with_max_collection_elements(2);
+
+        assert_eq!(
+            QueryValuesDisplay::new(&values, Some(&col_specs), Some(&redactor)).to_string(),
+            "['it''s', 5, 0x<3 bytes>, [1, 2, ...1 more]]"
+        );
+    }
+
+    #[test]
+    fn should_redact_values() {
+        let values = QueryValues::SimpleValues(vec![Value::new("user"), Value::new("secret")]);
+        let col_specs = [
+            col_spec("login", ColType::Varchar, None),
+            col_spec("password", ColType::Varchar, None),
+        ];
+
+        let redactor = Redactor::default().with_name("Password");
+        assert_eq!(
+            QueryValuesDisplay::new(&values, Some(&col_specs), Some(&redactor)).to_string(),
+            "['user', <redacted>]"
+        );
+
+        let redactor = Redactor::default().with_index(0);
+        assert_eq!(
+            QueryValuesDisplay::new(&values, None, Some(&redactor)).to_string(),
+            "[<redacted>, 0x<6 bytes>]"
+        );
+
+        let values = QueryValues::NamedValues(HashMap::from([
+            ("password".to_string(), Value::new("secret")),
+            ("login".to_string(), Value::new(Bytes::new(vec![]))),
+        ]));
+        let redactor = Redactor::default().with_name("password");
+        assert_eq!(
+            QueryValuesDisplay::new(&values, None, Some(&redactor)).to_string(),
+            "{login: 0x<0 bytes>, password: <redacted>}"
+        );
+    }
+}
@@ -1,5 +1,499 @@
+use std::fmt::{self, Write};
+
+use crate::types::cassandra_type::CassandraType;
+use crate::types::date::CqlDate;
+
+// keywords which can't be used as unquoted identifiers
+// https://cassandra.apache.org/doc/latest/cassandra/developing/cql/appendices.html#appendix-A
+const RESERVED_KEYWORDS: &[&str] = &[
+    "add",
+    "allow",
+    "alter",
+    "and",
+    "apply",
+    "asc",
+    "authorize",
+    "batch",
+    "begin",
+    "by",
+    "columnfamily",
+    "create",
+    "default",
+    "delete",
+    "desc",
+    "describe",
+    "drop",
+    "entries",
+    "execute",
+    "from",
+    "full",
+    "grant",
+    "if",
+    "in",
+    "index",
+    "infinity",
+    "insert",
+    "into",
+    "is",
+    "keyspace",
+    "limit",
+    "materialized",
+    "mbean",
+    "mbeans",
+    "modify",
+    "nan",
+    "norecursive",
+    "not",
+    "null",
+    "of",
+    "on",
+    "or",
+    "order",
+    "primary",
+    "rename",
+    "replace",
+    "revoke",
+    "schema",
+    "select",
+    "set",
+    "table",
+    "to",
+    "token",
+    "truncate",
+    "unlogged",
+    "unset",
+    "update",
+    "use",
+    "using",
+    "view",
+    "where",
+    "with",
+];
+
 /// Returns the identifier in a format appropriate for concatenation in a CQL query.
 #[inline]
 pub fn quote(text: &str) -> String {
     format!("\"{}\"", text.replace('"', "\"\""))
 }
+
+/// Returns the identifier in a format appropriate for concatenation in a CQL query, quoted only
+/// when needed, i.e. when it isn't a lowercase identifier or is a reserved keyword. Unlike
+/// [`quote`], simple names are left as they are, e.g. for readable DDL.
+pub fn quote_identifier(identifier: &str) -> String {
+    let is_plain = identifier
+        .chars()
+        .next()
+        .is_some_and(|c| c.is_ascii_lowercase())
+        && identifier
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_')
+        && !RESERVED_KEYWORDS.contains(&identifier);
+
+    if is_plain {
+        identifier.to_string()
+    } else {
+        quote(identifier)
+    }
+}
+
+/// Returns the text as a CQL string literal. CQL has no escape sequences, so apart from doubling
+/// single quotes, the text is kept verbatim, including control characters.
+pub fn quote_string_literal(text: &str) -> String {
+    format!("'{}'", text.replace('\'', "''"))
+}
+
+/// Formats the value as a CQL literal, which can be concatenated in a query in place of a bind
+/// marker of the same type. UDT fields are sorted by name, since their order is not known.
+pub fn format_cql_value(value: &CassandraType) -> String {
+    let mut literal = String::new();
+    // writing to a string can't fail
+    let _ = write_cql_value(&mut literal, value);
+    literal
+}
+
+fn write_separated<T>(
+    literal: &mut String,
+    open: char,
+    close: char,
+    elements: impl IntoIterator<Item = T>,
+    mut write_element: impl FnMut(&mut String, T) -> fmt::Result,
+) -> fmt::Result {
+    literal.push(open);
+
+    for (index, element) in elements.into_iter().enumerate() {
+        if index > 0 {
+            literal.push_str(", ");
+        }
+
+        write_element(literal, element)?;
+    }
+
+    literal.push(close);
+    Ok(())
+}
+
+fn write_float(literal: &mut String, value: f64) -> fmt::Result {
+    if value.is_nan() {
+        literal.write_str("NaN")
+    } else if value.is_infinite() {
+        literal.write_str(if value > 0.0 { "Infinity" } else { "-Infinity" })
+    } else {
+        write!(literal, "{value:?}")
+    }
+}
+
+fn write_cql_value(literal: &mut String, value: &CassandraType) -> fmt::Result {
+    match value {
+        CassandraType::Ascii(value) | CassandraType::Varchar(value) => {
+            literal.write_str(&quote_string_literal(value))
+        }
+        CassandraType::Bigint(value)
+        | CassandraType::Counter(value)
+        | CassandraType::Timestamp(value)
+        | CassandraType::Time(value) => write!(literal, "{value}"),
+        CassandraType::Int(value) => write!(literal, "{value}"),
+        CassandraType::Smallint(value) => write!(literal, "{value}"),
+        CassandraType::Tinyint(value) => write!(literal, "{value}"),
+        CassandraType::Varint(value) => write!(literal, "{value}"),
+        CassandraType::Boolean(value) => write!(literal, "{value}"),
+        CassandraType::Double(value) => write_float(literal, *value),
+        CassandraType::Float(value) => write_float(literal, (*value).into()),
+        CassandraType::Decimal(value) => {
+            write!(literal, "{}E{}", value.unscaled, -i64::from(value.scale))
+        }
+        CassandraType::Uuid(value) | CassandraType::Timeuuid(value) => write!(literal, "{value}"),
+        CassandraType::Inet(value) => write!(literal, "'{value}'"),
+        CassandraType::Date(value) => {
+            let date = CqlDate::from_raw(*value as u32);
+            match date.to_ymd() {
+                (0..=9999, _, _) => write!(literal, "'{date}'"),
+                // dates outside of the ISO format are given as raw days
+                _ => write!(literal, "'{}'", date.raw()),
+            }
+        }
+        CassandraType::Duration(value) => {
+            let sign = if value.months() < 0 || value.days() < 0 || value.nanoseconds() < 0 {
+                "-"
+            } else {
+                ""
+            };
+
+            write!(
+                literal,
+                "{}{}mo{}d{}ns",
+                sign,
+                value.months().unsigned_abs(),
+                value.days().unsigned_abs(),
+                value.nanoseconds().unsigned_abs()
+            )
+        }
+        CassandraType::Blob(value) => {
+            literal.write_str("0x")?;
+            value
+                .as_slice()
+                .iter()
+                .try_for_each(|byte| write!(literal, "{byte:02x}"))
+        }
+        CassandraType::List(values) | CassandraType::Vector(values) => {
+            write_separated(literal, '[', ']', values, write_cql_value)
+        }
+        CassandraType::Set(values) => write_separated(literal, '{', '}', values, write_cql_value),
+        CassandraType::Tuple(values) => write_separated(literal, '(', ')', values, write_cql_value),
+        CassandraType::Map(entries) => {
+            write_separated(literal, '{', '}', entries, |literal, (key, value)| {
+                write_cql_value(literal, key)?;
+                literal.write_str(": ")?;
+                write_cql_value(literal, value)
+            })
+        }
+        CassandraType::Udt(fields) => {
+            let mut fields: Vec<_> = fields.iter().collect();
+            fields.sort_unstable_by_key(|(name, _)| *name);
+
+            write_separated(literal, '{', '}', fields, |literal, (name, value)| {
+                literal.write_str(&quote_identifier(name))?;
+                literal.write_str(": ")?;
+                write_cql_value(literal, value)
+            })
+        }
+        CassandraType::Null => literal.write_str("null"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::net::IpAddr;
+
+    use num_bigint::BigInt;
+
+    use super::*;
+    use crate::types::blob::Blob;
+    use crate::types::decimal::Decimal;
+    use crate::types::duration::Duration;
+
+    #[derive(Debug, PartialEq)]
+    enum Lexeme {
+        String(String),
+        QuotedIdentifier(String),
+        Word(String),
+        Symbol(char),
+    }
+
+    // lexes CQL the way the server does, failing on unterminated quotes, unbalanced brackets and
+    // raw control characters outside of string literals
+    fn lex(cql: &str) -> Vec<Lexeme> {
+        let mut lexemes = vec![];
+        let mut brackets = vec![];
+        let mut chars = cql.chars().peekable();
+
+        while let Some(c) = chars.next() {
+            match c {
+                '\'' | '"' => {
+                    let mut text = String::new();
+                    loop {
+                        match chars.next() {
+                            Some(next) if next == c && chars.peek() == Some(&c) => {
+                                chars.next();
+                                text.push(c);
+                            }
+                            Some(next) if next == c => break,
+                            Some(next) => text.push(next),
+                            None => panic!("Unterminated quote in {:?}", cql),
+                        }
+                    }
+
+                    lexemes.push(if c == '\'' {
+                        Lexeme::String(text)
+                    } else {
+                        Lexeme::QuotedIdentifier(text)
+                    });
+                }
+                c if c.is_alphanumeric() || c == '_' || c == '.' || c == '-' => {
+                    let mut word = c.to_string();
+                    while let Some(&next) = chars.peek() {
+                        if !(next.is_alphanumeric() || next == '_' || next == '.' || next == '-') {
+                            break;
+                        }
+
+                        word.push(next);
+                        chars.next();
+                    }
+
+                    lexemes.push(Lexeme::Word(word));
+                }
+                ' ' => {}
+                c if c.is_control() => panic!("Raw control character in {:?}", cql),
+                '[' | '{' | '(' => {
+                    brackets.push(c);
+                    lexemes.push(Lexeme::Symbol(c));
+                }
+                ']' | '}' | ')' => {
+                    let expected = match c {
+                        ']' => '[',
+                        '}' => '{',
+                        _ => '(',
+                    };
+                    assert_eq!(brackets.pop(), Some(expected), "{:?}", cql);
+                    lexemes.push(Lexeme::Symbol(c));
+                }
+                c => lexemes.push(Lexeme::Symbol(c)),
+            }
+        }
+
+        assert!(brackets.is_empty(), "{:?}", cql);
+        lexemes
+    }
+
+    // xorshift, so generated cases are reproducible
+    struct Generator(u64);
+
+    impl Generator {
+        fn next(&mut self, bound: u64) -> u64 {
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 7;
+            self.0 ^= self.0 << 17;
+            self.0 % bound
+        }
+
+        fn text(&mut self) -> String {
+            const CHARS: &[char] = &[
+                'a', 'Z', '0', '_', ' ', '\'', '"', '$', '-', '/', '*', ';', '\n', '\0', '\t',
+                '\u{7f}', 'ż', '語', '🦀',
+            ];
+
+            (0..self.next(8))
+                .map(|_| CHARS[self.next(CHARS.len() as u64) as usize])
+                .collect()
+        }
+
+        fn value(&mut self, depth: u32) -> CassandraType {
+            let kinds = if depth > 2 { 12 } else { 18 };
+            match self.next(kinds) {
+                0 => CassandraType::Varchar(self.text()),
+                1 => CassandraType::Ascii(self.text()),
+                2 => CassandraType::Bigint(self.next(u64::MAX) as i64),
+                3 => CassandraType::Int(self.next(u64::MAX) as i32),
+                4 => CassandraType::Double(f64::from_bits(self.next(u64::MAX))),
+                5 => CassandraType::Float(f32::from_bits(self.next(u64::MAX) as u32)),
+                6 => CassandraType::Boolean(self.next(2) == 0),
+                7 => CassandraType::Blob(Blob::new(self.text().into_bytes())),
+                8 => CassandraType::Date(self.next(u64::MAX) as i32),
+                9 => CassandraType::Decimal(Decimal::new(
+                    BigInt::from(self.next(u64::MAX) as i64),
+                    self.next(u64::MAX) as i32,
+                )),
+                10 => CassandraType::Inet(IpAddr::from([0, 0, 0, 0, 0, 0, 0, self.next(9) as u16])),
+                11 => CassandraType::Null,
+                12 => {
+                    CassandraType::List((0..self.next(3)).map(|_| self.value(depth + 1)).collect())
+                }
+                13 => {
+                    CassandraType::Set((0..self.next(3)).map(|_| self.value(depth + 1)).collect())
+                }
+                14 => {
+                    CassandraType::Tuple((0..self.next(3)).map(|_| self.value(depth + 1)).collect())
+                }
+                15 => CassandraType::Vector(
+                    (0..self.next(3)).map(|_| self.value(depth + 1)).collect(),
+                ),
+                16 => CassandraType::Map(
+                    (0..self.next(3))
+                        .map(|_| (self.value(depth + 1), self.value(depth + 1)))
+                        .collect(),
+                ),
+                _ => CassandraType::Udt(
+                    (0..self.next(3))
+                        .map(|_| (self.text(), self.value(depth + 1)))
+                        .collect(),
+                ),
+            }
+        }
+    }
+
+    #[test]
+    fn should_quote_identifiers_when_needed() {
+        assert_eq!(quote_identifier("users"), "users");
+        assert_eq!(quote_identifier("user_2"), "user_2");
+        assert_eq!(quote_identifier("Users"), "\"Users\"");
+        assert_eq!(quote_identifier("2users"), "\"2users\"");
+        assert_eq!(quote_identifier("_users"), "\"_users\"");
+        assert_eq!(quote_identifier("select"), "\"select\"");
+        assert_eq!(quote_identifier(""), "\"\"");
+        assert_eq!(quote_identifier("a\"b"), "\"a\"\"b\"");
+        assert_eq!(quote_identifier("ks.t"), "\"ks.t\"");
+    }
+
+    #[test]
+    fn should_quote_string_literals() {
+        assert_eq!(quote_string_literal("it's"), "'it''s'");
+        assert_eq!(quote_string_literal(""), "''");
+        assert_eq!(
+            quote_string_literal("'; DROP TABLE t; --"),
+            "'''; DROP TABLE t; --'"
+        );
+    }
+
+    #[test]
+    fn should_round_trip_quoted_text() {
+        let mut generator = Generator(0x2545_f491_4f6c_dd1d);
+
+        for _ in 0..2000 {
+            let text = generator.text();
+
+            assert_eq!(
+                lex(&quote_string_literal(&text)),
+                vec![Lexeme::String(text.clone())]
+            );
+
+            let identifier = quote_identifier(&text);
+            match lex(&identifier).as_slice() {
+                [Lexeme::QuotedIdentifier(unquoted)] => assert_eq!(*unquoted, text),
+                [Lexeme::Word(word)] => {
+                    assert_eq!(*word, text);
+                    assert_eq!(word.to_lowercase(), text);
+                }
+                lexemes => panic!("{:?} lexed as {:?}", identifier, lexemes),
+            }
+        }
+    }
+
+    #[test]
+    fn should_format_cql_values() {
+        let udt = HashMap::from([
+            ("name".to_string(), CassandraType::Varchar("O'Hara".into())),
+            ("Age".to_string(), CassandraType::Int(42)),
+        ]);
+
+        let cases = [
+            (CassandraType::Varchar("it's".into()), "'it''s'"),
+            (CassandraType::Bigint(-5), "-5"),
+            (CassandraType::Double(1.0), "1.0"),
+            (CassandraType::Float(f32::NEG_INFINITY), "-Infinity"),
+            (CassandraType::Double(f64::NAN), "NaN"),
+            (
+                CassandraType::Decimal(Decimal::new(12345.into(), 2)),
+                "12345E-2",
+            ),
+            (
+                CassandraType::Decimal(Decimal::new(1.into(), i32::MIN)),
+                "1E2147483648",
+            ),
+            (CassandraType::Blob(Blob::new(vec![0, 171])), "0x00ab"),
+            (CassandraType::Blob(Blob::new(vec![])), "0x"),
+            (
+                CassandraType::Date(CqlDate::from_ymd(2024, 2, 29).unwrap().raw() as i32),
+                "'2024-02-29'",
+            ),
+            (CassandraType::Date(0), "'0'"),
+            (
+                CassandraType::Duration(Duration::new(-1, -2, -3).unwrap()),
+                "-1mo2d3ns",
+            ),
+            (
+                CassandraType::Inet("127.0.0.1".parse().unwrap()),
+                "'127.0.0.1'",
+            ),
+            (
+                CassandraType::List(vec![CassandraType::Int(1), CassandraType::Null]),
+                "[1, null]",
+            ),
+            (CassandraType::Set(vec![]), "{}"),
+            (
+                CassandraType::Map(vec![(
+                    CassandraType::Varchar("k".into()),
+                    CassandraType::Tuple(vec![CassandraType::Boolean(true)]),
+                )]),
+                "{'k': (true)}",
+            ),
+            (CassandraType::Udt(udt), "{\"Age\": 42, name: 'O''Hara'}"),
+        ];
+
+        for (value, literal) in cases {
+            assert_eq!(format_cql_value(&value), literal);
+        }
+    }
+
+    #[test]
+    fn should_format_lexable_cql_values() {
+        let mut generator = Generator(0x9e37_79b9_7f4a_7c15);
+
+        for _ in 0..2000 {
+            let value = generator.value(0);
+            let literal = format_cql_value(&value);
+
+            // text can only appear inside string literals and quoted identifiers
+            for lexeme in lex(&literal) {
+                if let Lexeme::Word(word) = lexeme {
+                    assert!(
+                        word.chars()
+                            .all(|c| c.is_ascii_alphanumeric() || "_.-".contains(c)),
+                        "{:?} in {:?}",
+                        word,
+                        literal
+                    );
+                }
+            }
+        }
+    }
+}
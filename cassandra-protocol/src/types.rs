@@ -4,7 +4,8 @@ use crate::frame::traits::FromCursor;
 use crate::frame::{Serialize, Version};
 use crate::types::data_serialization_types::*;
 use derive_more::Constructor;
-use std::convert::TryInto;
+use std::collections::BTreeMap;
+use std::convert::{TryFrom, TryInto};
 use std::io::{self, Write};
 use std::io::{Cursor, Read};
 use std::net::{IpAddr, SocketAddr};
@@ -14,6 +15,12 @@ pub const INT_LEN: usize = 4;
 pub const LONG_LEN: usize = 8;
 pub const UUID_LEN: usize = 16;
 
+/// Maximum number of elements of collections prefixed with their unsigned `[short]` length, e.g.
+/// values bound to a query or statements in a batch.
+pub const MAX_SHORT_COUNT: usize = u16::MAX as usize;
+/// Maximum length of a `[string]` in bytes.
+pub const MAX_STRING_LEN: usize = u16::MAX as usize;
+
 const NULL_INT_LEN: CInt = -1;
 const NULL_SHORT_LEN: CIntShort = -1;
 
@@ -21,6 +28,7 @@ const NULL_SHORT_LEN: CIntShort = -1;
 pub mod blob;
 pub mod cassandra_type;
 pub mod data_serialization_types;
+pub mod date;
 pub mod decimal;
 pub mod duration;
 pub mod from_cdrs;
@@ -29,6 +37,7 @@ pub mod map;
 pub mod rows;
 pub mod tuple;
 pub mod udt;
+pub mod udt_value;
 pub mod value;
 pub mod vector;
 
@@ -36,6 +45,7 @@ pub mod prelude {
     pub use crate::error::{Error, Result};
     pub use crate::frame::{TryFromRow, TryFromUdt};
     pub use crate::types::blob::Blob;
+    pub use crate::types::date::CqlDate;
     pub use crate::types::decimal::Decimal;
     pub use crate::types::duration::Duration;
     pub use crate::types::list::List;
@@ -43,6 +53,7 @@ pub mod prelude {
     pub use crate::types::rows::Row;
     pub use crate::types::tuple::Tuple;
     pub use crate::types::udt::Udt;
+    pub use crate::types::udt_value::UdtValue;
     pub use crate::types::value::{Bytes, Value};
     pub use crate::types::AsRustType;
 }
@@ -214,9 +225,45 @@ pub fn to_float_big(f: f64) -> Vec<u8> {
     f.to_be_bytes().into()
 }
 
-pub fn serialize_str(cursor: &mut Cursor<&mut Vec<u8>>, value: &str, version: Version) {
-    let len = value.len() as CIntShort;
-    len.serialize(cursor, version);
+/// Checks if a length of a request field fits into its protocol representation.
+pub(crate) fn check_len(what: &'static str, actual: usize, max: usize) -> CDRSResult<()> {
+    if actual > max {
+        Err(CdrsError::ValueTooLarge { what, max, actual })
+    } else {
+        Ok(())
+    }
+}
+
+/// Encodes a length or number of elements as an unsigned `[short]`, failing with
+/// [`ValueTooLarge`](CdrsError::ValueTooLarge) if it doesn't fit.
+pub(crate) fn to_short_len(what: &'static str, len: usize) -> CDRSResult<[u8; SHORT_LEN]> {
+    u16::try_from(len)
+        .map(u16::to_be_bytes)
+        .map_err(|_| CdrsError::ValueTooLarge {
+            what,
+            max: MAX_SHORT_COUNT,
+            actual: len,
+        })
+}
+
+/// Writes a length or number of elements as an unsigned `[short]` in serializers, which can't
+/// fail. Envelope constructors check lengths of request bodies first and fail with
+/// [`ValueTooLarge`](CdrsError::ValueTooLarge), so larger values only come from serializing
+/// bodies directly, and are saturated instead of wrapping around.
+pub(crate) fn serialize_short_len(cursor: &mut Cursor<&mut Vec<u8>>, len: usize) {
+    let _ = cursor.write(&u16::try_from(len).unwrap_or(u16::MAX).to_be_bytes());
+}
+
+/// Reads an unsigned `[short]` length or number of elements.
+pub(crate) fn from_cursor_short_len(cursor: &mut Cursor<&[u8]>) -> CDRSResult<usize> {
+    let mut buff = [0; SHORT_LEN];
+    cursor.read_exact(&mut buff)?;
+
+    Ok(usize::from(u16::from_be_bytes(buff)))
+}
+
+pub fn serialize_str(cursor: &mut Cursor<&mut Vec<u8>>, value: &str, _version: Version) {
+    serialize_short_len(cursor, value.len());
     let _ = cursor.write(value.as_bytes());
 }
 
@@ -227,11 +274,8 @@ pub(crate) fn serialize_str_long(cursor: &mut Cursor<&mut Vec<u8>>, value: &str,
 }
 
 pub(crate) fn from_cursor_str<'a>(cursor: &mut Cursor<&'a [u8]>) -> CDRSResult<&'a str> {
-    let mut buff = [0; SHORT_LEN];
-    cursor.read_exact(&mut buff)?;
-
-    let len = CIntShort::from_be_bytes(buff);
-    let body_bytes = cursor_next_value_ref(cursor, len as usize)?;
+    let len = from_cursor_short_len(cursor)?;
+    let body_bytes = cursor_next_value_ref(cursor, len)?;
 
     std::str::from_utf8(body_bytes).map_err(Into::into)
 }
@@ -246,13 +290,42 @@ pub(crate) fn from_cursor_str_long<'a>(cursor: &mut Cursor<&'a [u8]>) -> CDRSRes
     std::str::from_utf8(body_bytes).map_err(Into::into)
 }
 
+/// Reads `[bytes]` without copying them. Null and not set values are returned as `None`.
+pub(crate) fn from_cursor_bytes_borrowed<'a>(
+    cursor: &mut Cursor<&'a [u8]>,
+) -> CDRSResult<Option<&'a [u8]>> {
+    let mut buff = [0; INT_LEN];
+    cursor.read_exact(&mut buff)?;
+
+    let len = CInt::from_be_bytes(buff);
+    if len < 0 {
+        return Ok(None);
+    }
+
+    cursor_next_value_ref(cursor, len as usize).map(Some)
+}
+
+/// Reads `[short bytes]` without copying them. Negative lengths are returned as `None`.
+pub(crate) fn from_cursor_short_bytes_borrowed<'a>(
+    cursor: &mut Cursor<&'a [u8]>,
+) -> CDRSResult<Option<&'a [u8]>> {
+    let mut buff = [0; SHORT_LEN];
+    cursor.read_exact(&mut buff)?;
+
+    let len = CIntShort::from_be_bytes(buff);
+    if len < 0 {
+        return Ok(None);
+    }
+
+    cursor_next_value_ref(cursor, len as usize).map(Some)
+}
+
 pub(crate) fn serialize_str_list<'a>(
     cursor: &mut Cursor<&mut Vec<u8>>,
     list: impl ExactSizeIterator<Item = &'a str>,
     version: Version,
 ) {
-    let len = list.len() as CIntShort;
-    len.serialize(cursor, version);
+    serialize_short_len(cursor, list.len());
 
     for string in list {
         serialize_str(cursor, string, version);
@@ -260,11 +333,8 @@ pub(crate) fn serialize_str_list<'a>(
 }
 
 pub fn from_cursor_string_list(cursor: &mut Cursor<&[u8]>) -> CDRSResult<Vec<String>> {
-    let mut buff = [0; SHORT_LEN];
-    cursor.read_exact(&mut buff)?;
-
-    let len = i16::from_be_bytes(buff);
-    let mut list = Vec::with_capacity(len as usize);
+    let len = from_cursor_short_len(cursor)?;
+    let mut list = Vec::with_capacity(bounded_capacity(len, cursor));
     for _ in 0..len {
         list.push(from_cursor_str(cursor)?.to_string());
     }
@@ -272,6 +342,38 @@ pub fn from_cursor_string_list(cursor: &mut Cursor<&[u8]>) -> CDRSResult<Vec<Str
     Ok(list)
 }
 
+/// Reads a `[bytes map]`, e.g. a custom payload.
+pub fn from_cursor_bytes_map(
+    cursor: &mut Cursor<&[u8]>,
+    version: Version,
+) -> CDRSResult<BTreeMap<String, CBytes>> {
+    let len = from_cursor_short_len(cursor)?;
+    let mut map = BTreeMap::new();
+    for _ in 0..len {
+        let key = from_cursor_str(cursor)?.to_string();
+        let value = CBytes::from_cursor(cursor, version)?;
+        map.insert(key, value);
+    }
+
+    Ok(map)
+}
+
+pub(crate) fn serialize_bytes_map(
+    cursor: &mut Cursor<&mut Vec<u8>>,
+    map: &BTreeMap<String, CBytes>,
+    version: Version,
+) -> CDRSResult<()> {
+    let _ = cursor.write(&to_short_len("number of map entries", map.len())?);
+
+    for (key, value) in map {
+        check_len("map key", key.len(), MAX_STRING_LEN)?;
+        serialize_str(cursor, key, version);
+        value.serialize(cursor, version);
+    }
+
+    Ok(())
+}
+
 #[derive(Debug, Clone, Hash, PartialEq, Eq, Ord, PartialOrd)]
 /// The structure that represents Cassandra byte type.
 pub struct CBytes {
@@ -396,8 +498,7 @@ impl Serialize for CBytesShort {
     fn serialize(&self, cursor: &mut Cursor<&mut Vec<u8>>, version: Version) {
         match &self.bytes {
             Some(bytes) => {
-                let len = bytes.len() as CIntShort;
-                len.serialize(cursor, version);
+                serialize_short_len(cursor, bytes.len());
                 bytes.serialize(cursor, version);
             }
             None => NULL_SHORT_LEN.serialize(cursor, version),
@@ -472,26 +573,43 @@ impl FromCursor for SocketAddr {
 }
 
 pub fn cursor_next_value(cursor: &mut Cursor<&[u8]>, len: usize) -> CDRSResult<Vec<u8>> {
+    // malformed lengths must not cause huge allocations
+    if len > remaining_len(cursor) {
+        return Err(io::Error::from(io::ErrorKind::UnexpectedEof).into());
+    }
+
     let mut buff = vec![0u8; len];
     cursor.read_exact(&mut buff)?;
     Ok(buff)
 }
 
+#[inline]
+fn remaining_len(cursor: &Cursor<&[u8]>) -> usize {
+    (cursor.get_ref().len() as u64).saturating_sub(cursor.position()) as usize
+}
+
+/// Returns capacity to reserve for `len` elements about to be read from given cursor. Each
+/// element takes at least one byte, so the capacity is limited by the number of remaining bytes,
+/// which protects against huge allocations caused by malformed lengths.
+#[inline]
+pub(crate) fn bounded_capacity(len: usize, cursor: &Cursor<&[u8]>) -> usize {
+    len.min(remaining_len(cursor))
+}
+
 pub fn cursor_next_value_ref<'a>(
     cursor: &mut Cursor<&'a [u8]>,
     len: usize,
 ) -> CDRSResult<&'a [u8]> {
     let start = cursor.position() as usize;
-    let result = &cursor.get_ref()[start..start + len];
-    cursor.set_position(cursor.position() + len as u64);
+    let result = start
+        .checked_add(len)
+        .and_then(|end| cursor.get_ref().get(start..end))
+        .ok_or_else(|| {
+            CdrsError::General("cursor_next_value_ref could not retrieve a full slice".into())
+        })?;
 
-    if result.len() != len {
-        Err(CdrsError::General(
-            "cursor_next_value_ref could not retrieve a full slice".into(),
-        ))
-    } else {
-        Ok(result)
-    }
+    cursor.set_position(cursor.position() + len as u64);
+    Ok(result)
 }
 
 #[cfg(test)]
@@ -539,6 +657,19 @@ mod tests {
         assert_eq!(buf, &[0, 3, 102, 111, 111]);
     }
 
+    #[test]
+    fn should_reject_too_large_short_lengths() {
+        assert_eq!(to_short_len("test", MAX_SHORT_COUNT).unwrap(), [255, 255]);
+        assert!(matches!(
+            to_short_len("test", MAX_SHORT_COUNT + 1),
+            Err(CdrsError::ValueTooLarge {
+                what: "test",
+                max: MAX_SHORT_COUNT,
+                actual,
+            }) if actual == MAX_SHORT_COUNT + 1
+        ));
+    }
+
     #[test]
     fn test_serialize_str_long() {
         let input = "foo";
@@ -6,6 +6,12 @@ use derive_more::Constructor;
 pub struct Blob(Vec<u8>);
 
 impl Blob {
+    /// Returns a reference to an underlying slice of bytes.
+    #[inline]
+    pub fn as_slice(&self) -> &[u8] {
+        self.0.as_slice()
+    }
+
     /// Returns a mutable reference to an underlying slice of bytes.
     #[inline]
     pub fn as_mut_slice(&mut self) -> &[u8] {
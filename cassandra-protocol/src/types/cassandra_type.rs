@@ -71,12 +71,13 @@ pub fn wrapper_fn(
         ColType::Set => &wrappers::set,
         ColType::Udt => &wrappers::udt,
         ColType::Tuple => &wrappers::tuple,
+        ColType::Other(_) => &wrappers::other,
     }
 }
 
 pub mod wrappers {
     use super::CassandraType;
-    use crate::error::Result as CDRSResult;
+    use crate::error::{Error, Result as CDRSResult};
     use crate::frame::message_result::{ColType, ColTypeOption, ColTypeOptionValue};
     use crate::frame::Version;
     use crate::types::data_serialization_types::*;
@@ -113,6 +114,17 @@ pub mod wrappers {
         Ok(CassandraType::Null)
     }
 
+    pub fn other(
+        _bytes: &CBytes,
+        col_type: &ColTypeOption,
+        _version: Version,
+    ) -> CDRSResult<CassandraType> {
+        Err(match col_type.id {
+            ColType::Other(id) => Error::UnexpectedColumnType(id),
+            id => Error::General(format!("Unexpected column type: {id}")),
+        })
+    }
+
     pub fn map(
         bytes: &CBytes,
         col_type: &ColTypeOption,
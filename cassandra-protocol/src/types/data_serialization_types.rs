@@ -6,13 +6,14 @@ use std::net;
 use std::string::FromUtf8Error;
 
 use super::blob::Blob;
+use super::date::CqlDate;
 use super::decimal::Decimal;
 use super::duration::Duration;
 use crate::error;
 use crate::frame::{FromCursor, Version};
 use crate::types::{
-    try_f32_from_bytes, try_f64_from_bytes, try_i16_from_bytes, try_i32_from_bytes,
-    try_i64_from_bytes, CBytes, CInt, INT_LEN,
+    bounded_capacity, try_f32_from_bytes, try_f64_from_bytes, try_i16_from_bytes,
+    try_i32_from_bytes, try_i64_from_bytes, CBytes, CInt, INT_LEN,
 };
 
 // https://github.com/apache/cassandra/blob/trunk/doc/native_protocol_v4.spec#L813
@@ -78,8 +79,18 @@ pub fn decode_date(bytes: &[u8]) -> Result<i32, io::Error> {
     try_i32_from_bytes(bytes)
 }
 
+// Decodes Cassandra `date` data (bytes) into `CqlDate`
+#[inline]
+pub fn decode_cql_date(bytes: &[u8]) -> Result<CqlDate, io::Error> {
+    try_i32_from_bytes(bytes).map(|raw| CqlDate::from_raw(raw as u32))
+}
+
 // Decodes Cassandra `decimal` data (bytes)
 pub fn decode_decimal(bytes: &[u8]) -> Result<Decimal, io::Error> {
+    if bytes.len() < INT_LEN {
+        return Err(io::Error::from(io::ErrorKind::UnexpectedEof));
+    }
+
     let lr = bytes.split_at(INT_LEN);
 
     let scale = try_i32_from_bytes(lr.0)?;
@@ -136,7 +147,7 @@ pub fn decode_list(bytes: &[u8], version: Version) -> Result<Vec<CBytes>, io::Er
     let mut cursor = io::Cursor::new(bytes);
     let l = CInt::from_cursor(&mut cursor, version)
         .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
-    let mut list = Vec::with_capacity(l as usize);
+    let mut list = Vec::with_capacity(bounded_capacity(l as usize, &cursor));
     for _ in 0..l {
         let b = CBytes::from_cursor(&mut cursor, version)
             .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
@@ -152,12 +163,15 @@ pub fn decode_float_vector(
 ) -> Result<Vec<CBytes>, io::Error> {
     let type_size = 4;
 
-    let mut vector = Vec::with_capacity(count);
-    for i in (0..(count * type_size)).step_by(4) {
-        vector.push(CBytes::new(bytes[i..i + type_size].to_vec()));
-    }
+    let len = count
+        .checked_mul(type_size)
+        .filter(|len| *len <= bytes.len())
+        .ok_or_else(|| io::Error::from(io::ErrorKind::UnexpectedEof))?;
 
-    Ok(vector)
+    Ok(bytes[..len]
+        .chunks_exact(type_size)
+        .map(|value| CBytes::new(value.to_vec()))
+        .collect())
 }
 
 // Decodes Cassandra `set` data (bytes)
@@ -171,7 +185,7 @@ pub fn decode_map(bytes: &[u8], version: Version) -> Result<Vec<(CBytes, CBytes)
     let mut cursor = io::Cursor::new(bytes);
     let l = CInt::from_cursor(&mut cursor, version)
         .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
-    let mut map = Vec::with_capacity(l as usize);
+    let mut map = Vec::with_capacity(bounded_capacity(l as usize, &cursor));
     for _ in 0..l {
         let k = CBytes::from_cursor(&mut cursor, version)
             .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
@@ -191,7 +205,10 @@ pub fn decode_smallint(bytes: &[u8]) -> Result<i16, io::Error> {
 // Decodes Cassandra `tinyint` data (bytes)
 #[inline]
 pub fn decode_tinyint(bytes: &[u8]) -> Result<i8, io::Error> {
-    Ok(bytes[0] as i8)
+    bytes
+        .first()
+        .map(|value| *value as i8)
+        .ok_or_else(|| io::Error::from(io::ErrorKind::UnexpectedEof))
 }
 
 // Decodes Cassandra `text` data (bytes)
@@ -237,7 +254,7 @@ pub fn decode_duration(bytes: &[u8]) -> Result<Duration, io::Error> {
 // Decodes Cassandra `Udt` data (bytes)
 pub fn decode_udt(bytes: &[u8], l: usize, version: Version) -> Result<Vec<CBytes>, io::Error> {
     let mut cursor = io::Cursor::new(bytes);
-    let mut udt = Vec::with_capacity(l);
+    let mut udt = Vec::with_capacity(bounded_capacity(l, &cursor));
     for _ in 0..l {
         let v = CBytes::from_cursor(&mut cursor, version)
             .or_else(|err| match err {
@@ -260,7 +277,7 @@ pub fn decode_udt(bytes: &[u8], l: usize, version: Version) -> Result<Vec<CBytes
 // Decodes Cassandra `Tuple` data (bytes)
 pub fn decode_tuple(bytes: &[u8], l: usize, version: Version) -> Result<Vec<CBytes>, io::Error> {
     let mut cursor = io::Cursor::new(bytes);
-    let mut tuple = Vec::with_capacity(l);
+    let mut tuple = Vec::with_capacity(bounded_capacity(l, &cursor));
     for _ in 0..l {
         let v = CBytes::from_cursor(&mut cursor, version)
             .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
@@ -323,6 +340,19 @@ mod tests {
         assert_eq!(decode_date(&[0, 0, 0, 3]).unwrap(), 3);
     }
 
+    #[test]
+    fn decode_cql_date_test() {
+        assert_eq!(
+            decode_cql_date(&[128, 0, 0, 3]).unwrap(),
+            CqlDate::from_days_since_epoch(3)
+        );
+        assert_eq!(
+            decode_cql_date(&[127, 255, 255, 255]).unwrap(),
+            CqlDate::from_days_since_epoch(-1)
+        );
+        assert!(decode_cql_date(&[0, 0, 3]).is_err());
+    }
+
     #[test]
     fn decode_double_test() {
         let bytes = to_float_big(0.3);
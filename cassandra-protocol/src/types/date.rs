@@ -0,0 +1,221 @@
+use std::convert::TryFrom;
+use std::fmt::{Display, Formatter};
+use std::io::{Cursor, Write};
+use thiserror::Error;
+
+use crate::frame::{Serialize, Version};
+
+// dates are stored as unsigned days, with the epoch in the middle of the range
+const EPOCH: u32 = 1 << 31;
+
+// days from 0000-03-01 to 1970-01-01
+const EPOCH_OFFSET: i64 = 719_468;
+const DAYS_PER_ERA: i64 = 146_097;
+
+/// Possible `CqlDate` creation error.
+#[derive(Debug, Error, Copy, Clone, Ord, PartialOrd, Eq, PartialEq, Hash)]
+pub enum CqlDateCreationError {
+    #[error("Invalid month {month}, expected 1-12")]
+    InvalidMonth { month: u32 },
+    #[error("Invalid day {day} for {year:04}-{month:02}")]
+    InvalidDay { year: i64, month: u32, day: u32 },
+    #[error(
+        "Date {year:04}-{month:02}-{day:02} is outside of the range representable by CQL date"
+    )]
+    OutOfRange { year: i64, month: u32, day: u32 },
+}
+
+/// Cassandra `date` type - an unsigned number of days, with the Unix epoch at 2^31. Every value is
+/// a valid date in the proleptic Gregorian calendar, ranging from about 5.8 million years before to
+/// 5.8 million years after the epoch.
+#[derive(Debug, Copy, Clone, Ord, PartialOrd, Eq, PartialEq, Hash)]
+pub struct CqlDate(u32);
+
+impl CqlDate {
+    /// Creates a date from the raw representation used by the protocol.
+    #[inline]
+    pub const fn from_raw(raw: u32) -> Self {
+        CqlDate(raw)
+    }
+
+    /// Creates a date from the number of days since 1970-01-01. The whole `i32` range is
+    /// representable.
+    #[inline]
+    pub const fn from_days_since_epoch(days: i32) -> Self {
+        CqlDate((days as u32).wrapping_add(EPOCH))
+    }
+
+    /// Creates a date from a year, month (1-12) and day of the month (1-31).
+    pub fn from_ymd(year: i64, month: u32, day: u32) -> Result<Self, CqlDateCreationError> {
+        if !(1..=12).contains(&month) {
+            return Err(CqlDateCreationError::InvalidMonth { month });
+        }
+
+        if day == 0 || day > days_in_month(year, month) {
+            return Err(CqlDateCreationError::InvalidDay { year, month, day });
+        }
+
+        days_from_civil(year, month, day)
+            .and_then(|days| i32::try_from(days).ok())
+            .map(Self::from_days_since_epoch)
+            .ok_or(CqlDateCreationError::OutOfRange { year, month, day })
+    }
+
+    /// Returns the raw representation used by the protocol.
+    #[inline]
+    pub const fn raw(&self) -> u32 {
+        self.0
+    }
+
+    /// Returns the number of days since 1970-01-01.
+    #[inline]
+    pub const fn days_since_epoch(&self) -> i32 {
+        self.0.wrapping_sub(EPOCH) as i32
+    }
+
+    /// Returns the year, month (1-12) and day of the month (1-31).
+    pub fn to_ymd(&self) -> (i64, u32, u32) {
+        civil_from_days(self.days_since_epoch().into())
+    }
+}
+
+impl Display for CqlDate {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let (year, month, day) = self.to_ymd();
+        write!(f, "{year:04}-{month:02}-{day:02}")
+    }
+}
+
+impl Serialize for CqlDate {
+    #[inline]
+    fn serialize(&self, cursor: &mut Cursor<&mut Vec<u8>>, _version: Version) {
+        let _ = cursor.write(&self.0.to_be_bytes());
+    }
+}
+
+#[inline]
+fn is_leap_year(year: i64) -> bool {
+    year % 4 == 0 && (year % 100 != 0 || year % 400 == 0)
+}
+
+fn days_in_month(year: i64, month: u32) -> u32 {
+    match month {
+        2 if is_leap_year(year) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+// Civil calendar conversions based on http://howardhinnant.github.io/date_algorithms.html - years
+// are shifted to start in March, so leap days end up at the end of a year, and split into 400
+// year eras, which all have the same number of days.
+fn days_from_civil(year: i64, month: u32, day: u32) -> Option<i64> {
+    let year = if month <= 2 {
+        year.checked_sub(1)?
+    } else {
+        year
+    };
+
+    let era = year.div_euclid(400);
+    let year_of_era = year.rem_euclid(400);
+    let month = i64::from(month);
+    let day_of_year =
+        (153 * (if month > 2 { month - 3 } else { month + 9 }) + 2) / 5 + i64::from(day) - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+
+    era.checked_mul(DAYS_PER_ERA)?
+        .checked_add(day_of_era - EPOCH_OFFSET)
+}
+
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let days = days + EPOCH_OFFSET;
+    let era = days.div_euclid(DAYS_PER_ERA);
+    let day_of_era = days.rem_euclid(DAYS_PER_ERA);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153;
+    let day = (day_of_year - (153 * shifted_month + 2) / 5 + 1) as u32;
+    let month = if shifted_month < 10 {
+        shifted_month + 3
+    } else {
+        shifted_month - 9
+    } as u32;
+
+    let year = year_of_era + era * 400;
+    (if month <= 2 { year + 1 } else { year }, month, day)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::frame::{Serialize, Version};
+    use crate::types::date::{CqlDate, CqlDateCreationError};
+
+    #[test]
+    fn should_convert_days_since_epoch() {
+        assert_eq!(CqlDate::from_days_since_epoch(0).raw(), 1 << 31);
+        assert_eq!(CqlDate::from_days_since_epoch(i32::MIN).raw(), 0);
+        assert_eq!(CqlDate::from_days_since_epoch(i32::MAX).raw(), u32::MAX);
+        assert_eq!(CqlDate::from_raw(0).days_since_epoch(), i32::MIN);
+        assert_eq!(CqlDate::from_days_since_epoch(-1).days_since_epoch(), -1);
+    }
+
+    #[test]
+    fn should_convert_civil_dates() {
+        let dates = [
+            ((1970, 1, 1), 0),
+            ((1969, 12, 31), -1),
+            ((2000, 2, 29), 11016),
+            ((2000, 3, 1), 11017),
+            ((1900, 3, 1), -25508),
+            ((-1, 12, 31), -719_529),
+            ((2262, 4, 12), 106_752),
+        ];
+
+        for ((year, month, day), days) in dates.iter().copied() {
+            let date = CqlDate::from_ymd(year, month, day).unwrap();
+            assert_eq!(date.days_since_epoch(), days);
+            assert_eq!(date.to_ymd(), (year, month, day));
+        }
+
+        assert_eq!(CqlDate::from_raw(0).to_ymd(), (-5_877_641, 6, 23));
+        assert_eq!(CqlDate::from_raw(u32::MAX).to_ymd(), (5_881_580, 7, 11));
+        assert_eq!(CqlDate::from_ymd(5_881_580, 7, 11).unwrap().raw(), u32::MAX);
+    }
+
+    #[test]
+    fn should_reject_invalid_dates() {
+        assert_eq!(
+            CqlDate::from_ymd(2021, 13, 1),
+            Err(CqlDateCreationError::InvalidMonth { month: 13 })
+        );
+        assert_eq!(
+            CqlDate::from_ymd(1900, 2, 29),
+            Err(CqlDateCreationError::InvalidDay {
+                year: 1900,
+                month: 2,
+                day: 29
+            })
+        );
+        assert_eq!(
+            CqlDate::from_ymd(5_881_580, 7, 12),
+            Err(CqlDateCreationError::OutOfRange {
+                year: 5_881_580,
+                month: 7,
+                day: 12
+            })
+        );
+        assert!(matches!(
+            CqlDate::from_ymd(i64::MIN, 1, 1),
+            Err(CqlDateCreationError::OutOfRange { .. })
+        ));
+    }
+
+    #[test]
+    fn should_serialize_date() {
+        let date = CqlDate::from_ymd(1970, 1, 2).unwrap();
+        assert_eq!(date.serialize_to_vec(Version::V4), vec![128, 0, 0, 1]);
+        assert_eq!(date.to_string(), "1970-01-02");
+    }
+}
@@ -7,6 +7,7 @@ use uuid::Uuid;
 
 use crate::error::Result as CdrsResult;
 use crate::types::blob::Blob;
+use crate::types::date::CqlDate;
 use crate::types::decimal::Decimal;
 use crate::types::list::List;
 use crate::types::map::Map;
@@ -49,6 +50,7 @@ impl FromCdrs for Udt {}
 impl FromCdrs for Tuple {}
 impl FromCdrs for PrimitiveDateTime {}
 impl FromCdrs for Decimal {}
+impl FromCdrs for CqlDate {}
 impl FromCdrs for NonZeroI8 {}
 impl FromCdrs for NonZeroI16 {}
 impl FromCdrs for NonZeroI32 {}
@@ -91,6 +93,7 @@ impl FromCdrsByName for Udt {}
 impl FromCdrsByName for Tuple {}
 impl FromCdrsByName for PrimitiveDateTime {}
 impl FromCdrsByName for Decimal {}
+impl FromCdrsByName for CqlDate {}
 impl FromCdrsByName for NonZeroI8 {}
 impl FromCdrsByName for NonZeroI16 {}
 impl FromCdrsByName for NonZeroI32 {}
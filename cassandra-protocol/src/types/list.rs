@@ -9,6 +9,7 @@ use crate::frame::message_result::{ColType, ColTypeOption, ColTypeOptionValue};
 use crate::frame::Version;
 use crate::types::blob::Blob;
 use crate::types::data_serialization_types::*;
+use crate::types::date::CqlDate;
 use crate::types::decimal::Decimal;
 use crate::types::map::Map;
 use crate::types::tuple::Tuple;
@@ -59,6 +60,7 @@ list_as_rust!(Map);
 list_as_rust!(Udt);
 list_as_rust!(Tuple);
 list_as_rust!(Decimal);
+list_as_rust!(CqlDate);
 list_as_rust!(BigInt);
 
 list_as_cassandra_type!();
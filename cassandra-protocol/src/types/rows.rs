@@ -1,6 +1,8 @@
+use std::collections::HashMap;
 use std::net::IpAddr;
 use std::num::{NonZeroI16, NonZeroI32, NonZeroI64, NonZeroI8};
 use std::sync::Arc;
+use std::time::Duration;
 
 use chrono::prelude::*;
 use time::PrimitiveDateTime;
@@ -8,11 +10,13 @@ use uuid::Uuid;
 
 use crate::error::{column_is_empty_err, Error, Result};
 use crate::frame::message_result::{
-    BodyResResultRows, ColSpec, ColType, ColTypeOption, ColTypeOptionValue, RowsMetadata,
+    BodyResResultRows, ColSpec, ColType, ColTypeOption, ColTypeOptionValue, RowsMetadata, TableSpec,
 };
 use crate::frame::Version;
+use crate::query::utils::quote;
 use crate::types::blob::Blob;
 use crate::types::data_serialization_types::*;
+use crate::types::date::CqlDate;
 use crate::types::decimal::Decimal;
 use crate::types::list::List;
 use crate::types::map::Map;
@@ -21,36 +25,82 @@ use crate::types::udt::Udt;
 use crate::types::{ByIndex, ByName, CBytes, IntoRustByIndex, IntoRustByName};
 use num_bigint::BigInt;
 
+// result metadata shared by all rows of a response, with column indices resolved once
+#[derive(Debug)]
+struct RowsColumns {
+    metadata: RowsMetadata,
+    // for duplicate column names, e.g. the same column selected twice, the first one wins
+    indices: HashMap<String, usize>,
+}
+
+impl RowsColumns {
+    fn new(metadata: RowsMetadata) -> Self {
+        let mut indices = HashMap::with_capacity(metadata.col_specs.len());
+        for (index, spec) in metadata.col_specs.iter().enumerate() {
+            indices.entry(spec.name.clone()).or_insert(index);
+        }
+
+        RowsColumns { metadata, indices }
+    }
+
+    #[inline]
+    fn index_of(&self, name: &str) -> Option<usize> {
+        self.indices.get(name).copied()
+    }
+}
+
 #[derive(Clone, Debug)]
 pub struct Row {
-    metadata: Arc<RowsMetadata>,
+    columns: Arc<RowsColumns>,
     row_content: Vec<CBytes>,
     protocol_version: Version,
 }
 
 impl Row {
     pub fn from_body(body: BodyResResultRows) -> Vec<Row> {
-        let metadata = Arc::new(body.metadata);
+        let columns = Arc::new(RowsColumns::new(body.metadata));
         let protocol_version = body.protocol_version;
         body.rows_content
             .into_iter()
             .map(|row| Row {
-                metadata: metadata.clone(),
+                columns: columns.clone(),
                 row_content: row,
                 protocol_version,
             })
             .collect()
     }
 
+    /// Returns the number of columns in the row.
+    #[inline]
+    pub fn column_count(&self) -> usize {
+        self.row_content.len()
+    }
+
     /// Checks if a column is present in the row.
     pub fn contains_column(&self, name: &str) -> bool {
-        self.metadata
-            .col_specs
-            .iter()
-            .any(|spec| spec.name.as_str() == name)
+        self.columns.index_of(name).is_some()
     }
 
-    /// Checks for NULL for a given column. Returns false if given column does not exist.
+    /// Returns the serialized value of a given column, or `None` if it does not exist.
+    #[inline]
+    pub fn raw_value_by_name(&self, name: &str) -> Option<&CBytes> {
+        self.col_spec_by_name(name).map(|(_, data)| data)
+    }
+
+    /// Returns the keyspace and table the row comes from, taken from the global table spec or the
+    /// first column.
+    pub fn table_spec(&self) -> Option<&TableSpec> {
+        let metadata = &self.columns.metadata;
+        metadata.global_table_spec.as_ref().or_else(|| {
+            metadata
+                .col_specs
+                .first()
+                .and_then(|spec| spec.table_spec.as_ref())
+        })
+    }
+
+    /// Checks for NULL or an empty value for a given column. Returns false if given column does
+    /// not exist.
     pub fn is_empty(&self, index: usize) -> bool {
         self.row_content
             .get(index)
@@ -58,30 +108,99 @@ impl Row {
             .unwrap_or(false)
     }
 
-    /// Checks for NULL for a given column. Returns false if given column does not exist.
+    /// Checks for NULL or an empty value for a given column. Returns false if given column does
+    /// not exist.
     pub fn is_empty_by_name(&self, name: &str) -> bool {
-        self.metadata
-            .col_specs
-            .iter()
-            .position(|spec| spec.name.as_str() == name)
+        self.columns
+            .index_of(name)
             .map(|index| self.is_empty(index))
             .unwrap_or(false)
     }
 
-    fn col_spec_by_name(&self, name: &str) -> Option<(&ColSpec, &CBytes)> {
-        self.metadata
+    /// Checks for NULL for a given column, treating empty values as not null. Returns false if
+    /// given column does not exist.
+    pub fn is_null(&self, index: usize) -> bool {
+        self.row_content
+            .get(index)
+            .map(|data| data.as_slice().is_none())
+            .unwrap_or(false)
+    }
+
+    /// Checks for NULL for a given column, treating empty values as not null. Returns false if
+    /// given column does not exist.
+    pub fn is_null_by_name(&self, name: &str) -> bool {
+        self.columns
+            .index_of(name)
+            .map(|index| self.is_null(index))
+            .unwrap_or(false)
+    }
+
+    /// Returns the time to live of given column, selected with `TTL(column)`. The column is looked
+    /// up by the name generated by the server, e.g. `ttl(column)`. If no such column exists,
+    /// `column` is treated as an alias given to the selector. NULL, i.e. no TTL, is returned as
+    /// `None`.
+    pub fn ttl_of(&self, column: &str) -> Result<Option<Duration>> {
+        let index = self
+            .generated_column_index("ttl", column)
+            .ok_or_else(|| column_is_empty_err(format!("ttl({column})")))?;
+
+        let ttl: Option<i32> = self.get_by_index(index)?;
+        Ok(ttl.map(|ttl| Duration::from_secs(ttl.max(0) as u64)))
+    }
+
+    /// Returns the write time of given column, selected with `WRITETIME(column)`. The column is
+    /// looked up in the same way as in [`Row::ttl_of`].
+    pub fn writetime_of(&self, column: &str) -> Result<Option<DateTime<Utc>>> {
+        self.writetime_micros_of(column)?
+            .map(|micros| {
+                Utc.timestamp_micros(micros).single().ok_or_else(|| {
+                    Error::General(format!("Invalid write time of column '{column}': {micros}"))
+                })
+            })
+            .transpose()
+    }
+
+    /// Returns the raw write time of given column in microseconds since the Unix epoch, selected
+    /// with `WRITETIME(column)`.
+    pub fn writetime_micros_of(&self, column: &str) -> Result<Option<i64>> {
+        let index = self
+            .generated_column_index("writetime", column)
+            .ok_or_else(|| column_is_empty_err(format!("writetime({column})")))?;
+
+        self.get_by_index(index)
+    }
+
+    fn generated_column_index(&self, function: &str, column: &str) -> Option<usize> {
+        let quoted_column = quote(column);
+        let is_generated = |name: &str| {
+            name.len() > function.len()
+                && name.is_char_boundary(function.len())
+                && name[..function.len()].eq_ignore_ascii_case(function)
+                && name[function.len()..]
+                    .strip_prefix('(')
+                    .and_then(|name| name.strip_suffix(')'))
+                    .map(|name| name == column || name == quoted_column)
+                    .unwrap_or(false)
+        };
+
+        self.columns
+            .metadata
             .col_specs
             .iter()
-            .position(|spec| spec.name.as_str() == name)
-            .map(|i| {
-                let col_spec = &self.metadata.col_specs[i];
-                let data = &self.row_content[i];
-                (col_spec, data)
-            })
+            .position(|spec| is_generated(&spec.name))
+            .or_else(|| self.columns.index_of(column))
+    }
+
+    fn col_spec_by_name(&self, name: &str) -> Option<(&ColSpec, &CBytes)> {
+        self.columns.index_of(name).and_then(|i| {
+            let col_spec = &self.columns.metadata.col_specs[i];
+            let data = self.row_content.get(i)?;
+            Some((col_spec, data))
+        })
     }
 
     fn col_spec_by_index(&self, index: usize) -> Option<(&ColSpec, &CBytes)> {
-        let specs = self.metadata.col_specs.iter();
+        let specs = self.columns.metadata.col_specs.iter();
         let values = self.row_content.iter();
         specs.zip(values).nth(index)
     }
@@ -106,6 +225,7 @@ into_rust_by_name!(Row, Udt);
 into_rust_by_name!(Row, Tuple);
 into_rust_by_name!(Row, PrimitiveDateTime);
 into_rust_by_name!(Row, Decimal);
+into_rust_by_name!(Row, CqlDate);
 into_rust_by_name!(Row, NonZeroI8);
 into_rust_by_name!(Row, NonZeroI16);
 into_rust_by_name!(Row, NonZeroI32);
@@ -133,6 +253,7 @@ into_rust_by_index!(Row, Udt);
 into_rust_by_index!(Row, Tuple);
 into_rust_by_index!(Row, PrimitiveDateTime);
 into_rust_by_index!(Row, Decimal);
+into_rust_by_index!(Row, CqlDate);
 into_rust_by_index!(Row, NonZeroI8);
 into_rust_by_index!(Row, NonZeroI16);
 into_rust_by_index!(Row, NonZeroI32);
@@ -140,3 +261,242 @@ into_rust_by_index!(Row, NonZeroI64);
 into_rust_by_index!(Row, NaiveDateTime);
 into_rust_by_index!(Row, DateTime<Utc>);
 into_rust_by_index!(Row, BigInt);
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use chrono::{TimeZone, Utc};
+
+    use super::Row;
+    use crate::frame::message_result::{
+        BodyResResultRows, ColSpec, ColType, ColTypeOption, ColTypeOptionValue, RowsMetadata,
+        RowsMetadataFlags,
+    };
+    use crate::frame::Version;
+    use crate::types::blob::Blob;
+    use crate::types::date::CqlDate;
+    use crate::types::list::List;
+    use crate::types::map::Map;
+    use crate::types::value::Bytes;
+    use crate::types::{AsRustType, CBytes, IntoRustByIndex, IntoRustByName};
+    use std::collections::HashMap;
+
+    fn col_spec(name: &str, id: ColType) -> ColSpec {
+        ColSpec {
+            table_spec: None,
+            name: name.into(),
+            col_type: ColTypeOption { id, value: None },
+        }
+    }
+
+    fn row(col_specs: Vec<ColSpec>, row_content: Vec<CBytes>) -> Row {
+        Row::from_body(BodyResResultRows {
+            metadata: RowsMetadata {
+                flags: RowsMetadataFlags::empty(),
+                columns_count: col_specs.len() as i32,
+                paging_state: None,
+                new_metadata_id: None,
+                global_table_spec: None,
+                col_specs,
+            },
+            rows_count: 1,
+            rows_content: vec![row_content],
+            protocol_version: Version::V4,
+        })
+        .remove(0)
+    }
+
+    #[test]
+    fn should_read_generated_columns() {
+        let row = row(
+            vec![
+                col_spec("ttl(value)", ColType::Int),
+                col_spec("writetime(value)", ColType::Bigint),
+                col_spec("ttl(\"Other\")", ColType::Int),
+            ],
+            vec![
+                CBytes::new(60i32.to_be_bytes().to_vec()),
+                CBytes::new(1_500_000i64.to_be_bytes().to_vec()),
+                CBytes::new_null(),
+            ],
+        );
+
+        assert_eq!(row.ttl_of("value").unwrap(), Some(Duration::from_secs(60)));
+        assert_eq!(
+            row.writetime_of("value").unwrap(),
+            Some(Utc.timestamp_micros(1_500_000).unwrap())
+        );
+        assert_eq!(row.ttl_of("Other").unwrap(), None);
+        assert!(row.ttl_of("missing").is_err());
+    }
+
+    #[test]
+    fn should_read_aliased_columns() {
+        let row = row(
+            vec![
+                col_spec("value_ttl", ColType::Int),
+                col_spec("value_writetime", ColType::Bigint),
+            ],
+            vec![
+                CBytes::new(10i32.to_be_bytes().to_vec()),
+                CBytes::new(20i64.to_be_bytes().to_vec()),
+            ],
+        );
+
+        assert_eq!(
+            row.ttl_of("value_ttl").unwrap(),
+            Some(Duration::from_secs(10))
+        );
+        assert_eq!(
+            row.writetime_micros_of("value_writetime").unwrap(),
+            Some(20)
+        );
+    }
+
+    #[test]
+    fn should_read_dates() {
+        let date = CqlDate::from_ymd(1969, 12, 31).unwrap();
+        let row = row(
+            vec![
+                col_spec("date", ColType::Date),
+                col_spec("id", ColType::Int),
+            ],
+            vec![
+                CBytes::new(Bytes::from(date).into_inner()),
+                CBytes::new(1i32.to_be_bytes().to_vec()),
+            ],
+        );
+
+        let by_name: CqlDate = row.get_r_by_name("date").unwrap();
+        let by_index: Option<CqlDate> = row.get_by_index(0).unwrap();
+        let invalid: Result<Option<CqlDate>, _> = row.get_by_name("id");
+
+        assert_eq!(by_name, date);
+        assert_eq!(by_index, Some(date));
+        assert!(invalid.is_err());
+    }
+
+    #[test]
+    fn should_read_rows_with_unknown_column_types() {
+        let row = row(
+            vec![
+                col_spec("exotic", ColType::Other(0x0040)),
+                col_spec("id", ColType::Int),
+            ],
+            vec![
+                CBytes::new(vec![1, 2, 3]),
+                CBytes::new(5i32.to_be_bytes().to_vec()),
+            ],
+        );
+
+        let id: i32 = row.get_r_by_name("id").unwrap();
+        let raw: Blob = row.get_r_by_name("exotic").unwrap();
+        let invalid: Result<Option<i32>, _> = row.get_by_name("exotic");
+
+        assert_eq!(id, 5);
+        assert_eq!(raw.into_vec(), vec![1, 2, 3]);
+        assert!(invalid.is_err());
+    }
+
+    #[test]
+    fn should_distinguish_empty_values_from_nulls() {
+        let list_type = ColTypeOption {
+            id: ColType::List,
+            value: Some(ColTypeOptionValue::CList(Box::new(ColTypeOption {
+                id: ColType::Varchar,
+                value: None,
+            }))),
+        };
+        let map_type = ColTypeOption {
+            id: ColType::Map,
+            value: Some(ColTypeOptionValue::CMap(
+                Box::new(ColTypeOption {
+                    id: ColType::Varchar,
+                    value: None,
+                }),
+                Box::new(ColTypeOption {
+                    id: ColType::Blob,
+                    value: None,
+                }),
+            )),
+        };
+
+        let row = row(
+            vec![
+                col_spec("empty_text", ColType::Varchar),
+                col_spec("null_text", ColType::Varchar),
+                col_spec("empty_blob", ColType::Blob),
+                col_spec("null_blob", ColType::Blob),
+                ColSpec {
+                    table_spec: None,
+                    name: "list".into(),
+                    col_type: list_type,
+                },
+                ColSpec {
+                    table_spec: None,
+                    name: "map".into(),
+                    col_type: map_type,
+                },
+            ],
+            vec![
+                CBytes::new(Bytes::from("").into_inner()),
+                CBytes::new_null(),
+                CBytes::new(Bytes::from(Blob::new(vec![])).into_inner()),
+                CBytes::new_null(),
+                CBytes::new(Bytes::from(vec!["", "a"]).into_inner()),
+                CBytes::new(Bytes::from(HashMap::from([("", Blob::new(vec![]))])).into_inner()),
+            ],
+        );
+
+        let empty_text: Option<String> = row.get_by_name("empty_text").unwrap();
+        let null_text: Option<String> = row.get_by_name("null_text").unwrap();
+        assert_eq!(empty_text, Some(String::new()));
+        assert_eq!(null_text, None);
+
+        let empty_blob: Option<Blob> = row.get_by_name("empty_blob").unwrap();
+        let null_blob: Option<Blob> = row.get_by_name("null_blob").unwrap();
+        assert_eq!(empty_blob.map(Blob::into_vec), Some(vec![]));
+        assert!(null_blob.is_none());
+
+        assert!(!row.is_null_by_name("empty_text"));
+        assert!(row.is_null_by_name("null_text"));
+        assert!(row.is_empty_by_name("empty_text"));
+
+        let list: List = row.get_r_by_name("list").unwrap();
+        let list: Vec<String> = list.as_r_type().unwrap();
+        assert_eq!(list, vec![String::new(), "a".to_string()]);
+
+        let map: Map = row.get_r_by_name("map").unwrap();
+        let map: HashMap<String, Blob> = map.as_r_type().unwrap();
+        assert_eq!(map[""].clone().into_vec(), Vec::<u8>::new());
+    }
+
+    #[test]
+    fn should_resolve_duplicate_column_names_to_first_column() {
+        let row = row(
+            vec![
+                col_spec("id", ColType::Int),
+                col_spec("value", ColType::Int),
+                col_spec("value", ColType::Int),
+            ],
+            vec![
+                CBytes::new(1i32.to_be_bytes().to_vec()),
+                CBytes::new(2i32.to_be_bytes().to_vec()),
+                CBytes::new_null(),
+            ],
+        );
+
+        let value: Option<i32> = row.get_by_name("value").unwrap();
+        assert_eq!(value, Some(2));
+        assert!(!row.is_null_by_name("value"));
+        assert!(row.contains_column("id"));
+        assert!(!row.contains_column("missing"));
+        assert_eq!(
+            row.raw_value_by_name("value"),
+            Some(&CBytes::new(2i32.to_be_bytes().to_vec()))
+        );
+        assert_eq!(row.raw_value_by_name("missing"), None);
+        assert_eq!(row.table_spec(), None);
+    }
+}
@@ -10,6 +10,7 @@ use crate::frame::message_result::{CTuple, ColType, ColTypeOption, ColTypeOption
 use crate::frame::Version;
 use crate::types::blob::Blob;
 use crate::types::data_serialization_types::*;
+use crate::types::date::CqlDate;
 use crate::types::decimal::Decimal;
 use crate::types::list::List;
 use crate::types::map::Map;
@@ -79,6 +80,7 @@ into_rust_by_index!(Tuple, Udt);
 into_rust_by_index!(Tuple, Tuple);
 into_rust_by_index!(Tuple, PrimitiveDateTime);
 into_rust_by_index!(Tuple, Decimal);
+into_rust_by_index!(Tuple, CqlDate);
 into_rust_by_index!(Tuple, NaiveDateTime);
 into_rust_by_index!(Tuple, DateTime<Utc>);
 into_rust_by_index!(Tuple, BigInt);
@@ -11,6 +11,7 @@ use crate::frame::message_result::{CUdt, ColType, ColTypeOption, ColTypeOptionVa
 use crate::frame::Version;
 use crate::types::blob::Blob;
 use crate::types::data_serialization_types::*;
+use crate::types::date::CqlDate;
 use crate::types::decimal::Decimal;
 use crate::types::list::List;
 use crate::types::map::Map;
@@ -58,6 +59,7 @@ into_rust_by_name!(Udt, Udt);
 into_rust_by_name!(Udt, Tuple);
 into_rust_by_name!(Udt, PrimitiveDateTime);
 into_rust_by_name!(Udt, Decimal);
+into_rust_by_name!(Udt, CqlDate);
 into_rust_by_name!(Udt, NonZeroI8);
 into_rust_by_name!(Udt, NonZeroI16);
 into_rust_by_name!(Udt, NonZeroI32);
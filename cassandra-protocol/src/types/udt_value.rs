@@ -0,0 +1,287 @@
+use std::io::Cursor;
+
+use crate::error::{Error, Result};
+use crate::frame::message_result::{CUdt, ColSpec, ColType, ColTypeOption, ColTypeOptionValue};
+use crate::frame::{Serialize, Version};
+use crate::types::value::{Bytes, Value};
+
+/// Value of a user defined type with named fields. Unlike structs converted directly into
+/// [`Bytes`], fields are matched by name with the type definition, e.g. from prepared statement
+/// metadata, so they get serialized in declaration order regardless of the order they were added
+/// in.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct UdtValue {
+    fields: Vec<(String, UdtFieldValue)>,
+}
+
+/// Value of a single [`UdtValue`] field.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum UdtFieldValue {
+    /// Already serialized value.
+    Value(Value),
+    /// Nested user defined type, serialized according to the definition of the field type.
+    Udt(UdtValue),
+}
+
+impl<T: Into<Value>> From<T> for UdtFieldValue {
+    #[inline]
+    fn from(value: T) -> Self {
+        UdtFieldValue::Value(value.into())
+    }
+}
+
+impl From<UdtValue> for UdtFieldValue {
+    #[inline]
+    fn from(value: UdtValue) -> Self {
+        UdtFieldValue::Udt(value)
+    }
+}
+
+impl UdtValue {
+    #[inline]
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Adds a field with given name and value. Use [`Value::Null`] for fields without a value.
+    #[must_use]
+    pub fn with_field(mut self, name: impl Into<String>, value: impl Into<UdtFieldValue>) -> Self {
+        self.fields.push((name.into(), value.into()));
+        self
+    }
+
+    /// Returns fields in the order they were added.
+    #[inline]
+    pub fn fields(&self) -> &[(String, UdtFieldValue)] {
+        &self.fields
+    }
+
+    /// Converts this value into a [`Value`] which can be bound to a variable described by given
+    /// prepared statement metadata. Fails with [`Error::InvalidBoundValue`] if the variable is not
+    /// a user defined type, any of its fields is missing or a field unknown to the type is present.
+    pub fn bind(&self, col_spec: &ColSpec, version: Version) -> Result<Value> {
+        self.serialize_as(&col_spec.col_type, version)
+            .map(Value::new)
+            .map_err(|(path, reason)| Error::InvalidBoundValue {
+                name: field_path(&col_spec.name, path),
+                reason,
+            })
+    }
+
+    /// Serializes fields in the order declared by given type definition.
+    pub fn serialize_for(&self, udt: &CUdt, version: Version) -> Result<Bytes> {
+        self.serialize_fields(udt, version)
+            .map_err(|(path, reason)| Error::InvalidBoundValue {
+                name: field_path(&udt.udt_name, path),
+                reason,
+            })
+    }
+
+    // errors contain the path to the failing field, innermost first
+    fn serialize_as(
+        &self,
+        col_type: &ColTypeOption,
+        version: Version,
+    ) -> std::result::Result<Bytes, (Vec<String>, String)> {
+        match (&col_type.id, &col_type.value) {
+            (ColType::Udt, Some(ColTypeOptionValue::UdtType(udt))) => {
+                self.serialize_fields(udt, version)
+            }
+            (id, _) => Err((vec![], format!("{id} is not a user defined type"))),
+        }
+    }
+
+    fn serialize_fields(
+        &self,
+        udt: &CUdt,
+        version: Version,
+    ) -> std::result::Result<Bytes, (Vec<String>, String)> {
+        if let Some((name, _)) = self
+            .fields
+            .iter()
+            .find(|(name, _)| !udt.descriptions.iter().any(|(field, _)| field == name))
+        {
+            return Err((
+                vec![name.clone()],
+                format!("unknown field of type {}", udt.udt_name),
+            ));
+        }
+
+        let mut bytes = vec![];
+        let mut cursor = Cursor::new(&mut bytes);
+
+        for (field, field_type) in &udt.descriptions {
+            let value = self
+                .fields
+                .iter()
+                .find(|(name, _)| name == field)
+                .map(|(_, value)| value)
+                .ok_or_else(|| (vec![field.clone()], "missing field".to_string()))?;
+
+            match value {
+                // unset values are not allowed inside a user defined type
+                UdtFieldValue::Value(Value::NotSet) => Value::Null.serialize(&mut cursor, version),
+                UdtFieldValue::Value(value) => value.serialize(&mut cursor, version),
+                UdtFieldValue::Udt(value) => {
+                    let nested =
+                        value
+                            .serialize_as(field_type, version)
+                            .map_err(|(mut path, reason)| {
+                                path.push(field.clone());
+                                (path, reason)
+                            })?;
+
+                    Value::new(nested).serialize(&mut cursor, version);
+                }
+            }
+        }
+
+        Ok(Bytes::new(bytes))
+    }
+}
+
+fn field_path(root: &str, path: Vec<String>) -> String {
+    path.into_iter()
+        .rev()
+        .fold(root.to_string(), |name, field| format!("{name}.{field}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::error::Error;
+    use crate::frame::message_result::{CUdt, ColSpec, ColType, ColTypeOption, ColTypeOptionValue};
+    use crate::frame::Version;
+    use crate::types::udt_value::UdtValue;
+    use crate::types::value::Value;
+
+    fn field(name: &str, id: ColType) -> (String, ColTypeOption) {
+        (name.into(), ColTypeOption { id, value: None })
+    }
+
+    fn udt(name: &str, descriptions: Vec<(String, ColTypeOption)>) -> ColTypeOption {
+        ColTypeOption {
+            id: ColType::Udt,
+            value: Some(ColTypeOptionValue::UdtType(CUdt {
+                ks: "ks".into(),
+                udt_name: name.into(),
+                descriptions,
+            })),
+        }
+    }
+
+    fn address() -> ColTypeOption {
+        udt(
+            "address",
+            vec![
+                field("street", ColType::Varchar),
+                field("number", ColType::Int),
+            ],
+        )
+    }
+
+    fn col_spec(col_type: ColTypeOption) -> ColSpec {
+        ColSpec {
+            table_spec: None,
+            name: "column".into(),
+            col_type,
+        }
+    }
+
+    fn bytes(value: Value) -> Vec<u8> {
+        match value {
+            Value::Some(bytes) => bytes,
+            value => panic!("Unexpected value: {:?}", value),
+        }
+    }
+
+    #[test]
+    fn should_serialize_fields_in_declaration_order() {
+        let value = UdtValue::new()
+            .with_field("number", 7)
+            .with_field("street", "Main");
+
+        assert_eq!(
+            bytes(value.bind(&col_spec(address()), Version::V4).unwrap()),
+            [0, 0, 0, 4, b'M', b'a', b'i', b'n', 0, 0, 0, 4, 0, 0, 0, 7]
+        );
+    }
+
+    #[test]
+    fn should_serialize_unset_fields_as_null() {
+        let value = UdtValue::new()
+            .with_field("street", Value::NotSet)
+            .with_field("number", Value::Null);
+
+        assert_eq!(
+            bytes(value.bind(&col_spec(address()), Version::V4).unwrap()),
+            [255, 255, 255, 255, 255, 255, 255, 255]
+        );
+    }
+
+    #[test]
+    fn should_reject_missing_and_unknown_fields() {
+        let missing = UdtValue::new().with_field("street", "Main");
+        assert!(matches!(
+            missing.bind(&col_spec(address()), Version::V4),
+            Err(Error::InvalidBoundValue { name, .. }) if name == "column.number"
+        ));
+
+        let unknown = UdtValue::new()
+            .with_field("street", "Main")
+            .with_field("number", 7)
+            .with_field("city", "Springfield");
+        assert!(matches!(
+            unknown.bind(&col_spec(address()), Version::V4),
+            Err(Error::InvalidBoundValue { name, .. }) if name == "column.city"
+        ));
+    }
+
+    #[test]
+    fn should_serialize_nested_udts() {
+        let person = udt(
+            "person",
+            vec![field("name", ColType::Varchar), ("home".into(), address())],
+        );
+
+        let value = UdtValue::new()
+            .with_field(
+                "home",
+                UdtValue::new()
+                    .with_field("number", 1)
+                    .with_field("street", "A"),
+            )
+            .with_field("name", "B");
+
+        assert_eq!(
+            bytes(value.bind(&col_spec(person.clone()), Version::V4).unwrap()),
+            [
+                0, 0, 0, 1, b'B', // name
+                0, 0, 0, 13, // home
+                0, 0, 0, 1, b'A', 0, 0, 0, 4, 0, 0, 0, 1,
+            ]
+        );
+
+        let invalid = UdtValue::new()
+            .with_field("name", "B")
+            .with_field("home", UdtValue::new().with_field("number", 1));
+        assert!(matches!(
+            invalid.bind(&col_spec(person), Version::V4),
+            Err(Error::InvalidBoundValue { name, .. }) if name == "column.home.street"
+        ));
+    }
+
+    #[test]
+    fn should_reject_non_udt_columns() {
+        let value = UdtValue::new().with_field("street", "Main");
+        assert!(matches!(
+            value.bind(
+                &col_spec(ColTypeOption {
+                    id: ColType::Int,
+                    value: None
+                }),
+                Version::V4
+            ),
+            Err(Error::InvalidBoundValue { name, .. }) if name == "column"
+        ));
+    }
+}
@@ -12,9 +12,11 @@ use time::PrimitiveDateTime;
 use uuid::Uuid;
 
 use super::blob::Blob;
+use super::date::CqlDate;
 use super::decimal::Decimal;
 use super::duration::Duration;
 use super::*;
+use crate::frame::FromCursorBorrowed;
 use crate::Error;
 
 const NULL_INT_VALUE: i32 = -1;
@@ -59,7 +61,8 @@ impl FromCursor for Value {
             CInt::from_be_bytes(buff)
         };
 
-        if value_size > 0 {
+        // empty values are distinct from null
+        if value_size >= 0 {
             Ok(Value::Some(cursor_next_value(cursor, value_size as usize)?))
         } else if value_size == -1 {
             Ok(Value::Null)
@@ -71,6 +74,48 @@ impl FromCursor for Value {
     }
 }
 
+/// Borrowed counterpart of [`Value`], referencing bytes in the parsed buffer.
+#[derive(Debug, Clone, Copy, PartialEq, Ord, PartialOrd, Eq, Hash)]
+pub enum ValueBorrowed<'a> {
+    Some(&'a [u8]),
+    Null,
+    NotSet,
+}
+
+impl ValueBorrowed<'_> {
+    /// Copies the value into an owned [`Value`].
+    pub fn to_owned_value(&self) -> Value {
+        match self {
+            ValueBorrowed::Some(bytes) => Value::Some(bytes.to_vec()),
+            ValueBorrowed::Null => Value::Null,
+            ValueBorrowed::NotSet => Value::NotSet,
+        }
+    }
+}
+
+impl<'a> FromCursorBorrowed<'a> for ValueBorrowed<'a> {
+    fn from_cursor_borrowed(
+        cursor: &mut Cursor<&'a [u8]>,
+        _version: Version,
+    ) -> Result<Self, Error> {
+        let value_size = {
+            let mut buff = [0; INT_LEN];
+            cursor.read_exact(&mut buff)?;
+            CInt::from_be_bytes(buff)
+        };
+
+        match value_size {
+            size if size >= 0 => Ok(ValueBorrowed::Some(cursor_next_value_ref(
+                cursor,
+                size as usize,
+            )?)),
+            -1 => Ok(ValueBorrowed::Null),
+            -2 => Ok(ValueBorrowed::NotSet),
+            _ => Err(Error::General("Could not decode query values".into())),
+        }
+    }
+}
+
 // We are assuming here primitive value serialization will not change across protocol versions,
 // which gives us simpler user API.
 
@@ -89,6 +134,18 @@ impl<T: Into<Bytes>> From<Option<T>> for Value {
     }
 }
 
+impl<'a, T> From<&'a Option<T>> for Value
+where
+    &'a T: Into<Bytes>,
+{
+    fn from(b: &'a Option<T>) -> Value {
+        match b {
+            Some(b) => Value::new(b),
+            None => Value::Null,
+        }
+    }
+}
+
 #[derive(Debug, Clone, Constructor)]
 pub struct Bytes(Vec<u8>);
 
@@ -113,6 +170,25 @@ impl From<&str> for Bytes {
     }
 }
 
+impl From<&String> for Bytes {
+    #[inline]
+    fn from(value: &String) -> Self {
+        value.as_str().into()
+    }
+}
+
+/// Byte slices are serialized as blobs.
+///
+/// **Note:** this differs from `Vec<u8>` and `&Vec<u8>`, which are serialized as a
+/// `list<tinyint>`, like vectors of any other type. Use a slice, e.g. `vec.as_slice()`, or
+/// [`Blob`] to send bytes as a blob.
+impl From<&[u8]> for Bytes {
+    #[inline]
+    fn from(value: &[u8]) -> Self {
+        Bytes(value.to_vec())
+    }
+}
+
 impl From<i8> for Bytes {
     #[inline]
     fn from(value: i8) -> Self {
@@ -255,9 +331,23 @@ impl From<Blob> for Bytes {
     }
 }
 
+impl From<&Blob> for Bytes {
+    #[inline]
+    fn from(value: &Blob) -> Self {
+        value.as_slice().into()
+    }
+}
+
 impl From<Decimal> for Bytes {
     #[inline]
     fn from(value: Decimal) -> Self {
+        (&value).into()
+    }
+}
+
+impl From<&Decimal> for Bytes {
+    #[inline]
+    fn from(value: &Decimal) -> Self {
         Bytes(value.serialize_to_vec(Version::V4))
     }
 }
@@ -276,6 +366,13 @@ impl From<DateTime<Utc>> for Bytes {
     }
 }
 
+impl From<CqlDate> for Bytes {
+    #[inline]
+    fn from(value: CqlDate) -> Self {
+        Bytes(value.raw().to_be_bytes().into())
+    }
+}
+
 impl From<Duration> for Bytes {
     #[inline]
     fn from(value: Duration) -> Self {
@@ -283,54 +380,115 @@ impl From<Duration> for Bytes {
     }
 }
 
+/// Vectors are serialized as lists. This includes `Vec<u8>`, which is a `list<tinyint>` and not a
+/// blob - see `From<&[u8]>`.
 impl<T: Into<Bytes>> From<Vec<T>> for Bytes {
     fn from(vec: Vec<T>) -> Bytes {
-        let mut bytes = Vec::with_capacity(INT_LEN);
-        let len = vec.len() as CInt;
+        collection_bytes(vec.len(), vec.into_iter().map(Into::into))
+    }
+}
 
-        bytes.extend_from_slice(&len.to_be_bytes());
+/// Borrowed vectors are serialized as lists, like owned ones, so `&Vec<u8>` is a `list<tinyint>`
+/// and not a blob - see `From<&[u8]>`.
+// elements are cloned one at a time, since references to them might not be convertible
+impl<T: Clone + Into<Bytes>> From<&Vec<T>> for Bytes {
+    fn from(vec: &Vec<T>) -> Bytes {
+        collection_bytes(vec.len(), vec.iter().map(|element| element.clone().into()))
+    }
+}
 
-        let mut cursor = Cursor::new(&mut bytes);
-        cursor.set_position(INT_LEN as u64);
+// lists, sets and maps are serialized as their length followed by elements, with map keys and
+// values as consecutive elements
+fn collection_bytes(len: usize, elements: impl Iterator<Item = Bytes>) -> Bytes {
+    let mut bytes = Vec::with_capacity(INT_LEN);
+    bytes.extend_from_slice(&(len as CInt).to_be_bytes());
 
-        for v in vec {
-            let b: Bytes = v.into();
-            Value::new(b).serialize(&mut cursor, Version::V4);
-        }
+    let mut cursor = Cursor::new(&mut bytes);
+    cursor.set_position(INT_LEN as u64);
 
-        Bytes(bytes)
+    for element in elements {
+        Value::new(element).serialize(&mut cursor, Version::V4);
     }
+
+    Bytes(bytes)
 }
 
 impl From<BigInt> for Bytes {
     fn from(value: BigInt) -> Self {
+        (&value).into()
+    }
+}
+
+impl From<&BigInt> for Bytes {
+    fn from(value: &BigInt) -> Self {
         Self(value.serialize_to_vec(Version::V4))
     }
 }
 
+// references to copyable values are serialized the same way as the values
+macro_rules! bytes_from_ref {
+    ($($ty:ty),*) => {
+        $(
+            impl From<&$ty> for Bytes {
+                #[inline]
+                fn from(value: &$ty) -> Self {
+                    (*value).into()
+                }
+            }
+        )*
+    };
+}
+
+bytes_from_ref!(
+    &str,
+    i8,
+    i16,
+    i32,
+    i64,
+    u8,
+    u16,
+    u32,
+    u64,
+    NonZeroI8,
+    NonZeroI16,
+    NonZeroI32,
+    NonZeroI64,
+    bool,
+    Uuid,
+    IpAddr,
+    f32,
+    f64,
+    PrimitiveDateTime,
+    NaiveDateTime,
+    DateTime<Utc>,
+    CqlDate,
+    Duration
+);
+
 impl<K, V> From<HashMap<K, V>> for Bytes
 where
     K: Into<Bytes> + Hash + Eq,
     V: Into<Bytes>,
 {
     fn from(map: HashMap<K, V>) -> Bytes {
-        let mut bytes = Vec::with_capacity(INT_LEN);
-        let len = map.len() as CInt;
-
-        bytes.extend_from_slice(&len.to_be_bytes());
-
-        let mut cursor = Cursor::new(&mut bytes);
-        cursor.set_position(INT_LEN as u64);
-
-        for (k, v) in map {
-            let key_bytes: Bytes = k.into();
-            let val_bytes: Bytes = v.into();
-
-            Value::new(key_bytes).serialize(&mut cursor, Version::V4);
-            Value::new(val_bytes).serialize(&mut cursor, Version::V4);
-        }
+        collection_bytes(
+            map.len(),
+            map.into_iter().flat_map(|(k, v)| [k.into(), v.into()]),
+        )
+    }
+}
 
-        Bytes(bytes)
+impl<K, V> From<&HashMap<K, V>> for Bytes
+where
+    K: Clone + Into<Bytes>,
+    V: Clone + Into<Bytes>,
+{
+    fn from(map: &HashMap<K, V>) -> Bytes {
+        collection_bytes(
+            map.len(),
+            map.iter()
+                .flat_map(|(k, v)| [k.clone().into(), v.clone().into()]),
+        )
     }
 }
 
@@ -340,23 +498,24 @@ where
     V: Into<Bytes>,
 {
     fn from(map: BTreeMap<K, V>) -> Bytes {
-        let mut bytes = Vec::with_capacity(INT_LEN);
-        let len = map.len() as CInt;
-
-        bytes.extend_from_slice(&len.to_be_bytes());
-
-        let mut cursor = Cursor::new(&mut bytes);
-        cursor.set_position(INT_LEN as u64);
-
-        for (k, v) in map {
-            let key_bytes: Bytes = k.into();
-            let val_bytes: Bytes = v.into();
-
-            Value::new(key_bytes).serialize(&mut cursor, Version::V4);
-            Value::new(val_bytes).serialize(&mut cursor, Version::V4);
-        }
+        collection_bytes(
+            map.len(),
+            map.into_iter().flat_map(|(k, v)| [k.into(), v.into()]),
+        )
+    }
+}
 
-        Bytes(bytes)
+impl<K, V> From<&BTreeMap<K, V>> for Bytes
+where
+    K: Clone + Into<Bytes>,
+    V: Clone + Into<Bytes>,
+{
+    fn from(map: &BTreeMap<K, V>) -> Bytes {
+        collection_bytes(
+            map.len(),
+            map.iter()
+                .flat_map(|(k, v)| [k.clone().into(), v.clone().into()]),
+        )
     }
 }
 
@@ -386,6 +545,71 @@ mod tests {
         )
     }
 
+    #[test]
+    fn test_empty_value_round_trip() {
+        for value in [
+            Value::new(""),
+            Value::new(Vec::<u8>::new()),
+            Value::Null,
+            Value::NotSet,
+        ] {
+            let bytes = value.serialize_to_vec(Version::V4);
+            let decoded = Value::from_cursor(&mut Cursor::new(&bytes), Version::V4).unwrap();
+            assert_eq!(decoded, value);
+        }
+
+        assert_eq!(Value::from(Some("")), Value::Some(vec![]));
+    }
+
+    #[test]
+    fn test_value_from_references() {
+        let text = "hello".to_string();
+        assert_eq!(Value::from(&text), Value::from(text.clone()));
+        assert_eq!(Value::from(&[1u8, 2][..]), Value::Some(vec![1, 2]));
+        assert_eq!(
+            Value::from(&Blob::new(vec![1, 2])),
+            Value::from(Blob::new(vec![1, 2]))
+        );
+        assert_eq!(Value::from(&5i32), Value::from(5i32));
+        assert_eq!(Value::from(&Uuid::nil()), Value::from(Uuid::nil()));
+        assert_eq!(
+            Value::from(&Decimal::new(12.into(), 2)),
+            Value::from(Decimal::new(12.into(), 2))
+        );
+        assert_eq!(
+            Value::from(&BigInt::from(-7)),
+            Value::from(BigInt::from(-7))
+        );
+
+        assert_eq!(Value::from(&Some(text.clone())), Value::from(text));
+        assert_eq!(Value::from(&None::<String>), Value::Null);
+        assert_eq!(Value::from(Some(&1.5f64)), Value::from(1.5f64));
+        assert_eq!(Value::from(None::<&str>), Value::Null);
+
+        let list = vec!["a".to_string(), "b".to_string()];
+        assert_eq!(Value::from(&list), Value::from(list.clone()));
+
+        let map = HashMap::from([(1i32, list.clone())]);
+        assert_eq!(Value::from(&map), Value::from(map.clone()));
+
+        let map = BTreeMap::from([(1i32, list)]);
+        assert_eq!(Value::from(&map), Value::from(map.clone()));
+    }
+
+    #[test]
+    fn test_byte_slices_are_blobs_and_byte_vectors_are_lists() {
+        let bytes = vec![1u8, 2];
+        let list = Value::Some(vec![0, 0, 0, 2, 0, 0, 0, 1, 1, 0, 0, 0, 1, 2]);
+
+        assert_eq!(Value::from(bytes.as_slice()), Value::Some(vec![1, 2]));
+        assert_eq!(
+            Value::from(&Blob::new(bytes.clone())),
+            Value::Some(vec![1, 2])
+        );
+        assert_eq!(Value::from(&bytes), list);
+        assert_eq!(Value::from(bytes), list);
+    }
+
     #[test]
     fn test_new_value_all_types() {
         assert_eq!(
@@ -73,7 +73,7 @@ pub fn get_map_params_string(ty: &Type, name: &str) -> Result<(Type, Type)> {
     }
 }
 
-fn remove_r(s: String) -> String {
+pub fn remove_r(s: String) -> String {
     if let Some(s) = s.strip_prefix("r#") {
         s.to_string()
     } else {
@@ -110,7 +110,8 @@ fn into_rust_with_args(
     let field_type_ident = get_cdrs_type(field_type, name)?;
     Ok(match get_ident_string(&field_type_ident, name)?.as_str() {
         "Blob" | "String" | "bool" | "i64" | "i32" | "i16" | "i8" | "f64" | "f32" | "Decimal"
-        | "IpAddr" | "Uuid" | "Timespec" | "PrimitiveDateTime" | "NaiveDateTime" | "DateTime" => {
+        | "IpAddr" | "Uuid" | "Timespec" | "PrimitiveDateTime" | "NaiveDateTime" | "DateTime"
+        | "CqlDate" => {
             quote! {
               #field_type_ident::from_cdrs_r(#arguments)?
             }
@@ -189,6 +190,7 @@ fn get_cdrs_type(ty: &Type, name: &str) -> Result<Type> {
         "f64" => parse_str("f64").unwrap(),
         "f32" => parse_str("f32").unwrap(),
         "Decimal" => parse_str("Decimal").unwrap(),
+        "CqlDate" => parse_str("CqlDate").unwrap(),
         "IpAddr" => parse_str("IpAddr").unwrap(),
         "Uuid" => parse_str("Uuid").unwrap(),
         "Timespec" => parse_str("Timespec").unwrap(),
@@ -231,7 +233,7 @@ fn as_rust(ty: &Type, val: TokenStream, name: &str) -> Result<TokenStream> {
     let cdrs_type = get_cdrs_type(ty, name)?;
     Ok(match get_ident_string(&cdrs_type, name)?.as_str() {
         "Blob" | "String" | "bool" | "i64" | "i32" | "i16" | "i8" | "f64" | "f32" | "IpAddr"
-        | "Uuid" | "Timespec" | "Decimal" | "PrimitiveDateTime" => val,
+        | "Uuid" | "Timespec" | "Decimal" | "PrimitiveDateTime" | "CqlDate" => val,
         "List" => {
             let vec_type = get_ident_params_string(ty, name)?;
             let inter_rust_type = get_cdrs_type(&vec_type, name)?;
@@ -2,9 +2,83 @@ use itertools::Itertools;
 use proc_macro2::TokenStream;
 use quote::*;
 use syn::spanned::Spanned;
-use syn::{Data, DataStruct, DeriveInput, Error, Result};
+use syn::{Data, DataStruct, DeriveInput, Error, Field, LitStr, Result};
 
-use crate::common::get_ident_string;
+use crate::common::{get_ident_string, remove_r};
+
+#[derive(Default)]
+struct FieldOptions {
+    rename: Option<String>,
+    udt: bool,
+}
+
+fn parse_field_options(field: &Field) -> Result<FieldOptions> {
+    let mut options = FieldOptions::default();
+
+    for attr in field
+        .attrs
+        .iter()
+        .filter(|attr| attr.path().is_ident("cdrs"))
+    {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("rename") {
+                let name: LitStr = meta.value()?.parse()?;
+                options.rename = Some(name.value());
+            } else if meta.path.is_ident("udt") {
+                options.udt = true;
+            } else {
+                return Err(meta.error("Unsupported cdrs attribute"));
+            }
+
+            Ok(())
+        })?;
+    }
+
+    Ok(options)
+}
+
+// named fields used for serializing in the order declared by the target type
+fn udt_fields(fields: &syn::Fields) -> Result<Vec<TokenStream>> {
+    fields
+        .iter()
+        .map(|field| {
+            let options = parse_field_options(field)?;
+            let field_ident = field
+                .ident
+                .clone()
+                .ok_or_else(|| Error::new(field.span(), "IntoCdrsValue requires all fields be named!"))?;
+            let field_name = options
+                .rename
+                .unwrap_or_else(|| remove_r(field_ident.to_string()));
+
+            let convert = if options.udt {
+                quote! { cdrs_tokio::types::udt_value::UdtValue::from(field_value) }
+            } else {
+                quote! { cdrs_tokio::types::value::Value::new(field_value) }
+            };
+
+            let value = if get_ident_string(&field.ty, &field_name)? == "Option" {
+                quote_spanned! { field.ty.span() =>
+                  match value.#field_ident {
+                    Some(field_value) => cdrs_tokio::types::udt_value::UdtFieldValue::from(#convert),
+                    None => cdrs_tokio::types::udt_value::UdtFieldValue::Value(cdrs_tokio::types::value::Value::Null),
+                  }
+                }
+            } else {
+                quote_spanned! { field.ty.span() =>
+                  {
+                    let field_value = value.#field_ident;
+                    cdrs_tokio::types::udt_value::UdtFieldValue::from(#convert)
+                  }
+                }
+            };
+
+            Ok(quote! {
+              .with_field(#field_name, #value)
+            })
+        })
+        .try_collect()
+}
 
 pub fn impl_into_cdrs_value(ast: &DeriveInput) -> Result<TokenStream> {
     let name = &ast.ident;
@@ -16,9 +90,9 @@ pub fn impl_into_cdrs_value(ast: &DeriveInput) -> Result<TokenStream> {
                     // We are assuming here primitive value serialization will not change across protocol
                     // versions, which gives us simpler user API.
                     quote! {
-                  match value.#field_ident {
-                    Some(ref val) => {
-                      let field_bytes: Self = val.clone().into();
+                  match &value.#field_ident {
+                    Some(val) => {
+                      let field_bytes: Self = val.into();
                       cdrs_tokio::types::value::Value::new(field_bytes).serialize(&mut cursor, cdrs_tokio::frame::Version::V4);
                     },
                     None => {
@@ -28,19 +102,29 @@ pub fn impl_into_cdrs_value(ast: &DeriveInput) -> Result<TokenStream> {
                 }
                 } else {
                     quote! {
-                  let field_bytes: Self = value.#field_ident.into();
+                  let field_bytes: Self = (&value.#field_ident).into();
                   cdrs_tokio::types::value::Value::new(field_bytes).serialize(&mut cursor, cdrs_tokio::frame::Version::V4);
                 }
                 }
             })
         }).try_collect()?;
+        let udt_fields = udt_fields(fields)?;
         // As Value has following implementation impl<T: Into<Bytes>> From<T> for Value
         // for a struct it's enough to implement Into<Bytes> in order to be convertible into Value
         // which is used for making queries
         Ok(quote! {
             #[automatically_derived]
             impl From<#name> for cdrs_tokio::types::value::Bytes {
+              #[inline]
               fn from(value: #name) -> Self {
+                (&value).into()
+              }
+            }
+
+            // fields are serialized by reference, so values can be bound without cloning them
+            #[automatically_derived]
+            impl From<&#name> for cdrs_tokio::types::value::Bytes {
+              fn from(value: &#name) -> Self {
                 use cdrs_tokio::frame::Serialize;
 
                 let mut bytes: Vec<u8> = Vec::new();
@@ -49,6 +133,14 @@ pub fn impl_into_cdrs_value(ast: &DeriveInput) -> Result<TokenStream> {
                 Self::new(bytes)
               }
             }
+
+            #[automatically_derived]
+            impl From<#name> for cdrs_tokio::types::udt_value::UdtValue {
+              fn from(value: #name) -> Self {
+                Self::new()
+                  #(#udt_fields)*
+              }
+            }
         })
     } else {
         Err(Error::new(
@@ -0,0 +1,146 @@
+use proc_macro2::TokenStream;
+use quote::*;
+use syn::spanned::Spanned;
+use syn::{DeriveInput, Error, Field, LitStr, Result};
+
+use crate::common::{get_ident_string, remove_r, struct_fields};
+
+#[derive(Default)]
+struct FieldOptions {
+    rename: Option<String>,
+    skip: bool,
+    none_as_unset: bool,
+}
+
+fn parse_field_options(field: &Field) -> Result<FieldOptions> {
+    let mut options = FieldOptions::default();
+
+    for attr in field
+        .attrs
+        .iter()
+        .filter(|attr| attr.path().is_ident("cdrs"))
+    {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("rename") {
+                let name: LitStr = meta.value()?.parse()?;
+                options.rename = Some(name.value());
+            } else if meta.path.is_ident("skip") {
+                options.skip = true;
+            } else if meta.path.is_ident("none") {
+                let mode: LitStr = meta.value()?.parse()?;
+                options.none_as_unset = match mode.value().as_str() {
+                    "null" => false,
+                    "unset" => true,
+                    _ => {
+                        return Err(Error::new(
+                            mode.span(),
+                            "Expected \"null\" or \"unset\" as the value of `none`",
+                        ))
+                    }
+                };
+            } else {
+                return Err(meta.error("Unsupported cdrs attribute"));
+            }
+
+            Ok(())
+        })?;
+    }
+
+    Ok(options)
+}
+
+// spanned on the type, so unsupported field types are reported at the field
+fn field_value(
+    field: &Field,
+    none_as_unset: bool,
+    is_option: bool,
+    access: TokenStream,
+) -> TokenStream {
+    if is_option {
+        let none = if none_as_unset {
+            quote! { cdrs_tokio::types::value::Value::NotSet }
+        } else {
+            quote! { cdrs_tokio::types::value::Value::Null }
+        };
+
+        quote_spanned! { field.ty.span() =>
+          match #access {
+            Some(field_value) => cdrs_tokio::types::value::Value::new(field_value),
+            None => #none,
+          }
+        }
+    } else {
+        quote_spanned! { field.ty.span() =>
+          cdrs_tokio::types::value::Value::new(#access)
+        }
+    }
+}
+
+pub fn impl_into_query_values(ast: &DeriveInput) -> Result<TokenStream> {
+    let name = &ast.ident;
+    let fields = struct_fields(ast)?;
+
+    let mut column_names = vec![];
+    let mut owned_values = vec![];
+    let mut borrowed_values = vec![];
+
+    for field in &fields.named {
+        let options = parse_field_options(field)?;
+        if options.skip {
+            continue;
+        }
+
+        let field_ident = field
+            .ident
+            .clone()
+            .ok_or_else(|| Error::new(field.span(), "Expected a named field!"))?;
+        let column_name = options
+            .rename
+            .unwrap_or_else(|| remove_r(field_ident.to_string()));
+
+        let is_option = get_ident_string(&field.ty, &column_name)? == "Option";
+        if options.none_as_unset && !is_option {
+            return Err(Error::new(
+                field.span(),
+                "`none` can only be used with Option fields",
+            ));
+        }
+
+        owned_values.push(field_value(
+            field,
+            options.none_as_unset,
+            is_option,
+            quote! { value.#field_ident },
+        ));
+        borrowed_values.push(field_value(
+            field,
+            options.none_as_unset,
+            is_option,
+            quote! { &self.#field_ident },
+        ));
+        column_names.push(column_name);
+    }
+
+    Ok(quote! {
+        #[automatically_derived]
+        impl From<#name> for cdrs_tokio::query::QueryValues {
+          fn from(value: #name) -> Self {
+            #[allow(unused_mut)]
+            let mut values = std::collections::HashMap::new();
+            #(values.insert(#column_names.to_string(), #owned_values);)*
+            cdrs_tokio::query::QueryValues::NamedValues(values)
+          }
+        }
+
+        #[automatically_derived]
+        impl cdrs_tokio::helpers::RowValues for #name {
+          fn columns() -> &'static [&'static str] {
+            &[#(#column_names),*]
+          }
+
+          fn row_values(&self) -> Vec<cdrs_tokio::types::value::Value> {
+            vec![#(#borrowed_values),*]
+          }
+        }
+    })
+}
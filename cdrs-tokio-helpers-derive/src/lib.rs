@@ -6,11 +6,13 @@ use syn::{parse_macro_input, DeriveInput, Error};
 mod common;
 mod db_mirror;
 mod into_cdrs_value;
+mod into_query_values;
 mod try_from_row;
 mod try_from_udt;
 
 use crate::db_mirror::impl_db_mirror;
 use crate::into_cdrs_value::impl_into_cdrs_value;
+use crate::into_query_values::impl_into_query_values;
 use crate::try_from_row::impl_try_from_row;
 use crate::try_from_udt::impl_try_from_udt;
 
@@ -25,7 +27,15 @@ pub fn db_mirror(input: TokenStream) -> TokenStream {
         .into()
 }
 
-#[proc_macro_derive(IntoCdrsValue)]
+/// Derives conversion into `Bytes`, serializing fields as a user defined type value in the
+/// order they are declared in the struct, along with conversion into `UdtValue`, which matches
+/// fields by name with the type definition from prepared statement metadata. Supported field
+/// attributes:
+///
+/// * `#[cdrs(rename = "field")]` - uses given name for the UDT field,
+/// * `#[cdrs(udt)]` - converts a nested struct into `UdtValue` as well, so its fields are also
+///   matched by name.
+#[proc_macro_derive(IntoCdrsValue, attributes(cdrs))]
 pub fn into_cdrs_value(input: TokenStream) -> TokenStream {
     // Parse the string representation
     let ast = parse_macro_input!(input as DeriveInput);
@@ -36,6 +46,23 @@ pub fn into_cdrs_value(input: TokenStream) -> TokenStream {
         .into()
 }
 
+/// Derives conversion into named `QueryValues`, with a value for every field, along with
+/// `RowValues` used for inserting rows. Supported field attributes:
+///
+/// * `#[cdrs(rename = "column")]` - binds the field under given name,
+/// * `#[cdrs(skip)]` - doesn't bind the field,
+/// * `#[cdrs(none = "unset")]` - binds `None` as an unset value, instead of the default `NULL`.
+#[proc_macro_derive(IntoQueryValues, attributes(cdrs))]
+pub fn into_query_values(input: TokenStream) -> TokenStream {
+    // Parse the string representation
+    let ast = parse_macro_input!(input as DeriveInput);
+
+    // Build the impl
+    impl_into_query_values(&ast)
+        .unwrap_or_else(Error::into_compile_error)
+        .into()
+}
+
 #[proc_macro_derive(TryFromRow)]
 pub fn try_from_row(input: TokenStream) -> TokenStream {
     // Parse the string representation
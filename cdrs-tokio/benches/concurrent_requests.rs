@@ -0,0 +1,144 @@
+//! Measures throughput of many small requests sent concurrently through a single connection,
+//! which exercises the per-connection write path: requests are queued by callers and encoded into
+//! frames by the connection's writer task. The server side is an in-memory stream answering every
+//! request with an empty result.
+//!
+//! Run with `cargo bench -p cdrs-tokio --bench concurrent_requests`.
+
+use cdrs_tokio::cluster::session::DEFAULT_TRANSPORT_BUFFER_SIZE;
+use cdrs_tokio::cluster::KeyspaceHolder;
+use cdrs_tokio::compression::Compression;
+use cdrs_tokio::frame::{Direction, Envelope, Flags, Opcode, Version};
+use cdrs_tokio::frame_encoding::{FrameEncodingFactory, ProtocolFrameEncodingFactory};
+use cdrs_tokio::transport::{CdrsTransport, TransportTcp};
+use std::convert::{TryFrom, TryInto};
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Instant;
+use tokio::io::{duplex, split, AsyncReadExt, AsyncWriteExt, BufReader, BufWriter, DuplexStream};
+use tokio::sync::{mpsc, watch};
+
+const REQUESTS: usize = 10_000;
+const ROUNDS: usize = 5;
+
+fn response(opcode: Opcode, stream_id: i16, body: Vec<u8>) -> Vec<u8> {
+    Envelope::new(
+        Version::V4,
+        Direction::Response,
+        Flags::empty(),
+        opcode,
+        stream_id,
+        body,
+        None,
+        vec![],
+    )
+    .encode_with(Compression::None)
+    .unwrap()
+}
+
+// answers the handshake with READY and every other request with a VOID result
+fn serve(server: DuplexStream) {
+    let (read_half, write_half) = split(server);
+    let (stream_id_sender, mut stream_id_receiver) = mpsc::unbounded_channel();
+
+    tokio::spawn(async move {
+        let mut read_half = BufReader::new(read_half);
+        let mut header = [0; 9];
+        while read_half.read_exact(&mut header).await.is_ok() {
+            let mut body = vec![0; i32::from_be_bytes(header[5..9].try_into().unwrap()) as usize];
+            if read_half.read_exact(&mut body).await.is_err() {
+                break;
+            }
+
+            let opcode = Opcode::try_from(header[4]).unwrap();
+            let stream_id = i16::from_be_bytes(header[2..4].try_into().unwrap());
+            if stream_id_sender.send((opcode, stream_id)).is_err() {
+                break;
+            }
+        }
+    });
+
+    tokio::spawn(async move {
+        let mut write_half = BufWriter::new(write_half);
+        while let Some((opcode, stream_id)) = stream_id_receiver.recv().await {
+            let envelope = if opcode == Opcode::Startup {
+                response(Opcode::Ready, stream_id, vec![])
+            } else {
+                response(Opcode::Result, stream_id, 1i32.to_be_bytes().to_vec())
+            };
+
+            if write_half.write_all(&envelope).await.is_err() {
+                break;
+            }
+
+            if stream_id_receiver.is_empty() && write_half.flush().await.is_err() {
+                break;
+            }
+        }
+    });
+}
+
+async fn connect() -> TransportTcp {
+    let (client, server) = duplex(64 * 1024);
+    serve(server);
+
+    let (keyspace_sender, _) = watch::channel(None);
+    let transport = TransportTcp::with_stream(
+        client,
+        SocketAddr::from(([127, 0, 0, 1], 9042)),
+        Arc::new(KeyspaceHolder::new(keyspace_sender)),
+        None,
+        None,
+        Compression::None,
+        0,
+        ProtocolFrameEncodingFactory.create_encoder(Version::V4, Compression::None),
+        ProtocolFrameEncodingFactory.create_decoder(Version::V4, Compression::None),
+        DEFAULT_TRANSPORT_BUFFER_SIZE,
+        None,
+        None,
+    )
+    .unwrap();
+
+    transport
+        .write_envelope(&Envelope::new_req_startup(None, Version::V4).unwrap(), true)
+        .await
+        .unwrap();
+
+    transport
+}
+
+async fn measure(transport: &Arc<TransportTcp>) {
+    let start = Instant::now();
+
+    let requests: Vec<_> = (0..REQUESTS)
+        .map(|_| {
+            let transport = transport.clone();
+            tokio::spawn(async move {
+                transport
+                    .write_envelope(&Envelope::new_req_options(Version::V4), false)
+                    .await
+                    .unwrap();
+            })
+        })
+        .collect();
+
+    for request in requests {
+        request.await.unwrap();
+    }
+
+    let elapsed = start.elapsed();
+    println!(
+        "{REQUESTS} concurrent requests: {:?} ({:.0} requests/s)",
+        elapsed,
+        REQUESTS as f64 / elapsed.as_secs_f64()
+    );
+}
+
+#[tokio::main]
+async fn main() {
+    let transport = Arc::new(connect().await);
+
+    for _ in 0..ROUNDS {
+        measure(&transport).await;
+    }
+}
@@ -0,0 +1,132 @@
+//! Measures throughput of requests with large responses, e.g. pages of big rows, which exercises
+//! the per-connection read path. Adaptive read buffers are compared with a fixed buffer of the size
+//! used before buffers became adaptive. The server side is an in-memory stream answering every
+//! request with a result of given size.
+//!
+//! Run with `cargo bench -p cdrs-tokio --bench large_responses`.
+
+use cdrs_tokio::cluster::session::DEFAULT_TRANSPORT_BUFFER_SIZE;
+use cdrs_tokio::cluster::KeyspaceHolder;
+use cdrs_tokio::compression::Compression;
+use cdrs_tokio::frame::{Direction, Envelope, Flags, Opcode, Version, MAX_FRAME_SIZE};
+use cdrs_tokio::frame_encoding::{FrameEncodingFactory, ProtocolFrameEncodingFactory};
+use cdrs_tokio::transport::{CdrsTransport, TransportTcp};
+use std::convert::{TryFrom, TryInto};
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Instant;
+use tokio::io::{duplex, split, AsyncReadExt, AsyncWriteExt, DuplexStream};
+use tokio::sync::watch;
+
+const REQUESTS: usize = 500;
+const RESPONSE_SIZES: [usize; 3] = [64 * 1024, 512 * 1024, 4 * 1024 * 1024];
+const STREAM_CAPACITY: usize = 8 * 1024 * 1024;
+
+fn response(opcode: Opcode, stream_id: i16, body: Vec<u8>) -> Vec<u8> {
+    Envelope::new(
+        Version::V4,
+        Direction::Response,
+        Flags::empty(),
+        opcode,
+        stream_id,
+        body,
+        None,
+        vec![],
+    )
+    .encode_with(Compression::None)
+    .unwrap()
+}
+
+// answers the handshake with READY and every other request with a VOID result padded to given size
+fn serve(server: DuplexStream, response_size: usize) {
+    let (mut read_half, mut write_half) = split(server);
+
+    tokio::spawn(async move {
+        let mut body = vec![0; response_size];
+        body[..4].copy_from_slice(&1i32.to_be_bytes());
+
+        let mut header = [0; 9];
+        while read_half.read_exact(&mut header).await.is_ok() {
+            let mut request_body =
+                vec![0; i32::from_be_bytes(header[5..9].try_into().unwrap()) as usize];
+            if read_half.read_exact(&mut request_body).await.is_err() {
+                break;
+            }
+
+            let opcode = Opcode::try_from(header[4]).unwrap();
+            let stream_id = i16::from_be_bytes(header[2..4].try_into().unwrap());
+            let envelope = if opcode == Opcode::Startup {
+                response(Opcode::Ready, stream_id, vec![])
+            } else {
+                response(Opcode::Result, stream_id, body.clone())
+            };
+
+            if write_half.write_all(&envelope).await.is_err() {
+                break;
+            }
+        }
+    });
+}
+
+async fn connect(response_size: usize, read_buffer_size: Option<usize>) -> TransportTcp {
+    let (client, server) = duplex(STREAM_CAPACITY);
+    serve(server, response_size);
+
+    let (keyspace_sender, _) = watch::channel(None);
+    let transport = TransportTcp::with_stream(
+        client,
+        SocketAddr::from(([127, 0, 0, 1], 9042)),
+        Arc::new(KeyspaceHolder::new(keyspace_sender)),
+        None,
+        None,
+        Compression::None,
+        0,
+        ProtocolFrameEncodingFactory.create_encoder(Version::V4, Compression::None),
+        ProtocolFrameEncodingFactory.create_decoder(Version::V4, Compression::None),
+        DEFAULT_TRANSPORT_BUFFER_SIZE,
+        read_buffer_size,
+        None,
+    )
+    .unwrap();
+
+    transport
+        .write_envelope(&Envelope::new_req_startup(None, Version::V4).unwrap(), true)
+        .await
+        .unwrap();
+
+    transport
+}
+
+async fn measure(response_size: usize, read_buffer_size: Option<usize>) {
+    let transport = connect(response_size, read_buffer_size).await;
+    let start = Instant::now();
+
+    for _ in 0..REQUESTS {
+        transport
+            .write_envelope(&Envelope::new_req_options(Version::V4), false)
+            .await
+            .unwrap();
+    }
+
+    let elapsed = start.elapsed();
+    println!(
+        "{} KiB responses, {} read buffer: {:?} ({:.0} MiB/s, final buffer size {} KiB)",
+        response_size / 1024,
+        if read_buffer_size.is_some() {
+            "fixed"
+        } else {
+            "adaptive"
+        },
+        elapsed,
+        (REQUESTS * response_size) as f64 / elapsed.as_secs_f64() / (1024.0 * 1024.0),
+        transport.read_buffer_size() / 1024,
+    );
+}
+
+#[tokio::main]
+async fn main() {
+    for response_size in RESPONSE_SIZES {
+        measure(response_size, Some(MAX_FRAME_SIZE)).await;
+        measure(response_size, None).await;
+    }
+}
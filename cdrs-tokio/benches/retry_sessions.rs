@@ -0,0 +1,175 @@
+//! Measures the per-request cost of retry sessions on the common path, where requests succeed
+//! without retrying. Sessions started eagerly for every request, as done before sessions became
+//! lazy, are compared with sessions started on the first failure. Heap allocations are counted
+//! along with throughput. The server side is an in-memory stream answering every request with an
+//! empty result.
+//!
+//! Run with `cargo bench -p cdrs-tokio --bench retry_sessions`.
+
+#[path = "../../cassandra-protocol/benches/common/mod.rs"]
+mod common;
+
+use cdrs_tokio::cluster::session::DEFAULT_TRANSPORT_BUFFER_SIZE;
+use cdrs_tokio::cluster::KeyspaceHolder;
+use cdrs_tokio::compression::Compression;
+use cdrs_tokio::frame::{Direction, Envelope, Flags, Opcode, Version};
+use cdrs_tokio::frame_encoding::{FrameEncodingFactory, ProtocolFrameEncodingFactory};
+use cdrs_tokio::retry::{
+    DefaultRetryPolicy, LazyRetrySession, QueryInfo, RetryDecision, RetryPolicy, RetrySession,
+};
+use cdrs_tokio::transport::{CdrsTransport, TransportTcp};
+use common::Measurement;
+use futures::stream::{self, StreamExt};
+use std::convert::{TryFrom, TryInto};
+use std::net::SocketAddr;
+use std::sync::Arc;
+use tokio::io::{duplex, split, AsyncReadExt, AsyncWriteExt, BufReader, BufWriter, DuplexStream};
+use tokio::sync::{mpsc, watch};
+
+const REQUESTS: usize = 100_000;
+const CONCURRENCY: usize = 256;
+const ROUNDS: usize = 5;
+
+fn response(opcode: Opcode, stream_id: i16, body: Vec<u8>) -> Vec<u8> {
+    Envelope::new(
+        Version::V4,
+        Direction::Response,
+        Flags::empty(),
+        opcode,
+        stream_id,
+        body,
+        None,
+        vec![],
+    )
+    .encode_with(Compression::None)
+    .unwrap()
+}
+
+// answers the handshake with READY and every other request with a VOID result
+fn serve(server: DuplexStream) {
+    let (read_half, write_half) = split(server);
+    let (stream_id_sender, mut stream_id_receiver) = mpsc::unbounded_channel();
+
+    tokio::spawn(async move {
+        let mut read_half = BufReader::new(read_half);
+        let mut header = [0; 9];
+        while read_half.read_exact(&mut header).await.is_ok() {
+            let mut body = vec![0; i32::from_be_bytes(header[5..9].try_into().unwrap()) as usize];
+            if read_half.read_exact(&mut body).await.is_err() {
+                break;
+            }
+
+            let opcode = Opcode::try_from(header[4]).unwrap();
+            let stream_id = i16::from_be_bytes(header[2..4].try_into().unwrap());
+            if stream_id_sender.send((opcode, stream_id)).is_err() {
+                break;
+            }
+        }
+    });
+
+    tokio::spawn(async move {
+        let mut write_half = BufWriter::new(write_half);
+        while let Some((opcode, stream_id)) = stream_id_receiver.recv().await {
+            let envelope = if opcode == Opcode::Startup {
+                response(Opcode::Ready, stream_id, vec![])
+            } else {
+                response(Opcode::Result, stream_id, 1i32.to_be_bytes().to_vec())
+            };
+
+            if write_half.write_all(&envelope).await.is_err() {
+                break;
+            }
+
+            if stream_id_receiver.is_empty() && write_half.flush().await.is_err() {
+                break;
+            }
+        }
+    });
+}
+
+async fn connect() -> TransportTcp {
+    let (client, server) = duplex(64 * 1024);
+    serve(server);
+
+    let (keyspace_sender, _) = watch::channel(None);
+    let transport = TransportTcp::with_stream(
+        client,
+        SocketAddr::from(([127, 0, 0, 1], 9042)),
+        Arc::new(KeyspaceHolder::new(keyspace_sender)),
+        None,
+        None,
+        Compression::None,
+        0,
+        ProtocolFrameEncodingFactory.create_encoder(Version::V4, Compression::None),
+        ProtocolFrameEncodingFactory.create_decoder(Version::V4, Compression::None),
+        DEFAULT_TRANSPORT_BUFFER_SIZE,
+        None,
+        None,
+    )
+    .unwrap();
+
+    transport
+        .write_envelope(&Envelope::new_req_startup(None, Version::V4).unwrap(), true)
+        .await
+        .unwrap();
+
+    transport
+}
+
+// sends a request the way the driver does, consulting the retry session only on failure
+async fn send(transport: &TransportTcp, mut retry_session: impl RetrySession) {
+    loop {
+        let error = match transport
+            .write_envelope(&Envelope::new_req_options(Version::V4), false)
+            .await
+        {
+            Ok(_) => return,
+            Err(error) => error,
+        };
+
+        let decision = retry_session.decide(QueryInfo {
+            error: &error,
+            is_idempotent: true,
+            batch_type: None,
+        });
+
+        if decision == RetryDecision::DontRetry {
+            panic!("{:?}", error);
+        }
+    }
+}
+
+async fn measure(transport: &TransportTcp, lazy: bool) {
+    let retry_policy = DefaultRetryPolicy;
+
+    let measurement = Measurement::start();
+
+    stream::iter(0..REQUESTS)
+        .for_each_concurrent(CONCURRENCY, |_| async {
+            if lazy {
+                send(transport, LazyRetrySession::new(&retry_policy)).await;
+            } else {
+                send(transport, retry_policy.new_session()).await;
+            }
+        })
+        .await;
+
+    let (allocations, elapsed) = measurement.finish();
+    println!(
+        "{REQUESTS} requests with {} retry sessions: {:?} ({:.0} requests/s, {:.2} allocations/request)",
+        if lazy { "lazy" } else { "eager" },
+        elapsed,
+        REQUESTS as f64 / elapsed.as_secs_f64(),
+        allocations as f64 / REQUESTS as f64
+    );
+}
+
+#[tokio::main]
+async fn main() {
+    let transport = connect().await;
+
+    for _ in 0..ROUNDS {
+        measure(&transport, false).await;
+        measure(&transport, true).await;
+    }
+}
@@ -1,10 +1,8 @@
 use cdrs_tokio::cluster::connection_pool::ConnectionPoolConfig;
 use cdrs_tokio::cluster::session::{
     NodeDistanceEvaluatorWrapper, ReconnectionPolicyWrapper, RetryPolicyWrapper,
-    DEFAULT_TRANSPORT_BUFFER_SIZE,
 };
-use cdrs_tokio::cluster::{ConnectionManager, KeyspaceHolder};
-use cdrs_tokio::compression::Compression;
+use cdrs_tokio::cluster::{ConnectionManager, ConnectionManagerConfigBuilder, KeyspaceHolder};
 use cdrs_tokio::frame::{Envelope, Version};
 use cdrs_tokio::frame_encoding::ProtocolFrameEncodingFactory;
 use cdrs_tokio::future::BoxFuture;
@@ -111,12 +109,7 @@ impl VirtualConnectionManager {
                 config.authenticator.clone(),
                 keyspace_holder,
                 Box::<ProtocolFrameEncodingFactory>::default(),
-                Compression::None,
-                DEFAULT_TRANSPORT_BUFFER_SIZE,
-                true,
-                config.version,
-                #[cfg(feature = "http-proxy")]
-                None,
+                ConnectionManagerConfigBuilder::new(config.version).build(),
             ),
             mask: config.mask,
             actual: config.actual,
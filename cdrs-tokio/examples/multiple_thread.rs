@@ -1,5 +1,5 @@
 use cdrs_tokio::authenticators::NoneAuthenticatorProvider;
-use cdrs_tokio::cluster::session::{Session, SessionBuilder, TcpSessionBuilder};
+use cdrs_tokio::cluster::session::{SessionBuilder, TcpSession, TcpSessionBuilder};
 use cdrs_tokio::cluster::{NodeTcpConfigBuilder, TcpConnectionManager};
 use cdrs_tokio::frame::TryFromRow;
 use cdrs_tokio::load_balancing::RoundRobinLoadBalancingStrategy;
@@ -9,11 +9,8 @@ use cdrs_tokio::transport::TransportTcp;
 use cdrs_tokio::{IntoCdrsValue, TryFromRow, TryFromUdt};
 use std::sync::Arc;
 
-type CurrentSession = Session<
-    TransportTcp,
-    TcpConnectionManager,
-    RoundRobinLoadBalancingStrategy<TransportTcp, TcpConnectionManager>,
->;
+type CurrentSession =
+    TcpSession<RoundRobinLoadBalancingStrategy<TransportTcp, TcpConnectionManager>>;
 
 #[tokio::main]
 async fn main() {
@@ -24,12 +21,11 @@ async fn main() {
         .await
         .unwrap();
     let lb = RoundRobinLoadBalancingStrategy::new();
-    let session: Arc<CurrentSession> = Arc::new(
-        TcpSessionBuilder::new(lb, cluster_config)
-            .build()
-            .await
-            .unwrap(),
-    );
+    // sessions are cheap to clone and clones share all state
+    let session: CurrentSession = TcpSessionBuilder::new(lb, cluster_config)
+        .build()
+        .await
+        .unwrap();
 
     create_keyspace(session.clone()).await;
     create_table(session.clone()).await;
@@ -62,7 +58,7 @@ struct User {
     username: String,
 }
 
-async fn create_keyspace(session: Arc<CurrentSession>) {
+async fn create_keyspace(session: CurrentSession) {
     let create_ks: &'static str = "CREATE KEYSPACE IF NOT EXISTS test_ks WITH REPLICATION = { \
                                    'class' : 'SimpleStrategy', 'replication_factor' : 1 };";
     session
@@ -71,7 +67,7 @@ async fn create_keyspace(session: Arc<CurrentSession>) {
         .expect("Keyspace creation error");
 }
 
-async fn create_table(session: Arc<CurrentSession>) {
+async fn create_table(session: CurrentSession) {
     let create_table_cql =
         "CREATE TABLE IF NOT EXISTS test_ks.multi_thread_table (key int PRIMARY KEY);";
     session
@@ -80,7 +76,7 @@ async fn create_table(session: Arc<CurrentSession>) {
         .expect("Table creation error");
 }
 
-async fn insert_struct(session: Arc<CurrentSession>, key: i32) {
+async fn insert_struct(session: CurrentSession, key: i32) {
     let row = RowStruct { key };
 
     let insert_struct_cql = "INSERT INTO test_ks.multi_thread_table (key) VALUES (?)";
@@ -90,7 +86,7 @@ async fn insert_struct(session: Arc<CurrentSession>, key: i32) {
         .expect("insert");
 }
 
-async fn select_struct(session: Arc<CurrentSession>) {
+async fn select_struct(session: CurrentSession) {
     let select_struct_cql = "SELECT * FROM test_ks.multi_thread_table";
     let rows = session
         .query(select_struct_cql)
@@ -116,8 +116,8 @@ async fn paged_selection_query(session: &CurrentSession) {
     let mut query_pager = pager.query(q);
 
     loop {
-        let rows = query_pager.next().await.expect("pager next");
-        for row in rows {
+        let page = query_pager.next_page().await.expect("pager next");
+        for row in page.into_rows() {
             let my_row = RowStruct::try_from_row(row).expect("decode row");
             println!("row - {my_row:?}");
         }
@@ -165,14 +165,14 @@ async fn paged_with_value(session: &CurrentSession) {
     // Oddly enough, this returns false the first time...
     assert!(!query_pager.has_more());
 
-    let rows = query_pager.next().await.expect("pager next");
-    assert_eq!(3, rows.len());
-    let rows = query_pager.next().await.expect("pager next");
-    assert_eq!(3, rows.len());
-    let rows = query_pager.next().await.expect("pager next");
-    assert_eq!(3, rows.len());
-    let rows = query_pager.next().await.expect("pager next");
-    assert_eq!(1, rows.len());
+    let page = query_pager.next_page().await.expect("pager next");
+    assert_eq!(3, page.row_count());
+    let page = query_pager.next_page().await.expect("pager next");
+    assert_eq!(3, page.row_count());
+    let page = query_pager.next_page().await.expect("pager next");
+    assert_eq!(3, page.row_count());
+    let page = query_pager.next_page().await.expect("pager next");
+    assert_eq!(1, page.row_count());
 
     assert!(!query_pager.has_more());
 }
@@ -190,9 +190,9 @@ async fn paged_with_values_list(session: &CurrentSession) {
     // Macro instead of a function or closure, since problem with lifetimes
     macro_rules! assert_amount_query_pager {
         ($row_amount: expr) => {{
-            let rows = query_pager.next().await.expect("pager next");
+            let page = query_pager.next_page().await.expect("pager next");
 
-            assert_eq!($row_amount, rows.len());
+            assert_eq!($row_amount, page.row_count());
         }};
     }
 
@@ -228,8 +228,8 @@ async fn paged_selection_query_with_state(session: &CurrentSession, state: Pager
         let mut pager = session.paged(2);
         let mut query_pager = pager.query_with_pager_state(q, st);
 
-        let rows = query_pager.next().await.expect("pager next");
-        for row in rows {
+        let page = query_pager.next_page().await.expect("pager next");
+        for row in page.into_rows() {
             let my_row = RowStruct::try_from_row(row).expect("decode row");
             println!("row - {my_row:?}");
         }
@@ -1,54 +1,110 @@
+pub use self::batch_split::{
+    BatchEntry, BatchSplitOptions, PartitionBatchResult, DEFAULT_BATCH_SPLIT_CONCURRENCY,
+};
 pub(crate) use self::cluster_metadata_manager::ClusterMetadataManager;
 #[cfg(feature = "http-proxy")]
 pub use self::config_proxy::{HttpProxyConfig, HttpProxyConfigBuilder};
 #[cfg(feature = "rust-tls")]
 pub use self::config_rustls::{NodeRustlsConfig, NodeRustlsConfigBuilder};
 pub use self::config_tcp::{NodeTcpConfig, NodeTcpConfigBuilder};
-pub use self::connection_manager::{startup, ConnectionManager};
+pub use self::connection_error::{ConnectionError, ConnectionPhase, WarmupError, WarmupReport};
+pub use self::connection_manager::{
+    startup, ConnectionManager, ConnectionManagerConfig, ConnectionManagerConfigBuilder,
+};
+pub use self::connection_string::{ConnectionString, ConnectionStringError, DEFAULT_PORT};
+pub use self::dyn_session::DynSession;
+pub use self::event_registration::RegistrationsRestored;
+pub use self::event_subscription::{EventSubscriptionBuilder, Subscription};
+pub use self::execute_concurrent::{ConcurrentErrorMode, ConcurrentExecutionError};
+pub use self::happy_eyeballs::{AddressFamilyPreference, DEFAULT_CONNECTION_STAGGER_DELAY};
+pub use self::init_statement::{initialize, InitStatement};
 pub use self::keyspace_holder::KeyspaceHolder;
+pub use self::keyspace_session::KeyspaceSession;
 pub use self::node_address::NodeAddress;
 pub use self::node_info::NodeInfo;
-pub use self::pager::{ExecPager, PagerState, QueryPager, SessionPager};
+pub use self::node_state_listener::NodeStateListener;
+pub use self::pager::{
+    DynSessionPager, ExecPager, Page, PageCheckpoint, PagerState, QueryPager, RowPager,
+    SessionPager, TypedPager,
+};
+pub use self::partition_grouper::{PartitionGrouper, PartitionKey};
+pub use self::pinned_session::PinnedSession;
+pub use self::prepare_all::{PrepareAllError, PrepareError, DEFAULT_PREPARE_CONCURRENCY};
+pub use self::prepared_cache::{
+    PreparedCacheEntry, PreparedCacheSnapshot, DEFAULT_PREPARED_CACHE_CAPACITY,
+};
+pub use self::prepared_metadata_listener::PreparedMetadataListener;
+pub use self::query_handle::QueryHandle;
 #[cfg(feature = "rust-tls")]
 pub use self::rustls_connection_manager::RustlsConnectionManager;
 pub use self::session::connect_generic;
 pub(crate) use self::session_context::SessionContext;
+pub use self::statement_request::StatementRequest;
+pub(crate) use self::statement_request::StatementTarget;
 pub use self::tcp_connection_manager::TcpConnectionManager;
-pub use self::token_map::TokenMap;
+pub use self::token_map::{TokenMap, TokenRange};
 pub use self::topology::cluster_metadata::ClusterMetadata;
+pub use self::warning_policy::{WarningAction, WarningClass, WarningPolicy};
 use crate::cluster::connection_pool::ConnectionPoolConfig;
+use crate::cluster::session::DEFAULT_SCHEMA_AGREEMENT_TIMEOUT;
 use crate::future::BoxFuture;
+use crate::retry::RetryBudget;
 use crate::transport::CdrsTransport;
+use cassandra_protocol::compression::CompressionMode;
+use cassandra_protocol::consistency::Consistency;
 use cassandra_protocol::error;
 use cassandra_protocol::frame::Version;
+use cassandra_protocol::query::Redactor;
 pub use cassandra_protocol::token::Murmur3Token;
 use std::sync::Arc;
+use std::time::Duration;
 
+mod batch_split;
 mod cluster_metadata_manager;
 #[cfg(feature = "http-proxy")]
 mod config_proxy;
 #[cfg(feature = "rust-tls")]
 mod config_rustls;
 mod config_tcp;
+mod connection_error;
 #[cfg(not(test))]
 mod connection_manager;
 #[cfg(test)]
 pub mod connection_manager;
 pub mod connection_pool;
+mod connection_string;
 mod control_connection;
+mod dyn_session;
+mod event_registration;
+mod event_subscription;
+mod execute_concurrent;
+mod happy_eyeballs;
+mod init_statement;
 mod keyspace_holder;
+mod keyspace_session;
 mod metadata_builder;
 mod node_address;
 mod node_info;
+mod node_state_listener;
 mod pager;
+mod partition_grouper;
+mod pinned_session;
+mod prepare_all;
+mod prepared_cache;
+mod prepared_metadata_listener;
+mod query_handle;
 #[cfg(feature = "rust-tls")]
 mod rustls_connection_manager;
 pub mod send_envelope;
 pub mod session;
 mod session_context;
+mod statement_request;
 mod tcp_connection_manager;
+#[cfg(test)]
+pub(crate) mod test_nodes;
 mod token_map;
 pub mod topology;
+mod warning_policy;
 
 /// Generic connection configuration trait that can be used to create user-supplied
 /// connection objects that can be used with the `session::connect()` function.
@@ -69,4 +125,97 @@ pub trait GenericClusterConfig<T: CdrsTransport, CM: ConnectionManager<T>>: Send
     fn beta_protocol(&self) -> bool {
         false
     }
+
+    /// Disable checking values bound to prepared statements against their parameter types.
+    fn lenient_conversions(&self) -> bool {
+        false
+    }
+
+    /// Enable the debug assertion for tables not qualified with a keyspace.
+    fn keyspace_qualification_check(&self) -> bool {
+        false
+    }
+
+    /// Reject `USE` statements sent through the session.
+    fn keyspace_lock(&self) -> bool {
+        false
+    }
+
+    /// Redactor hiding sensitive values of logged statements.
+    fn redactor(&self) -> Redactor {
+        Default::default()
+    }
+
+    /// Time to wait for schema agreement after schema changes.
+    fn schema_agreement_timeout(&self) -> Duration {
+        DEFAULT_SCHEMA_AGREEMENT_TIMEOUT
+    }
+
+    /// Route unprepared statements using partition key values found in statement text.
+    fn unprepared_routing(&self) -> bool {
+        false
+    }
+
+    /// Fail requests immediately when no node in their query plan has a live connection.
+    fn fail_fast_when_disconnected(&self) -> bool {
+        false
+    }
+
+    /// Fail executions with positional values when bound variables change after re-preparing.
+    fn strict_prepared_metadata(&self) -> bool {
+        false
+    }
+
+    /// Listener notified about changed result metadata of prepared statements.
+    fn prepared_metadata_listener(
+        &self,
+    ) -> Option<Arc<dyn PreparedMetadataListener + Send + Sync>> {
+        None
+    }
+
+    /// Listener notified about connection progress and state changes of nodes.
+    fn node_state_listener(&self) -> Option<Arc<dyn NodeStateListener + Send + Sync>> {
+        None
+    }
+
+    /// Compression mode reported by [`Session::compression_mode`](session::Session::compression_mode).
+    /// Compression is configured by the connection manager, so it's unknown by default.
+    fn compression_mode(&self) -> Option<CompressionMode> {
+        None
+    }
+
+    /// Policy applied to server warnings.
+    fn warning_policy(&self) -> WarningPolicy {
+        Default::default()
+    }
+
+    /// Session-wide retry budget. There is no budget by default.
+    fn retry_budget(&self) -> Option<RetryBudget> {
+        None
+    }
+
+    /// Fraction of requests traced regardless of their statement parameters.
+    fn tracing_sample_rate(&self) -> f64 {
+        0.0
+    }
+
+    /// Use OPTIONS requests for heartbeats and idle connection verification.
+    fn options_probe(&self) -> bool {
+        true
+    }
+
+    /// Consistency of queries the driver sends on its own.
+    fn system_query_consistency(&self) -> Consistency {
+        Consistency::One
+    }
+
+    /// Statements prepared by another session to preload.
+    fn prepared_cache_snapshot(&self) -> Option<PreparedCacheSnapshot> {
+        None
+    }
+
+    /// Maximum number of statements kept in the prepared statement cache.
+    fn prepared_cache_capacity(&self) -> usize {
+        DEFAULT_PREPARED_CACHE_CAPACITY
+    }
 }
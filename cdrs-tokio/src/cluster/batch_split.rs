@@ -0,0 +1,390 @@
+use cassandra_protocol::consistency::Consistency;
+use cassandra_protocol::error;
+use cassandra_protocol::frame::message_batch::{BatchQuery, BatchQuerySubj};
+use cassandra_protocol::frame::{Envelope, Version};
+use cassandra_protocol::query::{PreparedQuery, QueryValues, MAX_BATCH_STATEMENTS};
+use cassandra_protocol::types::value::Value;
+use cassandra_protocol::types::CLong;
+use fxhash::FxHashMap;
+
+use crate::cluster::session::{serialize_routing_key, serialize_routing_key_with_indexes};
+use crate::statement::{StatementAnalysis, StatementParams};
+
+/// Default number of sub-batches sent at the same time by
+/// [`Session::batch_split_by_partition`](crate::cluster::session::Session::batch_split_by_partition).
+pub const DEFAULT_BATCH_SPLIT_CONCURRENCY: usize = 8;
+
+#[derive(Debug, Clone)]
+enum BatchEntrySubject<'a> {
+    Prepared(&'a PreparedQuery),
+    Query(String),
+}
+
+/// Statement to be sent in a batch split by partition, along with information needed to find its
+/// partition.
+#[derive(Debug, Clone)]
+pub struct BatchEntry<'a> {
+    subject: BatchEntrySubject<'a>,
+    values: QueryValues,
+    routing_key: Option<Vec<Value>>,
+}
+
+impl<'a> BatchEntry<'a> {
+    /// Creates an entry for a prepared statement. Its partition is determined from bound partition
+    /// key values, falling back to the explicit routing key, if set.
+    pub fn prepared<V: Into<QueryValues>>(prepared: &'a PreparedQuery, values: V) -> Self {
+        BatchEntry {
+            subject: BatchEntrySubject::Prepared(prepared),
+            values: values.into(),
+            routing_key: None,
+        }
+    }
+
+    /// Creates an entry for a non-prepared query. Its partition is only known if an explicit
+    /// routing key is set.
+    pub fn query<Q: Into<String>, V: Into<QueryValues>>(query: Q, values: V) -> Self {
+        BatchEntry {
+            subject: BatchEntrySubject::Query(query.into()),
+            values: values.into(),
+            routing_key: None,
+        }
+    }
+
+    /// Sets partition key values of the statement.
+    #[must_use]
+    pub fn with_routing_key(mut self, routing_key: Vec<Value>) -> Self {
+        self.routing_key = Some(routing_key);
+        self
+    }
+
+    fn serialized_routing_key(&self, version: Version) -> Option<Vec<u8>> {
+        let from_prepared = match (&self.subject, &self.values) {
+            (BatchEntrySubject::Prepared(prepared), QueryValues::SimpleValues(values)) => {
+                serialize_routing_key_with_indexes(values, &prepared.pk_indexes, version)
+            }
+            _ => None,
+        };
+
+        from_prepared.or_else(|| {
+            self.routing_key
+                .as_ref()
+                .map(|routing_key| serialize_routing_key(routing_key, version))
+        })
+    }
+
+    fn keyspace(&self) -> Option<&str> {
+        match &self.subject {
+            BatchEntrySubject::Prepared(prepared) => prepared.keyspace.as_deref(),
+            BatchEntrySubject::Query(_) => None,
+        }
+    }
+
+    fn query_text(&self) -> &str {
+        match &self.subject {
+            BatchEntrySubject::Prepared(prepared) => &prepared.query,
+            BatchEntrySubject::Query(query) => query,
+        }
+    }
+
+    fn partition_id(&self, version: Version) -> Option<PartitionId> {
+        let routing_key = self.serialized_routing_key(version)?;
+        let query = self.query_text();
+
+        let (keyspace, table) = match StatementAnalysis::analyze(query) {
+            Some(analysis) => (
+                self.keyspace().or(analysis.keyspace()).map(str::to_string),
+                analysis.table().to_string(),
+            ),
+            None => (self.keyspace().map(str::to_string), query.to_string()),
+        };
+
+        Some(PartitionId {
+            keyspace,
+            table,
+            routing_key,
+        })
+    }
+
+    fn into_batch_query(self) -> BatchQuery {
+        let subject = match self.subject {
+            BatchEntrySubject::Prepared(prepared) => {
+                BatchQuerySubj::PreparedId(prepared.id.clone())
+            }
+            BatchEntrySubject::Query(query) => BatchQuerySubj::QueryString(query),
+        };
+
+        BatchQuery {
+            subject,
+            values: self.values,
+        }
+    }
+}
+
+/// Options of batches split by partition. Apart from limits, they apply to all sub-batches.
+#[derive(Debug, Clone)]
+pub struct BatchSplitOptions {
+    pub(crate) concurrency: usize,
+    pub(crate) max_group_statements: usize,
+    pub(crate) consistency: Consistency,
+    pub(crate) serial_consistency: Option<Consistency>,
+    pub(crate) timestamp: Option<CLong>,
+    pub(crate) parameters: StatementParams,
+}
+
+impl Default for BatchSplitOptions {
+    fn default() -> Self {
+        BatchSplitOptions {
+            concurrency: DEFAULT_BATCH_SPLIT_CONCURRENCY,
+            max_group_statements: MAX_BATCH_STATEMENTS,
+            consistency: Consistency::One,
+            serial_consistency: None,
+            timestamp: None,
+            parameters: Default::default(),
+        }
+    }
+}
+
+impl BatchSplitOptions {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Sets the maximum number of sub-batches sent at the same time.
+    #[must_use]
+    pub fn with_concurrency(mut self, concurrency: usize) -> Self {
+        self.concurrency = concurrency;
+        self
+    }
+
+    /// Sets the maximum number of statements in a sub-batch. Partitions with more statements are
+    /// sent as multiple sub-batches.
+    #[must_use]
+    pub fn with_max_group_statements(mut self, max_group_statements: usize) -> Self {
+        self.max_group_statements = max_group_statements.clamp(1, MAX_BATCH_STATEMENTS);
+        self
+    }
+
+    #[must_use]
+    pub fn with_consistency(mut self, consistency: Consistency) -> Self {
+        self.consistency = consistency;
+        self
+    }
+
+    #[must_use]
+    pub fn with_serial_consistency(mut self, serial_consistency: Consistency) -> Self {
+        self.serial_consistency = Some(serial_consistency);
+        self
+    }
+
+    #[must_use]
+    pub fn with_timestamp(mut self, timestamp: CLong) -> Self {
+        self.timestamp = Some(timestamp);
+        self
+    }
+
+    /// Sets statement parameters used to send sub-batches, e.g. idempotency or the retry policy.
+    /// Routing information and values are ignored.
+    #[must_use]
+    pub fn with_parameters(mut self, parameters: StatementParams) -> Self {
+        self.parameters = parameters;
+        self
+    }
+}
+
+/// Result of a single sub-batch of a batch split by partition.
+#[derive(Debug)]
+pub struct PartitionBatchResult {
+    /// Serialized routing key of the partition, or `None` for the group of entries without routing
+    /// information.
+    pub routing_key: Option<Vec<u8>>,
+    /// Indexes of entries sent in the sub-batch.
+    pub indexes: Vec<usize>,
+    /// Result of the sub-batch.
+    pub result: error::Result<Envelope>,
+}
+
+// the same routing key belongs to different partitions in different tables, so entries are grouped
+// by table as well - entries of statements which can't be analyzed are only assumed to share a
+// table with entries of the same statement
+#[derive(Clone, PartialEq, Eq, Hash)]
+struct PartitionId {
+    keyspace: Option<String>,
+    table: String,
+    routing_key: Vec<u8>,
+}
+
+/// Entries of a single partition, ready to be sent as a sub-batch.
+#[derive(Debug)]
+pub(crate) struct PartitionGroup {
+    pub(crate) routing_key: Option<Vec<u8>>,
+    pub(crate) keyspace: Option<String>,
+    pub(crate) indexes: Vec<usize>,
+    pub(crate) queries: Vec<BatchQuery>,
+}
+
+/// Groups entries by partition, i.e. keyspace, table and routing key, in order of first
+/// appearance, with entries lacking routing information in a single fallback group. Groups are
+/// split further, so none exceeds `max_group_statements`.
+pub(crate) fn group_by_partition<'a>(
+    entries: impl IntoIterator<Item = BatchEntry<'a>>,
+    max_group_statements: usize,
+    version: Version,
+) -> Vec<PartitionGroup> {
+    let max_group_statements = max_group_statements.max(1);
+
+    let mut groups: Vec<PartitionGroup> = vec![];
+    let mut open_groups: FxHashMap<Option<PartitionId>, usize> = FxHashMap::default();
+
+    for (index, entry) in entries.into_iter().enumerate() {
+        let partition_id = entry.partition_id(version);
+
+        let group_index = match open_groups.get(&partition_id) {
+            Some(group_index) if groups[*group_index].queries.len() < max_group_statements => {
+                *group_index
+            }
+            _ => {
+                open_groups.insert(partition_id.clone(), groups.len());
+
+                let (routing_key, keyspace) = match partition_id {
+                    Some(PartitionId {
+                        keyspace,
+                        routing_key,
+                        ..
+                    }) => (Some(routing_key), keyspace),
+                    None => (None, None),
+                };
+
+                groups.push(PartitionGroup {
+                    routing_key,
+                    keyspace,
+                    indexes: vec![],
+                    queries: vec![],
+                });
+
+                groups.len() - 1
+            }
+        };
+
+        let group = &mut groups[group_index];
+        if group.keyspace.is_none() {
+            group.keyspace = entry.keyspace().map(str::to_string);
+        }
+
+        group.indexes.push(index);
+        group.queries.push(entry.into_batch_query());
+    }
+
+    groups
+}
+
+#[cfg(test)]
+mod tests {
+    use arc_swap::ArcSwapOption;
+    use cassandra_protocol::frame::message_batch::BatchQuerySubj;
+    use cassandra_protocol::frame::Version;
+    use cassandra_protocol::query::{PreparedQuery, QueryValues};
+    use cassandra_protocol::types::value::Value;
+    use cassandra_protocol::types::CBytesShort;
+
+    use super::{group_by_partition, BatchEntry};
+
+    fn prepared() -> PreparedQuery {
+        PreparedQuery::new(
+            CBytesShort::new(vec![1]),
+            "INSERT INTO ks.t (id, value) VALUES (?, ?)".into(),
+            Some("ks".into()),
+            vec![0],
+            ArcSwapOption::empty(),
+        )
+    }
+
+    fn values(id: i32) -> QueryValues {
+        QueryValues::SimpleValues(vec![Value::new(id), Value::new("value")])
+    }
+
+    #[test]
+    fn should_group_entries_by_partition() {
+        let prepared = prepared();
+        let entries = vec![
+            BatchEntry::prepared(&prepared, values(1)),
+            BatchEntry::prepared(&prepared, values(2)),
+            BatchEntry::query(
+                "INSERT INTO ks.t (id) VALUES (3)",
+                QueryValues::SimpleValues(vec![]),
+            ),
+            BatchEntry::prepared(&prepared, values(1)),
+            BatchEntry::query(
+                "INSERT INTO ks.t (id) VALUES (2)",
+                QueryValues::SimpleValues(vec![]),
+            )
+            .with_routing_key(vec![Value::new(2)]),
+        ];
+
+        let groups = group_by_partition(entries, 10, Version::V4);
+
+        assert_eq!(groups.len(), 3);
+        assert_eq!(groups[0].indexes, vec![0, 3]);
+        assert_eq!(groups[0].keyspace.as_deref(), Some("ks"));
+        assert_eq!(groups[0].routing_key, Some(1i32.to_be_bytes().to_vec()));
+        assert_eq!(groups[1].indexes, vec![1, 4]);
+        assert_eq!(groups[2].indexes, vec![2]);
+        assert_eq!(groups[2].routing_key, None);
+        assert!(matches!(
+            groups[2].queries[0].subject,
+            BatchQuerySubj::QueryString(_)
+        ));
+    }
+
+    #[test]
+    fn should_group_entries_by_table() {
+        let prepared = prepared();
+        let mut other_table = prepared.clone();
+        other_table.query = "INSERT INTO ks.other (id, value) VALUES (?, ?)".into();
+        let mut other_keyspace = prepared.clone();
+        other_keyspace.keyspace = Some("other".into());
+        other_keyspace.query = "INSERT INTO other.t (id, value) VALUES (?, ?)".into();
+        let entries = vec![
+            BatchEntry::prepared(&prepared, values(1)),
+            BatchEntry::prepared(&other_table, values(1)),
+            BatchEntry::prepared(&other_keyspace, values(1)),
+            BatchEntry::query("INSERT INTO ks.t (id, value) VALUES (?, ?)", values(1))
+                .with_routing_key(vec![Value::new(1)]),
+            BatchEntry::query(
+                "/* unknown table */ INSERT INTO ks.t (id, value) VALUES (?, ?)",
+                values(1),
+            )
+            .with_routing_key(vec![Value::new(1)]),
+        ];
+
+        let groups = group_by_partition(entries, 10, Version::V4);
+
+        assert_eq!(
+            groups
+                .iter()
+                .map(|group| group.indexes.clone())
+                .collect::<Vec<_>>(),
+            vec![vec![0, 3], vec![1], vec![2], vec![4]]
+        );
+        assert_eq!(groups[2].keyspace.as_deref(), Some("other"));
+        assert_eq!(groups[3].keyspace, None);
+    }
+
+    #[test]
+    fn should_cap_group_size() {
+        let prepared = prepared();
+        let entries = (0..5).map(|_| BatchEntry::prepared(&prepared, values(1)));
+
+        let groups = group_by_partition(entries, 2, Version::V4);
+
+        assert_eq!(
+            groups
+                .iter()
+                .map(|group| group.indexes.clone())
+                .collect::<Vec<_>>(),
+            vec![vec![0, 1], vec![2, 3], vec![4]]
+        );
+        assert!(groups
+            .iter()
+            .all(|group| group.routing_key == Some(1i32.to_be_bytes().to_vec())));
+    }
+}
@@ -1,4 +1,5 @@
 use arc_swap::ArcSwap;
+use cassandra_protocol::consistency::Consistency;
 use cassandra_protocol::error::{Error, Result};
 use cassandra_protocol::events::{SchemaChange, ServerEvent};
 use cassandra_protocol::frame::events::{
@@ -20,18 +21,24 @@ use std::convert::TryInto;
 use std::net::{IpAddr, SocketAddr};
 use std::str::FromStr;
 use std::sync::atomic::{AtomicBool, Ordering};
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
 use tokio::sync::broadcast::error::RecvError;
 use tokio::sync::broadcast::Receiver;
 use tracing::*;
+use uuid::Uuid;
 
 use crate::cluster::connection_pool::ConnectionPoolFactory;
-use crate::cluster::metadata_builder::{add_new_node, build_initial_metadata, refresh_metadata};
+use crate::cluster::metadata_builder::{
+    add_new_node, build_initial_metadata, refresh_metadata, replace_node,
+};
+use crate::cluster::prepared_cache::PreparedCache;
+use crate::cluster::session::prepare_flags;
 use crate::cluster::topology::{KeyspaceMetadata, Node, NodeState, ReplicationStrategy};
 use crate::cluster::Murmur3Token;
 use crate::cluster::{ClusterMetadata, ConnectionManager};
 use crate::cluster::{NodeInfo, SessionContext};
 use crate::load_balancing::node_distance_evaluator::NodeDistanceEvaluator;
+use crate::runtime::TaskTracker;
 use crate::transport::CdrsTransport;
 
 fn find_in_peers(
@@ -51,13 +58,18 @@ fn find_in_peers(
         .transpose()
 }
 
+// system queries are sent directly to the control connection, so they are never affected by
+// retry policies or other statement defaults of the session
 async fn send_query<T: CdrsTransport>(
     query: &str,
     transport: &T,
     version: Version,
     beta_protocol: bool,
+    consistency: Consistency,
 ) -> Result<Option<Vec<Row>>> {
-    let query_params = QueryParamsBuilder::new().build();
+    let query_params = QueryParamsBuilder::new()
+        .with_consistency(consistency)
+        .build();
     send_query_with_params(query, query_params, transport, version, beta_protocol).await
 }
 
@@ -67,8 +79,12 @@ async fn send_query_with_values<T: CdrsTransport, V: Into<QueryValues>>(
     transport: &T,
     version: Version,
     beta_protocol: bool,
+    consistency: Consistency,
 ) -> Result<Option<Vec<Row>>> {
-    let query_params = QueryParamsBuilder::new().with_values(values.into()).build();
+    let query_params = QueryParamsBuilder::new()
+        .with_consistency(consistency)
+        .with_values(values.into())
+        .build();
     send_query_with_params(query, query_params, transport, version, beta_protocol).await
 }
 
@@ -90,7 +106,7 @@ async fn send_query_with_params<T: CdrsTransport>(
         Flags::empty()
     };
 
-    let envelope = Envelope::new_query(query, flags, version);
+    let envelope = Envelope::new_query(query, flags, version)?;
 
     transport
         .write_envelope(&envelope, false)
@@ -209,8 +225,8 @@ fn broadcast_rpc_address_from_row(row: &Row, control_addr: SocketAddr) -> Option
 
 fn is_peer_row_valid(row: &Row) -> bool {
     let has_peers_rpc_address = !row.is_empty_by_name("rpc_address");
-    let has_peers_v_2_rpc_address =
-        !row.is_empty_by_name("native_address") && !row.is_empty_by_name("native_port");
+    // a missing native port falls back to the control connection port
+    let has_peers_v_2_rpc_address = !row.is_empty_by_name("native_address");
     let has_rpc_address = has_peers_rpc_address || has_peers_v_2_rpc_address;
 
     has_rpc_address
@@ -221,17 +237,43 @@ fn is_peer_row_valid(row: &Row) -> bool {
         && !row.is_empty_by_name("schema_version")
 }
 
+// nodes which have not reported their schema version yet can't disagree
+fn is_schema_in_agreement(schema_versions: impl IntoIterator<Item = Option<Uuid>>) -> bool {
+    schema_versions.into_iter().flatten().unique().count() <= 1
+}
+
+// the same node can be reported more than once, e.g. by a stale peer entry during host
+// replacement - the first entry wins, so the control node's own info takes precedence
+fn deduplicate_node_infos(node_infos: Vec<NodeInfo>) -> Vec<NodeInfo> {
+    let node_count = node_infos.len();
+    let node_infos = node_infos
+        .into_iter()
+        .unique_by(|node_info| node_info.host_id)
+        .collect_vec();
+
+    if node_infos.len() != node_count {
+        warn!(
+            duplicates = node_count - node_infos.len(),
+            "Ignoring duplicate node entries in system tables."
+        );
+    }
+
+    node_infos
+}
+
 async fn fetch_control_connection_info<T: CdrsTransport>(
     control_transport: &T,
     control_addr: &SocketAddr,
     version: Version,
     beta_protocol: bool,
+    consistency: Consistency,
 ) -> Result<Row> {
     send_query(
         "SELECT * FROM system.local",
         control_transport,
         version,
         beta_protocol,
+        consistency,
     )
     .await?
     .and_then(|mut rows| rows.pop())
@@ -241,7 +283,17 @@ async fn fetch_control_connection_info<T: CdrsTransport>(
 fn build_keyspace(row: &Row) -> Result<(String, KeyspaceMetadata)> {
     let keyspace_name = row.get_r_by_name("keyspace_name")?;
 
-    let replication: String = row.get_r_by_name("replication")?;
+    // system.schema_keyspaces (Cassandra < 3.0) stores the class separately from options
+    let (replication, legacy_class): (String, Option<String>) =
+        if row.contains_column("strategy_class") {
+            (
+                row.get_r_by_name("strategy_options")?,
+                Some(row.get_r_by_name("strategy_class")?),
+            )
+        } else {
+            (row.get_r_by_name("replication")?, None)
+        };
+
     let replication: JsonValue = serde_json::from_str(&replication).map_err(|error| {
         Error::General(format!(
             "Error parsing replication for {keyspace_name}: {error}"
@@ -249,7 +301,13 @@ fn build_keyspace(row: &Row) -> Result<(String, KeyspaceMetadata)> {
     })?;
 
     let replication_strategy = match replication {
-        JsonValue::Object(properties) => build_replication_strategy(properties)?,
+        JsonValue::Object(mut properties) => {
+            if let Some(class) = legacy_class {
+                properties.insert("class".into(), JsonValue::String(class));
+            }
+
+            build_replication_strategy(properties)?
+        }
         _ => {
             return Err(Error::InvalidReplicationFormat {
                 keyspace: keyspace_name,
@@ -279,7 +337,13 @@ fn build_replication_strategy(
                     )?,
                 }
             }
-            _ => ReplicationStrategy::Other,
+            "org.apache.cassandra.locator.LocalStrategy" | "LocalStrategy" => {
+                ReplicationStrategy::LocalStrategy
+            }
+            _ => ReplicationStrategy::Other {
+                options: extract_options(properties),
+                class,
+            },
         }),
         _ => Err("Missing replication strategy class!".into()),
     }
@@ -297,6 +361,16 @@ fn extract_datacenter_replication_factor(
         .try_collect()
 }
 
+fn extract_options(properties: Map<String, JsonValue>) -> FxHashMap<String, String> {
+    properties
+        .into_iter()
+        .map(|(key, value)| match value {
+            JsonValue::String(value) => (key, value),
+            value => (key, value.to_string()),
+        })
+        .collect()
+}
+
 fn extract_replication_factor(value: Option<&JsonValue>) -> Result<usize> {
     match value {
         Some(JsonValue::String(replication_factor)) => {
@@ -314,6 +388,9 @@ fn extract_replication_factor(value: Option<&JsonValue>) -> Result<usize> {
     }
 }
 
+// partition key columns by keyspace and table name
+type PartitionKeys = FxHashMap<(String, String), Arc<[String]>>;
+
 pub(crate) struct ClusterMetadataManager<
     T: CdrsTransport + 'static,
     CM: ConnectionManager<T> + 'static,
@@ -322,38 +399,52 @@ pub(crate) struct ClusterMetadataManager<
     contact_points: Vec<Arc<Node<T, CM>>>,
     connection_pool_factory: Arc<ConnectionPoolFactory<T, CM>>,
     did_initial_refresh: AtomicBool,
-    is_schema_v2: AtomicBool,
+    // control node known to lack system.peers_v2 - probed again when the control connection
+    // moves, since nodes get upgraded one by one
+    legacy_peers_node: Mutex<Option<SocketAddr>>,
+    has_system_schema: AtomicBool,
+    // used for routing unprepared statements - empty for unknown tables
+    partition_keys: Mutex<PartitionKeys>,
     session_context: Arc<SessionContext<T>>,
     node_distance_evaluator: Box<dyn NodeDistanceEvaluator + Send + Sync>,
+    prepared_cache: Arc<PreparedCache>,
     version: Version,
     beta_protocol: bool,
+    system_query_consistency: Consistency,
 }
 
 impl<T: CdrsTransport + 'static, CM: ConnectionManager<T> + 'static> ClusterMetadataManager<T, CM> {
+    #[allow(clippy::too_many_arguments)]
     pub(crate) fn new(
         contact_points: Vec<Arc<Node<T, CM>>>,
         connection_pool_factory: Arc<ConnectionPoolFactory<T, CM>>,
         session_context: Arc<SessionContext<T>>,
         node_distance_evaluator: Box<dyn NodeDistanceEvaluator + Send + Sync>,
+        prepared_cache: Arc<PreparedCache>,
         version: Version,
         beta_protocol: bool,
+        system_query_consistency: Consistency,
     ) -> Self {
         ClusterMetadataManager {
             metadata: ArcSwap::from_pointee(ClusterMetadata::default()),
             contact_points,
             connection_pool_factory,
             did_initial_refresh: AtomicBool::new(false),
-            is_schema_v2: AtomicBool::new(true),
+            legacy_peers_node: Default::default(),
+            has_system_schema: AtomicBool::new(true),
+            partition_keys: Default::default(),
             session_context,
             node_distance_evaluator,
+            prepared_cache,
             version,
             beta_protocol,
+            system_query_consistency,
         }
     }
 
     pub(crate) fn listen_to_events(self: &Arc<Self>, mut event_receiver: Receiver<ServerEvent>) {
         let cmm = Arc::downgrade(self);
-        tokio::spawn(async move {
+        self.task_tracker().spawn(async move {
             loop {
                 let event = event_receiver.recv().await;
                 match event {
@@ -385,6 +476,8 @@ impl<T: CdrsTransport + 'static, CM: ConnectionManager<T> + 'static> ClusterMeta
     }
 
     async fn process_schema_event(&self, event: SchemaChange) {
+        self.invalidate_partition_keys(&event);
+
         if let SchemaChangeOptions::Keyspace(keyspace) = &event.options {
             match event.change_type {
                 SchemaChangeType::Created | SchemaChangeType::Updated => {
@@ -402,11 +495,16 @@ impl<T: CdrsTransport + 'static, CM: ConnectionManager<T> + 'static> ClusterMeta
         let metadata = self.metadata.load().clone();
         match event.change_type {
             TopologyChangeType::NewNode => {
-                if metadata.has_node_by_rpc_address(event.addr) {
-                    debug!(
-                        broadcast_rpc_address = %event.addr,
-                        "Trying to add already existing node - ignoring."
-                    );
+                if let Some(node) = metadata.find_node_by_rpc_address(event.addr) {
+                    if !self
+                        .replace_changed_node(&node, NodeState::Unknown, &metadata)
+                        .await
+                    {
+                        debug!(
+                            broadcast_rpc_address = %event.addr,
+                            "Trying to add already existing node - ignoring."
+                        );
+                    }
                 } else {
                     self.add_new_node(event.addr, NodeState::Unknown, metadata)
                         .await;
@@ -435,6 +533,13 @@ impl<T: CdrsTransport + 'static, CM: ConnectionManager<T> + 'static> ClusterMeta
         match event.change_type {
             StatusChangeType::Up => {
                 if let Some(node) = node {
+                    if self
+                        .replace_changed_node(&node, NodeState::Up, &metadata)
+                        .await
+                    {
+                        return;
+                    }
+
                     if node.state() != NodeState::Up {
                         debug!(?node, "Setting existing node state to up.");
 
@@ -475,6 +580,21 @@ impl<T: CdrsTransport + 'static, CM: ConnectionManager<T> + 'static> ClusterMeta
         }
     }
 
+    fn invalidate_partition_keys(&self, event: &SchemaChange) {
+        let mut partition_keys = self.partition_keys.lock().unwrap();
+        match &event.options {
+            SchemaChangeOptions::TableType(keyspace, table) => {
+                partition_keys.remove(&(keyspace.clone(), table.clone()));
+            }
+            SchemaChangeOptions::Keyspace(keyspace)
+                if event.change_type == SchemaChangeType::Dropped =>
+            {
+                partition_keys.retain(|(table_keyspace, _), _| table_keyspace != keyspace);
+            }
+            _ => {}
+        }
+    }
+
     fn remove_keyspace(&self, keyspace: &str) {
         let metadata = self.metadata.load().clone();
         self.metadata
@@ -491,32 +611,26 @@ impl<T: CdrsTransport + 'static, CM: ConnectionManager<T> + 'static> ClusterMeta
         debug!(%keyspace, "Refreshing keyspace.");
 
         let control_transport = self.control_transport()?;
-        send_query_with_values(
-            "SELECT keyspace_name, toJson(replication) AS replication FROM system_schema.keyspaces WHERE keyspace_name = ?",
-            QueryValues::SimpleValues(vec![keyspace.into()]),
-            control_transport.as_ref(),
-            self.version,
-            self.beta_protocol,
-        )
-        .await
-        .map(|rows| { rows.and_then(|mut rows| rows.pop()) })
-        .and_then(|row| {
-            match row {
-                Some(row) => {
-                    let (keyspace_name, keyspace) = build_keyspace(&row)?;
-                    let metadata = self.metadata.load().clone();
-                    self.metadata.store(Arc::new(
-                        metadata.clone_with_keyspace(keyspace_name, keyspace),
-                    ));
-                }
-                None => {
-                    warn!(%keyspace, "Keyspace to refresh disappeared.");
-                    self.remove_keyspace(keyspace);
+        self.query_keyspaces(control_transport.as_ref(), Some(keyspace))
+            .await
+            .map(|rows| rows.and_then(|mut rows| rows.pop()))
+            .and_then(|row| {
+                match row {
+                    Some(row) => {
+                        let (keyspace_name, keyspace) = build_keyspace(&row)?;
+                        let metadata = self.metadata.load().clone();
+                        self.metadata.store(Arc::new(
+                            metadata.clone_with_keyspace(keyspace_name, keyspace),
+                        ));
+                    }
+                    None => {
+                        warn!(%keyspace, "Keyspace to refresh disappeared.");
+                        self.remove_keyspace(keyspace);
+                    }
                 }
-            }
 
-            Ok(())
-        })
+                Ok(())
+            })
     }
 
     async fn add_new_node(
@@ -546,6 +660,84 @@ impl<T: CdrsTransport + 'static, CM: ConnectionManager<T> + 'static> ClusterMeta
         }
     }
 
+    // A node replaced in place keeps its address, but comes back with a new host id. Its
+    // connections, along with everything prepared on the old node, are gone, so it's replaced
+    // by a new node and cached statements are prepared on it up front.
+    async fn replace_changed_node(
+        &self,
+        node: &Arc<Node<T, CM>>,
+        state: NodeState,
+        metadata: &ClusterMetadata<T, CM>,
+    ) -> bool {
+        let broadcast_rpc_address = node.broadcast_rpc_address();
+        let node_info = match self.find_new_node_info(broadcast_rpc_address).await {
+            Ok(Some(node_info)) => node_info,
+            Ok(None) => return false,
+            Err(error) => {
+                warn!(%error, %broadcast_rpc_address, "Error checking node host id.");
+                return false;
+            }
+        };
+
+        let old_host_id = match node.host_id() {
+            Some(old_host_id) if old_host_id != node_info.host_id => old_host_id,
+            _ => return false,
+        };
+
+        let host_id = node_info.host_id;
+        info!(%broadcast_rpc_address, %old_host_id, %host_id, "Node replaced in place.");
+
+        let metadata = replace_node(
+            node_info,
+            metadata,
+            &self.connection_pool_factory,
+            self.node_distance_evaluator.as_ref(),
+            state,
+        );
+
+        if let Some(node) = metadata.find_node_by_host_id(&host_id) {
+            self.prepare_cached_statements(node);
+        }
+
+        self.metadata.store(Arc::new(metadata));
+        true
+    }
+
+    fn prepare_cached_statements(&self, node: Arc<Node<T, CM>>) {
+        let statements = self.prepared_cache.snapshot().entries;
+        if statements.is_empty() {
+            return;
+        }
+
+        let flags = prepare_flags(false, false, self.beta_protocol);
+        let version = self.version;
+
+        self.task_tracker().spawn(async move {
+            let transport = match node.persistent_connection().await {
+                Ok(transport) => transport,
+                Err(error) => {
+                    warn!(%error, ?node, "Cannot prepare cached statements on replaced node.");
+                    return;
+                }
+            };
+
+            debug!(
+                count = statements.len(),
+                ?node,
+                "Preparing cached statements on replaced node."
+            );
+
+            for statement in statements {
+                let envelope =
+                    Envelope::new_req_prepare(statement.query, statement.keyspace, flags, version);
+
+                if let Err(error) = transport.write_envelope(&envelope, false).await {
+                    warn!(%error, ?node, "Error preparing cached statement on replaced node.");
+                }
+            }
+        });
+    }
+
     async fn find_new_node_info(
         &self,
         broadcast_rpc_address: SocketAddr,
@@ -562,25 +754,21 @@ impl<T: CdrsTransport + 'static, CM: ConnectionManager<T> + 'static> ClusterMeta
                 &control_addr,
                 self.version,
                 self.beta_protocol,
+                self.system_query_consistency,
             )
             .await?;
 
             return build_node_info(&local_info, broadcast_rpc_address).map(Some);
         }
 
-        send_query(
-            &format!("SELECT * FROM {}", self.peer_table_name()),
-            control_transport.as_ref(),
-            self.version,
-            self.beta_protocol,
-        )
-        .await
-        .map(|peers| {
-            peers.and_then(|peers| {
-                find_in_peers(&peers, broadcast_rpc_address, control_addr).transpose()
-            })
-        })?
-        .transpose()
+        self.query_peers(control_transport.as_ref())
+            .await
+            .map(|peers| {
+                peers.and_then(|peers| {
+                    find_in_peers(&peers, broadcast_rpc_address, control_addr).transpose()
+                })
+            })?
+            .transpose()
     }
 
     #[inline]
@@ -593,12 +781,13 @@ impl<T: CdrsTransport + 'static, CM: ConnectionManager<T> + 'static> ClusterMeta
     }
 
     #[inline]
-    fn peer_table_name(&self) -> &'static str {
-        if self.is_schema_v2.load(Ordering::Relaxed) {
-            "system.peers_v2"
-        } else {
-            "system.peers"
-        }
+    pub(crate) fn connection_manager(&self) -> &CM {
+        self.connection_pool_factory.connection_manager()
+    }
+
+    #[inline]
+    pub(crate) fn task_tracker(&self) -> &Arc<TaskTracker> {
+        self.connection_pool_factory.task_tracker()
     }
 
     #[inline]
@@ -657,20 +846,117 @@ impl<T: CdrsTransport + 'static, CM: ConnectionManager<T> + 'static> ClusterMeta
         Ok(())
     }
 
-    async fn refresh_keyspaces(&self) -> Result<FxHashMap<String, KeyspaceMetadata>> {
+    // Returns partition key columns of given table, in order, or an empty list if the table is
+    // unknown.
+    pub(crate) async fn fetch_partition_key(
+        &self,
+        keyspace: &str,
+        table: &str,
+    ) -> Result<Vec<String>> {
         let control_transport = self.control_transport()?;
-        send_query(
-            "SELECT keyspace_name, toJson(replication) AS replication FROM system_schema.keyspaces",
+        let rows = send_query_with_values(
+            "SELECT column_name, kind, position FROM system_schema.columns WHERE keyspace_name = ? AND table_name = ?",
+            QueryValues::SimpleValues(vec![keyspace.into(), table.into()]),
             control_transport.as_ref(),
             self.version,
             self.beta_protocol,
+            self.system_query_consistency,
         )
-        .await
-        .and_then(|rows| {
-            rows.map(|rows| rows.iter().map(build_keyspace).try_collect())
-                .transpose()
-        })
-        .map(|keyspaces| keyspaces.unwrap_or_default())
+        .await?
+        .unwrap_or_default();
+
+        let mut partition_key = vec![];
+        for row in rows {
+            let kind: String = row.get_r_by_name("kind")?;
+            if kind == "partition_key" {
+                let position: i32 = row.get_r_by_name("position")?;
+                let name: String = row.get_r_by_name("column_name")?;
+                partition_key.push((position, name));
+            }
+        }
+
+        partition_key.sort_unstable();
+        Ok(partition_key.into_iter().map(|(_, name)| name).collect())
+    }
+
+    // Returns cached partition key columns of given table, fetching them if needed. Failures are
+    // not cached, so fetching is retried on next use.
+    pub(crate) async fn partition_key(&self, keyspace: &str, table: &str) -> Option<Arc<[String]>> {
+        let key = (keyspace.to_string(), table.to_string());
+        if let Some(partition_key) = self.partition_keys.lock().unwrap().get(&key) {
+            return Some(partition_key.clone());
+        }
+
+        match self.fetch_partition_key(keyspace, table).await {
+            Ok(partition_key) => {
+                let partition_key: Arc<[String]> = partition_key.into();
+                self.partition_keys
+                    .lock()
+                    .unwrap()
+                    .insert(key, partition_key.clone());
+                Some(partition_key)
+            }
+            Err(error) => {
+                debug!(?error, %keyspace, %table, "Error fetching partition key.");
+                None
+            }
+        }
+    }
+
+    // Checks if the control node and all its peers, which are not known to be down, report the
+    // same schema version.
+    pub(crate) async fn check_schema_agreement(&self) -> Result<bool> {
+        let control_transport = self.control_transport()?;
+
+        let local = send_query(
+            "SELECT schema_version FROM system.local WHERE key='local'",
+            control_transport.as_ref(),
+            self.version,
+            self.beta_protocol,
+            self.system_query_consistency,
+        )
+        .await?
+        .unwrap_or_default();
+
+        let peers = send_query(
+            "SELECT host_id, schema_version FROM system.peers",
+            control_transport.as_ref(),
+            self.version,
+            self.beta_protocol,
+            self.system_query_consistency,
+        )
+        .await?
+        .unwrap_or_default();
+
+        let metadata = self.metadata();
+        let peer_versions = peers
+            .iter()
+            .filter(|peer| {
+                let host_id: Option<Uuid> = peer.get_by_name("host_id").ok().flatten();
+                host_id
+                    .and_then(|host_id| metadata.find_node_by_host_id(&host_id))
+                    .map(|node| !matches!(node.state(), NodeState::Down | NodeState::ForcedDown))
+                    .unwrap_or(true)
+            })
+            .map(|peer| peer.get_by_name("schema_version").ok().flatten());
+
+        Ok(is_schema_in_agreement(
+            local
+                .iter()
+                .map(|local| local.get_by_name("schema_version").ok().flatten())
+                .chain(peer_versions),
+        ))
+    }
+
+    async fn refresh_keyspaces(&self) -> Result<FxHashMap<String, KeyspaceMetadata>> {
+        let control_transport = self.control_transport()?;
+        self.query_keyspaces(control_transport.as_ref(), None)
+            .await
+            .and_then(|rows| {
+                rows.map(|rows| rows.iter().map(build_keyspace).try_collect())
+                    .transpose()
+            })
+            .map(|keyspaces| keyspaces.unwrap_or_default())
     }
 
     async fn refresh_node_infos(&self) -> Result<Vec<NodeInfo>> {
@@ -682,6 +968,7 @@ impl<T: CdrsTransport + 'static, CM: ConnectionManager<T> + 'static> ClusterMeta
             &control_addr,
             self.version,
             self.beta_protocol,
+            self.system_query_consistency,
         )
         .await?;
 
@@ -714,12 +1001,80 @@ impl<T: CdrsTransport + 'static, CM: ConnectionManager<T> + 'static> ClusterMeta
                 })?;
         }
 
-        Ok(node_infos)
+        Ok(deduplicate_node_infos(node_infos))
+    }
+
+    async fn query_keyspaces(
+        &self,
+        transport: &T,
+        keyspace: Option<&str>,
+    ) -> Result<Option<Vec<Row>>> {
+        if self.has_system_schema.load(Ordering::Relaxed) {
+            let result = Self::query_keyspace_table(
+                "SELECT keyspace_name, toJson(replication) AS replication FROM system_schema.keyspaces",
+                keyspace,
+                transport,
+                self.version,
+                self.beta_protocol,
+                self.system_query_consistency,
+            )
+            .await;
+
+            match result {
+                // system_schema does not exist
+                Err(Error::Server {
+                    body:
+                        ErrorBody {
+                            ty: ErrorType::Invalid,
+                            ..
+                        },
+                    ..
+                }) => {
+                    self.has_system_schema.store(false, Ordering::Relaxed);
+                }
+                result => return result,
+            }
+        }
+
+        Self::query_keyspace_table(
+            "SELECT keyspace_name, strategy_class, strategy_options FROM system.schema_keyspaces",
+            keyspace,
+            transport,
+            self.version,
+            self.beta_protocol,
+            self.system_query_consistency,
+        )
+        .await
+    }
+
+    async fn query_keyspace_table(
+        query: &str,
+        keyspace: Option<&str>,
+        transport: &T,
+        version: Version,
+        beta_protocol: bool,
+        consistency: Consistency,
+    ) -> Result<Option<Vec<Row>>> {
+        match keyspace {
+            Some(keyspace) => {
+                send_query_with_values(
+                    &format!("{query} WHERE keyspace_name = ?"),
+                    QueryValues::SimpleValues(vec![keyspace.into()]),
+                    transport,
+                    version,
+                    beta_protocol,
+                    consistency,
+                )
+                .await
+            }
+            None => send_query(query, transport, version, beta_protocol, consistency).await,
+        }
     }
 
     async fn query_peers(&self, transport: &T) -> Result<Option<Vec<Row>>> {
-        if !self.is_schema_v2.load(Ordering::Relaxed) {
-            // we've already checked for v2 before, so proceed with legacy peers
+        let control_addr = transport.address();
+        if *self.legacy_peers_node.lock().unwrap() == Some(control_addr) {
+            // we've already checked for v2 on this node, so proceed with legacy peers
             return self.query_legacy_peers(transport).await;
         }
 
@@ -728,6 +1083,7 @@ impl<T: CdrsTransport + 'static, CM: ConnectionManager<T> + 'static> ClusterMeta
             transport,
             self.version,
             self.beta_protocol,
+            self.system_query_consistency,
         )
         .await;
 
@@ -742,7 +1098,8 @@ impl<T: CdrsTransport + 'static, CM: ConnectionManager<T> + 'static> ClusterMeta
                     },
                 ..
             }) => {
-                self.is_schema_v2.store(false, Ordering::Relaxed);
+                debug!(%control_addr, "Node has no system.peers_v2 - falling back to system.peers.");
+                *self.legacy_peers_node.lock().unwrap() = Some(control_addr);
                 self.query_legacy_peers(transport).await
             }
             Err(error) => Err(error),
@@ -756,7 +1113,116 @@ impl<T: CdrsTransport + 'static, CM: ConnectionManager<T> + 'static> ClusterMeta
             transport,
             self.version,
             self.beta_protocol,
+            self.system_query_consistency,
         )
         .await
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use serde_json::{json, Value as JsonValue};
+    use uuid::Uuid;
+
+    use crate::cluster::cluster_metadata_manager::{
+        build_replication_strategy, deduplicate_node_infos, is_schema_in_agreement,
+    };
+    use crate::cluster::topology::ReplicationStrategy;
+    use crate::cluster::NodeInfo;
+
+    fn build(replication: JsonValue) -> ReplicationStrategy {
+        match replication {
+            JsonValue::Object(properties) => build_replication_strategy(properties).unwrap(),
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn should_build_replication_strategies() {
+        assert!(matches!(
+            build(json!({
+                "class": "org.apache.cassandra.locator.SimpleStrategy",
+                "replication_factor": "3"
+            })),
+            ReplicationStrategy::SimpleStrategy {
+                replication_factor: 3
+            }
+        ));
+
+        match build(json!({
+            "class": "org.apache.cassandra.locator.NetworkTopologyStrategy",
+            "dc1": "3",
+            "dc2": "2/1"
+        })) {
+            ReplicationStrategy::NetworkTopologyStrategy {
+                datacenter_replication_factor,
+            } => {
+                assert_eq!(datacenter_replication_factor.len(), 2);
+                assert_eq!(datacenter_replication_factor["dc1"], 3);
+                assert_eq!(datacenter_replication_factor["dc2"], 2);
+            }
+            strategy => panic!("Unexpected strategy: {:?}", strategy),
+        }
+
+        assert!(matches!(
+            build(json!({ "class": "org.apache.cassandra.locator.LocalStrategy" })),
+            ReplicationStrategy::LocalStrategy
+        ));
+    }
+
+    #[test]
+    fn should_pass_through_unknown_replication_strategies() {
+        match build(json!({
+            "class": "com.example.CustomStrategy",
+            "copies": "2",
+            "enabled": true
+        })) {
+            ReplicationStrategy::Other { class, options } => {
+                assert_eq!(class, "com.example.CustomStrategy");
+                assert_eq!(options["copies"], "2");
+                assert_eq!(options["enabled"], "true");
+            }
+            strategy => panic!("Unexpected strategy: {:?}", strategy),
+        }
+    }
+
+    #[test]
+    fn should_deduplicate_node_infos_by_host_id() {
+        let host_id = Uuid::new_v4();
+        let node_info = |host_id, address: &str| {
+            NodeInfo::new(
+                host_id,
+                address.parse().unwrap(),
+                None,
+                "dc1".into(),
+                vec![],
+                "rack1".into(),
+            )
+        };
+
+        let node_infos = deduplicate_node_infos(vec![
+            node_info(host_id, "127.0.0.1:9042"),
+            node_info(Uuid::new_v4(), "127.0.0.2:9042"),
+            node_info(host_id, "127.0.0.3:9043"),
+        ]);
+
+        assert_eq!(node_infos.len(), 2);
+        assert_eq!(
+            node_infos[0].broadcast_rpc_address,
+            "127.0.0.1:9042".parse().unwrap()
+        );
+    }
+
+    #[test]
+    fn should_check_schema_agreement() {
+        let version = Uuid::new_v4();
+
+        assert!(is_schema_in_agreement(vec![]));
+        assert!(is_schema_in_agreement(vec![Some(version), Some(version)]));
+        assert!(is_schema_in_agreement(vec![Some(version), None]));
+        assert!(!is_schema_in_agreement(vec![
+            Some(version),
+            Some(Uuid::new_v4())
+        ]));
+    }
+}
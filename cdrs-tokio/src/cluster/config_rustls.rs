@@ -14,7 +14,8 @@ use crate::cluster::NodeAddress;
 #[derive(Derivative, Clone)]
 #[derivative(Debug)]
 pub struct NodeRustlsConfig {
-    pub(crate) contact_points: Vec<SocketAddr>,
+    // addresses of each contact point
+    pub(crate) contact_points: Vec<Vec<SocketAddr>>,
     pub(crate) dns_name: ServerName<'static>,
     #[derivative(Debug = "ignore")]
     pub(crate) authenticator_provider: Arc<dyn SaslAuthenticatorProvider + Send + Sync>,
@@ -106,7 +107,7 @@ impl NodeRustlsConfigBuilder {
         // replace with map() when async lambdas become available
         let mut contact_points = Vec::with_capacity(self.addrs.len());
         for contact_point in self.addrs {
-            contact_points.append(&mut contact_point.resolve_address().await?);
+            contact_points.push(contact_point.resolve_address().await?);
         }
 
         Ok(NodeRustlsConfig {
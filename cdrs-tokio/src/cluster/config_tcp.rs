@@ -1,19 +1,21 @@
 use cassandra_protocol::authenticators::{NoneAuthenticatorProvider, SaslAuthenticatorProvider};
-use cassandra_protocol::error::Result;
+use cassandra_protocol::error::{Error, Result};
 use cassandra_protocol::frame::Version;
 use derivative::Derivative;
-use std::net::SocketAddr;
+use std::net::{Ipv4Addr, SocketAddr};
 use std::sync::Arc;
 
+use crate::cluster::connection_string::parse_host;
 #[cfg(feature = "http-proxy")]
 use crate::cluster::HttpProxyConfig;
-use crate::cluster::NodeAddress;
+use crate::cluster::{NodeAddress, DEFAULT_PORT};
 
 /// Single node TCP connection config. See [NodeTcpConfigBuilder].
 #[derive(Derivative, Clone)]
 #[derivative(Debug)]
 pub struct NodeTcpConfig {
-    pub(crate) contact_points: Vec<SocketAddr>,
+    // addresses of each contact point
+    pub(crate) contact_points: Vec<Vec<SocketAddr>>,
     #[derivative(Debug = "ignore")]
     pub(crate) authenticator_provider: Arc<dyn SaslAuthenticatorProvider + Send + Sync>,
     pub(crate) version: Version,
@@ -53,6 +55,29 @@ impl NodeTcpConfigBuilder {
         Default::default()
     }
 
+    /// Creates a new builder with `127.0.0.1` and the default port as the contact point.
+    pub fn localhost() -> NodeTcpConfigBuilder {
+        Self::new().with_contact_point(NodeAddress::Direct(SocketAddr::from((
+            Ipv4Addr::LOCALHOST,
+            DEFAULT_PORT,
+        ))))
+    }
+
+    /// Creates a new builder with given contact points, in the `host[:port]` form with the
+    /// default port used when missing. IPv6 addresses need to be enclosed in brackets.
+    pub fn from_addrs<A: AsRef<str>>(addrs: &[A]) -> Result<NodeTcpConfigBuilder> {
+        let addrs = addrs
+            .iter()
+            .map(|addr| {
+                let addr = addr.as_ref();
+                parse_host(addr)
+                    .map_err(|_| Error::General(format!("Invalid contact point: \"{addr}\"")))
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok(Self::new().with_contact_points(addrs))
+    }
+
     /// Sets new authenticator.
     #[must_use]
     pub fn with_authenticator_provider(
@@ -105,7 +130,7 @@ impl NodeTcpConfigBuilder {
         // replace with map() when async lambdas become available
         let mut contact_points = Vec::with_capacity(self.addrs.len());
         for contact_point in self.addrs {
-            contact_points.append(&mut contact_point.resolve_address().await?);
+            contact_points.push(contact_point.resolve_address().await?);
         }
 
         Ok(NodeTcpConfig {
@@ -118,3 +143,35 @@ impl NodeTcpConfigBuilder {
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::net::SocketAddr;
+
+    use super::NodeTcpConfigBuilder;
+    use crate::cluster::NodeAddress;
+
+    #[test]
+    fn should_parse_contact_points() {
+        assert_eq!(
+            NodeTcpConfigBuilder::localhost().addrs,
+            vec![NodeAddress::Direct(SocketAddr::from((
+                [127, 0, 0, 1],
+                9042
+            )))]
+        );
+        assert_eq!(
+            NodeTcpConfigBuilder::from_addrs(&["10.0.0.1:9043", "[::1]", "host"])
+                .unwrap()
+                .addrs,
+            vec![
+                NodeAddress::Direct(SocketAddr::from(([10, 0, 0, 1], 9043))),
+                NodeAddress::Direct("[::1]:9042".parse().unwrap()),
+                NodeAddress::Hostname("host:9042".into()),
+            ]
+        );
+
+        let error = NodeTcpConfigBuilder::from_addrs(&["10.0.0.1", "10.0.0.2:x"]).unwrap_err();
+        assert!(error.to_string().contains("10.0.0.2:x"), "{:?}", error);
+    }
+}
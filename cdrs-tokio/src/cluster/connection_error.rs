@@ -0,0 +1,154 @@
+use cassandra_protocol::error::Error;
+use cassandra_protocol::frame::message_error::ErrorType;
+use derive_more::Display;
+use itertools::Itertools;
+use std::net::SocketAddr;
+use std::time::Duration;
+use thiserror::Error as ThisError;
+
+/// Phase of establishing a connection to a node.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Display)]
+pub enum ConnectionPhase {
+    /// Opening a TCP connection, including connecting through a proxy.
+    #[display("tcp-connect")]
+    TcpConnect,
+    /// Negotiating TLS on an open TCP connection.
+    #[display("tls-handshake")]
+    TlsHandshake,
+    /// Sending STARTUP and negotiating protocol options.
+    #[display("startup")]
+    Startup,
+    /// Authenticating with the node.
+    #[display("authenticate")]
+    Authenticate,
+    /// Setting the current keyspace on a new connection.
+    #[display("use-keyspace")]
+    UseKeyspace,
+    /// Executing initialization statements on a new connection.
+    #[display("initialize")]
+    Initialize,
+}
+
+impl ConnectionPhase {
+    /// Guesses the phase in which given error happened. Used for connection managers which don't
+    /// track phases themselves.
+    pub fn from_error(error: &Error) -> Self {
+        match error {
+            Error::Io(_) | Error::Timeout { .. } | Error::RequestNotSent(_) => {
+                ConnectionPhase::TcpConnect
+            }
+            Error::Tls { .. } => ConnectionPhase::TlsHandshake,
+            Error::UnexpectedAuthResponse(_)
+            | Error::AuthenticationTimeout(_)
+            | Error::Server {
+                body:
+                    cassandra_protocol::frame::message_error::ErrorBody {
+                        ty: ErrorType::Authentication,
+                        ..
+                    },
+                ..
+            } => ConnectionPhase::Authenticate,
+            _ => ConnectionPhase::Startup,
+        }
+    }
+}
+
+/// Detailed information about a failed connection attempt.
+#[derive(Debug, Clone, ThisError)]
+#[error("Connection to {address} failed during {phase} after {elapsed:?}: {error}")]
+pub struct ConnectionError {
+    /// Address of the node.
+    pub address: SocketAddr,
+    /// Phase in which the connection failed.
+    pub phase: ConnectionPhase,
+    /// Underlying error.
+    #[source]
+    pub error: Error,
+    /// Time from starting the connection attempt until failure.
+    pub elapsed: Duration,
+}
+
+impl ConnectionError {
+    pub fn new(
+        address: SocketAddr,
+        phase: ConnectionPhase,
+        error: Error,
+        elapsed: Duration,
+    ) -> Self {
+        ConnectionError {
+            address,
+            phase,
+            error,
+            elapsed,
+        }
+    }
+}
+
+impl From<ConnectionError> for Error {
+    #[inline]
+    fn from(error: ConnectionError) -> Self {
+        error.error
+    }
+}
+
+/// Per-node results of eagerly connecting to the cluster.
+#[derive(Debug, Clone, Default)]
+pub struct WarmupReport {
+    /// Nodes which have been connected to.
+    pub connected: Vec<SocketAddr>,
+    /// Nodes which could not be connected to.
+    pub failed: Vec<ConnectionError>,
+}
+
+/// Error returned when no node could be connected to.
+#[derive(Debug, Clone, ThisError)]
+#[error("Could not connect to any node: {}", .failed.iter().join("; "))]
+pub struct WarmupError {
+    /// Errors for every node which has been tried.
+    pub failed: Vec<ConnectionError>,
+}
+
+#[cfg(test)]
+mod tests {
+    use cassandra_protocol::error::Error;
+    use std::io;
+    use std::net::SocketAddr;
+    use std::time::Duration;
+
+    use crate::cluster::{ConnectionError, ConnectionPhase, WarmupError};
+
+    #[test]
+    fn should_describe_failed_connections() {
+        let error = WarmupError {
+            failed: vec![ConnectionError::new(
+                SocketAddr::from(([127, 0, 0, 1], 9042)),
+                ConnectionPhase::TlsHandshake,
+                Error::Io(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    "bad certificate",
+                )),
+                Duration::from_millis(5),
+            )],
+        };
+
+        assert_eq!(
+            error.to_string(),
+            "Could not connect to any node: Connection to 127.0.0.1:9042 failed during tls-handshake after 5ms: IO error: bad certificate"
+        );
+    }
+
+    #[test]
+    fn should_classify_errors() {
+        assert_eq!(
+            ConnectionPhase::from_error(&Error::Io(io::ErrorKind::ConnectionRefused.into())),
+            ConnectionPhase::TcpConnect
+        );
+        assert_eq!(
+            ConnectionPhase::from_error(&Error::InvalidProtocol(SocketAddr::from((
+                [127, 0, 0, 1],
+                9042
+            )))),
+            ConnectionPhase::Startup
+        );
+    }
+}
@@ -1,12 +1,29 @@
+use futures::FutureExt;
+use fxhash::FxHashMap;
+use std::future::Future;
 use std::io;
 use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
 use tokio::sync::mpsc::Sender;
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
 
 #[cfg(test)]
 use mockall::*;
 
-use crate::cluster::KeyspaceHolder;
+use crate::cluster::session::{
+    DEFAULT_AUTHENTICATION_TIMEOUT, DEFAULT_COMPRESSION_THRESHOLD, DEFAULT_HANDSHAKE_TIMEOUT,
+    DEFAULT_MAX_CONCURRENT_AUTHENTICATIONS, DEFAULT_TRANSPORT_BUFFER_SIZE,
+};
+#[cfg(feature = "http-proxy")]
+use crate::cluster::HttpProxyConfig;
+use crate::cluster::{
+    initialize, ConnectionError, ConnectionPhase, InitStatement, KeyspaceHolder,
+    DEFAULT_CONNECTION_STAGGER_DELAY,
+};
+use crate::frame_recording::FrameRecorder;
 use crate::future::BoxFuture;
+use crate::runtime::timeout;
 use crate::transport::CdrsTransport;
 use cassandra_protocol::authenticators::SaslAuthenticatorProvider;
 use cassandra_protocol::compression::Compression;
@@ -18,13 +35,104 @@ use cassandra_protocol::query::utils::quote;
 /// Manages establishing connections to nodes.
 pub trait ConnectionManager<T: CdrsTransport>: Send + Sync {
     /// Tries to establish a new, ready to use connection with optional server event and error
-    /// handlers.
+    /// handlers. Only the session control connection is given an event handler - data
+    /// connections in node pools don't register for events, and drop any they receive.
     fn connection(
         &self,
         event_handler: Option<Sender<Envelope>>,
         error_handler: Option<Sender<Error>>,
         addr: SocketAddr,
     ) -> BoxFuture<Result<T>>;
+
+    /// Like [`connection`](ConnectionManager::connection), but describes failures with the
+    /// connection phase and time taken. The default implementation guesses the phase from the
+    /// returned error, so managers which know better should override it.
+    fn connection_with_diagnostics<'a>(
+        &'a self,
+        event_handler: Option<Sender<Envelope>>,
+        error_handler: Option<Sender<Error>>,
+        addr: SocketAddr,
+    ) -> BoxFuture<'a, std::result::Result<T, ConnectionError>>
+    where
+        T: 'a,
+    {
+        let start = Instant::now();
+        self.connection(event_handler, error_handler, addr)
+            .map(move |result| {
+                result.map_err(|error| {
+                    ConnectionError::new(
+                        addr,
+                        ConnectionPhase::from_error(&error),
+                        error,
+                        start.elapsed(),
+                    )
+                })
+            })
+            .boxed()
+    }
+
+    /// Like [`connection_with_diagnostics`](ConnectionManager::connection_with_diagnostics), but
+    /// for nodes reachable through multiple addresses, tried in given order. The default
+    /// implementation tries them one by one, while the built-in managers race staggered TCP
+    /// connection attempts and perform the handshake only on the first socket to connect.
+    fn connection_with_alternatives<'a>(
+        &'a self,
+        event_handler: Option<Sender<Envelope>>,
+        error_handler: Option<Sender<Error>>,
+        addr: SocketAddr,
+        alternative_addrs: &'a [SocketAddr],
+    ) -> BoxFuture<'a, std::result::Result<T, ConnectionError>>
+    where
+        T: 'a,
+    {
+        async move {
+            let mut result = self
+                .connection_with_diagnostics(event_handler.clone(), error_handler.clone(), addr)
+                .await;
+
+            for addr in alternative_addrs {
+                if result.is_ok() {
+                    break;
+                }
+
+                result = self
+                    .connection_with_diagnostics(
+                        event_handler.clone(),
+                        error_handler.clone(),
+                        *addr,
+                    )
+                    .await;
+            }
+
+            result
+        }
+        .boxed()
+    }
+
+    /// Returns compression used by newly established connections, if known.
+    fn compression(&self) -> Option<Compression> {
+        None
+    }
+
+    /// Changes compression used by newly established connections. Existing connections keep the
+    /// compression negotiated during their handshake. The default implementation doesn't support
+    /// changing compression.
+    fn set_compression(&self, _compression: Compression) -> Result<()> {
+        Err(Error::General(
+            "Connection manager doesn't support changing compression!".into(),
+        ))
+    }
+}
+
+/// Checks if given compression can be negotiated with given protocol version.
+pub(crate) fn verify_compression(compression: Compression, version: Version) -> Result<()> {
+    if version >= Version::V5 && compression == Compression::Snappy {
+        Err(Error::General(
+            "Given compression type is not supported for selected protocol!".into(),
+        ))
+    } else {
+        Ok(())
+    }
 }
 
 #[cfg(test)]
@@ -54,22 +162,284 @@ pub async fn startup<
     compression: Compression,
     version: Version,
 ) -> Result<()> {
-    let startup_envelope =
-        Envelope::new_req_startup(compression.as_str().map(String::from), version);
+    let mut phase = ConnectionPhase::Startup;
+    startup_with_phase(
+        transport,
+        authenticator_provider,
+        keyspace_holder,
+        compression,
+        version,
+        &[],
+        &mut phase,
+        None,
+    )
+    .await
+}
 
-    let start_response = match transport.write_envelope(&startup_envelope, true).await {
-        Ok(response) => Ok(response),
-        Err(Error::Server { body, .. }) if body.is_bad_protocol() => {
-            Err(Error::InvalidProtocol(transport.address()))
+/// Configuration of connections established by the built-in connection managers. By default,
+/// connections are uncompressed, use [`DEFAULT_TRANSPORT_BUFFER_SIZE`] byte buffers and
+/// `TCP_NODELAY`, and handshakes are limited by [`DEFAULT_HANDSHAKE_TIMEOUT`],
+/// [`DEFAULT_AUTHENTICATION_TIMEOUT`] and [`DEFAULT_MAX_CONCURRENT_AUTHENTICATIONS`]. See
+/// [ConnectionManagerConfigBuilder].
+#[derive(Clone)]
+pub struct ConnectionManagerConfig {
+    pub(crate) version: Version,
+    pub(crate) compression: Compression,
+    pub(crate) compression_threshold: usize,
+    pub(crate) buffer_size: usize,
+    pub(crate) read_buffer_size: Option<usize>,
+    pub(crate) tcp_nodelay: bool,
+    pub(crate) strict_socket_options: bool,
+    pub(crate) handshake_timeout: Duration,
+    pub(crate) authentication_timeout: Duration,
+    pub(crate) max_concurrent_authentications: usize,
+    pub(crate) connection_stagger_delay: Duration,
+    pub(crate) frame_recorder: Option<Arc<FrameRecorder>>,
+    pub(crate) init_statements: Vec<InitStatement>,
+    #[cfg(feature = "http-proxy")]
+    pub(crate) http_proxy: Option<HttpProxyConfig>,
+}
+
+impl ConnectionManagerConfig {
+    pub(crate) fn handshake_limits(&self) -> HandshakeLimits {
+        HandshakeLimits::new(
+            self.handshake_timeout,
+            self.authentication_timeout,
+            self.max_concurrent_authentications,
+        )
+    }
+}
+
+/// A builder for [ConnectionManagerConfig].
+#[derive(Clone)]
+pub struct ConnectionManagerConfigBuilder {
+    config: ConnectionManagerConfig,
+}
+
+impl ConnectionManagerConfigBuilder {
+    /// Creates a builder of configuration for given protocol version.
+    pub fn new(version: Version) -> Self {
+        ConnectionManagerConfigBuilder {
+            config: ConnectionManagerConfig {
+                version,
+                compression: Compression::None,
+                compression_threshold: DEFAULT_COMPRESSION_THRESHOLD,
+                buffer_size: DEFAULT_TRANSPORT_BUFFER_SIZE,
+                read_buffer_size: None,
+                tcp_nodelay: true,
+                strict_socket_options: false,
+                handshake_timeout: DEFAULT_HANDSHAKE_TIMEOUT,
+                authentication_timeout: DEFAULT_AUTHENTICATION_TIMEOUT,
+                max_concurrent_authentications: DEFAULT_MAX_CONCURRENT_AUTHENTICATIONS,
+                connection_stagger_delay: DEFAULT_CONNECTION_STAGGER_DELAY,
+                frame_recorder: None,
+                init_statements: vec![],
+                #[cfg(feature = "http-proxy")]
+                http_proxy: None,
+            },
         }
-        Err(error) => Err(error),
-    }?;
+    }
+
+    /// Sets compression of new connections.
+    #[must_use]
+    pub fn with_compression(mut self, compression: Compression) -> Self {
+        self.config.compression = compression;
+        self
+    }
+
+    /// Sets minimum envelope body size, in bytes, which gets compressed.
+    #[must_use]
+    pub fn with_compression_threshold(mut self, compression_threshold: usize) -> Self {
+        self.config.compression_threshold = compression_threshold;
+        self
+    }
+
+    /// Sets transport buffer size.
+    #[must_use]
+    pub fn with_buffer_size(mut self, buffer_size: usize) -> Self {
+        self.config.buffer_size = buffer_size;
+        self
+    }
+
+    /// Sets read buffer size. `None` uses the transport buffer size.
+    #[must_use]
+    pub fn with_read_buffer_size(mut self, read_buffer_size: Option<usize>) -> Self {
+        self.config.read_buffer_size = read_buffer_size;
+        self
+    }
+
+    /// Sets if `TCP_NODELAY` is enabled on new sockets.
+    #[must_use]
+    pub fn with_tcp_nodelay(mut self, tcp_nodelay: bool) -> Self {
+        self.config.tcp_nodelay = tcp_nodelay;
+        self
+    }
+
+    /// Sets if failing to apply socket options fails connecting.
+    #[must_use]
+    pub fn with_strict_socket_options(mut self, strict_socket_options: bool) -> Self {
+        self.config.strict_socket_options = strict_socket_options;
+        self
+    }
+
+    /// Sets the timeout for handshake steps other than authentication.
+    #[must_use]
+    pub fn with_handshake_timeout(mut self, handshake_timeout: Duration) -> Self {
+        self.config.handshake_timeout = handshake_timeout;
+        self
+    }
 
-    if start_response.opcode == Opcode::Ready {
-        return set_keyspace(transport, keyspace_holder, version).await;
+    /// Sets the timeout for authenticating a connection.
+    #[must_use]
+    pub fn with_authentication_timeout(mut self, authentication_timeout: Duration) -> Self {
+        self.config.authentication_timeout = authentication_timeout;
+        self
     }
 
+    /// Sets the maximum number of connections authenticating with a single node at the same time.
+    #[must_use]
+    pub fn with_max_concurrent_authentications(
+        mut self,
+        max_concurrent_authentications: usize,
+    ) -> Self {
+        self.config.max_concurrent_authentications = max_concurrent_authentications;
+        self
+    }
+
+    /// Sets the delay between connection attempts to alternative addresses of a node.
+    #[must_use]
+    pub fn with_connection_stagger_delay(mut self, connection_stagger_delay: Duration) -> Self {
+        self.config.connection_stagger_delay = connection_stagger_delay;
+        self
+    }
+
+    /// Sets the recorder of envelopes sent and received by new connections.
+    #[must_use]
+    pub fn with_frame_recorder(mut self, frame_recorder: Option<Arc<FrameRecorder>>) -> Self {
+        self.config.frame_recorder = frame_recorder;
+        self
+    }
+
+    /// Sets statements executed on new connections before they are used.
+    #[must_use]
+    pub fn with_init_statements(mut self, init_statements: Vec<InitStatement>) -> Self {
+        self.config.init_statements = init_statements;
+        self
+    }
+
+    /// Sets the HTTP proxy used to connect to nodes.
+    #[cfg(feature = "http-proxy")]
+    #[must_use]
+    pub fn with_http_proxy(mut self, http_proxy: Option<HttpProxyConfig>) -> Self {
+        self.config.http_proxy = http_proxy;
+        self
+    }
+
+    /// Build the resulting config.
+    #[must_use]
+    pub fn build(self) -> ConnectionManagerConfig {
+        self.config
+    }
+}
+
+/// Limits applied to connection handshakes by the built-in connection managers.
+pub(crate) struct HandshakeLimits {
+    handshake_timeout: Duration,
+    authentication_timeout: Duration,
+    max_concurrent_authentications: usize,
+    // authentications in progress are limited separately for each node, so a slow one doesn't
+    // hold up connecting to others
+    authentication_permits: Mutex<FxHashMap<SocketAddr, Arc<Semaphore>>>,
+}
+
+impl HandshakeLimits {
+    pub(crate) fn new(
+        handshake_timeout: Duration,
+        authentication_timeout: Duration,
+        max_concurrent_authentications: usize,
+    ) -> Self {
+        HandshakeLimits {
+            handshake_timeout,
+            authentication_timeout,
+            max_concurrent_authentications: max_concurrent_authentications
+                .clamp(1, Semaphore::MAX_PERMITS),
+            authentication_permits: Default::default(),
+        }
+    }
+
+    async fn acquire_authentication_permit(&self, addr: SocketAddr) -> OwnedSemaphorePermit {
+        let semaphore = self
+            .authentication_permits
+            .lock()
+            .unwrap()
+            .entry(addr)
+            .or_insert_with(|| Arc::new(Semaphore::new(self.max_concurrent_authentications)))
+            .clone();
+
+        semaphore
+            .acquire_owned()
+            .await
+            .expect("Authentication semaphores are never closed!")
+    }
+}
+
+async fn with_timeout<R>(
+    duration: Option<Duration>,
+    future: impl Future<Output = Result<R>>,
+    error: impl FnOnce() -> Error,
+) -> Result<R> {
+    match duration {
+        Some(duration) => timeout(duration, future)
+            .await
+            .unwrap_or_else(|_| Err(error())),
+        None => future.await,
+    }
+}
+
+/// Same as [`startup`], but runs given initialization statements afterwards and keeps `phase`
+/// updated with the current step of the handshake. When `limits` are given, authentication is
+/// limited by its own timeout and the number of concurrent authentications with the node, while
+/// other steps are limited by the handshake timeout. Waiting for other authentications to finish
+/// doesn't count towards the timeout.
+#[allow(clippy::too_many_arguments)]
+pub(crate) async fn startup_with_phase<
+    T: CdrsTransport + 'static,
+    A: SaslAuthenticatorProvider + Send + Sync + ?Sized + 'static,
+>(
+    transport: &T,
+    authenticator_provider: &A,
+    keyspace_holder: &KeyspaceHolder,
+    compression: Compression,
+    version: Version,
+    init_statements: &[InitStatement],
+    phase: &mut ConnectionPhase,
+    limits: Option<&HandshakeLimits>,
+) -> Result<()> {
+    let addr = transport.address();
+    let handshake_timeout = limits.map(|limits| limits.handshake_timeout);
+
+    *phase = ConnectionPhase::Startup;
+
+    let startup_envelope =
+        Envelope::new_req_startup(compression.as_str().map(String::from), version)?;
+
+    let start_response = with_timeout(
+        handshake_timeout,
+        async {
+            match transport.write_envelope(&startup_envelope, true).await {
+                Err(Error::Server { body, .. }) if body.is_bad_protocol() => {
+                    Err(Error::InvalidProtocol(addr))
+                }
+                result => result,
+            }
+        },
+        || Error::HandshakeTimeout(addr),
+    )
+    .await?;
+
     if start_response.opcode == Opcode::Authenticate {
+        *phase = ConnectionPhase::Authenticate;
+
         let body = start_response.response_body()?;
         let authenticator = body.authenticator()
             .ok_or_else(|| Error::General("Cassandra server did communicate that it needed authentication but the auth schema was missing in the body response".into()))?;
@@ -98,33 +468,67 @@ pub async fn startup<
                 Ok(())
             })?;
 
-        let authenticator = authenticator_provider.create_authenticator();
-        let response = authenticator.initial_response();
-        let mut envelope = transport
-            .write_envelope(&Envelope::new_req_auth_response(response, version), false)
-            .await?;
+        let _permit = match limits {
+            Some(limits) => Some(limits.acquire_authentication_permit(addr).await),
+            None => None,
+        };
 
-        loop {
-            match envelope.response_body()? {
-                ResponseBody::AuthChallenge(challenge) => {
-                    let response = authenticator.evaluate_challenge(challenge.data)?;
+        with_timeout(
+            limits.map(|limits| limits.authentication_timeout),
+            authenticate(transport, authenticator_provider, version),
+            || Error::AuthenticationTimeout(addr),
+        )
+        .await?;
+    } else if start_response.opcode != Opcode::Ready {
+        return Err(Error::UnexpectedStartupResponse(start_response.opcode));
+    }
 
-                    envelope = transport
-                        .write_envelope(&Envelope::new_req_auth_response(response, version), false)
-                        .await?;
-                }
-                ResponseBody::AuthSuccess(success) => {
-                    authenticator.handle_success(success.data)?;
-                    break;
-                }
-                _ => return Err(Error::UnexpectedAuthResponse(envelope.opcode)),
+    *phase = ConnectionPhase::UseKeyspace;
+    with_timeout(
+        handshake_timeout,
+        set_keyspace(transport, keyspace_holder, version),
+        || Error::HandshakeTimeout(addr),
+    )
+    .await?;
+
+    *phase = ConnectionPhase::Initialize;
+    with_timeout(
+        handshake_timeout,
+        initialize(transport, init_statements, version),
+        || Error::HandshakeTimeout(addr),
+    )
+    .await
+}
+
+async fn authenticate<
+    T: CdrsTransport + 'static,
+    A: SaslAuthenticatorProvider + Send + Sync + ?Sized + 'static,
+>(
+    transport: &T,
+    authenticator_provider: &A,
+    version: Version,
+) -> Result<()> {
+    let authenticator = authenticator_provider.create_authenticator();
+    let response = authenticator.initial_response();
+    let mut envelope = transport
+        .write_envelope(&Envelope::new_req_auth_response(response, version), false)
+        .await?;
+
+    loop {
+        match envelope.response_body()? {
+            ResponseBody::AuthChallenge(challenge) => {
+                let response = authenticator.evaluate_challenge(challenge.data)?;
+
+                envelope = transport
+                    .write_envelope(&Envelope::new_req_auth_response(response, version), false)
+                    .await?;
             }
+            ResponseBody::AuthSuccess(success) => {
+                return authenticator.handle_success(success.data);
+            }
+            _ => return Err(Error::UnexpectedAuthResponse(envelope.opcode)),
         }
-
-        return set_keyspace(transport, keyspace_holder, version).await;
     }
-
-    Err(Error::UnexpectedStartupResponse(start_response.opcode))
 }
 
 async fn set_keyspace<T: CdrsTransport>(
@@ -146,7 +550,7 @@ async fn set_keyspace<T: CdrsTransport>(
             None,
             Default::default(),
             version,
-        );
+        )?;
 
         transport
             .write_envelope(&use_envelope, false)
@@ -1,5 +1,6 @@
 use atomic::Atomic;
 use bytemuck::NoUninit;
+use cassandra_protocol::consistency::Consistency;
 use cassandra_protocol::frame::{Envelope, Version};
 use cassandra_protocol::query::utils::quote;
 use derive_more::Display;
@@ -7,21 +8,25 @@ use futures::future::join_all;
 use itertools::Itertools;
 use std::marker::PhantomData;
 use std::net::SocketAddr;
-use std::ops::{Deref, DerefMut};
+use std::ops::Deref;
 use std::sync::atomic::{AtomicUsize, Ordering};
-use std::sync::{Arc, Weak};
-use std::time::Duration;
+use std::sync::{Arc, Mutex, Weak};
+use std::time::{Duration, Instant};
+use tokio::pin;
 use tokio::sync::watch::Receiver;
-use tokio::sync::{mpsc, RwLock};
-use tokio::time::{interval_at, sleep, Instant};
+use tokio::sync::{mpsc, Notify, RwLock, Semaphore};
 use tracing::*;
 
 use crate::cluster::topology::{Node, NodeDistance, NodeState};
-use crate::cluster::ConnectionManager;
+use crate::cluster::{ConnectionError, ConnectionManager, ConnectionPhase, NodeStateListener};
 use crate::error::{Error, Result as CdrsResult};
 use crate::retry::{ReconnectionPolicy, ReconnectionSchedule};
+use crate::runtime::{self, sleep, timeout, TaskTracker};
 use crate::transport::CdrsTransport;
 
+/// Default limit of connections established at the same time by all pools of a session.
+pub const DEFAULT_CONNECT_CONCURRENCY: usize = 8;
+
 #[derive(Copy, Clone, PartialEq, Eq, Display, NoUninit)]
 #[repr(u8)]
 enum ReconnectionState {
@@ -30,40 +35,106 @@ enum ReconnectionState {
     Disabled,
 }
 
+// OPTIONS is the cheapest request, but some proxies reject it, so a lightweight query is sent
+// instead when probing with OPTIONS is disabled
+fn probe_envelope(config: &ConnectionPoolConfig, version: Version) -> CdrsResult<Envelope> {
+    if config.options_probe {
+        Ok(Envelope::new_req_options(version))
+    } else {
+        Envelope::new_req_query(
+            "SELECT key FROM system.local".into(),
+            config.system_query_consistency,
+            None,
+            false,
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
+            Default::default(),
+            version,
+        )
+    }
+}
+
+// a server error response still proves the connection works, e.g. when a proxy rejects the probe
+fn is_connection_error(error: &Error) -> bool {
+    !matches!(error, Error::Server { .. })
+}
+
 async fn new_connection<T: CdrsTransport, CM: ConnectionManager<T>>(
     connection_manager: &CM,
+    connect_permits: &Semaphore,
     broadcast_rpc_address: SocketAddr,
     timeout: Option<Duration>,
     error_handler: mpsc::Sender<Error>,
-) -> CdrsResult<T> {
-    if let Some(timeout) = timeout {
-        tokio::time::timeout(
-            timeout,
-            connection_manager.connection(None, Some(error_handler), broadcast_rpc_address),
-        )
+) -> Result<T, ConnectionError> {
+    let _permit = connect_permits
+        .acquire()
         .await
-        .map_err(|_| {
-            Error::Timeout(format!(
-                "Timeout waiting for connection to: {broadcast_rpc_address}"
-            ))
-        })
-        .and_then(|result| result)
-    } else {
-        connection_manager
-            .connection(None, Some(error_handler), broadcast_rpc_address)
+        .expect("Connect semaphore is never closed!");
+
+    let connection = connection_manager.connection_with_diagnostics(
+        None,
+        Some(error_handler),
+        broadcast_rpc_address,
+    );
+
+    if let Some(timeout) = timeout {
+        let start = Instant::now();
+        runtime::timeout(timeout, connection)
             .await
+            .map_err(|_| {
+                let error = Error::Timeout {
+                    message: format!("Timeout waiting for connection to: {broadcast_rpc_address}"),
+                    possibly_applied: false,
+                };
+
+                ConnectionError::new(
+                    broadcast_rpc_address,
+                    ConnectionPhase::from_error(&error),
+                    error,
+                    start.elapsed(),
+                )
+            })
+            .and_then(|result| result)
+    } else {
+        connection.await
     }
 }
 
+/// Decides what happens when a connection is requested from a pool with all connections down.
+/// Reconnection always runs in the background, regardless of the mode.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum ReconnectWaitMode {
+    /// Fails immediately with a retriable error, so the request can go to the next node. Sessions
+    /// can also skip such nodes before sending, with
+    /// [`with_fail_fast_when_disconnected`](crate::cluster::session::SessionBuilder::with_fail_fast_when_disconnected).
+    #[default]
+    FailFast,
+    /// Waits up to given time for the reconnection in progress to bring up a connection. All
+    /// waiting requests are woken up by the same reconnection.
+    Wait(Duration),
+}
+
 /// Configuration for node connection pools. By default, the pool size depends on the number of
 /// cpu for local nodes and a fixed value for remote, and there is no timeout. If the distance to a
-/// given node is unknown, it is treated as remote. See [ConnectionPoolConfigBuilder].
+/// given node is unknown, it is treated as remote. Idle connections are not verified before use by
+/// default, and requests don't wait for reconnection. At most [`DEFAULT_CONNECT_CONCURRENCY`]
+/// connections are established at the same time. See [ConnectionPoolConfigBuilder].
 #[derive(Clone, Copy, Debug)]
 pub struct ConnectionPoolConfig {
     local_size: usize,
     remote_size: usize,
     connect_timeout: Option<Duration>,
+    connect_concurrency: usize,
     heartbeat_interval: Duration,
+    verify_after_idle: Option<Duration>,
+    verify_timeout: Duration,
+    reconnect_wait_mode: ReconnectWaitMode,
+    options_probe: bool,
+    system_query_consistency: Consistency,
 }
 
 impl Default for ConnectionPoolConfig {
@@ -72,11 +143,41 @@ impl Default for ConnectionPoolConfig {
             local_size: 1,
             remote_size: 1,
             connect_timeout: None,
+            connect_concurrency: DEFAULT_CONNECT_CONCURRENCY,
             heartbeat_interval: Duration::from_secs(30),
+            verify_after_idle: None,
+            verify_timeout: Duration::from_secs(2),
+            reconnect_wait_mode: Default::default(),
+            options_probe: true,
+            system_query_consistency: Consistency::One,
         }
     }
 }
 
+impl ConnectionPoolConfig {
+    #[inline]
+    pub(crate) fn local_size(&self) -> usize {
+        self.local_size
+    }
+
+    #[inline]
+    pub(crate) fn heartbeat_interval(&self) -> Duration {
+        self.heartbeat_interval
+    }
+
+    #[inline]
+    pub(crate) fn with_options_probe(mut self, options_probe: bool) -> Self {
+        self.options_probe = options_probe;
+        self
+    }
+
+    #[inline]
+    pub(crate) fn with_system_query_consistency(mut self, consistency: Consistency) -> Self {
+        self.system_query_consistency = consistency;
+        self
+    }
+}
+
 /// A builder for [ConnectionPoolConfig].
 #[derive(Default, Clone, Debug)]
 pub struct ConnectionPoolConfigBuilder {
@@ -109,6 +210,16 @@ impl ConnectionPoolConfigBuilder {
         self
     }
 
+    /// Sets how many connections can be established at the same time, shared by pools to all
+    /// nodes. Limits bursts of new connections, e.g. during warmup, when many nodes are discovered
+    /// at once or when reconnecting after a network failure, which could otherwise exhaust file
+    /// descriptors or overload authentication backends.
+    #[must_use]
+    pub fn with_connect_concurrency(mut self, connect_concurrency: usize) -> Self {
+        self.config.connect_concurrency = connect_concurrency;
+        self
+    }
+
     /// Sets new heartbeat interval.
     #[must_use]
     pub fn with_heartbeat_interval(mut self, heartbeat_interval: Duration) -> Self {
@@ -116,6 +227,30 @@ impl ConnectionPoolConfigBuilder {
         self
     }
 
+    /// Sets the idle time after which a connection is verified with an OPTIONS request, or a
+    /// lightweight query if the session disables OPTIONS probing, before being used. If the
+    /// verification fails, a new connection is established in its place.
+    /// `None` disables verification, avoiding the additional round trip.
+    #[must_use]
+    pub fn with_verify_after_idle(mut self, verify_after_idle: Option<Duration>) -> Self {
+        self.config.verify_after_idle = verify_after_idle;
+        self
+    }
+
+    /// Sets the timeout for idle connection verification.
+    #[must_use]
+    pub fn with_verify_timeout(mut self, verify_timeout: Duration) -> Self {
+        self.config.verify_timeout = verify_timeout;
+        self
+    }
+
+    /// Sets what happens when a connection is requested while all connections to a node are down.
+    #[must_use]
+    pub fn with_reconnect_wait_mode(mut self, reconnect_wait_mode: ReconnectWaitMode) -> Self {
+        self.config.reconnect_wait_mode = reconnect_wait_mode;
+        self
+    }
+
     /// Build the resulting config.
     #[must_use]
     pub fn build(self) -> ConnectionPoolConfig {
@@ -132,6 +267,10 @@ pub(crate) struct ConnectionPoolFactory<
     connection_manager: Arc<CM>,
     keyspace_receiver: Receiver<Option<String>>,
     reconnection_policy: Arc<dyn ReconnectionPolicy + Send + Sync>,
+    task_tracker: Arc<TaskTracker>,
+    // shared by all pools, so bulk connecting to many nodes is limited as a whole
+    connect_permits: Arc<Semaphore>,
+    node_state_listener: Option<Arc<dyn NodeStateListener + Send + Sync>>,
     _transport: PhantomData<T>,
 }
 
@@ -149,10 +288,35 @@ impl<T: CdrsTransport + 'static, CM: ConnectionManager<T> + 'static> ConnectionP
             connection_manager: Arc::new(connection_manager),
             keyspace_receiver,
             reconnection_policy,
+            task_tracker: Default::default(),
+            connect_permits: Arc::new(Semaphore::new(
+                config.connect_concurrency.clamp(1, Semaphore::MAX_PERMITS),
+            )),
+            node_state_listener: None,
             _transport: Default::default(),
         }
     }
 
+    /// Sets the listener notified about established pools and node state changes.
+    pub(crate) fn with_node_state_listener(
+        mut self,
+        node_state_listener: Option<Arc<dyn NodeStateListener + Send + Sync>>,
+    ) -> Self {
+        self.node_state_listener = node_state_listener;
+        self
+    }
+
+    #[inline]
+    pub(crate) fn node_state_listener(&self) -> Option<&(dyn NodeStateListener + Send + Sync)> {
+        self.node_state_listener.as_deref()
+    }
+
+    /// Returns the tracker of background tasks of pools and other session components.
+    #[inline]
+    pub(crate) fn task_tracker(&self) -> &Arc<TaskTracker> {
+        &self.task_tracker
+    }
+
     #[inline]
     pub(crate) fn connection_manager(&self) -> &CM {
         self.connection_manager.as_ref()
@@ -174,14 +338,23 @@ impl<T: CdrsTransport + 'static, CM: ConnectionManager<T> + 'static> ConnectionP
         let pool = Arc::new(
             ConnectionPool::new(
                 &self.connection_manager,
+                self.connect_permits.clone(),
                 broadcast_rpc_address,
                 node_distance,
                 self.config,
+                self.version,
                 error_sender,
             )
             .await?,
         );
 
+        if let Some(listener) = &self.node_state_listener {
+            let connections = pool.pool.read().await.len();
+            if connections > 0 {
+                listener.on_connected(broadcast_rpc_address, connections);
+            }
+        }
+
         let weak_pool = Arc::downgrade(&pool);
 
         Self::monitor_connections(
@@ -189,27 +362,35 @@ impl<T: CdrsTransport + 'static, CM: ConnectionManager<T> + 'static> ConnectionP
             weak_pool.clone(),
             node.clone(),
             self.reconnection_policy.clone(),
+            self.task_tracker.clone(),
         );
 
         Self::start_heartbeat(
-            weak_pool,
+            &self.task_tracker,
+            weak_pool.clone(),
             node,
             self.config.heartbeat_interval,
-            self.version,
+            probe_envelope(&self.config, self.version)?,
         );
 
         // watch for keyspace changes
         let mut keyspace_receiver = self.keyspace_receiver.clone();
-        let pool_clone = pool.clone();
         let version = self.version;
+        let consistency = self.config.system_query_consistency;
 
-        tokio::spawn(async move {
+        self.task_tracker.spawn(async move {
             while let Ok(()) = keyspace_receiver.changed().await {
                 let keyspace = keyspace_receiver.borrow().clone();
                 if let Some(keyspace) = keyspace {
-                    let use_envelope = Arc::new(Envelope::new_req_query(
+                    // a strong reference would keep removed pools alive
+                    let pool_clone = match weak_pool.upgrade() {
+                        Some(pool) => pool,
+                        None => break,
+                    };
+
+                    let use_envelope = match Envelope::new_req_query(
                         format!("USE {}", quote(&keyspace)),
-                        Default::default(),
+                        consistency,
                         None,
                         false,
                         None,
@@ -220,7 +401,13 @@ impl<T: CdrsTransport + 'static, CM: ConnectionManager<T> + 'static> ConnectionP
                         None,
                         Default::default(),
                         version,
-                    ));
+                    ) {
+                        Ok(envelope) => Arc::new(envelope),
+                        Err(error) => {
+                            error!(%error, ?keyspace, "Error creating keyspace request!");
+                            continue;
+                        }
+                    };
 
                     let pool = pool_clone.pool.read().await;
                     join_all(pool.iter()
@@ -241,15 +428,15 @@ impl<T: CdrsTransport + 'static, CM: ConnectionManager<T> + 'static> ConnectionP
     }
 
     fn start_heartbeat(
+        task_tracker: &TaskTracker,
         pool: Weak<ConnectionPool<T, CM>>,
         node: Weak<Node<T, CM>>,
         heartbeat_interval: Duration,
-        version: Version,
+        envelope: Envelope,
     ) {
-        let mut interval = interval_at(Instant::now() + heartbeat_interval, heartbeat_interval);
-        tokio::spawn(async move {
+        task_tracker.spawn(async move {
             loop {
-                interval.tick().await;
+                sleep(heartbeat_interval).await;
 
                 if let Some(node) = node.upgrade() {
                     let broadcast_rpc_address = node.broadcast_address();
@@ -264,14 +451,16 @@ impl<T: CdrsTransport + 'static, CM: ConnectionManager<T> + 'static> ConnectionP
 
                     if state == NodeState::Up {
                         if let Some(pool) = pool.upgrade() {
-                            let envelope = Envelope::new_req_options(version);
-
                             let pool = pool.pool.read().await;
                             for connection in pool.deref() {
-                                if let Err(error) =
-                                    connection.write_envelope(&envelope, false).await
-                                {
-                                    warn!(?broadcast_rpc_address, %error, "Error waiting for heartbeat response - the connection will probably go down.");
+                                match connection.write_envelope(&envelope, false).await {
+                                    Err(error) if is_connection_error(&error) => {
+                                        warn!(?broadcast_rpc_address, %error, "Error waiting for heartbeat response - the connection will probably go down.");
+                                    }
+                                    Err(error) => {
+                                        debug!(?broadcast_rpc_address, %error, "Heartbeat rejected by server - the connection is still alive.");
+                                    }
+                                    Ok(_) => {}
                                 }
                             }
                         } else {
@@ -296,8 +485,10 @@ impl<T: CdrsTransport + 'static, CM: ConnectionManager<T> + 'static> ConnectionP
         pool: Weak<ConnectionPool<T, CM>>,
         node: Weak<Node<T, CM>>,
         reconnection_policy: Arc<dyn ReconnectionPolicy + Send + Sync>,
+        task_tracker: Arc<TaskTracker>,
     ) {
-        tokio::spawn(async move {
+        let reconnection_tracker = task_tracker.clone();
+        task_tracker.spawn(async move {
             let reconnection_state = Arc::new(Atomic::new(ReconnectionState::NotRunning));
             while receiver.recv().await.is_some() {
                 if let Some(node) = node.upgrade() {
@@ -350,7 +541,7 @@ impl<T: CdrsTransport + 'static, CM: ConnectionManager<T> + 'static> ConnectionP
                     let pool = pool.clone();
                     let node = Arc::downgrade(&node);
 
-                    tokio::spawn(async move {
+                    reconnection_tracker.spawn(async move {
                         let new_state =
                             Self::run_reconnection_loop(reconnection_schedule, pool.clone()).await;
 
@@ -419,6 +610,11 @@ impl<T: CdrsTransport + 'static, CM: ConnectionManager<T> + 'static> ConnectionP
         pool: Weak<ConnectionPool<T, CM>>,
     ) -> ReconnectionState {
         while let Some(delay) = reconnection_schedule.next_delay() {
+            debug!(
+                attempt = reconnection_schedule.attempt(),
+                ?delay,
+                "Waiting before reconnecting."
+            );
             sleep(delay).await;
 
             let pool = match pool.upgrade() {
@@ -440,20 +636,30 @@ impl<T: CdrsTransport + 'static, CM: ConnectionManager<T> + 'static> ConnectionP
 
 pub(crate) struct ConnectionPool<T: CdrsTransport, CM: ConnectionManager<T>> {
     connection_manager: Weak<CM>,
+    connect_permits: Arc<Semaphore>,
     broadcast_rpc_address: SocketAddr,
     config: ConnectionPoolConfig,
+    version: Version,
     pool: RwLock<Vec<Arc<T>>>,
     desired_size: usize,
     current_index: AtomicUsize,
     error_sender: mpsc::Sender<Error>,
+    reconnected: Notify,
+    // TLS failures are not going away on their own, so they are reported instead of a generic
+    // error when there are no connections
+    last_tls_error: Mutex<Option<Error>>,
+    // diagnostics of the last failed attempt, cleared when a connection is established
+    last_connection_error: Mutex<Option<ConnectionError>>,
 }
 
 impl<T: CdrsTransport + 'static, CM: ConnectionManager<T>> ConnectionPool<T, CM> {
     async fn new(
         connection_manager: &Arc<CM>,
+        connect_permits: Arc<Semaphore>,
         broadcast_rpc_address: SocketAddr,
         node_distance: NodeDistance,
         config: ConnectionPoolConfig,
+        version: Version,
         error_sender: mpsc::Sender<Error>,
     ) -> CdrsResult<Self> {
         let desired_size = if node_distance == NodeDistance::Local {
@@ -463,9 +669,12 @@ impl<T: CdrsTransport + 'static, CM: ConnectionManager<T>> ConnectionPool<T, CM>
         };
 
         // initialize the pool
+        let mut last_tls_error = None;
+        let mut last_connection_error = None;
         let pool: Vec<_> = join_all((0..desired_size).map(|_| {
             new_connection(
                 connection_manager.as_ref(),
+                &connect_permits,
                 broadcast_rpc_address,
                 config.connect_timeout,
                 error_sender.clone(),
@@ -476,9 +685,19 @@ impl<T: CdrsTransport + 'static, CM: ConnectionManager<T>> ConnectionPool<T, CM>
         .filter_map(|connection| match connection {
             Ok(connection) => Some(Ok(connection)),
             // propagate unrecoverable error
-            Err(Error::InvalidProtocol(addr)) => Some(Err(Error::InvalidProtocol(addr))),
+            Err(ConnectionError {
+                error: Error::InvalidProtocol(addr),
+                ..
+            }) => Some(Err(Error::InvalidProtocol(addr))),
             // skip invalid connections which can be established later
-            Err(_) => None,
+            Err(error) => {
+                if let Error::Tls { .. } = error.error {
+                    last_tls_error = Some(error.error.clone());
+                }
+
+                last_connection_error = Some(error);
+                None
+            }
         })
         .map_ok(Arc::new)
         .try_collect()?;
@@ -494,28 +713,62 @@ impl<T: CdrsTransport + 'static, CM: ConnectionManager<T>> ConnectionPool<T, CM>
 
         Ok(ConnectionPool {
             connection_manager: Arc::downgrade(connection_manager),
+            connect_permits,
             broadcast_rpc_address,
             config,
+            version,
             pool: RwLock::new(pool),
             desired_size,
             current_index: AtomicUsize::new(0),
             error_sender,
+            reconnected: Notify::new(),
+            last_tls_error: Mutex::new(last_tls_error),
+            last_connection_error: Mutex::new(last_connection_error),
         })
     }
 
     pub(crate) async fn connection(&self) -> CdrsResult<Arc<T>> {
-        fn create_no_connections_error(broadcast_rpc_address: SocketAddr) -> Error {
-            warn!(%broadcast_rpc_address, "All connections down to node.");
-            Error::General(format!(
-                "No active connections to: {}",
-                broadcast_rpc_address
-            ))
+        let connection = self.next_connection().await?;
+        match self.config.verify_after_idle {
+            Some(verify_after_idle) if connection.idle_time() > verify_after_idle => {
+                self.verify_idle_connection(connection).await
+            }
+            _ => Ok(connection),
+        }
+    }
+
+    async fn next_connection(&self) -> CdrsResult<Arc<T>> {
+        let wait_time = match self.config.reconnect_wait_mode {
+            ReconnectWaitMode::FailFast => return self.next_live_connection().await,
+            ReconnectWaitMode::Wait(wait_time) => wait_time,
+        };
+
+        // register before checking, so a reconnection finishing in between is not missed
+        let reconnected = self.reconnected.notified();
+        pin!(reconnected);
+        reconnected.as_mut().enable();
+
+        match self.next_live_connection().await {
+            Ok(connection) => Ok(connection),
+            Err(error) => {
+                debug!(broadcast_rpc_address = ?self.broadcast_rpc_address, "Waiting for reconnection.");
+
+                // dropping the waiting future doesn't affect the reconnection, which runs in its
+                // own task
+                if timeout(wait_time, reconnected).await.is_err() {
+                    return Err(error);
+                }
+
+                self.next_live_connection().await
+            }
         }
+    }
 
+    async fn next_live_connection(&self) -> CdrsResult<Arc<T>> {
         let pool = self.pool.read().await;
         let pool_len = pool.len();
         if pool_len == 0 {
-            return Err(create_no_connections_error(self.broadcast_rpc_address));
+            return Err(self.create_no_connections_error());
         }
 
         let mut index = self.current_index.fetch_add(1, Ordering::Relaxed) % pool_len;
@@ -531,9 +784,76 @@ impl<T: CdrsTransport + 'static, CM: ConnectionManager<T>> ConnectionPool<T, CM>
 
             if index == first_index {
                 // we've checked the whole pool and everything's down
-                return Err(create_no_connections_error(self.broadcast_rpc_address));
+                return Err(self.create_no_connections_error());
+            }
+        }
+    }
+
+    /// Returns diagnostics of the most recent failed connection attempt, if any.
+    pub(crate) fn last_connection_error(&self) -> Option<ConnectionError> {
+        self.last_connection_error.lock().unwrap().clone()
+    }
+
+    fn create_no_connections_error(&self) -> Error {
+        let broadcast_rpc_address = self.broadcast_rpc_address;
+        warn!(%broadcast_rpc_address, "All connections down to node.");
+
+        self.last_tls_error
+            .lock()
+            .unwrap()
+            .clone()
+            .unwrap_or_else(|| {
+                Error::General(format!(
+                    "No active connections to: {}",
+                    broadcast_rpc_address
+                ))
+            })
+    }
+
+    async fn verify_idle_connection(&self, connection: Arc<T>) -> CdrsResult<Arc<T>> {
+        let broadcast_rpc_address = self.broadcast_rpc_address;
+        let envelope = probe_envelope(&self.config, self.version)?;
+
+        match timeout(
+            self.config.verify_timeout,
+            connection.write_envelope(&envelope, false),
+        )
+        .await
+        {
+            Ok(Ok(_)) => return Ok(connection),
+            Ok(Err(error)) if !is_connection_error(&error) => {
+                debug!(?broadcast_rpc_address, %error, "Idle connection verification rejected by server - the connection is still alive.");
+                return Ok(connection);
+            }
+            Ok(Err(error)) => {
+                warn!(?broadcast_rpc_address, %error, "Idle connection verification failed - reconnecting.")
             }
+            Err(_) => {
+                warn!(
+                    ?broadcast_rpc_address,
+                    "Timeout verifying idle connection - reconnecting."
+                )
+            }
+        }
+
+        let connection_manager = self
+            .connection_manager
+            .upgrade()
+            .ok_or_else(|| Error::General("Connection manager is gone!".into()))?;
+
+        let new_connection = self.establish_connection(&connection_manager).await?;
+
+        // the stale connection might have been replaced concurrently, in which case the new one is
+        // only used for the current request
+        let mut pool = self.pool.write().await;
+        if let Some(stale_connection) = pool
+            .iter_mut()
+            .find(|pool_connection| Arc::ptr_eq(pool_connection, &connection))
+        {
+            *stale_connection = new_connection.clone();
         }
+
+        Ok(new_connection)
     }
 
     pub(crate) async fn is_any_connection_up(&self) -> bool {
@@ -547,44 +867,640 @@ impl<T: CdrsTransport + 'static, CM: ConnectionManager<T>> ConnectionPool<T, CM>
         false
     }
 
+    // checked without waiting for the pool lock - pools being modified are not considered
+    // disconnected, since they're about to get a new connection or lose a broken one
+    pub(crate) fn is_disconnected(&self) -> bool {
+        self.pool
+            .try_read()
+            .map(|connections| connections.iter().all(|connection| connection.is_broken()))
+            .unwrap_or(false)
+    }
+
+    // connections are established without holding the pool lock, so requests can still use the
+    // remaining connections in the meantime, and waiting ones are woken up by the first new one
     async fn reconnect_broken(&self) -> CdrsResult<bool> {
-        if let Some(connection_manager) = self.connection_manager.upgrade() {
+        let connection_manager = match self.connection_manager.upgrade() {
+            Some(connection_manager) => connection_manager,
+            // connection manager is gone - we're probably dropping the session
+            None => return Ok(false),
+        };
+
+        // 1. try to reconnect broken
+        let broken_connections = self
+            .pool
+            .read()
+            .await
+            .iter()
+            .filter(|connection| connection.is_broken())
+            .cloned()
+            .collect_vec();
+
+        for broken_connection in broken_connections {
+            let connection = self.establish_connection(&connection_manager).await?;
+
+            // the broken connection might have been replaced concurrently, e.g. by idle
+            // verification, in which case the new one is not needed
+            if let Some(pool_connection) = self
+                .pool
+                .write()
+                .await
+                .iter_mut()
+                .find(|pool_connection| Arc::ptr_eq(pool_connection, &broken_connection))
+            {
+                *pool_connection = connection;
+            }
+
+            self.reconnected.notify_waiters();
+        }
+
+        // 2. try to fill missing
+        while self.pool.read().await.len() < self.desired_size {
+            let connection = self.establish_connection(&connection_manager).await?;
+
             let mut pool = self.pool.write().await;
+            if pool.len() < self.desired_size {
+                pool.push(connection);
+            }
 
-            // 1. try to reconnect broken
-            for connection in pool.deref_mut() {
-                if connection.is_broken() {
-                    *connection = Arc::new(
-                        new_connection(
-                            connection_manager.as_ref(),
-                            self.broadcast_rpc_address,
-                            self.config.connect_timeout,
-                            self.error_sender.clone(),
-                        )
-                        .await?,
-                    );
-                }
+            drop(pool);
+            self.reconnected.notify_waiters();
+        }
+
+        // at this point either all connections are up or some might have died in the meantime,
+        // which will trigger a new reconnection
+        Ok(true)
+    }
+
+    pub(crate) async fn connections(&self) -> Vec<Arc<T>> {
+        self.pool.read().await.clone()
+    }
+
+    // connections are replaced one by one, so the pool keeps serving requests in the meantime -
+    // replaced connections are closed once requests in flight on them complete
+    pub(crate) async fn recycle(&self) -> CdrsResult<()> {
+        let connection_manager = match self.connection_manager.upgrade() {
+            Some(connection_manager) => connection_manager,
+            None => return Ok(()),
+        };
+
+        for old_connection in self.connections().await {
+            let connection = self.establish_connection(&connection_manager).await?;
+
+            // the old connection might have been replaced concurrently, e.g. by reconnection, in
+            // which case the pool already has a new one
+            if let Some(pool_connection) = self
+                .pool
+                .write()
+                .await
+                .iter_mut()
+                .find(|pool_connection| Arc::ptr_eq(pool_connection, &old_connection))
+            {
+                *pool_connection = connection;
             }
+        }
+
+        Ok(())
+    }
+
+    async fn establish_connection(&self, connection_manager: &CM) -> CdrsResult<Arc<T>> {
+        let result = new_connection(
+            connection_manager,
+            &self.connect_permits,
+            self.broadcast_rpc_address,
+            self.config.connect_timeout,
+            self.error_sender.clone(),
+        )
+        .await;
 
-            // 2. try to fill missing
-            for _ in pool.len()..self.desired_size {
-                pool.push(Arc::new(
-                    new_connection(
-                        connection_manager.as_ref(),
-                        self.broadcast_rpc_address,
-                        self.config.connect_timeout,
-                        self.error_sender.clone(),
-                    )
-                    .await?,
-                ));
+        match &result {
+            Ok(_) => {
+                *self.last_tls_error.lock().unwrap() = None;
+                *self.last_connection_error.lock().unwrap() = None;
             }
+            Err(error) => {
+                if let Error::Tls { .. } = error.error {
+                    *self.last_tls_error.lock().unwrap() = Some(error.error.clone());
+                }
 
-            // at this point either all connections are up or some might have died in the meantime,
-            // which will trigger a new reconnection
-            Ok(true)
-        } else {
-            // connection manager is gone - we're probably dropping the session
-            Ok(false)
+                *self.last_connection_error.lock().unwrap() = Some(error.clone());
+            }
         }
+
+        result.map(Arc::new).map_err(Error::from)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use cassandra_protocol::consistency::Consistency;
+    use cassandra_protocol::error::{Error, TlsErrorKind};
+    use cassandra_protocol::frame::message_error::{ErrorBody, ErrorType};
+    use cassandra_protocol::frame::message_query::BodyReqQuery;
+    use cassandra_protocol::frame::message_request::RequestBody;
+    use cassandra_protocol::frame::{Opcode, Version};
+    use cassandra_protocol::query::QueryParams;
+    use std::io;
+    use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::{Arc, Mutex};
+    use std::time::{Duration, Instant};
+    use tokio::time::{sleep, timeout};
+
+    use crate::cluster::connection_manager::MockConnectionManager;
+    use crate::cluster::connection_pool::{
+        probe_envelope, ConnectionPoolConfig, ConnectionPoolConfigBuilder, ReconnectWaitMode,
+    };
+    use crate::cluster::test_nodes::{connection_pool_factory, local_node, TestNode};
+    use crate::cluster::topology::NodeState;
+    use crate::cluster::{ConnectionPhase, NodeStateListener};
+    use crate::retry::ConstantReconnectionPolicy;
+    use crate::transport::{CdrsTransport, MockCdrsTransport};
+
+    const ADDR: SocketAddr = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)), 9042);
+
+    // the first connection attempt fails, so the pool starts empty and reconnects in the background
+    fn create_node(
+        reconnect_wait_mode: ReconnectWaitMode,
+        attempts: Arc<AtomicUsize>,
+    ) -> Arc<TestNode> {
+        let mut connection_manager = MockConnectionManager::<MockCdrsTransport>::new();
+        connection_manager
+            .expect_connection()
+            .returning(move |_, _, addr| {
+                if attempts.fetch_add(1, Ordering::SeqCst) == 0 {
+                    return Box::pin(async {
+                        Err(Error::Io(io::Error::new(
+                            io::ErrorKind::ConnectionRefused,
+                            "connection refused",
+                        )))
+                    });
+                }
+
+                let mut transport = MockCdrsTransport::new();
+                transport.expect_is_broken().return_const(false);
+                transport.expect_address().return_const(addr);
+                transport.expect_idle_time().return_const(Duration::ZERO);
+
+                Box::pin(async move { Ok(transport) })
+            });
+
+        create_node_with_manager(connection_manager, reconnect_wait_mode)
+    }
+
+    fn create_node_with_manager(
+        connection_manager: MockConnectionManager<MockCdrsTransport>,
+        reconnect_wait_mode: ReconnectWaitMode,
+    ) -> Arc<TestNode> {
+        create_node_with_config(
+            connection_manager,
+            ConnectionPoolConfigBuilder::new()
+                .with_local_size(1)
+                .with_reconnect_wait_mode(reconnect_wait_mode)
+                .build(),
+        )
+    }
+
+    fn create_node_with_config(
+        connection_manager: MockConnectionManager<MockCdrsTransport>,
+        config: ConnectionPoolConfig,
+    ) -> Arc<TestNode> {
+        local_node(
+            Arc::new(connection_pool_factory(
+                config,
+                connection_manager,
+                Arc::new(ConstantReconnectionPolicy::new(Duration::from_millis(100))),
+            )),
+            ADDR,
+        )
+    }
+
+    #[derive(Default)]
+    struct RecordingListener {
+        connected: Mutex<Vec<(SocketAddr, usize)>>,
+        states: Mutex<Vec<(SocketAddr, NodeState)>>,
+    }
+
+    impl NodeStateListener for RecordingListener {
+        fn on_connected(&self, broadcast_rpc_address: SocketAddr, connections: usize) {
+            self.connected
+                .lock()
+                .unwrap()
+                .push((broadcast_rpc_address, connections));
+        }
+
+        fn on_state_changed(&self, broadcast_rpc_address: SocketAddr, state: NodeState) {
+            self.states
+                .lock()
+                .unwrap()
+                .push((broadcast_rpc_address, state));
+        }
+    }
+
+    #[tokio::test]
+    async fn should_limit_concurrent_connections_across_pools() {
+        let in_flight = Arc::new(AtomicUsize::new(0));
+        let max_in_flight = Arc::new(AtomicUsize::new(0));
+
+        let mut connection_manager = MockConnectionManager::<MockCdrsTransport>::new();
+        connection_manager.expect_connection().returning({
+            let in_flight = in_flight.clone();
+            let max_in_flight = max_in_flight.clone();
+            move |_, _, addr| {
+                let in_flight = in_flight.clone();
+                let max_in_flight = max_in_flight.clone();
+                Box::pin(async move {
+                    let current = in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+                    max_in_flight.fetch_max(current, Ordering::SeqCst);
+                    sleep(Duration::from_millis(20)).await;
+                    in_flight.fetch_sub(1, Ordering::SeqCst);
+
+                    let mut transport = MockCdrsTransport::new();
+                    transport.expect_is_broken().return_const(false);
+                    transport.expect_address().return_const(addr);
+                    transport.expect_idle_time().return_const(Duration::ZERO);
+                    Ok(transport)
+                })
+            }
+        });
+
+        let listener = Arc::new(RecordingListener::default());
+        let connection_pool_factory = Arc::new(
+            connection_pool_factory(
+                ConnectionPoolConfigBuilder::new()
+                    .with_local_size(4)
+                    .with_connect_concurrency(3)
+                    .build(),
+                connection_manager,
+                Arc::new(ConstantReconnectionPolicy::new(Duration::from_millis(100))),
+            )
+            .with_node_state_listener(Some(listener.clone())),
+        );
+
+        let nodes: Vec<_> = (0..3)
+            .map(|port| {
+                local_node(
+                    connection_pool_factory.clone(),
+                    SocketAddr::new(ADDR.ip(), port),
+                )
+            })
+            .collect();
+
+        futures::future::try_join_all(nodes.iter().map(|node| node.persistent_connection()))
+            .await
+            .unwrap();
+
+        assert_eq!(max_in_flight.load(Ordering::SeqCst), 3);
+
+        let mut connected = listener.connected.lock().unwrap().clone();
+        connected.sort_unstable();
+        assert_eq!(
+            connected,
+            (0..3)
+                .map(|port| (SocketAddr::new(ADDR.ip(), port), 4))
+                .collect::<Vec<_>>()
+        );
+
+        nodes[0].mark_down();
+        nodes[0].mark_down();
+        assert_eq!(
+            *listener.states.lock().unwrap(),
+            vec![(SocketAddr::new(ADDR.ip(), 0), NodeState::Down)]
+        );
+    }
+
+    #[tokio::test]
+    async fn should_wait_for_background_reconnection() {
+        let attempts = Arc::new(AtomicUsize::new(0));
+        let node = create_node(
+            ReconnectWaitMode::Wait(Duration::from_secs(5)),
+            attempts.clone(),
+        );
+
+        // cancelling a waiting request doesn't abort the reconnection
+        assert!(
+            timeout(Duration::from_millis(10), node.persistent_connection())
+                .await
+                .is_err()
+        );
+
+        let (first, second) =
+            tokio::join!(node.persistent_connection(), node.persistent_connection());
+        assert!(first.is_ok());
+        assert!(second.is_ok());
+
+        // a single reconnection served all waiting requests
+        assert_eq!(attempts.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn should_fail_fast_while_reconnecting() {
+        let node = create_node(ReconnectWaitMode::FailFast, Arc::new(AtomicUsize::new(0)));
+
+        // waiting for the reconnection, which succeeds, would return a connection - the bound is
+        // generous, since timers might be delayed on a busy machine
+        let start = Instant::now();
+        assert!(node.persistent_connection().await.is_err());
+        assert!(start.elapsed() < Duration::from_secs(2));
+
+        // the reconnection still proceeds in the background
+        timeout(Duration::from_secs(5), async {
+            while node.persistent_connection().await.is_err() {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        })
+        .await
+        .unwrap();
+    }
+
+    #[tokio::test]
+    async fn should_report_disconnected_nodes() {
+        let node = create_node(ReconnectWaitMode::FailFast, Arc::new(AtomicUsize::new(0)));
+
+        // nodes are not disconnected before trying to connect
+        assert!(!node.is_disconnected());
+
+        assert!(node.persistent_connection().await.is_err());
+        assert!(node.is_disconnected());
+
+        timeout(Duration::from_secs(5), async {
+            while node.is_disconnected() {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        })
+        .await
+        .unwrap();
+        assert!(node.persistent_connection().await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn should_report_tls_errors_after_reconnection() {
+        let attempts = Arc::new(AtomicUsize::new(0));
+
+        let mut connection_manager = MockConnectionManager::<MockCdrsTransport>::new();
+        connection_manager.expect_connection().returning({
+            let attempts = attempts.clone();
+            move |_, _, addr| {
+                attempts.fetch_add(1, Ordering::SeqCst);
+                Box::pin(async move {
+                    Err(Error::Tls {
+                        node: addr,
+                        kind: TlsErrorKind::CertificateVerification,
+                        details: "invalid peer certificate: UnknownIssuer".into(),
+                    })
+                })
+            }
+        });
+
+        let node = create_node_with_manager(connection_manager, ReconnectWaitMode::FailFast);
+        assert!(matches!(
+            node.persistent_connection().await,
+            Err(Error::Tls {
+                kind: TlsErrorKind::CertificateVerification,
+                ..
+            })
+        ));
+
+        // reconnection attempts fail the same way
+        timeout(Duration::from_secs(5), async {
+            while attempts.load(Ordering::SeqCst) < 2 {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        })
+        .await
+        .unwrap();
+
+        let error = match node.persistent_connection().await {
+            Err(error) => error,
+            Ok(_) => panic!("Unexpected connection"),
+        };
+        assert!(error
+            .to_string()
+            .starts_with("TLS certificate verification failed for node 127.0.0.1:9042"));
+    }
+    #[tokio::test]
+    async fn should_keep_diagnostics_of_failed_connection_attempts() {
+        let attempts = Arc::new(AtomicUsize::new(0));
+
+        let mut connection_manager = MockConnectionManager::<MockCdrsTransport>::new();
+        connection_manager.expect_connection().returning({
+            let attempts = attempts.clone();
+            move |_, _, addr| {
+                if attempts.fetch_add(1, Ordering::SeqCst) == 0 {
+                    return Box::pin(async move { Err(Error::AuthenticationTimeout(addr)) });
+                }
+
+                let mut transport = MockCdrsTransport::new();
+                transport.expect_is_broken().return_const(false);
+                transport.expect_address().return_const(addr);
+                transport.expect_idle_time().return_const(Duration::ZERO);
+
+                Box::pin(async move { Ok(transport) })
+            }
+        });
+
+        let node = create_node_with_manager(connection_manager, ReconnectWaitMode::FailFast);
+        assert!(node.persistent_connection().await.is_err());
+        assert_eq!(attempts.load(Ordering::SeqCst), 1);
+
+        let error = node.last_connection_error().unwrap();
+        assert_eq!(error.address, ADDR);
+        assert_eq!(error.phase, ConnectionPhase::Authenticate);
+        assert!(matches!(error.error, Error::AuthenticationTimeout(_)));
+
+        // diagnostics are cleared once reconnection succeeds
+        timeout(Duration::from_secs(5), async {
+            while node.persistent_connection().await.is_err() {
+                sleep(Duration::from_millis(10)).await;
+            }
+        })
+        .await
+        .unwrap();
+        assert!(node.last_connection_error().is_none());
+    }
+
+    #[test]
+    fn should_probe_with_query_when_options_are_disabled() {
+        let config = ConnectionPoolConfig::default();
+        assert_eq!(
+            probe_envelope(&config, Version::V4).unwrap().opcode,
+            Opcode::Options
+        );
+
+        let config = config
+            .with_options_probe(false)
+            .with_system_query_consistency(Consistency::LocalOne);
+        let envelope = probe_envelope(&config, Version::V4).unwrap();
+        assert_eq!(envelope.opcode, Opcode::Query);
+
+        let body = envelope.request_body().unwrap();
+        assert!(matches!(
+            body,
+            RequestBody::Query(BodyReqQuery {
+                query_params: QueryParams {
+                    consistency: Consistency::LocalOne,
+                    ..
+                },
+                ..
+            })
+        ));
+    }
+
+    #[tokio::test]
+    async fn should_keep_idle_connection_rejecting_probe() {
+        let attempts = Arc::new(AtomicUsize::new(0));
+        let probes = Arc::new(AtomicUsize::new(0));
+
+        let mut connection_manager = MockConnectionManager::<MockCdrsTransport>::new();
+        connection_manager.expect_connection().returning({
+            let attempts = attempts.clone();
+            let probes = probes.clone();
+            move |_, _, addr| {
+                attempts.fetch_add(1, Ordering::SeqCst);
+                let probes = probes.clone();
+
+                let mut transport = MockCdrsTransport::new();
+                transport.expect_is_broken().return_const(false);
+                transport.expect_address().return_const(addr);
+                transport
+                    .expect_idle_time()
+                    .return_const(Duration::from_secs(60));
+                transport
+                    .expect_write_envelope()
+                    .withf(|envelope, _| envelope.opcode == Opcode::Query)
+                    .returning(move |_, _| {
+                        probes.fetch_add(1, Ordering::SeqCst);
+                        Box::pin(async move {
+                            Err(Error::Server {
+                                body: ErrorBody {
+                                    message: "".into(),
+                                    ty: ErrorType::Unauthorized,
+                                },
+                                addr,
+                            })
+                        })
+                    });
+
+                Box::pin(async move { Ok(transport) })
+            }
+        });
+
+        let node = create_node_with_config(
+            connection_manager,
+            ConnectionPoolConfigBuilder::new()
+                .with_local_size(1)
+                .with_verify_after_idle(Some(Duration::from_secs(1)))
+                .build()
+                .with_options_probe(false),
+        );
+
+        assert!(node.persistent_connection().await.is_ok());
+        assert!(node.persistent_connection().await.is_ok());
+
+        // rejected probes don't cause reconnection
+        assert_eq!(probes.load(Ordering::SeqCst), 2);
+        assert_eq!(attempts.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn should_reconnect_idle_connection_failing_verification() {
+        let attempts = Arc::new(AtomicUsize::new(0));
+        let probes = Arc::new(AtomicUsize::new(0));
+
+        // the first connection is stale and fails the probe, while the replacement isn't idle
+        let mut connection_manager = MockConnectionManager::<MockCdrsTransport>::new();
+        connection_manager.expect_connection().returning({
+            let attempts = attempts.clone();
+            let probes = probes.clone();
+            move |_, _, addr| {
+                let stale = attempts.fetch_add(1, Ordering::SeqCst) == 0;
+                let probes = probes.clone();
+
+                let mut transport = MockCdrsTransport::new();
+                transport.expect_is_broken().return_const(false);
+                transport.expect_address().return_const(addr);
+                transport.expect_idle_time().return_const(if stale {
+                    Duration::from_secs(60)
+                } else {
+                    Duration::ZERO
+                });
+                transport
+                    .expect_write_envelope()
+                    .withf(|envelope, _| envelope.opcode == Opcode::Options)
+                    .returning(move |_, _| {
+                        probes.fetch_add(1, Ordering::SeqCst);
+                        Box::pin(async {
+                            Err(Error::Io(io::Error::new(
+                                io::ErrorKind::BrokenPipe,
+                                "broken pipe",
+                            )))
+                        })
+                    });
+
+                Box::pin(async move { Ok(transport) })
+            }
+        });
+
+        let node = create_node_with_config(
+            connection_manager,
+            ConnectionPoolConfigBuilder::new()
+                .with_local_size(1)
+                .with_verify_after_idle(Some(Duration::from_secs(1)))
+                .build(),
+        );
+
+        let connection = node.persistent_connection().await.unwrap();
+        assert_eq!(probes.load(Ordering::SeqCst), 1);
+        assert_eq!(attempts.load(Ordering::SeqCst), 2);
+
+        // the request got the fresh connection, which replaced the stale one in the pool
+        let pooled_connections = node.pooled_connections().await;
+        assert_eq!(pooled_connections.len(), 1);
+        assert!(Arc::ptr_eq(&pooled_connections[0], &connection));
+        assert_eq!(connection.idle_time(), Duration::ZERO);
+
+        // the fresh connection is used without verification
+        let next_connection = node.persistent_connection().await.unwrap();
+        assert!(Arc::ptr_eq(&next_connection, &connection));
+        assert_eq!(probes.load(Ordering::SeqCst), 1);
+        assert_eq!(attempts.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn should_recycle_connections() {
+        let attempts = Arc::new(AtomicUsize::new(0));
+
+        let mut connection_manager = MockConnectionManager::<MockCdrsTransport>::new();
+        connection_manager.expect_connection().returning({
+            let attempts = attempts.clone();
+            move |_, _, addr| {
+                attempts.fetch_add(1, Ordering::SeqCst);
+
+                let mut transport = MockCdrsTransport::new();
+                transport.expect_is_broken().return_const(false);
+                transport.expect_address().return_const(addr);
+                transport.expect_idle_time().return_const(Duration::ZERO);
+
+                Box::pin(async move { Ok(transport) })
+            }
+        });
+
+        let node = create_node_with_config(
+            connection_manager,
+            ConnectionPoolConfigBuilder::new()
+                .with_local_size(2)
+                .build(),
+        );
+
+        node.persistent_connection().await.unwrap();
+        let old_connections = node.pooled_connections().await;
+
+        node.recycle_connections().await.unwrap();
+        let new_connections = node.pooled_connections().await;
+
+        assert_eq!(new_connections.len(), 2);
+        assert!(new_connections.iter().all(|new_connection| !old_connections
+            .iter()
+            .any(|old_connection| Arc::ptr_eq(old_connection, new_connection))));
+        assert_eq!(attempts.load(Ordering::SeqCst), 4);
     }
 }
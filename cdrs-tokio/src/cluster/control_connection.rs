@@ -1,23 +1,24 @@
-use derive_more::Constructor;
 use std::sync::Arc;
 use std::time::Duration;
 use tokio::sync::broadcast::Sender;
 use tokio::sync::mpsc::{channel, Receiver};
-use tokio::time::sleep;
 use tracing::*;
 
+use crate::cluster::event_registration::EVENT_CHANNEL_CAPACITY;
 use crate::cluster::topology::Node;
-use crate::cluster::{ClusterMetadataManager, ConnectionManager, SessionContext};
+use crate::cluster::{
+    ClusterMetadataManager, ConnectionManager, RegistrationsRestored, SessionContext,
+};
 use crate::load_balancing::LoadBalancingStrategy;
 use crate::retry::{ReconnectionPolicy, ReconnectionSchedule};
+use crate::runtime::{sleep, TaskTracker};
 use crate::transport::CdrsTransport;
+use cassandra_protocol::error::Error;
 use cassandra_protocol::events::{ServerEvent, SimpleServerEvent};
 use cassandra_protocol::frame::{Envelope, Version};
 
 const DEFAULT_RECONNECT_DELAY: Duration = Duration::from_secs(10);
-const EVENT_CHANNEL_CAPACITY: usize = 32;
 
-#[derive(Constructor)]
 pub(crate) struct ControlConnection<
     T: CdrsTransport + 'static,
     CM: ConnectionManager<T> + 'static,
@@ -28,6 +29,7 @@ pub(crate) struct ControlConnection<
     reconnection_policy: Arc<dyn ReconnectionPolicy + Send + Sync>,
     cluster_metadata_manager: Arc<ClusterMetadataManager<T, CM>>,
     event_sender: Sender<ServerEvent>,
+    registrations_sender: Sender<RegistrationsRestored>,
     session_context: Arc<SessionContext<T>>,
     version: Version,
 }
@@ -38,12 +40,46 @@ impl<
         LB: LoadBalancingStrategy<T, CM> + Send + Sync,
     > ControlConnection<T, CM, LB>
 {
+    #[allow(clippy::too_many_arguments)]
+    pub(crate) fn new(
+        load_balancing: Arc<LB>,
+        contact_points: Vec<Arc<Node<T, CM>>>,
+        reconnection_policy: Arc<dyn ReconnectionPolicy + Send + Sync>,
+        cluster_metadata_manager: Arc<ClusterMetadataManager<T, CM>>,
+        event_sender: Sender<ServerEvent>,
+        registrations_sender: Sender<RegistrationsRestored>,
+        session_context: Arc<SessionContext<T>>,
+        version: Version,
+    ) -> Self {
+        ControlConnection {
+            load_balancing,
+            contact_points,
+            reconnection_policy,
+            cluster_metadata_manager,
+            event_sender,
+            registrations_sender,
+            session_context,
+            version,
+        }
+    }
+
     pub(crate) async fn run(self, init_complete_sender: tokio::sync::oneshot::Sender<()>) {
         let (event_envelope_sender, event_envelope_receiver) = channel(EVENT_CHANNEL_CAPACITY);
         let (error_sender, mut error_receiver) = channel(1);
 
-        Self::process_events(event_envelope_receiver, self.event_sender.clone());
+        Self::process_events(
+            self.cluster_metadata_manager.task_tracker(),
+            event_envelope_receiver,
+            self.event_sender.clone(),
+        );
         let mut init_complete_sender = Some(init_complete_sender);
+        let events = vec![
+            SimpleServerEvent::SchemaChange,
+            SimpleServerEvent::StatusChange,
+            SimpleServerEvent::TopologyChange,
+        ];
+        // registrations are restored when a connection replaces a registered one
+        let mut registered = false;
 
         'listen: loop {
             let current_connection = self
@@ -52,14 +88,7 @@ impl<
                 .load()
                 .clone();
             if let Some(current_connection) = current_connection {
-                let register_envelope = Envelope::new_req_register(
-                    vec![
-                        SimpleServerEvent::SchemaChange,
-                        SimpleServerEvent::StatusChange,
-                        SimpleServerEvent::TopologyChange,
-                    ],
-                    self.version,
-                );
+                let register_envelope = Envelope::new_req_register(events.clone(), self.version);
 
                 // in case of error, simply reconnect
                 let result = current_connection
@@ -70,6 +99,16 @@ impl<
                 }
                 match result {
                     Ok(_) => {
+                        if registered {
+                            // nobody might be listening
+                            let _ = self.registrations_sender.send(RegistrationsRestored {
+                                node: current_connection.address(),
+                                events: events.clone(),
+                            });
+                        }
+
+                        registered = true;
+
                         let error = error_receiver.recv().await;
                         match error {
                             Some(error) => {
@@ -152,21 +191,40 @@ impl<
     async fn wait_for_reconnection(schedule: &mut Box<dyn ReconnectionSchedule + Send + Sync>) {
         // as long as the session is alive, try establishing control connection
         let delay = schedule.next_delay().unwrap_or(DEFAULT_RECONNECT_DELAY);
+        debug!(
+            attempt = schedule.attempt(),
+            ?delay,
+            "Waiting before reconnecting control connection."
+        );
         sleep(delay).await;
     }
 
     fn process_events(
+        task_tracker: &TaskTracker,
         mut event_envelope_receiver: Receiver<Envelope>,
         event_sender: Sender<ServerEvent>,
     ) {
-        tokio::spawn(async move {
+        task_tracker.spawn(async move {
             while let Some(envelope) = event_envelope_receiver.recv().await {
-                if let Ok(body) = envelope.response_body() {
-                    if let Some(event) = body.into_server_event() {
-                        let _ = event_sender.send(event.event);
-                    }
+                if let Some(event) = server_event(envelope) {
+                    let _ = event_sender.send(event);
                 }
             }
         });
     }
 }
+
+/// Extracts a server event from given envelope, skipping unknown and invalid ones.
+pub(crate) fn server_event(envelope: Envelope) -> Option<ServerEvent> {
+    match envelope.response_body() {
+        Ok(body) => body.into_server_event().map(|event| event.event),
+        Err(Error::UnknownServerEvent(event_type)) => {
+            warn!(%event_type, "Skipping unknown server event.");
+            None
+        }
+        Err(error) => {
+            warn!(%error, "Skipping invalid server event.");
+            None
+        }
+    }
+}
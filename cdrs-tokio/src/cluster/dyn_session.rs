@@ -0,0 +1,732 @@
+use cassandra_protocol::compression::{Compression, CompressionMode};
+use cassandra_protocol::error;
+use cassandra_protocol::events::ServerEvent;
+use cassandra_protocol::frame::message_response::ParsedResponse;
+use cassandra_protocol::frame::message_result::{BodyResResultPrepared, SchemaChange};
+use cassandra_protocol::frame::Envelope;
+use cassandra_protocol::query::{PreparedQuery, QueryBatch, QueryValues};
+use derivative::Derivative;
+use futures::FutureExt;
+use fxhash::FxHashMap;
+use std::borrow::{Borrow, Cow};
+use std::net::SocketAddr;
+use std::sync::Arc;
+use tokio::sync::broadcast::Receiver;
+
+use crate::cluster::execute_concurrent::{execute_in_order, execute_summarized};
+use crate::cluster::prepare_all::prepare_concurrently;
+use crate::cluster::session::Session;
+use crate::cluster::{ConcurrentErrorMode, ConcurrentExecutionError};
+use crate::cluster::{ConnectionManager, DynSessionPager, WarmupError, WarmupReport};
+use crate::cluster::{PrepareAllError, WarningPolicy, DEFAULT_PREPARE_CONCURRENCY};
+use crate::cluster::{StatementRequest, StatementTarget};
+use crate::future::BoxFuture;
+use crate::load_balancing::LoadBalancingStrategy;
+use crate::retry::RetryPolicy;
+use crate::statement::{StatementParams, StatementParamsBuilder};
+use crate::transport::CdrsTransport;
+
+/// Object-safe subset of session operations, which do not depend on session generic parameters.
+trait SessionOps: Send + Sync {
+    fn exec_with_params_parsed<'a>(
+        &'a self,
+        prepared: &'a PreparedQuery,
+        parameters: &'a StatementParams,
+    ) -> BoxFuture<'a, error::Result<ParsedResponse>>;
+
+    fn prepare_raw_tw<'a>(
+        &'a self,
+        query: Cow<'a, str>,
+        keyspace: Option<String>,
+        with_tracing: bool,
+        with_warnings: bool,
+        beta_protocol: bool,
+    ) -> BoxFuture<'a, error::Result<BodyResResultPrepared>>;
+
+    fn prepare_tw<'a>(
+        &'a self,
+        query: Cow<'a, str>,
+        keyspace: Option<String>,
+        with_tracing: bool,
+        with_warnings: bool,
+        beta_protocol: bool,
+    ) -> BoxFuture<'a, error::Result<PreparedQuery>>;
+
+    fn batch_with_params<'a>(
+        &'a self,
+        batch: &'a QueryBatch,
+        parameters: &'a StatementParams,
+    ) -> BoxFuture<'a, error::Result<Envelope>>;
+
+    fn query_with_params<'a>(
+        &'a self,
+        query: Cow<'a, str>,
+        parameters: StatementParams,
+    ) -> BoxFuture<'a, error::Result<Envelope>>;
+
+    fn count<'a>(&'a self, query_or_table: &'a str) -> BoxFuture<'a, error::Result<u64>>;
+
+    fn execute_schema<'a>(
+        &'a self,
+        query: Cow<'a, str>,
+    ) -> BoxFuture<'a, error::Result<Option<SchemaChange>>>;
+
+    fn send_raw(
+        &self,
+        envelope: Envelope,
+        is_idempotent: bool,
+    ) -> BoxFuture<'_, error::Result<Envelope>>;
+
+    fn send_raw_to_node(
+        &self,
+        broadcast_rpc_address: SocketAddr,
+        envelope: Envelope,
+    ) -> BoxFuture<'_, error::Result<Envelope>>;
+
+    fn current_keyspace(&self) -> Option<Arc<String>>;
+
+    fn compression_mode(&self) -> Option<CompressionMode>;
+
+    fn compression(&self) -> Option<Compression>;
+
+    fn set_compression(&self, compression: Compression) -> error::Result<()>;
+
+    fn connection_compressions(&self) -> BoxFuture<'_, FxHashMap<Compression, usize>>;
+
+    fn recycle_connections(&self) -> BoxFuture<'_, error::Result<()>>;
+
+    fn shutdown(&self);
+
+    fn is_shut_down(&self) -> bool;
+
+    fn warning_policy(&self) -> WarningPolicy;
+
+    fn create_event_receiver(&self) -> Receiver<ServerEvent>;
+
+    fn retry_policy(&self) -> &dyn RetryPolicy;
+
+    fn warmup(&self) -> BoxFuture<'_, Result<WarmupReport, WarmupError>>;
+}
+
+impl<
+        T: CdrsTransport + 'static,
+        CM: ConnectionManager<T> + Send + Sync + 'static,
+        LB: LoadBalancingStrategy<T, CM> + Send + Sync + 'static,
+    > SessionOps for Session<T, CM, LB>
+{
+    fn exec_with_params_parsed<'a>(
+        &'a self,
+        prepared: &'a PreparedQuery,
+        parameters: &'a StatementParams,
+    ) -> BoxFuture<'a, error::Result<ParsedResponse>> {
+        Session::exec_with_params_parsed(self, prepared, parameters).boxed()
+    }
+
+    fn prepare_raw_tw<'a>(
+        &'a self,
+        query: Cow<'a, str>,
+        keyspace: Option<String>,
+        with_tracing: bool,
+        with_warnings: bool,
+        beta_protocol: bool,
+    ) -> BoxFuture<'a, error::Result<BodyResResultPrepared>> {
+        Session::prepare_raw_tw(
+            self,
+            query,
+            keyspace,
+            with_tracing,
+            with_warnings,
+            beta_protocol,
+        )
+        .boxed()
+    }
+
+    fn prepare_tw<'a>(
+        &'a self,
+        query: Cow<'a, str>,
+        keyspace: Option<String>,
+        with_tracing: bool,
+        with_warnings: bool,
+        beta_protocol: bool,
+    ) -> BoxFuture<'a, error::Result<PreparedQuery>> {
+        Session::prepare_tw(
+            self,
+            query,
+            keyspace,
+            with_tracing,
+            with_warnings,
+            beta_protocol,
+        )
+        .boxed()
+    }
+
+    fn batch_with_params<'a>(
+        &'a self,
+        batch: &'a QueryBatch,
+        parameters: &'a StatementParams,
+    ) -> BoxFuture<'a, error::Result<Envelope>> {
+        Session::batch_with_params(self, batch, parameters).boxed()
+    }
+
+    fn query_with_params<'a>(
+        &'a self,
+        query: Cow<'a, str>,
+        parameters: StatementParams,
+    ) -> BoxFuture<'a, error::Result<Envelope>> {
+        Session::query_with_params(self, query, parameters).boxed()
+    }
+
+    fn count<'a>(&'a self, query_or_table: &'a str) -> BoxFuture<'a, error::Result<u64>> {
+        Session::count(self, query_or_table).boxed()
+    }
+
+    fn execute_schema<'a>(
+        &'a self,
+        query: Cow<'a, str>,
+    ) -> BoxFuture<'a, error::Result<Option<SchemaChange>>> {
+        Session::execute_schema(self, query).boxed()
+    }
+
+    fn send_raw(
+        &self,
+        envelope: Envelope,
+        is_idempotent: bool,
+    ) -> BoxFuture<'_, error::Result<Envelope>> {
+        Session::send_raw(self, envelope, is_idempotent).boxed()
+    }
+
+    fn send_raw_to_node(
+        &self,
+        broadcast_rpc_address: SocketAddr,
+        envelope: Envelope,
+    ) -> BoxFuture<'_, error::Result<Envelope>> {
+        Session::send_raw_to_node(self, broadcast_rpc_address, envelope).boxed()
+    }
+
+    #[inline]
+    fn current_keyspace(&self) -> Option<Arc<String>> {
+        Session::current_keyspace(self)
+    }
+
+    #[inline]
+    fn compression_mode(&self) -> Option<CompressionMode> {
+        Session::compression_mode(self)
+    }
+
+    #[inline]
+    fn compression(&self) -> Option<Compression> {
+        Session::compression(self)
+    }
+
+    #[inline]
+    fn set_compression(&self, compression: Compression) -> error::Result<()> {
+        Session::set_compression(self, compression)
+    }
+
+    fn connection_compressions(&self) -> BoxFuture<'_, FxHashMap<Compression, usize>> {
+        Session::connection_compressions(self).boxed()
+    }
+
+    fn recycle_connections(&self) -> BoxFuture<'_, error::Result<()>> {
+        Session::recycle_connections(self).boxed()
+    }
+
+    #[inline]
+    fn shutdown(&self) {
+        Session::shutdown(self)
+    }
+
+    #[inline]
+    fn is_shut_down(&self) -> bool {
+        Session::is_shut_down(self)
+    }
+
+    #[inline]
+    fn warning_policy(&self) -> WarningPolicy {
+        Session::warning_policy(self)
+    }
+
+    #[inline]
+    fn create_event_receiver(&self) -> Receiver<ServerEvent> {
+        Session::create_event_receiver(self)
+    }
+
+    #[inline]
+    fn retry_policy(&self) -> &dyn RetryPolicy {
+        Session::retry_policy(self)
+    }
+
+    fn warmup(&self) -> BoxFuture<'_, Result<WarmupReport, WarmupError>> {
+        Session::warmup(self).boxed()
+    }
+}
+
+/// Session with erased transport, connection manager and load balancing types, for use in
+/// signatures which should not depend on connection details. Can be created from any [`Session`]
+/// with [`Session::into_dyn`]. Like `Session`, it is cheap to clone and all clones share the same
+/// state.
+///
+/// Every request goes through dynamic dispatch and a boxed future, so performance-sensitive code
+/// might prefer using `Session` directly. Operations which expose generic parameters, such as
+/// cluster metadata access, are only available on `Session`.
+#[derive(Derivative, Clone)]
+#[derivative(Debug)]
+pub struct DynSession {
+    #[derivative(Debug = "ignore")]
+    session: Arc<dyn SessionOps>,
+}
+
+impl<
+        T: CdrsTransport + 'static,
+        CM: ConnectionManager<T> + Send + Sync + 'static,
+        LB: LoadBalancingStrategy<T, CM> + Send + Sync + 'static,
+    > From<Session<T, CM, LB>> for DynSession
+{
+    fn from(session: Session<T, CM, LB>) -> Self {
+        DynSession {
+            session: Arc::new(session),
+        }
+    }
+}
+
+impl DynSession {
+    /// Returns new `DynSessionPager` that can be used for performing paged queries.
+    pub fn paged(&self, page_size: i32) -> DynSessionPager<'_> {
+        DynSessionPager::new(self, page_size)
+    }
+
+    /// Executes given prepared query with query parameters.
+    pub async fn exec_with_params(
+        &self,
+        prepared: &PreparedQuery,
+        parameters: &StatementParams,
+    ) -> error::Result<Envelope> {
+        self.exec_with_params_parsed(prepared, parameters)
+            .await
+            .map(ParsedResponse::into_envelope)
+    }
+
+    /// Executes given prepared query with query parameters, returning the response along with its
+    /// parsed body. See [`Session::exec_with_params_parsed`].
+    pub async fn exec_with_params_parsed(
+        &self,
+        prepared: &PreparedQuery,
+        parameters: &StatementParams,
+    ) -> error::Result<ParsedResponse> {
+        self.session
+            .exec_with_params_parsed(prepared, parameters)
+            .await
+    }
+
+    /// Executes given prepared query with query values.
+    pub async fn exec_with_values<V: Into<QueryValues>>(
+        &self,
+        prepared: &PreparedQuery,
+        values: V,
+    ) -> error::Result<Envelope> {
+        self.exec_with_params(
+            prepared,
+            &StatementParamsBuilder::new()
+                .with_values(values.into())
+                .build(),
+        )
+        .await
+    }
+
+    /// Executes given prepared query. Statement parameters can be overridden on the returned
+    /// [`StatementRequest`] before awaiting it.
+    #[inline]
+    pub fn exec<'a>(&'a self, prepared: &'a PreparedQuery) -> StatementRequest<'a> {
+        StatementRequest::prepared(self, prepared)
+    }
+
+    /// Executes given prepared query once for every set of values, with at most `concurrency`
+    /// executions in flight. See [`Session::execute_concurrent`] for details.
+    #[inline]
+    pub async fn execute_concurrent<I>(
+        &self,
+        prepared: &PreparedQuery,
+        values: I,
+        concurrency: usize,
+    ) -> Vec<error::Result<Envelope>>
+    where
+        I: IntoIterator,
+        I::Item: Into<QueryValues>,
+    {
+        self.execute_concurrent_with_params(prepared, values, &Default::default(), concurrency)
+            .await
+    }
+
+    /// Executes given prepared query once for every set of values, with at most `concurrency`
+    /// executions in flight. See [`Session::execute_concurrent_with_params`] for details.
+    pub async fn execute_concurrent_with_params<I>(
+        &self,
+        prepared: &PreparedQuery,
+        values: I,
+        parameters: &StatementParams,
+        concurrency: usize,
+    ) -> Vec<error::Result<Envelope>>
+    where
+        I: IntoIterator,
+        I::Item: Into<QueryValues>,
+    {
+        let executions = values.into_iter().map(|values| {
+            let mut parameters = parameters.clone();
+            parameters.query_params.values = Some(values.into());
+            async move { self.exec_with_params(prepared, &parameters).await }
+        });
+
+        execute_in_order(executions, concurrency).await
+    }
+
+    /// Executes given prepared query once for every set of values and summarizes the results. See
+    /// [`Session::execute_concurrent_summarized`] for details.
+    pub async fn execute_concurrent_summarized<I>(
+        &self,
+        prepared: &PreparedQuery,
+        values: I,
+        parameters: &StatementParams,
+        concurrency: usize,
+        mode: ConcurrentErrorMode,
+    ) -> Result<usize, ConcurrentExecutionError>
+    where
+        I: IntoIterator,
+        I::Item: Into<QueryValues>,
+    {
+        let executions = values.into_iter().enumerate().map(|(index, values)| {
+            let mut parameters = parameters.clone();
+            parameters.query_params.values = Some(values.into());
+            async move { (index, self.exec_with_params(prepared, &parameters).await) }
+        });
+
+        execute_summarized(executions, concurrency, mode).await
+    }
+
+    /// Prepares a query for execution. Along with query itself, the
+    /// method takes `with_tracing` and `with_warnings` flags to get
+    /// tracing information and warnings. Returns the raw prepared
+    /// query result.
+    pub async fn prepare_raw_tw<'a, Q: Into<Cow<'a, str>>>(
+        &self,
+        query: Q,
+        keyspace: Option<String>,
+        with_tracing: bool,
+        with_warnings: bool,
+        beta_protocol: bool,
+    ) -> error::Result<BodyResResultPrepared> {
+        self.session
+            .prepare_raw_tw(
+                query.into(),
+                keyspace,
+                with_tracing,
+                with_warnings,
+                beta_protocol,
+            )
+            .await
+    }
+
+    /// Prepares query without additional tracing information and warnings.
+    /// Returns the raw prepared query result.
+    #[inline]
+    pub async fn prepare_raw<'a, Q: Into<Cow<'a, str>>>(
+        &self,
+        query: Q,
+    ) -> error::Result<BodyResResultPrepared> {
+        self.prepare_raw_tw(query, None, false, false, false).await
+    }
+
+    /// Prepares a query for execution. Along with query itself,
+    /// the method takes `with_tracing` and `with_warnings` flags
+    /// to get tracing information and warnings. Returns the prepared
+    /// query.
+    pub async fn prepare_tw<'a, Q: Into<Cow<'a, str>>>(
+        &self,
+        query: Q,
+        keyspace: Option<String>,
+        with_tracing: bool,
+        with_warnings: bool,
+        beta_protocol: bool,
+    ) -> error::Result<PreparedQuery> {
+        self.session
+            .prepare_tw(
+                query.into(),
+                keyspace,
+                with_tracing,
+                with_warnings,
+                beta_protocol,
+            )
+            .await
+    }
+
+    /// It prepares query without additional tracing information and warnings.
+    /// Returns the prepared query.
+    #[inline]
+    pub async fn prepare<'a, Q: Into<Cow<'a, str>>>(
+        &self,
+        query: Q,
+    ) -> error::Result<PreparedQuery> {
+        self.prepare_tw(query, None, false, false, false).await
+    }
+
+    /// Prepares multiple queries concurrently. See [`Session::prepare_all`] for details.
+    #[inline]
+    pub async fn prepare_all<Q: AsRef<str>>(
+        &self,
+        queries: &[Q],
+    ) -> Result<Vec<PreparedQuery>, PrepareAllError> {
+        self.prepare_all_with_concurrency(queries, DEFAULT_PREPARE_CONCURRENCY)
+            .await
+    }
+
+    /// Prepares multiple queries concurrently, with at most `concurrency` being prepared at the
+    /// same time. See [`Session::prepare_all_with_concurrency`] for details.
+    pub async fn prepare_all_with_concurrency<Q: AsRef<str>>(
+        &self,
+        queries: &[Q],
+        concurrency: usize,
+    ) -> Result<Vec<PreparedQuery>, PrepareAllError> {
+        let statements = queries
+            .iter()
+            .map(|query| {
+                let query = query.as_ref();
+                (query.to_string(), self.prepare(query))
+            })
+            .collect();
+
+        prepare_concurrently(statements, concurrency).await
+    }
+
+    /// Executes batch query. The batch can be passed by reference, so it can be executed again
+    /// without cloning.
+    #[inline]
+    pub async fn batch<B: Borrow<QueryBatch>>(&self, batch: B) -> error::Result<Envelope> {
+        self.batch_with_params(batch, &Default::default()).await
+    }
+
+    /// Executes batch query with parameters. The batch can be passed by reference, so it can be
+    /// executed again without cloning.
+    pub async fn batch_with_params<B: Borrow<QueryBatch>>(
+        &self,
+        batch: B,
+        parameters: &StatementParams,
+    ) -> error::Result<Envelope> {
+        self.session
+            .batch_with_params(batch.borrow(), parameters)
+            .await
+    }
+
+    /// Executes a query. Statement parameters can be overridden on the returned
+    /// [`StatementRequest`] before awaiting it.
+    #[inline]
+    pub fn query<'a, Q: Into<Cow<'a, str>>>(&'a self, query: Q) -> StatementRequest<'a> {
+        StatementRequest::query(self, query.into())
+    }
+
+    /// Executes a query with bounded values (either with or without names).
+    #[inline]
+    pub async fn query_with_values<'a, Q: Into<Cow<'a, str>>, V: Into<QueryValues>>(
+        &self,
+        query: Q,
+        values: V,
+    ) -> error::Result<Envelope> {
+        self.query_with_params(
+            query,
+            StatementParamsBuilder::new()
+                .with_values(values.into())
+                .build(),
+        )
+        .await
+    }
+
+    /// Executes a query with query parameters.
+    pub async fn query_with_params<'a, Q: Into<Cow<'a, str>>>(
+        &self,
+        query: Q,
+        parameters: StatementParams,
+    ) -> error::Result<Envelope> {
+        self.session
+            .query_with_params(query.into(), parameters)
+            .await
+    }
+
+    /// Counts rows using given query, or in given table. See [`Session::count`].
+    pub async fn count(&self, query_or_table: &str) -> error::Result<u64> {
+        self.session.count(query_or_table).await
+    }
+
+    /// Executes a schema altering statement and waits for schema agreement. See
+    /// [`Session::execute_schema`].
+    pub async fn execute_schema<'a, Q: Into<Cow<'a, str>>>(
+        &self,
+        query: Q,
+    ) -> error::Result<Option<SchemaChange>> {
+        self.session.execute_schema(query.into()).await
+    }
+
+    /// Sends given envelope and returns the response as is. See [`Session::send_raw`].
+    pub async fn send_raw(
+        &self,
+        envelope: Envelope,
+        is_idempotent: bool,
+    ) -> error::Result<Envelope> {
+        self.session.send_raw(envelope, is_idempotent).await
+    }
+
+    /// Sends given envelope to given node and returns the response as is. See
+    /// [`Session::send_raw_to_node`].
+    pub async fn send_raw_to_node(
+        &self,
+        broadcast_rpc_address: SocketAddr,
+        envelope: Envelope,
+    ) -> error::Result<Envelope> {
+        self.session
+            .send_raw_to_node(broadcast_rpc_address, envelope)
+            .await
+    }
+
+    /// Returns currently set global keyspace.
+    #[inline]
+    pub fn current_keyspace(&self) -> Option<Arc<String>> {
+        self.session.current_keyspace()
+    }
+
+    /// Returns compression mode in effect for new connections of this session, or `None` if
+    /// traffic is not compressed.
+    #[inline]
+    pub fn compression_mode(&self) -> Option<CompressionMode> {
+        self.session.compression_mode()
+    }
+
+    /// Returns compression used by newly established connections, or `None` if the connection
+    /// manager doesn't report it.
+    #[inline]
+    pub fn compression(&self) -> Option<Compression> {
+        self.session.compression()
+    }
+
+    /// Changes compression used by newly established connections. See
+    /// [`Session::set_compression`].
+    #[inline]
+    pub fn set_compression(&self, compression: Compression) -> error::Result<()> {
+        self.session.set_compression(compression)
+    }
+
+    /// Returns how many pooled connections use each compression.
+    pub async fn connection_compressions(&self) -> FxHashMap<Compression, usize> {
+        self.session.connection_compressions().await
+    }
+
+    /// Replaces all pooled connections with new ones. See [`Session::recycle_connections`].
+    pub async fn recycle_connections(&self) -> error::Result<()> {
+        self.session.recycle_connections().await
+    }
+
+    /// Stops all background tasks of the session. See [`Session::shutdown`].
+    #[inline]
+    pub fn shutdown(&self) {
+        self.session.shutdown()
+    }
+
+    /// Returns `true` if [`shutdown`](DynSession::shutdown) has been called.
+    #[inline]
+    pub fn is_shut_down(&self) -> bool {
+        self.session.is_shut_down()
+    }
+
+    /// Returns the policy applied to server warnings.
+    #[inline]
+    pub fn warning_policy(&self) -> WarningPolicy {
+        self.session.warning_policy()
+    }
+
+    /// Creates a new server event receiver. You can use multiple receivers at the same time.
+    #[inline]
+    pub fn create_event_receiver(&self) -> Receiver<ServerEvent> {
+        self.session.create_event_receiver()
+    }
+
+    /// Returns current retry policy.
+    #[inline]
+    pub fn retry_policy(&self) -> &dyn RetryPolicy {
+        self.session.retry_policy()
+    }
+
+    /// Eagerly establishes connection pools to all nodes which are not ignored. See
+    /// [`Session::warmup`] for details.
+    #[inline]
+    pub async fn warmup(&self) -> Result<WarmupReport, WarmupError> {
+        self.session.warmup().await
+    }
+}
+
+impl StatementTarget for DynSession {
+    fn send_query<'a>(
+        &'a self,
+        query: Cow<'a, str>,
+        parameters: StatementParams,
+    ) -> BoxFuture<'a, error::Result<Envelope>> {
+        self.session.query_with_params(query, parameters)
+    }
+
+    fn send_prepared<'a>(
+        &'a self,
+        prepared: &'a PreparedQuery,
+        parameters: StatementParams,
+    ) -> BoxFuture<'a, error::Result<Envelope>> {
+        async move { self.exec_with_params(prepared, &parameters).await }.boxed()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use cassandra_protocol::frame::{Envelope, Version};
+    use cassandra_protocol::query::{BatchQueryBuilder, PreparedQuery, QueryValues};
+    use std::future::IntoFuture;
+
+    use crate::cluster::session::DynTcpSession;
+    use crate::cluster::test_nodes::assert_send;
+    use crate::cluster::{ConcurrentErrorMode, DynSession};
+
+    #[allow(dead_code)]
+    fn requests_should_be_send(session: DynTcpSession, prepared: PreparedQuery) {
+        let session = session.into_dyn();
+        assert_send(session.query("SELECT * FROM system.local").into_future());
+        assert_send(
+            session
+                .paged(10)
+                .query("SELECT * FROM system.local")
+                .next_page(),
+        );
+        assert_send(session.prepare_all(&["SELECT * FROM system.local"]));
+
+        assert_send(session.execute_concurrent(
+            &prepared,
+            vec![QueryValues::SimpleValues(vec![])],
+            8,
+        ));
+        assert_send(session.execute_concurrent_summarized(
+            &prepared,
+            (0..10).map(|id: i32| QueryValues::SimpleValues(vec![id.into()])),
+            &Default::default(),
+            8,
+            ConcurrentErrorMode::CollectAll,
+        ));
+
+        let batch = BatchQueryBuilder::new().build().unwrap();
+        assert_send(session.batch(&batch));
+        assert_send(session.batch(batch));
+
+        assert_send(session.count("system.local"));
+        assert_send(session.send_raw(Envelope::new_req_options(Version::V4), true));
+        assert_send(session.send_raw_to_node(
+            "127.0.0.1:9042".parse().unwrap(),
+            Envelope::new_req_options(Version::V4),
+        ));
+    }
+
+    #[test]
+    fn dyn_session_should_be_shareable() {
+        fn assert_shareable<T: Clone + Send + Sync + 'static>() {}
+        assert_shareable::<DynSession>();
+    }
+}
@@ -0,0 +1,324 @@
+use cassandra_protocol::error::{Error, Result};
+use cassandra_protocol::events::SimpleServerEvent;
+use cassandra_protocol::frame::{Envelope, Version};
+use std::net::SocketAddr;
+use std::sync::Arc;
+use tokio::select;
+use tokio::sync::mpsc;
+use tracing::*;
+
+use crate::future::BoxFuture;
+use crate::retry::ReconnectionPolicy;
+use crate::runtime::sleep;
+use crate::transport::CdrsTransport;
+
+pub(crate) const EVENT_CHANNEL_CAPACITY: usize = 32;
+
+/// Notification about a connection registered for server events being established and registered
+/// again, after the previous one was lost. The server forgets registrations of closed
+/// connections, so events stop until that happens. Events sent in the meantime are lost, so state
+/// derived from them might need to be refreshed.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct RegistrationsRestored {
+    /// Address of the node the new connection is registered with.
+    pub node: SocketAddr,
+    /// Registered event types.
+    pub events: Vec<SimpleServerEvent>,
+}
+
+pub(crate) type ConnectFn<T> = Box<
+    dyn Fn(mpsc::Sender<Envelope>, mpsc::Sender<Error>) -> BoxFuture<'static, Result<T>>
+        + Send
+        + Sync,
+>;
+
+/// Connection registered for server events, along with channels receiving its events and errors.
+/// Dropping it closes the connection, which unregisters it.
+pub(crate) struct RegisteredConnection<T> {
+    _connection: T,
+    event_receiver: mpsc::Receiver<Envelope>,
+    error_receiver: mpsc::Receiver<Error>,
+}
+
+impl<T> RegisteredConnection<T> {
+    // delivers events until the connection is lost
+    async fn receive(&mut self, handle_event: &impl Fn(Envelope)) {
+        loop {
+            select! {
+                biased;
+                envelope = self.event_receiver.recv() => match envelope {
+                    Some(envelope) => handle_event(envelope),
+                    None => break,
+                },
+                Some(error) = self.error_receiver.recv() => {
+                    warn!(%error, "Event connection lost!");
+                    break;
+                }
+            }
+        }
+    }
+}
+
+/// Keeps a dedicated connection to a node registered for given events. Lost connections are
+/// re-established according to the reconnection policy and registered again.
+pub(crate) struct EventRegistration<T> {
+    connect: ConnectFn<T>,
+    node: SocketAddr,
+    events: Vec<SimpleServerEvent>,
+    version: Version,
+    reconnection_policy: Arc<dyn ReconnectionPolicy + Send + Sync>,
+}
+
+impl<T: CdrsTransport> EventRegistration<T> {
+    pub(crate) fn new(
+        connect: ConnectFn<T>,
+        node: SocketAddr,
+        events: Vec<SimpleServerEvent>,
+        version: Version,
+        reconnection_policy: Arc<dyn ReconnectionPolicy + Send + Sync>,
+    ) -> Self {
+        EventRegistration {
+            connect,
+            node,
+            events,
+            version,
+            reconnection_policy,
+        }
+    }
+
+    /// Opens a new connection and registers it for events.
+    pub(crate) async fn register(&self) -> Result<RegisteredConnection<T>> {
+        let (event_sender, event_receiver) = mpsc::channel(EVENT_CHANNEL_CAPACITY);
+        let (error_sender, error_receiver) = mpsc::channel(1);
+
+        let connection = (self.connect)(event_sender, error_sender).await?;
+        connection
+            .write_envelope(
+                &Envelope::new_req_register(self.events.clone(), self.version),
+                false,
+            )
+            .await?;
+
+        Ok(RegisteredConnection {
+            _connection: connection,
+            event_receiver,
+            error_receiver,
+        })
+    }
+
+    /// Delivers events received on given connection and on its replacements, until the
+    /// reconnection schedule gives up.
+    pub(crate) async fn run(
+        self,
+        mut connection: RegisteredConnection<T>,
+        handle_event: impl Fn(Envelope),
+        handle_restored: impl Fn(RegistrationsRestored),
+    ) {
+        loop {
+            connection.receive(&handle_event).await;
+            drop(connection);
+
+            connection = match self.reregister().await {
+                Some(connection) => connection,
+                None => return,
+            };
+
+            info!(node = %self.node, "Event registrations restored.");
+            handle_restored(RegistrationsRestored {
+                node: self.node,
+                events: self.events.clone(),
+            });
+        }
+    }
+
+    async fn reregister(&self) -> Option<RegisteredConnection<T>> {
+        let mut schedule = self.reconnection_policy.new_node_schedule();
+
+        loop {
+            match self.register().await {
+                Ok(connection) => return Some(connection),
+                Err(error) => {
+                    let delay = match schedule.next_delay() {
+                        Some(delay) => delay,
+                        None => {
+                            warn!(%error, node = %self.node, "Giving up restoring event registrations.");
+                            return None;
+                        }
+                    };
+
+                    debug!(
+                        %error,
+                        attempt = schedule.attempt(),
+                        ?delay,
+                        "Waiting before restoring event registrations."
+                    );
+                    sleep(delay).await;
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use cassandra_protocol::authenticators::NoneAuthenticatorProvider;
+    use cassandra_protocol::compression::Compression;
+    use cassandra_protocol::events::{ServerEvent, SimpleServerEvent};
+    use cassandra_protocol::frame::events::{StatusChange, StatusChangeType};
+    use cassandra_protocol::frame::{Direction, Envelope, Flags, Opcode, Serialize, Version};
+    use futures::FutureExt;
+    use std::convert::{TryFrom, TryInto};
+    use std::net::SocketAddr;
+    use std::sync::Arc;
+    use std::time::Duration;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::{TcpListener, TcpStream};
+    use tokio::sync::{mpsc, watch};
+
+    use super::{EventRegistration, RegistrationsRestored};
+    use crate::cluster::connection_manager::ConnectionManager;
+    use crate::cluster::control_connection::server_event;
+    use crate::cluster::{ConnectionManagerConfigBuilder, KeyspaceHolder, TcpConnectionManager};
+    use crate::frame_encoding::ProtocolFrameEncodingFactory;
+    use crate::retry::ConstantReconnectionPolicy;
+
+    fn create_connection_manager() -> TcpConnectionManager {
+        let (keyspace_sender, _) = watch::channel(None);
+        TcpConnectionManager::new(
+            Arc::new(NoneAuthenticatorProvider),
+            Arc::new(KeyspaceHolder::new(keyspace_sender)),
+            Box::<ProtocolFrameEncodingFactory>::default(),
+            ConnectionManagerConfigBuilder::new(Version::V4)
+                .with_compression_threshold(0)
+                .with_buffer_size(16)
+                .with_strict_socket_options(true)
+                .with_handshake_timeout(Duration::from_secs(1))
+                .with_authentication_timeout(Duration::from_secs(1))
+                .with_max_concurrent_authentications(1)
+                .build(),
+        )
+    }
+
+    fn response(opcode: Opcode, stream_id: i16, body: Vec<u8>) -> Vec<u8> {
+        Envelope::new(
+            Version::V4,
+            Direction::Response,
+            Flags::empty(),
+            opcode,
+            stream_id,
+            body,
+            None,
+            vec![],
+        )
+        .encode_with(Compression::None)
+        .unwrap()
+    }
+
+    fn status_event(addr: SocketAddr) -> ServerEvent {
+        ServerEvent::StatusChange(StatusChange {
+            change_type: StatusChangeType::Up,
+            addr,
+        })
+    }
+
+    // answers STARTUP and REGISTER with READY, and sends an event after registering
+    async fn accept_and_register(listener: &TcpListener, event: &ServerEvent) -> TcpStream {
+        let (mut stream, _) = listener.accept().await.unwrap();
+
+        loop {
+            let mut header = [0; 9];
+            stream.read_exact(&mut header).await.unwrap();
+
+            let mut body = vec![0; i32::from_be_bytes(header[5..9].try_into().unwrap()) as usize];
+            stream.read_exact(&mut body).await.unwrap();
+
+            let stream_id = i16::from_be_bytes(header[2..4].try_into().unwrap());
+            stream
+                .write_all(&response(Opcode::Ready, stream_id, vec![]))
+                .await
+                .unwrap();
+
+            if Opcode::try_from(header[4]).unwrap() == Opcode::Register {
+                break;
+            }
+        }
+
+        stream
+            .write_all(&response(
+                Opcode::Event,
+                -1,
+                event.serialize_to_vec(Version::V4),
+            ))
+            .await
+            .unwrap();
+
+        stream
+    }
+
+    #[tokio::test]
+    async fn should_restore_registrations_after_reconnecting() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        let connection_manager = Arc::new(create_connection_manager());
+        let registration = EventRegistration::new(
+            Box::new(move |event_handler, error_handler| {
+                let connection_manager = connection_manager.clone();
+                async move {
+                    connection_manager
+                        .connection(Some(event_handler), Some(error_handler), addr)
+                        .await
+                }
+                .boxed()
+            }),
+            addr,
+            vec![SimpleServerEvent::StatusChange],
+            Version::V4,
+            Arc::new(ConstantReconnectionPolicy::new(Duration::from_millis(10))),
+        );
+
+        let first_event = status_event("127.0.0.1:1".parse().unwrap());
+        let second_event = status_event("127.0.0.2:1".parse().unwrap());
+
+        let server = tokio::spawn({
+            let first_event = first_event.clone();
+            async move { accept_and_register(&listener, &first_event).await }
+        });
+
+        let connection = registration.register().await.unwrap();
+        let (event_sender, mut event_receiver) = mpsc::unbounded_channel();
+        let (restored_sender, mut restored_receiver) = mpsc::unbounded_channel();
+
+        let registration = tokio::spawn(registration.run(
+            connection,
+            move |envelope| {
+                let _ = event_sender.send(server_event(envelope));
+            },
+            move |restored| {
+                let _ = restored_sender.send(restored);
+            },
+        ));
+
+        assert_eq!(event_receiver.recv().await.unwrap(), Some(first_event));
+
+        // kill the server and bring it back on the same port
+        drop(server.await.unwrap());
+        let listener = TcpListener::bind(addr).await.unwrap();
+        let server = tokio::spawn({
+            let second_event = second_event.clone();
+            async move { accept_and_register(&listener, &second_event).await }
+        });
+
+        assert_eq!(
+            restored_receiver.recv().await.unwrap(),
+            RegistrationsRestored {
+                node: addr,
+                events: vec![SimpleServerEvent::StatusChange],
+            }
+        );
+        assert_eq!(event_receiver.recv().await.unwrap(), Some(second_event));
+
+        registration.abort();
+        drop(server);
+    }
+}
@@ -0,0 +1,263 @@
+use cassandra_protocol::error;
+use cassandra_protocol::frame::events::{
+    SchemaChange, ServerEvent, SimpleServerEvent, StatusChange, TopologyChange,
+};
+use futures::future::AbortHandle;
+use std::net::SocketAddr;
+use tokio::select;
+use tokio::sync::broadcast::error::RecvError;
+use tracing::*;
+
+use crate::cluster::control_connection::server_event;
+use crate::cluster::session::Session;
+use crate::cluster::{ConnectionManager, RegistrationsRestored};
+use crate::load_balancing::LoadBalancingStrategy;
+use crate::runtime;
+use crate::transport::CdrsTransport;
+
+type Handler<E> = Box<dyn Fn(E) + Send + Sync>;
+
+#[derive(Default)]
+struct EventHandlers {
+    topology_change: Option<Handler<TopologyChange>>,
+    status_change: Option<Handler<StatusChange>>,
+    schema_change: Option<Handler<SchemaChange>>,
+    registrations_restored: Option<Handler<RegistrationsRestored>>,
+}
+
+impl EventHandlers {
+    fn events(&self) -> Vec<SimpleServerEvent> {
+        let mut events = vec![];
+
+        if self.topology_change.is_some() {
+            events.push(SimpleServerEvent::TopologyChange);
+        }
+
+        if self.status_change.is_some() {
+            events.push(SimpleServerEvent::StatusChange);
+        }
+
+        if self.schema_change.is_some() {
+            events.push(SimpleServerEvent::SchemaChange);
+        }
+
+        events
+    }
+
+    fn dispatch(&self, event: ServerEvent) {
+        match event {
+            ServerEvent::TopologyChange(event) => {
+                if let Some(handler) = &self.topology_change {
+                    handler(event);
+                }
+            }
+            ServerEvent::StatusChange(event) => {
+                if let Some(handler) = &self.status_change {
+                    handler(event);
+                }
+            }
+            ServerEvent::SchemaChange(event) => {
+                if let Some(handler) = &self.schema_change {
+                    handler(event);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn restored(&self, restored: RegistrationsRestored) {
+        if let Some(handler) = &self.registrations_restored {
+            handler(restored);
+        }
+    }
+}
+
+/// Builds a [`Subscription`] to server events, with separate handlers for each event type,
+/// returned by [`Session::subscribe_events`]. Only events with handlers get delivered.
+///
+/// By default, events are received on the session control connection. Selecting a node with
+/// [`with_node`](EventSubscriptionBuilder::with_node) opens a dedicated connection to it instead,
+/// which is registered only for handled event types. When the connection is lost, a new one is
+/// opened and registered according to the session reconnection policy - the subscription ends
+/// when the policy gives up.
+#[must_use = "subscriptions are only started with subscribe()"]
+pub struct EventSubscriptionBuilder<
+    'a,
+    T: CdrsTransport + 'static,
+    CM: ConnectionManager<T> + 'static,
+    LB: LoadBalancingStrategy<T, CM> + Send + Sync,
+> {
+    session: &'a Session<T, CM, LB>,
+    node: Option<SocketAddr>,
+    handlers: EventHandlers,
+}
+
+impl<
+        'a,
+        T: CdrsTransport + 'static,
+        CM: ConnectionManager<T> + Send + Sync + 'static,
+        LB: LoadBalancingStrategy<T, CM> + Send + Sync + 'static,
+    > EventSubscriptionBuilder<'a, T, CM, LB>
+{
+    pub(crate) fn new(session: &'a Session<T, CM, LB>) -> Self {
+        EventSubscriptionBuilder {
+            session,
+            node: None,
+            handlers: Default::default(),
+        }
+    }
+
+    /// Receives events on a dedicated connection to the node with given broadcast RPC address,
+    /// instead of the session control connection.
+    pub fn with_node(mut self, broadcast_rpc_address: SocketAddr) -> Self {
+        self.node = Some(broadcast_rpc_address);
+        self
+    }
+
+    /// Sets the handler for nodes joining, leaving or moving in the cluster.
+    pub fn on_topology_change(
+        mut self,
+        handler: impl Fn(TopologyChange) + Send + Sync + 'static,
+    ) -> Self {
+        self.handlers.topology_change = Some(Box::new(handler));
+        self
+    }
+
+    /// Sets the handler for nodes going up or down.
+    pub fn on_status_change(
+        mut self,
+        handler: impl Fn(StatusChange) + Send + Sync + 'static,
+    ) -> Self {
+        self.handlers.status_change = Some(Box::new(handler));
+        self
+    }
+
+    /// Sets the handler for schema changes.
+    pub fn on_schema_change(
+        mut self,
+        handler: impl Fn(SchemaChange) + Send + Sync + 'static,
+    ) -> Self {
+        self.handlers.schema_change = Some(Box::new(handler));
+        self
+    }
+
+    /// Sets the handler notified when events flow again after the connection receiving them has
+    /// been lost and replaced. Events sent in the meantime are not delivered.
+    pub fn on_registrations_restored(
+        mut self,
+        handler: impl Fn(RegistrationsRestored) + Send + Sync + 'static,
+    ) -> Self {
+        self.handlers.registrations_restored = Some(Box::new(handler));
+        self
+    }
+
+    /// Starts delivering events to the handlers, until the returned subscription is dropped.
+    pub async fn subscribe(self) -> error::Result<Subscription> {
+        let handlers = self.handlers;
+
+        let node = match self.node {
+            Some(node) => node,
+            None => {
+                let mut event_receiver = self.session.create_event_receiver();
+                let mut registrations_receiver = self.session.create_registrations_receiver();
+                return Ok(Subscription::new(runtime::spawn(async move {
+                    loop {
+                        select! {
+                            event = event_receiver.recv() => match event {
+                                Ok(event) => handlers.dispatch(event),
+                                Err(RecvError::Lagged(skipped)) => {
+                                    warn!(skipped, "Event subscription lagged behind server events.");
+                                }
+                                Err(RecvError::Closed) => break,
+                            },
+                            restored = registrations_receiver.recv() => match restored {
+                                Ok(restored) => handlers.restored(restored),
+                                Err(RecvError::Lagged(_)) => {}
+                                Err(RecvError::Closed) => break,
+                            },
+                        }
+                    }
+                })));
+            }
+        };
+
+        let registration = self.session.event_registration(node, handlers.events())?;
+        let connection = registration.register().await?;
+
+        Ok(Subscription::new(runtime::spawn(async move {
+            // the connection is closed when the subscription is dropped, which unregisters it
+            registration
+                .run(
+                    connection,
+                    |envelope| {
+                        if let Some(event) = server_event(envelope) {
+                            handlers.dispatch(event);
+                        }
+                    },
+                    |restored| handlers.restored(restored),
+                )
+                .await;
+
+            debug!(%node, "Event subscription ended.");
+        })))
+    }
+}
+
+/// An active subscription to server events, created by [`EventSubscriptionBuilder`]. Dropping it
+/// stops delivering events and closes the dedicated connection, if any.
+#[derive(Debug)]
+pub struct Subscription {
+    handle: AbortHandle,
+}
+
+impl Subscription {
+    fn new(handle: AbortHandle) -> Self {
+        Subscription { handle }
+    }
+}
+
+impl Drop for Subscription {
+    fn drop(&mut self) {
+        self.handle.abort();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use cassandra_protocol::frame::events::{
+        SchemaChange, SchemaChangeOptions, SchemaChangeTarget, SchemaChangeType, ServerEvent,
+        SimpleServerEvent, StatusChange, StatusChangeType,
+    };
+    use std::sync::{Arc, Mutex};
+
+    use super::EventHandlers;
+
+    #[test]
+    fn should_dispatch_events_by_type() {
+        let schema_changes = Arc::new(Mutex::new(vec![]));
+
+        let handlers = EventHandlers {
+            schema_change: Some(Box::new({
+                let schema_changes = schema_changes.clone();
+                move |event| schema_changes.lock().unwrap().push(event)
+            })),
+            ..Default::default()
+        };
+
+        assert_eq!(handlers.events(), vec![SimpleServerEvent::SchemaChange]);
+
+        let schema_change = SchemaChange {
+            change_type: SchemaChangeType::Created,
+            target: SchemaChangeTarget::Keyspace,
+            options: SchemaChangeOptions::Keyspace("ks".into()),
+        };
+
+        handlers.dispatch(ServerEvent::StatusChange(StatusChange {
+            change_type: StatusChangeType::Up,
+            addr: "127.0.0.1:9042".parse().unwrap(),
+        }));
+        handlers.dispatch(ServerEvent::SchemaChange(schema_change.clone()));
+
+        assert_eq!(*schema_changes.lock().unwrap(), vec![schema_change]);
+    }
+}
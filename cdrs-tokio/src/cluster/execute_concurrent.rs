@@ -0,0 +1,193 @@
+use cassandra_protocol::error::Error;
+use futures::{stream, Future, StreamExt};
+use itertools::Itertools;
+use thiserror::Error as ThisError;
+
+/// Decides what happens with remaining statements when one of concurrently executed statements
+/// fails.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum ConcurrentErrorMode {
+    /// Stops executing on the first error. Statements which are still in flight are abandoned and
+    /// no new ones are started.
+    #[default]
+    FailFast,
+    /// Executes all statements and collects all errors.
+    CollectAll,
+}
+
+/// Error returned when any of concurrently executed statements failed.
+#[derive(Debug, Clone, ThisError)]
+#[error(
+    "Could not execute {} statement(s): {}",
+    .failed.len(),
+    .failed.iter().map(|(index, error)| format!("#{index}: {error}")).join("; ")
+)]
+pub struct ConcurrentExecutionError {
+    /// Errors along with indexes of values which failed, in completion order. Contains only the
+    /// first error in [`ConcurrentErrorMode::FailFast`] mode.
+    pub failed: Vec<(usize, Error)>,
+    /// Number of statements which succeeded before execution finished.
+    pub succeeded: usize,
+}
+
+impl From<ConcurrentExecutionError> for Error {
+    #[inline]
+    fn from(error: ConcurrentExecutionError) -> Self {
+        Error::General(error.to_string())
+    }
+}
+
+/// Runs given executions with at most `concurrency` running at the same time. Results are returned
+/// in input order. Executions are started lazily, so dropping the returned future stops the rest.
+pub(crate) async fn execute_in_order<T, Fut>(
+    executions: impl Iterator<Item = Fut>,
+    concurrency: usize,
+) -> Vec<Result<T, Error>>
+where
+    Fut: Future<Output = Result<T, Error>>,
+{
+    stream::iter(executions)
+        .buffered(concurrency.max(1))
+        .collect()
+        .await
+}
+
+/// Runs given executions, which return their input index along with the result, with at most
+/// `concurrency` running at the same time, in any order, and returns the number of successful ones.
+// indexes are attached by callers, since wrapping caller futures in a closure here trips the
+// compiler when checking if the resulting future is Send
+pub(crate) async fn execute_summarized<T, Fut>(
+    executions: impl Iterator<Item = Fut>,
+    concurrency: usize,
+    mode: ConcurrentErrorMode,
+) -> Result<usize, ConcurrentExecutionError>
+where
+    Fut: Future<Output = (usize, Result<T, Error>)>,
+{
+    let mut results = stream::iter(executions).buffer_unordered(concurrency.max(1));
+
+    let mut succeeded = 0;
+    let mut failed = vec![];
+
+    while let Some((index, result)) = results.next().await {
+        match result {
+            Ok(_) => succeeded += 1,
+            Err(error) => {
+                failed.push((index, error));
+                if mode == ConcurrentErrorMode::FailFast {
+                    break;
+                }
+            }
+        }
+    }
+
+    if failed.is_empty() {
+        Ok(succeeded)
+    } else {
+        Err(ConcurrentExecutionError { failed, succeeded })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use cassandra_protocol::error::Error;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::time::Duration;
+    use tokio::time::sleep;
+
+    use super::{execute_in_order, execute_summarized, ConcurrentErrorMode};
+
+    #[tokio::test]
+    async fn should_preserve_order_and_limit_concurrency() {
+        let running = AtomicUsize::new(0);
+        let max_running = AtomicUsize::new(0);
+
+        let executions = (0..10u64).map(|index| {
+            let running = &running;
+            let max_running = &max_running;
+
+            async move {
+                let now_running = running.fetch_add(1, Ordering::SeqCst) + 1;
+                max_running.fetch_max(now_running, Ordering::SeqCst);
+
+                // later statements finish first
+                sleep(Duration::from_millis(20 - index)).await;
+
+                running.fetch_sub(1, Ordering::SeqCst);
+                if index == 4 {
+                    Err(Error::General("Timeout".into()))
+                } else {
+                    Ok(index)
+                }
+            }
+        });
+
+        let results = execute_in_order(executions, 3).await;
+
+        assert_eq!(results.len(), 10);
+        assert_eq!(results[3].as_ref().unwrap(), &3);
+        assert!(results[4].is_err());
+        assert_eq!(results[9].as_ref().unwrap(), &9);
+        assert_eq!(max_running.load(Ordering::SeqCst), 3);
+    }
+
+    #[tokio::test]
+    async fn should_stop_on_first_error() {
+        let started = AtomicUsize::new(0);
+
+        let executions = (0..100).map(|index| {
+            let started = &started;
+
+            async move {
+                started.fetch_add(1, Ordering::SeqCst);
+                if index == 5 {
+                    (index, Err(Error::General("Timeout".into())))
+                } else {
+                    sleep(Duration::from_millis(1)).await;
+                    (index, Ok(()))
+                }
+            }
+        });
+
+        let error = execute_summarized(executions, 4, ConcurrentErrorMode::FailFast)
+            .await
+            .unwrap_err();
+
+        assert_eq!(error.failed.len(), 1);
+        assert_eq!(error.failed[0].0, 5);
+        assert!(started.load(Ordering::SeqCst) < 100);
+    }
+
+    #[tokio::test]
+    async fn should_collect_all_errors() {
+        let executions = (0..10).map(|index| async move {
+            if index % 3 == 0 {
+                (index, Err(Error::General("Timeout".into())))
+            } else {
+                (index, Ok(()))
+            }
+        });
+
+        let error = execute_summarized(executions, 4, ConcurrentErrorMode::CollectAll)
+            .await
+            .unwrap_err();
+
+        let mut failed: Vec<_> = error.failed.iter().map(|(index, _)| *index).collect();
+        failed.sort_unstable();
+
+        assert_eq!(failed, vec![0, 3, 6, 9]);
+        assert_eq!(error.succeeded, 6);
+        assert!(error
+            .to_string()
+            .starts_with("Could not execute 4 statement(s): #"));
+
+        let succeeded = execute_summarized(
+            (0..10).map(|index| async move { (index, Ok(())) }),
+            4,
+            ConcurrentErrorMode::CollectAll,
+        )
+        .await
+        .unwrap();
+        assert_eq!(succeeded, 10);
+    }
+}
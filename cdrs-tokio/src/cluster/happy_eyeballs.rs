@@ -0,0 +1,257 @@
+use futures::stream::{FuturesUnordered, StreamExt};
+use futures::FutureExt;
+use std::future::Future;
+use std::io;
+use std::net::SocketAddr;
+use std::time::Duration;
+
+use crate::runtime::timeout;
+
+/// Default delay between starting consecutive connection attempts, as recommended by RFC 8305.
+pub const DEFAULT_CONNECTION_STAGGER_DELAY: Duration = Duration::from_millis(250);
+
+/// Order in which addresses of a node reachable through multiple addresses, e.g. a contact point
+/// hostname resolving to both IPv6 and IPv4 addresses, are tried when connecting. Address families
+/// are interleaved, starting with the preferred one.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Default)]
+pub enum AddressFamilyPreference {
+    /// Start with IPv6 addresses, as recommended by RFC 8305.
+    #[default]
+    Ipv6,
+    /// Start with IPv4 addresses.
+    Ipv4,
+    /// Keep the order addresses were resolved in.
+    AsResolved,
+}
+
+impl AddressFamilyPreference {
+    /// Orders given addresses for connection attempts, removing duplicates.
+    pub(crate) fn order(self, addrs: &[SocketAddr]) -> Vec<SocketAddr> {
+        let mut unique = Vec::with_capacity(addrs.len());
+        for addr in addrs {
+            if !unique.contains(addr) {
+                unique.push(*addr);
+            }
+        }
+
+        let prefer_ipv6 = match self {
+            AddressFamilyPreference::Ipv6 => true,
+            AddressFamilyPreference::Ipv4 => false,
+            AddressFamilyPreference::AsResolved => return unique,
+        };
+
+        let mut ordered = Vec::with_capacity(unique.len());
+        let (preferred, other): (Vec<_>, Vec<_>) = unique
+            .into_iter()
+            .partition(|addr| addr.is_ipv6() == prefer_ipv6);
+
+        let mut preferred = preferred.into_iter();
+        let mut other = other.into_iter();
+        loop {
+            match (preferred.next(), other.next()) {
+                (None, None) => break,
+                (preferred, other) => ordered.extend(preferred.into_iter().chain(other)),
+            }
+        }
+
+        ordered
+    }
+
+    /// Turns addresses resolved for a single contact point into contact points, each with its
+    /// addresses in connection order. Only a dual-stack result - a single address of each family -
+    /// is treated as one host reachable through alternative addresses. Other results, e.g. from a
+    /// hostname pointing to all nodes of a cluster, give a contact point per address.
+    pub(crate) fn contact_points(self, addrs: &[SocketAddr]) -> Vec<Vec<SocketAddr>> {
+        let ordered = self.order(addrs);
+
+        let ipv6_count = ordered.iter().filter(|addr| addr.is_ipv6()).count();
+        if ipv6_count <= 1 && ordered.len() - ipv6_count <= 1 {
+            return vec![ordered];
+        }
+
+        ordered.into_iter().map(|addr| vec![addr]).collect()
+    }
+}
+
+/// Connects to the first of given addresses which accepts a connection. Each attempt is started
+/// after the stagger delay passes, or as soon as the previous one fails, without canceling the
+/// ones still in progress. Once any attempt succeeds, the remaining ones are dropped, which closes
+/// their sockets. If all attempts fail, the last error is returned along with its address.
+pub(crate) async fn connect_staggered<S, F, Fut>(
+    addr: SocketAddr,
+    alternative_addrs: &[SocketAddr],
+    stagger_delay: Duration,
+    connect: F,
+) -> Result<(S, SocketAddr), (io::Error, SocketAddr)>
+where
+    F: Fn(SocketAddr) -> Fut,
+    Fut: Future<Output = io::Result<S>>,
+{
+    let attempt = |addr: SocketAddr| connect(addr).map(move |result| (result, addr));
+
+    let mut pending = alternative_addrs.iter().copied();
+    let mut attempts = FuturesUnordered::new();
+    attempts.push(attempt(addr));
+
+    let mut last_error = None;
+    while !attempts.is_empty() {
+        let finished = if pending.len() > 0 {
+            match timeout(stagger_delay, attempts.next()).await {
+                Ok(finished) => finished,
+                Err(_) => {
+                    attempts.extend(pending.next().map(attempt));
+                    continue;
+                }
+            }
+        } else {
+            attempts.next().await
+        };
+
+        match finished {
+            Some((Ok(stream), addr)) => return Ok((stream, addr)),
+            Some((Err(error), addr)) => {
+                last_error = Some((error, addr));
+
+                if attempts.is_empty() {
+                    attempts.extend(pending.next().map(attempt));
+                }
+            }
+            None => break,
+        }
+    }
+
+    Err(last_error.unwrap_or_else(|| {
+        (
+            io::Error::new(io::ErrorKind::NotConnected, "No connection attempt made"),
+            addr,
+        )
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::future::{pending, ready, BoxFuture};
+    use futures::FutureExt;
+    use std::io;
+    use std::net::SocketAddr;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::time::{Duration, Instant};
+
+    use super::{connect_staggered, AddressFamilyPreference};
+
+    fn addr(addr: &str) -> SocketAddr {
+        addr.parse().unwrap()
+    }
+
+    #[test]
+    fn should_interleave_address_families() {
+        let addrs = [
+            addr("10.0.0.1:9042"),
+            addr("10.0.0.2:9042"),
+            addr("10.0.0.1:9042"),
+            addr("[::1]:9042"),
+            addr("10.0.0.3:9042"),
+        ];
+
+        assert_eq!(
+            AddressFamilyPreference::Ipv6.order(&addrs),
+            [
+                addr("[::1]:9042"),
+                addr("10.0.0.1:9042"),
+                addr("10.0.0.2:9042"),
+                addr("10.0.0.3:9042"),
+            ]
+        );
+        assert_eq!(
+            AddressFamilyPreference::Ipv4.order(&addrs),
+            [
+                addr("10.0.0.1:9042"),
+                addr("[::1]:9042"),
+                addr("10.0.0.2:9042"),
+                addr("10.0.0.3:9042"),
+            ]
+        );
+        assert_eq!(
+            AddressFamilyPreference::AsResolved.order(&addrs),
+            [
+                addr("10.0.0.1:9042"),
+                addr("10.0.0.2:9042"),
+                addr("[::1]:9042"),
+                addr("10.0.0.3:9042"),
+            ]
+        );
+    }
+
+    #[test]
+    fn should_only_group_dual_stack_addresses() {
+        assert_eq!(
+            AddressFamilyPreference::Ipv6.contact_points(&[
+                addr("10.0.0.1:9042"),
+                addr("[::1]:9042"),
+                addr("10.0.0.1:9042"),
+            ]),
+            [vec![addr("[::1]:9042"), addr("10.0.0.1:9042")]]
+        );
+
+        assert_eq!(
+            AddressFamilyPreference::AsResolved.contact_points(&[
+                addr("10.0.0.1:9042"),
+                addr("10.0.0.2:9042"),
+                addr("[::1]:9042"),
+            ]),
+            [
+                vec![addr("10.0.0.1:9042")],
+                vec![addr("10.0.0.2:9042")],
+                vec![addr("[::1]:9042")],
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn should_start_next_attempt_after_stagger_delay() {
+        let hanging = addr("[::1]:9042");
+        let reachable = addr("127.0.0.1:9042");
+
+        let result = connect_staggered(
+            hanging,
+            &[reachable],
+            Duration::from_millis(10),
+            |addr| -> BoxFuture<io::Result<SocketAddr>> {
+                if addr == hanging {
+                    pending().boxed()
+                } else {
+                    ready(Ok(addr)).boxed()
+                }
+            },
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(result, (reachable, reachable));
+    }
+
+    #[tokio::test]
+    async fn should_start_next_attempt_after_failure() {
+        let attempts = AtomicUsize::new(0);
+        let start = Instant::now();
+
+        let (error, error_addr) = connect_staggered(
+            addr("[::1]:9042"),
+            &[addr("127.0.0.1:9042"), addr("127.0.0.2:9042")],
+            Duration::from_secs(60),
+            |_| {
+                attempts.fetch_add(1, Ordering::Relaxed);
+                ready(Err::<(), _>(io::Error::from(
+                    io::ErrorKind::ConnectionRefused,
+                )))
+            },
+        )
+        .await
+        .unwrap_err();
+
+        assert!(start.elapsed() < Duration::from_secs(60));
+        assert_eq!(attempts.load(Ordering::Relaxed), 3);
+        assert_eq!(error.kind(), io::ErrorKind::ConnectionRefused);
+        assert_eq!(error_addr, addr("127.0.0.2:9042"));
+    }
+}
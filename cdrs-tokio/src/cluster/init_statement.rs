@@ -0,0 +1,140 @@
+use cassandra_protocol::consistency::Consistency;
+use cassandra_protocol::error::Result;
+use cassandra_protocol::frame::{Envelope, Flags, Version};
+use cassandra_protocol::query::QueryValues;
+
+use crate::transport::CdrsTransport;
+
+/// Statement executed on every new connection, after the handshake and setting the current
+/// keyspace, but before the connection is used for requests. Failing statements fail the
+/// connection.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct InitStatement {
+    query: String,
+    values: Option<QueryValues>,
+    consistency: Consistency,
+}
+
+impl InitStatement {
+    /// Creates a statement executing given query with `ONE` consistency.
+    pub fn new<Q: ToString>(query: Q) -> Self {
+        InitStatement {
+            query: query.to_string(),
+            values: None,
+            consistency: Consistency::One,
+        }
+    }
+
+    /// Binds given values to the query.
+    #[must_use]
+    pub fn with_values(mut self, values: QueryValues) -> Self {
+        self.values = Some(values);
+        self
+    }
+
+    /// Sets consistency of the query.
+    #[must_use]
+    pub fn with_consistency(mut self, consistency: Consistency) -> Self {
+        self.consistency = consistency;
+        self
+    }
+
+    #[inline]
+    pub fn query(&self) -> &str {
+        &self.query
+    }
+
+    fn to_envelope(&self, version: Version) -> Result<Envelope> {
+        Envelope::new_req_query(
+            self.query.clone(),
+            self.consistency,
+            self.values.clone(),
+            false,
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
+            Flags::empty(),
+            version,
+        )
+    }
+}
+
+/// Executes given statements on a connection in order, stopping at the first failure. Used by the
+/// built-in connection managers after [`startup`](crate::cluster::startup) and available for custom
+/// ones.
+pub async fn initialize<T: CdrsTransport>(
+    transport: &T,
+    init_statements: &[InitStatement],
+    version: Version,
+) -> Result<()> {
+    for init_statement in init_statements {
+        transport
+            .write_envelope(&init_statement.to_envelope(version)?, false)
+            .await?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use cassandra_protocol::error::Error;
+    use cassandra_protocol::frame::message_error::{ErrorBody, ErrorType};
+    use cassandra_protocol::frame::message_query::BodyReqQuery;
+    use cassandra_protocol::frame::{Envelope, FromCursor, Version};
+    use std::io::Cursor;
+    use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+    use std::sync::{Arc, Mutex};
+
+    use super::{initialize, InitStatement};
+    use crate::transport::MockCdrsTransport;
+
+    const ADDR: SocketAddr = SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), 9042);
+
+    #[tokio::test]
+    async fn should_stop_at_first_failing_statement() {
+        let queries = Arc::new(Mutex::new(vec![]));
+
+        let mut transport = MockCdrsTransport::new();
+        let sent_queries = queries.clone();
+        transport
+            .expect_write_envelope()
+            .returning(move |envelope, _| {
+                let query = BodyReqQuery::from_cursor(
+                    &mut Cursor::new(envelope.body.as_slice()),
+                    Version::V4,
+                )
+                .unwrap()
+                .query;
+                let failed = query.starts_with("FAIL");
+                sent_queries.lock().unwrap().push(query);
+
+                Box::pin(async move {
+                    if failed {
+                        Err(Error::Server {
+                            body: ErrorBody {
+                                message: "error".into(),
+                                ty: ErrorType::Invalid,
+                            },
+                            addr: ADDR,
+                        })
+                    } else {
+                        Ok(Envelope::new_req_options(Version::V4))
+                    }
+                })
+            });
+
+        let init_statements = [
+            InitStatement::new("USE ks"),
+            InitStatement::new("FAIL"),
+            InitStatement::new("NEVER SENT"),
+        ];
+
+        let result = initialize(&transport, &init_statements, Version::V4).await;
+        assert!(matches!(result, Err(Error::Server { .. })));
+        assert_eq!(*queries.lock().unwrap(), vec!["USE ks", "FAIL"]);
+    }
+}
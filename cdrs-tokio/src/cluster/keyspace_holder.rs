@@ -1,13 +1,15 @@
 use std::sync::Arc;
 
 use arc_swap::ArcSwapOption;
-use tokio::sync::watch::Sender;
+use tokio::sync::watch::{self, Receiver, Sender};
 
 /// Holds currently set global keyspace.
 #[derive(Debug)]
 pub struct KeyspaceHolder {
     current_keyspace: ArcSwapOption<String>,
     keyspace_sender: Sender<Option<String>>,
+    // unlike the keyspace sender, reflects all keyspace updates
+    change_sender: Sender<Option<String>>,
 }
 
 impl KeyspaceHolder {
@@ -15,9 +17,16 @@ impl KeyspaceHolder {
         KeyspaceHolder {
             current_keyspace: Default::default(),
             keyspace_sender,
+            change_sender: watch::channel(None).0,
         }
     }
 
+    /// Returns a receiver observing the current keyspace, including the initial one.
+    #[inline]
+    pub fn subscribe(&self) -> Receiver<Option<String>> {
+        self.change_sender.subscribe()
+    }
+
     #[inline]
     pub fn current_keyspace(&self) -> Option<Arc<String>> {
         self.current_keyspace.load().clone()
@@ -26,6 +35,8 @@ impl KeyspaceHolder {
     #[inline]
     pub fn update_current_keyspace(&self, keyspace: String) {
         let old_keyspace = self.current_keyspace.swap(Some(Arc::new(keyspace.clone())));
+        self.notify_change(&keyspace);
+
         match &old_keyspace {
             None => {
                 self.send_notification(keyspace);
@@ -39,11 +50,50 @@ impl KeyspaceHolder {
 
     #[inline]
     pub fn update_current_keyspace_without_notification(&self, keyspace: String) {
+        self.notify_change(&keyspace);
         self.current_keyspace.store(Some(Arc::new(keyspace)));
     }
 
+    #[inline]
+    fn notify_change(&self, keyspace: &str) {
+        self.change_sender.send_if_modified(|current| {
+            if current.as_deref() == Some(keyspace) {
+                false
+            } else {
+                *current = Some(keyspace.to_string());
+                true
+            }
+        });
+    }
+
     #[inline]
     fn send_notification(&self, keyspace: String) {
         let _ = self.keyspace_sender.send(Some(keyspace));
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use tokio::sync::watch;
+
+    use super::KeyspaceHolder;
+
+    #[test]
+    fn should_notify_subscribers_about_changes() {
+        let (keyspace_sender, _keyspace_receiver) = watch::channel(None);
+        let holder = KeyspaceHolder::new(keyspace_sender);
+
+        let mut changes = holder.subscribe();
+        assert_eq!(*changes.borrow_and_update(), None);
+
+        holder.update_current_keyspace_without_notification("ks1".into());
+        assert!(changes.has_changed().unwrap());
+        assert_eq!(changes.borrow_and_update().as_deref(), Some("ks1"));
+
+        holder.update_current_keyspace("ks1".into());
+        assert!(!changes.has_changed().unwrap());
+
+        holder.update_current_keyspace("ks2".into());
+        assert_eq!(changes.borrow_and_update().as_deref(), Some("ks2"));
+    }
+}
@@ -0,0 +1,183 @@
+use cassandra_protocol::error;
+use cassandra_protocol::frame::Envelope;
+use cassandra_protocol::query::{PreparedQuery, QueryValues};
+use futures::FutureExt;
+use std::borrow::Cow;
+
+use crate::cluster::session::Session;
+use crate::cluster::{ConnectionManager, StatementRequest, StatementTarget};
+use crate::future::BoxFuture;
+use crate::load_balancing::LoadBalancingStrategy;
+use crate::statement::{StatementParams, StatementParamsBuilder};
+use crate::transport::CdrsTransport;
+
+/// Handle to a [`Session`] scoped to a single keyspace, returned by [`Session::with_keyspace`].
+/// Queries and prepared statements carry the keyspace with each request instead of relying on
+/// `USE`, so one session can serve many keyspaces at the same time, e.g. one per tenant. Handles
+/// only borrow the session, so they are cheap to create per request.
+///
+/// Statements prepared through handles are kept in the prepared statement cache of the session,
+/// by keyspace and query, so they are shared by all handles and included in its snapshots.
+pub struct KeyspaceSession<
+    'a,
+    T: CdrsTransport + 'static,
+    CM: ConnectionManager<T> + 'static,
+    LB: LoadBalancingStrategy<T, CM> + Send + Sync,
+> {
+    session: &'a Session<T, CM, LB>,
+    keyspace: Cow<'a, str>,
+}
+
+impl<
+        T: CdrsTransport + 'static,
+        CM: ConnectionManager<T> + 'static,
+        LB: LoadBalancingStrategy<T, CM> + Send + Sync,
+    > Clone for KeyspaceSession<'_, T, CM, LB>
+{
+    fn clone(&self) -> Self {
+        KeyspaceSession {
+            session: self.session,
+            keyspace: self.keyspace.clone(),
+        }
+    }
+}
+
+impl<
+        'a,
+        T: CdrsTransport + 'static,
+        CM: ConnectionManager<T> + Send + Sync + 'static,
+        LB: LoadBalancingStrategy<T, CM> + Send + Sync + 'static,
+    > KeyspaceSession<'a, T, CM, LB>
+{
+    #[inline]
+    pub(crate) fn new(session: &'a Session<T, CM, LB>, keyspace: Cow<'a, str>) -> Self {
+        KeyspaceSession { session, keyspace }
+    }
+
+    /// Returns the keyspace of this handle.
+    #[inline]
+    pub fn keyspace(&self) -> &str {
+        &self.keyspace
+    }
+
+    /// Returns the underlying session.
+    #[inline]
+    pub fn session(&self) -> &'a Session<T, CM, LB> {
+        self.session
+    }
+
+    /// Executes a query in the keyspace of this handle. Statement parameters can be overridden on
+    /// the returned [`StatementRequest`] before awaiting it, except for the keyspace.
+    #[inline]
+    pub fn query<'b, Q: Into<Cow<'b, str>>>(&'b self, query: Q) -> StatementRequest<'b> {
+        StatementRequest::query(self, query.into())
+    }
+
+    /// Executes a query with bounded values (either with or without names) in the keyspace of
+    /// this handle.
+    #[inline]
+    pub async fn query_with_values<'b, Q: Into<Cow<'b, str>>, V: Into<QueryValues>>(
+        &self,
+        query: Q,
+        values: V,
+    ) -> error::Result<Envelope> {
+        self.query_with_params(
+            query,
+            StatementParamsBuilder::new()
+                .with_values(values.into())
+                .build(),
+        )
+        .await
+    }
+
+    /// Executes a query with query parameters in the keyspace of this handle, which replaces any
+    /// keyspace set in the parameters.
+    pub async fn query_with_params<'b, Q: Into<Cow<'b, str>>>(
+        &self,
+        query: Q,
+        mut parameters: StatementParams,
+    ) -> error::Result<Envelope> {
+        parameters.keyspace = Some(self.keyspace.to_string());
+        parameters.query_params.keyspace = parameters.keyspace.clone();
+
+        self.session.query_with_params(query, parameters).await
+    }
+
+    /// Prepares a query in the keyspace of this handle. Statements already prepared in the same
+    /// keyspace, through any handle, are returned without contacting the cluster.
+    pub async fn prepare<'b, Q: Into<Cow<'b, str>>>(
+        &self,
+        query: Q,
+    ) -> error::Result<PreparedQuery> {
+        let query = query.into();
+        if let Some(prepared) = self
+            .session
+            .prepared_cache()
+            .get(&query, Some(self.keyspace.as_ref()))
+        {
+            return Ok(prepared);
+        }
+
+        // concurrent requests might prepare the same statement, which is harmless
+        self.session
+            .prepare_tw(query, Some(self.keyspace.to_string()), false, false, false)
+            .await
+    }
+
+    /// Executes given prepared query. Statement parameters can be overridden on the returned
+    /// [`StatementRequest`] before awaiting it, except for the keyspace.
+    #[inline]
+    pub fn exec<'b>(&'b self, prepared: &'b PreparedQuery) -> StatementRequest<'b> {
+        StatementRequest::prepared(self, prepared)
+    }
+
+    /// Executes given prepared query with query values.
+    #[inline]
+    pub async fn exec_with_values<V: Into<QueryValues>>(
+        &self,
+        prepared: &PreparedQuery,
+        values: V,
+    ) -> error::Result<Envelope> {
+        self.exec_with_params(
+            prepared,
+            StatementParamsBuilder::new()
+                .with_values(values.into())
+                .build(),
+        )
+        .await
+    }
+
+    /// Executes given prepared query with query parameters. The keyspace of this handle is used
+    /// for routing, unless the statement was prepared with its own keyspace.
+    pub async fn exec_with_params(
+        &self,
+        prepared: &PreparedQuery,
+        mut parameters: StatementParams,
+    ) -> error::Result<Envelope> {
+        parameters.keyspace = Some(self.keyspace.to_string());
+        self.session.exec_with_params(prepared, &parameters).await
+    }
+}
+
+impl<
+        T: CdrsTransport + 'static,
+        CM: ConnectionManager<T> + Send + Sync + 'static,
+        LB: LoadBalancingStrategy<T, CM> + Send + Sync + 'static,
+    > StatementTarget for KeyspaceSession<'_, T, CM, LB>
+{
+    fn send_query<'a>(
+        &'a self,
+        query: Cow<'a, str>,
+        parameters: StatementParams,
+    ) -> BoxFuture<'a, error::Result<Envelope>> {
+        self.query_with_params(query, parameters).boxed()
+    }
+
+    fn send_prepared<'a>(
+        &'a self,
+        prepared: &'a PreparedQuery,
+        parameters: StatementParams,
+    ) -> BoxFuture<'a, error::Result<Envelope>> {
+        self.exec_with_params(prepared, parameters).boxed()
+    }
+}
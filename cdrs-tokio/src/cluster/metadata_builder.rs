@@ -134,6 +134,30 @@ pub(crate) fn add_new_node<T: CdrsTransport, CM: ConnectionManager<T>>(
     ))
 }
 
+/// Replaces a node which came back under the same address with a new host id, e.g. after being
+/// replaced in place. Nothing is carried over from the old node, including its connections.
+pub(crate) fn replace_node<T: CdrsTransport, CM: ConnectionManager<T>>(
+    node_info: NodeInfo,
+    old_metadata: &ClusterMetadata<T, CM>,
+    connection_pool_factory: &Arc<ConnectionPoolFactory<T, CM>>,
+    node_distance_evaluator: &dyn NodeDistanceEvaluator,
+    state: NodeState,
+) -> ClusterMetadata<T, CM> {
+    old_metadata
+        .clone_without_node(node_info.broadcast_rpc_address)
+        .clone_with_node(Node::new_with_state(
+            connection_pool_factory.clone(),
+            node_info.broadcast_rpc_address,
+            node_info.broadcast_address,
+            Some(node_info.host_id),
+            node_distance_evaluator.compute_distance(&node_info),
+            state,
+            node_info.tokens,
+            node_info.rack,
+            node_info.datacenter,
+        ))
+}
+
 //noinspection DuplicatedCode
 #[cfg(test)]
 mod tests {
@@ -146,7 +170,7 @@ mod tests {
     use crate::cluster::connection_manager::MockConnectionManager;
     use crate::cluster::connection_pool::ConnectionPoolFactory;
     use crate::cluster::metadata_builder::{
-        add_new_node, build_initial_metadata, refresh_metadata,
+        add_new_node, build_initial_metadata, refresh_metadata, replace_node,
     };
     use crate::cluster::topology::NodeMap;
     use crate::cluster::topology::{Node, NodeDistance, NodeState};
@@ -501,4 +525,57 @@ mod tests {
         );
         assert!(nodes.get(&node_info.host_id).unwrap().distance().is_none());
     }
+
+    #[test]
+    fn should_replace_node_with_new_host_id() {
+        let connection_pool_factory = create_connection_pool_factory();
+
+        let mut node_distance_evaluator = MockNodeDistanceEvaluator::new();
+        node_distance_evaluator
+            .expect_compute_distance()
+            .return_const(Some(NodeDistance::Local));
+
+        let addr = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)), 8080);
+        let old_host_id = Uuid::new_v4();
+
+        let mut old_nodes = NodeMap::default();
+        old_nodes.insert(
+            old_host_id,
+            Arc::new(Node::with_distance(
+                connection_pool_factory.clone(),
+                addr,
+                None,
+                Some(old_host_id),
+                NodeDistance::Local,
+            )),
+        );
+
+        let old_metadata = ClusterMetadata::new(old_nodes, Default::default());
+
+        let node_info = NodeInfo::new(
+            Uuid::new_v4(),
+            addr,
+            None,
+            "".into(),
+            Default::default(),
+            "".into(),
+        );
+
+        let metadata = replace_node(
+            node_info.clone(),
+            &old_metadata,
+            &connection_pool_factory,
+            &node_distance_evaluator,
+            NodeState::Up,
+        );
+
+        let nodes = metadata.nodes();
+        assert_eq!(nodes.len(), 1);
+        assert!(nodes.get(&old_host_id).is_none());
+
+        let node = nodes.get(&node_info.host_id).unwrap();
+        assert_eq!(node.broadcast_rpc_address(), addr);
+        assert_eq!(node.state(), NodeState::Up);
+        assert_eq!(node.distance(), Some(NodeDistance::Local));
+    }
 }
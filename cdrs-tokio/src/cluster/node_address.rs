@@ -1,9 +1,10 @@
 use derive_more::Display;
 use std::net::SocketAddr;
-use tokio::net::lookup_host;
 
 use cassandra_protocol::error::Result;
 
+use crate::runtime::lookup_host;
+
 /// Representation of a node address. Can be a direct socket address or a hostname. In the latter
 /// case, the host can be resolved to multiple addresses, which could result in multiple node
 /// configurations.
@@ -42,10 +43,7 @@ impl NodeAddress {
     pub async fn resolve_address(&self) -> Result<Vec<SocketAddr>> {
         match self {
             NodeAddress::Direct(addr) => Ok(vec![*addr]),
-            NodeAddress::Hostname(hostname) => lookup_host(hostname)
-                .await
-                .map(|addrs| addrs.collect())
-                .map_err(Into::into),
+            NodeAddress::Hostname(hostname) => lookup_host(hostname).await.map_err(Into::into),
         }
     }
 }
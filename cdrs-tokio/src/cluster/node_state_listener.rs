@@ -0,0 +1,17 @@
+use std::net::SocketAddr;
+
+use crate::cluster::topology::NodeState;
+
+/// Listener notified about connection progress and state changes of nodes, e.g. to observe
+/// warmup or the session connecting to nodes added by topology changes. Called from connection
+/// management tasks, so implementations should return quickly.
+pub trait NodeStateListener {
+    /// Called when the connection pool to a node has been established, with the number of
+    /// connections which could be opened. Connections are established concurrently, up to the
+    /// limit set with
+    /// [`with_connect_concurrency`](crate::cluster::connection_pool::ConnectionPoolConfigBuilder::with_connect_concurrency).
+    fn on_connected(&self, _broadcast_rpc_address: SocketAddr, _connections: usize) {}
+
+    /// Called when the state of a node changes due to connection activity.
+    fn on_state_changed(&self, _broadcast_rpc_address: SocketAddr, _state: NodeState) {}
+}
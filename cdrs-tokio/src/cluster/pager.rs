@@ -1,14 +1,18 @@
 use cassandra_protocol::consistency::Consistency;
 use cassandra_protocol::error;
-use cassandra_protocol::frame::message_result::RowsMetadataFlags;
+use cassandra_protocol::frame::message_response::ParsedResponse;
+use cassandra_protocol::frame::{Envelope, TryFromRow};
 use cassandra_protocol::query::{PreparedQuery, QueryParams, QueryParamsBuilder, QueryValues};
 use cassandra_protocol::types::rows::Row;
 use cassandra_protocol::types::CBytes;
+use futures::FutureExt;
+use std::marker::PhantomData;
 
 use crate::cluster::session::Session;
-use crate::cluster::ConnectionManager;
+use crate::cluster::{ConnectionManager, DynSession};
+use crate::future::BoxFuture;
 use crate::load_balancing::LoadBalancingStrategy;
-use crate::statement::StatementParamsBuilder;
+use crate::statement::{StatementParams, StatementParamsBuilder};
 use crate::transport::CdrsTransport;
 
 pub struct SessionPager<
@@ -58,6 +62,7 @@ impl<
             query,
             qv: qp.values,
             consistency: qp.consistency,
+            total_row_count: 0,
         }
     }
 
@@ -105,6 +110,23 @@ impl<
             pager: self,
             pager_state: state,
             query,
+            values: None,
+            total_row_count: 0,
+        }
+    }
+
+    pub fn exec_with_values_and_pager_state<V: Into<QueryValues>>(
+        &'a mut self,
+        query: &'a PreparedQuery,
+        values: V,
+        state: PagerState,
+    ) -> ExecPager<'a, SessionPager<'a, T, CM, LB>> {
+        ExecPager {
+            pager: self,
+            pager_state: state,
+            query,
+            values: Some(values.into()),
+            total_row_count: 0,
         }
     }
 
@@ -114,6 +136,140 @@ impl<
     ) -> ExecPager<'a, SessionPager<'a, T, CM, LB>> {
         self.exec_with_pager_state(query, PagerState::new())
     }
+
+    /// Pages through results of a query, converting rows with [`TryFromRow`].
+    pub fn query_as<R: TryFromRow, Q: ToString>(
+        &'a mut self,
+        query: Q,
+    ) -> TypedPager<QueryPager<'a, Q, SessionPager<'a, T, CM, LB>>, R> {
+        TypedPager::new(self.query(query))
+    }
+
+    /// Pages through results of a prepared statement executed with given values, converting rows
+    /// with [`TryFromRow`].
+    pub fn exec_as<R: TryFromRow, V: Into<QueryValues>>(
+        &'a mut self,
+        query: &'a PreparedQuery,
+        values: V,
+    ) -> TypedPager<ExecPager<'a, SessionPager<'a, T, CM, LB>>, R> {
+        TypedPager::new(self.exec_with_values_and_pager_state(query, values, PagerState::new()))
+    }
+}
+
+/// Pager for [`DynSession`].
+pub struct DynSessionPager<'a> {
+    page_size: i32,
+    session: &'a DynSession,
+}
+
+impl<'a> DynSessionPager<'a> {
+    pub fn new(session: &'a DynSession, page_size: i32) -> DynSessionPager<'a> {
+        DynSessionPager { session, page_size }
+    }
+
+    pub fn query_with_pager_state<Q>(
+        &'a mut self,
+        query: Q,
+        state: PagerState,
+    ) -> QueryPager<'a, Q, DynSessionPager<'a>>
+    where
+        Q: ToString,
+    {
+        self.query_with_pager_state_params(query, state, Default::default())
+    }
+
+    pub fn query_with_pager_state_params<Q>(
+        &'a mut self,
+        query: Q,
+        state: PagerState,
+        qp: QueryParams,
+    ) -> QueryPager<'a, Q, DynSessionPager<'a>>
+    where
+        Q: ToString,
+    {
+        QueryPager {
+            pager: self,
+            pager_state: state,
+            query,
+            qv: qp.values,
+            consistency: qp.consistency,
+            total_row_count: 0,
+        }
+    }
+
+    pub fn query<Q>(&'a mut self, query: Q) -> QueryPager<'a, Q, DynSessionPager<'a>>
+    where
+        Q: ToString,
+    {
+        self.query_with_params(
+            query,
+            QueryParamsBuilder::new()
+                .with_consistency(Consistency::One)
+                .build(),
+        )
+    }
+
+    pub fn query_with_params<Q>(
+        &'a mut self,
+        query: Q,
+        qp: QueryParams,
+    ) -> QueryPager<'a, Q, DynSessionPager<'a>>
+    where
+        Q: ToString,
+    {
+        self.query_with_pager_state_params(query, PagerState::new(), qp)
+    }
+
+    pub fn exec_with_pager_state(
+        &'a mut self,
+        query: &'a PreparedQuery,
+        state: PagerState,
+    ) -> ExecPager<'a, DynSessionPager<'a>> {
+        ExecPager {
+            pager: self,
+            pager_state: state,
+            query,
+            values: None,
+            total_row_count: 0,
+        }
+    }
+
+    pub fn exec_with_values_and_pager_state<V: Into<QueryValues>>(
+        &'a mut self,
+        query: &'a PreparedQuery,
+        values: V,
+        state: PagerState,
+    ) -> ExecPager<'a, DynSessionPager<'a>> {
+        ExecPager {
+            pager: self,
+            pager_state: state,
+            query,
+            values: Some(values.into()),
+            total_row_count: 0,
+        }
+    }
+
+    pub fn exec(&'a mut self, query: &'a PreparedQuery) -> ExecPager<'a, DynSessionPager<'a>> {
+        self.exec_with_pager_state(query, PagerState::new())
+    }
+
+    /// Pages through results of a query, converting rows with [`TryFromRow`].
+    pub fn query_as<R: TryFromRow, Q: ToString>(
+        &'a mut self,
+        query: Q,
+    ) -> TypedPager<QueryPager<'a, Q, DynSessionPager<'a>>, R> {
+        TypedPager::new(self.query(query))
+    }
+
+    /// Pages through results of a prepared statement executed with given values, converting rows
+    /// with [`TryFromRow`].
+    pub fn exec_as<R: TryFromRow, V: Into<QueryValues>>(
+        &'a mut self,
+        query: &'a PreparedQuery,
+        values: V,
+    ) -> TypedPager<ExecPager<'a, DynSessionPager<'a>>, R> {
+        TypedPager::new(self.exec_with_values_and_pager_state(query, values, PagerState::new()))
+    }
 }
 
 pub struct QueryPager<'a, Q: ToString, P: 'a> {
@@ -122,24 +278,28 @@ pub struct QueryPager<'a, Q: ToString, P: 'a> {
     query: Q,
     qv: Option<QueryValues>,
     consistency: Consistency,
+    total_row_count: usize,
 }
 
-impl<
-        'a,
-        Q: ToString,
-        T: CdrsTransport + 'static,
-        CM: ConnectionManager<T> + Send + Sync + 'static,
-        LB: LoadBalancingStrategy<T, CM> + Send + Sync + 'static,
-    > QueryPager<'a, Q, SessionPager<'a, T, CM, LB>>
-{
+impl<'a, Q: ToString, P> QueryPager<'a, Q, P> {
     pub fn into_pager_state(self) -> PagerState {
         self.pager_state
     }
 
-    pub async fn next(&mut self) -> error::Result<Vec<Row>> {
+    pub fn has_more(&self) -> bool {
+        self.pager_state.has_more_pages.unwrap_or(false)
+    }
+
+    /// This method returns a copy of pager state so
+    /// the state may be used later for continuing paging.
+    pub fn pager_state(&self) -> PagerState {
+        self.pager_state.clone()
+    }
+
+    fn page_params(&self, page_size: i32) -> StatementParams {
         let mut params = StatementParamsBuilder::new()
             .with_consistency(self.consistency)
-            .with_page_size(self.pager.page_size);
+            .with_page_size(page_size);
 
         if let Some(qv) = &self.qv {
             params = params.with_values(qv.clone());
@@ -147,42 +307,126 @@ impl<
         if let Some(cursor) = &self.pager_state.cursor {
             params = params.with_paging_state(cursor.clone());
         }
+
+        params.build()
+    }
+
+    /// Number of rows fetched by this pager so far.
+    #[inline]
+    pub fn total_row_count(&self) -> usize {
+        self.total_row_count
+    }
+
+    fn save_progress(&self) -> PageCheckpoint {
+        PageCheckpoint::new(&self.pager_state, self.total_row_count)
+    }
+
+    fn restore_progress(&mut self, checkpoint: PageCheckpoint) {
+        checkpoint.restore(&mut self.pager_state, &mut self.total_row_count);
+    }
+}
+
+impl<
+        'a,
+        Q: ToString,
+        T: CdrsTransport + 'static,
+        CM: ConnectionManager<T> + Send + Sync + 'static,
+        LB: LoadBalancingStrategy<T, CM> + Send + Sync + 'static,
+    > QueryPager<'a, Q, SessionPager<'a, T, CM, LB>>
+{
+    /// Fetches the next page along with its metadata.
+    pub async fn next_page(&mut self) -> error::Result<Page> {
+        let params = self.page_params(self.pager.page_size);
         let query = self.query.to_string();
 
-        let body = self
+        let response = self
             .pager
             .session
-            .query_with_params(query, params.build())
+            .query_with_params(query, params)
             .await
-            .and_then(|envelope| envelope.response_body())?;
+            .and_then(Envelope::into_parsed_response);
+        self.pager_state
+            .read_page(response, &mut self.total_row_count)
+    }
 
-        let metadata = body
-            .as_rows_metadata()
-            .ok_or("Pager query should yield a vector of rows")?;
+    #[deprecated(note = "Use next_page().")]
+    pub async fn next(&mut self) -> error::Result<Vec<Row>> {
+        self.next_page().await.map(Page::into_rows)
+    }
+}
+
+impl<'a, Q: ToString> QueryPager<'a, Q, DynSessionPager<'a>> {
+    /// Fetches the next page along with its metadata.
+    pub async fn next_page(&mut self) -> error::Result<Page> {
+        let params = self.page_params(self.pager.page_size);
+        let query = self.query.to_string();
 
-        self.pager_state.has_more_pages =
-            Some(metadata.flags.contains(RowsMetadataFlags::HAS_MORE_PAGES));
-        self.pager_state.cursor.clone_from(&metadata.paging_state);
+        let response = self
+            .pager
+            .session
+            .query_with_params(query, params)
+            .await
+            .and_then(Envelope::into_parsed_response);
+        self.pager_state
+            .read_page(response, &mut self.total_row_count)
+    }
 
-        body.into_rows()
-            .ok_or_else(|| "Pager query should yield a vector of rows".into())
+    #[deprecated(note = "Use next_page().")]
+    pub async fn next(&mut self) -> error::Result<Vec<Row>> {
+        self.next_page().await.map(Page::into_rows)
     }
+}
 
+pub struct ExecPager<'a, P: 'a> {
+    pager: &'a mut P,
+    pager_state: PagerState,
+    query: &'a PreparedQuery,
+    values: Option<QueryValues>,
+    total_row_count: usize,
+}
+
+impl<'a, P> ExecPager<'a, P> {
+    pub fn into_pager_state(self) -> PagerState {
+        self.pager_state
+    }
+
+    #[inline]
     pub fn has_more(&self) -> bool {
         self.pager_state.has_more_pages.unwrap_or(false)
     }
 
     /// This method returns a copy of pager state so
     /// the state may be used later for continuing paging.
+    #[inline]
     pub fn pager_state(&self) -> PagerState {
         self.pager_state.clone()
     }
-}
 
-pub struct ExecPager<'a, P: 'a> {
-    pager: &'a mut P,
-    pager_state: PagerState,
-    query: &'a PreparedQuery,
+    fn page_params(&self, page_size: i32) -> StatementParams {
+        let mut params = StatementParamsBuilder::new().with_page_size(page_size);
+        if let Some(values) = &self.values {
+            params = params.with_values(values.clone());
+        }
+        if let Some(cursor) = &self.pager_state.cursor {
+            params = params.with_paging_state(cursor.clone());
+        }
+
+        params.build()
+    }
+
+    /// Number of rows fetched by this pager so far.
+    #[inline]
+    pub fn total_row_count(&self) -> usize {
+        self.total_row_count
+    }
+
+    fn save_progress(&self) -> PageCheckpoint {
+        PageCheckpoint::new(&self.pager_state, self.total_row_count)
+    }
+
+    fn restore_progress(&mut self, checkpoint: PageCheckpoint) {
+        checkpoint.restore(&mut self.pager_state, &mut self.total_row_count);
+    }
 }
 
 impl<
@@ -192,45 +436,195 @@ impl<
         LB: LoadBalancingStrategy<T, CM> + Send + Sync + 'static,
     > ExecPager<'a, SessionPager<'a, T, CM, LB>>
 {
-    pub fn into_pager_state(self) -> PagerState {
+    /// Fetches the next page along with its metadata.
+    pub async fn next_page(&mut self) -> error::Result<Page> {
+        let params = self.page_params(self.pager.page_size);
+
+        let response = self
+            .pager
+            .session
+            .exec_with_params_parsed(self.query, &params)
+            .await;
         self.pager_state
+            .read_page(response, &mut self.total_row_count)
     }
 
+    #[deprecated(note = "Use next_page().")]
     pub async fn next(&mut self) -> error::Result<Vec<Row>> {
-        let mut params = StatementParamsBuilder::new().with_page_size(self.pager.page_size);
-        if let Some(cursor) = &self.pager_state.cursor {
-            params = params.with_paging_state(cursor.clone());
-        }
+        self.next_page().await.map(Page::into_rows)
+    }
+}
+
+impl<'a> ExecPager<'a, DynSessionPager<'a>> {
+    /// Fetches the next page along with its metadata.
+    pub async fn next_page(&mut self) -> error::Result<Page> {
+        let params = self.page_params(self.pager.page_size);
 
-        let body = self
+        let response = self
             .pager
             .session
-            .exec_with_params(self.query, &params.build())
-            .await
-            .and_then(|envelope| envelope.response_body())?;
+            .exec_with_params_parsed(self.query, &params)
+            .await;
+        self.pager_state
+            .read_page(response, &mut self.total_row_count)
+    }
 
-        let metadata = body
-            .as_rows_metadata()
-            .ok_or("Pager query should yield a vector of rows")?;
+    #[deprecated(note = "Use next_page().")]
+    pub async fn next(&mut self) -> error::Result<Vec<Row>> {
+        self.next_page().await.map(Page::into_rows)
+    }
+}
 
-        self.pager_state.has_more_pages =
-            Some(metadata.flags.contains(RowsMetadataFlags::HAS_MORE_PAGES));
-        self.pager_state.cursor.clone_from(&metadata.paging_state);
+/// Pager converting rows of fetched pages with [`TryFromRow`]. Created with `query_as()` or
+/// `exec_as()` of a session pager, or by wrapping a [`QueryPager`] or [`ExecPager`], e.g. one
+/// resumed from a [`PagerState`].
+///
+/// If a row can't be converted, [`next`](Self::next) fails with
+/// [`Error::RowConversion`](error::Error::RowConversion) containing the index of the row within
+/// the page. The pager state is then rolled back to before the page, so resuming from it fetches
+/// the page again.
+pub struct TypedPager<P, R> {
+    pager: P,
+    row_type: PhantomData<fn() -> R>,
+}
 
-        body.into_rows()
-            .ok_or_else(|| "Pager query should yield a vector of rows".into())
+impl<P, R: TryFromRow> TypedPager<P, R> {
+    pub fn new(pager: P) -> Self {
+        TypedPager {
+            pager,
+            row_type: PhantomData,
+        }
     }
 
+    /// Underlying pager, e.g. to check if there are more pages or to get its state.
     #[inline]
-    pub fn has_more(&self) -> bool {
-        self.pager_state.has_more_pages.unwrap_or(false)
+    pub fn pager(&self) -> &P {
+        &self.pager
     }
 
-    /// This method returns a copy of pager state so
-    /// the state may be used later for continuing paging.
+    /// Consumes the typed pager, returning the underlying one.
     #[inline]
-    pub fn pager_state(&self) -> PagerState {
-        self.pager_state.clone()
+    pub fn into_pager(self) -> P {
+        self.pager
+    }
+}
+
+impl<P: RowPager, R: TryFromRow> TypedPager<P, R> {
+    /// Fetches the next page and converts its rows.
+    pub async fn next(&mut self) -> error::Result<Vec<R>> {
+        let checkpoint = self.pager.checkpoint();
+        let rows = self.pager.next_page().await?.into_rows();
+
+        // roll back to before the page, so resuming from the pager state fetches it again
+        let rows = R::try_from_rows(rows);
+        if rows.is_err() {
+            self.pager.roll_back(checkpoint);
+        }
+
+        rows
+    }
+}
+
+/// Pager fetching pages of untyped rows, which can be wrapped by a [`TypedPager`]. Implemented by
+/// [`QueryPager`] and [`ExecPager`].
+pub trait RowPager: Send {
+    /// Fetches the next page along with its metadata.
+    fn next_page(&mut self) -> BoxFuture<'_, error::Result<Page>>;
+
+    /// Saves the current paging progress.
+    fn checkpoint(&self) -> PageCheckpoint;
+
+    /// Restores paging progress saved by [`checkpoint`](Self::checkpoint).
+    fn roll_back(&mut self, checkpoint: PageCheckpoint);
+}
+
+/// Paging progress of a [`RowPager`], saved before fetching a page.
+#[derive(Clone, Debug)]
+pub struct PageCheckpoint {
+    pager_state: PagerState,
+    total_row_count: usize,
+}
+
+impl PageCheckpoint {
+    fn new(pager_state: &PagerState, total_row_count: usize) -> Self {
+        PageCheckpoint {
+            pager_state: pager_state.clone(),
+            total_row_count,
+        }
+    }
+
+    fn restore(self, pager_state: &mut PagerState, total_row_count: &mut usize) {
+        *pager_state = self.pager_state;
+        *total_row_count = self.total_row_count;
+    }
+}
+
+impl<
+        'a,
+        Q: ToString + Send,
+        T: CdrsTransport + 'static,
+        CM: ConnectionManager<T> + Send + Sync + 'static,
+        LB: LoadBalancingStrategy<T, CM> + Send + Sync + 'static,
+    > RowPager for QueryPager<'a, Q, SessionPager<'a, T, CM, LB>>
+{
+    fn next_page(&mut self) -> BoxFuture<'_, error::Result<Page>> {
+        self.next_page().boxed()
+    }
+
+    fn checkpoint(&self) -> PageCheckpoint {
+        self.save_progress()
+    }
+
+    fn roll_back(&mut self, checkpoint: PageCheckpoint) {
+        self.restore_progress(checkpoint)
+    }
+}
+
+impl<'a, Q: ToString + Send> RowPager for QueryPager<'a, Q, DynSessionPager<'a>> {
+    fn next_page(&mut self) -> BoxFuture<'_, error::Result<Page>> {
+        self.next_page().boxed()
+    }
+
+    fn checkpoint(&self) -> PageCheckpoint {
+        self.save_progress()
+    }
+
+    fn roll_back(&mut self, checkpoint: PageCheckpoint) {
+        self.restore_progress(checkpoint)
+    }
+}
+
+impl<
+        'a,
+        T: CdrsTransport + 'static,
+        CM: ConnectionManager<T> + Send + Sync + 'static,
+        LB: LoadBalancingStrategy<T, CM> + Send + Sync + 'static,
+    > RowPager for ExecPager<'a, SessionPager<'a, T, CM, LB>>
+{
+    fn next_page(&mut self) -> BoxFuture<'_, error::Result<Page>> {
+        self.next_page().boxed()
+    }
+
+    fn checkpoint(&self) -> PageCheckpoint {
+        self.save_progress()
+    }
+
+    fn roll_back(&mut self, checkpoint: PageCheckpoint) {
+        self.restore_progress(checkpoint)
+    }
+}
+
+impl<'a> RowPager for ExecPager<'a, DynSessionPager<'a>> {
+    fn next_page(&mut self) -> BoxFuture<'_, error::Result<Page>> {
+        self.next_page().boxed()
+    }
+
+    fn checkpoint(&self) -> PageCheckpoint {
+        self.save_progress()
+    }
+
+    fn roll_back(&mut self, checkpoint: PageCheckpoint) {
+        self.restore_progress(checkpoint)
     }
 }
 
@@ -283,4 +677,208 @@ impl PagerState {
     pub fn into_cursor(self) -> Option<CBytes> {
         self.cursor
     }
+
+    fn read_page(
+        &mut self,
+        response: error::Result<ParsedResponse>,
+        total_row_count: &mut usize,
+    ) -> error::Result<Page> {
+        let response = response?;
+        if response.rows_metadata().is_none() {
+            return Err("Pager query should yield a vector of rows".into());
+        }
+
+        self.has_more_pages = Some(response.has_more_pages());
+        self.cursor = response.paging_state().cloned();
+
+        let rows = response
+            .into_rows()
+            .ok_or("Pager query should yield a vector of rows")?;
+
+        *total_row_count += rows.len();
+
+        Ok(Page {
+            rows,
+            has_more: self.has_more(),
+            paging_state: self.cursor.clone(),
+            total_row_count: *total_row_count,
+        })
+    }
+}
+
+/// Single page of rows fetched by a pager, along with paging metadata.
+#[derive(Clone, Debug)]
+pub struct Page {
+    rows: Vec<Row>,
+    has_more: bool,
+    paging_state: Option<CBytes>,
+    total_row_count: usize,
+}
+
+impl Page {
+    /// Rows of this page.
+    #[inline]
+    pub fn rows(&self) -> &[Row] {
+        &self.rows
+    }
+
+    /// Consumes the page, returning its rows.
+    #[inline]
+    pub fn into_rows(self) -> Vec<Row> {
+        self.rows
+    }
+
+    /// Checks if there are more pages to fetch.
+    #[inline]
+    pub fn has_more(&self) -> bool {
+        self.has_more
+    }
+
+    /// Raw paging state returned by the server, which can be used to fetch the next page with
+    /// [`PagerState::new_with_cursor`].
+    #[inline]
+    pub fn paging_state(&self) -> Option<&CBytes> {
+        self.paging_state.as_ref()
+    }
+
+    /// Number of rows in this page.
+    #[inline]
+    pub fn row_count(&self) -> usize {
+        self.rows.len()
+    }
+
+    /// Number of rows fetched by the pager so far, including this page. Pagers resumed from a
+    /// [`PagerState`] only count rows they fetched themselves.
+    #[inline]
+    pub fn total_row_count(&self) -> usize {
+        self.total_row_count
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use cassandra_protocol::consistency::Consistency;
+    use cassandra_protocol::error::{Error, Result};
+    use cassandra_protocol::frame::message_result::{
+        BodyResResultRows, ColSpec, ColType, ColTypeOption, RowsMetadata, RowsMetadataFlags,
+    };
+    use cassandra_protocol::frame::{TryFromRow, Version};
+    use cassandra_protocol::types::rows::Row;
+    use cassandra_protocol::types::{CBytes, IntoRustByName};
+
+    use futures::future::{self, FutureExt};
+    use std::collections::VecDeque;
+
+    use super::{Page, PageCheckpoint, PagerState, QueryPager, RowPager, TypedPager};
+    use crate::future::BoxFuture;
+
+    #[derive(Debug, PartialEq)]
+    struct Positive(i32);
+
+    impl TryFromRow for Positive {
+        fn try_from_row(row: Row) -> Result<Self> {
+            let value: i32 = row.get_r_by_name("value")?;
+            if value > 0 {
+                Ok(Positive(value))
+            } else {
+                Err(Error::General(format!("Not positive: {value}")))
+            }
+        }
+    }
+
+    fn page(values: &[i32]) -> Page {
+        let rows = Row::from_body(BodyResResultRows {
+            metadata: RowsMetadata {
+                flags: RowsMetadataFlags::empty(),
+                columns_count: 1,
+                paging_state: None,
+                new_metadata_id: None,
+                global_table_spec: None,
+                col_specs: vec![ColSpec {
+                    table_spec: None,
+                    name: "value".into(),
+                    col_type: ColTypeOption {
+                        id: ColType::Int,
+                        value: None,
+                    },
+                }],
+            },
+            rows_count: values.len() as i32,
+            rows_content: values
+                .iter()
+                .map(|value| vec![CBytes::new(value.to_be_bytes().to_vec())])
+                .collect(),
+            protocol_version: Version::V4,
+        });
+
+        Page {
+            rows,
+            has_more: true,
+            paging_state: Some(CBytes::new(vec![2])),
+            total_row_count: 5 + values.len(),
+        }
+    }
+
+    struct TestPager<'a> {
+        pager: QueryPager<'a, &'static str, ()>,
+        pages: VecDeque<Vec<i32>>,
+    }
+
+    impl RowPager for TestPager<'_> {
+        // simulates fetching a page, which advances the pager state
+        fn next_page(&mut self) -> BoxFuture<'_, Result<Page>> {
+            let values = self.pages.pop_front().unwrap();
+            self.pager.pager_state =
+                PagerState::new_with_cursor_and_more_flag(CBytes::new(vec![2]), true);
+            self.pager.total_row_count += values.len();
+
+            future::ready(Ok(page(&values))).boxed()
+        }
+
+        fn checkpoint(&self) -> PageCheckpoint {
+            self.pager.save_progress()
+        }
+
+        fn roll_back(&mut self, checkpoint: PageCheckpoint) {
+            self.pager.restore_progress(checkpoint)
+        }
+    }
+
+    #[tokio::test]
+    async fn should_roll_back_pager_state_on_conversion_failure() {
+        let mut session_pager = ();
+        let mut pager = TypedPager::<_, Positive>::new(TestPager {
+            pager: QueryPager {
+                pager: &mut session_pager,
+                pager_state: PagerState::new_with_cursor_and_more_flag(CBytes::new(vec![1]), true),
+                query: "SELECT value FROM table",
+                qv: None,
+                consistency: Consistency::One,
+                total_row_count: 5,
+            },
+            pages: vec![vec![1, 2, -3], vec![1, 2]].into(),
+        });
+
+        let error = pager.next().await.unwrap_err();
+
+        assert!(
+            matches!(error, Error::RowConversion { index: 2, .. }),
+            "{:?}",
+            error
+        );
+        assert_eq!(
+            pager.pager().pager.pager_state().cursor(),
+            Some(CBytes::new(vec![1]))
+        );
+        assert_eq!(pager.pager().pager.total_row_count(), 5);
+
+        let rows = pager.next().await.unwrap();
+
+        assert_eq!(rows, vec![Positive(1), Positive(2)]);
+        assert_eq!(
+            pager.pager().pager.pager_state().cursor(),
+            Some(CBytes::new(vec![2]))
+        );
+        assert_eq!(pager.pager().pager.total_row_count(), 7);
+    }
 }
@@ -0,0 +1,231 @@
+use cassandra_protocol::error::{Error, Result};
+use cassandra_protocol::types::rows::Row;
+use cassandra_protocol::types::CBytes;
+
+use crate::cluster::Page;
+
+/// Serialized values of partition key columns of a row, in partition key order.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct PartitionKey {
+    values: Vec<CBytes>,
+}
+
+impl PartitionKey {
+    /// Serialized values of partition key columns.
+    #[inline]
+    pub fn values(&self) -> &[CBytes] {
+        &self.values
+    }
+
+    /// Consumes the key, returning serialized values of partition key columns.
+    #[inline]
+    pub fn into_values(self) -> Vec<CBytes> {
+        self.values
+    }
+}
+
+/// Groups consecutive rows of the same partition, e.g. results of `SELECT ... PER PARTITION
+/// LIMIT`, which the server returns partition after partition. Groups are yielded in server order
+/// and are never split - when a page ends, the last group is held back until a row of another
+/// partition or the last page arrives.
+///
+/// Partition key columns need to be selected by the query. They can be given explicitly or looked
+/// up with [`Session::partition_grouper`](crate::cluster::session::Session::partition_grouper).
+///
+/// ```no_run
+/// # use cdrs_tokio::cluster::{DynSessionPager, PartitionGrouper, QueryPager};
+/// # async fn example<'a>(mut pager: QueryPager<'a, &'a str, DynSessionPager<'a>>) -> cdrs_tokio::error::Result<()> {
+/// let mut grouper = PartitionGrouper::new(["sensor_id"]);
+/// loop {
+///     let page = pager.next_page().await?;
+///     let has_more = page.has_more();
+///
+///     for (key, rows) in grouper.push_page(page)? {
+///         // ...
+///     }
+///
+///     if !has_more {
+///         break;
+///     }
+/// }
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone, Debug)]
+pub struct PartitionGrouper {
+    columns: Vec<String>,
+    pending: Option<(PartitionKey, Vec<Row>)>,
+}
+
+impl PartitionGrouper {
+    /// Creates a grouper for given partition key columns.
+    pub fn new<S: Into<String>>(columns: impl IntoIterator<Item = S>) -> Self {
+        PartitionGrouper {
+            columns: columns.into_iter().map(Into::into).collect(),
+            pending: None,
+        }
+    }
+
+    /// Partition key columns used for grouping.
+    #[inline]
+    pub fn columns(&self) -> &[String] {
+        &self.columns
+    }
+
+    /// Adds rows to group, returning groups which are known to be complete. The last group is held
+    /// back, since the following rows might belong to it.
+    pub fn push_rows(
+        &mut self,
+        rows: impl IntoIterator<Item = Row>,
+    ) -> Result<Vec<(PartitionKey, Vec<Row>)>> {
+        let mut groups = vec![];
+
+        for row in rows {
+            let key = self.partition_key(&row)?;
+            match &mut self.pending {
+                Some((pending_key, pending_rows)) if *pending_key == key => pending_rows.push(row),
+                pending => groups.extend(pending.replace((key, vec![row]))),
+            }
+        }
+
+        Ok(groups)
+    }
+
+    /// Adds rows of a page to group, returning groups which are known to be complete. When the page
+    /// is the last one, the last group is returned too.
+    pub fn push_page(&mut self, page: Page) -> Result<Vec<(PartitionKey, Vec<Row>)>> {
+        let has_more = page.has_more();
+        let mut groups = self.push_rows(page.into_rows())?;
+
+        if !has_more {
+            groups.extend(self.finish());
+        }
+
+        Ok(groups)
+    }
+
+    /// Returns the group held back, if any. Should be called after the last rows have been pushed,
+    /// unless they were pushed with [`push_page`](Self::push_page).
+    #[inline]
+    pub fn finish(&mut self) -> Option<(PartitionKey, Vec<Row>)> {
+        self.pending.take()
+    }
+
+    /// Groups all rows of a complete result.
+    pub fn group<S: Into<String>>(
+        columns: impl IntoIterator<Item = S>,
+        rows: impl IntoIterator<Item = Row>,
+    ) -> Result<Vec<(PartitionKey, Vec<Row>)>> {
+        let mut grouper = Self::new(columns);
+        let mut groups = grouper.push_rows(rows)?;
+        groups.extend(grouper.finish());
+        Ok(groups)
+    }
+
+    fn partition_key(&self, row: &Row) -> Result<PartitionKey> {
+        self.columns
+            .iter()
+            .map(|column| {
+                row.raw_value_by_name(column).cloned().ok_or_else(|| {
+                    Error::General(format!(
+                        "Partition key column missing from result: {column}"
+                    ))
+                })
+            })
+            .collect::<Result<Vec<_>>>()
+            .map(|values| PartitionKey { values })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use cassandra_protocol::frame::message_result::{
+        BodyResResultRows, ColSpec, ColType, ColTypeOption, RowsMetadata, RowsMetadataFlags,
+    };
+    use cassandra_protocol::frame::Version;
+    use cassandra_protocol::types::rows::Row;
+    use cassandra_protocol::types::CBytes;
+    use cassandra_protocol::types::IntoRustByName;
+
+    use super::PartitionGrouper;
+
+    fn rows(rows: &[(i32, i32, i32)]) -> Vec<Row> {
+        let col_spec = |name: &str| ColSpec {
+            table_spec: None,
+            name: name.into(),
+            col_type: ColTypeOption {
+                id: ColType::Int,
+                value: None,
+            },
+        };
+
+        Row::from_body(BodyResResultRows {
+            metadata: RowsMetadata {
+                flags: RowsMetadataFlags::empty(),
+                columns_count: 3,
+                paging_state: None,
+                new_metadata_id: None,
+                global_table_spec: None,
+                col_specs: vec![col_spec("id"), col_spec("bucket"), col_spec("value")],
+            },
+            rows_count: rows.len() as i32,
+            rows_content: rows
+                .iter()
+                .map(|(id, bucket, value)| {
+                    [id, bucket, value]
+                        .iter()
+                        .map(|value| CBytes::new(value.to_be_bytes().to_vec()))
+                        .collect()
+                })
+                .collect(),
+            protocol_version: Version::V4,
+        })
+    }
+
+    fn values(groups: Vec<(super::PartitionKey, Vec<Row>)>) -> Vec<Vec<i32>> {
+        groups
+            .into_iter()
+            .map(|(_, rows)| {
+                rows.iter()
+                    .map(|row| row.get_r_by_name("value").unwrap())
+                    .collect()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn should_group_rows_across_pages() {
+        let mut grouper = PartitionGrouper::new(["id", "bucket"]);
+
+        let groups = grouper
+            .push_rows(rows(&[(1, 1, 1), (1, 1, 2), (1, 2, 3), (2, 1, 4)]))
+            .unwrap();
+        assert_eq!(values(groups), vec![vec![1, 2], vec![3]]);
+
+        let groups = grouper.push_rows(rows(&[(2, 1, 5)])).unwrap();
+        assert!(groups.is_empty());
+
+        let groups = grouper.push_rows(rows(&[(2, 1, 6), (3, 1, 7)])).unwrap();
+        assert_eq!(values(groups), vec![vec![4, 5, 6]]);
+
+        let (key, last) = grouper.finish().unwrap();
+        assert_eq!(values(vec![(key.clone(), last)]), vec![vec![7]]);
+        assert_eq!(
+            key.values(),
+            &[
+                CBytes::new(3i32.to_be_bytes().to_vec()),
+                CBytes::new(1i32.to_be_bytes().to_vec())
+            ]
+        );
+        assert!(grouper.finish().is_none());
+    }
+
+    #[test]
+    fn should_group_complete_results() {
+        let groups = PartitionGrouper::group(["id"], rows(&[(1, 1, 1), (1, 2, 2), (2, 1, 3)]));
+        assert_eq!(values(groups.unwrap()), vec![vec![1, 2], vec![3]]);
+
+        assert!(PartitionGrouper::group(["id"], vec![]).unwrap().is_empty());
+        assert!(PartitionGrouper::group(["missing"], rows(&[(1, 1, 1)])).is_err());
+    }
+}
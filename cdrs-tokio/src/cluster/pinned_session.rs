@@ -0,0 +1,415 @@
+use cassandra_protocol::error::{self, Error};
+use cassandra_protocol::frame::message_response::ParsedResponse;
+use cassandra_protocol::frame::Envelope;
+use cassandra_protocol::query::{PreparedQuery, QueryBatch, QueryValues};
+use futures::FutureExt;
+use std::borrow::{Borrow, Cow};
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Instant;
+use tracing::*;
+
+use crate::cluster::send_envelope::CompletionHook;
+use crate::cluster::session::Session;
+use crate::cluster::topology::Node;
+use crate::cluster::{ConnectionManager, StatementRequest, StatementTarget};
+use crate::future::BoxFuture;
+use crate::load_balancing::LoadBalancingStrategy;
+use crate::statement::{StatementParams, StatementParamsBuilder};
+use crate::transport::CdrsTransport;
+
+type UnpinnedListener = Box<dyn Fn(SocketAddr) + Send + Sync>;
+
+// connection selected for a pinned session, which gets abandoned for good once it dies
+pub(crate) struct PinnedConnection<T: CdrsTransport + 'static, CM: ConnectionManager<T> + 'static> {
+    node: Arc<Node<T, CM>>,
+    transport: Arc<T>,
+    unpinned: AtomicBool,
+    unpinned_listener: Option<UnpinnedListener>,
+}
+
+impl<T: CdrsTransport + 'static, CM: ConnectionManager<T> + 'static> PinnedConnection<T, CM> {
+    pub(crate) fn new(node: Arc<Node<T, CM>>, transport: Arc<T>) -> Self {
+        PinnedConnection {
+            node,
+            transport,
+            unpinned: AtomicBool::new(false),
+            unpinned_listener: None,
+        }
+    }
+
+    // sends the envelope over the pinned connection, returning `None` if the connection is dead
+    // and the envelope can be sent using load balancing instead
+    pub(crate) async fn send(
+        &self,
+        envelope: &Envelope,
+        is_idempotent: bool,
+        completion_hook: CompletionHook<'_, T, CM>,
+        written: Option<&AtomicBool>,
+    ) -> Option<error::Result<Envelope>> {
+        if self.unpinned.load(Ordering::Relaxed) {
+            return None;
+        }
+
+        if self.transport.is_broken() {
+            self.unpin();
+            return None;
+        }
+
+        let start = Instant::now();
+        let result = match written {
+            Some(written) => {
+                self.transport
+                    .write_envelope_tracked(envelope, written)
+                    .await
+            }
+            None => self.transport.write_envelope(envelope, false).await,
+        };
+        completion_hook(&self.node, start.elapsed(), &result);
+
+        match result {
+            Err(Error::RequestNotSent(_)) => {
+                self.unpin();
+                None
+            }
+            // the request might have been processed, so only idempotent ones can be sent again
+            Err(error) if self.transport.is_broken() => {
+                self.unpin();
+                if is_idempotent {
+                    None
+                } else {
+                    Some(Err(error))
+                }
+            }
+            result => Some(result),
+        }
+    }
+
+    fn unpin(&self) {
+        if self.unpinned.swap(true, Ordering::Relaxed) {
+            return;
+        }
+
+        let address = self.node.broadcast_rpc_address();
+        warn!(%address, "Pinned connection lost, falling back to load balancing.");
+
+        if let Some(unpinned_listener) = &self.unpinned_listener {
+            unpinned_listener(address);
+        }
+    }
+}
+
+/// Handle to a [`Session`] sending all statements over a single connection, returned by
+/// [`Session::pinned`]. Consecutive statements have the same coordinator, which helps
+/// read-after-write patterns, e.g. in migrations or interactive shells.
+///
+/// Statements sent over the pinned connection are not retried on other nodes and don't use
+/// speculative executions. Once the connection dies, the handle falls back to regular load
+/// balancing for good, which can be observed with
+/// [`with_unpinned_listener`](Self::with_unpinned_listener). Non-idempotent statements in flight
+/// when the connection dies fail, since they might have been applied. The connection stays shared
+/// with other requests of the session, which are not affected by pinning.
+pub struct PinnedSession<
+    'a,
+    T: CdrsTransport + 'static,
+    CM: ConnectionManager<T> + 'static,
+    LB: LoadBalancingStrategy<T, CM> + Send + Sync,
+> {
+    session: &'a Session<T, CM, LB>,
+    connection: PinnedConnection<T, CM>,
+}
+
+impl<
+        'a,
+        T: CdrsTransport + 'static,
+        CM: ConnectionManager<T> + Send + Sync + 'static,
+        LB: LoadBalancingStrategy<T, CM> + Send + Sync + 'static,
+    > PinnedSession<'a, T, CM, LB>
+{
+    #[inline]
+    pub(crate) fn new(
+        session: &'a Session<T, CM, LB>,
+        connection: PinnedConnection<T, CM>,
+    ) -> Self {
+        PinnedSession {
+            session,
+            connection,
+        }
+    }
+
+    /// Sets a listener called with the address of the pinned node when its connection dies and
+    /// the handle falls back to load balancing.
+    #[must_use]
+    pub fn with_unpinned_listener(
+        mut self,
+        unpinned_listener: impl Fn(SocketAddr) + Send + Sync + 'static,
+    ) -> Self {
+        self.connection.unpinned_listener = Some(Box::new(unpinned_listener));
+        self
+    }
+
+    /// Returns the broadcast RPC address of the pinned node.
+    #[inline]
+    pub fn node_address(&self) -> SocketAddr {
+        self.connection.node.broadcast_rpc_address()
+    }
+
+    /// Checks if statements are still sent over the pinned connection.
+    #[inline]
+    pub fn is_pinned(&self) -> bool {
+        !self.connection.unpinned.load(Ordering::Relaxed)
+    }
+
+    /// Returns the underlying session.
+    #[inline]
+    pub fn session(&self) -> &'a Session<T, CM, LB> {
+        self.session
+    }
+
+    /// Executes a query. Statement parameters can be overridden on the returned
+    /// [`StatementRequest`] before awaiting it.
+    #[inline]
+    pub fn query<'b, Q: Into<Cow<'b, str>>>(&'b self, query: Q) -> StatementRequest<'b> {
+        StatementRequest::query(self, query.into())
+    }
+
+    /// Executes a query with bounded values (either with or without names).
+    #[inline]
+    pub async fn query_with_values<'b, Q: Into<Cow<'b, str>>, V: Into<QueryValues>>(
+        &self,
+        query: Q,
+        values: V,
+    ) -> error::Result<Envelope> {
+        self.query_with_params(
+            query,
+            StatementParamsBuilder::new()
+                .with_values(values.into())
+                .build(),
+        )
+        .await
+    }
+
+    /// Executes a query with query parameters.
+    #[inline]
+    pub async fn query_with_params<'b, Q: Into<Cow<'b, str>>>(
+        &self,
+        query: Q,
+        parameters: StatementParams,
+    ) -> error::Result<Envelope> {
+        self.session
+            .query_with_params_pinned(query, parameters, Some(&self.connection))
+            .await
+    }
+
+    /// Executes given prepared query. Statement parameters can be overridden on the returned
+    /// [`StatementRequest`] before awaiting it.
+    #[inline]
+    pub fn exec<'b>(&'b self, prepared: &'b PreparedQuery) -> StatementRequest<'b> {
+        StatementRequest::prepared(self, prepared)
+    }
+
+    /// Executes given prepared query with bounded values (either with or without names).
+    #[inline]
+    pub async fn exec_with_values<V: Into<QueryValues>>(
+        &self,
+        prepared: &PreparedQuery,
+        values: V,
+    ) -> error::Result<Envelope> {
+        self.exec_with_params(
+            prepared,
+            &StatementParamsBuilder::new()
+                .with_values(values.into())
+                .build(),
+        )
+        .await
+    }
+
+    /// Executes given prepared query with query parameters.
+    #[inline]
+    pub async fn exec_with_params(
+        &self,
+        prepared: &PreparedQuery,
+        parameters: &StatementParams,
+    ) -> error::Result<Envelope> {
+        self.session
+            .exec_with_params_pinned(prepared, parameters, Some(&self.connection))
+            .await
+            .map(ParsedResponse::into_envelope)
+    }
+
+    /// Executes batch query.
+    #[inline]
+    pub async fn batch<B: Borrow<QueryBatch>>(&self, batch: B) -> error::Result<Envelope> {
+        self.batch_with_params(batch, &StatementParamsBuilder::new().build())
+            .await
+    }
+
+    /// Executes batch query with parameters.
+    #[inline]
+    pub async fn batch_with_params<B: Borrow<QueryBatch>>(
+        &self,
+        batch: B,
+        parameters: &StatementParams,
+    ) -> error::Result<Envelope> {
+        self.session
+            .send_batch(
+                batch.borrow(),
+                parameters,
+                parameters.keyspace.as_deref(),
+                None,
+                Some(&self.connection),
+            )
+            .await
+    }
+}
+
+impl<
+        T: CdrsTransport + 'static,
+        CM: ConnectionManager<T> + Send + Sync + 'static,
+        LB: LoadBalancingStrategy<T, CM> + Send + Sync + 'static,
+    > StatementTarget for PinnedSession<'_, T, CM, LB>
+{
+    fn send_query<'a>(
+        &'a self,
+        query: Cow<'a, str>,
+        parameters: StatementParams,
+    ) -> BoxFuture<'a, error::Result<Envelope>> {
+        self.query_with_params(query, parameters).boxed()
+    }
+
+    fn send_prepared<'a>(
+        &'a self,
+        prepared: &'a PreparedQuery,
+        parameters: StatementParams,
+    ) -> BoxFuture<'a, error::Result<Envelope>> {
+        async move { self.exec_with_params(prepared, &parameters).await }.boxed()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use cassandra_protocol::error::Error;
+    use cassandra_protocol::frame::{Envelope, Version};
+    use std::io;
+    use std::net::SocketAddr;
+    use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    use crate::cluster::connection_manager::MockConnectionManager;
+    use crate::cluster::pinned_session::PinnedConnection;
+    use crate::cluster::test_nodes::{addr, local_nodes};
+    use crate::transport::MockCdrsTransport;
+
+    type TestConnection =
+        PinnedConnection<MockCdrsTransport, MockConnectionManager<MockCdrsTransport>>;
+
+    // creates a connection failing requests with given error, which breaks while writing them
+    // if `breaks_on_error` is set
+    fn pinned_connection(
+        broken: Arc<AtomicBool>,
+        error: Option<Error>,
+        breaks_on_error: bool,
+    ) -> (TestConnection, Arc<AtomicUsize>) {
+        let node = local_nodes(MockConnectionManager::new(), &[addr(1)]).remove(0);
+
+        let mut transport = MockCdrsTransport::new();
+        transport.expect_is_broken().returning({
+            let broken = broken.clone();
+            move || broken.load(Ordering::Relaxed)
+        });
+        transport.expect_write_envelope().returning(move |_, _| {
+            let result = match &error {
+                Some(error) => {
+                    if breaks_on_error {
+                        broken.store(true, Ordering::Relaxed);
+                    }
+
+                    Err(error.clone())
+                }
+                None => Ok(Envelope::new_req_options(Version::V4)),
+            };
+
+            Box::pin(async move { result })
+        });
+
+        let unpinned = Arc::new(AtomicUsize::new(0));
+        let mut connection = PinnedConnection::new(node, Arc::new(transport));
+        connection.unpinned_listener = Some(Box::new({
+            let unpinned = unpinned.clone();
+            move |address: SocketAddr| {
+                assert_eq!(address.port(), 9042);
+                unpinned.fetch_add(1, Ordering::Relaxed);
+            }
+        }));
+
+        (connection, unpinned)
+    }
+
+    async fn send(
+        connection: &TestConnection,
+        is_idempotent: bool,
+    ) -> Option<Result<Envelope, Error>> {
+        connection
+            .send(
+                &Envelope::new_req_options(Version::V4),
+                is_idempotent,
+                &|_, _, _| {},
+                None,
+            )
+            .await
+    }
+
+    fn io_error() -> Error {
+        Error::Io(io::Error::new(io::ErrorKind::BrokenPipe, "broken pipe"))
+    }
+
+    #[tokio::test]
+    async fn should_fall_back_once_connection_breaks() {
+        let broken = Arc::new(AtomicBool::new(false));
+        let (connection, unpinned) = pinned_connection(broken.clone(), None, false);
+
+        assert!(matches!(send(&connection, false).await, Some(Ok(_))));
+
+        broken.store(true, Ordering::Relaxed);
+        assert!(send(&connection, false).await.is_none());
+
+        // the connection is not used again, even if it reports being healthy
+        broken.store(false, Ordering::Relaxed);
+        assert!(send(&connection, false).await.is_none());
+        assert_eq!(unpinned.load(Ordering::Relaxed), 1);
+    }
+
+    #[tokio::test]
+    async fn should_fall_back_after_sending_only_idempotent_requests() {
+        let broken = Arc::new(AtomicBool::new(false));
+        let (connection, unpinned) = pinned_connection(broken, Some(io_error()), true);
+        assert!(send(&connection, true).await.is_none());
+        assert_eq!(unpinned.load(Ordering::Relaxed), 1);
+
+        let broken = Arc::new(AtomicBool::new(false));
+        let (connection, unpinned) = pinned_connection(broken, Some(io_error()), true);
+        assert!(matches!(
+            send(&connection, false).await,
+            Some(Err(Error::Io(_)))
+        ));
+        assert!(send(&connection, false).await.is_none());
+        assert_eq!(unpinned.load(Ordering::Relaxed), 1);
+
+        let broken = Arc::new(AtomicBool::new(false));
+        let not_sent = Error::RequestNotSent("127.0.0.1:9042".parse().unwrap());
+        let (connection, _) = pinned_connection(broken, Some(not_sent), false);
+        assert!(send(&connection, false).await.is_none());
+    }
+
+    #[tokio::test]
+    async fn should_return_errors_of_healthy_connections() {
+        let broken = Arc::new(AtomicBool::new(false));
+        let (connection, unpinned) = pinned_connection(broken, Some(io_error()), false);
+        assert!(matches!(
+            send(&connection, true).await,
+            Some(Err(Error::Io(_)))
+        ));
+        assert_eq!(unpinned.load(Ordering::Relaxed), 0);
+    }
+}
@@ -0,0 +1,130 @@
+use cassandra_protocol::error::Error;
+use futures::{stream, Future, StreamExt};
+use itertools::Itertools;
+use thiserror::Error as ThisError;
+
+/// Default number of statements prepared at the same time by `prepare_all`.
+pub const DEFAULT_PREPARE_CONCURRENCY: usize = 8;
+
+/// Error preparing a single statement.
+#[derive(Debug, Clone, ThisError)]
+#[error("Could not prepare \"{query}\": {error}")]
+pub struct PrepareError {
+    /// Statement which failed to prepare.
+    pub query: String,
+    /// Underlying error.
+    #[source]
+    pub error: Error,
+}
+
+/// Error returned when any statement could not be prepared.
+#[derive(Debug, Clone, ThisError)]
+#[error("Could not prepare {} statement(s): {}", .failed.len(), .failed.iter().join("; "))]
+pub struct PrepareAllError {
+    /// Errors for every statement which failed to prepare, in input order.
+    pub failed: Vec<PrepareError>,
+}
+
+impl From<PrepareAllError> for Error {
+    #[inline]
+    fn from(error: PrepareAllError) -> Self {
+        Error::General(error.to_string())
+    }
+}
+
+/// Runs given prepare futures, along with their queries, with at most `concurrency` running at the
+/// same time. Results are returned in input order.
+pub(crate) async fn prepare_concurrently<T, Fut>(
+    statements: Vec<(String, Fut)>,
+    concurrency: usize,
+) -> Result<Vec<T>, PrepareAllError>
+where
+    Fut: Future<Output = Result<T, Error>>,
+{
+    let results: Vec<_> =
+        stream::iter(statements.into_iter().map(|(query, prepared)| async move {
+            prepared
+                .await
+                .map_err(|error| PrepareError { query, error })
+        }))
+        .buffered(concurrency.max(1))
+        .collect()
+        .await;
+
+    let (prepared, failed): (Vec<_>, Vec<_>) = results.into_iter().partition_result();
+    if failed.is_empty() {
+        Ok(prepared)
+    } else {
+        Err(PrepareAllError { failed })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use cassandra_protocol::error::Error;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::time::Duration;
+    use tokio::time::sleep;
+
+    use crate::cluster::prepare_all::prepare_concurrently;
+
+    #[tokio::test]
+    async fn should_preserve_order_and_limit_concurrency() {
+        let running = AtomicUsize::new(0);
+        let max_running = AtomicUsize::new(0);
+
+        let queries: Vec<_> = (0..10).map(|index| format!("SELECT {index}")).collect();
+        let statements = queries
+            .iter()
+            .enumerate()
+            .map(|(index, query)| {
+                let running = &running;
+                let max_running = &max_running;
+
+                let prepared = async move {
+                    let now_running = running.fetch_add(1, Ordering::SeqCst) + 1;
+                    max_running.fetch_max(now_running, Ordering::SeqCst);
+
+                    // later statements finish first
+                    sleep(Duration::from_millis(20 - index as u64)).await;
+
+                    running.fetch_sub(1, Ordering::SeqCst);
+                    Ok(query.clone())
+                };
+
+                (query.clone(), prepared)
+            })
+            .collect();
+
+        let prepared = prepare_concurrently(statements, 3).await.unwrap();
+
+        assert_eq!(prepared, queries);
+        assert_eq!(max_running.load(Ordering::SeqCst), 3);
+    }
+
+    #[tokio::test]
+    async fn should_aggregate_failures() {
+        let statements = ["SELECT 1", "SELECT x", "SELECT 3", "SELECT y"]
+            .iter()
+            .map(|query| {
+                let prepared = async move {
+                    if query.ends_with(char::is_numeric) {
+                        Ok(())
+                    } else {
+                        Err(Error::General("Syntax error".into()))
+                    }
+                };
+
+                (query.to_string(), prepared)
+            })
+            .collect();
+
+        let error = prepare_concurrently(statements, 8).await.unwrap_err();
+
+        assert_eq!(
+            error.to_string(),
+            "Could not prepare 2 statement(s): Could not prepare \"SELECT x\": General error: Syntax error; Could not prepare \"SELECT y\": General error: Syntax error"
+        );
+        assert_eq!(error.failed[1].query, "SELECT y");
+    }
+}
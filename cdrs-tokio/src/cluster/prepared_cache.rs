@@ -0,0 +1,390 @@
+use arc_swap::ArcSwapOption;
+use cassandra_protocol::error;
+use cassandra_protocol::frame::message_result::{
+    BodyResResultPrepared, PreparedMetadata, TableSpec,
+};
+use cassandra_protocol::frame::{FromCursor, Serialize, Version};
+use cassandra_protocol::query::PreparedQuery;
+use cassandra_protocol::types::CBytesShort;
+use fxhash::FxHashMap;
+use itertools::Itertools;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize as SerdeSerialize};
+use std::io::Cursor;
+use std::sync::{Arc, Mutex};
+use tracing::*;
+
+// metadata is always encoded with the same version, independently of the session protocol version
+const METADATA_VERSION: Version = Version::V4;
+
+/// Default maximum number of statements kept in the prepared statement cache of a session.
+pub const DEFAULT_PREPARED_CACHE_CAPACITY: usize = 10_000;
+
+/// Statements prepared by a session, which can be exported with
+/// [`Session::prepared_cache_snapshot`](crate::cluster::session::Session::prepared_cache_snapshot)
+/// and imported by another session with
+/// [`SessionBuilder::with_prepared_cache_snapshot`](crate::cluster::session::SessionBuilder::with_prepared_cache_snapshot).
+/// Sessions preparing imported statements use the imported ids without contacting the cluster,
+/// which avoids preparation spikes when many clients start at once. Ids no longer known to the
+/// server get re-prepared on first execution.
+///
+/// Serializable with serde when the `serde` feature is enabled.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(SerdeSerialize, Deserialize))]
+pub struct PreparedCacheSnapshot {
+    pub entries: Vec<PreparedCacheEntry>,
+}
+
+/// Single prepared statement in a [`PreparedCacheSnapshot`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(SerdeSerialize, Deserialize))]
+pub struct PreparedCacheEntry {
+    pub query: String,
+    /// Keyspace the statement was prepared in, if given explicitly.
+    pub keyspace: Option<String>,
+    pub id: Vec<u8>,
+    pub result_metadata_id: Option<Vec<u8>>,
+    /// Metadata of bound variables, encoded as in protocol V4.
+    pub metadata: Vec<u8>,
+}
+
+impl PreparedCacheEntry {
+    fn new(query: String, keyspace: Option<String>, prepared: &BodyResResultPrepared) -> Self {
+        PreparedCacheEntry {
+            query,
+            keyspace,
+            id: prepared.id.clone().into_bytes().unwrap_or_default(),
+            result_metadata_id: prepared
+                .result_metadata_id
+                .clone()
+                .and_then(CBytesShort::into_bytes),
+            metadata: prepared.metadata.serialize_to_vec(METADATA_VERSION),
+        }
+    }
+
+    fn to_prepared_query(&self) -> error::Result<PreparedQuery> {
+        let metadata =
+            PreparedMetadata::from_cursor(&mut Cursor::new(&self.metadata), METADATA_VERSION)?;
+
+        Ok(into_prepared_query(
+            self.query.clone(),
+            CBytesShort::new(self.id.clone()),
+            self.result_metadata_id.clone().map(CBytesShort::new),
+            metadata,
+        ))
+    }
+}
+
+pub(crate) fn into_prepared_query(
+    query: String,
+    id: CBytesShort,
+    result_metadata_id: Option<CBytesShort>,
+    metadata: PreparedMetadata,
+) -> PreparedQuery {
+    PreparedQuery::new(
+        id,
+        query,
+        metadata
+            .global_table_spec
+            .map(|TableSpec { ks_name, .. }| ks_name),
+        metadata.pk_indexes,
+        ArcSwapOption::new(result_metadata_id.map(Arc::new)),
+    )
+    .with_col_specs(metadata.col_specs)
+}
+
+struct CachedStatement {
+    entry: PreparedCacheEntry,
+    // imported statements are used without preparing them, until they are prepared again
+    imported: bool,
+    // value of the use counter when the statement was last used
+    last_used: u64,
+}
+
+#[derive(Default)]
+struct Statements {
+    statements: FxHashMap<(String, Option<String>), CachedStatement>,
+    use_counter: u64,
+}
+
+impl Statements {
+    fn next_use(&mut self) -> u64 {
+        self.use_counter += 1;
+        self.use_counter
+    }
+
+    fn get(&mut self, query: &str, keyspace: Option<&str>) -> Option<&CachedStatement> {
+        let last_used = self.next_use();
+        let statement = self
+            .statements
+            .get_mut(&(query.to_string(), keyspace.map(str::to_string)))?;
+        statement.last_used = last_used;
+
+        Some(statement)
+    }
+
+    fn insert(&mut self, entry: PreparedCacheEntry, imported: bool) -> Option<CachedStatement> {
+        let last_used = self.next_use();
+        self.statements.insert(
+            (entry.query.clone(), entry.keyspace.clone()),
+            CachedStatement {
+                entry,
+                imported,
+                last_used,
+            },
+        )
+    }
+
+    // statements are only inserted after a round trip to the cluster, so a linear scan is cheap
+    // in comparison
+    fn evict_least_recently_used(&mut self, capacity: usize) {
+        while self.statements.len() > capacity {
+            let key = self
+                .statements
+                .iter()
+                .min_by_key(|(_, statement)| statement.last_used)
+                .map(|(key, _)| key.clone());
+
+            match key {
+                Some(key) => {
+                    debug!(
+                        query = key.0,
+                        "Evicting least recently used prepared statement."
+                    );
+                    self.statements.remove(&key);
+                }
+                None => break,
+            }
+        }
+    }
+}
+
+/// Statements prepared by a session, keyed by query and keyspace. Holds at most given number of
+/// statements, evicting the least recently used ones.
+pub(crate) struct PreparedCache {
+    statements: Mutex<Statements>,
+    capacity: usize,
+}
+
+impl Default for PreparedCache {
+    fn default() -> Self {
+        PreparedCache::new(None, DEFAULT_PREPARED_CACHE_CAPACITY)
+    }
+}
+
+impl PreparedCache {
+    pub(crate) fn new(snapshot: Option<PreparedCacheSnapshot>, capacity: usize) -> Self {
+        let capacity = capacity.max(1);
+
+        let mut statements = Statements::default();
+        for entry in snapshot
+            .map(|snapshot| snapshot.entries)
+            .unwrap_or_default()
+        {
+            statements.insert(entry, true);
+        }
+
+        // later entries of the snapshot are kept
+        statements.evict_least_recently_used(capacity);
+
+        PreparedCache {
+            statements: Mutex::new(statements),
+            capacity,
+        }
+    }
+
+    /// Inserts a prepared statement, returning the one previously cached for the same query and
+    /// keyspace, if present and valid.
+    pub(crate) fn insert(
+        &self,
+        query: String,
+        keyspace: Option<String>,
+        prepared: &BodyResResultPrepared,
+    ) -> Option<PreparedQuery> {
+        let entry = PreparedCacheEntry::new(query, keyspace, prepared);
+
+        let mut statements = self.statements.lock().unwrap();
+        let previous = statements.insert(entry, false);
+        statements.evict_least_recently_used(self.capacity);
+
+        previous.and_then(|previous| previous.entry.to_prepared_query().ok())
+    }
+
+    /// Returns a prepared or imported statement, if present and valid.
+    pub(crate) fn get(&self, query: &str, keyspace: Option<&str>) -> Option<PreparedQuery> {
+        let mut statements = self.statements.lock().unwrap();
+        let statement = statements.get(query, keyspace)?;
+
+        statement
+            .entry
+            .to_prepared_query()
+            .map_err(|error| warn!(%error, query, "Ignoring invalid cached prepared statement."))
+            .ok()
+    }
+
+    /// Returns an imported statement, if present and valid.
+    pub(crate) fn imported(&self, query: &str, keyspace: Option<&str>) -> Option<PreparedQuery> {
+        let mut statements = self.statements.lock().unwrap();
+        let statement = statements.get(query, keyspace)?;
+        if !statement.imported {
+            return None;
+        }
+
+        statement
+            .entry
+            .to_prepared_query()
+            .map_err(|error| warn!(%error, query, "Ignoring invalid imported prepared statement."))
+            .ok()
+    }
+
+    pub(crate) fn snapshot(&self) -> PreparedCacheSnapshot {
+        let statements = self.statements.lock().unwrap();
+
+        // least recently used first, so importing sessions with smaller caches keep the most
+        // recently used statements
+        let entries = statements
+            .statements
+            .values()
+            .sorted_by_key(|statement| statement.last_used)
+            .map(|statement| statement.entry.clone())
+            .collect();
+
+        PreparedCacheSnapshot { entries }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use cassandra_protocol::frame::message_result::{
+        BodyResResultPrepared, ColSpec, ColType, ColTypeOption, PreparedMetadata, RowsMetadata,
+        RowsMetadataFlags, TableSpec,
+    };
+    use cassandra_protocol::types::CBytesShort;
+
+    use super::{PreparedCache, PreparedCacheSnapshot, DEFAULT_PREPARED_CACHE_CAPACITY};
+
+    fn prepared() -> BodyResResultPrepared {
+        BodyResResultPrepared {
+            id: CBytesShort::new(vec![1, 2, 3]),
+            result_metadata_id: Some(CBytesShort::new(vec![4])),
+            metadata: PreparedMetadata {
+                pk_indexes: vec![0],
+                global_table_spec: Some(TableSpec {
+                    ks_name: "ks".into(),
+                    table_name: "t".into(),
+                }),
+                col_specs: vec![ColSpec {
+                    table_spec: None,
+                    name: "id".into(),
+                    col_type: ColTypeOption {
+                        id: ColType::Int,
+                        value: None,
+                    },
+                }],
+            },
+            result_metadata: RowsMetadata {
+                flags: RowsMetadataFlags::NO_METADATA,
+                columns_count: 0,
+                paging_state: None,
+                new_metadata_id: None,
+                global_table_spec: None,
+                col_specs: vec![],
+            },
+        }
+    }
+
+    const QUERY: &str = "SELECT * FROM ks.t WHERE id = ?";
+
+    #[test]
+    fn should_only_use_imported_statements() {
+        let cache = PreparedCache::default();
+        cache.insert(QUERY.into(), None, &prepared());
+        assert!(cache.imported(QUERY, None).is_none());
+
+        let cache = PreparedCache::new(Some(cache.snapshot()), DEFAULT_PREPARED_CACHE_CAPACITY);
+        let imported = cache.imported(QUERY, None).unwrap();
+        assert_eq!(imported.id, prepared().id);
+        assert_eq!(imported.query, QUERY);
+        assert_eq!(imported.keyspace.as_deref(), Some("ks"));
+        assert_eq!(imported.pk_indexes, vec![0]);
+        assert_eq!(imported.col_specs(), prepared().metadata.col_specs);
+        assert_eq!(
+            imported.result_metadata_id.load().as_deref(),
+            prepared().result_metadata_id.as_ref()
+        );
+
+        assert!(cache.imported(QUERY, Some("other")).is_none());
+
+        // preparing again replaces the imported statement
+        cache.insert(QUERY.into(), None, &prepared());
+        assert!(cache.imported(QUERY, None).is_none());
+    }
+
+    #[test]
+    fn should_get_prepared_statements_by_keyspace() {
+        let cache = PreparedCache::default();
+        cache.insert(QUERY.into(), Some("ks".into()), &prepared());
+
+        assert_eq!(cache.get(QUERY, Some("ks")).unwrap().id, prepared().id);
+        assert!(cache.get(QUERY, Some("other")).is_none());
+        assert!(cache.get(QUERY, None).is_none());
+    }
+
+    #[test]
+    fn should_return_previously_cached_statements() {
+        let cache = PreparedCache::default();
+        assert!(cache.insert(QUERY.into(), None, &prepared()).is_none());
+
+        let mut altered = prepared();
+        altered.metadata.col_specs[0].col_type.id = ColType::Bigint;
+
+        let previous = cache.insert(QUERY.into(), None, &altered).unwrap();
+        assert_eq!(previous.col_specs(), prepared().metadata.col_specs);
+        assert_ne!(previous.col_specs(), altered.metadata.col_specs);
+    }
+
+    #[test]
+    fn should_evict_least_recently_used_statements() {
+        let cache = PreparedCache::new(None, 2);
+        cache.insert("SELECT 1".into(), None, &prepared());
+        cache.insert("SELECT 2".into(), None, &prepared());
+        assert!(cache.get("SELECT 1", None).is_some());
+
+        cache.insert("SELECT 3".into(), None, &prepared());
+        assert!(cache.get("SELECT 1", None).is_some());
+        assert!(cache.get("SELECT 2", None).is_none());
+        assert!(cache.get("SELECT 3", None).is_some());
+
+        // the most recently used statements are kept when importing into a smaller cache
+        let snapshot = cache.snapshot();
+        assert_eq!(
+            snapshot
+                .entries
+                .iter()
+                .map(|entry| entry.query.as_str())
+                .collect::<Vec<_>>(),
+            vec!["SELECT 1", "SELECT 3"]
+        );
+
+        let cache = PreparedCache::new(Some(snapshot), 1);
+        assert!(cache.imported("SELECT 1", None).is_none());
+        assert!(cache.imported("SELECT 3", None).is_some());
+    }
+
+    #[test]
+    fn should_ignore_invalid_imported_statements() {
+        let mut snapshot = PreparedCache::default().snapshot();
+        assert_eq!(snapshot, PreparedCacheSnapshot::default());
+
+        let cache = PreparedCache::default();
+        cache.insert(QUERY.into(), None, &prepared());
+        snapshot.entries = cache.snapshot().entries;
+        snapshot.entries[0].metadata.truncate(3);
+
+        assert!(
+            PreparedCache::new(Some(snapshot), DEFAULT_PREPARED_CACHE_CAPACITY)
+                .imported(QUERY, None)
+                .is_none()
+        );
+    }
+}
@@ -0,0 +1,328 @@
+use cassandra_protocol::error::Result;
+use cassandra_protocol::frame::message_response::ResponseBody;
+use cassandra_protocol::frame::message_result::ColSpec;
+use cassandra_protocol::query::PreparedQuery;
+use cassandra_protocol::types::CBytesShort;
+use fxhash::FxHashSet;
+use std::sync::{Arc, Mutex};
+use tracing::*;
+
+use crate::cluster::{WarningAction, WarningClass, WarningPolicy};
+
+/// Listener notified when the server reports changed result metadata of a prepared statement,
+/// e.g. after an `ALTER TABLE` adding a column selected with `SELECT *`. Only protocol V5 and later
+/// send result metadata ids.
+pub trait PreparedMetadataListener {
+    /// Called after the result metadata id of given statement has been updated. Subsequent
+    /// executions send the new id, which can be read from
+    /// [`PreparedQuery::result_metadata_id`].
+    fn on_metadata_changed(&self, prepared: &PreparedQuery, previous_id: Option<&CBytesShort>);
+
+    /// Called when re-preparing given statement returned different bound variables, e.g. after an
+    /// `ALTER TABLE` changing column types. Positional values bound according to
+    /// [`PreparedQuery::col_specs`] might no longer match the statement, which should be prepared
+    /// again.
+    fn on_variables_changed(&self, _prepared: &PreparedQuery, _col_specs: &[ColSpec]) {}
+}
+
+// enough for any reasonable number of statements, while stale ones can't grow it without bound
+const MAX_REPORTED_VARIABLES: usize = 1024;
+
+/// Bound variable changes already reported, by statement id. Statements prepared before a change
+/// keep their original variables, so every execution re-preparing them would report it again.
+/// Holds at most `MAX_REPORTED_VARIABLES` changes - when full, it's cleared, so changes might be
+/// reported again.
+#[derive(Default)]
+pub(crate) struct ReportedVariables {
+    changes: Mutex<FxHashSet<(CBytesShort, Vec<ColSpec>)>>,
+}
+
+impl ReportedVariables {
+    fn insert(&self, id: &CBytesShort, col_specs: &[ColSpec]) -> bool {
+        let mut changes = self.changes.lock().unwrap();
+        let change = (id.clone(), col_specs.to_vec());
+        if changes.contains(&change) {
+            return false;
+        }
+
+        if changes.len() >= MAX_REPORTED_VARIABLES {
+            changes.clear();
+        }
+
+        changes.insert(change)
+    }
+}
+
+/// Checks if bound variables returned by re-preparing a statement differ from the ones it has been
+/// prepared with. The first time a change is seen, the listener is notified and the warning policy
+/// applied as [`WarningClass::PreparedMetadata`]. Values bound by name still match the statement,
+/// so the policy can only fail executions with `positional` values, which it does every time, not
+/// only for the first one. Returns `true` if the variables have changed.
+pub(crate) fn check_variables_metadata(
+    prepared: &PreparedQuery,
+    col_specs: &[ColSpec],
+    reported: &ReportedVariables,
+    warning_policy: &WarningPolicy,
+    listener: Option<&(dyn PreparedMetadataListener + Send + Sync)>,
+    positional: bool,
+) -> Result<bool> {
+    if prepared.col_specs() == col_specs {
+        return Ok(false);
+    }
+
+    let first_report = reported.insert(&prepared.id, col_specs);
+    if first_report {
+        if let Some(listener) = listener {
+            listener.on_variables_changed(prepared, col_specs);
+        }
+    }
+
+    let fails =
+        positional && warning_policy.action(WarningClass::PreparedMetadata) == WarningAction::Error;
+    if first_report || fails {
+        let result = warning_policy.check_detected(
+            WarningClass::PreparedMetadata,
+            "Bound variables of prepared statement changed.",
+            &prepared.query,
+        );
+
+        if positional {
+            result?;
+        }
+    }
+
+    Ok(true)
+}
+
+/// Stores a new result metadata id returned by an execution, if present and different from the
+/// current one. Returns `true` if the id has changed.
+pub(crate) fn update_result_metadata_id(
+    prepared: &PreparedQuery,
+    response: &ResponseBody,
+    listener: Option<&(dyn PreparedMetadataListener + Send + Sync)>,
+) -> bool {
+    let new_metadata_id = match response
+        .as_rows_metadata()
+        .and_then(|metadata| metadata.new_metadata_id.as_ref())
+    {
+        Some(new_metadata_id) => new_metadata_id,
+        None => return false,
+    };
+
+    let previous_id = prepared
+        .result_metadata_id
+        .swap(Some(Arc::new(new_metadata_id.clone())));
+    if previous_id.as_deref() == Some(new_metadata_id) {
+        return false;
+    }
+
+    debug!(query = %prepared.query, "Result metadata of prepared statement changed.");
+
+    if let Some(listener) = listener {
+        listener.on_metadata_changed(prepared, previous_id.as_deref());
+    }
+
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use arc_swap::ArcSwapOption;
+    use cassandra_protocol::compression::Compression;
+    use cassandra_protocol::frame::message_result::{ColSpec, ColType, ColTypeOption};
+    use cassandra_protocol::frame::{Envelope, Version};
+    use cassandra_protocol::query::PreparedQuery;
+    use cassandra_protocol::types::CBytesShort;
+    use std::sync::{Arc, Mutex};
+
+    use super::{
+        check_variables_metadata, update_result_metadata_id, PreparedMetadataListener,
+        ReportedVariables, MAX_REPORTED_VARIABLES,
+    };
+    use crate::cluster::{WarningAction, WarningClass, WarningPolicy};
+    use cassandra_protocol::error::Error;
+
+    #[derive(Default)]
+    struct RecordingListener {
+        changes: Mutex<Vec<(Option<CBytesShort>, Option<CBytesShort>)>>,
+        variables: Mutex<Vec<Vec<ColSpec>>>,
+    }
+
+    impl PreparedMetadataListener for RecordingListener {
+        fn on_metadata_changed(&self, prepared: &PreparedQuery, previous_id: Option<&CBytesShort>) {
+            self.changes.lock().unwrap().push((
+                previous_id.cloned(),
+                prepared.result_metadata_id.load().as_deref().cloned(),
+            ));
+        }
+
+        fn on_variables_changed(&self, _prepared: &PreparedQuery, col_specs: &[ColSpec]) {
+            self.variables.lock().unwrap().push(col_specs.to_vec());
+        }
+    }
+
+    fn prepared(result_metadata_id: &[u8]) -> PreparedQuery {
+        PreparedQuery::new(
+            CBytesShort::new(vec![1]),
+            "SELECT * FROM ks.t".into(),
+            None,
+            vec![],
+            ArcSwapOption::new(Some(Arc::new(CBytesShort::new(
+                result_metadata_id.to_vec(),
+            )))),
+        )
+    }
+
+    // V5 rows result for a single int column "a" in ks.t, optionally with a new metadata id
+    fn rows_envelope(new_metadata_id: Option<&[u8]>) -> Envelope {
+        let mut body = vec![0, 0, 0, 2];
+        match new_metadata_id {
+            Some(id) => {
+                body.extend_from_slice(&[0, 0, 0, 9, 0, 0, 0, 1]);
+                body.extend_from_slice(&(id.len() as i16).to_be_bytes());
+                body.extend_from_slice(id);
+            }
+            None => body.extend_from_slice(&[0, 0, 0, 1, 0, 0, 0, 1]),
+        }
+        body.extend_from_slice(&[0, 2, b'k', b's', 0, 1, b't', 0, 1, b'a', 0, 9]);
+        body.extend_from_slice(&[0, 0, 0, 1, 0, 0, 0, 4, 0, 0, 0, 7]);
+
+        let mut buffer = vec![0x85, 0, 0, 1, 8];
+        buffer.extend_from_slice(&(body.len() as i32).to_be_bytes());
+        buffer.extend_from_slice(&body);
+
+        Envelope::from_buffer(&buffer, Compression::None)
+            .unwrap()
+            .envelope
+    }
+
+    #[test]
+    fn should_update_metadata_id_after_alter() {
+        let listener = RecordingListener::default();
+        let prepared = prepared(&[1]);
+
+        let before_alter = rows_envelope(None);
+        assert_eq!(before_alter.version, Version::V5);
+        assert!(!update_result_metadata_id(
+            &prepared,
+            &before_alter.response_body().unwrap(),
+            Some(&listener)
+        ));
+
+        let after_alter = rows_envelope(Some(&[2, 2]));
+        assert!(update_result_metadata_id(
+            &prepared,
+            &after_alter.response_body().unwrap(),
+            Some(&listener)
+        ));
+        assert_eq!(
+            prepared.result_metadata_id.load().as_deref(),
+            Some(&CBytesShort::new(vec![2, 2]))
+        );
+
+        // the same id reported again is not a change
+        assert!(!update_result_metadata_id(
+            &prepared,
+            &after_alter.response_body().unwrap(),
+            Some(&listener)
+        ));
+
+        assert_eq!(
+            *listener.changes.lock().unwrap(),
+            vec![(
+                Some(CBytesShort::new(vec![1])),
+                Some(CBytesShort::new(vec![2, 2]))
+            )]
+        );
+    }
+
+    fn int_col_spec() -> Vec<ColSpec> {
+        vec![ColSpec {
+            table_spec: None,
+            name: "a".into(),
+            col_type: ColTypeOption {
+                id: ColType::Int,
+                value: None,
+            },
+        }]
+    }
+
+    #[test]
+    fn should_detect_changed_variables() {
+        let listener = RecordingListener::default();
+        let reported = ReportedVariables::default();
+        let policy = WarningPolicy::default();
+        let prepared = prepared(&[1]);
+
+        assert!(!check_variables_metadata(
+            &prepared,
+            &[],
+            &reported,
+            &policy,
+            Some(&listener),
+            true
+        )
+        .unwrap());
+
+        let col_specs = int_col_spec();
+        assert!(check_variables_metadata(
+            &prepared,
+            &col_specs,
+            &reported,
+            &policy,
+            Some(&listener),
+            true
+        )
+        .unwrap());
+
+        // executing the stale statement again reports the change only once
+        assert!(check_variables_metadata(
+            &prepared,
+            &col_specs,
+            &reported,
+            &policy,
+            Some(&listener),
+            true
+        )
+        .unwrap());
+
+        assert_eq!(*listener.variables.lock().unwrap(), vec![col_specs]);
+    }
+
+    #[test]
+    fn should_apply_warning_policy_to_changed_variables() {
+        let reported = ReportedVariables::default();
+        let policy =
+            WarningPolicy::new().with_action(WarningClass::PreparedMetadata, WarningAction::Error);
+        let prepared = prepared(&[1]);
+        let col_specs = int_col_spec();
+
+        // named values still match the statement
+        assert!(
+            check_variables_metadata(&prepared, &col_specs, &reported, &policy, None, false)
+                .unwrap()
+        );
+
+        // positional values fail every time, even though the change has already been reported
+        for _ in 0..2 {
+            match check_variables_metadata(&prepared, &col_specs, &reported, &policy, None, true) {
+                Err(Error::PolicyViolation { query, .. }) => assert_eq!(query, prepared.query),
+                result => panic!("Unexpected result: {:?}", result),
+            }
+        }
+    }
+
+    #[test]
+    fn should_bound_reported_variables() {
+        let reported = ReportedVariables::default();
+        let col_specs = int_col_spec();
+
+        for id in 0..=MAX_REPORTED_VARIABLES {
+            let id = CBytesShort::new((id as u32).to_be_bytes().to_vec());
+            assert!(reported.insert(&id, &col_specs));
+            assert!(!reported.insert(&id, &col_specs));
+        }
+
+        assert_eq!(reported.changes.lock().unwrap().len(), 1);
+    }
+}
@@ -0,0 +1,113 @@
+use cassandra_protocol::error::{Error, Result};
+use std::future::Future;
+use std::sync::atomic::{AtomicU8, Ordering};
+use std::sync::Arc;
+use tokio::select;
+use tokio::sync::Notify;
+
+const PENDING: u8 = 0;
+const COMPLETED: u8 = 1;
+const CANCELLED: u8 = 2;
+
+#[derive(Default, Debug)]
+struct QueryState {
+    status: AtomicU8,
+    cancelled: Notify,
+}
+
+impl QueryState {
+    #[inline]
+    fn transition(&self, status: u8) -> bool {
+        self.status
+            .compare_exchange(PENDING, status, Ordering::AcqRel, Ordering::Acquire)
+            .is_ok()
+    }
+}
+
+/// Handle to a query started with
+/// [`Session::query_cancellable`](crate::cluster::session::Session::query_cancellable), which
+/// can be used to stop waiting for its result.
+///
+/// Cancelling resolves the query future with [`Error::Cancelled`]. A request which has already
+/// been sent can't be recalled, so the server might still process it - its stream id is released
+/// once the late response arrives, and the response is discarded. Requests sent to nodes are
+/// reported as cancelled to the load balancing strategy.
+#[derive(Clone, Debug)]
+pub struct QueryHandle {
+    state: Arc<QueryState>,
+}
+
+impl QueryHandle {
+    /// Cancels the query. Returns `true` if the query got cancelled, or `false` if it has
+    /// already completed or been cancelled before, in which case this is a no-op.
+    pub fn cancel(&self) -> bool {
+        let cancelled = self.state.transition(CANCELLED);
+        if cancelled {
+            self.state.cancelled.notify_one();
+        }
+
+        cancelled
+    }
+
+    /// Checks if the query has been cancelled.
+    #[inline]
+    pub fn is_cancelled(&self) -> bool {
+        self.state.status.load(Ordering::Acquire) == CANCELLED
+    }
+}
+
+/// Wraps given query future, so it can be cancelled with the returned handle.
+pub(crate) fn cancellable<F: Future<Output = Result<T>>, T>(
+    future: F,
+) -> (QueryHandle, impl Future<Output = Result<T>>) {
+    let state = Arc::new(QueryState::default());
+    let handle = QueryHandle {
+        state: state.clone(),
+    };
+
+    let future = async move {
+        select! {
+            biased;
+            _ = state.cancelled.notified() => Err(Error::Cancelled),
+            result = future => {
+                // the query might have been cancelled after completing, but before being polled
+                if state.transition(COMPLETED) {
+                    result
+                } else {
+                    Err(Error::Cancelled)
+                }
+            }
+        }
+    };
+
+    (handle, future)
+}
+
+#[cfg(test)]
+mod tests {
+    use cassandra_protocol::error::Error;
+    use futures::future::pending;
+
+    use super::cancellable;
+
+    #[tokio::test]
+    async fn should_cancel_pending_query() {
+        let (handle, future) = cancellable(pending::<cassandra_protocol::error::Result<()>>());
+
+        assert!(!handle.is_cancelled());
+        assert!(handle.cancel());
+        assert!(handle.is_cancelled());
+        assert!(!handle.cancel());
+
+        assert!(matches!(future.await, Err(Error::Cancelled)));
+    }
+
+    #[tokio::test]
+    async fn should_ignore_cancellation_after_completion() {
+        let (handle, future) = cancellable(async { Ok(1) });
+
+        assert_eq!(future.await.unwrap(), 1);
+        assert!(!handle.cancel());
+        assert!(!handle.is_cancelled());
+    }
+}
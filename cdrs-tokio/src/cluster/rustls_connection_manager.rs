@@ -1,15 +1,22 @@
-use crate::cluster::connection_manager::{startup, ConnectionManager};
+use crate::cluster::connection_manager::{
+    startup_with_phase, verify_compression, ConnectionManager, ConnectionManagerConfig,
+    HandshakeLimits,
+};
+use crate::cluster::happy_eyeballs::connect_staggered;
 #[cfg(feature = "http-proxy")]
 use crate::cluster::HttpProxyConfig;
-use crate::cluster::KeyspaceHolder;
+use crate::cluster::{ConnectionError, ConnectionPhase, InitStatement, KeyspaceHolder};
 use crate::frame_encoding::FrameEncodingFactory;
+use crate::frame_recording::FrameRecorder;
 use crate::future::BoxFuture;
+use crate::runtime;
 use crate::transport::TransportRustls;
+use arc_swap::ArcSwap;
 #[cfg(feature = "http-proxy")]
 use async_http_proxy::{http_connect_tokio, http_connect_tokio_with_basic_auth};
 use cassandra_protocol::authenticators::SaslAuthenticatorProvider;
 use cassandra_protocol::compression::Compression;
-use cassandra_protocol::error::{Error, Result};
+use cassandra_protocol::error::{Error, Result, TlsErrorKind};
 use cassandra_protocol::frame::{Envelope, Version};
 use futures::FutureExt;
 use std::io;
@@ -18,10 +25,9 @@ use std::io::ErrorKind;
 use std::net::SocketAddr;
 use std::ops::Deref;
 use std::sync::Arc;
-#[cfg(feature = "http-proxy")]
-use tokio::net::TcpStream;
+use std::time::{Duration, Instant};
 use tokio::sync::mpsc::Sender;
-use tokio_rustls::rustls::{pki_types::ServerName, ClientConfig};
+use tokio_rustls::rustls::{self, pki_types::ServerName, CertificateError, ClientConfig};
 
 pub struct RustlsConnectionManager {
     dns_name: ServerName<'static>,
@@ -29,10 +35,18 @@ pub struct RustlsConnectionManager {
     config: Arc<ClientConfig>,
     keyspace_holder: Arc<KeyspaceHolder>,
     frame_encoder_factory: Box<dyn FrameEncodingFactory + Send + Sync>,
-    compression: Compression,
+    // changing compression affects only new connections
+    compression: ArcSwap<Compression>,
+    compression_threshold: usize,
     buffer_size: usize,
+    read_buffer_size: Option<usize>,
     tcp_nodelay: bool,
+    strict_socket_options: bool,
     version: Version,
+    handshake_limits: HandshakeLimits,
+    connection_stagger_delay: Duration,
+    frame_recorder: Option<Arc<FrameRecorder>>,
+    init_statements: Vec<InitStatement>,
     #[cfg(feature = "http-proxy")]
     http_proxy: Option<HttpProxyConfig>,
 }
@@ -45,50 +59,86 @@ impl ConnectionManager<TransportRustls> for RustlsConnectionManager {
         error_handler: Option<Sender<Error>>,
         addr: SocketAddr,
     ) -> BoxFuture<Result<TransportRustls>> {
-        self.establish_connection(event_handler, error_handler, addr)
+        self.establish_connection(event_handler, error_handler, addr, &[])
+            .map(|result| result.map_err(Error::from))
             .boxed()
     }
+
+    fn connection_with_diagnostics<'a>(
+        &'a self,
+        event_handler: Option<Sender<Envelope>>,
+        error_handler: Option<Sender<Error>>,
+        addr: SocketAddr,
+    ) -> BoxFuture<'a, std::result::Result<TransportRustls, ConnectionError>>
+    where
+        TransportRustls: 'a,
+    {
+        self.establish_connection(event_handler, error_handler, addr, &[])
+            .boxed()
+    }
+
+    fn connection_with_alternatives<'a>(
+        &'a self,
+        event_handler: Option<Sender<Envelope>>,
+        error_handler: Option<Sender<Error>>,
+        addr: SocketAddr,
+        alternative_addrs: &'a [SocketAddr],
+    ) -> BoxFuture<'a, std::result::Result<TransportRustls, ConnectionError>>
+    where
+        TransportRustls: 'a,
+    {
+        self.establish_connection(event_handler, error_handler, addr, alternative_addrs)
+            .boxed()
+    }
+
+    #[inline]
+    fn compression(&self) -> Option<Compression> {
+        Some(**self.compression.load())
+    }
+
+    fn set_compression(&self, compression: Compression) -> Result<()> {
+        verify_compression(compression, self.version)?;
+        self.compression.store(Arc::new(compression));
+        Ok(())
+    }
 }
 
 impl RustlsConnectionManager {
-    #[allow(clippy::too_many_arguments)]
     pub fn new(
         dns_name: ServerName<'static>,
         authenticator_provider: Arc<dyn SaslAuthenticatorProvider + Send + Sync>,
-        config: Arc<ClientConfig>,
+        tls_config: Arc<ClientConfig>,
         keyspace_holder: Arc<KeyspaceHolder>,
         frame_encoder_factory: Box<dyn FrameEncodingFactory + Send + Sync>,
-        compression: Compression,
-        buffer_size: usize,
-        tcp_nodelay: bool,
-        version: Version,
-        #[cfg(feature = "http-proxy")] http_proxy: Option<HttpProxyConfig>,
+        config: ConnectionManagerConfig,
     ) -> Self {
         RustlsConnectionManager {
             dns_name,
             authenticator_provider,
-            config,
+            config: tls_config,
             keyspace_holder,
             frame_encoder_factory,
-            compression,
-            buffer_size,
-            tcp_nodelay,
-            version,
+            compression: ArcSwap::from_pointee(config.compression),
+            compression_threshold: config.compression_threshold,
+            buffer_size: config.buffer_size,
+            read_buffer_size: config.read_buffer_size,
+            tcp_nodelay: config.tcp_nodelay,
+            strict_socket_options: config.strict_socket_options,
+            version: config.version,
+            handshake_limits: config.handshake_limits(),
+            connection_stagger_delay: config.connection_stagger_delay,
+            frame_recorder: config.frame_recorder,
+            init_statements: config.init_statements,
             #[cfg(feature = "http-proxy")]
-            http_proxy,
+            http_proxy: config.http_proxy,
         }
     }
 
     //noinspection DuplicatedCode
     #[cfg(feature = "http-proxy")]
-    async fn create_transport(
-        &self,
-        event_handler: Option<Sender<Envelope>>,
-        error_handler: Option<Sender<Error>>,
-        addr: SocketAddr,
-    ) -> io::Result<TransportRustls> {
-        if let Some(http_proxy) = &self.http_proxy {
-            let mut stream = TcpStream::connect(&http_proxy.address).await?;
+    async fn connect_stream(&self, addr: SocketAddr) -> io::Result<runtime::TcpStream> {
+        let stream = if let Some(http_proxy) = &self.http_proxy {
+            let mut stream = runtime::connect_host(&http_proxy.address).await?;
 
             if let Some(auth) = &http_proxy.basic_auth {
                 http_connect_tokio_with_basic_auth(
@@ -106,88 +156,175 @@ impl RustlsConnectionManager {
                     .map_err(|error| io::Error::new(ErrorKind::Other, error.to_string()))?;
             }
 
-            stream.set_nodelay(self.tcp_nodelay)?;
-            TransportRustls::with_stream(
-                stream,
-                addr,
-                self.dns_name.clone(),
-                self.config.clone(),
-                self.keyspace_holder.clone(),
-                event_handler,
-                error_handler,
-                self.compression,
-                self.frame_encoder_factory
-                    .create_encoder(self.version, self.compression),
-                self.frame_encoder_factory
-                    .create_decoder(self.version, self.compression),
-                self.buffer_size,
-            )
-            .await
+            runtime::apply_nodelay(&stream, addr, self.tcp_nodelay, self.strict_socket_options)?;
+            stream
         } else {
-            TransportRustls::new(
-                addr,
-                self.dns_name.clone(),
-                self.config.clone(),
-                self.keyspace_holder.clone(),
-                event_handler,
-                error_handler,
-                self.compression,
-                self.frame_encoder_factory
-                    .create_encoder(self.version, self.compression),
-                self.frame_encoder_factory
-                    .create_decoder(self.version, self.compression),
-                self.buffer_size,
-                self.tcp_nodelay,
-            )
-            .await
-        }
+            runtime::connect(addr, self.tcp_nodelay, self.strict_socket_options).await?
+        };
+
+        Ok(stream)
     }
 
-    //noinspection DuplicatedCode
     #[cfg(not(feature = "http-proxy"))]
-    async fn create_transport(
+    async fn connect_stream(&self, addr: SocketAddr) -> io::Result<runtime::TcpStream> {
+        runtime::connect(addr, self.tcp_nodelay, self.strict_socket_options).await
+    }
+
+    async fn establish_connection(
         &self,
         event_handler: Option<Sender<Envelope>>,
         error_handler: Option<Sender<Error>>,
         addr: SocketAddr,
-    ) -> io::Result<TransportRustls> {
-        TransportRustls::new(
+        alternative_addrs: &[SocketAddr],
+    ) -> std::result::Result<TransportRustls, ConnectionError> {
+        let start = Instant::now();
+        let (stream, addr) = connect_staggered(
+            addr,
+            alternative_addrs,
+            self.connection_stagger_delay,
+            |addr| self.connect_stream(addr),
+        )
+        .await
+        .map_err(|(error, addr)| {
+            ConnectionError::new(
+                addr,
+                ConnectionPhase::TcpConnect,
+                error.into(),
+                start.elapsed(),
+            )
+        })?;
+
+        // read once, so the whole connection uses the same compression even if it changes
+        let compression = **self.compression.load();
+        let transport = TransportRustls::with_stream(
+            stream,
             addr,
             self.dns_name.clone(),
             self.config.clone(),
             self.keyspace_holder.clone(),
             event_handler,
             error_handler,
-            self.compression,
+            compression,
+            self.compression_threshold,
             self.frame_encoder_factory
-                .create_encoder(self.version, self.compression),
+                .create_encoder(self.version, compression),
             self.frame_encoder_factory
-                .create_decoder(self.version, self.compression),
+                .create_decoder(self.version, compression),
             self.buffer_size,
-            self.tcp_nodelay,
+            self.read_buffer_size,
+            self.frame_recorder.clone(),
         )
         .await
-    }
-
-    async fn establish_connection(
-        &self,
-        event_handler: Option<Sender<Envelope>>,
-        error_handler: Option<Sender<Error>>,
-        addr: SocketAddr,
-    ) -> Result<TransportRustls> {
-        let transport = self
-            .create_transport(event_handler, error_handler, addr)
-            .await?;
+        .map_err(|error| {
+            ConnectionError::new(
+                addr,
+                ConnectionPhase::TlsHandshake,
+                classify_tls_error(addr, error),
+                start.elapsed(),
+            )
+        })?;
 
-        startup(
+        let mut phase = ConnectionPhase::Startup;
+        let result = startup_with_phase(
             &transport,
             self.authenticator_provider.deref(),
             self.keyspace_holder.deref(),
-            self.compression,
+            compression,
             self.version,
+            &self.init_statements,
+            &mut phase,
+            Some(&self.handshake_limits),
+        )
+        .await;
+
+        result
+            .map(|_| transport)
+            .map_err(|error| ConnectionError::new(addr, phase, error, start.elapsed()))
+    }
+}
+
+// rustls reports its errors wrapped in io::Error, which would otherwise be indistinguishable from
+// network failures
+fn classify_tls_error(addr: SocketAddr, error: io::Error) -> Error {
+    let tls_error = match error
+        .get_ref()
+        .and_then(|error| error.downcast_ref::<rustls::Error>())
+    {
+        Some(tls_error) => tls_error,
+        None => return Error::Io(error),
+    };
+
+    let kind = match tls_error {
+        rustls::Error::InvalidCertificate(
+            CertificateError::NotValidForName | CertificateError::NotValidForNameContext { .. },
+        ) => TlsErrorKind::ServerNameMismatch,
+        rustls::Error::InvalidCertificate(_)
+        | rustls::Error::InvalidCertRevocationList(_)
+        | rustls::Error::NoCertificatesPresented
+        | rustls::Error::UnsupportedNameType => TlsErrorKind::CertificateVerification,
+        _ => TlsErrorKind::HandshakeProtocol,
+    };
+
+    Error::Tls {
+        node: addr,
+        kind,
+        details: tls_error.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use cassandra_protocol::error::{Error, TlsErrorKind};
+    use std::io;
+    use std::net::SocketAddr;
+    use tokio_rustls::rustls::{self, AlertDescription, CertificateError};
+
+    use super::classify_tls_error;
+
+    fn classify(error: rustls::Error) -> Error {
+        classify_tls_error(
+            SocketAddr::from(([127, 0, 0, 1], 9142)),
+            io::Error::new(io::ErrorKind::InvalidData, error),
         )
-        .await?;
+    }
+
+    fn kind(error: Error) -> TlsErrorKind {
+        match error {
+            Error::Tls { kind, .. } => kind,
+            error => panic!("Unexpected error: {}", error),
+        }
+    }
+
+    #[test]
+    fn should_classify_tls_errors() {
+        let error = classify(rustls::Error::InvalidCertificate(
+            CertificateError::UnknownIssuer,
+        ));
+        assert!(error
+            .to_string()
+            .starts_with("TLS certificate verification failed for node 127.0.0.1:9142"));
+        assert_eq!(kind(error), TlsErrorKind::CertificateVerification);
+
+        assert_eq!(
+            kind(classify(rustls::Error::InvalidCertificate(
+                CertificateError::NotValidForName
+            ))),
+            TlsErrorKind::ServerNameMismatch
+        );
+        assert_eq!(
+            kind(classify(rustls::Error::AlertReceived(
+                AlertDescription::HandshakeFailure
+            ))),
+            TlsErrorKind::HandshakeProtocol
+        );
+    }
 
-        Ok(transport)
+    #[test]
+    fn should_keep_io_errors() {
+        let error = classify_tls_error(
+            SocketAddr::from(([127, 0, 0, 1], 9142)),
+            io::ErrorKind::ConnectionReset.into(),
+        );
+        assert!(matches!(error, Error::Io(_)));
     }
 }
@@ -1,50 +1,707 @@
-use cassandra_protocol::error;
-use cassandra_protocol::frame::Envelope;
+use cassandra_protocol::error::{self, Error};
+use cassandra_protocol::frame::message_batch::BatchType;
+use cassandra_protocol::frame::{Envelope, Opcode};
+use fxhash::FxHashSet;
+use std::convert::TryFrom;
+use std::net::SocketAddr;
+use std::sync::atomic::AtomicBool;
 use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tracing::*;
 
 use crate::cluster::topology::Node;
 use crate::cluster::ConnectionManager;
-use crate::retry::{QueryInfo, RetryDecision, RetrySession};
+use crate::retry::{QueryInfo, RetryBudget, RetryDecision, RetrySession};
 use crate::transport::CdrsTransport;
 
+/// Callback invoked after each attempt to send a request to a node, with the time it took to get
+/// the result. Attempts abandoned before getting a result are reported with [`Error::Cancelled`].
+pub(crate) type CompletionHook<'a, T, CM> =
+    &'a (dyn Fn(&Node<T, CM>, Duration, &error::Result<Envelope>) + Send + Sync);
+
 /// Mid-level interface for sending envelopes to the cluster. Uses a query plan to route envelope to
-/// appropriate node, and retry policy for error handling. Returns `None` if no nodes were present
-/// in the query plan.
+/// appropriate node, and retry policy for error handling. Selection failures, i.e. not getting a
+/// connection or the request failing before being written to one, always move on to the next node
+/// without consulting the retry policy, since no server has seen the request. Execution failures
+/// are handled by the retry policy, since the request might have been already processed. Nodes
+/// appearing in the query plan more than once are only tried once, so retrying on the next node
+/// never goes back to one which already failed - the retry policy can still retry on the same node.
+/// If all nodes fail, the last execution error is preferred over selection errors. Returns `None`
+/// if no nodes were present in the query plan.
 pub async fn send_envelope<T: CdrsTransport + 'static, CM: ConnectionManager<T> + 'static>(
     query_plan: impl Iterator<Item = Arc<Node<T, CM>>>,
     envelope: &Envelope,
     is_idempotent: bool,
-    mut retry_session: Box<dyn RetrySession + Send + Sync>,
+    retry_session: impl RetrySession + Send,
+) -> Option<error::Result<Envelope>> {
+    send_envelope_with_hook(
+        query_plan,
+        envelope,
+        is_idempotent,
+        retry_session,
+        &|_, _, _| {},
+        None,
+        None,
+    )
+    .await
+}
+
+// reports an attempt to the completion hook once it completes, or as cancelled if it gets
+// abandoned, e.g. when the query is cancelled or loses a speculative execution race
+struct Attempt<'a, T: CdrsTransport + 'static, CM: ConnectionManager<T> + 'static> {
+    node: &'a Node<T, CM>,
+    start: Instant,
+    completion_hook: Option<CompletionHook<'a, T, CM>>,
+}
+
+impl<'a, T: CdrsTransport + 'static, CM: ConnectionManager<T> + 'static> Attempt<'a, T, CM> {
+    fn new(node: &'a Node<T, CM>, completion_hook: CompletionHook<'a, T, CM>) -> Self {
+        Attempt {
+            node,
+            start: Instant::now(),
+            completion_hook: Some(completion_hook),
+        }
+    }
+
+    fn complete(mut self, response: &error::Result<Envelope>) {
+        if let Some(completion_hook) = self.completion_hook.take() {
+            completion_hook(self.node, self.start.elapsed(), response);
+        }
+    }
+}
+
+impl<T: CdrsTransport + 'static, CM: ConnectionManager<T> + 'static> Drop for Attempt<'_, T, CM> {
+    fn drop(&mut self) {
+        if let Some(completion_hook) = self.completion_hook.take() {
+            completion_hook(self.node, self.start.elapsed(), &Err(Error::Cancelled));
+        }
+    }
+}
+
+// nodes already attempted for a request - most requests only ever attempt a single node, so the
+// first one is kept inline to avoid allocating a set
+#[derive(Default)]
+struct AttemptedNodes {
+    first: Option<SocketAddr>,
+    rest: FxHashSet<SocketAddr>,
+}
+
+impl AttemptedNodes {
+    fn insert(&mut self, address: SocketAddr) -> bool {
+        match self.first {
+            None => {
+                self.first = Some(address);
+                true
+            }
+            Some(first) if first == address => false,
+            Some(_) => self.rest.insert(address),
+        }
+    }
+}
+
+// batch bodies start with the batch type
+fn batch_type(envelope: &Envelope) -> Option<BatchType> {
+    if envelope.opcode != Opcode::Batch {
+        return None;
+    }
+
+    envelope
+        .body
+        .first()
+        .and_then(|batch_type| BatchType::try_from(*batch_type).ok())
+}
+
+// same as send_envelope, but reports attempts to the completion hook, sets `written` if the
+// envelope might have been written to a connection and limits retries with the budget, if given
+pub(crate) async fn send_envelope_with_hook<
+    T: CdrsTransport + 'static,
+    CM: ConnectionManager<T> + 'static,
+>(
+    query_plan: impl Iterator<Item = Arc<Node<T, CM>>>,
+    envelope: &Envelope,
+    is_idempotent: bool,
+    mut retry_session: impl RetrySession + Send,
+    completion_hook: CompletionHook<'_, T, CM>,
+    written: Option<&AtomicBool>,
+    retry_budget: Option<&RetryBudget>,
 ) -> Option<error::Result<Envelope>> {
-    let mut result = None;
+    let mut last_selection_error = None;
+    let mut last_execution_error = None;
+    let mut attempted_nodes = AttemptedNodes::default();
+    let batch_type = batch_type(envelope);
 
     'next_node: for node in query_plan {
+        // plans can repeat nodes, e.g. when round robin wraps around
+        if !attempted_nodes.insert(node.broadcast_rpc_address()) {
+            debug!(?node, "Node already attempted, trying next node.");
+            continue;
+        }
+
         loop {
-            let transport = node.persistent_connection().await;
-            match transport {
-                Ok(transport) => match transport.write_envelope(envelope, false).await {
-                    Ok(envelope) => return Some(Ok(envelope)),
-                    Err(error) => {
-                        let query_info = QueryInfo {
-                            error: &error,
-                            is_idempotent,
-                        };
-
-                        match retry_session.decide(query_info) {
-                            RetryDecision::RetrySameNode => continue,
-                            RetryDecision::RetryNextNode => continue 'next_node,
-                            RetryDecision::DontRetry => return Some(Err(error)),
-                        }
-                    }
-                },
+            let transport = match node.persistent_connection().await {
+                Ok(transport) => transport,
                 // save the error, but keep trying, since another node might be up
                 Err(error) => {
-                    result = Some(Err(error));
+                    debug!(%error, "Cannot get connection to node, trying next node.");
+                    last_selection_error = Some(error);
                     continue 'next_node;
                 }
+            };
+
+            let attempt = Attempt::new(&node, completion_hook);
+            let response = match written {
+                Some(written) => transport.write_envelope_tracked(envelope, written).await,
+                None => transport.write_envelope(envelope, false).await,
+            };
+            attempt.complete(&response);
+
+            match response {
+                Ok(envelope) => {
+                    if let Some(retry_budget) = retry_budget {
+                        retry_budget.deposit();
+                    }
+
+                    return Some(Ok(envelope));
+                }
+                // the request never reached a coordinator, so it can be sent to the next node
+                // regardless of idempotency or retry policy
+                Err(error @ Error::RequestNotSent(_)) => {
+                    debug!(%error, "Connection failed before sending request, trying next node.");
+                    last_selection_error = Some(error);
+                    continue 'next_node;
+                }
+                Err(error) => {
+                    let query_info = QueryInfo {
+                        error: &error,
+                        is_idempotent,
+                        batch_type,
+                    };
+
+                    let decision = retry_session.decide(query_info);
+                    if decision != RetryDecision::DontRetry
+                        && retry_budget.is_some_and(|retry_budget| !retry_budget.try_withdraw())
+                    {
+                        debug!(%error, "Retry budget exhausted, not retrying.");
+                        return Some(Err(Error::RetrySuppressed(Box::new(error))));
+                    }
+
+                    match decision {
+                        RetryDecision::RetrySameNode => {
+                            last_execution_error = Some(error);
+                            continue;
+                        }
+                        RetryDecision::RetryNextNode => {
+                            last_execution_error = Some(error);
+                            continue 'next_node;
+                        }
+                        RetryDecision::DontRetry => return Some(Err(error)),
+                    }
+                }
             }
         }
     }
 
-    result
+    last_execution_error.or(last_selection_error).map(Err)
+}
+
+#[cfg(test)]
+mod tests {
+    use cassandra_protocol::consistency::Consistency;
+    use cassandra_protocol::error::Error;
+    use cassandra_protocol::frame::message_batch::{BatchType, BodyReqBatch};
+    use cassandra_protocol::frame::message_error::{ErrorBody, ErrorType};
+    use cassandra_protocol::frame::{Envelope, Flags, Version};
+    use std::io;
+    use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+    use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+    use std::sync::Arc;
+    use std::time::Duration;
+    use tokio::time::timeout;
+
+    use crate::cluster::connection_manager::MockConnectionManager;
+    use crate::cluster::send_envelope::{batch_type, send_envelope, send_envelope_with_hook};
+    use crate::cluster::test_nodes::{local_nodes, TestNode};
+    use crate::retry::{
+        DefaultRetrySession, FallthroughRetrySession, LazyRetrySession, QueryInfo, RetryBudget,
+        RetryDecision, RetryPolicy, RetrySession,
+    };
+    use crate::transport::MockCdrsTransport;
+
+    const FAILING_ADDR: SocketAddr = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)), 9042);
+    const WORKING_ADDR: SocketAddr = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 2)), 9042);
+    const OTHER_WORKING_ADDR: SocketAddr =
+        SocketAddr::new(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 3)), 9042);
+
+    // creates nodes where the first one fails requests with given error and the second one works
+    fn create_nodes(error: Error) -> Vec<Arc<TestNode>> {
+        let mut connection_manager = MockConnectionManager::<MockCdrsTransport>::new();
+        connection_manager
+            .expect_connection()
+            .returning(move |_, _, addr| {
+                let mut transport = MockCdrsTransport::new();
+                transport.expect_is_broken().return_const(false);
+                transport.expect_address().return_const(addr);
+                transport.expect_idle_time().return_const(Duration::ZERO);
+
+                let error = error.clone();
+                transport.expect_write_envelope().returning(move |_, _| {
+                    let result = if addr == FAILING_ADDR {
+                        Err(error.clone())
+                    } else {
+                        Ok(Envelope::new_req_options(Version::V4))
+                    };
+
+                    Box::pin(async move { result })
+                });
+
+                Box::pin(async move { Ok(transport) })
+            });
+
+        create_nodes_with_manager(connection_manager)
+    }
+
+    // creates nodes where the first one cannot be connected to and the second one works
+    fn create_nodes_with_dead_node() -> Vec<Arc<TestNode>> {
+        let mut connection_manager = MockConnectionManager::<MockCdrsTransport>::new();
+        connection_manager
+            .expect_connection()
+            .returning(move |_, _, addr| {
+                if addr == FAILING_ADDR {
+                    return Box::pin(async move {
+                        Err(Error::Io(io::ErrorKind::ConnectionRefused.into()))
+                    });
+                }
+
+                let mut transport = MockCdrsTransport::new();
+                transport.expect_is_broken().return_const(false);
+                transport.expect_address().return_const(addr);
+                transport.expect_idle_time().return_const(Duration::ZERO);
+                transport.expect_write_envelope().returning(|_, _| {
+                    Box::pin(async { Ok(Envelope::new_req_options(Version::V4)) })
+                });
+
+                Box::pin(async move { Ok(transport) })
+            });
+
+        create_nodes_with_manager(connection_manager)
+    }
+
+    fn create_nodes_with_manager(
+        connection_manager: MockConnectionManager<MockCdrsTransport>,
+    ) -> Vec<Arc<TestNode>> {
+        local_nodes(connection_manager, &[FAILING_ADDR, WORKING_ADDR])
+    }
+
+    #[derive(Default)]
+    struct CountingRetrySession {
+        decisions: Arc<AtomicUsize>,
+    }
+
+    impl RetrySession for CountingRetrySession {
+        fn decide(&mut self, _query_info: QueryInfo) -> RetryDecision {
+            self.decisions.fetch_add(1, Ordering::Relaxed);
+            RetryDecision::RetryNextNode
+        }
+    }
+
+    #[derive(Default)]
+    struct CountingRetryPolicy {
+        sessions: AtomicUsize,
+    }
+
+    impl RetryPolicy for CountingRetryPolicy {
+        fn new_session(&self) -> Box<dyn RetrySession + Send + Sync> {
+            self.sessions.fetch_add(1, Ordering::Relaxed);
+            Box::<CountingRetrySession>::default()
+        }
+    }
+
+    async fn send(
+        error: Error,
+        is_idempotent: bool,
+        retry_session: Box<dyn RetrySession + Send + Sync>,
+    ) -> Option<Result<Envelope, Error>> {
+        send_envelope(
+            create_nodes(error).into_iter(),
+            &Envelope::new_req_options(Version::V4),
+            is_idempotent,
+            retry_session,
+        )
+        .await
+    }
+
+    #[tokio::test]
+    async fn should_fail_over_unsent_requests() {
+        let result = send(
+            Error::RequestNotSent(FAILING_ADDR),
+            false,
+            Box::<FallthroughRetrySession>::default(),
+        )
+        .await;
+
+        assert!(matches!(result, Some(Ok(_))));
+    }
+
+    #[tokio::test]
+    async fn should_not_fail_over_sent_non_idempotent_requests() {
+        let result = send(
+            Error::Io(io::Error::new(io::ErrorKind::BrokenPipe, "broken pipe")),
+            false,
+            Box::<DefaultRetrySession>::default(),
+        )
+        .await;
+
+        assert!(matches!(result, Some(Err(Error::Io(_)))));
+    }
+
+    #[tokio::test]
+    async fn should_fail_over_sent_idempotent_requests() {
+        let result = send(
+            Error::Io(io::Error::new(io::ErrorKind::BrokenPipe, "broken pipe")),
+            true,
+            Box::<DefaultRetrySession>::default(),
+        )
+        .await;
+
+        assert!(matches!(result, Some(Ok(_))));
+    }
+
+    #[tokio::test]
+    async fn should_skip_dead_nodes_without_consulting_retry_policy() {
+        let nodes = create_nodes_with_dead_node();
+        let decisions = Arc::new(AtomicUsize::new(0));
+
+        for _ in 0..100 {
+            let result = send_envelope(
+                nodes.iter().cloned(),
+                &Envelope::new_req_options(Version::V4),
+                false,
+                Box::new(CountingRetrySession {
+                    decisions: decisions.clone(),
+                }),
+            )
+            .await;
+
+            assert!(matches!(result, Some(Ok(_))));
+        }
+
+        assert_eq!(decisions.load(Ordering::Relaxed), 0);
+    }
+
+    #[tokio::test]
+    async fn should_start_retry_sessions_on_first_failure() {
+        let nodes = create_nodes(Error::Io(io::Error::new(
+            io::ErrorKind::BrokenPipe,
+            "broken pipe",
+        )));
+        let retry_policy = CountingRetryPolicy::default();
+
+        for _ in 0..10 {
+            let result = send_envelope(
+                nodes.iter().skip(1).cloned(),
+                &Envelope::new_req_options(Version::V4),
+                true,
+                LazyRetrySession::new(&retry_policy),
+            )
+            .await;
+
+            assert!(matches!(result, Some(Ok(_))));
+        }
+
+        assert_eq!(retry_policy.sessions.load(Ordering::Relaxed), 0);
+
+        let mut retry_session = LazyRetrySession::new(&retry_policy);
+        let result = send_envelope(
+            nodes.iter().cloned(),
+            &Envelope::new_req_options(Version::V4),
+            true,
+            &mut retry_session,
+        )
+        .await;
+
+        assert!(matches!(result, Some(Ok(_))));
+        assert!(retry_session.is_started());
+        assert_eq!(retry_policy.sessions.load(Ordering::Relaxed), 1);
+    }
+
+    #[tokio::test]
+    async fn should_prefer_execution_errors_over_selection_errors() {
+        let mut nodes = create_nodes(Error::Io(io::Error::new(
+            io::ErrorKind::BrokenPipe,
+            "broken pipe",
+        )));
+
+        // the first node fails after sending and the next one can't be connected to
+        let mut connection_manager = MockConnectionManager::<MockCdrsTransport>::new();
+        connection_manager.expect_connection().returning(|_, _, _| {
+            Box::pin(async { Err(Error::Io(io::ErrorKind::ConnectionRefused.into())) })
+        });
+
+        nodes.truncate(1);
+        nodes.push(create_nodes_with_manager(connection_manager).remove(1));
+
+        let result = send_envelope(
+            nodes.into_iter(),
+            &Envelope::new_req_options(Version::V4),
+            true,
+            Box::<CountingRetrySession>::default(),
+        )
+        .await;
+
+        assert!(
+            matches!(result, Some(Err(Error::Io(error))) if error.kind() == io::ErrorKind::BrokenPipe)
+        );
+    }
+
+    #[tokio::test]
+    async fn should_not_retry_on_already_attempted_nodes() {
+        let failed_writes = Arc::new(AtomicUsize::new(0));
+        let failed_writes_clone = failed_writes.clone();
+
+        let mut connection_manager = MockConnectionManager::<MockCdrsTransport>::new();
+        connection_manager
+            .expect_connection()
+            .returning(move |_, _, addr| {
+                let mut transport = MockCdrsTransport::new();
+                transport.expect_is_broken().return_const(false);
+                transport.expect_address().return_const(addr);
+                transport.expect_idle_time().return_const(Duration::ZERO);
+
+                let failed_writes = failed_writes_clone.clone();
+                transport.expect_write_envelope().returning(move |_, _| {
+                    let result = if addr == FAILING_ADDR {
+                        failed_writes.fetch_add(1, Ordering::Relaxed);
+                        Err(Error::Io(io::ErrorKind::BrokenPipe.into()))
+                    } else {
+                        Ok(Envelope::new_req_options(Version::V4))
+                    };
+
+                    Box::pin(async move { result })
+                });
+
+                Box::pin(async move { Ok(transport) })
+            });
+
+        let nodes = local_nodes(
+            connection_manager,
+            &[FAILING_ADDR, WORKING_ADDR, OTHER_WORKING_ADDR],
+        );
+        let decisions = Arc::new(AtomicUsize::new(0));
+
+        // round robin wrapped around to the failing node
+        let result = send_envelope(
+            vec![nodes[0].clone(), nodes[0].clone(), nodes[1].clone()].into_iter(),
+            &Envelope::new_req_options(Version::V4),
+            true,
+            Box::new(CountingRetrySession {
+                decisions: decisions.clone(),
+            }),
+        )
+        .await;
+
+        assert!(matches!(result, Some(Ok(_))));
+        assert_eq!(failed_writes.load(Ordering::Relaxed), 1);
+        assert_eq!(decisions.load(Ordering::Relaxed), 1);
+    }
+
+    #[tokio::test]
+    async fn should_suppress_retries_over_budget() {
+        let budget = RetryBudget::new(0, 0.0).with_max_balance(0);
+        let result = send_envelope_with_hook(
+            create_nodes(Error::Io(io::Error::new(
+                io::ErrorKind::BrokenPipe,
+                "broken pipe",
+            )))
+            .into_iter(),
+            &Envelope::new_req_options(Version::V4),
+            true,
+            Box::<CountingRetrySession>::default(),
+            &|_, _, _| {},
+            None,
+            Some(&budget),
+        )
+        .await;
+
+        assert!(
+            matches!(result, Some(Err(Error::RetrySuppressed(error))) if matches!(*error, Error::Io(_)))
+        );
+        assert_eq!(budget.snapshot().suppressed_retries, 1);
+    }
+
+    async fn written_before_timeout(
+        connection_manager: MockConnectionManager<MockCdrsTransport>,
+    ) -> bool {
+        let written = AtomicBool::new(false);
+        let result = timeout(
+            Duration::from_millis(50),
+            send_envelope_with_hook(
+                create_nodes_with_manager(connection_manager).into_iter(),
+                &Envelope::new_req_options(Version::V4),
+                false,
+                Box::<FallthroughRetrySession>::default(),
+                &|_, _, _| {},
+                Some(&written),
+                None,
+            ),
+        )
+        .await;
+
+        assert!(result.is_err());
+        written.load(Ordering::Relaxed)
+    }
+
+    #[tokio::test]
+    async fn should_report_abandoned_attempts_as_cancelled() {
+        let mut connection_manager = MockConnectionManager::<MockCdrsTransport>::new();
+        connection_manager
+            .expect_connection()
+            .returning(move |_, _, addr| {
+                let mut transport = MockCdrsTransport::new();
+                transport.expect_is_broken().return_const(false);
+                transport.expect_address().return_const(addr);
+                transport.expect_idle_time().return_const(Duration::ZERO);
+                transport
+                    .expect_write_envelope()
+                    .returning(|_, _| Box::pin(futures::future::pending()));
+
+                Box::pin(async move { Ok(transport) })
+            });
+
+        let cancelled = AtomicUsize::new(0);
+        let result = timeout(
+            Duration::from_millis(50),
+            send_envelope_with_hook(
+                create_nodes_with_manager(connection_manager).into_iter(),
+                &Envelope::new_req_options(Version::V4),
+                false,
+                Box::<FallthroughRetrySession>::default(),
+                &|node, _, result| {
+                    assert_eq!(node.broadcast_rpc_address(), FAILING_ADDR);
+                    assert!(matches!(result, Err(Error::Cancelled)));
+                    cancelled.fetch_add(1, Ordering::Relaxed);
+                },
+                None,
+                None,
+            ),
+        )
+        .await;
+
+        assert!(result.is_err());
+        assert_eq!(cancelled.load(Ordering::Relaxed), 1);
+    }
+
+    #[tokio::test]
+    async fn should_mark_requests_handed_to_connections_as_written() {
+        let mut connection_manager = MockConnectionManager::<MockCdrsTransport>::new();
+        connection_manager
+            .expect_connection()
+            .returning(move |_, _, addr| {
+                let mut transport = MockCdrsTransport::new();
+                transport.expect_is_broken().return_const(false);
+                transport.expect_address().return_const(addr);
+                transport.expect_idle_time().return_const(Duration::ZERO);
+                transport
+                    .expect_write_envelope()
+                    .returning(|_, _| Box::pin(futures::future::pending()));
+
+                Box::pin(async move { Ok(transport) })
+            });
+
+        assert!(written_before_timeout(connection_manager).await);
+    }
+
+    #[tokio::test]
+    async fn should_not_mark_requests_without_connection_as_written() {
+        let mut connection_manager = MockConnectionManager::<MockCdrsTransport>::new();
+        connection_manager
+            .expect_connection()
+            .returning(|_, _, _| Box::pin(futures::future::pending()));
+
+        assert!(!written_before_timeout(connection_manager).await);
+    }
+
+    fn server_error(ty: ErrorType) -> Error {
+        Error::Server {
+            body: ErrorBody {
+                message: "error".into(),
+                ty,
+            },
+            addr: FAILING_ADDR,
+        }
+    }
+
+    #[tokio::test]
+    async fn should_fail_over_rejected_requests() {
+        for ty in [ErrorType::Overloaded, ErrorType::IsBootstrapping] {
+            let result = send(
+                server_error(ty),
+                false,
+                Box::<DefaultRetrySession>::default(),
+            )
+            .await;
+
+            assert!(matches!(result, Some(Ok(_))));
+        }
+    }
+
+    #[tokio::test]
+    async fn should_fail_over_truncate_errors_for_idempotent_requests() {
+        let result = send(
+            server_error(ErrorType::Truncate),
+            true,
+            Box::<DefaultRetrySession>::default(),
+        )
+        .await;
+        assert!(matches!(result, Some(Ok(_))));
+
+        let result = send(
+            server_error(ErrorType::Truncate),
+            false,
+            Box::<DefaultRetrySession>::default(),
+        )
+        .await;
+        assert!(matches!(
+            result,
+            Some(Err(Error::Server {
+                body: ErrorBody {
+                    ty: ErrorType::Truncate,
+                    ..
+                },
+                ..
+            }))
+        ));
+    }
+
+    #[tokio::test]
+    async fn should_not_retry_server_protocol_and_authentication_errors() {
+        for ty in [
+            ErrorType::Server,
+            ErrorType::Protocol,
+            ErrorType::Authentication,
+        ] {
+            let result = send(
+                server_error(ty.clone()),
+                true,
+                Box::<DefaultRetrySession>::default(),
+            )
+            .await;
+
+            assert!(
+                matches!(&result, Some(Err(Error::Server { body, .. })) if body.ty == ty),
+                "{:?}",
+                ty
+            );
+        }
+    }
+
+    #[test]
+    fn should_read_batch_type_from_envelope() {
+        for ty in [BatchType::Logged, BatchType::Unlogged, BatchType::Counter] {
+            let batch = BodyReqBatch::new(ty, vec![], Consistency::One, None, None, None, None);
+            let envelope = Envelope::new_req_batch(batch, Flags::empty(), Version::V4).unwrap();
+            assert_eq!(batch_type(&envelope), Some(ty));
+        }
+
+        assert_eq!(batch_type(&Envelope::new_req_options(Version::V4)), None);
+    }
 }
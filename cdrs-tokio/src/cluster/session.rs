@@ -1,65 +1,127 @@
-use arc_swap::ArcSwapOption;
-use cassandra_protocol::compression::Compression;
+use cassandra_protocol::compression::{Compression, CompressionMode};
 use cassandra_protocol::consistency::Consistency;
 use cassandra_protocol::error;
-use cassandra_protocol::events::ServerEvent;
-use cassandra_protocol::frame::message_error::ErrorType;
-use cassandra_protocol::frame::message_query::BodyReqQuery;
-use cassandra_protocol::frame::message_response::ResponseBody;
-use cassandra_protocol::frame::message_result::{BodyResResultPrepared, TableSpec};
+use cassandra_protocol::events::{ServerEvent, SimpleServerEvent};
+use cassandra_protocol::frame::message_batch::BatchType;
+use cassandra_protocol::frame::message_error::{ErrorBody, ErrorType};
+use cassandra_protocol::frame::message_response::{ParsedResponse, ResponseBody};
+use cassandra_protocol::frame::message_result::{
+    BodyResResultPrepared, ColSpec, RowsMetadataFlags, SchemaChange,
+};
 use cassandra_protocol::frame::{Envelope, Flags, Serialize, Version};
-use cassandra_protocol::query::{PreparedQuery, QueryBatch, QueryValues};
+use cassandra_protocol::query::utils::quote_identifier;
+use cassandra_protocol::query::{
+    convert_bound_values, PreparedQuery, QueryBatch, QueryParams, QueryValues, QueryValuesDisplay,
+    Redactor,
+};
+use cassandra_protocol::types::rows::Row;
 use cassandra_protocol::types::value::Value;
-use cassandra_protocol::types::{CIntShort, SHORT_LEN};
+use cassandra_protocol::types::CBytes;
+use cassandra_protocol::types::{CIntShort, INT_LEN, SHORT_LEN};
 use derivative::Derivative;
 use futures::stream::FuturesUnordered;
-use futures::{FutureExt, StreamExt};
+use futures::stream::{self, BoxStream};
+use futures::{FutureExt, StreamExt, TryStreamExt};
+use fxhash::FxHashMap;
 use itertools::Itertools;
+use rand::{rng, Rng};
+use std::borrow::{Borrow, Cow};
+use std::convert::TryFrom;
+use std::fmt::{self, Display, Formatter};
+use std::future::Future;
 use std::io::{Cursor, Write};
+use std::iter::FromIterator;
 use std::marker::PhantomData;
 use std::net::SocketAddr;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::{Arc, LazyLock, Mutex};
+use std::time::{Duration, Instant};
 use thiserror::Error;
 use tokio::sync::broadcast::{channel, Receiver, Sender};
 use tokio::sync::watch;
-use tokio::task::JoinHandle;
-use tokio::time::sleep;
 use tokio::{pin, select};
+#[cfg(feature = "rust-tls")]
+use tokio_rustls::rustls::{pki_types::ServerName, ClientConfig};
 use tracing::*;
 
+use crate::cluster::batch_split::group_by_partition;
 use crate::cluster::connection_manager::ConnectionManager;
 use crate::cluster::connection_pool::{ConnectionPoolConfig, ConnectionPoolFactory};
 use crate::cluster::control_connection::ControlConnection;
+use crate::cluster::event_registration::EventRegistration;
+use crate::cluster::execute_concurrent::{execute_in_order, execute_summarized};
+use crate::cluster::pinned_session::PinnedConnection;
+use crate::cluster::prepare_all::prepare_concurrently;
+use crate::cluster::prepared_cache::{into_prepared_query, PreparedCache};
+use crate::cluster::prepared_metadata_listener::{
+    check_variables_metadata, update_result_metadata_id, ReportedVariables,
+};
+use crate::cluster::query_handle::cancellable;
 #[cfg(feature = "rust-tls")]
 use crate::cluster::rustls_connection_manager::RustlsConnectionManager;
-use crate::cluster::send_envelope::send_envelope;
+use crate::cluster::send_envelope::{send_envelope, send_envelope_with_hook};
 use crate::cluster::tcp_connection_manager::TcpConnectionManager;
 use crate::cluster::topology::{Node, NodeDistance, NodeState};
+use crate::cluster::ConnectionManagerConfigBuilder;
+use crate::cluster::ConnectionString;
+use crate::cluster::EventSubscriptionBuilder;
 use crate::cluster::Murmur3Token;
+use crate::cluster::NodeStateListener;
+use crate::cluster::PartitionGrouper;
+use crate::cluster::QueryHandle;
+use crate::cluster::RegistrationsRestored;
+use crate::cluster::TokenRange;
+use crate::cluster::WarningPolicy;
+use crate::cluster::{AddressFamilyPreference, DEFAULT_CONNECTION_STAGGER_DELAY};
+use crate::cluster::{BatchEntry, BatchSplitOptions, PartitionBatchResult};
+use crate::cluster::{ClusterMetadata, ClusterMetadataManager, DynSession, SessionContext};
+use crate::cluster::{ConcurrentErrorMode, ConcurrentExecutionError};
+use crate::cluster::{ConnectionError, ConnectionPhase, WarmupError, WarmupReport};
+use crate::cluster::{GenericClusterConfig, InitStatement, KeyspaceHolder, KeyspaceSession};
 #[cfg(feature = "rust-tls")]
-use crate::cluster::NodeRustlsConfig;
-use crate::cluster::{ClusterMetadata, ClusterMetadataManager, SessionContext};
-use crate::cluster::{GenericClusterConfig, KeyspaceHolder};
-use crate::cluster::{NodeTcpConfig, SessionPager};
+use crate::cluster::{NodeRustlsConfig, NodeRustlsConfigBuilder};
+use crate::cluster::{NodeTcpConfig, NodeTcpConfigBuilder, SessionPager};
+use crate::cluster::{
+    PinnedSession, PrepareAllError, PreparedMetadataListener, DEFAULT_PREPARE_CONCURRENCY,
+};
+use crate::cluster::{PreparedCacheSnapshot, DEFAULT_PREPARED_CACHE_CAPACITY};
+use crate::cluster::{StatementRequest, StatementTarget};
 use crate::frame_encoding::{FrameEncodingFactory, ProtocolFrameEncodingFactory};
+use crate::frame_recording::FrameRecorder;
 use crate::future::BoxFuture;
+use crate::helpers::InsertStatementKey;
 use crate::load_balancing::node_distance_evaluator::AllLocalNodeDistanceEvaluator;
 use crate::load_balancing::node_distance_evaluator::NodeDistanceEvaluator;
 use crate::load_balancing::{
     InitializingWrapperLoadBalancingStrategy, LoadBalancingStrategy, QueryPlan, Request,
 };
 use crate::retry::{
-    DefaultRetryPolicy, ExponentialReconnectionPolicy, ReconnectionPolicy, RetryPolicy,
+    DefaultRetryPolicy, ExponentialReconnectionPolicy, LazyRetrySession, ReconnectionPolicy,
+    RetryBudget, RetryPolicy,
 };
+use crate::runtime::{sleep, timeout, TaskTracker};
 use crate::speculative_execution::{Context, SpeculativeExecutionPolicy};
-use crate::statement::{StatementParams, StatementParamsBuilder};
+use crate::statement::{
+    find_unqualified_table, is_use_statement, verify_named_values, StatementAnalysis,
+    StatementParams, StatementParamsBuilder,
+};
 #[cfg(feature = "rust-tls")]
 use crate::transport::TransportRustls;
-use crate::transport::{CdrsTransport, TransportTcp};
+use crate::transport::{CdrsTransport, StreamedBlob, TransportTcp};
 
 pub const DEFAULT_TRANSPORT_BUFFER_SIZE: usize = 1024;
+pub const DEFAULT_COMPRESSION_THRESHOLD: usize = 512;
+pub const DEFAULT_HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(5);
+pub const DEFAULT_AUTHENTICATION_TIMEOUT: Duration = Duration::from_secs(10);
+pub const DEFAULT_MAX_CONCURRENT_AUTHENTICATIONS: usize = 4;
+pub const DEFAULT_SCHEMA_AGREEMENT_TIMEOUT: Duration = Duration::from_secs(10);
 const DEFAULT_EVENT_CHANNEL_CAPACITY: usize = 128;
 
+const SCHEMA_AGREEMENT_INTERVAL: Duration = Duration::from_millis(200);
+
+// number of rows fetched at once from a single token range by `Session::token_range_scan`
+const TOKEN_RANGE_SCAN_PAGE_SIZE: i32 = 5000;
+
 static DEFAULT_STATEMENT_PARAMETERS: LazyLock<StatementParams> =
     LazyLock::new(|| Default::default());
 
@@ -69,8 +131,123 @@ fn convert_to_prepared(body: ResponseBody) -> error::Result<BodyResResultPrepare
         .ok_or_else(|| "Cannot convert envelope into prepare response!".into())
 }
 
+// errors caused by the statement itself, which say nothing about its coordinator
+#[inline]
+fn is_statement_error(error: &error::Error) -> bool {
+    matches!(
+        error,
+        error::Error::Server {
+            body: ErrorBody {
+                ty: ErrorType::Syntax
+                    | ErrorType::Unauthorized
+                    | ErrorType::Invalid
+                    | ErrorType::Config
+                    | ErrorType::AlreadyExists(_),
+                ..
+            },
+            ..
+        }
+    )
+}
+
+#[inline]
+fn count_query(query_or_table: &str) -> Cow<'_, str> {
+    let query_or_table = query_or_table.trim();
+    if query_or_table.contains(char::is_whitespace) {
+        Cow::Borrowed(query_or_table)
+    } else {
+        Cow::Owned(format!("SELECT count(*) FROM {query_or_table}"))
+    }
+}
+
+// traces requests which asked for it explicitly and a random sample of the rest
+fn sample_tracing(requested: bool, sample_rate: f64) -> bool {
+    requested || (sample_rate > 0.0 && rng().random::<f64>() < sample_rate)
+}
+
+// query for a page of given token range - paging and bound values are set for the range,
+// everything else is taken from given parameters
+fn token_range_envelope(
+    query: &str,
+    range: TokenRange,
+    parameters: &StatementParams,
+    paging_state: Option<CBytes>,
+    flags: Flags,
+    version: Version,
+) -> error::Result<Envelope> {
+    let mut query_params = parameters.query_params.clone();
+    query_params.values = Some(QueryValues::SimpleValues(vec![
+        Value::from(range.start.value),
+        Value::from(range.end.value),
+    ]));
+    query_params.with_names = false;
+    query_params.page_size = query_params.page_size.or(Some(TOKEN_RANGE_SCAN_PAGE_SIZE));
+    query_params.paging_state = paging_state;
+
+    Envelope::new_query_borrowed(query, &query_params, flags, version)
+}
+
+// returns rows of a token range page along with the paging state of the next page, if any
+fn token_range_page(response: Envelope) -> error::Result<(Vec<Row>, Option<CBytes>)> {
+    let body = response.response_body()?;
+
+    let metadata = body
+        .as_rows_metadata()
+        .ok_or("Token range query should yield a vector of rows")?;
+
+    let paging_state = if metadata.flags.contains(RowsMetadataFlags::HAS_MORE_PAGES) {
+        metadata.paging_state.clone()
+    } else {
+        None
+    };
+
+    let rows = body
+        .into_rows()
+        .ok_or("Token range query should yield a vector of rows")?;
+
+    Ok((rows, paging_state))
+}
+
+// fails the statement once given time limit expires - `send` is dropped by then, so `written`
+// tells if the statement might have been applied anyway
+async fn limit_statement_time<R>(
+    time_limit: Option<Duration>,
+    written: &AtomicBool,
+    statement: &str,
+    send: impl Future<Output = error::Result<R>>,
+) -> error::Result<R> {
+    match time_limit {
+        Some(time_limit) => timeout(time_limit, send).await.unwrap_or_else(|_| {
+            Err(error::Error::Timeout {
+                message: format!("{statement} not completed within {time_limit:?}"),
+                possibly_applied: written.load(Ordering::Relaxed),
+            })
+        }),
+        None => send.await,
+    }
+}
+
+// in fail fast mode, nodes without live connections are skipped as the plan is consumed, so
+// requests don't wait for reconnection, and fail if no nodes are left
+fn connected_nodes<T: CdrsTransport + 'static, CM: ConnectionManager<T> + 'static>(
+    query_plan: QueryPlan<T, CM>,
+    fail_fast: bool,
+) -> error::Result<impl Iterator<Item = Arc<Node<T, CM>>>> {
+    let mut query_plan = query_plan
+        .into_iter()
+        .filter(move |node| !fail_fast || !node.is_disconnected())
+        .peekable();
+
+    if fail_fast && query_plan.peek().is_none() {
+        debug!("No nodes with live connections, failing fast.");
+        return Err(error::Error::NoNodesAvailable);
+    }
+
+    Ok(query_plan)
+}
+
 #[inline]
-fn prepare_flags(with_tracing: bool, with_warnings: bool, beta_protocol: bool) -> Flags {
+pub(crate) fn prepare_flags(with_tracing: bool, with_warnings: bool, beta_protocol: bool) -> Flags {
     let mut flags = Flags::empty();
 
     if with_tracing {
@@ -88,6 +265,24 @@ fn prepare_flags(with_tracing: bool, with_warnings: bool, beta_protocol: bool) -
     flags
 }
 
+fn convert_query_params<'a>(
+    query_params: &'a QueryParams,
+    col_specs: &[ColSpec],
+) -> error::Result<Cow<'a, QueryParams>> {
+    let values = match &query_params.values {
+        Some(values) => convert_bound_values(values, col_specs)?,
+        None => return Ok(Cow::Borrowed(query_params)),
+    };
+
+    Ok(match values {
+        Cow::Borrowed(_) => Cow::Borrowed(query_params),
+        Cow::Owned(values) => Cow::Owned(QueryParams {
+            values: Some(values),
+            ..query_params.clone()
+        }),
+    })
+}
+
 fn create_keyspace_holder() -> (Arc<KeyspaceHolder>, watch::Receiver<Option<String>>) {
     let (keyspace_sender, keyspace_receiver) = watch::channel(None);
     (
@@ -96,14 +291,15 @@ fn create_keyspace_holder() -> (Arc<KeyspaceHolder>, watch::Receiver<Option<Stri
     )
 }
 
-fn verify_compression_configuration(
+fn verify_beta_protocol_configuration(
     version: Version,
-    compression: Compression,
+    beta_protocol: bool,
 ) -> Result<(), SessionBuildError> {
-    if version < Version::V5 || compression != Compression::Snappy {
+    // a stable version with the beta flag most likely means beta got enabled by accident
+    if beta_protocol == version.is_beta() {
         Ok(())
     } else {
-        Err(SessionBuildError::CompressionTypeNotSupported)
+        Err(SessionBuildError::BetaProtocolMismatch)
     }
 }
 
@@ -125,7 +321,7 @@ fn serialize_routing_value(cursor: &mut Cursor<&mut Vec<u8>>, value: &Vec<u8>, v
     let _ = cursor.write(&[0]);
 }
 
-fn serialize_routing_key_with_indexes(
+pub(crate) fn serialize_routing_key_with_indexes(
     values: &[Value],
     pk_indexes: &[i16],
     version: Version,
@@ -159,7 +355,7 @@ fn serialize_routing_key_with_indexes(
     }
 }
 
-fn serialize_routing_key(values: &[Value], version: Version) -> Vec<u8> {
+pub(crate) fn serialize_routing_key(values: &[Value], version: Version) -> Vec<u8> {
     match values.len() {
         0 => vec![],
         1 => match &values[0] {
@@ -183,22 +379,57 @@ fn serialize_routing_key(values: &[Value], version: Version) -> Vec<u8> {
 
 /// CDRS session that holds a pool of connections to nodes and provides an interface for
 /// interacting with the cluster.
+///
+/// Sessions are cheap to clone - all clones share the same state, including connection pools,
+/// current keyspace and cluster metadata, and can be freely used concurrently. Background tasks
+/// are stopped when the last clone gets dropped.
 #[derive(Derivative)]
-#[derivative(Debug)]
+#[derivative(Debug, Clone(bound = ""))]
 pub struct Session<
     T: CdrsTransport + 'static,
     CM: ConnectionManager<T> + 'static,
     LB: LoadBalancingStrategy<T, CM> + Send + Sync,
+> {
+    inner: Arc<SessionInner<T, CM, LB>>,
+}
+
+/// Session using plain TCP connections.
+pub type TcpSession<LB> = Session<TransportTcp, TcpConnectionManager, LB>;
+
+/// Session using plain TCP connections with a type-erased load balancing strategy.
+pub type DynTcpSession =
+    TcpSession<Box<dyn LoadBalancingStrategy<TransportTcp, TcpConnectionManager> + Send + Sync>>;
+
+/// Session using TLS connections.
+#[cfg(feature = "rust-tls")]
+pub type RustlsSession<LB> = Session<TransportRustls, RustlsConnectionManager, LB>;
+
+/// Session using TLS connections with a type-erased load balancing strategy.
+#[cfg(feature = "rust-tls")]
+pub type DynRustlsSession = RustlsSession<
+    Box<dyn LoadBalancingStrategy<TransportRustls, RustlsConnectionManager> + Send + Sync>,
+>;
+
+#[derive(Derivative)]
+#[derivative(Debug)]
+struct SessionInner<
+    T: CdrsTransport + 'static,
+    CM: ConnectionManager<T> + 'static,
+    LB: LoadBalancingStrategy<T, CM> + Send + Sync,
 > {
     #[derivative(Debug = "ignore")]
     load_balancing: Arc<InitializingWrapperLoadBalancingStrategy<T, CM, LB>>,
     keyspace_holder: Arc<KeyspaceHolder>,
     #[derivative(Debug = "ignore")]
     retry_policy: Box<dyn RetryPolicy + Send + Sync>,
+    retry_budget: Option<RetryBudget>,
     #[derivative(Debug = "ignore")]
     speculative_execution_policy: Option<Box<dyn SpeculativeExecutionPolicy + Send + Sync>>,
-    control_connection_handle: JoinHandle<()>,
+    task_tracker: Arc<TaskTracker>,
     event_sender: Sender<ServerEvent>,
+    registrations_sender: Sender<RegistrationsRestored>,
+    #[derivative(Debug = "ignore")]
+    reconnection_policy: Arc<dyn ReconnectionPolicy + Send + Sync>,
     #[derivative(Debug = "ignore")]
     cluster_metadata_manager: Arc<ClusterMetadataManager<T, CM>>,
     #[derivative(Debug = "ignore")]
@@ -206,16 +437,40 @@ pub struct Session<
     #[derivative(Debug = "ignore")]
     _connection_manager: PhantomData<CM>,
     version: Version,
+    lenient_conversions: bool,
+    keyspace_qualification_check: bool,
+    keyspace_lock: bool,
+    redactor: Redactor,
+    schema_agreement_timeout: Duration,
+    unprepared_routing: bool,
+    fail_fast_when_disconnected: bool,
+    strict_prepared_metadata: bool,
+    // node all schema statements are sent to, until it fails
+    schema_coordinator: Mutex<Option<SocketAddr>>,
+    #[derivative(Debug = "ignore")]
+    prepared_metadata_listener: Option<Arc<dyn PreparedMetadataListener + Send + Sync>>,
+    #[derivative(Debug = "ignore")]
+    reported_variables: ReportedVariables,
+    // configured mode - the one in effect depends on current compression
+    compression_mode: Option<CompressionMode>,
+    warning_policy: WarningPolicy,
+    tracing_sample_rate: f64,
+    options_probe: bool,
+    system_query_consistency: Consistency,
+    #[derivative(Debug = "ignore")]
+    prepared_cache: Arc<PreparedCache>,
+    #[derivative(Debug = "ignore")]
+    insert_statements: Mutex<FxHashMap<InsertStatementKey, Arc<PreparedQuery>>>,
 }
 
 impl<
         T: CdrsTransport + 'static,
         CM: ConnectionManager<T>,
         LB: LoadBalancingStrategy<T, CM> + Send + Sync,
-    > Drop for Session<T, CM, LB>
+    > Drop for SessionInner<T, CM, LB>
 {
     fn drop(&mut self) {
-        self.control_connection_handle.abort();
+        self.task_tracker.shutdown();
     }
 }
 
@@ -230,16 +485,59 @@ impl<
         SessionPager::new(self, page_size)
     }
 
+    /// Converts this session into a [`DynSession`], which erases generic parameters.
+    #[inline]
+    pub fn into_dyn(self) -> DynSession {
+        self.into()
+    }
+
     /// Executes given prepared query with query parameters.
+    #[inline]
     pub async fn exec_with_params(
         &self,
         prepared: &PreparedQuery,
         parameters: &StatementParams,
     ) -> error::Result<Envelope> {
+        self.exec_with_params_parsed(prepared, parameters)
+            .await
+            .map(ParsedResponse::into_envelope)
+    }
+
+    /// Executes given prepared query with query parameters, returning the response along with its
+    /// parsed body. Responses to prepared statements are parsed anyway to track changes of result
+    /// metadata, so this avoids parsing them again, e.g. to read rows.
+    #[inline]
+    pub async fn exec_with_params_parsed(
+        &self,
+        prepared: &PreparedQuery,
+        parameters: &StatementParams,
+    ) -> error::Result<ParsedResponse> {
+        self.exec_with_params_pinned(prepared, parameters, None)
+            .await
+    }
+
+    pub(crate) async fn exec_with_params_pinned(
+        &self,
+        prepared: &PreparedQuery,
+        parameters: &StatementParams,
+        pinned: Option<&PinnedConnection<T, CM>>,
+    ) -> error::Result<ParsedResponse> {
+        let written = AtomicBool::new(false);
+        let send = self.send_exec(prepared, parameters, pinned, &written);
+        limit_statement_time(parameters.timeout, &written, "Execution", send).await
+    }
+
+    async fn send_exec(
+        &self,
+        prepared: &PreparedQuery,
+        parameters: &StatementParams,
+        pinned: Option<&PinnedConnection<T, CM>>,
+        written: &AtomicBool,
+    ) -> error::Result<ParsedResponse> {
         let consistency = parameters.query_params.consistency;
         let flags = prepare_flags(
-            parameters.tracing,
-            parameters.warnings,
+            sample_tracing(parameters.tracing, self.inner.tracing_sample_rate),
+            parameters.warnings || self.inner.warning_policy.requires_warnings(),
             parameters.beta_protocol,
         );
 
@@ -249,38 +547,29 @@ impl<
             .as_ref()
             .map(|metadata| (**metadata).clone());
 
+        let query_params = if self.inner.lenient_conversions {
+            Cow::Borrowed(&parameters.query_params)
+        } else {
+            convert_query_params(&parameters.query_params, prepared.col_specs())?
+        };
+
         let envelope = Envelope::new_req_execute(
             &prepared.id,
             result_metadata_id.as_ref(),
-            &parameters.query_params,
+            &query_params,
             flags,
-            self.version,
-        );
+            self.inner.version,
+        )?;
 
         let keyspace = prepared
             .keyspace
             .as_deref()
             .or(parameters.keyspace.as_deref());
 
-        let routing_key = parameters
-            .query_params
-            .values
-            .as_ref()
-            .and_then(|values| match values {
-                QueryValues::SimpleValues(values) => {
-                    serialize_routing_key_with_indexes(values, &prepared.pk_indexes, self.version)
-                        .or_else(|| {
-                            parameters
-                                .routing_key
-                                .as_ref()
-                                .map(|values| serialize_routing_key(values, self.version))
-                        })
-                }
-                QueryValues::NamedValues(_) => None,
-            });
+        let routing_key = self.prepared_routing_key(prepared, &query_params, parameters);
 
         let mut result = self
-            .send_envelope(
+            .send_envelope_tracked(
                 envelope,
                 parameters.is_idempotent,
                 keyspace,
@@ -289,6 +578,8 @@ impl<
                 Some(consistency),
                 parameters.speculative_execution_policy.as_ref(),
                 parameters.retry_policy.as_ref(),
+                pinned,
+                Some(written),
             )
             .await;
 
@@ -299,6 +590,7 @@ impl<
 
                 // We need to send the prepare statement to the failing node.
                 let node = self
+                    .inner
                     .cluster_metadata_manager
                     .find_node_by_rpc_address(*addr)
                     .ok_or_else(|| {
@@ -307,11 +599,11 @@ impl<
                         ))
                     })?;
 
-                let prepare_envelope = Envelope::new_req_prepare(
-                    prepared.query.clone(),
-                    keyspace.map(|keyspace| keyspace.to_string()),
+                let prepare_envelope = Envelope::new_req_prepare_borrowed(
+                    &prepared.query,
+                    keyspace,
                     flags,
-                    self.version,
+                    self.inner.version,
                 );
 
                 let retry_policy = self.effective_retry_policy(parameters.retry_policy.as_ref());
@@ -319,7 +611,7 @@ impl<
                     [node].iter().cloned(),
                     &prepare_envelope,
                     true,
-                    retry_policy.new_session(),
+                    LazyRetrySession::new(retry_policy),
                 )
                 .await
                 .unwrap_or_else(|| Err("No response for re-prepare statement!".into()))
@@ -336,22 +628,37 @@ impl<
                         return Err("Re-preparing an unprepared statement resulted in a different id - probably schema changed on the server.".into());
                     }
 
-                    let flags = prepare_flags(
-                        parameters.tracing,
-                        parameters.warnings,
-                        parameters.beta_protocol,
+                    // the id stays the same when bound variables change, so positional values
+                    // might silently end up bound to other columns
+                    let positional = matches!(
+                        parameters.query_params.values,
+                        Some(QueryValues::SimpleValues(_))
                     );
+                    if check_variables_metadata(
+                        prepared,
+                        &new.metadata.col_specs,
+                        &self.inner.reported_variables,
+                        &self.inner.warning_policy,
+                        self.inner.prepared_metadata_listener.as_deref(),
+                        positional,
+                    )? && self.inner.strict_prepared_metadata
+                        && positional
+                    {
+                        return Err(error::Error::PreparedMetadataChanged {
+                            query: prepared.query.clone(),
+                        });
+                    }
 
                     let envelope = Envelope::new_req_execute(
                         &new.id,
                         new.result_metadata_id.as_ref(),
-                        &parameters.query_params,
+                        &query_params,
                         flags,
-                        self.version,
-                    );
+                        self.inner.version,
+                    )?;
 
                     result = self
-                        .send_envelope(
+                        .send_envelope_tracked(
                             envelope,
                             parameters.is_idempotent,
                             keyspace,
@@ -360,30 +667,152 @@ impl<
                             Some(consistency),
                             parameters.speculative_execution_policy.as_ref(),
                             parameters.retry_policy.as_ref(),
+                            pinned,
+                            Some(written),
                         )
                         .await;
                 }
             }
         }
 
-        let response = result
+        self.log_failed_statement(
+            &result,
+            &prepared.query,
+            parameters.query_params.values.as_ref(),
+            Some(prepared.col_specs()),
+        );
+
+        let response = result?.into_parsed_response()?;
+        update_result_metadata_id(
+            prepared,
+            response.body(),
+            self.inner.prepared_metadata_listener.as_deref(),
+        );
+
+        self.inner
+            .warning_policy
+            .check(response.warnings(), &prepared.query)?;
+
+        Ok(response)
+    }
+
+    /// Executes given prepared query with a blob bound as the last value, following positional
+    /// values from given parameters. The blob is read in chunks fitting a single frame, which are
+    /// written as they are read, without holding the blob in memory. The request fails if the
+    /// reader fails, or stalls for longer than its [read timeout](StreamedBlob::with_read_timeout) -
+    /// if that happens after the first chunk has been written, the connection is closed too.
+    ///
+    /// Since the blob can only be read once, the request is sent to the first node which provides
+    /// a connection, without retries, speculative executions or re-preparing unprepared
+    /// statements. Before protocol V5, streamed requests are sent uncompressed, since the body
+    /// can't be compressed without reading it as a whole - since V5, frames are compressed as
+    /// they are written.
+    pub async fn exec_with_blob(
+        &self,
+        prepared: &PreparedQuery,
+        parameters: &StatementParams,
+        blob: StreamedBlob,
+    ) -> error::Result<Envelope> {
+        let mut values = match &parameters.query_params.values {
+            Some(QueryValues::SimpleValues(values)) => values.clone(),
+            Some(QueryValues::NamedValues(_)) => {
+                return Err("Blobs can only be streamed with positional values!".into())
+            }
+            None => vec![],
+        };
+
+        // empty placeholder, which gets the blob length and data inserted later
+        values.push(Value::Some(vec![]));
+
+        let query_params = QueryParams {
+            values: Some(QueryValues::SimpleValues(values)),
+            with_names: false,
+            ..parameters.query_params.clone()
+        };
+
+        let query_params = if self.inner.lenient_conversions {
+            Cow::Borrowed(&query_params)
+        } else {
+            convert_query_params(&query_params, prepared.col_specs())?
+        };
+
+        let consistency = query_params.consistency;
+        let flags = prepare_flags(
+            sample_tracing(parameters.tracing, self.inner.tracing_sample_rate),
+            parameters.warnings || self.inner.warning_policy.requires_warnings(),
+            parameters.beta_protocol,
+        );
+
+        let result_metadata_id = prepared
+            .result_metadata_id
+            .load()
             .as_ref()
-            .map_err(|error| error.clone())
-            .and_then(|result| result.response_body());
+            .map(|metadata| (**metadata).clone());
 
-        let new_metadata_id = response.as_ref().map(|result| {
-            result
-                .as_rows_metadata()
-                .and_then(|metadata| metadata.new_metadata_id.as_ref())
-        });
+        let mut envelope = Envelope::new_req_execute(
+            &prepared.id,
+            result_metadata_id.as_ref(),
+            &query_params,
+            flags,
+            self.inner.version,
+        )?;
+
+        // query parameters are serialized at the end of the body, right after values
+        let offset = envelope.body.len() - query_params.trailing_len(self.inner.version);
+        envelope.body[(offset - INT_LEN)..offset]
+            .copy_from_slice(&(blob.len() as i32).to_be_bytes());
+
+        let keyspace = prepared
+            .keyspace
+            .as_deref()
+            .or(parameters.keyspace.as_deref());
+
+        let routing_key = self.prepared_routing_key(prepared, &query_params, parameters);
+
+        let current_keyspace = self.current_keyspace();
+        let request = Request::new(
+            keyspace.or_else(|| current_keyspace.as_ref().map(|keyspace| &***keyspace)),
+            parameters.token,
+            routing_key.as_deref(),
+            Some(consistency),
+        );
 
-        if let Ok(Some(new_metadata_id)) = new_metadata_id {
-            prepared
-                .result_metadata_id
-                .swap(Some(Arc::new(new_metadata_id.clone())));
+        let mut last_error = None;
+        for node in self.connected_query_plan(Some(request))? {
+            let transport = match node.persistent_connection().await {
+                Ok(transport) => transport,
+                Err(error) => {
+                    debug!(%error, "Cannot get connection to node, trying next node.");
+                    last_error = Some(error);
+                    continue;
+                }
+            };
+
+            let start = Instant::now();
+            let result = transport
+                .write_envelope_with_blob(&envelope, offset, blob)
+                .await;
+
+            self.inner
+                .load_balancing
+                .on_request_completed(&node, start.elapsed(), &result);
+
+            if let Ok(response) = result
+                .as_ref()
+                .map_err(|error| error.clone())
+                .and_then(|result| result.response_body())
+            {
+                update_result_metadata_id(
+                    prepared,
+                    &response,
+                    self.inner.prepared_metadata_listener.as_deref(),
+                );
+            }
+
+            return self.inner.warning_policy.apply(result, &prepared.query);
         }
 
-        result
+        Err(last_error.unwrap_or_else(|| "No nodes available in query plan!".into()))
     }
 
     /// Executes given prepared query with query values.
@@ -401,18 +830,92 @@ impl<
         .await
     }
 
-    /// Executes given prepared query.
+    /// Executes given prepared query. Statement parameters can be overridden on the returned
+    /// [`StatementRequest`] before awaiting it.
     #[inline]
-    pub async fn exec(&self, prepared: &PreparedQuery) -> error::Result<Envelope> {
-        self.exec_with_params(prepared, &DEFAULT_STATEMENT_PARAMETERS)
-            .await
+    pub fn exec<'a>(&'a self, prepared: &'a PreparedQuery) -> StatementRequest<'a> {
+        StatementRequest::prepared(self, prepared)
+    }
+
+    /// Executes given prepared query once for every set of values, with at most `concurrency`
+    /// executions in flight. See [`Session::execute_concurrent_with_params`] for details.
+    #[inline]
+    pub async fn execute_concurrent<I>(
+        &self,
+        prepared: &PreparedQuery,
+        values: I,
+        concurrency: usize,
+    ) -> Vec<error::Result<Envelope>>
+    where
+        I: IntoIterator,
+        I::Item: Into<QueryValues>,
+    {
+        self.execute_concurrent_with_params(
+            prepared,
+            values,
+            &DEFAULT_STATEMENT_PARAMETERS,
+            concurrency,
+        )
+        .await
+    }
+
+    /// Executes given prepared query once for every set of values, with at most `concurrency`
+    /// executions in flight. Values replace the ones in given parameters, which apply to all
+    /// executions, and every execution is retried separately according to the retry policy.
+    /// Returns results in input order.
+    ///
+    /// Values are consumed lazily, so the input can be larger than memory, as long as the results
+    /// fit. Dropping the returned future abandons executions in flight and doesn't start new ones.
+    pub async fn execute_concurrent_with_params<I>(
+        &self,
+        prepared: &PreparedQuery,
+        values: I,
+        parameters: &StatementParams,
+        concurrency: usize,
+    ) -> Vec<error::Result<Envelope>>
+    where
+        I: IntoIterator,
+        I::Item: Into<QueryValues>,
+    {
+        let executions = values.into_iter().map(|values| {
+            let mut parameters = parameters.clone();
+            parameters.query_params.values = Some(values.into());
+            async move { self.exec_with_params(prepared, &parameters).await }
+        });
+
+        execute_in_order(executions, concurrency).await
+    }
+
+    /// Executes given prepared query once for every set of values, like
+    /// [`Session::execute_concurrent_with_params`], but only returns the number of successful
+    /// executions, or the errors, depending on `mode`. Results of successful executions are
+    /// discarded, so this is suitable for bulk loading data of any size.
+    pub async fn execute_concurrent_summarized<I>(
+        &self,
+        prepared: &PreparedQuery,
+        values: I,
+        parameters: &StatementParams,
+        concurrency: usize,
+        mode: ConcurrentErrorMode,
+    ) -> Result<usize, ConcurrentExecutionError>
+    where
+        I: IntoIterator,
+        I::Item: Into<QueryValues>,
+    {
+        let executions = values.into_iter().enumerate().map(|(index, values)| {
+            let mut parameters = parameters.clone();
+            parameters.query_params.values = Some(values.into());
+            async move { (index, self.exec_with_params(prepared, &parameters).await) }
+        });
+
+        execute_summarized(executions, concurrency, mode).await
     }
 
     /// Prepares a query for execution. Along with query itself, the
     /// method takes `with_tracing` and `with_warnings` flags to get
     /// tracing information and warnings. Returns the raw prepared
     /// query result.
-    pub async fn prepare_raw_tw<Q: ToString>(
+    pub async fn prepare_raw_tw<'a, Q: Into<Cow<'a, str>>>(
         &self,
         query: Q,
         keyspace: Option<String>,
@@ -420,28 +923,69 @@ impl<
         with_warnings: bool,
         beta_protocol: bool,
     ) -> error::Result<BodyResResultPrepared> {
-        let flags = prepare_flags(with_tracing, with_warnings, beta_protocol);
+        self.prepare_query(
+            query.into().into_owned(),
+            keyspace,
+            prepare_flags(with_tracing, with_warnings, beta_protocol),
+        )
+        .await
+    }
+
+    async fn prepare_query(
+        &self,
+        query: String,
+        keyspace: Option<String>,
+        flags: Flags,
+    ) -> error::Result<BodyResResultPrepared> {
+        self.check_keyspace_lock(&query)?;
+        self.check_keyspace_qualification(&query, keyspace.as_deref());
 
-        let envelope = Envelope::new_req_prepare(query.to_string(), keyspace, flags, self.version);
+        let envelope = Envelope::new_req_prepare_borrowed(
+            &query,
+            keyspace.as_deref(),
+            flags,
+            self.inner.version,
+        );
 
         self.send_envelope(envelope, true, None, None, None, None, None, None)
             .await
             .and_then(|response| response.response_body())
             .and_then(convert_to_prepared)
+            .and_then(|prepared| {
+                if let Some(previous) = self.inner.prepared_cache.insert(query, keyspace, &prepared)
+                {
+                    check_variables_metadata(
+                        &previous,
+                        &prepared.metadata.col_specs,
+                        &self.inner.reported_variables,
+                        &self.inner.warning_policy,
+                        self.inner.prepared_metadata_listener.as_deref(),
+                        // preparing binds no values
+                        false,
+                    )?;
+                }
+
+                Ok(prepared)
+            })
     }
 
     /// Prepares query without additional tracing information and warnings.
     /// Returns the raw prepared query result.
     #[inline]
-    pub async fn prepare_raw<Q: ToString>(&self, query: Q) -> error::Result<BodyResResultPrepared> {
+    pub async fn prepare_raw<'a, Q: Into<Cow<'a, str>>>(
+        &self,
+        query: Q,
+    ) -> error::Result<BodyResResultPrepared> {
         self.prepare_raw_tw(query, None, false, false, false).await
     }
 
     /// Prepares a query for execution. Along with query itself,
     /// the method takes `with_tracing` and `with_warnings` flags
     /// to get tracing information and warnings. Returns the prepared
-    /// query.
-    pub async fn prepare_tw<Q: ToString>(
+    /// query. Statements imported with
+    /// [`SessionBuilder::with_prepared_cache_snapshot`] are returned without contacting the
+    /// cluster.
+    pub async fn prepare_tw<'a, Q: Into<Cow<'a, str>>>(
         &self,
         query: Q,
         keyspace: Option<String>,
@@ -449,78 +993,255 @@ impl<
         with_warnings: bool,
         beta_protocol: bool,
     ) -> error::Result<PreparedQuery> {
-        let s = query.to_string();
-        self.prepare_raw_tw(query, keyspace, with_tracing, with_warnings, beta_protocol)
-            .await
-            .map(|result| PreparedQuery {
-                id: result.id,
-                query: s,
-                keyspace: result
-                    .metadata
-                    .global_table_spec
-                    .map(|TableSpec { ks_name, .. }| ks_name),
-                pk_indexes: result.metadata.pk_indexes,
-                result_metadata_id: ArcSwapOption::new(result.result_metadata_id.map(Arc::new)),
-            })
+        let query = query.into();
+        if let Some(prepared) = self
+            .inner
+            .prepared_cache
+            .imported(&query, keyspace.as_deref())
+        {
+            return Ok(prepared);
+        }
+
+        let query = query.into_owned();
+
+        self.prepare_query(
+            query.clone(),
+            keyspace,
+            prepare_flags(with_tracing, with_warnings, beta_protocol),
+        )
+        .await
+        .map(|result| {
+            into_prepared_query(query, result.id, result.result_metadata_id, result.metadata)
+        })
     }
 
     /// It prepares query without additional tracing information and warnings.
     /// Returns the prepared query.
     #[inline]
-    pub async fn prepare<Q: ToString>(&self, query: Q) -> error::Result<PreparedQuery> {
+    pub async fn prepare<'a, Q: Into<Cow<'a, str>>>(
+        &self,
+        query: Q,
+    ) -> error::Result<PreparedQuery> {
         self.prepare_tw(query, None, false, false, false).await
     }
 
-    /// Executes batch query.
+    /// Returns statements prepared by this session, along with the ones imported and not prepared
+    /// again. The snapshot can be persisted and imported by a new session with
+    /// [`SessionBuilder::with_prepared_cache_snapshot`], to avoid preparing the same statements
+    /// again, e.g. after a deployment.
+    pub fn prepared_cache_snapshot(&self) -> PreparedCacheSnapshot {
+        self.inner.prepared_cache.snapshot()
+    }
+
+    /// Prepares multiple queries concurrently, with at most [`DEFAULT_PREPARE_CONCURRENCY`]
+    /// being prepared at the same time. Returns prepared queries in input order, or errors for all
+    /// queries which failed to prepare.
+    #[inline]
+    pub async fn prepare_all<Q: AsRef<str>>(
+        &self,
+        queries: &[Q],
+    ) -> Result<Vec<PreparedQuery>, PrepareAllError> {
+        self.prepare_all_with_concurrency(queries, DEFAULT_PREPARE_CONCURRENCY)
+            .await
+    }
+
+    /// Prepares multiple queries concurrently, with at most `concurrency` being prepared at the
+    /// same time. Each query is sent according to its own query plan, so the load is spread
+    /// across nodes by the load balancing strategy. Returns prepared queries in input order, or
+    /// errors for all queries which failed to prepare.
+    pub async fn prepare_all_with_concurrency<Q: AsRef<str>>(
+        &self,
+        queries: &[Q],
+        concurrency: usize,
+    ) -> Result<Vec<PreparedQuery>, PrepareAllError> {
+        let statements = queries
+            .iter()
+            .map(|query| {
+                let query = query.as_ref();
+                (query.to_string(), self.prepare(query))
+            })
+            .collect();
+
+        prepare_concurrently(statements, concurrency).await
+    }
+
+    /// Executes batch query. The batch can be passed by reference, so it can be executed again
+    /// without cloning.
     #[inline]
-    pub async fn batch(&self, batch: QueryBatch) -> error::Result<Envelope> {
+    pub async fn batch<B: Borrow<QueryBatch>>(&self, batch: B) -> error::Result<Envelope> {
         self.batch_with_params(batch, &DEFAULT_STATEMENT_PARAMETERS)
             .await
     }
 
-    /// Executes batch query with parameters.
-    pub async fn batch_with_params(
+    /// Executes batch query with parameters. If the parameters have a timeout, the batch fails with
+    /// [`Error::Timeout`](error::Error::Timeout) once the timeout expires. The error tells if the
+    /// batch has already been written, in which case the server might still apply it, so only
+    /// idempotent batches should be re-run.
+    pub async fn batch_with_params<B: Borrow<QueryBatch>>(
+        &self,
+        batch: B,
+        parameters: &StatementParams,
+    ) -> error::Result<Envelope> {
+        self.send_batch(
+            batch.borrow(),
+            parameters,
+            parameters.keyspace.as_deref(),
+            None,
+            None,
+        )
+        .await
+    }
+
+    /// Splits given statements into unlogged sub-batches, one per partition, and sends them
+    /// concurrently, with at most the configured number in flight. Multi-partition batches put a
+    /// heavy load on their coordinator, while sub-batches are routed directly to replicas of their
+    /// partitions.
+    ///
+    /// Partitions are determined from the keyspace and table of statements, along with bound
+    /// partition key values of prepared statements or explicit routing keys. Entries without
+    /// routing information are sent together in a single fallback sub-batch. Partitions with more statements than the configured limit are sent as
+    /// multiple sub-batches. Returns results of sub-batches in order of first appearance of their
+    /// partitions. Since sub-batches are sent separately, some can be applied while others fail.
+    pub async fn batch_split_by_partition<'a, I>(
+        &self,
+        entries: I,
+        options: &BatchSplitOptions,
+    ) -> Vec<PartitionBatchResult>
+    where
+        I: IntoIterator<Item = BatchEntry<'a>>,
+    {
+        let groups = group_by_partition(entries, options.max_group_statements, self.inner.version);
+
+        let executions = groups.into_iter().map(|group| async move {
+            let batch = QueryBatch {
+                batch_type: BatchType::Unlogged,
+                queries: group.queries,
+                consistency: options.consistency,
+                serial_consistency: options.serial_consistency,
+                timestamp: options.timestamp,
+                keyspace: options.parameters.keyspace.clone(),
+                now_in_seconds: None,
+            };
+
+            let keyspace = group
+                .keyspace
+                .as_deref()
+                .or(options.parameters.keyspace.as_deref());
+
+            let result = self
+                .send_batch(
+                    &batch,
+                    &options.parameters,
+                    keyspace,
+                    group.routing_key.as_deref(),
+                    None,
+                )
+                .await;
+
+            PartitionBatchResult {
+                routing_key: group.routing_key,
+                indexes: group.indexes,
+                result,
+            }
+        });
+
+        stream::iter(executions)
+            .buffered(options.concurrency.max(1))
+            .collect()
+            .await
+    }
+
+    pub(crate) async fn send_batch(
         &self,
-        batch: QueryBatch,
+        batch: &QueryBatch,
         parameters: &StatementParams,
+        keyspace: Option<&str>,
+        routing_key: Option<&[u8]>,
+        pinned: Option<&PinnedConnection<T, CM>>,
     ) -> error::Result<Envelope> {
         let flags = prepare_flags(
-            parameters.tracing,
-            parameters.warnings,
+            sample_tracing(parameters.tracing, self.inner.tracing_sample_rate),
+            parameters.warnings || self.inner.warning_policy.requires_warnings(),
             parameters.beta_protocol,
         );
 
         let consistency = batch.consistency;
 
-        let envelope = Envelope::new_req_batch(batch, flags, self.version);
+        let envelope = Envelope::new_req_batch_ref(batch, flags, self.inner.version)?;
 
-        self.send_envelope(
+        let written = AtomicBool::new(false);
+        let send = self.send_envelope_tracked(
             envelope,
             parameters.is_idempotent,
-            parameters.keyspace.as_deref(),
-            None,
+            keyspace,
             None,
+            routing_key,
             Some(consistency),
             parameters.speculative_execution_policy.as_ref(),
             parameters.retry_policy.as_ref(),
-        )
-        .await
+            pinned,
+            Some(&written),
+        );
+
+        let result = limit_statement_time(parameters.timeout, &written, "Batch", send).await;
+
+        self.inner.warning_policy.apply(result, "BATCH")
     }
 
-    /// Executes a query.
-    #[inline]
-    pub async fn query<Q: ToString>(&self, query: Q) -> error::Result<Envelope> {
-        self.query_with_params(query, DEFAULT_STATEMENT_PARAMETERS.clone())
-            .await
+    /// Returns a handle scoped to given keyspace, which sends the keyspace with each request
+    /// instead of relying on `USE`. Handles share connections and prepared statements of this
+    /// session, and are cheap to create, e.g. per request in multi-tenant applications with a
+    /// keyspace per tenant. Requires protocol V5 or newer.
+    pub fn with_keyspace<'a, K: Into<Cow<'a, str>>>(
+        &'a self,
+        keyspace: K,
+    ) -> error::Result<KeyspaceSession<'a, T, CM, LB>> {
+        if self.inner.version < Version::V5 {
+            return Err(error::Error::General(
+                "Keyspace-scoped requests require protocol V5 or newer!".into(),
+            ));
+        }
+
+        Ok(KeyspaceSession::new(self, keyspace.into()))
     }
 
-    /// Executes a query with bounded values (either with or without names).
-    #[inline]
-    pub async fn query_with_values<Q: ToString, V: Into<QueryValues>>(
-        &self,
-        query: Q,
-        values: V,
-    ) -> error::Result<Envelope> {
+    /// Returns a handle which sends all statements over a single connection, selected up front
+    /// using the load balancing strategy, so consecutive statements have the same coordinator,
+    /// e.g. for read-after-write patterns in migrations or interactive shells. If the connection
+    /// dies, statements fall back to regular load balancing. Other requests of this session are
+    /// not affected. See [`PinnedSession`] for details.
+    pub async fn pinned(&self) -> error::Result<PinnedSession<'_, T, CM, LB>> {
+        let mut last_error = None;
+        for node in self.query_plan(None) {
+            match node.persistent_connection().await {
+                Ok(transport) if !transport.is_broken() => {
+                    return Ok(PinnedSession::new(
+                        self,
+                        PinnedConnection::new(node, transport),
+                    ));
+                }
+                Ok(_) => {}
+                Err(error) => last_error = Some(error),
+            }
+        }
+
+        Err(last_error.unwrap_or_else(|| "No nodes available in query plan!".into()))
+    }
+
+    /// Executes a query. Statement parameters can be overridden on the returned
+    /// [`StatementRequest`] before awaiting it.
+    #[inline]
+    pub fn query<'a, Q: Into<Cow<'a, str>>>(&'a self, query: Q) -> StatementRequest<'a> {
+        StatementRequest::query(self, query.into())
+    }
+
+    /// Executes a query with bounded values (either with or without names).
+    #[inline]
+    pub async fn query_with_values<'a, Q: Into<Cow<'a, str>>, V: Into<QueryValues>>(
+        &self,
+        query: Q,
+        values: V,
+    ) -> error::Result<Envelope> {
         self.query_with_params(
             query,
             StatementParamsBuilder::new()
@@ -530,35 +1251,81 @@ impl<
         .await
     }
 
-    /// Executes a query with query parameters.
-    pub async fn query_with_params<Q: ToString>(
+    /// Executes a query with query parameters. The query text is only borrowed, so passing
+    /// string literals doesn't allocate.
+    #[inline]
+    pub async fn query_with_params<'a, Q: Into<Cow<'a, str>>>(
+        &self,
+        query: Q,
+        parameters: StatementParams,
+    ) -> error::Result<Envelope> {
+        self.query_with_params_pinned(query, parameters, None).await
+    }
+
+    pub(crate) async fn query_with_params_pinned<'a, Q: Into<Cow<'a, str>>>(
         &self,
         query: Q,
         parameters: StatementParams,
+        pinned: Option<&PinnedConnection<T, CM>>,
     ) -> error::Result<Envelope> {
         let is_idempotent = parameters.is_idempotent;
+        let query = query.into();
+        self.check_keyspace_lock(&query)?;
+        self.check_keyspace_qualification(
+            &query,
+            parameters
+                .keyspace
+                .as_deref()
+                .or(parameters.query_params.keyspace.as_deref()),
+        );
+
+        if !parameters.skip_named_values_validation {
+            verify_named_values(
+                &query,
+                parameters.query_params.values.as_ref(),
+                parameters.query_params.with_names,
+            )?;
+        }
+
         let consistency = parameters.query_params.consistency;
-        let keyspace = parameters.keyspace;
+        let mut keyspace = parameters.keyspace;
         let token = parameters.token;
-        let routing_key = parameters
+        let mut routing_key = parameters
             .routing_key
             .as_ref()
-            .map(|values| serialize_routing_key(values, self.version));
-
-        let query = BodyReqQuery {
-            query: query.to_string(),
-            query_params: parameters.query_params,
-        };
+            .map(|values| serialize_routing_key(values, self.inner.version));
+
+        if self.inner.unprepared_routing && token.is_none() && routing_key.is_none() {
+            if let Some((table_keyspace, key)) = self
+                .unprepared_routing_key(
+                    &query,
+                    keyspace
+                        .as_deref()
+                        .or(parameters.query_params.keyspace.as_deref()),
+                    parameters.query_params.values.as_ref(),
+                )
+                .await
+            {
+                keyspace = Some(table_keyspace);
+                routing_key = Some(key);
+            }
+        }
 
         let flags = prepare_flags(
-            parameters.tracing,
-            parameters.warnings,
+            sample_tracing(parameters.tracing, self.inner.tracing_sample_rate),
+            parameters.warnings || self.inner.warning_policy.requires_warnings(),
             parameters.beta_protocol,
         );
 
-        let envelope = Envelope::new_query(query, flags, self.version);
+        let envelope = Envelope::new_query_borrowed(
+            &query,
+            &parameters.query_params,
+            flags,
+            self.inner.version,
+        )?;
 
-        self.send_envelope(
+        let written = AtomicBool::new(false);
+        let send = self.send_envelope_tracked(
             envelope,
             is_idempotent,
             keyspace.as_deref(),
@@ -567,43 +1334,752 @@ impl<
             Some(consistency),
             parameters.speculative_execution_policy.as_ref(),
             parameters.retry_policy.as_ref(),
-        )
+            pinned,
+            Some(&written),
+        );
+
+        let result = limit_statement_time(parameters.timeout, &written, "Query", send).await;
+
+        self.log_failed_statement(
+            &result,
+            &query,
+            parameters.query_params.values.as_ref(),
+            None,
+        );
+
+        self.inner.warning_policy.apply(result, &query)
+    }
+
+    // returns the keyspace of the statement table along with the routing key, if partition key
+    // values can be identified
+    async fn unprepared_routing_key(
+        &self,
+        query: &str,
+        keyspace: Option<&str>,
+        values: Option<&QueryValues>,
+    ) -> Option<(String, Vec<u8>)> {
+        let values = match values {
+            Some(QueryValues::SimpleValues(values)) => values,
+            _ => return None,
+        };
+
+        let analysis = StatementAnalysis::analyze(query)?;
+        let current_keyspace = self.current_keyspace();
+        let keyspace = analysis
+            .keyspace()
+            .or(keyspace)
+            .or_else(|| current_keyspace.as_ref().map(|keyspace| keyspace.as_str()))?;
+
+        let partition_key = self
+            .inner
+            .cluster_metadata_manager
+            .partition_key(keyspace, analysis.table())
+            .await?;
+
+        let pk_indexes = analysis
+            .partition_key_markers(&partition_key)?
+            .into_iter()
+            .map(i16::try_from)
+            .collect::<Result<Vec<_>, _>>()
+            .ok()?;
+
+        serialize_routing_key_with_indexes(values, &pk_indexes, self.inner.version)
+            .map(|routing_key| (keyspace.to_string(), routing_key))
+    }
+
+    /// Executes a query with query parameters, like [`Session::query_with_params`], returning a
+    /// handle which can cancel it. The query only runs when the returned future is polled.
+    /// Cancelling resolves the future with [`Error::Cancelled`](error::Error::Cancelled) - see
+    /// [`QueryHandle`] for details.
+    pub fn query_cancellable<'a, Q: Into<Cow<'a, str>>>(
+        &'a self,
+        query: Q,
+        parameters: StatementParams,
+    ) -> (
+        QueryHandle,
+        impl Future<Output = error::Result<Envelope>> + Send + 'a,
+    ) {
+        let query = query.into();
+        cancellable(self.query_with_params(query, parameters))
+    }
+
+    /// Executes a schema altering statement, e.g. `CREATE TABLE`, and waits until all nodes, which
+    /// are not known to be down, agree on the resulting schema. All schema statements are sent to
+    /// the same coordinator, which is kept until it goes down or a statement fails for reasons
+    /// other than the statement itself, so statements of a migration are applied in order and
+    /// don't cause conflicting schema versions. Returns the schema change made, or `None` if
+    /// nothing changed, e.g. for `CREATE TABLE IF NOT EXISTS` with an existing table. If nodes
+    /// don't agree within the time set with `with_schema_agreement_timeout()`,
+    /// [`Error::Timeout`](error::Error::Timeout) is returned, although the change has been applied.
+    pub async fn execute_schema<'a, Q: Into<Cow<'a, str>>>(
+        &self,
+        query: Q,
+    ) -> error::Result<Option<SchemaChange>> {
+        let query = query.into();
+        let node = self.schema_coordinator()?;
+        let envelope = Envelope::new_query_borrowed(
+            &query,
+            &QueryParams::default(),
+            prepare_flags(false, self.inner.warning_policy.requires_warnings(), false),
+            self.inner.version,
+        )?;
+
+        let result = self.send_to_node(&node, &envelope).await;
+        if let Err(error) = &result {
+            if !is_statement_error(error) {
+                self.inner.schema_coordinator.lock().unwrap().take();
+            }
+        }
+
+        let schema_change = self
+            .inner
+            .warning_policy
+            .apply(result, &query)?
+            .response_body()?
+            .into_schema_change();
+
+        if schema_change.is_some() {
+            self.wait_for_schema_agreement().await?;
+        }
+
+        Ok(schema_change)
+    }
+
+    // reuses the current coordinator of schema statements while it's up
+    fn schema_coordinator(&self) -> error::Result<Arc<Node<T, CM>>> {
+        let mut coordinator = self.inner.schema_coordinator.lock().unwrap();
+
+        let node = coordinator
+            .and_then(|address| {
+                self.inner
+                    .cluster_metadata_manager
+                    .find_node_by_rpc_address(address)
+            })
+            .filter(|node| !matches!(node.state(), NodeState::Down | NodeState::ForcedDown));
+        if let Some(node) = node {
+            return Ok(node);
+        }
+
+        let node = self.query_plan(None).into_iter().next().ok_or_else(|| {
+            error::Error::from("No nodes available to execute schema statements!")
+        })?;
+
+        debug!(address = %node.broadcast_rpc_address(), "Selected schema statement coordinator.");
+        *coordinator = Some(node.broadcast_rpc_address());
+        Ok(node)
+    }
+
+    async fn wait_for_schema_agreement(&self) -> error::Result<()> {
+        timeout(self.inner.schema_agreement_timeout, async {
+            while !self
+                .inner
+                .cluster_metadata_manager
+                .check_schema_agreement()
+                .await?
+            {
+                sleep(SCHEMA_AGREEMENT_INTERVAL).await;
+            }
+
+            Ok(())
+        })
         .await
+        .map_err(|_| error::Error::Timeout {
+            message: "Timeout waiting for schema agreement".into(),
+            possibly_applied: true,
+        })
+        .and_then(|result| result)
+    }
+
+    /// Counts rows using given `SELECT count(*) ...` query, or counts all rows in given table, if
+    /// the argument is a single, optionally keyspace-qualified, table name. Counting all rows in
+    /// a table requires a full scan, so it should be used with care.
+    pub async fn count(&self, query_or_table: &str) -> error::Result<u64> {
+        let count: i64 = self
+            .query(count_query(query_or_table))
+            .await?
+            .response_body()?
+            .into_scalar()?;
+        u64::try_from(count).map_err(|_| format!("Invalid row count: {count}").into())
+    }
+
+    /// Creates a [`PartitionGrouper`] for given table, with partition key columns read from the
+    /// schema. The keyspace and table of received rows are available via [`Row::table_spec`].
+    pub async fn partition_grouper(
+        &self,
+        keyspace: &str,
+        table: &str,
+    ) -> error::Result<PartitionGrouper> {
+        self.partition_key_columns(keyspace, table)
+            .await
+            .map(PartitionGrouper::new)
+    }
+
+    /// Reads given columns of all rows in given table, token range by token range, with at most
+    /// `parallelism` ranges read at the same time. Each range is queried with
+    /// `token(partition key) > start AND token(partition key) <= end` directly on its replicas, in
+    /// order, starting with nodes not known to be down, with failures handled by the session
+    /// retry policy. Ranges are paged separately and rows are returned in no particular order.
+    /// A range which fails on all replicas yields an error and ends, while remaining ranges are
+    /// still read. Intended for full table exports, where touching each replica only for data it
+    /// owns is much cheaper than paging through a single query.
+    #[inline]
+    pub async fn token_range_scan(
+        &self,
+        keyspace: &str,
+        table: &str,
+        columns: &[&str],
+        parallelism: usize,
+    ) -> error::Result<BoxStream<'_, error::Result<Row>>> {
+        self.token_range_scan_with_params(keyspace, table, columns, parallelism, Default::default())
+            .await
+    }
+
+    /// Like [`Session::token_range_scan`], but queries ranges with given parameters, e.g.
+    /// consistency, retry policy or page size. Bound values and paging state are set for each
+    /// range, and range queries are always idempotent.
+    pub async fn token_range_scan_with_params(
+        &self,
+        keyspace: &str,
+        table: &str,
+        columns: &[&str],
+        parallelism: usize,
+        parameters: StatementParams,
+    ) -> error::Result<BoxStream<'_, error::Result<Row>>> {
+        let partition_key = self
+            .partition_key_columns(keyspace, table)
+            .await?
+            .iter()
+            .map(|column| quote_identifier(column))
+            .join(", ");
+
+        let columns = if columns.is_empty() {
+            "*".to_string()
+        } else {
+            columns
+                .iter()
+                .map(|column| quote_identifier(column))
+                .join(", ")
+        };
+
+        let query: Arc<str> = format!(
+            "SELECT {columns} FROM {}.{} WHERE token({partition_key}) > ? AND token({partition_key}) <= ?",
+            quote_identifier(keyspace),
+            quote_identifier(table),
+        )
+        .into();
+
+        let keyspace: Arc<str> = keyspace.into();
+        let parameters = Arc::new(parameters);
+        let ranges = self.cluster_metadata().token_map().token_ranges();
+
+        Ok(stream::iter(ranges)
+            .map(move |range| {
+                self.token_range_pages(query.clone(), keyspace.clone(), parameters.clone(), range)
+            })
+            .flatten_unordered(parallelism.max(1))
+            .map_ok(|rows| stream::iter(rows.into_iter().map(Ok)))
+            .try_flatten()
+            .boxed())
+    }
+
+    async fn partition_key_columns(
+        &self,
+        keyspace: &str,
+        table: &str,
+    ) -> error::Result<Vec<String>> {
+        let partition_key = self
+            .inner
+            .cluster_metadata_manager
+            .fetch_partition_key(keyspace, table)
+            .await?;
+
+        if partition_key.is_empty() {
+            return Err(error::Error::General(format!(
+                "Unknown table or missing partition key: {keyspace}.{table}"
+            )));
+        }
+
+        Ok(partition_key)
+    }
+
+    // yields pages of rows in given range until there are no more or a page fails
+    fn token_range_pages(
+        &self,
+        query: Arc<str>,
+        keyspace: Arc<str>,
+        parameters: Arc<StatementParams>,
+        range: TokenRange,
+    ) -> BoxStream<'_, error::Result<Vec<Row>>> {
+        stream::unfold(Some(None), move |paging_state| {
+            let query = query.clone();
+            let keyspace = keyspace.clone();
+            let parameters = parameters.clone();
+
+            async move {
+                match self
+                    .fetch_token_range_page(&query, &keyspace, &parameters, range, paging_state?)
+                    .await
+                {
+                    Ok((rows, paging_state)) => Some((Ok(rows), paging_state.map(Some))),
+                    Err(error) => Some((Err(error), None)),
+                }
+            }
+        })
+        .boxed()
+    }
+
+    // returns rows along with the paging state of the next page, if there is one
+    async fn fetch_token_range_page(
+        &self,
+        query: &str,
+        keyspace: &str,
+        parameters: &StatementParams,
+        range: TokenRange,
+        paging_state: Option<CBytes>,
+    ) -> error::Result<(Vec<Row>, Option<CBytes>)> {
+        let envelope = token_range_envelope(
+            query,
+            range,
+            parameters,
+            paging_state,
+            prepare_flags(
+                sample_tracing(parameters.tracing, self.inner.tracing_sample_rate),
+                parameters.warnings || self.inner.warning_policy.requires_warnings(),
+                parameters.beta_protocol,
+            ),
+            self.inner.version,
+        )?;
+
+        let mut replicas = self.cluster_metadata().replicas(keyspace, range.end);
+        replicas
+            .sort_by_key(|node| matches!(node.state(), NodeState::Down | NodeState::ForcedDown));
+
+        let response = if replicas.is_empty() {
+            self.send_envelope(
+                envelope,
+                true,
+                Some(keyspace),
+                Some(range.end),
+                None,
+                Some(parameters.query_params.consistency),
+                parameters.speculative_execution_policy.as_ref(),
+                parameters.retry_policy.as_ref(),
+            )
+            .await
+        } else {
+            let completion_hook =
+                |node: &Node<T, CM>, latency, result: &error::Result<Envelope>| {
+                    self.inner
+                        .load_balancing
+                        .on_request_completed(node, latency, result)
+                };
+
+            send_envelope_with_hook(
+                replicas.into_iter(),
+                &envelope,
+                true,
+                LazyRetrySession::new(
+                    self.effective_retry_policy(parameters.retry_policy.as_ref()),
+                ),
+                &completion_hook,
+                None,
+                self.inner.retry_budget.as_ref(),
+            )
+            .await
+            .unwrap_or_else(|| Err("No replicas available".into()))
+        };
+
+        token_range_page(self.inner.warning_policy.apply(response, query)?)
+    }
+
+    /// Sends given envelope using session load balancing, retry policy and speculative
+    /// execution, and returns the response as is. This is an escape hatch for requests without a
+    /// dedicated method, e.g. experimental opcodes. The envelope is not inspected, so it's routed
+    /// without a keyspace or token, and side effects like changing the current keyspace are not
+    /// tracked. Stream ids are assigned by the transport, and error responses are returned as
+    /// [`Error::Server`](error::Error::Server), like for other requests.
+    pub async fn send_raw(
+        &self,
+        envelope: Envelope,
+        is_idempotent: bool,
+    ) -> error::Result<Envelope> {
+        self.send_envelope(envelope, is_idempotent, None, None, None, None, None, None)
+            .await
+    }
+
+    /// Sends given envelope to the node with given broadcast RPC address, and returns the
+    /// response as is. Like [`Session::send_raw`], but bypasses load balancing, so the request is
+    /// not retried.
+    pub async fn send_raw_to_node(
+        &self,
+        broadcast_rpc_address: SocketAddr,
+        envelope: Envelope,
+    ) -> error::Result<Envelope> {
+        let node = self
+            .cluster_metadata()
+            .find_node_by_rpc_address(broadcast_rpc_address)
+            .ok_or_else(|| {
+                error::Error::General(format!("Unknown node: {broadcast_rpc_address}"))
+            })?;
+
+        self.send_to_node(&node, &envelope).await
+    }
+
+    async fn send_to_node(
+        &self,
+        node: &Arc<Node<T, CM>>,
+        envelope: &Envelope,
+    ) -> error::Result<Envelope> {
+        let connection = node.persistent_connection().await?;
+
+        let start = Instant::now();
+        let response = connection.write_envelope(envelope, false).await;
+        self.inner
+            .load_balancing
+            .on_request_completed(node, start.elapsed(), &response);
+
+        response
     }
 
     /// Returns currently set global keyspace.
     #[inline]
     pub fn current_keyspace(&self) -> Option<Arc<String>> {
-        self.keyspace_holder.current_keyspace()
+        self.inner.keyspace_holder.current_keyspace()
+    }
+
+    /// Returns a receiver observing the global keyspace, e.g. to notice `USE` statements sent by
+    /// other components sharing the session. The keyspace set while building the session is the
+    /// initial value.
+    #[inline]
+    pub fn keyspace_changes(&self) -> watch::Receiver<Option<String>> {
+        self.inner.keyspace_holder.subscribe()
+    }
+
+    /// Displays given bound values with the session redactor applied. Values are decoded if the
+    /// metadata of prepared statement parameters is given.
+    #[inline]
+    pub fn display_values<'a>(
+        &'a self,
+        values: &'a QueryValues,
+        col_specs: Option<&'a [ColSpec]>,
+    ) -> QueryValuesDisplay<'a> {
+        values.display(col_specs, Some(&self.inner.redactor))
     }
 
     /// Returns current cluster metadata.
     #[inline]
     pub fn cluster_metadata(&self) -> Arc<ClusterMetadata<T, CM>> {
-        self.cluster_metadata_manager.metadata()
+        self.inner.cluster_metadata_manager.metadata()
+    }
+
+    /// Returns compression mode in effect for new connections of this session, or `None` if
+    /// traffic is not compressed.
+    pub fn compression_mode(&self) -> Option<CompressionMode> {
+        let compression_mode = self.inner.compression_mode?;
+        match self.compression() {
+            Some(compression) => compression_mode.effective(compression, self.inner.version),
+            None => Some(compression_mode),
+        }
+    }
+
+    /// Returns compression used by newly established connections, or `None` if the connection
+    /// manager doesn't report it.
+    #[inline]
+    pub fn compression(&self) -> Option<Compression> {
+        self.inner
+            .cluster_metadata_manager
+            .connection_manager()
+            .compression()
+    }
+
+    /// Changes compression used by newly established connections. Existing connections keep the
+    /// compression negotiated during their handshake until they are replaced, e.g. after being
+    /// broken or by [`recycle_connections`](Session::recycle_connections), so for a while the
+    /// session uses connections with both the old and the new compression. Use
+    /// [`connection_compressions`](Session::connection_compressions) to find out how far the
+    /// change has progressed.
+    pub fn set_compression(&self, compression: Compression) -> error::Result<()> {
+        self.inner
+            .cluster_metadata_manager
+            .connection_manager()
+            .set_compression(compression)?;
+
+        info!(%compression, "Changed compression of new connections.");
+        Ok(())
+    }
+
+    /// Returns how many pooled connections use each compression. Connections of transports which
+    /// don't report their compression are not counted.
+    pub async fn connection_compressions(&self) -> FxHashMap<Compression, usize> {
+        let mut compressions = FxHashMap::default();
+        for node in self.cluster_metadata().nodes().values() {
+            for connection in node.pooled_connections().await {
+                if let Some(compression) = connection.compression() {
+                    *compressions.entry(compression).or_default() += 1;
+                }
+            }
+        }
+
+        compressions
+    }
+
+    /// Replaces all pooled connections with new ones, e.g. to apply changed compression. Each
+    /// connection is replaced only after its successor is established, so requests can still be
+    /// sent in the meantime, and requests in flight on replaced connections are allowed to
+    /// complete. The control connection is not affected. Returns the first error encountered,
+    /// after trying to recycle pools of all nodes.
+    pub async fn recycle_connections(&self) -> error::Result<()> {
+        let metadata = self.cluster_metadata();
+        let mut results = metadata
+            .nodes()
+            .values()
+            .map(|node| node.recycle_connections())
+            .collect::<FuturesUnordered<_>>();
+
+        let mut first_error = None;
+        while let Some(result) = results.next().await {
+            if let Err(error) = result {
+                warn!(%error, "Error recycling connections.");
+                first_error.get_or_insert(error);
+            }
+        }
+
+        first_error.map_or(Ok(()), Err)
+    }
+
+    /// Stops all background tasks of this session: the control connection, server event
+    /// processing, heartbeats, reconnections and keyspace propagation. This also happens when the
+    /// last clone of the session is dropped, but calling it explicitly stops the tasks even if some
+    /// clones are still alive. Established connections can still be used afterwards, but broken ones
+    /// are no longer replaced and topology changes are no longer tracked.
+    pub fn shutdown(&self) {
+        self.inner.task_tracker.shutdown();
+    }
+
+    /// Returns `true` if [`shutdown`](Session::shutdown) has been called.
+    #[inline]
+    pub fn is_shut_down(&self) -> bool {
+        self.inner.task_tracker.is_shut_down()
+    }
+
+    /// Returns the policy applied to server warnings.
+    #[inline]
+    pub fn warning_policy(&self) -> WarningPolicy {
+        self.inner.warning_policy
+    }
+
+    /// Returns the fraction of requests traced regardless of their statement parameters.
+    #[inline]
+    pub fn tracing_sample_rate(&self) -> f64 {
+        self.inner.tracing_sample_rate
+    }
+
+    /// Returns whether OPTIONS requests are used for heartbeats and connection verification.
+    #[inline]
+    pub fn options_probe(&self) -> bool {
+        self.inner.options_probe
+    }
+
+    /// Returns the consistency of queries the driver sends on its own.
+    #[inline]
+    pub fn system_query_consistency(&self) -> Consistency {
+        self.inner.system_query_consistency
     }
 
     /// Returns query plan for given request. If no request is given, return a generic plan for
     /// establishing connection(s) to node(s).
     #[inline]
     pub fn query_plan(&self, request: Option<Request>) -> QueryPlan<T, CM> {
-        self.load_balancing
+        self.inner
+            .load_balancing
             .query_plan(request, self.cluster_metadata().as_ref())
     }
 
+    // query plan for sending a request, see connected_nodes()
+    #[inline]
+    fn connected_query_plan(
+        &self,
+        request: Option<Request<'_>>,
+    ) -> error::Result<impl Iterator<Item = Arc<Node<T, CM>>>> {
+        connected_nodes(
+            self.query_plan(request),
+            self.inner.fail_fast_when_disconnected,
+        )
+    }
+
     /// Creates a new server event receiver. You can use multiple receivers at the same time.
+    ///
+    /// Events are received on the control connection only, which registers for all event types
+    /// and keeps cluster metadata up to date on its own, so receivers are only needed to react to
+    /// events in application code. Pooled data connections don't receive events.
     #[inline]
     pub fn create_event_receiver(&self) -> Receiver<ServerEvent> {
-        self.event_sender.subscribe()
+        self.inner.event_sender.subscribe()
+    }
+
+    /// Creates a builder for a subscription to server events, which delivers each event type to
+    /// its own handler. See [`EventSubscriptionBuilder`] for details.
+    pub fn subscribe_events(&self) -> EventSubscriptionBuilder<'_, T, CM, LB> {
+        EventSubscriptionBuilder::new(self)
+    }
+
+    // opens a new connection to given node and registers it for given events
+    #[inline]
+    pub(crate) fn create_registrations_receiver(&self) -> Receiver<RegistrationsRestored> {
+        self.inner.registrations_sender.subscribe()
+    }
+
+    pub(crate) fn event_registration(
+        &self,
+        broadcast_rpc_address: SocketAddr,
+        events: Vec<SimpleServerEvent>,
+    ) -> error::Result<EventRegistration<T>> {
+        let node = self
+            .cluster_metadata()
+            .find_node_by_rpc_address(broadcast_rpc_address)
+            .ok_or_else(|| {
+                error::Error::General(format!("Unknown node: {broadcast_rpc_address}"))
+            })?;
+
+        Ok(EventRegistration::new(
+            Box::new(move |event_handler, error_handler| {
+                let node = node.clone();
+                async move {
+                    node.new_connection(Some(event_handler), Some(error_handler))
+                        .await
+                }
+                .boxed()
+            }),
+            broadcast_rpc_address,
+            events,
+            self.inner.version,
+            self.inner.reconnection_policy.clone(),
+        ))
     }
 
     /// Returns current retry policy.
     #[inline]
     pub fn retry_policy(&self) -> &dyn RetryPolicy {
-        self.retry_policy.as_ref()
+        self.inner.retry_policy.as_ref()
+    }
+
+    /// Returns the session-wide retry budget, if any.
+    #[inline]
+    pub fn retry_budget(&self) -> Option<&RetryBudget> {
+        self.inner.retry_budget.as_ref()
+    }
+
+    /// Eagerly establishes connection pools to all nodes which are not ignored, instead of
+    /// waiting for the first request to each node. Returns which nodes have been connected to,
+    /// along with detailed errors for the rest, or an error if no node could be connected to.
+    pub async fn warmup(&self) -> Result<WarmupReport, WarmupError> {
+        let mut results = self
+            .cluster_metadata()
+            .unignored_nodes()
+            .into_iter()
+            .map(|node| async move {
+                let start = Instant::now();
+
+                // the pool keeps diagnostics of its own connection attempts, which are more
+                // precise than the generic error of having no connections
+                node.persistent_connection()
+                    .await
+                    .map(|_| node.broadcast_rpc_address())
+                    .map_err(|error| {
+                        node.last_connection_error().unwrap_or_else(|| {
+                            ConnectionError::new(
+                                node.broadcast_rpc_address(),
+                                ConnectionPhase::from_error(&error),
+                                error,
+                                start.elapsed(),
+                            )
+                        })
+                    })
+            })
+            .collect::<FuturesUnordered<_>>();
+
+        let mut report = WarmupReport::default();
+        while let Some(result) = results.next().await {
+            match result {
+                Ok(address) => report.connected.push(address),
+                Err(error) => {
+                    warn!(%error, "Error warming up connection.");
+                    report.failed.push(error);
+                }
+            }
+        }
+
+        if report.connected.is_empty() && !report.failed.is_empty() {
+            return Err(WarmupError {
+                failed: report.failed,
+            });
+        }
+
+        Ok(report)
+    }
+
+    #[inline]
+    pub(crate) fn prepared_cache(&self) -> &PreparedCache {
+        &self.inner.prepared_cache
+    }
+
+    #[inline]
+    pub(crate) fn insert_statements(
+        &self,
+    ) -> &Mutex<FxHashMap<InsertStatementKey, Arc<PreparedQuery>>> {
+        &self.inner.insert_statements
+    }
+
+    fn log_failed_statement(
+        &self,
+        result: &error::Result<Envelope>,
+        query: &str,
+        values: Option<&QueryValues>,
+        col_specs: Option<&[ColSpec]>,
+    ) {
+        if let Err(error) = result {
+            if !enabled!(Level::DEBUG) {
+                return;
+            }
+
+            match values {
+                Some(values) => {
+                    let values = self.display_values(values, col_specs);
+                    debug!(%error, query, %values, "Statement failed.");
+                }
+                None => debug!(%error, query, "Statement failed."),
+            }
+        }
+    }
+
+    fn check_keyspace_lock(&self, query: &str) -> error::Result<()> {
+        if self.inner.keyspace_lock && is_use_statement(query) {
+            Err(error::Error::KeyspaceLocked(query.to_string()))
+        } else {
+            Ok(())
+        }
+    }
+
+    fn check_keyspace_qualification(&self, query: &str, keyspace: Option<&str>) {
+        if !cfg!(debug_assertions)
+            || !self.inner.keyspace_qualification_check
+            || keyspace.is_some()
+            || self.current_keyspace().is_some()
+        {
+            return;
+        }
+
+        let table = find_unqualified_table(query);
+        debug_assert!(
+            table.is_none(),
+            "Table {:?} is not qualified with a keyspace and no keyspace is set: {}",
+            table,
+            query
+        );
     }
 
     #[allow(clippy::too_many_arguments)]
+    #[inline]
     async fn send_envelope(
         &self,
         envelope: Envelope,
@@ -615,6 +2091,52 @@ impl<
         speculative_execution_policy: Option<&Arc<dyn SpeculativeExecutionPolicy + Send + Sync>>,
         retry_policy: Option<&Arc<dyn RetryPolicy + Send + Sync>>,
     ) -> error::Result<Envelope> {
+        self.send_envelope_tracked(
+            envelope,
+            is_idempotent,
+            keyspace,
+            token,
+            routing_key,
+            consistency,
+            speculative_execution_policy,
+            retry_policy,
+            None,
+            None,
+        )
+        .await
+    }
+
+    // sets `written` if the envelope might have been written to any connection, and sends it over
+    // the pinned connection first, if given
+    #[allow(clippy::too_many_arguments)]
+    async fn send_envelope_tracked(
+        &self,
+        envelope: Envelope,
+        is_idempotent: bool,
+        keyspace: Option<&str>,
+        token: Option<Murmur3Token>,
+        routing_key: Option<&[u8]>,
+        consistency: Option<Consistency>,
+        speculative_execution_policy: Option<&Arc<dyn SpeculativeExecutionPolicy + Send + Sync>>,
+        retry_policy: Option<&Arc<dyn RetryPolicy + Send + Sync>>,
+        pinned: Option<&PinnedConnection<T, CM>>,
+        written: Option<&AtomicBool>,
+    ) -> error::Result<Envelope> {
+        let completion_hook = |node: &Node<T, CM>, latency, result: &error::Result<Envelope>| {
+            self.inner
+                .load_balancing
+                .on_request_completed(node, latency, result)
+        };
+
+        if let Some(pinned) = pinned {
+            if let Some(result) = pinned
+                .send(&envelope, is_idempotent, &completion_hook, written)
+                .await
+            {
+                return result;
+            }
+        }
+
         let current_keyspace = self.current_keyspace();
         let request = Request::new(
             keyspace.or_else(|| current_keyspace.as_ref().map(|keyspace| &***keyspace)),
@@ -623,7 +2145,7 @@ impl<
             consistency,
         );
 
-        let query_plan = self.query_plan(Some(request));
+        let query_plan = self.connected_query_plan(Some(request))?;
 
         struct SharedQueryPlan<
             T: CdrsTransport + 'static,
@@ -661,7 +2183,7 @@ impl<
 
         let speculative_execution_policy = speculative_execution_policy
             .map(|speculative_execution_policy| speculative_execution_policy.as_ref())
-            .or(self.speculative_execution_policy.as_deref());
+            .or(self.inner.speculative_execution_policy.as_deref());
 
         let retry_policy = self.effective_retry_policy(retry_policy);
 
@@ -671,11 +2193,14 @@ impl<
 
                 let mut context = Context::new(1);
                 let mut async_tasks = FuturesUnordered::new();
-                async_tasks.push(send_envelope(
+                async_tasks.push(send_envelope_with_hook(
                     &shared_query_plan,
                     &envelope,
                     is_idempotent,
-                    retry_policy.new_session(),
+                    LazyRetrySession::new(retry_policy),
+                    &completion_hook,
+                    written,
+                    self.inner.retry_budget.as_ref(),
                 ));
 
                 let sleep_fut = sleep(
@@ -696,11 +2221,14 @@ impl<
                                 speculative_execution_policy.execution_interval(&context)
                             {
                                 context.running_executions += 1;
-                                async_tasks.push(send_envelope(
+                                async_tasks.push(send_envelope_with_hook(
                                     &shared_query_plan,
                                     &envelope,
                                     is_idempotent,
-                                    retry_policy.new_session(),
+                                    LazyRetrySession::new(retry_policy),
+                                    &completion_hook,
+                                    written,
+                                    self.inner.retry_budget.as_ref(),
                                 ));
 
                                 sleep_fut.set(sleep(interval).fuse());
@@ -710,7 +2238,13 @@ impl<
                             match result {
                                 Some(result) => {
                                     match result {
-                                        Err(error::Error::Io(_)) | Err(error::Error::Timeout(_)) => {
+                                        Err(error::Error::Io(_))
+                                        | Err(error::Error::Timeout { .. })
+                                        | Err(error::Error::HandshakeTimeout(_))
+                                        | Err(error::Error::AuthenticationTimeout(_))
+                                        | Err(error::Error::RequestNotSent(_))
+                                        | Err(error::Error::ProtocolDesync(_))
+                                        | Err(error::Error::UnexpectedFrame { .. }) => {
                                             last_error = Some(result);
                                         },
                                         _ => return result,
@@ -729,66 +2263,130 @@ impl<
                     }
                 }
             }
-            _ => send_envelope(
+            _ => send_envelope_with_hook(
                 query_plan.into_iter(),
                 &envelope,
                 is_idempotent,
-                retry_policy.new_session(),
+                LazyRetrySession::new(retry_policy),
+                &completion_hook,
+                written,
+                self.inner.retry_budget.as_ref(),
             )
             .await
             .unwrap_or_else(|| Err("No nodes available in query plan!".into())),
         }
     }
 
+    // routing key from partition key values, falling back to the one given in parameters
+    fn prepared_routing_key(
+        &self,
+        prepared: &PreparedQuery,
+        query_params: &QueryParams,
+        parameters: &StatementParams,
+    ) -> Option<Vec<u8>> {
+        query_params
+            .values
+            .as_ref()
+            .and_then(|values| match values {
+                QueryValues::SimpleValues(values) => serialize_routing_key_with_indexes(
+                    values,
+                    &prepared.pk_indexes,
+                    self.inner.version,
+                )
+                .or_else(|| {
+                    parameters
+                        .routing_key
+                        .as_ref()
+                        .map(|values| serialize_routing_key(values, self.inner.version))
+                }),
+                QueryValues::NamedValues(_) => None,
+            })
+    }
+
     #[inline]
-    fn effective_retry_policy<'a, 'b: 'a>(
+    pub(crate) fn effective_retry_policy<'a, 'b: 'a>(
         &'a self,
         retry_policy: Option<&'b Arc<dyn RetryPolicy + Send + Sync>>,
     ) -> &'a (dyn RetryPolicy + Send + Sync) {
         retry_policy
             .map(|retry_policy| retry_policy.as_ref())
-            .unwrap_or_else(|| self.retry_policy.as_ref())
+            .unwrap_or_else(|| self.inner.retry_policy.as_ref())
     }
 
-    #[allow(clippy::too_many_arguments)]
     async fn new(
-        load_balancing: LB,
+        settings: SessionSettings<LB>,
         keyspace_holder: Arc<KeyspaceHolder>,
         keyspace_receiver: watch::Receiver<Option<String>>,
-        retry_policy: Box<dyn RetryPolicy + Send + Sync>,
-        reconnection_policy: Arc<dyn ReconnectionPolicy + Send + Sync>,
-        node_distance_evaluator: Box<dyn NodeDistanceEvaluator + Send + Sync>,
-        speculative_execution_policy: Option<Box<dyn SpeculativeExecutionPolicy + Send + Sync>>,
-        contact_points: Vec<SocketAddr>,
+        contact_points: Vec<Vec<SocketAddr>>,
         connection_manager: CM,
-        event_channel_capacity: usize,
-        version: Version,
-        connection_pool_config: ConnectionPoolConfig,
-        beta_protocol: bool,
     ) -> Result<Self, SessionBuildError> {
-        let connection_pool_factory = Arc::new(ConnectionPoolFactory::new(
-            connection_pool_config,
+        let SessionSettings {
+            load_balancing,
+            retry_policy,
+            retry_budget,
+            reconnection_policy,
+            node_distance_evaluator,
+            speculative_execution_policy,
+            event_channel_capacity,
             version,
-            connection_manager,
-            keyspace_receiver,
-            reconnection_policy.clone(),
-        ));
+            connection_pool_config,
+            beta_protocol,
+            lenient_conversions,
+            keyspace_qualification_check,
+            keyspace_lock,
+            redactor,
+            schema_agreement_timeout,
+            unprepared_routing,
+            fail_fast_when_disconnected,
+            strict_prepared_metadata,
+            prepared_metadata_listener,
+            node_state_listener,
+            compression_mode,
+            warning_policy,
+            tracing_sample_rate,
+            options_probe,
+            system_query_consistency,
+            prepared_cache_snapshot,
+            prepared_cache_capacity,
+        } = settings;
+
+        verify_beta_protocol_configuration(version, beta_protocol)?;
+
+        let connection_pool_factory = Arc::new(
+            ConnectionPoolFactory::new(
+                connection_pool_config
+                    .with_options_probe(options_probe)
+                    .with_system_query_consistency(system_query_consistency),
+                version,
+                connection_manager,
+                keyspace_receiver,
+                reconnection_policy.clone(),
+            )
+            .with_node_state_listener(node_state_listener),
+        );
 
+        let task_tracker = connection_pool_factory.task_tracker().clone();
+
+        // each contact point is given with its alternative addresses, in connection order
         let contact_points = contact_points
             .into_iter()
-            .map(|contact_point| {
-                Arc::new(Node::new_with_state(
-                    connection_pool_factory.clone(),
-                    contact_point,
-                    None,
-                    None,
-                    // assume contact points are local until refresh
-                    Some(NodeDistance::Local),
-                    NodeState::Up,
-                    Default::default(),
-                    // as with distance, rack/dc is unknown until refresh
-                    "".into(),
-                    "".into(),
+            .filter_map(|addrs| {
+                let (contact_point, alternative_addresses) = addrs.split_first()?;
+                Some(Arc::new(
+                    Node::new_with_state(
+                        connection_pool_factory.clone(),
+                        *contact_point,
+                        None,
+                        None,
+                        // assume contact points are local until refresh
+                        Some(NodeDistance::Local),
+                        NodeState::Up,
+                        Default::default(),
+                        // as with distance, rack/dc is unknown until refresh
+                        "".into(),
+                        "".into(),
+                    )
+                    .with_alternative_addresses(alternative_addresses.to_vec()),
                 ))
             })
             .collect_vec();
@@ -799,16 +2397,24 @@ impl<
         ));
 
         let (event_sender, event_receiver) = channel(event_channel_capacity);
+        let (registrations_sender, _) = channel(event_channel_capacity);
 
         let session_context = Arc::new(SessionContext::default());
 
+        let prepared_cache = Arc::new(PreparedCache::new(
+            prepared_cache_snapshot,
+            prepared_cache_capacity,
+        ));
+
         let cluster_metadata_manager = Arc::new(ClusterMetadataManager::new(
             contact_points.clone(),
             connection_pool_factory,
             session_context.clone(),
             node_distance_evaluator,
+            prepared_cache.clone(),
             version,
             beta_protocol,
+            system_query_consistency,
         ));
 
         cluster_metadata_manager.listen_to_events(event_receiver);
@@ -819,31 +2425,79 @@ impl<
             reconnection_policy.clone(),
             cluster_metadata_manager.clone(),
             event_sender.clone(),
+            registrations_sender.clone(),
             session_context,
             version,
         );
 
         let (init_complete_sender, init_complete_receiver) = tokio::sync::oneshot::channel();
-        let control_connection_handle = tokio::spawn(control_connection.run(init_complete_sender));
+        task_tracker.spawn(control_connection.run(init_complete_sender));
         if init_complete_receiver.await.is_err() {
+            task_tracker.shutdown();
             return Err(SessionBuildError::SessionInitFailed);
         }
 
         Ok(Session {
-            load_balancing,
-            keyspace_holder,
-            retry_policy,
-            speculative_execution_policy,
-            control_connection_handle,
-            event_sender,
-            cluster_metadata_manager,
-            _transport: Default::default(),
-            _connection_manager: Default::default(),
-            version,
+            inner: Arc::new(SessionInner {
+                load_balancing,
+                keyspace_holder,
+                retry_policy,
+                retry_budget,
+                speculative_execution_policy,
+                task_tracker,
+                event_sender,
+                registrations_sender,
+                reconnection_policy,
+                cluster_metadata_manager,
+                _transport: Default::default(),
+                _connection_manager: Default::default(),
+                version,
+                lenient_conversions,
+                keyspace_qualification_check,
+                keyspace_lock,
+                redactor,
+                schema_agreement_timeout,
+                unprepared_routing,
+                fail_fast_when_disconnected,
+                strict_prepared_metadata,
+                schema_coordinator: Default::default(),
+                prepared_metadata_listener,
+                reported_variables: Default::default(),
+                compression_mode,
+                warning_policy,
+                tracing_sample_rate,
+                options_probe,
+                system_query_consistency,
+                prepared_cache,
+                insert_statements: Default::default(),
+            }),
         })
     }
 }
 
+impl<
+        T: CdrsTransport + 'static,
+        CM: ConnectionManager<T> + Send + Sync + 'static,
+        LB: LoadBalancingStrategy<T, CM> + Send + Sync + 'static,
+    > StatementTarget for Session<T, CM, LB>
+{
+    fn send_query<'a>(
+        &'a self,
+        query: Cow<'a, str>,
+        parameters: StatementParams,
+    ) -> BoxFuture<'a, error::Result<Envelope>> {
+        self.query_with_params(query, parameters).boxed()
+    }
+
+    fn send_prepared<'a>(
+        &'a self,
+        prepared: &'a PreparedQuery,
+        parameters: StatementParams,
+    ) -> BoxFuture<'a, error::Result<Envelope>> {
+        async move { self.exec_with_params(prepared, &parameters).await }.boxed()
+    }
+}
+
 /// Workaround for <https://github.com/rust-lang/rust/issues/63033>
 #[repr(transparent)]
 pub struct RetryPolicyWrapper(pub Box<dyn RetryPolicy + Send + Sync>);
@@ -886,41 +2540,122 @@ where
 {
     let (keyspace_holder, keyspace_receiver) = create_keyspace_holder();
     let connection_manager = config.create_manager(keyspace_holder.clone()).await?;
-    Session::new(
+    let settings = SessionSettings {
         load_balancing,
+        retry_policy: retry_policy.0,
+        retry_budget: config.retry_budget(),
+        reconnection_policy: reconnection_policy.0,
+        node_distance_evaluator: node_distance_evaluator.0,
+        speculative_execution_policy: speculative_execution_policy.map(|policy| policy.0),
+        event_channel_capacity: config.event_channel_capacity(),
+        version: config.version(),
+        connection_pool_config: config.connection_pool_config(),
+        beta_protocol: config.beta_protocol(),
+        lenient_conversions: config.lenient_conversions(),
+        keyspace_qualification_check: config.keyspace_qualification_check(),
+        keyspace_lock: config.keyspace_lock(),
+        redactor: config.redactor(),
+        schema_agreement_timeout: config.schema_agreement_timeout(),
+        unprepared_routing: config.unprepared_routing(),
+        fail_fast_when_disconnected: config.fail_fast_when_disconnected(),
+        strict_prepared_metadata: config.strict_prepared_metadata(),
+        prepared_metadata_listener: config.prepared_metadata_listener(),
+        node_state_listener: config.node_state_listener(),
+        compression_mode: config.compression_mode(),
+        warning_policy: config.warning_policy(),
+        tracing_sample_rate: config.tracing_sample_rate(),
+        options_probe: config.options_probe(),
+        system_query_consistency: config.system_query_consistency(),
+        prepared_cache_snapshot: config.prepared_cache_snapshot(),
+        prepared_cache_capacity: config.prepared_cache_capacity(),
+    };
+
+    Session::new(
+        settings,
         keyspace_holder,
         keyspace_receiver,
-        retry_policy.0,
-        reconnection_policy.0,
-        node_distance_evaluator.0,
-        speculative_execution_policy.map(|policy| policy.0),
-        initial_nodes.into_iter().collect(),
+        initial_nodes.into_iter().map(|addr| vec![addr]).collect(),
         connection_manager,
-        config.event_channel_capacity(),
-        config.version(),
-        config.connection_pool_config(),
-        config.beta_protocol(),
     )
     .await
     .map_err(|e| error::Error::General(e.to_string()))
 }
 
+// options a session is created with, independent of how its connections are established
+struct SessionSettings<LB> {
+    load_balancing: LB,
+    retry_policy: Box<dyn RetryPolicy + Send + Sync>,
+    retry_budget: Option<RetryBudget>,
+    reconnection_policy: Arc<dyn ReconnectionPolicy + Send + Sync>,
+    node_distance_evaluator: Box<dyn NodeDistanceEvaluator + Send + Sync>,
+    speculative_execution_policy: Option<Box<dyn SpeculativeExecutionPolicy + Send + Sync>>,
+    event_channel_capacity: usize,
+    version: Version,
+    connection_pool_config: ConnectionPoolConfig,
+    beta_protocol: bool,
+    lenient_conversions: bool,
+    keyspace_qualification_check: bool,
+    keyspace_lock: bool,
+    redactor: Redactor,
+    schema_agreement_timeout: Duration,
+    unprepared_routing: bool,
+    fail_fast_when_disconnected: bool,
+    strict_prepared_metadata: bool,
+    prepared_metadata_listener: Option<Arc<dyn PreparedMetadataListener + Send + Sync>>,
+    node_state_listener: Option<Arc<dyn NodeStateListener + Send + Sync>>,
+    compression_mode: Option<CompressionMode>,
+    warning_policy: WarningPolicy,
+    tracing_sample_rate: f64,
+    options_probe: bool,
+    system_query_consistency: Consistency,
+    prepared_cache_snapshot: Option<PreparedCacheSnapshot>,
+    prepared_cache_capacity: usize,
+}
+
 struct SessionConfig<
     T: CdrsTransport,
     CM: ConnectionManager<T>,
     LB: LoadBalancingStrategy<T, CM> + Send + Sync,
 > {
     compression: Compression,
+    compression_mode: CompressionMode,
+    compression_threshold: usize,
     transport_buffer_size: usize,
+    read_buffer_size: Option<usize>,
     tcp_nodelay: bool,
+    strict_socket_options: bool,
+    handshake_timeout: Duration,
+    authentication_timeout: Duration,
+    max_concurrent_authentications: usize,
+    connection_stagger_delay: Duration,
+    frame_recorder: Option<Arc<FrameRecorder>>,
+    init_statements: Vec<InitStatement>,
+    address_family_preference: AddressFamilyPreference,
     load_balancing: LB,
     retry_policy: Box<dyn RetryPolicy + Send + Sync>,
+    retry_budget: Option<RetryBudget>,
     reconnection_policy: Arc<dyn ReconnectionPolicy + Send + Sync>,
     node_distance_evaluator: Box<dyn NodeDistanceEvaluator + Send + Sync>,
     speculative_execution_policy: Option<Box<dyn SpeculativeExecutionPolicy + Send + Sync>>,
     event_channel_capacity: usize,
     connection_pool_config: ConnectionPoolConfig,
     keyspace: Option<String>,
+    lenient_conversions: bool,
+    keyspace_qualification_check: bool,
+    keyspace_lock: bool,
+    redactor: Redactor,
+    schema_agreement_timeout: Duration,
+    unprepared_routing: bool,
+    fail_fast_when_disconnected: bool,
+    strict_prepared_metadata: bool,
+    prepared_metadata_listener: Option<Arc<dyn PreparedMetadataListener + Send + Sync>>,
+    node_state_listener: Option<Arc<dyn NodeStateListener + Send + Sync>>,
+    warning_policy: WarningPolicy,
+    tracing_sample_rate: f64,
+    options_probe: bool,
+    system_query_consistency: Consistency,
+    prepared_cache_snapshot: Option<PreparedCacheSnapshot>,
+    prepared_cache_capacity: usize,
     _connection_manager: PhantomData<CM>,
     _transport: PhantomData<T>,
 }
@@ -934,48 +2669,206 @@ impl<
     fn new(load_balancing: LB) -> Self {
         SessionConfig {
             compression: Compression::None,
+            compression_mode: Default::default(),
+            compression_threshold: DEFAULT_COMPRESSION_THRESHOLD,
             transport_buffer_size: DEFAULT_TRANSPORT_BUFFER_SIZE,
+            read_buffer_size: None,
             tcp_nodelay: true,
+            strict_socket_options: false,
+            handshake_timeout: DEFAULT_HANDSHAKE_TIMEOUT,
+            authentication_timeout: DEFAULT_AUTHENTICATION_TIMEOUT,
+            max_concurrent_authentications: DEFAULT_MAX_CONCURRENT_AUTHENTICATIONS,
+            connection_stagger_delay: DEFAULT_CONNECTION_STAGGER_DELAY,
+            frame_recorder: None,
+            init_statements: vec![],
+            address_family_preference: Default::default(),
             load_balancing,
             retry_policy: Box::<DefaultRetryPolicy>::default(),
+            retry_budget: None,
             reconnection_policy: Arc::new(ExponentialReconnectionPolicy::default()),
             node_distance_evaluator: Box::<AllLocalNodeDistanceEvaluator>::default(),
             speculative_execution_policy: None,
             event_channel_capacity: DEFAULT_EVENT_CHANNEL_CAPACITY,
             connection_pool_config: Default::default(),
             keyspace: None,
+            lenient_conversions: false,
+            keyspace_qualification_check: false,
+            keyspace_lock: false,
+            redactor: Default::default(),
+            schema_agreement_timeout: DEFAULT_SCHEMA_AGREEMENT_TIMEOUT,
+            unprepared_routing: false,
+            fail_fast_when_disconnected: false,
+            strict_prepared_metadata: false,
+            prepared_metadata_listener: None,
+            node_state_listener: None,
+            warning_policy: Default::default(),
+            tracing_sample_rate: 0.0,
+            options_probe: true,
+            system_query_consistency: Consistency::One,
+            prepared_cache_snapshot: None,
+            prepared_cache_capacity: DEFAULT_PREPARED_CACHE_CAPACITY,
             _connection_manager: Default::default(),
             _transport: Default::default(),
         }
     }
 
+    fn connection_manager_config(&self, version: Version) -> ConnectionManagerConfigBuilder {
+        ConnectionManagerConfigBuilder::new(version)
+            .with_compression(self.compression)
+            .with_compression_threshold(
+                self.compression_mode
+                    .request_compression_threshold(self.compression_threshold),
+            )
+            .with_buffer_size(self.transport_buffer_size)
+            .with_read_buffer_size(self.read_buffer_size)
+            .with_tcp_nodelay(self.tcp_nodelay)
+            .with_strict_socket_options(self.strict_socket_options)
+            .with_handshake_timeout(self.handshake_timeout)
+            .with_authentication_timeout(self.authentication_timeout)
+            .with_max_concurrent_authentications(self.max_concurrent_authentications)
+            .with_connection_stagger_delay(self.connection_stagger_delay)
+            .with_frame_recorder(self.frame_recorder.clone())
+            .with_init_statements(self.init_statements.clone())
+    }
+
+    fn apply_connection_string(
+        &mut self,
+        connection_string: &ConnectionString,
+    ) -> error::Result<()> {
+        // consistency is set per statement or batch, so there's nothing in a session to apply it
+        // to - rejecting it is better than silently using a different one
+        if connection_string.consistency().is_some() {
+            return Err(error::Error::General(
+                "Connection string sets consistency, which sessions can't apply - set it in \
+                 statement parameters instead!"
+                    .into(),
+            ));
+        }
+
+        self.keyspace = connection_string.keyspace().map(String::from);
+        self.compression = connection_string.compression();
+        self.connection_pool_config = connection_string.connection_pool_config();
+        Ok(())
+    }
+
+    // checks everything up front, so all problems can be reported at once
+    fn validate(
+        &self,
+        contact_points: &[Vec<SocketAddr>],
+        version: Version,
+        beta_protocol: bool,
+    ) -> Result<(), SessionBuildError> {
+        let mut violations = SessionConfigViolations::default();
+
+        if contact_points.iter().all(Vec::is_empty) {
+            violations.insert(SessionConfigViolation::NoContactPoints);
+        }
+
+        if self.transport_buffer_size == 0 {
+            violations.insert(SessionConfigViolation::ZeroTransportBufferSize);
+        }
+
+        if self.read_buffer_size == Some(0) {
+            violations.insert(SessionConfigViolation::ZeroReadBufferSize);
+        }
+
+        if self.event_channel_capacity == 0 {
+            violations.insert(SessionConfigViolation::ZeroEventChannelCapacity);
+        }
+
+        if self.handshake_timeout.is_zero() {
+            violations.insert(SessionConfigViolation::ZeroHandshakeTimeout);
+        }
+
+        if self.authentication_timeout.is_zero() {
+            violations.insert(SessionConfigViolation::ZeroAuthenticationTimeout);
+        }
+
+        if self.max_concurrent_authentications == 0 {
+            violations.insert(SessionConfigViolation::ZeroMaxConcurrentAuthentications);
+        }
+
+        if self.connection_pool_config.local_size() == 0 {
+            violations.insert(SessionConfigViolation::ZeroLocalPoolSize);
+        }
+
+        if self.connection_pool_config.heartbeat_interval().is_zero() {
+            violations.insert(SessionConfigViolation::ZeroHeartbeatInterval);
+        }
+
+        if version >= Version::V5 && self.compression == Compression::Snappy {
+            violations.insert(SessionConfigViolation::CompressionTypeNotSupported);
+        }
+
+        if verify_beta_protocol_configuration(version, beta_protocol).is_err() {
+            violations.insert(SessionConfigViolation::BetaProtocolMismatch);
+        }
+
+        if !(0.0..=1.0).contains(&self.tracing_sample_rate) {
+            violations.insert(SessionConfigViolation::InvalidTracingSampleRate);
+        }
+
+        if violations.is_empty() {
+            Ok(())
+        } else {
+            Err(SessionBuildError::InvalidConfiguration(violations))
+        }
+    }
+
     async fn into_session(
         self,
         keyspace_holder: Arc<KeyspaceHolder>,
         keyspace_receiver: watch::Receiver<Option<String>>,
-        contact_points: Vec<SocketAddr>,
+        contact_points: Vec<Vec<SocketAddr>>,
         connection_manager: CM,
         version: Version,
         beta_protocol: bool,
     ) -> Result<Session<T, CM, LB>, SessionBuildError> {
+        let contact_points = contact_points
+            .iter()
+            .flat_map(|addrs| self.address_family_preference.contact_points(addrs))
+            .collect();
+
         if let Some(keyspace) = self.keyspace {
             keyspace_holder.update_current_keyspace_without_notification(keyspace);
         }
 
+        let settings = SessionSettings {
+            load_balancing: self.load_balancing,
+            retry_policy: self.retry_policy,
+            retry_budget: self.retry_budget,
+            reconnection_policy: self.reconnection_policy,
+            node_distance_evaluator: self.node_distance_evaluator,
+            speculative_execution_policy: self.speculative_execution_policy,
+            event_channel_capacity: self.event_channel_capacity,
+            version,
+            connection_pool_config: self.connection_pool_config,
+            beta_protocol,
+            lenient_conversions: self.lenient_conversions,
+            keyspace_qualification_check: self.keyspace_qualification_check,
+            keyspace_lock: self.keyspace_lock,
+            redactor: self.redactor,
+            schema_agreement_timeout: self.schema_agreement_timeout,
+            unprepared_routing: self.unprepared_routing,
+            fail_fast_when_disconnected: self.fail_fast_when_disconnected,
+            strict_prepared_metadata: self.strict_prepared_metadata,
+            prepared_metadata_listener: self.prepared_metadata_listener,
+            node_state_listener: self.node_state_listener,
+            compression_mode: Some(self.compression_mode),
+            warning_policy: self.warning_policy,
+            tracing_sample_rate: self.tracing_sample_rate,
+            options_probe: self.options_probe,
+            system_query_consistency: self.system_query_consistency,
+            prepared_cache_snapshot: self.prepared_cache_snapshot,
+            prepared_cache_capacity: self.prepared_cache_capacity,
+        };
+
         Session::new(
-            self.load_balancing,
+            settings,
             keyspace_holder,
             keyspace_receiver,
-            self.retry_policy,
-            self.reconnection_policy,
-            self.node_distance_evaluator,
-            self.speculative_execution_policy,
             contact_points,
             connection_manager,
-            self.event_channel_capacity,
-            version,
-            self.connection_pool_config,
-            beta_protocol,
         )
         .await
     }
@@ -984,10 +2877,119 @@ impl<
 /// `Session` build error.
 #[derive(Error, Debug, Ord, PartialOrd, Eq, PartialEq, Hash, Copy, Clone)]
 pub enum SessionBuildError {
-    #[error("Given compression type is not supported for selected protocol!")]
-    CompressionTypeNotSupported,
     #[error("Session control connection died before completing initialization")]
     SessionInitFailed,
+    #[error("Beta protocol flag needs to be used together with a beta protocol version!")]
+    BetaProtocolMismatch,
+    /// Session builder configuration is invalid. Contains all problems found.
+    #[error("Invalid session configuration: {0}")]
+    InvalidConfiguration(SessionConfigViolations),
+}
+
+/// A single problem found in session builder configuration before connecting.
+#[derive(Error, Debug, Ord, PartialOrd, Eq, PartialEq, Hash, Copy, Clone)]
+#[non_exhaustive]
+pub enum SessionConfigViolation {
+    #[error("No contact points given!")]
+    NoContactPoints,
+    #[error("Transport buffer size needs to be greater than 0!")]
+    ZeroTransportBufferSize,
+    #[error("Read buffer size needs to be greater than 0!")]
+    ZeroReadBufferSize,
+    #[error("Event channel capacity needs to be greater than 0!")]
+    ZeroEventChannelCapacity,
+    #[error("Handshake timeout needs to be greater than 0!")]
+    ZeroHandshakeTimeout,
+    #[error("Authentication timeout needs to be greater than 0!")]
+    ZeroAuthenticationTimeout,
+    #[error("Maximum number of concurrent authentications needs to be greater than 0!")]
+    ZeroMaxConcurrentAuthentications,
+    #[error("Local connection pool size needs to be greater than 0!")]
+    ZeroLocalPoolSize,
+    #[error("Heartbeat interval needs to be greater than 0!")]
+    ZeroHeartbeatInterval,
+    #[error("Given compression type is not supported for selected protocol!")]
+    CompressionTypeNotSupported,
+    #[error("Beta protocol flag needs to be used together with a beta protocol version!")]
+    BetaProtocolMismatch,
+    #[error("Tracing sample rate needs to be between 0 and 1!")]
+    InvalidTracingSampleRate,
+}
+
+impl SessionConfigViolation {
+    const ALL: [SessionConfigViolation; 12] = [
+        SessionConfigViolation::NoContactPoints,
+        SessionConfigViolation::ZeroTransportBufferSize,
+        SessionConfigViolation::ZeroReadBufferSize,
+        SessionConfigViolation::ZeroEventChannelCapacity,
+        SessionConfigViolation::ZeroHandshakeTimeout,
+        SessionConfigViolation::ZeroAuthenticationTimeout,
+        SessionConfigViolation::ZeroMaxConcurrentAuthentications,
+        SessionConfigViolation::ZeroLocalPoolSize,
+        SessionConfigViolation::ZeroHeartbeatInterval,
+        SessionConfigViolation::CompressionTypeNotSupported,
+        SessionConfigViolation::BetaProtocolMismatch,
+        SessionConfigViolation::InvalidTracingSampleRate,
+    ];
+
+    #[inline]
+    fn mask(self) -> u32 {
+        1 << self as u32
+    }
+}
+
+/// Set of problems found in session builder configuration. Iterates over them in declaration
+/// order of [`SessionConfigViolation`].
+#[derive(Debug, Default, Ord, PartialOrd, Eq, PartialEq, Hash, Copy, Clone)]
+pub struct SessionConfigViolations(u32);
+
+impl SessionConfigViolations {
+    /// Checks if given problem has been found.
+    #[inline]
+    pub fn contains(self, violation: SessionConfigViolation) -> bool {
+        self.0 & violation.mask() != 0
+    }
+
+    /// Returns the number of problems found.
+    #[inline]
+    pub fn len(self) -> usize {
+        self.0.count_ones() as usize
+    }
+
+    #[inline]
+    pub fn is_empty(self) -> bool {
+        self.0 == 0
+    }
+
+    /// Returns all problems found.
+    pub fn iter(self) -> impl Iterator<Item = SessionConfigViolation> {
+        SessionConfigViolation::ALL
+            .iter()
+            .copied()
+            .filter(move |violation| self.contains(*violation))
+    }
+
+    #[inline]
+    fn insert(&mut self, violation: SessionConfigViolation) {
+        self.0 |= violation.mask();
+    }
+}
+
+impl FromIterator<SessionConfigViolation> for SessionConfigViolations {
+    fn from_iter<I: IntoIterator<Item = SessionConfigViolation>>(iter: I) -> Self {
+        let mut violations = SessionConfigViolations::default();
+        for violation in iter {
+            violations.insert(violation);
+        }
+
+        violations
+    }
+}
+
+impl Display for SessionConfigViolations {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.iter().join(" "))
+    }
 }
 
 /// Builder for easy `Session` creation. Requires static `LoadBalancingStrategy`, but otherwise, other
@@ -1003,10 +3005,75 @@ pub trait SessionBuilder<
     #[must_use]
     fn with_compression(self, compression: Compression) -> Self;
 
+    /// Sets minimum envelope body size, in bytes, which gets compressed. Smaller envelopes are
+    /// sent uncompressed, since compressing them wastes CPU time and often makes them larger.
+    /// Responses are compressed at the server's discretion. Only applies to protocols older than
+    /// V5.
+    #[must_use]
+    fn with_compression_threshold(self, compression_threshold: usize) -> Self;
+
+    /// Sets which directions of traffic get compressed, when compression is enabled. Defaults to
+    /// [`CompressionMode::Both`]. Only applies to protocols older than V5, which compress whole
+    /// frames in both directions.
+    #[must_use]
+    fn with_compression_mode(self, compression_mode: CompressionMode) -> Self;
+
+    /// Sets the timeout for steps of the connection startup handshake other than authentication,
+    /// i.e. STARTUP and setting the current keyspace. It is independent of the connect timeout
+    /// in [ConnectionPoolConfig], and expiring results in [Error::HandshakeTimeout](error::Error::HandshakeTimeout).
+    #[must_use]
+    fn with_handshake_timeout(self, handshake_timeout: Duration) -> Self;
+
+    /// Sets the timeout for authenticating a connection, which can take much longer than other
+    /// handshake steps when the cluster uses an external authentication service, e.g. LDAP.
+    /// Expiring results in [Error::AuthenticationTimeout](error::Error::AuthenticationTimeout).
+    /// Defaults to [DEFAULT_AUTHENTICATION_TIMEOUT].
+    #[must_use]
+    fn with_authentication_timeout(self, authentication_timeout: Duration) -> Self;
+
+    /// Sets the maximum number of connections authenticating with a single node at the same time.
+    /// Other connections wait for their turn, which doesn't count towards the authentication
+    /// timeout, so reconnecting many connections at once doesn't overload external
+    /// authentication services. Defaults to [DEFAULT_MAX_CONCURRENT_AUTHENTICATIONS].
+    #[must_use]
+    fn with_max_concurrent_authentications(self, max_concurrent_authentications: usize) -> Self;
+
+    /// Sets the delay between starting consecutive TCP connection attempts to a node reachable
+    /// through multiple addresses, e.g. a contact point hostname resolving to both IPv6 and IPv4
+    /// addresses. Attempts already in progress are not canceled, and the first socket to connect
+    /// is used for the handshake, while the others get closed. Defaults to
+    /// [DEFAULT_CONNECTION_STAGGER_DELAY].
+    #[must_use]
+    fn with_connection_stagger_delay(self, connection_stagger_delay: Duration) -> Self;
+
+    /// Records envelopes sent and received by all connections with given recorder, for debugging
+    /// protocol-level issues. See [frame_recording](crate::frame_recording) for details.
+    #[must_use]
+    fn with_frame_recorder(self, frame_recorder: Arc<FrameRecorder>) -> Self;
+
+    /// Sets statements executed on every new connection, including reconnections and connections
+    /// receiving server events, after the handshake and before the connection is used. Failing
+    /// statements fail the connection, which is then retried according to the reconnection policy.
+    #[must_use]
+    fn with_connection_init(self, init_statements: Vec<InitStatement>) -> Self;
+
+    /// Sets which address family is tried first when connecting to a node reachable through
+    /// multiple addresses. Defaults to [AddressFamilyPreference::Ipv6].
+    #[must_use]
+    fn with_address_family_preference(
+        self,
+        address_family_preference: AddressFamilyPreference,
+    ) -> Self;
+
     /// Set new retry policy.
     #[must_use]
     fn with_retry_policy(self, retry_policy: Box<dyn RetryPolicy + Send + Sync>) -> Self;
 
+    /// Limits retries across the whole session with given budget, consulted after the retry
+    /// policy decides to retry. There is no budget by default.
+    #[must_use]
+    fn with_retry_budget(self, retry_budget: RetryBudget) -> Self;
+
     /// Set new reconnection policy.
     #[must_use]
     fn with_reconnection_policy(
@@ -1036,15 +3103,30 @@ pub trait SessionBuilder<
         speculative_execution_policy: Box<dyn SpeculativeExecutionPolicy + Send + Sync>,
     ) -> Self;
 
-    /// Sets new transport buffer size. High values are recommended with large amounts of in flight
-    /// queries.
+    /// Sets how many requests can be queued for writing on a single connection. High values are
+    /// recommended with large amounts of in flight queries.
     #[must_use]
     fn with_transport_buffer_size(self, transport_buffer_size: usize) -> Self;
 
+    /// Sets a fixed size of connection read buffers in bytes. By default, read buffers start at
+    /// [`MIN_ADAPTIVE_READ_BUFFER_SIZE`](crate::transport::MIN_ADAPTIVE_READ_BUFFER_SIZE) and
+    /// grow up to [`MAX_ADAPTIVE_READ_BUFFER_SIZE`](crate::transport::MAX_ADAPTIVE_READ_BUFFER_SIZE)
+    /// when responses need multiple reads, shrinking back when connections are idle. A fixed size
+    /// disables this adaptation.
+    #[must_use]
+    fn with_read_buffer_size(self, read_buffer_size: usize) -> Self;
+
     /// Sets NODELAY for given session connections.
     #[must_use]
     fn with_tcp_nodelay(self, tcp_nodelay: bool) -> Self;
 
+    /// Fails connection attempts when NODELAY, the only socket option set by the driver, cannot be
+    /// set. By default, such failures are logged as warnings naming the option and the node, and
+    /// connections proceed with default options, since some environments, e.g. container
+    /// runtimes, don't allow changing them.
+    #[must_use]
+    fn with_strict_socket_options(self, strict_socket_options: bool) -> Self;
+
     /// Sets event channel capacity. If the driver receives more server events than the capacity,
     /// some events might get dropped. This can result in the driver operating in a sub-optimal way.
     #[must_use]
@@ -1064,10 +3146,135 @@ pub trait SessionBuilder<
     fn with_keyspace(self, keyspace: String) -> Self;
 
     /// Sets the beta protocol flag. Server will respond with ERROR if protocol version is marked as
-    /// beta on server and client does not provide this flag.
+    /// beta on server and client does not provide this flag. The flag is only allowed with beta
+    /// protocol versions (see [`Version::is_beta`]), to avoid accidentally using it in production,
+    /// and is required for them.
     #[must_use]
     fn with_beta_protocol(self, beta_protocol: bool) -> Self;
 
+    /// Disables checking values bound to prepared statements against their parameter types.
+    /// By default, numeric values are converted to the parameter type, and values which would get
+    /// truncated or lose precision result in
+    /// [Error::InvalidBoundValue](error::Error::InvalidBoundValue) before sending the request.
+    /// Lenient conversions send values as they are, leaving validation to the server.
+    #[must_use]
+    fn with_lenient_conversions(self, lenient_conversions: bool) -> Self;
+
+    /// Enables a debug assertion, which fails when a query or a statement being prepared refers
+    /// to a table without qualifying it with a keyspace, while no keyspace is set, neither for
+    /// the session nor for the statement. Helps to avoid relying on `USE` statements, which only
+    /// affect the connection they're sent on. Table references are detected heuristically and the
+    /// check is only performed in builds with debug assertions enabled.
+    #[must_use]
+    fn with_keyspace_qualification_check(self, keyspace_qualification_check: bool) -> Self;
+
+    /// Locks the keyspace set while building the session, so `USE` statements sent through the
+    /// session get rejected with [`Error::KeyspaceLocked`](error::Error::KeyspaceLocked). Makes
+    /// sessions shared by multiple components safer, since none of them can switch the keyspace
+    /// of others.
+    #[must_use]
+    fn with_keyspace_lock(self, keyspace_lock: bool) -> Self;
+
+    /// Sets a redactor hiding sensitive bound values, e.g. passwords, when statements are
+    /// displayed. Failed statements are logged at debug level along with their values, which are
+    /// redacted before being passed to the log.
+    #[must_use]
+    fn with_redactor(self, redactor: Redactor) -> Self;
+
+    /// Sets how long [`Session::execute_schema`] waits for nodes to agree on the schema after a
+    /// schema change.
+    #[must_use]
+    fn with_schema_agreement_timeout(self, schema_agreement_timeout: Duration) -> Self;
+
+    /// Enables token-aware routing of unprepared statements with positional values. Partition key
+    /// values are found by analyzing statement text with [`StatementAnalysis`], using partition
+    /// key columns fetched once per table. Statements which can't be analyzed are routed without
+    /// a routing key, like before.
+    #[must_use]
+    fn with_unprepared_routing(self, unprepared_routing: bool) -> Self;
+
+    /// Makes requests fail immediately with
+    /// [`Error::NoNodesAvailable`](cassandra_protocol::error::Error::NoNodesAvailable) when none of
+    /// the nodes in their query plan has a live connection, instead of waiting for reconnection,
+    /// e.g. to shed load during a cluster outage. Nodes without live connections are skipped, while
+    /// reconnection continues in the background. Node state changes are reported to the listener
+    /// set with [`with_node_state_listener`](Self::with_node_state_listener), e.g. to expose
+    /// readiness of the application.
+    ///
+    /// Unlike [`ReconnectWaitMode::FailFast`](crate::cluster::connection_pool::ReconnectWaitMode::FailFast),
+    /// which makes a pool without live connections fail the attempt to get one, disconnected
+    /// nodes are skipped before sending, regardless of the pool's wait mode. Skipped nodes don't
+    /// consume retry decisions, and requests fail with `NoNodesAvailable` rather than the last
+    /// connection error.
+    #[must_use]
+    fn with_fail_fast_when_disconnected(self, fail_fast_when_disconnected: bool) -> Self;
+
+    /// Makes executions with positional values fail with
+    /// [`Error::PreparedMetadataChanged`](cassandra_protocol::error::Error::PreparedMetadataChanged)
+    /// when re-preparing their statement returns different bound variables, e.g. after an
+    /// `ALTER TABLE`, since the values might no longer match them. Executions with named values
+    /// proceed. Changes are reported once to the [`PreparedMetadataListener`] and the
+    /// [`WarningPolicy`], regardless of this setting.
+    #[must_use]
+    fn with_strict_prepared_metadata(self, strict_prepared_metadata: bool) -> Self;
+
+    /// Sets a listener notified when the server reports changed result metadata of a prepared
+    /// statement. The new metadata id is always used by subsequent executions, regardless of the
+    /// listener.
+    #[must_use]
+    fn with_prepared_metadata_listener(
+        self,
+        prepared_metadata_listener: Arc<dyn PreparedMetadataListener + Send + Sync>,
+    ) -> Self;
+
+    /// Sets the listener notified about connection progress and state changes of nodes, e.g. when
+    /// pools to nodes are established during warmup or after topology changes.
+    #[must_use]
+    fn with_node_state_listener(
+        self,
+        node_state_listener: Arc<dyn NodeStateListener + Send + Sync>,
+    ) -> Self;
+
+    /// Sets the policy deciding what happens with responses containing known classes of server
+    /// warnings, e.g. about reading too many tombstones. Warnings are requested for all
+    /// statements, if the policy handles any class of them.
+    #[must_use]
+    fn with_warning_policy(self, warning_policy: WarningPolicy) -> Self;
+
+    /// Sets the fraction of requests, between 0 and 1, which get traced server-side, in addition
+    /// to the ones with tracing enabled in their statement parameters. Allows continuous sampling
+    /// of traces, e.g. `0.01` traces 1% of requests. Defaults to 0. Prepare requests are not
+    /// sampled.
+    #[must_use]
+    fn with_tracing_sample_rate(self, tracing_sample_rate: f64) -> Self;
+
+    /// Enables or disables OPTIONS requests used for heartbeats and idle connection verification.
+    /// Some proxies reject OPTIONS, in which case a lightweight `SELECT key FROM system.local`
+    /// query is sent instead. Server errors in response to either probe don't mark the connection
+    /// as broken. The handshake is the same in both cases. Enabled by default.
+    #[must_use]
+    fn with_options_probe(self, options_probe: bool) -> Self;
+
+    /// Sets the consistency of queries the driver sends on its own, e.g. to discover nodes and
+    /// schema, or to probe connections. They are kept separate from application statements, so
+    /// a consistency like `EACH_QUORUM` used by the application can't make them fail when a
+    /// datacenter is down, and they are never affected by retry policies. Defaults to `ONE`.
+    #[must_use]
+    fn with_system_query_consistency(self, consistency: Consistency) -> Self;
+
+    /// Preloads statements prepared by another session, exported with
+    /// [`Session::prepared_cache_snapshot`]. Preparing an imported statement returns it without
+    /// contacting the cluster. If the server no longer knows its id, it gets re-prepared on first
+    /// execution.
+    #[must_use]
+    fn with_prepared_cache_snapshot(self, snapshot: PreparedCacheSnapshot) -> Self;
+
+    /// Sets the maximum number of statements kept in the prepared statement cache, including
+    /// imported ones. The least recently used statements are evicted first. Defaults to
+    /// [`DEFAULT_PREPARED_CACHE_CAPACITY`].
+    #[must_use]
+    fn with_prepared_cache_capacity(self, capacity: usize) -> Self;
+
     /// Builds the resulting session.
     fn build(self) -> BoxFuture<'static, Result<Session<T, CM, LB>, SessionBuildError>>;
 }
@@ -1081,92 +3288,286 @@ pub struct TcpSessionBuilder<
     frame_encoder_factory: Box<dyn FrameEncodingFactory + Send + Sync>,
 }
 
-impl<LB: LoadBalancingStrategy<TransportTcp, TcpConnectionManager> + Send + Sync + 'static>
-    TcpSessionBuilder<LB>
-{
-    //noinspection DuplicatedCode
-    /// Creates a new builder with default session configuration.
-    pub fn new(load_balancing: LB, node_config: NodeTcpConfig) -> Self {
-        TcpSessionBuilder {
-            config: SessionConfig::new(load_balancing),
-            node_config,
-            frame_encoder_factory: Box::<ProtocolFrameEncodingFactory>::default(),
-        }
+impl<LB: LoadBalancingStrategy<TransportTcp, TcpConnectionManager> + Send + Sync + 'static>
+    TcpSessionBuilder<LB>
+{
+    //noinspection DuplicatedCode
+    /// Creates a new builder with default session configuration.
+    pub fn new(load_balancing: LB, node_config: NodeTcpConfig) -> Self {
+        TcpSessionBuilder {
+            config: SessionConfig::new(load_balancing),
+            node_config,
+            frame_encoder_factory: Box::<ProtocolFrameEncodingFactory>::default(),
+        }
+    }
+
+    /// Creates a new builder configured by given [`ConnectionString`], resolving its hosts.
+    /// Connection strings enabling TLS are rejected - use `RustlsSessionBuilder` for those. So are
+    /// connection strings setting consistency, which needs to be set in statement parameters.
+    /// Setting a connection pool config overrides the connect timeout from the connection string.
+    pub async fn from_connection_string(
+        load_balancing: LB,
+        connection_string: &str,
+    ) -> error::Result<Self> {
+        let connection_string: ConnectionString = connection_string.parse()?;
+        if connection_string.tls() {
+            return Err(error::Error::General(
+                "Connection string requires TLS, which is not supported by TCP sessions!".into(),
+            ));
+        }
+
+        Self::from_parsed_connection_string(load_balancing, &connection_string).await
+    }
+
+    /// Creates a new builder configured by environment variables, as described in
+    /// [`ConnectionString::from_env`], resolving contact points. Values from the environment
+    /// are overridden by builder methods called afterwards.
+    pub async fn from_env(load_balancing: LB) -> error::Result<Self> {
+        let connection_string = ConnectionString::from_env()?;
+        Self::from_parsed_connection_string(load_balancing, &connection_string).await
+    }
+
+    async fn from_parsed_connection_string(
+        load_balancing: LB,
+        connection_string: &ConnectionString,
+    ) -> error::Result<Self> {
+        let node_config = NodeTcpConfigBuilder::new()
+            .with_contact_points(connection_string.contact_points().to_vec())
+            .with_authenticator_provider(connection_string.authenticator_provider())
+            .build()
+            .await?;
+
+        let mut builder = Self::new(load_balancing, node_config);
+        builder.config.apply_connection_string(connection_string)?;
+
+        Ok(builder)
+    }
+}
+
+impl<LB: LoadBalancingStrategy<TransportTcp, TcpConnectionManager> + Send + Sync + 'static>
+    SessionBuilder<TransportTcp, TcpConnectionManager, LB> for TcpSessionBuilder<LB>
+{
+    fn with_compression(mut self, compression: Compression) -> Self {
+        self.config.compression = compression;
+        self
+    }
+
+    fn with_compression_threshold(mut self, compression_threshold: usize) -> Self {
+        self.config.compression_threshold = compression_threshold;
+        self
+    }
+
+    fn with_compression_mode(mut self, compression_mode: CompressionMode) -> Self {
+        self.config.compression_mode = compression_mode;
+        self
+    }
+
+    fn with_handshake_timeout(mut self, handshake_timeout: Duration) -> Self {
+        self.config.handshake_timeout = handshake_timeout;
+        self
+    }
+
+    fn with_authentication_timeout(mut self, authentication_timeout: Duration) -> Self {
+        self.config.authentication_timeout = authentication_timeout;
+        self
+    }
+
+    fn with_max_concurrent_authentications(
+        mut self,
+        max_concurrent_authentications: usize,
+    ) -> Self {
+        self.config.max_concurrent_authentications = max_concurrent_authentications;
+        self
+    }
+
+    fn with_connection_stagger_delay(mut self, connection_stagger_delay: Duration) -> Self {
+        self.config.connection_stagger_delay = connection_stagger_delay;
+        self
+    }
+
+    fn with_frame_recorder(mut self, frame_recorder: Arc<FrameRecorder>) -> Self {
+        self.config.frame_recorder = Some(frame_recorder);
+        self
+    }
+
+    fn with_connection_init(mut self, init_statements: Vec<InitStatement>) -> Self {
+        self.config.init_statements = init_statements;
+        self
+    }
+
+    fn with_address_family_preference(
+        mut self,
+        address_family_preference: AddressFamilyPreference,
+    ) -> Self {
+        self.config.address_family_preference = address_family_preference;
+        self
+    }
+
+    fn with_retry_policy(mut self, retry_policy: Box<dyn RetryPolicy + Send + Sync>) -> Self {
+        self.config.retry_policy = retry_policy;
+        self
+    }
+
+    fn with_retry_budget(mut self, retry_budget: RetryBudget) -> Self {
+        self.config.retry_budget = Some(retry_budget);
+        self
+    }
+
+    fn with_reconnection_policy(
+        mut self,
+        reconnection_policy: Arc<dyn ReconnectionPolicy + Send + Sync>,
+    ) -> Self {
+        self.config.reconnection_policy = reconnection_policy;
+        self
+    }
+
+    fn with_frame_encoder_factory(
+        mut self,
+        frame_encoder_factory: Box<dyn FrameEncodingFactory + Send + Sync>,
+    ) -> Self {
+        self.frame_encoder_factory = frame_encoder_factory;
+        self
+    }
+
+    fn with_node_distance_evaluator(
+        mut self,
+        node_distance_evaluator: Box<dyn NodeDistanceEvaluator + Send + Sync>,
+    ) -> Self {
+        self.config.node_distance_evaluator = node_distance_evaluator;
+        self
+    }
+
+    fn with_speculative_execution_policy(
+        mut self,
+        speculative_execution_policy: Box<dyn SpeculativeExecutionPolicy + Send + Sync>,
+    ) -> Self {
+        self.config.speculative_execution_policy = Some(speculative_execution_policy);
+        self
+    }
+
+    fn with_transport_buffer_size(mut self, transport_buffer_size: usize) -> Self {
+        self.config.transport_buffer_size = transport_buffer_size;
+        self
+    }
+
+    fn with_read_buffer_size(mut self, read_buffer_size: usize) -> Self {
+        self.config.read_buffer_size = Some(read_buffer_size);
+        self
+    }
+
+    fn with_tcp_nodelay(mut self, tcp_nodelay: bool) -> Self {
+        self.config.tcp_nodelay = tcp_nodelay;
+        self
+    }
+
+    fn with_strict_socket_options(mut self, strict_socket_options: bool) -> Self {
+        self.config.strict_socket_options = strict_socket_options;
+        self
+    }
+
+    fn with_event_channel_capacity(mut self, event_channel_capacity: usize) -> Self {
+        self.config.event_channel_capacity = event_channel_capacity;
+        self
+    }
+
+    fn with_connection_pool_config(mut self, connection_pool_config: ConnectionPoolConfig) -> Self {
+        self.config.connection_pool_config = connection_pool_config;
+        self
+    }
+
+    fn with_keyspace(mut self, keyspace: String) -> Self {
+        self.config.keyspace = Some(keyspace);
+        self
+    }
+
+    fn with_beta_protocol(mut self, beta_protocol: bool) -> Self {
+        self.node_config.beta_protocol = beta_protocol;
+        self
+    }
+
+    fn with_lenient_conversions(mut self, lenient_conversions: bool) -> Self {
+        self.config.lenient_conversions = lenient_conversions;
+        self
+    }
+
+    fn with_keyspace_qualification_check(mut self, keyspace_qualification_check: bool) -> Self {
+        self.config.keyspace_qualification_check = keyspace_qualification_check;
+        self
+    }
+
+    fn with_keyspace_lock(mut self, keyspace_lock: bool) -> Self {
+        self.config.keyspace_lock = keyspace_lock;
+        self
+    }
+
+    fn with_redactor(mut self, redactor: Redactor) -> Self {
+        self.config.redactor = redactor;
+        self
     }
-}
 
-impl<LB: LoadBalancingStrategy<TransportTcp, TcpConnectionManager> + Send + Sync + 'static>
-    SessionBuilder<TransportTcp, TcpConnectionManager, LB> for TcpSessionBuilder<LB>
-{
-    fn with_compression(mut self, compression: Compression) -> Self {
-        self.config.compression = compression;
+    fn with_schema_agreement_timeout(mut self, schema_agreement_timeout: Duration) -> Self {
+        self.config.schema_agreement_timeout = schema_agreement_timeout;
         self
     }
 
-    fn with_retry_policy(mut self, retry_policy: Box<dyn RetryPolicy + Send + Sync>) -> Self {
-        self.config.retry_policy = retry_policy;
+    fn with_unprepared_routing(mut self, unprepared_routing: bool) -> Self {
+        self.config.unprepared_routing = unprepared_routing;
         self
     }
 
-    fn with_reconnection_policy(
-        mut self,
-        reconnection_policy: Arc<dyn ReconnectionPolicy + Send + Sync>,
-    ) -> Self {
-        self.config.reconnection_policy = reconnection_policy;
+    fn with_fail_fast_when_disconnected(mut self, fail_fast_when_disconnected: bool) -> Self {
+        self.config.fail_fast_when_disconnected = fail_fast_when_disconnected;
         self
     }
 
-    fn with_frame_encoder_factory(
-        mut self,
-        frame_encoder_factory: Box<dyn FrameEncodingFactory + Send + Sync>,
-    ) -> Self {
-        self.frame_encoder_factory = frame_encoder_factory;
+    fn with_strict_prepared_metadata(mut self, strict_prepared_metadata: bool) -> Self {
+        self.config.strict_prepared_metadata = strict_prepared_metadata;
         self
     }
 
-    fn with_node_distance_evaluator(
+    fn with_prepared_metadata_listener(
         mut self,
-        node_distance_evaluator: Box<dyn NodeDistanceEvaluator + Send + Sync>,
+        prepared_metadata_listener: Arc<dyn PreparedMetadataListener + Send + Sync>,
     ) -> Self {
-        self.config.node_distance_evaluator = node_distance_evaluator;
+        self.config.prepared_metadata_listener = Some(prepared_metadata_listener);
         self
     }
 
-    fn with_speculative_execution_policy(
+    fn with_node_state_listener(
         mut self,
-        speculative_execution_policy: Box<dyn SpeculativeExecutionPolicy + Send + Sync>,
+        node_state_listener: Arc<dyn NodeStateListener + Send + Sync>,
     ) -> Self {
-        self.config.speculative_execution_policy = Some(speculative_execution_policy);
+        self.config.node_state_listener = Some(node_state_listener);
         self
     }
 
-    fn with_transport_buffer_size(mut self, transport_buffer_size: usize) -> Self {
-        self.config.transport_buffer_size = transport_buffer_size;
+    fn with_warning_policy(mut self, warning_policy: WarningPolicy) -> Self {
+        self.config.warning_policy = warning_policy;
         self
     }
 
-    fn with_tcp_nodelay(mut self, tcp_nodelay: bool) -> Self {
-        self.config.tcp_nodelay = tcp_nodelay;
+    fn with_tracing_sample_rate(mut self, tracing_sample_rate: f64) -> Self {
+        self.config.tracing_sample_rate = tracing_sample_rate;
         self
     }
 
-    fn with_event_channel_capacity(mut self, event_channel_capacity: usize) -> Self {
-        self.config.event_channel_capacity = event_channel_capacity;
+    fn with_options_probe(mut self, options_probe: bool) -> Self {
+        self.config.options_probe = options_probe;
         self
     }
 
-    fn with_connection_pool_config(mut self, connection_pool_config: ConnectionPoolConfig) -> Self {
-        self.config.connection_pool_config = connection_pool_config;
+    fn with_system_query_consistency(mut self, consistency: Consistency) -> Self {
+        self.config.system_query_consistency = consistency;
         self
     }
 
-    fn with_keyspace(mut self, keyspace: String) -> Self {
-        self.config.keyspace = Some(keyspace);
+    fn with_prepared_cache_snapshot(mut self, snapshot: PreparedCacheSnapshot) -> Self {
+        self.config.prepared_cache_snapshot = Some(snapshot);
         self
     }
 
-    fn with_beta_protocol(mut self, beta_protocol: bool) -> Self {
-        self.node_config.beta_protocol = beta_protocol;
+    fn with_prepared_cache_capacity(mut self, capacity: usize) -> Self {
+        self.config.prepared_cache_capacity = capacity;
         self
     }
 
@@ -1177,37 +3578,37 @@ impl<LB: LoadBalancingStrategy<TransportTcp, TcpConnectionManager> + Send + Sync
         Result<Session<TransportTcp, TcpConnectionManager, LB>, SessionBuildError>,
     > {
         async move {
-            match verify_compression_configuration(
+            self.config.validate(
+                &self.node_config.contact_points,
                 self.node_config.version,
-                self.config.compression,
-            ) {
-                Ok(()) => {
-                    let (keyspace_holder, keyspace_receiver) = create_keyspace_holder();
-                    let connection_manager = TcpConnectionManager::new(
-                        self.node_config.authenticator_provider,
-                        keyspace_holder.clone(),
-                        self.frame_encoder_factory,
-                        self.config.compression,
-                        self.config.transport_buffer_size,
-                        self.config.tcp_nodelay,
-                        self.node_config.version,
-                        #[cfg(feature = "http-proxy")]
-                        self.node_config.http_proxy,
-                    );
-
-                    self.config
-                        .into_session(
-                            keyspace_holder,
-                            keyspace_receiver,
-                            self.node_config.contact_points,
-                            connection_manager,
-                            self.node_config.version,
-                            self.node_config.beta_protocol,
-                        )
-                        .await
-                }
-                Err(err) => Err(err),
-            }
+                self.node_config.beta_protocol,
+            )?;
+
+            let connection_manager_config = self
+                .config
+                .connection_manager_config(self.node_config.version);
+            #[cfg(feature = "http-proxy")]
+            let connection_manager_config =
+                connection_manager_config.with_http_proxy(self.node_config.http_proxy);
+
+            let (keyspace_holder, keyspace_receiver) = create_keyspace_holder();
+            let connection_manager = TcpConnectionManager::new(
+                self.node_config.authenticator_provider,
+                keyspace_holder.clone(),
+                self.frame_encoder_factory,
+                connection_manager_config.build(),
+            );
+
+            self.config
+                .into_session(
+                    keyspace_holder,
+                    keyspace_receiver,
+                    self.node_config.contact_points,
+                    connection_manager,
+                    self.node_config.version,
+                    self.node_config.beta_protocol,
+                )
+                .await
         }
         .boxed()
     }
@@ -1236,6 +3637,35 @@ impl<LB: LoadBalancingStrategy<TransportRustls, RustlsConnectionManager> + Send
             frame_encoder_factory: Box::<ProtocolFrameEncodingFactory>::default(),
         }
     }
+
+    /// Creates a new builder configured by given [`ConnectionString`], resolving its hosts. The
+    /// connection string needs to enable TLS, and not set consistency, which needs to be set in
+    /// statement parameters. Setting a connection pool config overrides the
+    /// connect timeout from the connection string.
+    pub async fn from_connection_string(
+        load_balancing: LB,
+        connection_string: &str,
+        dns_name: ServerName<'static>,
+        config: Arc<ClientConfig>,
+    ) -> error::Result<Self> {
+        let connection_string: ConnectionString = connection_string.parse()?;
+        if !connection_string.tls() {
+            return Err(error::Error::General(
+                "Connection string doesn't enable TLS, which is required by TLS sessions!".into(),
+            ));
+        }
+
+        let node_config = NodeRustlsConfigBuilder::new(dns_name, config)
+            .with_contact_points(connection_string.contact_points().to_vec())
+            .with_authenticator_provider(connection_string.authenticator_provider())
+            .build()
+            .await?;
+
+        let mut builder = Self::new(load_balancing, node_config);
+        builder.config.apply_connection_string(&connection_string)?;
+
+        Ok(builder)
+    }
 }
 
 #[cfg(feature = "rust-tls")]
@@ -1248,11 +3678,67 @@ impl<
         self
     }
 
+    fn with_compression_threshold(mut self, compression_threshold: usize) -> Self {
+        self.config.compression_threshold = compression_threshold;
+        self
+    }
+
+    fn with_compression_mode(mut self, compression_mode: CompressionMode) -> Self {
+        self.config.compression_mode = compression_mode;
+        self
+    }
+
+    fn with_handshake_timeout(mut self, handshake_timeout: Duration) -> Self {
+        self.config.handshake_timeout = handshake_timeout;
+        self
+    }
+
+    fn with_authentication_timeout(mut self, authentication_timeout: Duration) -> Self {
+        self.config.authentication_timeout = authentication_timeout;
+        self
+    }
+
+    fn with_max_concurrent_authentications(
+        mut self,
+        max_concurrent_authentications: usize,
+    ) -> Self {
+        self.config.max_concurrent_authentications = max_concurrent_authentications;
+        self
+    }
+
+    fn with_connection_stagger_delay(mut self, connection_stagger_delay: Duration) -> Self {
+        self.config.connection_stagger_delay = connection_stagger_delay;
+        self
+    }
+
+    fn with_frame_recorder(mut self, frame_recorder: Arc<FrameRecorder>) -> Self {
+        self.config.frame_recorder = Some(frame_recorder);
+        self
+    }
+
+    fn with_connection_init(mut self, init_statements: Vec<InitStatement>) -> Self {
+        self.config.init_statements = init_statements;
+        self
+    }
+
+    fn with_address_family_preference(
+        mut self,
+        address_family_preference: AddressFamilyPreference,
+    ) -> Self {
+        self.config.address_family_preference = address_family_preference;
+        self
+    }
+
     fn with_retry_policy(mut self, retry_policy: Box<dyn RetryPolicy + Send + Sync>) -> Self {
         self.config.retry_policy = retry_policy;
         self
     }
 
+    fn with_retry_budget(mut self, retry_budget: RetryBudget) -> Self {
+        self.config.retry_budget = Some(retry_budget);
+        self
+    }
+
     fn with_reconnection_policy(
         mut self,
         reconnection_policy: Arc<dyn ReconnectionPolicy + Send + Sync>,
@@ -1290,11 +3776,21 @@ impl<
         self
     }
 
+    fn with_read_buffer_size(mut self, read_buffer_size: usize) -> Self {
+        self.config.read_buffer_size = Some(read_buffer_size);
+        self
+    }
+
     fn with_tcp_nodelay(mut self, tcp_nodelay: bool) -> Self {
         self.config.tcp_nodelay = tcp_nodelay;
         self
     }
 
+    fn with_strict_socket_options(mut self, strict_socket_options: bool) -> Self {
+        self.config.strict_socket_options = strict_socket_options;
+        self
+    }
+
     fn with_event_channel_capacity(mut self, event_channel_capacity: usize) -> Self {
         self.config.event_channel_capacity = event_channel_capacity;
         self
@@ -1315,6 +3811,92 @@ impl<
         self
     }
 
+    fn with_lenient_conversions(mut self, lenient_conversions: bool) -> Self {
+        self.config.lenient_conversions = lenient_conversions;
+        self
+    }
+
+    fn with_keyspace_qualification_check(mut self, keyspace_qualification_check: bool) -> Self {
+        self.config.keyspace_qualification_check = keyspace_qualification_check;
+        self
+    }
+
+    fn with_keyspace_lock(mut self, keyspace_lock: bool) -> Self {
+        self.config.keyspace_lock = keyspace_lock;
+        self
+    }
+
+    fn with_redactor(mut self, redactor: Redactor) -> Self {
+        self.config.redactor = redactor;
+        self
+    }
+
+    fn with_schema_agreement_timeout(mut self, schema_agreement_timeout: Duration) -> Self {
+        self.config.schema_agreement_timeout = schema_agreement_timeout;
+        self
+    }
+
+    fn with_unprepared_routing(mut self, unprepared_routing: bool) -> Self {
+        self.config.unprepared_routing = unprepared_routing;
+        self
+    }
+
+    fn with_fail_fast_when_disconnected(mut self, fail_fast_when_disconnected: bool) -> Self {
+        self.config.fail_fast_when_disconnected = fail_fast_when_disconnected;
+        self
+    }
+
+    fn with_strict_prepared_metadata(mut self, strict_prepared_metadata: bool) -> Self {
+        self.config.strict_prepared_metadata = strict_prepared_metadata;
+        self
+    }
+
+    fn with_prepared_metadata_listener(
+        mut self,
+        prepared_metadata_listener: Arc<dyn PreparedMetadataListener + Send + Sync>,
+    ) -> Self {
+        self.config.prepared_metadata_listener = Some(prepared_metadata_listener);
+        self
+    }
+
+    fn with_node_state_listener(
+        mut self,
+        node_state_listener: Arc<dyn NodeStateListener + Send + Sync>,
+    ) -> Self {
+        self.config.node_state_listener = Some(node_state_listener);
+        self
+    }
+
+    fn with_warning_policy(mut self, warning_policy: WarningPolicy) -> Self {
+        self.config.warning_policy = warning_policy;
+        self
+    }
+
+    fn with_tracing_sample_rate(mut self, tracing_sample_rate: f64) -> Self {
+        self.config.tracing_sample_rate = tracing_sample_rate;
+        self
+    }
+
+    fn with_options_probe(mut self, options_probe: bool) -> Self {
+        self.config.options_probe = options_probe;
+        self
+    }
+
+    fn with_system_query_consistency(mut self, consistency: Consistency) -> Self {
+        self.config.system_query_consistency = consistency;
+        self
+    }
+
+    fn with_prepared_cache_snapshot(mut self, snapshot: PreparedCacheSnapshot) -> Self {
+        self.config.prepared_cache_snapshot = Some(snapshot);
+        self
+    }
+
+    fn with_prepared_cache_capacity(mut self, capacity: usize) -> Self {
+        self.config.prepared_cache_capacity = capacity;
+        self
+    }
+
     fn build(
         self,
     ) -> BoxFuture<
@@ -1322,39 +3904,39 @@ impl<
         Result<Session<TransportRustls, RustlsConnectionManager, LB>, SessionBuildError>,
     > {
         async move {
-            match verify_compression_configuration(
+            self.config.validate(
+                &self.node_config.contact_points,
                 self.node_config.version,
-                self.config.compression,
-            ) {
-                Ok(()) => {
-                    let (keyspace_holder, keyspace_receiver) = create_keyspace_holder();
-                    let connection_manager = RustlsConnectionManager::new(
-                        self.node_config.dns_name,
-                        self.node_config.authenticator_provider,
-                        self.node_config.config,
-                        keyspace_holder.clone(),
-                        self.frame_encoder_factory,
-                        self.config.compression,
-                        self.config.transport_buffer_size,
-                        self.config.tcp_nodelay,
-                        self.node_config.version,
-                        #[cfg(feature = "http-proxy")]
-                        self.node_config.http_proxy,
-                    );
-
-                    self.config
-                        .into_session(
-                            keyspace_holder,
-                            keyspace_receiver,
-                            self.node_config.contact_points,
-                            connection_manager,
-                            self.node_config.version,
-                            self.node_config.beta_protocol,
-                        )
-                        .await
-                }
-                Err(err) => Err(err),
-            }
+                self.node_config.beta_protocol,
+            )?;
+
+            let connection_manager_config = self
+                .config
+                .connection_manager_config(self.node_config.version);
+            #[cfg(feature = "http-proxy")]
+            let connection_manager_config =
+                connection_manager_config.with_http_proxy(self.node_config.http_proxy);
+
+            let (keyspace_holder, keyspace_receiver) = create_keyspace_holder();
+            let connection_manager = RustlsConnectionManager::new(
+                self.node_config.dns_name,
+                self.node_config.authenticator_provider,
+                self.node_config.config,
+                keyspace_holder.clone(),
+                self.frame_encoder_factory,
+                connection_manager_config.build(),
+            );
+
+            self.config
+                .into_session(
+                    keyspace_holder,
+                    keyspace_receiver,
+                    self.node_config.contact_points,
+                    connection_manager,
+                    self.node_config.version,
+                    self.node_config.beta_protocol,
+                )
+                .await
         }
         .boxed()
     }
@@ -1362,8 +3944,72 @@ impl<
 
 #[cfg(test)]
 mod tests {
-    use crate::cluster::session::prepare_flags;
-    use cassandra_protocol::frame::Flags;
+    use crate::cluster::connection_manager::MockConnectionManager;
+    use crate::cluster::connection_pool::ConnectionPoolConfigBuilder;
+    use crate::cluster::connection_pool::ReconnectWaitMode;
+    use crate::cluster::send_envelope::{send_envelope, send_envelope_with_hook};
+    use crate::cluster::session::{
+        connected_nodes, count_query, is_statement_error, prepare_flags, sample_tracing,
+        token_range_envelope, token_range_page, verify_beta_protocol_configuration, DynTcpSession,
+        SessionBuildError, SessionConfig, SessionConfigViolation, SessionConfigViolations,
+        TcpSessionBuilder,
+    };
+    use crate::cluster::test_nodes::{
+        addr, assert_send, connection_pool_factory, local_node, local_nodes, TestNode,
+    };
+    use crate::cluster::{Murmur3Token, TcpConnectionManager, TokenRange};
+    use crate::load_balancing::RoundRobinLoadBalancingStrategy;
+    use crate::retry::{
+        ConstantReconnectionPolicy, DefaultRetryPolicy, FallthroughRetryPolicy, LazyRetrySession,
+    };
+    use crate::statement::StatementParamsBuilder;
+    use crate::transport::{MockCdrsTransport, TransportTcp};
+    use cassandra_protocol::compression::Compression;
+    use cassandra_protocol::consistency::Consistency;
+    use cassandra_protocol::error::Error;
+    use cassandra_protocol::frame::message_error::{ErrorBody, ErrorType};
+    use cassandra_protocol::frame::message_request::RequestBody;
+    use cassandra_protocol::frame::{Direction, Envelope, Flags, Opcode, Version};
+    use cassandra_protocol::query::{PreparedQuery, QueryValues};
+    use std::future::IntoFuture;
+    use std::io;
+    use std::iter::FromIterator;
+    use std::net::SocketAddr;
+    use std::sync::{Arc, Mutex};
+    use std::time::Duration;
+
+    type TestSessionConfig = SessionConfig<
+        TransportTcp,
+        TcpConnectionManager,
+        RoundRobinLoadBalancingStrategy<TransportTcp, TcpConnectionManager>,
+    >;
+
+    #[test]
+    fn count_query_test() {
+        assert_eq!(count_query(" ks.t "), "SELECT count(*) FROM ks.t");
+        assert_eq!(
+            count_query("SELECT count(*) FROM ks.t WHERE id = 1"),
+            "SELECT count(*) FROM ks.t WHERE id = 1"
+        );
+    }
+
+    #[test]
+    fn should_detect_statement_errors() {
+        let server_error = |ty| Error::Server {
+            body: ErrorBody {
+                message: "error".into(),
+                ty,
+            },
+            addr: "127.0.0.1:9042".parse().unwrap(),
+        };
+
+        assert!(is_statement_error(&server_error(ErrorType::Invalid)));
+        assert!(is_statement_error(&server_error(ErrorType::Syntax)));
+        assert!(!is_statement_error(&server_error(ErrorType::Overloaded)));
+        assert!(!is_statement_error(&Error::RequestNotSent(
+            "127.0.0.1:9042".parse().unwrap()
+        )));
+    }
 
     #[test]
     fn prepare_flags_test() {
@@ -1376,4 +4022,325 @@ mod tests {
         assert!(all.contains(Flags::WARNING));
         assert!(all.contains(Flags::BETA));
     }
+
+    #[test]
+    fn sample_tracing_test() {
+        assert!(sample_tracing(true, 0.0));
+        assert!((0..1000).all(|_| !sample_tracing(false, 0.0)));
+        assert!((0..1000).all(|_| sample_tracing(false, 1.0)));
+    }
+
+    #[test]
+    fn verify_beta_protocol_configuration_test() {
+        assert!(verify_beta_protocol_configuration(Version::V6, true).is_ok());
+        assert!(verify_beta_protocol_configuration(Version::V4, false).is_ok());
+        assert!(verify_beta_protocol_configuration(Version::V5, true).is_err());
+        assert!(verify_beta_protocol_configuration(Version::V6, false).is_err());
+    }
+
+    #[tokio::test]
+    async fn should_configure_builder_from_connection_string() {
+        let builder = TcpSessionBuilder::from_connection_string(
+            RoundRobinLoadBalancingStrategy::new(),
+            "cassandra://127.0.0.1/ks?compression=lz4",
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(builder.config.keyspace.as_deref(), Some("ks"));
+        assert_eq!(builder.config.compression, Compression::Lz4);
+
+        // there's no session-wide consistency to apply it to
+        assert!(TcpSessionBuilder::from_connection_string(
+            RoundRobinLoadBalancingStrategy::new(),
+            "cassandra://127.0.0.1/ks?consistency=quorum",
+        )
+        .await
+        .is_err());
+    }
+
+    #[test]
+    fn should_accept_default_session_config() {
+        let config = TestSessionConfig::new(RoundRobinLoadBalancingStrategy::new());
+        let contact_points = [vec!["127.0.0.1:9042".parse::<SocketAddr>().unwrap()]];
+
+        assert!(config.validate(&contact_points, Version::V4, false).is_ok());
+    }
+
+    #[test]
+    fn should_collect_all_session_config_violations() {
+        let mut config = TestSessionConfig::new(RoundRobinLoadBalancingStrategy::new());
+        config.compression = Compression::Snappy;
+        config.transport_buffer_size = 0;
+        config.read_buffer_size = Some(0);
+        config.event_channel_capacity = 0;
+        config.handshake_timeout = Duration::ZERO;
+        config.authentication_timeout = Duration::ZERO;
+        config.max_concurrent_authentications = 0;
+        config.connection_pool_config = ConnectionPoolConfigBuilder::new()
+            .with_local_size(0)
+            .with_heartbeat_interval(Duration::ZERO)
+            .build();
+        config.tracing_sample_rate = 1.5;
+
+        let error = config.validate(&[], Version::V5, true).unwrap_err();
+        assert_eq!(
+            error,
+            SessionBuildError::InvalidConfiguration(SessionConfigViolations::from_iter(vec![
+                SessionConfigViolation::NoContactPoints,
+                SessionConfigViolation::ZeroTransportBufferSize,
+                SessionConfigViolation::ZeroReadBufferSize,
+                SessionConfigViolation::ZeroEventChannelCapacity,
+                SessionConfigViolation::ZeroHandshakeTimeout,
+                SessionConfigViolation::ZeroAuthenticationTimeout,
+                SessionConfigViolation::ZeroMaxConcurrentAuthentications,
+                SessionConfigViolation::ZeroLocalPoolSize,
+                SessionConfigViolation::ZeroHeartbeatInterval,
+                SessionConfigViolation::CompressionTypeNotSupported,
+                SessionConfigViolation::BetaProtocolMismatch,
+                SessionConfigViolation::InvalidTracingSampleRate,
+            ]))
+        );
+    }
+
+    #[test]
+    fn session_should_be_shareable() {
+        fn assert_shareable<T: Clone + Send + Sync + 'static>() {}
+        assert_shareable::<DynTcpSession>();
+    }
+
+    #[allow(dead_code)]
+    async fn statement_requests_should_be_send(session: DynTcpSession, prepared: PreparedQuery) {
+        assert_send(
+            session
+                .query("SELECT * FROM system.local")
+                .consistency(Consistency::Quorum)
+                .tracing(true)
+                .into_future(),
+        );
+        assert_send(
+            session
+                .exec(&prepared)
+                .values(QueryValues::SimpleValues(vec![]))
+                .idempotent(true)
+                .into_future(),
+        );
+
+        let keyspace_session = session.with_keyspace("ks").unwrap();
+        assert_send(keyspace_session.query("SELECT * FROM users").into_future());
+        assert_send(keyspace_session.exec(&prepared).into_future());
+
+        let pinned_session = session.pinned().await.unwrap();
+        assert_send(pinned_session.query("SELECT * FROM users").into_future());
+        assert_send(pinned_session.exec(&prepared).into_future());
+
+        let dyn_session = session.clone().into_dyn();
+        assert_send(dyn_session.query("SELECT * FROM users").into_future());
+        assert_send(dyn_session.exec(&prepared).into_future());
+    }
+
+    #[allow(dead_code)]
+    fn token_range_scan_should_be_send(session: DynTcpSession) {
+        assert_send(session.token_range_scan("ks", "table", &["id"], 4));
+    }
+
+    // V4 rows result with a single int column "id" in ks.t, with a single row
+    fn token_range_rows() -> Envelope {
+        let mut body = vec![0, 0, 0, 2, 0, 0, 0, 1, 0, 0, 0, 1];
+        body.extend_from_slice(&[0, 2, b'k', b's', 0, 1, b't', 0, 2, b'i', b'd', 0, 9]);
+        body.extend_from_slice(&[0, 0, 0, 1, 0, 0, 0, 4, 0, 0, 0, 7]);
+
+        Envelope::new(
+            Version::V4,
+            Direction::Response,
+            Flags::empty(),
+            Opcode::Result,
+            0,
+            body,
+            None,
+            vec![],
+        )
+    }
+
+    // the first replica fails with a broken connection, the second one returns a row
+    fn token_range_replicas(consistencies: Arc<Mutex<Vec<Consistency>>>) -> Vec<Arc<TestNode>> {
+        let mut connection_manager = MockConnectionManager::<MockCdrsTransport>::new();
+        connection_manager
+            .expect_connection()
+            .returning(move |_, _, address| {
+                let consistencies = consistencies.clone();
+
+                let mut transport = MockCdrsTransport::new();
+                transport.expect_is_broken().return_const(false);
+                transport.expect_address().return_const(address);
+                transport.expect_idle_time().return_const(Duration::ZERO);
+                transport
+                    .expect_write_envelope()
+                    .returning(move |envelope, _| {
+                        if let Ok(RequestBody::Query(body)) = envelope.request_body() {
+                            consistencies
+                                .lock()
+                                .unwrap()
+                                .push(body.query_params.consistency);
+                        }
+
+                        let result = if address == addr(1) {
+                            Err(Error::Io(io::Error::new(
+                                io::ErrorKind::BrokenPipe,
+                                "broken pipe",
+                            )))
+                        } else {
+                            Ok(token_range_rows())
+                        };
+
+                        Box::pin(async move { result })
+                    });
+
+                Box::pin(async move { Ok(transport) })
+            });
+
+        local_nodes(connection_manager, &[addr(1), addr(2)])
+    }
+
+    #[tokio::test]
+    async fn should_read_token_ranges_with_statement_parameters() {
+        let range = TokenRange {
+            start: Murmur3Token { value: -100 },
+            end: Murmur3Token { value: 100 },
+        };
+        let parameters = StatementParamsBuilder::new()
+            .with_consistency(Consistency::LocalQuorum)
+            .build();
+        let envelope = token_range_envelope(
+            "SELECT id FROM ks.t WHERE token(id) > ? AND token(id) <= ?",
+            range,
+            &parameters,
+            None,
+            Flags::empty(),
+            Version::V4,
+        )
+        .unwrap();
+
+        // retried on the next replica, with the same consistency
+        let consistencies = Arc::new(Mutex::new(vec![]));
+        let response = send_envelope_with_hook(
+            token_range_replicas(consistencies.clone()).into_iter(),
+            &envelope,
+            true,
+            LazyRetrySession::new(&DefaultRetryPolicy),
+            &|_, _, _| {},
+            None,
+            None,
+        )
+        .await
+        .unwrap();
+
+        let (rows, paging_state) = token_range_page(response.unwrap()).unwrap();
+        assert_eq!(rows.len(), 1);
+        assert!(paging_state.is_none());
+        assert_eq!(
+            *consistencies.lock().unwrap(),
+            vec![Consistency::LocalQuorum, Consistency::LocalQuorum]
+        );
+
+        // not retried if the retry policy says so
+        let consistencies = Arc::new(Mutex::new(vec![]));
+        let response = send_envelope_with_hook(
+            token_range_replicas(consistencies.clone()).into_iter(),
+            &envelope,
+            true,
+            LazyRetrySession::new(&FallthroughRetryPolicy),
+            &|_, _, _| {},
+            None,
+            None,
+        )
+        .await
+        .unwrap();
+
+        assert!(matches!(response, Err(Error::Io(_))));
+        assert_eq!(consistencies.lock().unwrap().len(), 1);
+    }
+
+    // the node at addr(1) never connects, while the one at addr(2) answers every request
+    fn partially_connected_nodes(requests: Arc<Mutex<Vec<SocketAddr>>>) -> Vec<Arc<TestNode>> {
+        let mut connection_manager = MockConnectionManager::<MockCdrsTransport>::new();
+        connection_manager
+            .expect_connection()
+            .returning(move |_, _, address| {
+                if address == addr(1) {
+                    return Box::pin(async {
+                        Err(Error::Io(io::Error::new(
+                            io::ErrorKind::ConnectionRefused,
+                            "connection refused",
+                        )))
+                    });
+                }
+
+                let requests = requests.clone();
+
+                let mut transport = MockCdrsTransport::new();
+                transport.expect_is_broken().return_const(false);
+                transport.expect_address().return_const(address);
+                transport.expect_idle_time().return_const(Duration::ZERO);
+                transport.expect_write_envelope().returning(move |_, _| {
+                    requests.lock().unwrap().push(address);
+                    Box::pin(async { Ok(token_range_rows()) })
+                });
+
+                Box::pin(async move { Ok(transport) })
+            });
+
+        let connection_pool_factory = Arc::new(connection_pool_factory(
+            ConnectionPoolConfigBuilder::new()
+                .with_local_size(1)
+                .with_reconnect_wait_mode(ReconnectWaitMode::FailFast)
+                .build(),
+            connection_manager,
+            Arc::new(ConstantReconnectionPolicy::new(Duration::from_secs(3600))),
+        ));
+
+        vec![
+            local_node(connection_pool_factory.clone(), addr(1)),
+            local_node(connection_pool_factory, addr(2)),
+        ]
+    }
+
+    #[tokio::test]
+    async fn should_fail_fast_when_all_nodes_are_disconnected() {
+        let requests = Arc::new(Mutex::new(vec![]));
+        let nodes = partially_connected_nodes(requests.clone());
+        let envelope = Envelope::new_req_options(Version::V4);
+
+        assert!(nodes[0].persistent_connection().await.is_err());
+        assert!(nodes[1].persistent_connection().await.is_ok());
+
+        let disconnected = vec![nodes[0].clone()];
+        assert!(matches!(
+            connected_nodes(disconnected.clone(), true).map(|_| ()),
+            Err(Error::NoNodesAvailable)
+        ));
+
+        // without fail fast, requests still try disconnected nodes
+        let response = send_envelope(
+            connected_nodes(disconnected, false).unwrap(),
+            &envelope,
+            true,
+            LazyRetrySession::new(&FallthroughRetryPolicy),
+        )
+        .await
+        .unwrap();
+        assert!(!matches!(response, Ok(_) | Err(Error::NoNodesAvailable)));
+
+        // disconnected nodes are skipped, even if they come first
+        let response = send_envelope(
+            connected_nodes(nodes, true).unwrap(),
+            &envelope,
+            true,
+            LazyRetrySession::new(&FallthroughRetryPolicy),
+        )
+        .await
+        .unwrap();
+        assert!(response.is_ok());
+        assert_eq!(*requests.lock().unwrap(), vec![addr(2)]);
+    }
 }
@@ -0,0 +1,161 @@
+use cassandra_protocol::consistency::Consistency;
+use cassandra_protocol::error;
+use cassandra_protocol::frame::Envelope;
+use cassandra_protocol::query::{PreparedQuery, QueryValues};
+use std::borrow::Cow;
+use std::future::IntoFuture;
+use std::sync::Arc;
+
+use crate::future::BoxFuture;
+use crate::retry::RetryPolicy;
+use crate::statement::{StatementParams, StatementParamsBuilder};
+
+/// Sends statements built with [`StatementRequest`]. Implemented by all session handles.
+pub(crate) trait StatementTarget: Send + Sync {
+    fn send_query<'a>(
+        &'a self,
+        query: Cow<'a, str>,
+        parameters: StatementParams,
+    ) -> BoxFuture<'a, error::Result<Envelope>>;
+
+    fn send_prepared<'a>(
+        &'a self,
+        prepared: &'a PreparedQuery,
+        parameters: StatementParams,
+    ) -> BoxFuture<'a, error::Result<Envelope>>;
+}
+
+enum Statement<'a> {
+    Query(Cow<'a, str>),
+    Prepared(&'a PreparedQuery),
+}
+
+/// A statement to execute, returned by `query()` and `exec()` of
+/// [`Session`](crate::cluster::session::Session) and its handles. Allows overriding statement
+/// parameters inline and gets sent when awaited:
+///
+/// ```no_run
+/// # use cdrs_tokio::cluster::session::{TcpSessionBuilder, SessionBuilder};
+/// # use cdrs_tokio::cluster::NodeTcpConfigBuilder;
+/// # use cdrs_tokio::consistency::Consistency;
+/// # use cdrs_tokio::load_balancing::RoundRobinLoadBalancingStrategy;
+/// # async fn run() -> cdrs_tokio::Result<()> {
+/// # let config = NodeTcpConfigBuilder::new()
+/// #     .with_contact_point("127.0.0.1:9042".into())
+/// #     .build()
+/// #     .await
+/// #     .unwrap();
+/// # let session = TcpSessionBuilder::new(RoundRobinLoadBalancingStrategy::new(), config)
+/// #     .build()
+/// #     .await
+/// #     .unwrap();
+/// let response = session
+///     .query("SELECT * FROM ks.users")
+///     .consistency(Consistency::Quorum)
+///     .idempotent(true)
+///     .page_size(100)
+///     .await?;
+/// # Ok(())
+/// # }
+/// ```
+///
+/// For reusable parameters, build [`StatementParams`](crate::statement::StatementParams) and use
+/// `query_with_params()` or `exec_with_params()` instead.
+#[must_use = "statements are only sent when awaited"]
+pub struct StatementRequest<'a> {
+    target: &'a dyn StatementTarget,
+    statement: Statement<'a>,
+    params: StatementParamsBuilder,
+}
+
+impl<'a> StatementRequest<'a> {
+    pub(crate) fn query(target: &'a dyn StatementTarget, query: Cow<'a, str>) -> Self {
+        StatementRequest {
+            target,
+            statement: Statement::Query(query),
+            params: StatementParamsBuilder::new(),
+        }
+    }
+
+    pub(crate) fn prepared(target: &'a dyn StatementTarget, prepared: &'a PreparedQuery) -> Self {
+        StatementRequest {
+            target,
+            statement: Statement::Prepared(prepared),
+            params: StatementParamsBuilder::new(),
+        }
+    }
+
+    /// Sets statement consistency.
+    pub fn consistency(mut self, consistency: Consistency) -> Self {
+        self.params = self.params.with_consistency(consistency);
+        self
+    }
+
+    /// Sets serial consistency for conditional updates.
+    pub fn serial_consistency(mut self, serial_consistency: Consistency) -> Self {
+        self.params = self.params.with_serial_consistency(serial_consistency);
+        self
+    }
+
+    /// Binds values to the statement, either with or without names.
+    pub fn values(mut self, values: impl Into<QueryValues>) -> Self {
+        self.params = self.params.with_values(values.into());
+        self
+    }
+
+    /// Marks the statement as idempotent or not, which allows retrying it after it has been sent.
+    pub fn idempotent(mut self, idempotent: bool) -> Self {
+        self.params = self.params.idempotent(idempotent);
+        self
+    }
+
+    /// Sets the number of rows returned in a single page.
+    pub fn page_size(mut self, page_size: i32) -> Self {
+        self.params = self.params.with_page_size(page_size);
+        self
+    }
+
+    /// Sets the write timestamp, in microseconds.
+    pub fn timestamp(mut self, timestamp: i64) -> Self {
+        self.params = self.params.with_timestamp(timestamp);
+        self
+    }
+
+    /// Sets the keyspace of the statement.
+    pub fn keyspace(mut self, keyspace: impl Into<String>) -> Self {
+        self.params = self.params.with_keyspace(keyspace.into());
+        self
+    }
+
+    /// Enables server-side tracing of the statement.
+    pub fn tracing(mut self, tracing: bool) -> Self {
+        self.params = self.params.with_tracing(tracing);
+        self
+    }
+
+    /// Requests server warnings to be attached to the response.
+    pub fn warnings(mut self, warnings: bool) -> Self {
+        self.params = self.params.with_warnings(warnings);
+        self
+    }
+
+    /// Overrides the session retry policy for this statement.
+    pub fn retry_policy(mut self, retry_policy: Arc<dyn RetryPolicy + Send + Sync>) -> Self {
+        self.params = self.params.with_retry_policy(retry_policy);
+        self
+    }
+}
+
+impl<'a> IntoFuture for StatementRequest<'a> {
+    type Output = error::Result<Envelope>;
+    type IntoFuture = BoxFuture<'a, Self::Output>;
+
+    fn into_future(self) -> Self::IntoFuture {
+        let params = self.params.build();
+
+        match self.statement {
+            Statement::Query(query) => self.target.send_query(query, params),
+            Statement::Prepared(prepared) => self.target.send_prepared(prepared, params),
+        }
+    }
+}
@@ -1,10 +1,17 @@
-use crate::cluster::connection_manager::{startup, ConnectionManager};
+use crate::cluster::connection_manager::{
+    startup_with_phase, verify_compression, ConnectionManager, ConnectionManagerConfig,
+    HandshakeLimits,
+};
+use crate::cluster::happy_eyeballs::connect_staggered;
 #[cfg(feature = "http-proxy")]
 use crate::cluster::HttpProxyConfig;
-use crate::cluster::KeyspaceHolder;
+use crate::cluster::{ConnectionError, ConnectionPhase, InitStatement, KeyspaceHolder};
 use crate::frame_encoding::FrameEncodingFactory;
+use crate::frame_recording::FrameRecorder;
 use crate::future::BoxFuture;
+use crate::runtime;
 use crate::transport::TransportTcp;
+use arc_swap::ArcSwap;
 #[cfg(feature = "http-proxy")]
 use async_http_proxy::{http_connect_tokio, http_connect_tokio_with_basic_auth};
 use cassandra_protocol::authenticators::SaslAuthenticatorProvider;
@@ -18,18 +25,25 @@ use std::io::ErrorKind;
 use std::net::SocketAddr;
 use std::ops::Deref;
 use std::sync::Arc;
-#[cfg(feature = "http-proxy")]
-use tokio::net::TcpStream;
+use std::time::{Duration, Instant};
 use tokio::sync::mpsc::Sender;
 
 pub struct TcpConnectionManager {
     authenticator_provider: Arc<dyn SaslAuthenticatorProvider + Send + Sync>,
     keyspace_holder: Arc<KeyspaceHolder>,
     frame_encoder_factory: Box<dyn FrameEncodingFactory + Send + Sync>,
-    compression: Compression,
+    // changing compression affects only new connections
+    compression: ArcSwap<Compression>,
+    compression_threshold: usize,
     buffer_size: usize,
+    read_buffer_size: Option<usize>,
     tcp_nodelay: bool,
+    strict_socket_options: bool,
     version: Version,
+    handshake_limits: HandshakeLimits,
+    connection_stagger_delay: Duration,
+    frame_recorder: Option<Arc<FrameRecorder>>,
+    init_statements: Vec<InitStatement>,
     #[cfg(feature = "http-proxy")]
     http_proxy: Option<HttpProxyConfig>,
 }
@@ -42,46 +56,82 @@ impl ConnectionManager<TransportTcp> for TcpConnectionManager {
         error_handler: Option<Sender<Error>>,
         addr: SocketAddr,
     ) -> BoxFuture<Result<TransportTcp>> {
-        self.establish_connection(event_handler, error_handler, addr)
+        self.establish_connection(event_handler, error_handler, addr, &[])
+            .map(|result| result.map_err(Error::from))
+            .boxed()
+    }
+
+    fn connection_with_diagnostics<'a>(
+        &'a self,
+        event_handler: Option<Sender<Envelope>>,
+        error_handler: Option<Sender<Error>>,
+        addr: SocketAddr,
+    ) -> BoxFuture<'a, std::result::Result<TransportTcp, ConnectionError>>
+    where
+        TransportTcp: 'a,
+    {
+        self.establish_connection(event_handler, error_handler, addr, &[])
+            .boxed()
+    }
+
+    fn connection_with_alternatives<'a>(
+        &'a self,
+        event_handler: Option<Sender<Envelope>>,
+        error_handler: Option<Sender<Error>>,
+        addr: SocketAddr,
+        alternative_addrs: &'a [SocketAddr],
+    ) -> BoxFuture<'a, std::result::Result<TransportTcp, ConnectionError>>
+    where
+        TransportTcp: 'a,
+    {
+        self.establish_connection(event_handler, error_handler, addr, alternative_addrs)
             .boxed()
     }
+
+    #[inline]
+    fn compression(&self) -> Option<Compression> {
+        Some(**self.compression.load())
+    }
+
+    fn set_compression(&self, compression: Compression) -> Result<()> {
+        verify_compression(compression, self.version)?;
+        self.compression.store(Arc::new(compression));
+        Ok(())
+    }
 }
 
 impl TcpConnectionManager {
-    #[allow(clippy::too_many_arguments)]
     pub fn new(
         authenticator_provider: Arc<dyn SaslAuthenticatorProvider + Send + Sync>,
         keyspace_holder: Arc<KeyspaceHolder>,
         frame_encoder_factory: Box<dyn FrameEncodingFactory + Send + Sync>,
-        compression: Compression,
-        buffer_size: usize,
-        tcp_nodelay: bool,
-        version: Version,
-        #[cfg(feature = "http-proxy")] http_proxy: Option<HttpProxyConfig>,
+        config: ConnectionManagerConfig,
     ) -> Self {
         Self {
             authenticator_provider,
             keyspace_holder,
             frame_encoder_factory,
-            compression,
-            buffer_size,
-            tcp_nodelay,
-            version,
+            compression: ArcSwap::from_pointee(config.compression),
+            compression_threshold: config.compression_threshold,
+            buffer_size: config.buffer_size,
+            read_buffer_size: config.read_buffer_size,
+            tcp_nodelay: config.tcp_nodelay,
+            strict_socket_options: config.strict_socket_options,
+            version: config.version,
+            handshake_limits: config.handshake_limits(),
+            connection_stagger_delay: config.connection_stagger_delay,
+            frame_recorder: config.frame_recorder,
+            init_statements: config.init_statements,
             #[cfg(feature = "http-proxy")]
-            http_proxy,
+            http_proxy: config.http_proxy,
         }
     }
 
     //noinspection DuplicatedCode
     #[cfg(feature = "http-proxy")]
-    async fn create_transport(
-        &self,
-        event_handler: Option<Sender<Envelope>>,
-        error_handler: Option<Sender<Error>>,
-        addr: SocketAddr,
-    ) -> io::Result<TransportTcp> {
-        if let Some(http_proxy) = &self.http_proxy {
-            let mut stream = TcpStream::connect(&http_proxy.address).await?;
+    async fn connect_stream(&self, addr: SocketAddr) -> io::Result<runtime::TcpStream> {
+        let stream = if let Some(http_proxy) = &self.http_proxy {
+            let mut stream = runtime::connect_host(&http_proxy.address).await?;
 
             if let Some(auth) = &http_proxy.basic_auth {
                 http_connect_tokio_with_basic_auth(
@@ -99,81 +149,355 @@ impl TcpConnectionManager {
                     .map_err(|error| io::Error::new(ErrorKind::Other, error.to_string()))?;
             }
 
-            stream.set_nodelay(self.tcp_nodelay)?;
-            TransportTcp::with_stream(
-                stream,
-                addr,
-                self.keyspace_holder.clone(),
-                event_handler,
-                error_handler,
-                self.compression,
-                self.frame_encoder_factory
-                    .create_encoder(self.version, self.compression),
-                self.frame_encoder_factory
-                    .create_decoder(self.version, self.compression),
-                self.buffer_size,
-            )
+            runtime::apply_nodelay(&stream, addr, self.tcp_nodelay, self.strict_socket_options)?;
+            stream
         } else {
-            TransportTcp::new(
-                addr,
-                self.keyspace_holder.clone(),
-                event_handler,
-                error_handler,
-                self.compression,
-                self.frame_encoder_factory
-                    .create_encoder(self.version, self.compression),
-                self.frame_encoder_factory
-                    .create_decoder(self.version, self.compression),
-                self.buffer_size,
-                self.tcp_nodelay,
-            )
-            .await
-        }
+            runtime::connect(addr, self.tcp_nodelay, self.strict_socket_options).await?
+        };
+
+        Ok(stream)
     }
 
-    //noinspection DuplicatedCode
     #[cfg(not(feature = "http-proxy"))]
-    async fn create_transport(
+    async fn connect_stream(&self, addr: SocketAddr) -> io::Result<runtime::TcpStream> {
+        runtime::connect(addr, self.tcp_nodelay, self.strict_socket_options).await
+    }
+
+    async fn establish_connection(
         &self,
         event_handler: Option<Sender<Envelope>>,
         error_handler: Option<Sender<Error>>,
         addr: SocketAddr,
-    ) -> io::Result<TransportTcp> {
-        TransportTcp::new(
+        alternative_addrs: &[SocketAddr],
+    ) -> std::result::Result<TransportTcp, ConnectionError> {
+        let start = Instant::now();
+        let (stream, addr) = connect_staggered(
+            addr,
+            alternative_addrs,
+            self.connection_stagger_delay,
+            |addr| self.connect_stream(addr),
+        )
+        .await
+        .map_err(|(error, addr)| {
+            ConnectionError::new(
+                addr,
+                ConnectionPhase::TcpConnect,
+                error.into(),
+                start.elapsed(),
+            )
+        })?;
+
+        // read once, so the whole connection uses the same compression even if it changes
+        let compression = **self.compression.load();
+        let transport = TransportTcp::with_stream(
+            stream,
             addr,
             self.keyspace_holder.clone(),
             event_handler,
             error_handler,
-            self.compression,
+            compression,
+            self.compression_threshold,
             self.frame_encoder_factory
-                .create_encoder(self.version, self.compression),
+                .create_encoder(self.version, compression),
             self.frame_encoder_factory
-                .create_decoder(self.version, self.compression),
+                .create_decoder(self.version, compression),
             self.buffer_size,
-            self.tcp_nodelay,
+            self.read_buffer_size,
+            self.frame_recorder.clone(),
         )
-        .await
-    }
-
-    async fn establish_connection(
-        &self,
-        event_handler: Option<Sender<Envelope>>,
-        error_handler: Option<Sender<Error>>,
-        addr: SocketAddr,
-    ) -> Result<TransportTcp> {
-        let transport = self
-            .create_transport(event_handler, error_handler, addr)
-            .await?;
+        .map_err(|error| {
+            ConnectionError::new(
+                addr,
+                ConnectionPhase::TcpConnect,
+                error.into(),
+                start.elapsed(),
+            )
+        })?;
 
-        startup(
+        let mut phase = ConnectionPhase::Startup;
+        let result = startup_with_phase(
             &transport,
             self.authenticator_provider.deref(),
             self.keyspace_holder.deref(),
-            self.compression,
+            compression,
             self.version,
+            &self.init_statements,
+            &mut phase,
+            Some(&self.handshake_limits),
+        )
+        .await;
+
+        result
+            .map(|_| transport)
+            .map_err(|error| ConnectionError::new(addr, phase, error, start.elapsed()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use cassandra_protocol::authenticators::{
+        NoneAuthenticatorProvider, SaslAuthenticatorProvider, StaticPasswordAuthenticatorProvider,
+    };
+    use cassandra_protocol::compression::Compression;
+    use cassandra_protocol::error::Error;
+    use cassandra_protocol::frame::message_authenticate::BodyResAuthenticate;
+    use cassandra_protocol::frame::message_error::{ErrorBody, ErrorType};
+    use cassandra_protocol::frame::{Direction, Envelope, Flags, Opcode, Serialize, Version};
+    use std::convert::TryInto;
+    use std::sync::Arc;
+    use std::time::{Duration, Instant};
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::{TcpListener, TcpStream};
+    use tokio::sync::watch;
+
+    use crate::cluster::connection_manager::{
+        verify_compression, ConnectionManager, ConnectionManagerConfigBuilder,
+    };
+    use crate::cluster::{ConnectionPhase, InitStatement, KeyspaceHolder, TcpConnectionManager};
+    use crate::frame_encoding::ProtocolFrameEncodingFactory;
+
+    fn create_connection_manager() -> TcpConnectionManager {
+        create_connection_manager_with_authenticator(Arc::new(NoneAuthenticatorProvider), vec![])
+    }
+
+    fn create_connection_manager_with_authenticator(
+        authenticator_provider: Arc<dyn SaslAuthenticatorProvider + Send + Sync>,
+        init_statements: Vec<InitStatement>,
+    ) -> TcpConnectionManager {
+        let (keyspace_sender, _) = watch::channel(None);
+        TcpConnectionManager::new(
+            authenticator_provider,
+            Arc::new(KeyspaceHolder::new(keyspace_sender)),
+            Box::<ProtocolFrameEncodingFactory>::default(),
+            ConnectionManagerConfigBuilder::new(Version::V4)
+                .with_compression_threshold(0)
+                .with_buffer_size(16)
+                .with_strict_socket_options(true)
+                .with_handshake_timeout(Duration::from_millis(100))
+                .with_authentication_timeout(Duration::from_millis(200))
+                .with_max_concurrent_authentications(1)
+                .with_init_statements(init_statements)
+                .build(),
         )
-        .await?;
+    }
+
+    #[test]
+    fn should_change_compression_of_new_connections() {
+        let connection_manager = create_connection_manager();
+        assert_eq!(connection_manager.compression(), Some(Compression::None));
+
+        connection_manager
+            .set_compression(Compression::Lz4)
+            .unwrap();
+        assert_eq!(connection_manager.compression(), Some(Compression::Lz4));
+
+        assert!(verify_compression(Compression::Snappy, Version::V5).is_err());
+    }
+
+    #[tokio::test]
+    async fn should_time_out_unanswered_handshake() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        // accept the connection, but never respond to STARTUP
+        let server = tokio::spawn(async move { listener.accept().await.unwrap() });
+
+        let connection_manager = create_connection_manager();
+
+        let result = connection_manager.connection(None, None, addr).await;
+        assert!(matches!(result, Err(Error::HandshakeTimeout(error_addr)) if error_addr == addr));
+
+        drop(server);
+    }
+
+    #[tokio::test]
+    async fn should_classify_handshake_failures() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        let server = tokio::spawn(async move { listener.accept().await.unwrap() });
+
+        let error = create_connection_manager()
+            .connection_with_diagnostics(None, None, addr)
+            .await
+            .unwrap_err();
+
+        assert_eq!(error.address, addr);
+        assert_eq!(error.phase, ConnectionPhase::Startup);
+        assert!(matches!(error.error, Error::HandshakeTimeout(_)));
+        assert!(error.elapsed >= Duration::from_millis(100));
+
+        drop(server);
+    }
+
+    #[tokio::test]
+    async fn should_classify_connect_failures() {
+        // bind and drop a listener to get a port which refuses connections
+        let addr = TcpListener::bind("127.0.0.1:0")
+            .await
+            .unwrap()
+            .local_addr()
+            .unwrap();
+
+        let error = create_connection_manager()
+            .connection_with_diagnostics(None, None, addr)
+            .await
+            .unwrap_err();
+
+        assert_eq!(error.address, addr);
+        assert_eq!(error.phase, ConnectionPhase::TcpConnect);
+        assert!(matches!(error.error, Error::Io(_)));
+    }
+
+    #[tokio::test]
+    async fn should_handshake_with_first_connected_alternative() {
+        let refusing_addr = TcpListener::bind("127.0.0.1:0")
+            .await
+            .unwrap()
+            .local_addr()
+            .unwrap();
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        let server = tokio::spawn(async move { listener.accept().await.unwrap() });
+
+        let error = create_connection_manager()
+            .connection_with_alternatives(None, None, refusing_addr, &[addr])
+            .await
+            .unwrap_err();
+
+        assert_eq!(error.address, addr);
+        assert_eq!(error.phase, ConnectionPhase::Startup);
+        assert!(matches!(error.error, Error::HandshakeTimeout(error_addr) if error_addr == addr));
+
+        drop(server);
+    }
+
+    async fn read_request(stream: &mut TcpStream) -> i16 {
+        let mut header = [0; 9];
+        stream.read_exact(&mut header).await.unwrap();
+
+        let mut body = vec![0; i32::from_be_bytes(header[5..9].try_into().unwrap()) as usize];
+        stream.read_exact(&mut body).await.unwrap();
+
+        i16::from_be_bytes(header[2..4].try_into().unwrap())
+    }
+
+    async fn respond(stream: &mut TcpStream, stream_id: i16, opcode: Opcode, body: Vec<u8>) {
+        let envelope = Envelope::new(
+            Version::V4,
+            Direction::Response,
+            Flags::empty(),
+            opcode,
+            stream_id,
+            body,
+            None,
+            vec![],
+        );
+
+        stream
+            .write_all(&envelope.encode_with(Compression::None).unwrap())
+            .await
+            .unwrap();
+    }
+
+    // answers STARTUP with AUTHENTICATE, but never answers AUTH_RESPONSE
+    async fn accept_and_stall_authentication(listener: &TcpListener) -> TcpStream {
+        let (mut stream, _) = listener.accept().await.unwrap();
+
+        let stream_id = read_request(&mut stream).await;
+        let authenticate = BodyResAuthenticate {
+            data: "org.apache.cassandra.auth.PasswordAuthenticator".into(),
+        };
+        respond(
+            &mut stream,
+            stream_id,
+            Opcode::Authenticate,
+            authenticate.serialize_to_vec(Version::V4),
+        )
+        .await;
+
+        stream
+    }
+
+    #[tokio::test]
+    async fn should_time_out_authentication_separately() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        let server = tokio::spawn(async move {
+            let first = accept_and_stall_authentication(&listener).await;
+            let second = accept_and_stall_authentication(&listener).await;
+            (first, second)
+        });
+
+        let connection_manager = create_connection_manager_with_authenticator(
+            Arc::new(StaticPasswordAuthenticatorProvider::new("user", "password")),
+            vec![],
+        );
+
+        // authentication takes longer than the handshake timeout, and only one connection can
+        // authenticate at a time
+        let start = Instant::now();
+        let (first, second) = tokio::join!(
+            connection_manager.connection_with_diagnostics(None, None, addr),
+            connection_manager.connection_with_diagnostics(None, None, addr)
+        );
+
+        for error in [first.unwrap_err(), second.unwrap_err()] {
+            assert_eq!(error.phase, ConnectionPhase::Authenticate);
+            assert!(
+                matches!(error.error, Error::AuthenticationTimeout(error_addr) if error_addr == addr)
+            );
+        }
+
+        assert!(start.elapsed() >= Duration::from_millis(400));
+
+        drop(server);
+    }
+
+    #[tokio::test]
+    async fn should_fail_connections_with_failing_init_statements() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        // accepts STARTUP and fails the first query
+        let server = tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+
+            let stream_id = read_request(&mut stream).await;
+            respond(&mut stream, stream_id, Opcode::Ready, vec![]).await;
+
+            let stream_id = read_request(&mut stream).await;
+            let error = ErrorBody {
+                message: "unconfigured table".into(),
+                ty: ErrorType::Invalid,
+            };
+            respond(
+                &mut stream,
+                stream_id,
+                Opcode::Error,
+                error.serialize_to_vec(Version::V4),
+            )
+            .await;
+
+            stream
+        });
+
+        let connection_manager = create_connection_manager_with_authenticator(
+            Arc::new(NoneAuthenticatorProvider),
+            vec![InitStatement::new("SELECT * FROM missing")],
+        );
+
+        let error = connection_manager
+            .connection_with_diagnostics(None, None, addr)
+            .await
+            .unwrap_err();
+
+        assert_eq!(error.phase, ConnectionPhase::Initialize);
+        assert!(matches!(error.error, Error::Server { .. }));
 
-        Ok(transport)
+        drop(server);
     }
 }
@@ -0,0 +1,74 @@
+use cassandra_protocol::frame::Version;
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use std::sync::Arc;
+use tokio::sync::watch;
+
+use crate::cluster::connection_manager::MockConnectionManager;
+use crate::cluster::connection_pool::{ConnectionPoolConfig, ConnectionPoolFactory};
+use crate::cluster::topology::{Node, NodeDistance, NodeState};
+use crate::retry::{MockReconnectionPolicy, ReconnectionPolicy};
+use crate::transport::MockCdrsTransport;
+
+pub(crate) type TestConnectionManager = MockConnectionManager<MockCdrsTransport>;
+pub(crate) type TestConnectionPoolFactory =
+    ConnectionPoolFactory<MockCdrsTransport, TestConnectionManager>;
+pub(crate) type TestNode = Node<MockCdrsTransport, TestConnectionManager>;
+
+/// Checks at compile time that given value, usually a future, can be sent between threads.
+pub(crate) fn assert_send<T: Send>(_: T) {}
+
+/// Returns a local address on the default port, ending with given octet.
+pub(crate) fn addr(last_octet: u8) -> SocketAddr {
+    SocketAddr::new(IpAddr::V4(Ipv4Addr::new(127, 0, 0, last_octet)), 9042)
+}
+
+/// Creates a pool factory for nodes connecting with given manager.
+pub(crate) fn connection_pool_factory(
+    config: ConnectionPoolConfig,
+    connection_manager: TestConnectionManager,
+    reconnection_policy: Arc<dyn ReconnectionPolicy + Send + Sync>,
+) -> TestConnectionPoolFactory {
+    let (_, keyspace_receiver) = watch::channel(None);
+    ConnectionPoolFactory::new(
+        config,
+        Version::V4,
+        connection_manager,
+        keyspace_receiver,
+        reconnection_policy,
+    )
+}
+
+/// Creates a local node which is up, without a host id, tokens or topology.
+pub(crate) fn local_node(
+    connection_pool_factory: Arc<TestConnectionPoolFactory>,
+    addr: SocketAddr,
+) -> Arc<TestNode> {
+    Arc::new(Node::new_with_state(
+        connection_pool_factory,
+        addr,
+        None,
+        None,
+        Some(NodeDistance::Local),
+        NodeState::Up,
+        Default::default(),
+        "".into(),
+        "".into(),
+    ))
+}
+
+/// Creates local nodes at given addresses, sharing a pool factory with default config.
+pub(crate) fn local_nodes(
+    connection_manager: TestConnectionManager,
+    addrs: &[SocketAddr],
+) -> Vec<Arc<TestNode>> {
+    let connection_pool_factory = Arc::new(connection_pool_factory(
+        Default::default(),
+        connection_manager,
+        Arc::new(MockReconnectionPolicy::new()),
+    ));
+
+    addrs
+        .iter()
+        .map(|addr| local_node(connection_pool_factory.clone(), *addr))
+        .collect()
+}
@@ -1,13 +1,23 @@
+use fxhash::{FxHashMap, FxHashSet};
+use itertools::Itertools;
 use std::collections::BTreeMap;
 use std::fmt::{Debug, Formatter};
 use std::net::SocketAddr;
 use std::sync::Arc;
 
-use crate::cluster::topology::{Node, NodeMap};
+use crate::cluster::topology::{DatacenterMetadata, Node, NodeMap, ReplicationStrategy};
 use crate::cluster::ConnectionManager;
 use crate::cluster::Murmur3Token;
 use crate::transport::CdrsTransport;
 
+/// Range of tokens owned by a single primary replica, exclusive of `start` and inclusive of `end`.
+/// Ranges never wrap around the ring, so `start` is always lower than `end`.
+#[derive(Copy, Clone, Ord, PartialOrd, Eq, PartialEq, Debug, Hash)]
+pub struct TokenRange {
+    pub start: Murmur3Token,
+    pub end: Murmur3Token,
+}
+
 /// Map of tokens to nodes.
 pub struct TokenMap<T: CdrsTransport + 'static, CM: ConnectionManager<T> + 'static> {
     token_ring: BTreeMap<Murmur3Token, Arc<Node<T, CM>>>,
@@ -51,6 +61,54 @@ impl<T: CdrsTransport, CM: ConnectionManager<T>> TokenMap<T, CM> {
         }
     }
 
+    /// Returns token ranges covering the whole ring, in token order. The range wrapping around
+    /// the end of the ring is split in two: from the last token to the maximum token and from the
+    /// minimum token to the first one. An empty map yields a single range covering all tokens.
+    pub fn token_ranges(&self) -> Vec<TokenRange> {
+        let min = Murmur3Token::new(i64::MIN);
+        let max = Murmur3Token::new(i64::MAX);
+
+        let (first, last) = match (
+            self.token_ring.keys().next(),
+            self.token_ring.keys().next_back(),
+        ) {
+            (Some(first), Some(last)) => (*first, *last),
+            _ => {
+                return vec![TokenRange {
+                    start: min,
+                    end: max,
+                }]
+            }
+        };
+
+        let mut ranges = Vec::with_capacity(self.token_ring.len() + 1);
+        if first > min {
+            ranges.push(TokenRange {
+                start: min,
+                end: first,
+            });
+        }
+
+        ranges.extend(
+            self.token_ring
+                .keys()
+                .tuple_windows()
+                .map(|(start, end)| TokenRange {
+                    start: *start,
+                    end: *end,
+                }),
+        );
+
+        if last < max {
+            ranges.push(TokenRange {
+                start: last,
+                end: max,
+            });
+        }
+
+        ranges
+    }
+
     /// Returns local nodes starting at given token and going in the direction of replicas.
     pub fn nodes_for_token_capped(
         &self,
@@ -76,6 +134,92 @@ impl<T: CdrsTransport, CM: ConnectionManager<T>> TokenMap<T, CM> {
             .map(|(_, node)| node.clone())
     }
 
+    /// Returns distinct replica nodes for given token, in the order they are placed by given
+    /// replication strategy. `LocalStrategy` has no replicas, while unknown strategies only yield
+    /// the primary replica.
+    pub fn replicas(
+        &self,
+        token: Murmur3Token,
+        replication_strategy: &ReplicationStrategy,
+        datacenters: &FxHashMap<String, DatacenterMetadata>,
+    ) -> Vec<Arc<Node<T, CM>>> {
+        match replication_strategy {
+            ReplicationStrategy::SimpleStrategy { replication_factor } => self
+                .nodes_for_token(token)
+                .unique_by(|node| node.broadcast_rpc_address())
+                .take(*replication_factor)
+                .collect(),
+            ReplicationStrategy::NetworkTopologyStrategy {
+                datacenter_replication_factor,
+            } => self.network_topology_strategy_replicas(
+                token,
+                datacenter_replication_factor,
+                datacenters,
+            ),
+            ReplicationStrategy::LocalStrategy => vec![],
+            ReplicationStrategy::Other { .. } => self.nodes_for_token_capped(token, 1).collect(),
+        }
+    }
+
+    // mirrors Cassandra placement: each datacenter gets replicas on distinct racks first and
+    // accepts rack repeats only when the replication factor exceeds the number of racks
+    fn network_topology_strategy_replicas(
+        &self,
+        token: Murmur3Token,
+        datacenter_replication_factor: &FxHashMap<String, usize>,
+        datacenters: &FxHashMap<String, DatacenterMetadata>,
+    ) -> Vec<Arc<Node<T, CM>>> {
+        let mut remaining_replicas: FxHashMap<&str, usize> = datacenter_replication_factor
+            .iter()
+            .filter(|(_, replication_factor)| **replication_factor > 0)
+            .map(|(dc, replication_factor)| (dc.as_str(), *replication_factor))
+            .collect();
+
+        let mut acceptable_rack_repeats: FxHashMap<&str, usize> = remaining_replicas
+            .iter()
+            .map(|(dc, replication_factor)| {
+                let rack_count = datacenters.get(*dc).map(|dc| dc.rack_count).unwrap_or(0);
+                (*dc, replication_factor.saturating_sub(rack_count))
+            })
+            .collect();
+
+        let mut desired_replica_count: usize = remaining_replicas.values().sum();
+        let mut result = Vec::with_capacity(desired_replica_count);
+        let mut used_nodes: FxHashSet<SocketAddr> = Default::default();
+        let mut used_dc_racks: FxHashSet<(String, String)> = Default::default();
+
+        for node in self.nodes_for_token(token) {
+            if desired_replica_count == 0 {
+                break;
+            }
+
+            let dc = node.datacenter();
+            let remaining_dc_replicas = match remaining_replicas.get_mut(dc) {
+                Some(remaining_dc_replicas) if *remaining_dc_replicas > 0 => remaining_dc_replicas,
+                _ => continue,
+            };
+
+            if used_nodes.contains(&node.broadcast_rpc_address()) {
+                continue;
+            }
+
+            if !used_dc_racks.insert((dc.into(), node.rack().into())) {
+                match acceptable_rack_repeats.get_mut(dc) {
+                    Some(rack_repeats) if *rack_repeats > 0 => *rack_repeats -= 1,
+                    _ => continue,
+                }
+            }
+
+            *remaining_dc_replicas -= 1;
+            desired_replica_count -= 1;
+
+            used_nodes.insert(node.broadcast_rpc_address());
+            result.push(node);
+        }
+
+        result
+    }
+
     /// Creates a new map with a new node inserted.
     #[must_use]
     pub fn clone_with_node(&self, node: Arc<Node<T, CM>>) -> Self {
@@ -109,6 +253,7 @@ impl<T: CdrsTransport, CM: ConnectionManager<T>> TokenMap<T, CM> {
 #[cfg(test)]
 mod tests {
     use cassandra_protocol::frame::Version;
+    use fxhash::FxHashMap;
     use itertools::Itertools;
     use std::net::{IpAddr, Ipv4Addr, SocketAddr};
     use std::sync::{Arc, LazyLock};
@@ -117,9 +262,9 @@ mod tests {
 
     use crate::cluster::connection_manager::MockConnectionManager;
     use crate::cluster::connection_pool::ConnectionPoolFactory;
-    use crate::cluster::topology::{Node, NodeMap};
+    use crate::cluster::topology::{DatacenterMetadata, Node, NodeMap, ReplicationStrategy};
     use crate::cluster::Murmur3Token;
-    use crate::cluster::TokenMap;
+    use crate::cluster::{TokenMap, TokenRange};
     use crate::retry::MockReconnectionPolicy;
     use crate::transport::MockCdrsTransport;
 
@@ -127,17 +272,24 @@ mod tests {
     static HOST_ID_2: LazyLock<Uuid> = LazyLock::new(|| Uuid::new_v4());
     static HOST_ID_3: LazyLock<Uuid> = LazyLock::new(|| Uuid::new_v4());
 
-    fn prepare_nodes() -> NodeMap<MockCdrsTransport, MockConnectionManager<MockCdrsTransport>> {
+    type TestConnectionPoolFactory =
+        ConnectionPoolFactory<MockCdrsTransport, MockConnectionManager<MockCdrsTransport>>;
+
+    fn create_connection_pool_factory() -> Arc<TestConnectionPoolFactory> {
         let (_, keyspace_receiver) = watch::channel(None);
         let connection_manager = MockConnectionManager::<MockCdrsTransport>::new();
         let reconnection_policy = MockReconnectionPolicy::new();
-        let connection_pool_factory = Arc::new(ConnectionPoolFactory::new(
+        Arc::new(ConnectionPoolFactory::new(
             Default::default(),
             Version::V4,
             connection_manager,
             keyspace_receiver,
             Arc::new(reconnection_policy),
-        ));
+        ))
+    }
+
+    fn prepare_nodes() -> NodeMap<MockCdrsTransport, MockConnectionManager<MockCdrsTransport>> {
+        let connection_pool_factory = create_connection_pool_factory();
 
         let mut nodes = NodeMap::default();
         nodes.insert(
@@ -223,4 +375,156 @@ mod tests {
             Murmur3Token::new(20),
         );
     }
+
+    fn token_range(start: i64, end: i64) -> TokenRange {
+        TokenRange {
+            start: Murmur3Token::new(start),
+            end: Murmur3Token::new(end),
+        }
+    }
+
+    #[test]
+    fn should_split_wraparound_token_range() {
+        let token_map = TokenMap::new(&prepare_nodes());
+        assert_eq!(
+            token_map.token_ranges(),
+            vec![
+                token_range(i64::MIN, -2),
+                token_range(-2, -1),
+                token_range(-1, 0),
+                token_range(0, 1),
+                token_range(1, 2),
+                token_range(2, 10),
+                token_range(10, 20),
+                token_range(20, i64::MAX),
+            ]
+        );
+
+        assert_eq!(
+            TokenMap::<MockCdrsTransport, MockConnectionManager<MockCdrsTransport>>::default()
+                .token_ranges(),
+            vec![token_range(i64::MIN, i64::MAX)]
+        );
+    }
+
+    // two datacenters with two vnodes per node: dc1 spans racks r1 and r2, dc2 has a single rack
+    // ring: 0 n1, 10 n2, 20 n3, 30 n4, 40 n5, 50 n1, 60 n2, 70 n3, 80 n4, 90 n5
+    fn prepare_multi_dc_nodes() -> (
+        NodeMap<MockCdrsTransport, MockConnectionManager<MockCdrsTransport>>,
+        FxHashMap<String, DatacenterMetadata>,
+    ) {
+        let connection_pool_factory = create_connection_pool_factory();
+        let layout = [
+            ("r1", "dc1"),
+            ("r1", "dc1"),
+            ("r2", "dc1"),
+            ("r1", "dc2"),
+            ("r1", "dc2"),
+        ];
+
+        let nodes = layout
+            .iter()
+            .enumerate()
+            .map(|(index, (rack, dc))| {
+                let host_id = Uuid::new_v4();
+                let node = Node::new(
+                    connection_pool_factory.clone(),
+                    SocketAddr::new(IpAddr::V4(Ipv4Addr::new(127, 0, 0, index as u8 + 1)), 9042),
+                    None,
+                    Some(host_id),
+                    None,
+                    vec![
+                        Murmur3Token::new(index as i64 * 10),
+                        Murmur3Token::new(index as i64 * 10 + 50),
+                    ],
+                    (*rack).into(),
+                    (*dc).into(),
+                );
+
+                (host_id, Arc::new(node))
+            })
+            .collect();
+
+        let mut datacenters = FxHashMap::default();
+        datacenters.insert("dc1".into(), DatacenterMetadata::new(2));
+        datacenters.insert("dc2".into(), DatacenterMetadata::new(1));
+
+        (nodes, datacenters)
+    }
+
+    fn verify_replicas(
+        replication_strategy: &ReplicationStrategy,
+        token: Murmur3Token,
+        expected_nodes: &[u8],
+    ) {
+        let (nodes, datacenters) = prepare_multi_dc_nodes();
+        let token_map = TokenMap::new(&nodes);
+        let replicas = token_map
+            .replicas(token, replication_strategy, &datacenters)
+            .iter()
+            .map(|node| match node.broadcast_rpc_address().ip() {
+                IpAddr::V4(ip) => ip.octets()[3],
+                IpAddr::V6(_) => unreachable!(),
+            })
+            .collect_vec();
+
+        assert_eq!(replicas, expected_nodes);
+    }
+
+    fn network_topology_strategy(dc_rf: &[(&str, usize)]) -> ReplicationStrategy {
+        ReplicationStrategy::NetworkTopologyStrategy {
+            datacenter_replication_factor: dc_rf
+                .iter()
+                .map(|(dc, replication_factor)| ((*dc).into(), *replication_factor))
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn should_return_distinct_simple_strategy_replicas() {
+        let strategy = ReplicationStrategy::SimpleStrategy {
+            replication_factor: 3,
+        };
+
+        verify_replicas(&strategy, Murmur3Token::new(0), &[1, 2, 3]);
+        verify_replicas(&strategy, Murmur3Token::new(35), &[5, 1, 2]);
+        verify_replicas(&strategy, Murmur3Token::new(91), &[1, 2, 3]);
+
+        let strategy = ReplicationStrategy::SimpleStrategy {
+            replication_factor: 10,
+        };
+        verify_replicas(&strategy, Murmur3Token::new(0), &[1, 2, 3, 4, 5]);
+    }
+
+    #[test]
+    fn should_place_network_topology_strategy_replicas_on_distinct_racks() {
+        let strategy = network_topology_strategy(&[("dc1", 2), ("dc2", 2)]);
+
+        verify_replicas(&strategy, Murmur3Token::new(0), &[1, 3, 4, 5]);
+        verify_replicas(&strategy, Murmur3Token::new(55), &[2, 3, 4, 5]);
+        verify_replicas(&strategy, Murmur3Token::new(65), &[3, 4, 5, 1]);
+    }
+
+    #[test]
+    fn should_repeat_racks_when_replication_factor_exceeds_rack_count() {
+        let strategy = network_topology_strategy(&[("dc1", 3)]);
+
+        verify_replicas(&strategy, Murmur3Token::new(0), &[1, 2, 3]);
+        verify_replicas(&strategy, Murmur3Token::new(20), &[3, 1, 2]);
+    }
+
+    #[test]
+    fn should_skip_unknown_datacenters() {
+        let strategy = network_topology_strategy(&[("dc2", 1), ("dc3", 2)]);
+        verify_replicas(&strategy, Murmur3Token::new(0), &[4]);
+    }
+
+    #[test]
+    fn should_not_return_local_strategy_replicas() {
+        verify_replicas(
+            &ReplicationStrategy::LocalStrategy,
+            Murmur3Token::new(0),
+            &[],
+        );
+    }
 }
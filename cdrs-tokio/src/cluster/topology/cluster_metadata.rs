@@ -7,7 +7,7 @@ use uuid::Uuid;
 use crate::cluster::topology::keyspace_metadata::KeyspaceMetadata;
 use crate::cluster::topology::node::Node;
 use crate::cluster::topology::{DatacenterMetadata, NodeMap};
-use crate::cluster::{ConnectionManager, TokenMap};
+use crate::cluster::{ConnectionManager, Murmur3Token, TokenMap};
 use crate::transport::CdrsTransport;
 
 fn build_datacenter_info<T: CdrsTransport, CM: ConnectionManager<T>>(
@@ -142,6 +142,17 @@ impl<T: CdrsTransport, CM: ConnectionManager<T>> ClusterMetadata<T, CM> {
         self.keyspaces.get(keyspace)
     }
 
+    /// Returns replica nodes of given token in given keyspace, according to the keyspace
+    /// replication strategy. Returns an empty list for unknown keyspaces.
+    pub fn replicas(&self, keyspace: &str, token: Murmur3Token) -> Vec<Arc<Node<T, CM>>> {
+        self.keyspace(keyspace)
+            .map(|keyspace| {
+                self.token_map
+                    .replicas(token, &keyspace.replication_strategy, &self.datacenters)
+            })
+            .unwrap_or_default()
+    }
+
     /// Returns known datacenters.
     #[inline]
     pub fn datacenters(&self) -> &FxHashMap<String, DatacenterMetadata> {
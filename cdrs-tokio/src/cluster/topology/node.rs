@@ -13,7 +13,7 @@ use uuid::Uuid;
 use crate::cluster::connection_pool::{ConnectionPool, ConnectionPoolFactory};
 use crate::cluster::topology::{NodeDistance, NodeState};
 use crate::cluster::Murmur3Token;
-use crate::cluster::{ConnectionManager, NodeInfo};
+use crate::cluster::{ConnectionError, ConnectionManager, NodeInfo};
 use crate::transport::CdrsTransport;
 
 /// Metadata about a Cassandra node in the cluster, along with a connection.
@@ -21,6 +21,8 @@ pub struct Node<T: CdrsTransport + 'static, CM: ConnectionManager<T> + 'static>
     connection_pool_factory: Arc<ConnectionPoolFactory<T, CM>>,
     connection_pool: OnceCell<Arc<ConnectionPool<T, CM>>>,
     broadcast_rpc_address: SocketAddr,
+    // other addresses of contact points resolved from a single hostname
+    alternative_addresses: Vec<SocketAddr>,
     broadcast_address: Option<SocketAddr>,
     distance: Option<NodeDistance>,
     state: Atomic<NodeState>,
@@ -34,6 +36,7 @@ impl<T: CdrsTransport, CM: ConnectionManager<T>> Debug for Node<T, CM> {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         f.debug_struct("Node")
             .field("broadcast_rpc_address", &self.broadcast_rpc_address)
+            .field("alternative_addresses", &self.alternative_addresses)
             .field("broadcast_address", &self.broadcast_address)
             .field("distance", &self.distance)
             .field("state", &self.state)
@@ -61,6 +64,7 @@ impl<T: CdrsTransport, CM: ConnectionManager<T>> Node<T, CM> {
             connection_pool_factory,
             connection_pool: Default::default(),
             broadcast_rpc_address,
+            alternative_addresses: Default::default(),
             broadcast_address,
             distance,
             state: Atomic::new(NodeState::Unknown),
@@ -87,6 +91,7 @@ impl<T: CdrsTransport, CM: ConnectionManager<T>> Node<T, CM> {
             connection_pool_factory,
             connection_pool: Default::default(),
             broadcast_rpc_address,
+            alternative_addresses: Default::default(),
             broadcast_address,
             distance,
             state: Atomic::new(state),
@@ -112,6 +117,7 @@ impl<T: CdrsTransport, CM: ConnectionManager<T>> Node<T, CM> {
             connection_pool_factory,
             connection_pool: Default::default(),
             broadcast_rpc_address,
+            alternative_addresses: Default::default(),
             broadcast_address,
             distance: None,
             state: Atomic::new(state),
@@ -134,6 +140,7 @@ impl<T: CdrsTransport, CM: ConnectionManager<T>> Node<T, CM> {
             connection_pool_factory,
             connection_pool: Default::default(),
             broadcast_rpc_address,
+            alternative_addresses: Default::default(),
             broadcast_address,
             distance: Some(distance),
             state: Atomic::new(NodeState::Unknown),
@@ -144,6 +151,16 @@ impl<T: CdrsTransport, CM: ConnectionManager<T>> Node<T, CM> {
         }
     }
 
+    /// Sets other addresses the node can be reached through, tried along the broadcast RPC address
+    /// when establishing new connections.
+    pub(crate) fn with_alternative_addresses(
+        mut self,
+        alternative_addresses: Vec<SocketAddr>,
+    ) -> Self {
+        self.alternative_addresses = alternative_addresses;
+        self
+    }
+
     #[inline]
     pub fn state(&self) -> NodeState {
         self.state.load(Ordering::Relaxed)
@@ -226,16 +243,66 @@ impl<T: CdrsTransport, CM: ConnectionManager<T>> Node<T, CM> {
         }
     }
 
+    // nodes without a pool yet haven't failed to connect, so they're not considered disconnected
+    pub(crate) fn is_disconnected(&self) -> bool {
+        if let Some(pool) = self.connection_pool.get() {
+            pool.is_disconnected()
+        } else {
+            false
+        }
+    }
+
+    pub(crate) async fn pooled_connections(&self) -> Vec<Arc<T>> {
+        if let Some(pool) = self.connection_pool.get() {
+            pool.connections().await
+        } else {
+            vec![]
+        }
+    }
+
+    pub(crate) async fn recycle_connections(&self) -> Result<()> {
+        if let Some(pool) = self.connection_pool.get() {
+            pool.recycle().await
+        } else {
+            Ok(())
+        }
+    }
+
     /// Creates a new connection to the node with optional event and error handlers.
     pub async fn new_connection(
         &self,
         event_handler: Option<Sender<Envelope>>,
         error_handler: Option<Sender<Error>>,
     ) -> Result<T> {
+        debug!("Establishing new connection to node...");
+        if self.alternative_addresses.is_empty() {
+            self.connection_pool_factory
+                .connection_manager()
+                .connection(event_handler, error_handler, self.broadcast_rpc_address)
+                .await
+        } else {
+            self.new_connection_with_diagnostics(event_handler, error_handler)
+                .await
+                .map_err(Error::from)
+        }
+    }
+
+    /// Creates a new connection to the node, like [`new_connection`](Node::new_connection), but
+    /// describes failures with the connection phase and time taken.
+    pub async fn new_connection_with_diagnostics(
+        &self,
+        event_handler: Option<Sender<Envelope>>,
+        error_handler: Option<Sender<Error>>,
+    ) -> std::result::Result<T, ConnectionError> {
         debug!("Establishing new connection to node...");
         self.connection_pool_factory
             .connection_manager()
-            .connection(event_handler, error_handler, self.broadcast_rpc_address)
+            .connection_with_alternatives(
+                event_handler,
+                error_handler,
+                self.broadcast_rpc_address,
+                &self.alternative_addresses,
+            )
             .await
     }
 
@@ -264,15 +331,32 @@ impl<T: CdrsTransport, CM: ConnectionManager<T>> Node<T, CM> {
     }
 
     pub(crate) fn force_down(&self) {
-        self.state.store(NodeState::ForcedDown, Ordering::Relaxed);
+        self.change_state(NodeState::ForcedDown);
     }
 
     pub(crate) fn mark_down(&self) {
-        self.state.store(NodeState::Down, Ordering::Relaxed);
+        self.change_state(NodeState::Down);
     }
 
     pub(crate) fn mark_up(&self) {
-        self.state.store(NodeState::Up, Ordering::Relaxed);
+        self.change_state(NodeState::Up);
+    }
+
+    fn change_state(&self, state: NodeState) {
+        if self.state.swap(state, Ordering::Relaxed) == state {
+            return;
+        }
+
+        if let Some(listener) = self.connection_pool_factory.node_state_listener() {
+            listener.on_state_changed(self.broadcast_rpc_address, state);
+        }
+    }
+
+    /// Returns diagnostics of the most recent failed attempt to connect the pool, if any.
+    pub(crate) fn last_connection_error(&self) -> Option<ConnectionError> {
+        self.connection_pool
+            .get()
+            .and_then(|pool| pool.last_connection_error())
     }
 
     #[inline]
@@ -308,6 +392,7 @@ impl<T: CdrsTransport, CM: ConnectionManager<T>> Node<T, CM> {
             connection_pool_factory: self.connection_pool_factory.clone(),
             connection_pool: Default::default(),
             broadcast_rpc_address: node_info.broadcast_rpc_address,
+            alternative_addresses: Default::default(),
             broadcast_address: node_info.broadcast_address,
             // since address could change, we can't be sure of distance or state
             distance: if address_changed { None } else { self.distance },
@@ -326,6 +411,7 @@ impl<T: CdrsTransport, CM: ConnectionManager<T>> Node<T, CM> {
             connection_pool_factory: self.connection_pool_factory.clone(),
             connection_pool: self.connection_pool.clone(),
             broadcast_rpc_address: self.broadcast_rpc_address,
+            alternative_addresses: self.alternative_addresses.clone(),
             broadcast_address: node_info.broadcast_address,
             distance: self.distance,
             state: Atomic::new(self.state.load(Ordering::Relaxed)),
@@ -346,6 +432,7 @@ impl<T: CdrsTransport, CM: ConnectionManager<T>> Node<T, CM> {
             connection_pool_factory: self.connection_pool_factory.clone(),
             connection_pool: Default::default(),
             broadcast_rpc_address: node_info.broadcast_rpc_address,
+            alternative_addresses: Default::default(),
             broadcast_address: node_info.broadcast_address,
             // since address could change, we can't be sure of distance
             distance: None,
@@ -363,6 +450,7 @@ impl<T: CdrsTransport, CM: ConnectionManager<T>> Node<T, CM> {
             connection_pool_factory: self.connection_pool_factory.clone(),
             connection_pool: Default::default(),
             broadcast_rpc_address: self.broadcast_rpc_address,
+            alternative_addresses: self.alternative_addresses.clone(),
             broadcast_address: self.broadcast_address,
             distance: self.distance,
             state: Atomic::new(state),
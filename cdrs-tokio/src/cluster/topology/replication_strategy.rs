@@ -9,5 +9,11 @@ pub enum ReplicationStrategy {
     NetworkTopologyStrategy {
         datacenter_replication_factor: FxHashMap<String, usize>,
     },
-    Other,
+    /// Strategy used by node-local system keyspaces - data is not replicated.
+    LocalStrategy,
+    /// Unknown strategy with its raw class name and options.
+    Other {
+        class: String,
+        options: FxHashMap<String, String>,
+    },
 }
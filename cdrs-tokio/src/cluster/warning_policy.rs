@@ -0,0 +1,231 @@
+use cassandra_protocol::error::{Error, Result};
+use cassandra_protocol::frame::Envelope;
+use tracing::*;
+
+/// Class of a server warning, which can have its own [`WarningAction`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum WarningClass {
+    /// Too many tombstones read, e.g. "Read 10 live rows and 5000 tombstone cells".
+    Tombstones,
+    /// Aggregation without a partition key, or over multiple partitions.
+    Aggregation,
+    /// Batch exceeding the size warning threshold, or an unlogged batch covering many partitions.
+    BatchSize,
+    /// Bound variables of a prepared statement changed after re-preparing it, e.g. after an
+    /// `ALTER TABLE`. Detected by the driver rather than sent by servers, and reported once per
+    /// change - [`SessionBuilder::with_strict_prepared_metadata`](crate::cluster::session::SessionBuilder::with_strict_prepared_metadata)
+    /// fails every execution of such statements instead. Logged by default.
+    PreparedMetadata,
+}
+
+impl WarningClass {
+    /// Classifies given warning text, returning `None` for unknown warnings.
+    pub fn classify(warning: &str) -> Option<WarningClass> {
+        let warning = warning.to_lowercase();
+
+        if warning.contains("tombstone") {
+            Some(WarningClass::Tombstones)
+        } else if warning.starts_with("aggregation query used") {
+            Some(WarningClass::Aggregation)
+        } else if warning.starts_with("batch for") || warning.starts_with("unlogged batch covering")
+        {
+            Some(WarningClass::BatchSize)
+        } else {
+            None
+        }
+    }
+}
+
+/// Action taken when a response contains a warning of a given class.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum WarningAction {
+    /// The warning is returned with the response, without any action.
+    #[default]
+    Ignore,
+    /// The warning is logged and returned with the response.
+    Log,
+    /// The response is replaced with [`Error::PolicyViolation`].
+    Error,
+}
+
+/// Decides what happens with responses containing known classes of warnings, e.g. to fail queries
+/// reading too many tombstones in staging environments. Unknown warnings are always ignored.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct WarningPolicy {
+    tombstones: WarningAction,
+    aggregation: WarningAction,
+    batch_size: WarningAction,
+    prepared_metadata: WarningAction,
+}
+
+impl Default for WarningPolicy {
+    fn default() -> Self {
+        WarningPolicy {
+            tombstones: WarningAction::Ignore,
+            aggregation: WarningAction::Ignore,
+            batch_size: WarningAction::Ignore,
+            prepared_metadata: WarningAction::Log,
+        }
+    }
+}
+
+impl WarningPolicy {
+    /// Creates a policy which ignores all server warnings and logs changed prepared metadata.
+    #[inline]
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Sets the action for given warning class.
+    #[must_use]
+    pub fn with_action(mut self, class: WarningClass, action: WarningAction) -> Self {
+        *self.action_mut(class) = action;
+        self
+    }
+
+    /// Returns the action for given warning class.
+    pub fn action(&self, class: WarningClass) -> WarningAction {
+        match class {
+            WarningClass::Tombstones => self.tombstones,
+            WarningClass::Aggregation => self.aggregation,
+            WarningClass::BatchSize => self.batch_size,
+            WarningClass::PreparedMetadata => self.prepared_metadata,
+        }
+    }
+
+    /// Checks if any class of server warnings is handled, in which case warnings need to be
+    /// requested for every statement.
+    #[inline]
+    pub fn requires_warnings(&self) -> bool {
+        [self.tombstones, self.aggregation, self.batch_size]
+            .iter()
+            .any(|action| *action != WarningAction::Ignore)
+    }
+
+    /// Applies the policy to warnings of given successful response.
+    pub(crate) fn apply(&self, response: Result<Envelope>, query: &str) -> Result<Envelope> {
+        let envelope = response?;
+        self.check(envelope.warnings(), query)?;
+        Ok(envelope)
+    }
+
+    /// Applies the policy to given warnings of a successful response.
+    pub(crate) fn check(&self, warnings: &[String], query: &str) -> Result<()> {
+        if !self.requires_warnings() {
+            return Ok(());
+        }
+
+        for warning in warnings {
+            let action = WarningClass::classify(warning)
+                .map(|class| self.action(class))
+                .unwrap_or_default();
+
+            match action {
+                WarningAction::Ignore => {}
+                WarningAction::Log => warn!(%warning, query, "Server warning."),
+                WarningAction::Error => {
+                    return Err(Error::PolicyViolation {
+                        warning: warning.clone(),
+                        query: query.into(),
+                    })
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Applies the policy to a warning detected by the driver, rather than sent by a server.
+    pub(crate) fn check_detected(
+        &self,
+        class: WarningClass,
+        warning: &str,
+        query: &str,
+    ) -> Result<()> {
+        match self.action(class) {
+            WarningAction::Ignore => Ok(()),
+            WarningAction::Log => {
+                warn!(warning, query, "Driver warning.");
+                Ok(())
+            }
+            WarningAction::Error => Err(Error::PolicyViolation {
+                warning: warning.into(),
+                query: query.into(),
+            }),
+        }
+    }
+
+    fn action_mut(&mut self, class: WarningClass) -> &mut WarningAction {
+        match class {
+            WarningClass::Tombstones => &mut self.tombstones,
+            WarningClass::Aggregation => &mut self.aggregation,
+            WarningClass::BatchSize => &mut self.batch_size,
+            WarningClass::PreparedMetadata => &mut self.prepared_metadata,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use cassandra_protocol::error::Error;
+    use cassandra_protocol::frame::{Envelope, Version};
+
+    use super::{WarningAction, WarningClass, WarningPolicy};
+
+    #[test]
+    fn should_classify_warnings() {
+        assert_eq!(
+            WarningClass::classify(
+                "Read 10 live rows and 5001 tombstone cells for query SELECT * FROM ks.t LIMIT 100"
+            ),
+            Some(WarningClass::Tombstones)
+        );
+        assert_eq!(
+            WarningClass::classify("Aggregation query used without partition key"),
+            Some(WarningClass::Aggregation)
+        );
+        assert_eq!(
+            WarningClass::classify(
+                "Batch for [ks.t] is of size 6.5KiB, exceeding specified threshold of 5.0KiB by 1.5KiB."
+            ),
+            Some(WarningClass::BatchSize)
+        );
+        assert_eq!(
+            WarningClass::classify(
+                "Unlogged batch covering 12 partitions detected against table [ks.t]."
+            ),
+            Some(WarningClass::BatchSize)
+        );
+        assert_eq!(WarningClass::classify("Something else"), None);
+    }
+
+    #[test]
+    fn should_apply_actions() {
+        let mut envelope = Envelope::new_req_options(Version::V4);
+        envelope.warnings = vec![
+            "Aggregation query used without partition key".into(),
+            "Read 0 live rows and 5000 tombstone cells".into(),
+        ];
+
+        let policy = WarningPolicy::new();
+        assert!(!policy.requires_warnings());
+        assert!(policy.apply(Ok(envelope.clone()), "SELECT").is_ok());
+
+        let policy = policy.with_action(WarningClass::Aggregation, WarningAction::Log);
+        assert!(policy.requires_warnings());
+        assert!(policy.apply(Ok(envelope.clone()), "SELECT").is_ok());
+
+        let policy = policy.with_action(WarningClass::Tombstones, WarningAction::Error);
+        assert_eq!(
+            policy.action(WarningClass::Tombstones),
+            WarningAction::Error
+        );
+        match policy.apply(Ok(envelope), "SELECT") {
+            Err(Error::PolicyViolation { warning, query }) => {
+                assert_eq!(warning, "Read 0 live rows and 5000 tombstone cells");
+                assert_eq!(query, "SELECT");
+            }
+            result => panic!("Unexpected result: {:?}", result),
+        }
+    }
+}
@@ -11,10 +11,11 @@ use cassandra_protocol::frame::{
 };
 use cassandra_protocol::types::data_serialization_types::decode_timeuuid;
 use cassandra_protocol::types::{
-    from_cursor_string_list, try_i16_from_bytes, try_i32_from_bytes, UUID_LEN,
+    from_cursor_bytes_map, from_cursor_string_list, try_i16_from_bytes, try_i32_from_bytes,
+    UUID_LEN,
 };
 
-async fn parse_raw_envelope<T: AsyncReadExt + Unpin>(
+pub(crate) async fn parse_raw_envelope<T: AsyncReadExt + Unpin>(
     cursor: &mut T,
     compressor: Compression,
 ) -> error::Result<Envelope> {
@@ -31,11 +32,13 @@ async fn parse_raw_envelope<T: AsyncReadExt + Unpin>(
     cursor.read_exact(&mut opcode_bytes).await?;
     cursor.read_exact(&mut length_bytes).await?;
 
-    let version = Version::try_from(version_bytes[0])?;
+    let version = Version::try_from(version_bytes[0])
+        .map_err(|error| error::Error::ProtocolDesync(error.to_string()))?;
     let direction = Direction::from(version_bytes[0]);
     let flags = Flags::from_bits_truncate(flag_bytes[0]);
     let stream_id = try_i16_from_bytes(&stream_bytes)?;
-    let opcode = Opcode::try_from(opcode_bytes[0])?;
+    let opcode = Opcode::try_from(opcode_bytes[0])
+        .map_err(|error| error::Error::ProtocolDesync(error.to_string()))?;
     let length = try_i32_from_bytes(&length_bytes)? as usize;
 
     let mut body_bytes = vec![0; length];
@@ -68,6 +71,12 @@ async fn parse_raw_envelope<T: AsyncReadExt + Unpin>(
         vec![]
     };
 
+    let custom_payload = if flags.contains(Flags::CUSTOM_PAYLOAD) {
+        from_cursor_bytes_map(&mut body_cursor, version)?
+    } else {
+        Default::default()
+    };
+
     let mut body = Vec::with_capacity(body_len - body_cursor.position() as usize);
 
     std::io::Read::read_to_end(&mut body_cursor, &mut body)?;
@@ -81,6 +90,7 @@ async fn parse_raw_envelope<T: AsyncReadExt + Unpin>(
         body,
         tracing_id,
         warnings,
+        custom_payload,
     };
 
     Ok(envelope)
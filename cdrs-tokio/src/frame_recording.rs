@@ -0,0 +1,544 @@
+//! Recording of envelopes exchanged with the cluster, which allows reproducing protocol-level
+//! issues without access to the original traffic. Recordings are enabled with
+//! [`SessionBuilder::with_frame_recorder`](crate::cluster::session::SessionBuilder::with_frame_recorder)
+//! and can be loaded with [`read_recording`]. Recorded responses can then be replayed against the
+//! driver with a [`ReplayTransport`].
+//!
+//! # Format
+//!
+//! A recording starts with an 8 byte magic `CDRSREC\0`, followed by a big-endian `u16` format
+//! version, currently `1`. Records follow until the end of the stream, each consisting of
+//! big-endian fields:
+//!
+//! * `u8` direction - `0` for envelopes sent to the server, `1` for received ones,
+//! * `u64` timestamp - microseconds since UNIX epoch,
+//! * `u64` connection id - unique within the process,
+//! * `u32` original envelope length,
+//! * `u32` recorded length,
+//! * recorded envelope bytes - envelope header and uncompressed body, which might be truncated.
+//!
+//! Envelopes are recorded regardless of protocol framing, as if they were sent without
+//! compression. Bodies are truncated if a maximum body length is set, so sensitive data can be
+//! left out, at the cost of not being able to replay truncated envelopes. Envelopes with
+//! [streamed blobs](crate::transport::StreamedBlob) are always truncated before the blob.
+//!
+//! Recording is synchronous, so it shouldn't be enabled in performance-sensitive environments.
+
+use cassandra_protocol::compression::Compression;
+use cassandra_protocol::error::{Error, Result};
+use cassandra_protocol::frame::{Direction, Envelope, Flags, Opcode};
+use std::collections::VecDeque;
+use std::convert::TryInto;
+use std::fmt::{Debug, Formatter};
+use std::fs::File;
+use std::io::{self, BufWriter, Read, Write};
+use std::net::SocketAddr;
+use std::path::Path;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{SystemTime, UNIX_EPOCH};
+use tracing::*;
+
+use crate::envelope_parser::convert_envelope_into_result;
+use crate::future::BoxFuture;
+use crate::transport::CdrsTransport;
+
+/// Magic bytes starting every recording.
+pub const RECORDING_MAGIC: &[u8; 8] = b"CDRSREC\0";
+
+/// Current recording format version.
+pub const RECORDING_FORMAT_VERSION: u16 = 1;
+
+const ENVELOPE_HEADER_LEN: usize = 9;
+
+const SENT: u8 = 0;
+const RECEIVED: u8 = 1;
+
+// direction, timestamp, connection id and both lengths
+const RECORD_HEADER_LEN: usize = 1 + 8 + 8 + 4 + 4;
+
+static NEXT_CONNECTION_ID: AtomicU64 = AtomicU64::new(1);
+
+/// Writes envelopes sent and received by connections to a sink in the recording format. A single
+/// recorder is shared by all connections of a session.
+pub struct FrameRecorder {
+    sink: Mutex<Box<dyn Write + Send>>,
+    max_body_len: Option<usize>,
+}
+
+impl Debug for FrameRecorder {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("FrameRecorder")
+            .field("max_body_len", &self.max_body_len)
+            .finish()
+    }
+}
+
+impl FrameRecorder {
+    /// Creates a recorder writing to given sink, starting with the recording header.
+    pub fn new<W: Write + Send + 'static>(mut sink: W) -> io::Result<Self> {
+        sink.write_all(RECORDING_MAGIC)?;
+        sink.write_all(&RECORDING_FORMAT_VERSION.to_be_bytes())?;
+
+        Ok(FrameRecorder {
+            sink: Mutex::new(Box::new(sink)),
+            max_body_len: None,
+        })
+    }
+
+    /// Creates a recorder writing to a new file at given path.
+    pub fn to_file<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        Self::new(BufWriter::new(File::create(path)?))
+    }
+
+    /// Truncates recorded envelope bodies to given length.
+    #[must_use]
+    pub fn with_max_body_len(mut self, max_body_len: usize) -> Self {
+        self.max_body_len = Some(max_body_len);
+        self
+    }
+
+    /// Flushes recorded data to the sink.
+    pub fn flush(&self) -> io::Result<()> {
+        self.sink.lock().unwrap().flush()
+    }
+
+    fn record(&self, direction: u8, connection_id: u64, envelope: &[u8], original_len: usize) {
+        let recorded_len = match self.max_body_len {
+            Some(max_body_len) => envelope
+                .len()
+                .min(ENVELOPE_HEADER_LEN.saturating_add(max_body_len)),
+            None => envelope.len(),
+        };
+
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|now| now.as_micros() as u64)
+            .unwrap_or_default();
+
+        let mut record = Vec::with_capacity(RECORD_HEADER_LEN + recorded_len);
+        record.push(direction);
+        record.extend_from_slice(&timestamp.to_be_bytes());
+        record.extend_from_slice(&connection_id.to_be_bytes());
+        record.extend_from_slice(&(original_len as u32).to_be_bytes());
+        record.extend_from_slice(&(recorded_len as u32).to_be_bytes());
+        record.extend_from_slice(&envelope[..recorded_len]);
+
+        if let Err(error) = self.sink.lock().unwrap().write_all(&record) {
+            warn!(%error, "Cannot record envelope.");
+        }
+    }
+}
+
+impl Drop for FrameRecorder {
+    fn drop(&mut self) {
+        if let Ok(sink) = self.sink.get_mut() {
+            let _ = sink.flush();
+        }
+    }
+}
+
+/// Recorder bound to a single connection.
+#[derive(Clone, Debug)]
+pub(crate) struct ConnectionRecorder {
+    recorder: Arc<FrameRecorder>,
+    connection_id: u64,
+    compression: Compression,
+}
+
+impl ConnectionRecorder {
+    pub(crate) fn new(recorder: Arc<FrameRecorder>, compression: Compression) -> Self {
+        ConnectionRecorder {
+            recorder,
+            connection_id: NEXT_CONNECTION_ID.fetch_add(1, Ordering::Relaxed),
+            compression,
+        }
+    }
+
+    /// Records an encoded envelope, which might be a prefix of a longer one with given length.
+    pub(crate) fn record_sent(&self, data: &[u8], original_len: usize) {
+        let compressed = data.len() == original_len
+            && data.get(1).is_some_and(|flags| {
+                Flags::from_bits_truncate(*flags).contains(Flags::COMPRESSION)
+            });
+
+        if compressed {
+            match Envelope::from_buffer(data, self.compression) {
+                Ok(parsed) => self.record_envelope(SENT, &parsed.envelope),
+                Err(error) => warn!(%error, "Cannot decompress envelope for recording."),
+            }
+        } else {
+            self.recorder
+                .record(SENT, self.connection_id, data, original_len);
+        }
+    }
+
+    pub(crate) fn record_received(&self, envelope: &Envelope) {
+        self.record_envelope(RECEIVED, envelope);
+    }
+
+    fn record_envelope(&self, direction: u8, envelope: &Envelope) {
+        match envelope.encode_with(Compression::None) {
+            Ok(data) => self
+                .recorder
+                .record(direction, self.connection_id, &data, data.len()),
+            Err(error) => warn!(%error, "Cannot encode envelope for recording."),
+        }
+    }
+}
+
+/// Single envelope loaded from a recording.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RecordedFrame {
+    /// [`Direction::Request`] for envelopes sent to the server, [`Direction::Response`] for
+    /// received ones.
+    pub direction: Direction,
+    /// Microseconds since UNIX epoch.
+    pub timestamp: u64,
+    /// Id of the connection which sent or received the envelope.
+    pub connection_id: u64,
+    /// Length of the whole envelope.
+    pub original_len: usize,
+    /// Recorded envelope bytes.
+    pub data: Vec<u8>,
+}
+
+impl RecordedFrame {
+    /// Checks if the envelope has been recorded partially.
+    #[inline]
+    pub fn is_truncated(&self) -> bool {
+        self.data.len() < self.original_len
+    }
+
+    /// Returns the recorded opcode, if the header has been recorded.
+    #[inline]
+    pub fn opcode(&self) -> Option<Opcode> {
+        Envelope::parse_header(&self.data)
+            .ok()
+            .map(|header| header.opcode)
+    }
+
+    /// Parses the recorded envelope. Fails for truncated envelopes.
+    pub fn envelope(&self) -> Result<Envelope> {
+        if self.is_truncated() {
+            return Err(Error::General(format!(
+                "Envelope recorded partially: {} of {} bytes!",
+                self.data.len(),
+                self.original_len
+            )));
+        }
+
+        Envelope::from_buffer(&self.data, Compression::None)
+            .map(|parsed| parsed.envelope)
+            .map_err(|error| Error::General(error.to_string()))
+    }
+}
+
+/// Loads all envelopes from a recording.
+pub fn read_recording<R: Read>(mut reader: R) -> io::Result<Vec<RecordedFrame>> {
+    let mut data = vec![];
+    reader.read_to_end(&mut data)?;
+
+    let invalid = |message: &str| io::Error::new(io::ErrorKind::InvalidData, message.to_string());
+
+    let header_len = RECORDING_MAGIC.len() + 2;
+    if data.len() < header_len || &data[..RECORDING_MAGIC.len()] != RECORDING_MAGIC {
+        return Err(invalid("Not a frame recording!"));
+    }
+
+    let version = u16::from_be_bytes([data[8], data[9]]);
+    if version != RECORDING_FORMAT_VERSION {
+        return Err(invalid(&format!(
+            "Unsupported recording format version: {version}"
+        )));
+    }
+
+    let mut frames = vec![];
+    let mut data = &data[header_len..];
+
+    while !data.is_empty() {
+        if data.len() < RECORD_HEADER_LEN {
+            return Err(invalid("Truncated record header!"));
+        }
+
+        let direction = match data[0] {
+            SENT => Direction::Request,
+            RECEIVED => Direction::Response,
+            direction => return Err(invalid(&format!("Invalid record direction: {direction}"))),
+        };
+
+        let timestamp = u64::from_be_bytes(data[1..9].try_into().unwrap());
+        let connection_id = u64::from_be_bytes(data[9..17].try_into().unwrap());
+        let original_len = u32::from_be_bytes(data[17..21].try_into().unwrap()) as usize;
+        let recorded_len = u32::from_be_bytes(data[21..25].try_into().unwrap()) as usize;
+
+        let record_data = data
+            .get(RECORD_HEADER_LEN..(RECORD_HEADER_LEN + recorded_len))
+            .ok_or_else(|| invalid("Truncated record!"))?;
+
+        frames.push(RecordedFrame {
+            direction,
+            timestamp,
+            connection_id,
+            original_len,
+            data: record_data.to_vec(),
+        });
+
+        data = &data[(RECORD_HEADER_LEN + recorded_len)..];
+    }
+
+    Ok(frames)
+}
+
+/// Transport which answers requests with responses recorded for a single connection, in the
+/// order of recorded requests. Each request needs to have the same opcode as the recorded one -
+/// otherwise the replay has diverged from the recording and the request fails. Server events are
+/// not replayed.
+#[derive(Debug)]
+pub struct ReplayTransport {
+    addr: SocketAddr,
+    exchanges: Mutex<VecDeque<(Option<Opcode>, Option<RecordedFrame>)>>,
+}
+
+impl ReplayTransport {
+    /// Creates a transport replaying given connection from recorded frames, pretending to be
+    /// connected to given address.
+    pub fn new(frames: &[RecordedFrame], connection_id: u64, addr: SocketAddr) -> Self {
+        let frames = frames
+            .iter()
+            .filter(|frame| frame.connection_id == connection_id)
+            .collect::<Vec<_>>();
+
+        let exchanges = frames
+            .iter()
+            .enumerate()
+            .filter(|(_, frame)| frame.direction == Direction::Request)
+            .map(|(index, request)| {
+                // responses are matched by stream id, since requests can be pipelined
+                let stream_id = Envelope::parse_header(&request.data)
+                    .ok()
+                    .map(|header| header.stream_id);
+
+                let response = frames[(index + 1)..]
+                    .iter()
+                    .find(|frame| {
+                        frame.direction == Direction::Response
+                            && stream_id.is_some()
+                            && Envelope::parse_header(&frame.data)
+                                .ok()
+                                .map(|header| header.stream_id)
+                                == stream_id
+                    })
+                    .map(|response| (*response).clone());
+
+                (request.opcode(), response)
+            })
+            .collect();
+
+        ReplayTransport {
+            addr,
+            exchanges: Mutex::new(exchanges),
+        }
+    }
+
+    /// Returns how many recorded requests haven't been replayed yet.
+    pub fn remaining(&self) -> usize {
+        self.exchanges.lock().unwrap().len()
+    }
+
+    fn replay(&self, envelope: &Envelope) -> Result<Envelope> {
+        let (opcode, response) = self
+            .exchanges
+            .lock()
+            .unwrap()
+            .pop_front()
+            .ok_or_else(|| Error::General("No more recorded requests to replay!".into()))?;
+
+        if opcode != Some(envelope.opcode) {
+            return Err(Error::General(format!(
+                "Replay diverged from recording: expected {:?} request, got {:?}!",
+                opcode, envelope.opcode
+            )));
+        }
+
+        let response = response
+            .ok_or_else(|| Error::General("No response recorded for request!".into()))?
+            .envelope()?;
+
+        convert_envelope_into_result(response, self.addr)
+    }
+}
+
+impl CdrsTransport for ReplayTransport {
+    fn write_envelope<'a>(
+        &'a self,
+        envelope: &'a Envelope,
+        _handshake: bool,
+    ) -> BoxFuture<'a, Result<Envelope>> {
+        let response = self.replay(envelope);
+        Box::pin(async move { response })
+    }
+
+    #[inline]
+    fn is_broken(&self) -> bool {
+        false
+    }
+
+    #[inline]
+    fn address(&self) -> SocketAddr {
+        self.addr
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use cassandra_protocol::compression::Compression;
+    use cassandra_protocol::frame::{Direction, Envelope, Flags, Opcode, Version};
+    use std::io::{self, Write};
+    use std::net::SocketAddr;
+    use std::sync::{Arc, Mutex};
+
+    use super::{read_recording, ConnectionRecorder, FrameRecorder, ReplayTransport};
+    use crate::transport::CdrsTransport;
+
+    #[derive(Clone, Default)]
+    struct SharedBuffer(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for SharedBuffer {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    fn envelope(direction: Direction, opcode: Opcode, stream_id: i16, body: Vec<u8>) -> Envelope {
+        Envelope::new(
+            Version::V4,
+            direction,
+            Flags::empty(),
+            opcode,
+            stream_id,
+            body,
+            None,
+            vec![],
+        )
+    }
+
+    fn encoded(envelope: &Envelope, compression: Compression) -> Vec<u8> {
+        envelope.encode_with(compression).unwrap()
+    }
+
+    #[test]
+    fn should_round_trip_recording() {
+        let buffer = SharedBuffer::default();
+        let recorder = Arc::new(FrameRecorder::new(buffer.clone()).unwrap());
+        let connection = ConnectionRecorder::new(recorder.clone(), Compression::Lz4);
+
+        let request = envelope(Direction::Request, Opcode::Options, 1, vec![0; 64]);
+        let response = envelope(Direction::Response, Opcode::Supported, 1, vec![0, 0]);
+
+        let data = encoded(&request, Compression::Lz4);
+        connection.record_sent(&data, data.len());
+        connection.record_received(&response);
+
+        let frames = read_recording(buffer.0.lock().unwrap().as_slice()).unwrap();
+
+        assert_eq!(frames.len(), 2);
+        assert_eq!(frames[0].direction, Direction::Request);
+        assert_eq!(frames[0].connection_id, frames[1].connection_id);
+        assert!(!frames[0].is_truncated());
+
+        // compressed envelopes are recorded uncompressed
+        assert_eq!(frames[0].data, encoded(&request, Compression::None));
+        assert_eq!(frames[1].envelope().unwrap(), response);
+        assert!(frames[0].timestamp <= frames[1].timestamp);
+    }
+
+    #[test]
+    fn should_truncate_bodies() {
+        let buffer = SharedBuffer::default();
+        let recorder = Arc::new(
+            FrameRecorder::new(buffer.clone())
+                .unwrap()
+                .with_max_body_len(4),
+        );
+        let connection = ConnectionRecorder::new(recorder, Compression::None);
+
+        let request = envelope(Direction::Request, Opcode::Query, 1, vec![1; 64]);
+        let data = encoded(&request, Compression::None);
+
+        // a prefix of a longer envelope, e.g. with a streamed blob
+        connection.record_sent(&data[..20], 1000);
+        connection.record_sent(&data, data.len());
+
+        let frames = read_recording(buffer.0.lock().unwrap().as_slice()).unwrap();
+
+        assert_eq!(frames.len(), 2);
+        assert_eq!(frames[0].original_len, 1000);
+        assert_eq!(frames[0].data, data[..13]);
+        assert_eq!(frames[1].data, data[..13]);
+        assert_eq!(frames[1].opcode(), Some(Opcode::Query));
+        assert!(frames[1].is_truncated());
+        assert!(frames[1].envelope().is_err());
+    }
+
+    #[test]
+    fn should_reject_invalid_recordings() {
+        assert!(read_recording(&b"CDRS"[..]).is_err());
+        assert!(read_recording(&b"CDRSREC\0\0\x02"[..]).is_err());
+        assert!(read_recording(&b"CDRSREC\0\0\x01\0"[..]).is_err());
+        assert!(read_recording(&b"CDRSREC\0\0\x01"[..]).unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn should_replay_responses_by_stream_id() {
+        let buffer = SharedBuffer::default();
+        let recorder = Arc::new(FrameRecorder::new(buffer.clone()).unwrap());
+        let connection = ConnectionRecorder::new(recorder.clone(), Compression::None);
+        let other_connection = ConnectionRecorder::new(recorder, Compression::None);
+
+        // pipelined requests with responses in reverse order
+        for (opcode, stream_id) in [(Opcode::Options, 1), (Opcode::Query, 2)] {
+            let data = encoded(
+                &envelope(Direction::Request, opcode, stream_id, vec![]),
+                Compression::None,
+            );
+            connection.record_sent(&data, data.len());
+        }
+
+        other_connection.record_received(&envelope(Direction::Response, Opcode::Ready, 1, vec![]));
+        connection.record_received(&envelope(
+            Direction::Response,
+            Opcode::Result,
+            2,
+            vec![0, 0, 0, 1],
+        ));
+        connection.record_received(&envelope(
+            Direction::Response,
+            Opcode::Supported,
+            1,
+            vec![0, 0],
+        ));
+
+        let frames = read_recording(buffer.0.lock().unwrap().as_slice()).unwrap();
+        let transport = ReplayTransport::new(
+            &frames,
+            frames[0].connection_id,
+            SocketAddr::from(([127, 0, 0, 1], 9042)),
+        );
+
+        assert_eq!(transport.remaining(), 2);
+
+        let options = envelope(Direction::Request, Opcode::Options, 0, vec![]);
+        let response = transport.write_envelope(&options, false).await.unwrap();
+        assert_eq!(response.opcode, Opcode::Supported);
+
+        // replaying a different request than recorded fails
+        let response = transport.write_envelope(&options, false).await;
+        assert!(response.is_err());
+        assert_eq!(transport.remaining(), 0);
+    }
+}
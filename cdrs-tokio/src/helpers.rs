@@ -0,0 +1,10 @@
+//! Higher-level helpers for common usage patterns, built on top of
+//! [`Session`](crate::cluster::session::Session).
+
+mod insert;
+mod lwt;
+mod typed_rows;
+
+pub(crate) use self::insert::InsertStatementKey;
+pub use self::insert::{InsertOptions, RowValues};
+pub use self::lwt::{LwtResult, MAX_CAS_ATTEMPTS};
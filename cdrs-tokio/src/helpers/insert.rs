@@ -0,0 +1,333 @@
+use cassandra_protocol::error::{Error, Result};
+use cassandra_protocol::frame::Envelope;
+use cassandra_protocol::query::utils::quote_identifier;
+use cassandra_protocol::query::{PreparedQuery, QueryValues};
+use cassandra_protocol::types::value::Value;
+use std::any::TypeId;
+use std::convert::TryFrom;
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::cluster::session::Session;
+use crate::cluster::ConnectionManager;
+use crate::load_balancing::LoadBalancingStrategy;
+use crate::statement::StatementParams;
+use crate::transport::CdrsTransport;
+
+/// Struct which can be written as a table row. Usually derived with
+/// [`IntoQueryValues`](crate::IntoQueryValues), which respects column renames and skipped fields.
+pub trait RowValues {
+    /// Names of columns, in the order of values returned by [`RowValues::row_values`].
+    fn columns() -> &'static [&'static str];
+
+    /// Values of all columns. Unset values are not written, as opposed to `NULL` values, which
+    /// create tombstones.
+    fn row_values(&self) -> Vec<Value>;
+}
+
+/// Options of statements generated by [`Session::insert_with_options`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct InsertOptions {
+    ttl: Option<Duration>,
+    timestamp: Option<i64>,
+    if_not_exists: bool,
+}
+
+impl InsertOptions {
+    /// Sets time to live of written values, with a granularity of seconds.
+    #[must_use]
+    pub fn with_ttl(mut self, ttl: Duration) -> Self {
+        self.ttl = Some(ttl);
+        self
+    }
+
+    /// Sets write timestamp, in microseconds since the Unix epoch. Conditional inserts, i.e.
+    /// [`IF NOT EXISTS`](InsertOptions::with_if_not_exists), can't use custom timestamps.
+    #[must_use]
+    pub fn with_timestamp(mut self, timestamp: i64) -> Self {
+        self.timestamp = Some(timestamp);
+        self
+    }
+
+    /// Inserts the row only if it doesn't exist yet. The result can be read with
+    /// [`LwtResult::from_envelope`](crate::helpers::LwtResult::from_envelope). Can't be combined
+    /// with a custom timestamp.
+    #[must_use]
+    pub fn with_if_not_exists(mut self, if_not_exists: bool) -> Self {
+        self.if_not_exists = if_not_exists;
+        self
+    }
+}
+
+// timestamp is sent as a query parameter, so it doesn't influence the statement
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub(crate) struct InsertStatementKey {
+    table: String,
+    row_type: TypeId,
+    with_ttl: bool,
+    if_not_exists: bool,
+}
+
+// the server rejects such statements, but only after preparing them
+fn check_timestamp(options: &InsertOptions, parameters_timestamp: Option<i64>) -> Result<()> {
+    if options.if_not_exists && (options.timestamp.is_some() || parameters_timestamp.is_some()) {
+        return Err(Error::General(
+            "Conditional inserts can't use custom timestamps!".into(),
+        ));
+    }
+
+    Ok(())
+}
+
+// values from parameters take precedence over row values of the same columns - only named ones
+// can be merged, since positions of columns depend on the row type
+fn merge_values(
+    columns: &[&str],
+    mut values: Vec<Value>,
+    parameters: Option<QueryValues>,
+) -> Result<Vec<Value>> {
+    match parameters {
+        Some(QueryValues::SimpleValues(parameters)) if !parameters.is_empty() => {
+            return Err(Error::General(
+                "Only named values can be merged with row values!".into(),
+            ));
+        }
+        Some(QueryValues::NamedValues(parameters)) => {
+            for (name, value) in parameters {
+                let index = columns
+                    .iter()
+                    .position(|column| *column == name)
+                    .ok_or_else(|| {
+                        Error::General(format!("Column {name} is not written by the insert!"))
+                    })?;
+
+                values[index] = value;
+            }
+        }
+        _ => {}
+    }
+
+    Ok(values)
+}
+
+fn insert_query(table: &str, columns: &[&str], options: &InsertOptions) -> String {
+    let mut query = format!(
+        "INSERT INTO {} ({}) VALUES ({})",
+        table,
+        columns
+            .iter()
+            .map(|column| quote_identifier(column))
+            .collect::<Vec<_>>()
+            .join(", "),
+        vec!["?"; columns.len()].join(", ")
+    );
+
+    if options.if_not_exists {
+        query.push_str(" IF NOT EXISTS");
+    }
+
+    if options.ttl.is_some() {
+        query.push_str(" USING TTL ?");
+    }
+
+    query
+}
+
+impl<
+        T: CdrsTransport + 'static,
+        CM: ConnectionManager<T> + Send + Sync + 'static,
+        LB: LoadBalancingStrategy<T, CM> + Send + Sync + 'static,
+    > Session<T, CM, LB>
+{
+    /// Inserts given row into a table, e.g. `keyspace.table`. See
+    /// [`Session::insert_with_params`] for details.
+    #[inline]
+    pub async fn insert<R: RowValues + 'static>(&self, table: &str, row: &R) -> Result<Envelope> {
+        self.insert_with_options(table, row, &Default::default())
+            .await
+    }
+
+    /// Inserts given row into a table, e.g. `keyspace.table`, with given options. See
+    /// [`Session::insert_with_params`] for details.
+    #[inline]
+    pub async fn insert_with_options<R: RowValues + 'static>(
+        &self,
+        table: &str,
+        row: &R,
+        options: &InsertOptions,
+    ) -> Result<Envelope> {
+        self.insert_with_params(table, row, options, Default::default())
+            .await
+    }
+
+    /// Inserts given row into a table, e.g. `keyspace.table`, with given options and statement
+    /// parameters. Named values in the parameters are merged with row values, replacing values of
+    /// the same columns, while positional ones are rejected. A timestamp in the options replaces
+    /// the one in the parameters.
+    ///
+    /// The generated statement is prepared once per table, row type and options, and reused by
+    /// subsequent inserts.
+    pub async fn insert_with_params<R: RowValues + 'static>(
+        &self,
+        table: &str,
+        row: &R,
+        options: &InsertOptions,
+        mut parameters: StatementParams,
+    ) -> Result<Envelope> {
+        check_timestamp(options, parameters.query_params.timestamp)?;
+
+        let mut values = merge_values(
+            R::columns(),
+            row.row_values(),
+            parameters.query_params.values.take(),
+        )?;
+
+        if let Some(ttl) = options.ttl {
+            let ttl = i32::try_from(ttl.as_secs())
+                .map_err(|_| Error::General(format!("TTL of {ttl:?} is too large!")))?;
+            values.push(ttl.into());
+        }
+
+        let prepared = self
+            .prepare_insert(
+                InsertStatementKey {
+                    table: table.to_string(),
+                    row_type: TypeId::of::<R>(),
+                    with_ttl: options.ttl.is_some(),
+                    if_not_exists: options.if_not_exists,
+                },
+                || insert_query(table, R::columns(), options),
+            )
+            .await?;
+
+        parameters.query_params.values = Some(QueryValues::SimpleValues(values));
+        parameters.query_params.with_names = false;
+        if options.timestamp.is_some() {
+            parameters.query_params.timestamp = options.timestamp;
+        }
+
+        self.exec_with_params(&prepared, &parameters).await
+    }
+
+    async fn prepare_insert(
+        &self,
+        key: InsertStatementKey,
+        query: impl FnOnce() -> String,
+    ) -> Result<Arc<PreparedQuery>> {
+        if let Some(prepared) = self.insert_statements().lock().unwrap().get(&key) {
+            return Ok(prepared.clone());
+        }
+
+        // concurrent inserts might prepare the same statement, which is harmless
+        let prepared = Arc::new(self.prepare(query()).await?);
+        self.insert_statements()
+            .lock()
+            .unwrap()
+            .insert(key, prepared.clone());
+
+        Ok(prepared)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use cassandra_protocol::types::value::Value;
+    use std::time::Duration;
+
+    use cassandra_protocol::error::Error;
+    use cassandra_protocol::query::QueryValues;
+    use std::collections::HashMap;
+
+    use super::{check_timestamp, insert_query, merge_values, InsertOptions, RowValues};
+    use crate::cluster::session::TcpSession;
+    use crate::cluster::test_nodes::assert_send;
+    use crate::cluster::TcpConnectionManager;
+    use crate::load_balancing::RoundRobinLoadBalancingStrategy;
+    use crate::transport::TransportTcp;
+
+    struct User {
+        id: i32,
+    }
+
+    impl RowValues for User {
+        fn columns() -> &'static [&'static str] {
+            &["id"]
+        }
+
+        fn row_values(&self) -> Vec<Value> {
+            vec![self.id.into()]
+        }
+    }
+
+    #[allow(dead_code)]
+    fn inserts_should_be_send(
+        session: TcpSession<RoundRobinLoadBalancingStrategy<TransportTcp, TcpConnectionManager>>,
+    ) {
+        assert_send(session.insert("ks.users", &User { id: 1 }));
+    }
+
+    #[test]
+    fn should_reject_timestamps_of_conditional_inserts() {
+        let if_not_exists = InsertOptions::default().with_if_not_exists(true);
+
+        assert!(check_timestamp(&if_not_exists, None).is_ok());
+        assert!(check_timestamp(&if_not_exists.with_timestamp(5), None).is_err());
+        assert!(check_timestamp(&if_not_exists, Some(5)).is_err());
+        assert!(check_timestamp(&InsertOptions::default().with_timestamp(5), Some(5)).is_ok());
+    }
+
+    #[test]
+    fn should_merge_named_values() {
+        let values = merge_values(
+            &["id", "name"],
+            vec![Value::new(1), Value::NotSet],
+            Some(QueryValues::NamedValues(HashMap::from([(
+                "name".to_string(),
+                Value::new("a"),
+            )]))),
+        )
+        .unwrap();
+        assert_eq!(values, vec![Value::new(1), Value::new("a")]);
+
+        assert!(matches!(
+            merge_values(
+                &["id"],
+                vec![Value::new(1)],
+                Some(QueryValues::NamedValues(HashMap::from([(
+                    "other".to_string(),
+                    Value::Null,
+                )])))
+            ),
+            Err(Error::General(_))
+        ));
+        assert!(matches!(
+            merge_values(
+                &["id"],
+                vec![Value::new(1)],
+                Some(QueryValues::SimpleValues(vec![Value::Null]))
+            ),
+            Err(Error::General(_))
+        ));
+    }
+
+    #[test]
+    fn should_generate_insert_queries() {
+        let columns = ["id", "userName", "weird\"name"];
+
+        assert_eq!(
+            insert_query("ks.users", &columns, &InsertOptions::default()),
+            "INSERT INTO ks.users (id, \"userName\", \"weird\"\"name\") VALUES (?, ?, ?)"
+        );
+        assert_eq!(
+            insert_query(
+                "ks.users",
+                &columns[..1],
+                &InsertOptions::default()
+                    .with_ttl(Duration::from_secs(60))
+                    .with_timestamp(5)
+                    .with_if_not_exists(true)
+            ),
+            "INSERT INTO ks.users (id) VALUES (?) IF NOT EXISTS USING TTL ?"
+        );
+    }
+}
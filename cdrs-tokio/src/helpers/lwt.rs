@@ -0,0 +1,432 @@
+use cassandra_protocol::consistency::Consistency;
+use cassandra_protocol::error::{Error, Result};
+use cassandra_protocol::frame::message_error::{ErrorBody, ErrorType, WriteType};
+use cassandra_protocol::frame::{Envelope, TryFromRow};
+use cassandra_protocol::types::rows::Row;
+use cassandra_protocol::types::IntoRustByName;
+use std::future::Future;
+use std::sync::Arc;
+use tracing::*;
+
+use crate::cluster::session::Session;
+use crate::cluster::ConnectionManager;
+use crate::load_balancing::LoadBalancingStrategy;
+use crate::retry::{
+    FallthroughRetryPolicy, LazyRetrySession, QueryInfo, RetryDecision, RetryPolicy, RetrySession,
+};
+use crate::statement::StatementParams;
+use crate::transport::CdrsTransport;
+
+const APPLIED_COLUMN: &str = "[applied]";
+
+/// Maximum number of attempts of a conditional statement, including the first one.
+pub const MAX_CAS_ATTEMPTS: usize = 3;
+
+/// Result of a conditional statement (lightweight transaction).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LwtResult<R> {
+    /// The condition was met and the statement has been applied.
+    Applied,
+    /// The condition was not met. Contains the current row, if the server returned one (e.g. for
+    /// `IF NOT EXISTS` when the row exists, but not for `IF EXISTS` when it doesn't).
+    NotApplied(Option<R>),
+    /// The condition was not met, but an earlier attempt failed in a way which might have
+    /// applied the statement. The current row might be the result of that attempt, so it should be
+    /// compared with the written values to find out if the write succeeded.
+    Uncertain(Option<R>),
+}
+
+impl<R: TryFromRow> LwtResult<R> {
+    /// Reads the result of a conditional statement from the returned rows.
+    pub fn from_rows(rows: Vec<Row>) -> Result<Self> {
+        let row = rows
+            .into_iter()
+            .next()
+            .ok_or_else(|| Error::General("Conditional statement returned no rows!".into()))?;
+
+        let applied: bool = row.get_r_by_name(APPLIED_COLUMN)?;
+        if applied {
+            return Ok(LwtResult::Applied);
+        }
+
+        // only the applied column is present if there is no current row
+        if row.column_count() > 1 {
+            R::try_from_row(row).map(|row| LwtResult::NotApplied(Some(row)))
+        } else {
+            Ok(LwtResult::NotApplied(None))
+        }
+    }
+
+    /// Reads the result of a conditional statement from the response envelope.
+    pub fn from_envelope(envelope: &Envelope) -> Result<Self> {
+        envelope
+            .response_body()?
+            .into_rows()
+            .ok_or_else(|| Error::General("Conditional statement didn't return rows!".into()))
+            .and_then(Self::from_rows)
+    }
+}
+
+impl<R> LwtResult<R> {
+    /// Checks if the statement has been applied.
+    #[inline]
+    pub fn is_applied(&self) -> bool {
+        matches!(self, LwtResult::Applied)
+    }
+
+    fn into_uncertain(self) -> Self {
+        match self {
+            LwtResult::NotApplied(row) => LwtResult::Uncertain(row),
+            result => result,
+        }
+    }
+}
+
+fn is_cas_write_timeout(error: &Error) -> bool {
+    match error {
+        Error::RetrySuppressed(error) => is_cas_write_timeout(error),
+        Error::Server {
+            body:
+                ErrorBody {
+                    ty: ErrorType::WriteTimeout(write_timeout),
+                    ..
+                },
+            ..
+        } => write_timeout.write_type == WriteType::Cas,
+        _ => false,
+    }
+}
+
+// sends the statement until it returns a result, the retry session gives up or the attempts run
+// out - CAS write timeouts are always retried, since their outcome is only known after reading
+// the current row
+async fn execute_cas<R: TryFromRow, F: Future<Output = Result<Envelope>>>(
+    mut send: impl FnMut() -> F,
+    retry_session: &mut (dyn RetrySession + Send),
+) -> Result<LwtResult<R>> {
+    let mut uncertain = false;
+    let mut attempt = 1;
+    loop {
+        let error = match send().await {
+            Ok(envelope) => {
+                let result = LwtResult::from_envelope(&envelope)?;
+                return Ok(if uncertain {
+                    result.into_uncertain()
+                } else {
+                    result
+                });
+            }
+            Err(error) => error,
+        };
+
+        if attempt >= MAX_CAS_ATTEMPTS {
+            debug!(%error, attempt, "Giving up on conditional statement.");
+            return Err(error);
+        }
+
+        if !is_cas_write_timeout(&error) {
+            let decision = retry_session.decide(QueryInfo {
+                error: &error,
+                is_idempotent: true,
+                batch_type: None,
+            });
+
+            if decision == RetryDecision::DontRetry {
+                return Err(error);
+            }
+        }
+
+        debug!(%error, attempt, "Retrying conditional statement.");
+        uncertain |= may_have_been_applied(&error);
+        attempt += 1;
+    }
+}
+
+/// Checks if a failed conditional statement might have been applied, despite the error.
+fn may_have_been_applied(error: &Error) -> bool {
+    if let Error::RetrySuppressed(error) = error {
+        return may_have_been_applied(error);
+    }
+
+    !matches!(
+        error,
+        Error::RequestNotSent(_)
+            | Error::Server {
+                body: ErrorBody {
+                    ty: ErrorType::Unavailable(_)
+                        | ErrorType::Overloaded
+                        | ErrorType::IsBootstrapping
+                        | ErrorType::Unprepared(_)
+                        | ErrorType::Syntax
+                        | ErrorType::Unauthorized
+                        | ErrorType::Invalid
+                        | ErrorType::Config
+                        | ErrorType::AlreadyExists(_),
+                    ..
+                },
+                ..
+            }
+    )
+}
+
+impl<
+        T: CdrsTransport + 'static,
+        CM: ConnectionManager<T> + Send + Sync + 'static,
+        LB: LoadBalancingStrategy<T, CM> + Send + Sync + 'static,
+    > Session<T, CM, LB>
+{
+    /// Executes a conditional statement, e.g. `INSERT ... IF NOT EXISTS` or `UPDATE ... IF ...`,
+    /// and returns whether it has been applied, along with the current row read with
+    /// [`TryFromRow`] if it hasn't. Serial consistency defaults to [`Consistency::Serial`].
+    ///
+    /// Failed attempts are retried according to the statement or session retry policy, with the
+    /// statement treated as idempotent, since the condition guards against applying it twice. A
+    /// CAS write timeout leaves the outcome of an attempt unknown, so the mutation is never
+    /// retried by the underlying request machinery. Instead, the whole conditional statement is
+    /// always sent again and, if its condition doesn't hold anymore, [`LwtResult::Uncertain`] is
+    /// returned. Statements whose condition still holds after being applied will be applied again,
+    /// so they should be idempotent. The statement is sent at most [`MAX_CAS_ATTEMPTS`] times,
+    /// after which the last error is returned.
+    pub async fn cas<R: TryFromRow, Q: ToString>(
+        &self,
+        query: Q,
+        mut parameters: StatementParams,
+    ) -> Result<LwtResult<R>> {
+        let query = query.to_string();
+
+        let fallthrough: Arc<dyn RetryPolicy + Send + Sync> = Arc::new(FallthroughRetryPolicy);
+        let retry_policy = parameters.retry_policy.replace(fallthrough);
+        let mut retry_session =
+            LazyRetrySession::new(self.effective_retry_policy(retry_policy.as_ref()));
+
+        if parameters.query_params.serial_consistency.is_none() {
+            parameters.query_params.serial_consistency = Some(Consistency::Serial);
+        }
+
+        execute_cas(
+            || self.query_with_params(&query, parameters.clone()),
+            &mut retry_session,
+        )
+        .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use cassandra_protocol::compression::Compression;
+    use cassandra_protocol::consistency::Consistency;
+    use cassandra_protocol::error::{Error, Result};
+    use cassandra_protocol::frame::message_error::{
+        ErrorBody, ErrorType, UnavailableError, WriteTimeoutError, WriteType,
+    };
+    use cassandra_protocol::frame::message_result::{
+        BodyResResultRows, ColSpec, ColType, ColTypeOption, RowsMetadata, RowsMetadataFlags,
+    };
+    use cassandra_protocol::frame::{Envelope, TryFromRow, Version};
+    use cassandra_protocol::types::rows::Row;
+    use cassandra_protocol::types::{CBytes, IntoRustByName};
+    use std::collections::VecDeque;
+    use std::io;
+    use std::net::SocketAddr;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::{Arc, Mutex};
+
+    use super::{execute_cas, may_have_been_applied, LwtResult, MAX_CAS_ATTEMPTS};
+    use crate::retry::DefaultRetrySession;
+    use crate::transport::{CdrsTransport, MockCdrsTransport};
+
+    #[derive(Debug, PartialEq)]
+    struct User {
+        id: i32,
+    }
+
+    impl TryFromRow for User {
+        fn try_from_row(row: Row) -> Result<Self> {
+            Ok(User {
+                id: row.get_r_by_name("id")?,
+            })
+        }
+    }
+
+    fn col_spec(name: &str, id: ColType) -> ColSpec {
+        ColSpec {
+            table_spec: None,
+            name: name.into(),
+            col_type: ColTypeOption { id, value: None },
+        }
+    }
+
+    fn rows(col_specs: Vec<ColSpec>, row_content: Vec<CBytes>) -> Vec<Row> {
+        Row::from_body(BodyResResultRows {
+            metadata: RowsMetadata {
+                flags: RowsMetadataFlags::empty(),
+                columns_count: col_specs.len() as i32,
+                paging_state: None,
+                new_metadata_id: None,
+                global_table_spec: None,
+                col_specs,
+            },
+            rows_count: 1,
+            rows_content: vec![row_content],
+            protocol_version: Version::V4,
+        })
+    }
+
+    fn server_error(ty: ErrorType) -> Error {
+        Error::Server {
+            body: ErrorBody {
+                message: "".into(),
+                ty,
+            },
+            addr: SocketAddr::from(([127, 0, 0, 1], 9042)),
+        }
+    }
+
+    fn cas_write_timeout() -> Error {
+        server_error(ErrorType::WriteTimeout(WriteTimeoutError {
+            cl: Consistency::Quorum,
+            received: 0,
+            block_for: 2,
+            write_type: WriteType::Cas,
+            contentions: Some(1),
+        }))
+    }
+
+    // V4 rows result with only the applied column
+    fn applied_envelope(applied: bool) -> Envelope {
+        let mut body = vec![0, 0, 0, 2, 0, 0, 0, 1, 0, 0, 0, 1];
+        body.extend_from_slice(&[0, 2, b'k', b's', 0, 1, b't']);
+        body.extend_from_slice(&[0, 9]);
+        body.extend_from_slice(b"[applied]");
+        body.extend_from_slice(&[0, 4, 0, 0, 0, 1, 0, 0, 0, 1, applied as u8]);
+
+        let mut buffer = vec![0x84, 0, 0, 1, 8];
+        buffer.extend_from_slice(&(body.len() as i32).to_be_bytes());
+        buffer.extend_from_slice(&body);
+
+        Envelope::from_buffer(&buffer, Compression::None)
+            .unwrap()
+            .envelope
+    }
+
+    // transport returning given responses in order, counting the requests
+    fn transport(responses: Vec<Result<Envelope>>) -> (MockCdrsTransport, Arc<AtomicUsize>) {
+        let requests = Arc::new(AtomicUsize::new(0));
+        let responses = Mutex::new(VecDeque::from(responses));
+
+        let mut transport = MockCdrsTransport::new();
+        transport.expect_write_envelope().returning({
+            let requests = requests.clone();
+            move |_, _| {
+                requests.fetch_add(1, Ordering::Relaxed);
+                let response = responses
+                    .lock()
+                    .unwrap()
+                    .pop_front()
+                    .expect("Unexpected request!");
+                Box::pin(async move { response })
+            }
+        });
+
+        (transport, requests)
+    }
+
+    async fn cas(transport: &MockCdrsTransport) -> Result<LwtResult<User>> {
+        let request = Envelope::new_req_options(Version::V4);
+        execute_cas(
+            || transport.write_envelope(&request, false),
+            &mut DefaultRetrySession::default(),
+        )
+        .await
+    }
+
+    #[tokio::test]
+    async fn should_retry_failed_attempts() {
+        let (transport, requests) = transport(vec![
+            Err(Error::Io(io::ErrorKind::ConnectionReset.into())),
+            Ok(applied_envelope(true)),
+        ]);
+
+        assert_eq!(cas(&transport).await.unwrap(), LwtResult::Applied);
+        assert_eq!(requests.load(Ordering::Relaxed), 2);
+    }
+
+    #[tokio::test]
+    async fn should_return_uncertain_result_after_cas_write_timeout() {
+        let (transport, requests) =
+            transport(vec![Err(cas_write_timeout()), Ok(applied_envelope(false))]);
+
+        assert_eq!(cas(&transport).await.unwrap(), LwtResult::Uncertain(None));
+        assert_eq!(requests.load(Ordering::Relaxed), 2);
+    }
+
+    #[tokio::test]
+    async fn should_stop_after_max_attempts() {
+        let (transport, requests) = transport(
+            (0..MAX_CAS_ATTEMPTS)
+                .map(|_| Err(Error::Io(io::ErrorKind::ConnectionReset.into())))
+                .collect(),
+        );
+
+        assert!(matches!(cas(&transport).await, Err(Error::Io(_))));
+        assert_eq!(requests.load(Ordering::Relaxed), MAX_CAS_ATTEMPTS);
+    }
+
+    #[test]
+    fn should_read_applied_result() {
+        let result = LwtResult::<User>::from_rows(rows(
+            vec![col_spec("[applied]", ColType::Boolean)],
+            vec![CBytes::new(vec![1])],
+        ))
+        .unwrap();
+
+        assert!(result.is_applied());
+    }
+
+    #[test]
+    fn should_read_current_row() {
+        let result = LwtResult::<User>::from_rows(rows(
+            vec![
+                col_spec("[applied]", ColType::Boolean),
+                col_spec("id", ColType::Int),
+            ],
+            vec![
+                CBytes::new(vec![0]),
+                CBytes::new(5i32.to_be_bytes().to_vec()),
+            ],
+        ))
+        .unwrap();
+
+        assert_eq!(result, LwtResult::NotApplied(Some(User { id: 5 })));
+        assert_eq!(
+            result.into_uncertain(),
+            LwtResult::Uncertain(Some(User { id: 5 }))
+        );
+    }
+
+    #[test]
+    fn should_read_missing_current_row() {
+        let result = LwtResult::<User>::from_rows(rows(
+            vec![col_spec("[applied]", ColType::Boolean)],
+            vec![CBytes::new(vec![0])],
+        ))
+        .unwrap();
+
+        assert_eq!(result, LwtResult::NotApplied(None));
+    }
+
+    #[test]
+    fn should_detect_possibly_applied_errors() {
+        assert!(may_have_been_applied(&cas_write_timeout()));
+        assert!(!may_have_been_applied(&server_error(
+            ErrorType::Unavailable(UnavailableError {
+                cl: Consistency::Quorum,
+                required: 2,
+                alive: 1,
+            })
+        )));
+        assert!(!may_have_been_applied(&Error::RequestNotSent(
+            SocketAddr::from(([127, 0, 0, 1], 9042))
+        )));
+    }
+}
@@ -0,0 +1,84 @@
+use cassandra_protocol::error::Result;
+use cassandra_protocol::frame::message_response::ParsedResponse;
+use cassandra_protocol::frame::{Envelope, TryFromRow};
+use cassandra_protocol::query::{PreparedQuery, QueryValues};
+use std::borrow::Cow;
+
+use crate::cluster::session::Session;
+use crate::cluster::{ConnectionManager, DynSession};
+use crate::load_balancing::LoadBalancingStrategy;
+use crate::statement::StatementParamsBuilder;
+use crate::transport::CdrsTransport;
+
+impl<
+        T: CdrsTransport + 'static,
+        CM: ConnectionManager<T> + Send + Sync + 'static,
+        LB: LoadBalancingStrategy<T, CM> + Send + Sync + 'static,
+    > Session<T, CM, LB>
+{
+    /// Executes a query and converts returned rows with [`TryFromRow`]. Only the first page of
+    /// paged results is returned - all of them can be fetched with
+    /// [`SessionPager::query_as`](crate::cluster::SessionPager::query_as).
+    pub async fn query_as<'a, R: TryFromRow, Q: Into<Cow<'a, str>>>(
+        &'a self,
+        query: Q,
+    ) -> Result<Vec<R>> {
+        rows_as(
+            self.query(query)
+                .await
+                .and_then(Envelope::into_parsed_response),
+        )
+    }
+
+    /// Executes a prepared statement with given values and converts returned rows with
+    /// [`TryFromRow`]. Only the first page of paged results is returned - all of them can be
+    /// fetched with [`SessionPager::exec_as`](crate::cluster::SessionPager::exec_as).
+    pub async fn exec_as<R: TryFromRow, V: Into<QueryValues>>(
+        &self,
+        prepared: &PreparedQuery,
+        values: V,
+    ) -> Result<Vec<R>> {
+        let parameters = StatementParamsBuilder::new()
+            .with_values(values.into())
+            .build();
+        rows_as(self.exec_with_params_parsed(prepared, &parameters).await)
+    }
+}
+
+impl DynSession {
+    /// Executes a query and converts returned rows with [`TryFromRow`]. Only the first page of
+    /// paged results is returned - all of them can be fetched with
+    /// [`DynSessionPager::query_as`](crate::cluster::DynSessionPager::query_as).
+    pub async fn query_as<'a, R: TryFromRow, Q: Into<Cow<'a, str>>>(
+        &'a self,
+        query: Q,
+    ) -> Result<Vec<R>> {
+        rows_as(
+            self.query(query)
+                .await
+                .and_then(Envelope::into_parsed_response),
+        )
+    }
+
+    /// Executes a prepared statement with given values and converts returned rows with
+    /// [`TryFromRow`]. Only the first page of paged results is returned - all of them can be
+    /// fetched with [`DynSessionPager::exec_as`](crate::cluster::DynSessionPager::exec_as).
+    pub async fn exec_as<R: TryFromRow, V: Into<QueryValues>>(
+        &self,
+        prepared: &PreparedQuery,
+        values: V,
+    ) -> Result<Vec<R>> {
+        let parameters = StatementParamsBuilder::new()
+            .with_values(values.into())
+            .build();
+        rows_as(self.exec_with_params_parsed(prepared, &parameters).await)
+    }
+}
+
+fn rows_as<R: TryFromRow>(response: Result<ParsedResponse>) -> Result<Vec<R>> {
+    let rows = response?
+        .into_rows()
+        .ok_or("Query should yield a vector of rows")?;
+
+    R::try_from_rows(rows)
+}
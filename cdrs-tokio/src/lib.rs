@@ -48,8 +48,13 @@ pub mod envelope_parser;
 pub mod load_balancing;
 
 pub mod frame_encoding;
+pub mod frame_recording;
 pub mod future;
+pub mod helpers;
+#[cfg(feature = "query-builder")]
+pub mod query_builder;
 pub mod retry;
+mod runtime;
 pub mod speculative_execution;
 pub mod statement;
 pub mod transport;
@@ -67,3 +72,66 @@ pub type Result<T> = error::Result<T>;
 
 #[cfg(feature = "derive")]
 pub use cdrs_tokio_helpers_derive::{DbMirror, IntoCdrsValue, TryFromRow, TryFromUdt};
+
+/// Derives conversion of a struct into named [`QueryValues`](query::QueryValues), which can be
+/// bound to a statement, and [`RowValues`](helpers::RowValues), which allows inserting the struct
+/// as a row with [`Session::insert`](cluster::session::Session::insert). Rows are written by
+/// converting references to their fields, so fields are not cloned, except elements of
+/// collections, which need to implement `Clone`.
+///
+/// ```
+/// use cdrs_tokio::query::QueryValues;
+/// use cdrs_tokio::types::value::Value;
+/// use cdrs_tokio::IntoQueryValues;
+///
+/// #[derive(IntoQueryValues)]
+/// struct User {
+///     id: i32,
+///     #[cdrs(rename = "user_name")]
+///     name: String,
+///     #[cdrs(none = "unset")]
+///     email: Option<String>,
+///     #[cdrs(skip)]
+///     cached: bool,
+/// }
+///
+/// let values: QueryValues = User {
+///     id: 1,
+///     name: "John".into(),
+///     email: None,
+///     cached: false,
+/// }
+/// .into();
+///
+/// if let QueryValues::NamedValues(values) = values {
+///     assert_eq!(values["user_name"], Value::from("John"));
+///     assert_eq!(values["email"], Value::NotSet);
+///     assert!(!values.contains_key("cached"));
+/// }
+/// ```
+///
+/// Fields of types which cannot be bound are rejected at compile time:
+///
+/// ```compile_fail
+/// use cdrs_tokio::IntoQueryValues;
+///
+/// #[derive(IntoQueryValues)]
+/// struct Connection {
+///     id: i32,
+///     socket: std::net::TcpStream,
+/// }
+/// ```
+///
+/// So are misused attributes:
+///
+/// ```compile_fail
+/// use cdrs_tokio::IntoQueryValues;
+///
+/// #[derive(IntoQueryValues)]
+/// struct User {
+///     #[cdrs(none = "unset")]
+///     id: i32,
+/// }
+/// ```
+#[cfg(feature = "derive")]
+pub use cdrs_tokio_helpers_derive::IntoQueryValues;
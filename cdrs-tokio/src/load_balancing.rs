@@ -1,16 +1,25 @@
 mod initializing_wrapper;
+mod latency_aware;
 pub mod node_distance_evaluator;
+mod pinned;
 mod random;
 mod request;
 mod round_robin;
+mod single_node;
 mod topology_aware;
 
+use cassandra_protocol::error::Result;
+use cassandra_protocol::frame::Envelope;
 use std::sync::Arc;
+use std::time::Duration;
 
 pub(crate) use self::initializing_wrapper::InitializingWrapperLoadBalancingStrategy;
+pub use self::latency_aware::LatencyAwareLoadBalancingStrategy;
+pub use self::pinned::PinnedLoadBalancingStrategy;
 pub use self::random::RandomLoadBalancingStrategy;
 pub use self::request::Request;
 pub use self::round_robin::RoundRobinLoadBalancingStrategy;
+pub use self::single_node::SingleNodeLoadBalancingStrategy;
 pub use self::topology_aware::TopologyAwareLoadBalancingStrategy;
 use crate::cluster::topology::Node;
 use crate::cluster::{ClusterMetadata, ConnectionManager};
@@ -19,6 +28,11 @@ use crate::transport::CdrsTransport;
 pub type QueryPlan<T, CM> = Vec<Arc<Node<T, CM>>>;
 
 /// Load balancing strategy, usually used for managing target node connections.
+///
+/// Strategies don't own the set of nodes - each query plan is built from a snapshot of cluster
+/// metadata, which the session replaces atomically as nodes get discovered, added or removed.
+/// Plans built while the topology changes can therefore briefly contain a just-removed node or
+/// miss a just-added one, but never observe a partially updated node set.
 pub trait LoadBalancingStrategy<T: CdrsTransport, CM: ConnectionManager<T>> {
     /// Returns query plan for given request.  If no request is given, return a generic plan for
     /// establishing connection(s) to node(s).
@@ -27,4 +41,40 @@ pub trait LoadBalancingStrategy<T: CdrsTransport, CM: ConnectionManager<T>> {
         request: Option<Request>,
         cluster: &ClusterMetadata<T, CM>,
     ) -> QueryPlan<T, CM>;
+
+    /// Called after a request sent to given node completes, either with a response or an error.
+    /// Requests abandoned before completing, e.g. cancelled ones, are reported with
+    /// [`Error::Cancelled`](cassandra_protocol::error::Error::Cancelled). Can be used to gather
+    /// node statistics. Does nothing by default.
+    #[inline]
+    fn on_request_completed(
+        &self,
+        _node: &Node<T, CM>,
+        _latency: Duration,
+        _result: &Result<Envelope>,
+    ) {
+    }
+}
+
+impl<T: CdrsTransport, CM: ConnectionManager<T>, LB: LoadBalancingStrategy<T, CM> + ?Sized>
+    LoadBalancingStrategy<T, CM> for Box<LB>
+{
+    #[inline]
+    fn query_plan(
+        &self,
+        request: Option<Request>,
+        cluster: &ClusterMetadata<T, CM>,
+    ) -> QueryPlan<T, CM> {
+        (**self).query_plan(request, cluster)
+    }
+
+    #[inline]
+    fn on_request_completed(
+        &self,
+        node: &Node<T, CM>,
+        latency: Duration,
+        result: &Result<Envelope>,
+    ) {
+        (**self).on_request_completed(node, latency, result)
+    }
 }
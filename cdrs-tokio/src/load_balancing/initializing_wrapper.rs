@@ -1,4 +1,7 @@
+use cassandra_protocol::error::Result;
+use cassandra_protocol::frame::Envelope;
 use std::sync::Arc;
+use std::time::Duration;
 
 use crate::cluster::topology::Node;
 use crate::cluster::{ClusterMetadata, ConnectionManager};
@@ -29,6 +32,16 @@ impl<T: CdrsTransport, CM: ConnectionManager<T>, LB: LoadBalancingStrategy<T, CM
             self.contact_points_query_plan.clone()
         }
     }
+
+    #[inline]
+    fn on_request_completed(
+        &self,
+        node: &Node<T, CM>,
+        latency: Duration,
+        result: &Result<Envelope>,
+    ) {
+        self.inner.on_request_completed(node, latency, result)
+    }
 }
 
 impl<T: CdrsTransport, CM: ConnectionManager<T>, LB: LoadBalancingStrategy<T, CM>>
@@ -0,0 +1,371 @@
+use cassandra_protocol::error::{Error, Result};
+use cassandra_protocol::frame::message_error::ErrorType;
+use cassandra_protocol::frame::Envelope;
+use derivative::Derivative;
+use fxhash::FxHashMap;
+use std::net::SocketAddr;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use crate::cluster::topology::Node;
+use crate::cluster::{ClusterMetadata, ConnectionManager};
+use crate::load_balancing::{LoadBalancingStrategy, QueryPlan, Request};
+use crate::transport::CdrsTransport;
+
+const DEFAULT_EXCLUSION_THRESHOLD: f64 = 2.0;
+const DEFAULT_SCALE: Duration = Duration::from_millis(100);
+const DEFAULT_RETRY_PERIOD: Duration = Duration::from_secs(10);
+const DEFAULT_UPDATE_RATE: Duration = Duration::from_millis(100);
+const DEFAULT_MINIMUM_MEASUREMENTS: usize = 50;
+
+#[derive(Debug, Clone, Copy)]
+struct NodeLatency {
+    // exponentially decaying average, in nanoseconds
+    average: f64,
+    measurements: usize,
+    updated_at: Instant,
+}
+
+#[derive(Debug, Default)]
+struct FastestAverage {
+    average: Option<f64>,
+    computed_at: Option<Instant>,
+}
+
+/// Latency-aware load balancing strategy wrapper. Keeps an exponentially decaying average of
+/// request latencies for every node and moves nodes slower than `exclusion_threshold` times the
+/// fastest node to the end of query plans returned by the wrapped strategy, so they are only used
+/// when all faster nodes fail. Since the order of the remaining nodes is retained, this strategy
+/// composes with other ones, e.g. [`TopologyAwareLoadBalancingStrategy`](crate::load_balancing::TopologyAwareLoadBalancingStrategy).
+///
+/// A node is judged only after at least `minimum_measurements` requests, and is given another
+/// chance if it didn't get any requests for `retry_period`. Errors which are not representative
+/// of node performance (e.g. validation errors or the node being overloaded) are not measured.
+///
+/// Behavior and default configuration are based on
+/// [DataStax Java Driver](https://docs.datastax.com/en/developer/java-driver/3.11/manual/load_balancing/#latency-aware-policy).
+#[derive(Derivative)]
+#[derivative(Debug)]
+pub struct LatencyAwareLoadBalancingStrategy<LB> {
+    #[derivative(Debug = "ignore")]
+    inner: LB,
+    exclusion_threshold: f64,
+    scale: Duration,
+    retry_period: Duration,
+    update_rate: Duration,
+    minimum_measurements: usize,
+    latencies: Mutex<FxHashMap<SocketAddr, NodeLatency>>,
+    fastest_average: Mutex<FastestAverage>,
+}
+
+impl<LB> LatencyAwareLoadBalancingStrategy<LB> {
+    /// Creates a new strategy wrapping given one, with default configuration.
+    pub fn new(inner: LB) -> Self {
+        LatencyAwareLoadBalancingStrategy {
+            inner,
+            exclusion_threshold: DEFAULT_EXCLUSION_THRESHOLD,
+            scale: DEFAULT_SCALE,
+            retry_period: DEFAULT_RETRY_PERIOD,
+            update_rate: DEFAULT_UPDATE_RATE,
+            minimum_measurements: DEFAULT_MINIMUM_MEASUREMENTS,
+            latencies: Default::default(),
+            fastest_average: Default::default(),
+        }
+    }
+
+    /// Sets how many times slower than the fastest node a node needs to be, to get excluded.
+    #[must_use]
+    pub fn with_exclusion_threshold(mut self, exclusion_threshold: f64) -> Self {
+        self.exclusion_threshold = exclusion_threshold;
+        self
+    }
+
+    /// Sets how quickly older measurements lose weight in the average. A measurement taken `scale`
+    /// after the previous one carries about a third of the resulting weight.
+    #[must_use]
+    pub fn with_scale(mut self, scale: Duration) -> Self {
+        self.scale = scale;
+        self
+    }
+
+    /// Sets how long a node stays excluded without new measurements.
+    #[must_use]
+    pub fn with_retry_period(mut self, retry_period: Duration) -> Self {
+        self.retry_period = retry_period;
+        self
+    }
+
+    /// Sets how often the fastest node average is recomputed.
+    #[must_use]
+    pub fn with_update_rate(mut self, update_rate: Duration) -> Self {
+        self.update_rate = update_rate;
+        self
+    }
+
+    /// Sets how many measurements are needed, before a node can be excluded.
+    #[must_use]
+    pub fn with_minimum_measurements(mut self, minimum_measurements: usize) -> Self {
+        self.minimum_measurements = minimum_measurements;
+        self
+    }
+
+    fn record_latency(&self, addr: SocketAddr, latency: Duration, now: Instant) {
+        let latency = latency.as_nanos() as f64;
+        let mut latencies = self.latencies.lock().unwrap();
+
+        let node_latency = latencies.entry(addr).or_insert(NodeLatency {
+            average: latency,
+            measurements: 0,
+            updated_at: now,
+        });
+
+        let delay = now.saturating_duration_since(node_latency.updated_at);
+        if node_latency.measurements > 0 && !delay.is_zero() {
+            let scaled_delay = delay.as_secs_f64() / self.scale.as_secs_f64();
+            let previous_weight = (scaled_delay + 1.0).ln() / scaled_delay;
+
+            node_latency.average =
+                (1.0 - previous_weight) * latency + previous_weight * node_latency.average;
+        }
+
+        node_latency.measurements = node_latency.measurements.saturating_add(1);
+        node_latency.updated_at = now;
+    }
+
+    #[inline]
+    fn is_judgeable(&self, node_latency: &NodeLatency, now: Instant) -> bool {
+        node_latency.measurements >= self.minimum_measurements
+            && now.saturating_duration_since(node_latency.updated_at) <= self.retry_period
+    }
+
+    fn fastest_average(&self, now: Instant) -> Option<f64> {
+        let mut fastest_average = self.fastest_average.lock().unwrap();
+
+        let is_stale = fastest_average
+            .computed_at
+            .map(|computed_at| now.saturating_duration_since(computed_at) >= self.update_rate)
+            .unwrap_or(true);
+
+        if is_stale {
+            fastest_average.average = self
+                .latencies
+                .lock()
+                .unwrap()
+                .values()
+                .filter(|node_latency| self.is_judgeable(node_latency, now))
+                .map(|node_latency| node_latency.average)
+                .min_by(|a, b| a.total_cmp(b));
+            fastest_average.computed_at = Some(now);
+        }
+
+        fastest_average.average
+    }
+
+    fn sort_query_plan<T: CdrsTransport, CM: ConnectionManager<T>>(
+        &self,
+        query_plan: QueryPlan<T, CM>,
+        now: Instant,
+    ) -> QueryPlan<T, CM> {
+        let fastest_average = match self.fastest_average(now) {
+            Some(fastest_average) => fastest_average,
+            None => return query_plan,
+        };
+
+        let latencies = self.latencies.lock().unwrap();
+        let (mut fast, slow): (Vec<_>, Vec<_>) = query_plan.into_iter().partition(|node| {
+            latencies
+                .get(&node.broadcast_rpc_address())
+                .map(|node_latency| {
+                    !self.is_judgeable(node_latency, now)
+                        || node_latency.average <= self.exclusion_threshold * fastest_average
+                })
+                .unwrap_or(true)
+        });
+
+        fast.extend(slow);
+        fast
+    }
+}
+
+fn should_measure(result: &Result<Envelope>) -> bool {
+    match result {
+        Ok(_) => true,
+        // fast failures, which don't reflect how quickly a node handles requests
+        Err(Error::Server { body, .. }) => !matches!(
+            body.ty,
+            ErrorType::Unavailable(_)
+                | ErrorType::Overloaded
+                | ErrorType::IsBootstrapping
+                | ErrorType::Unprepared(_)
+                | ErrorType::Syntax
+                | ErrorType::Unauthorized
+                | ErrorType::Invalid
+                | ErrorType::Config
+                | ErrorType::AlreadyExists(_)
+        ),
+        Err(Error::RequestNotSent(_)) => false,
+        // abandoned requests only give a lower bound of latency
+        Err(Error::Cancelled) => false,
+        Err(_) => true,
+    }
+}
+
+impl<T: CdrsTransport, CM: ConnectionManager<T>, LB: LoadBalancingStrategy<T, CM>>
+    LoadBalancingStrategy<T, CM> for LatencyAwareLoadBalancingStrategy<LB>
+{
+    fn query_plan(
+        &self,
+        request: Option<Request>,
+        cluster: &ClusterMetadata<T, CM>,
+    ) -> QueryPlan<T, CM> {
+        self.sort_query_plan(self.inner.query_plan(request, cluster), Instant::now())
+    }
+
+    fn on_request_completed(
+        &self,
+        node: &Node<T, CM>,
+        latency: Duration,
+        result: &Result<Envelope>,
+    ) {
+        if should_measure(result) {
+            self.record_latency(node.broadcast_rpc_address(), latency, Instant::now());
+        }
+
+        self.inner.on_request_completed(node, latency, result);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::SocketAddr;
+    use std::time::{Duration, Instant};
+
+    use crate::cluster::connection_manager::MockConnectionManager;
+    use crate::cluster::test_nodes::{addr, local_nodes};
+    use crate::load_balancing::{
+        LatencyAwareLoadBalancingStrategy, QueryPlan, RoundRobinLoadBalancingStrategy,
+    };
+    use crate::transport::MockCdrsTransport;
+
+    type TestQueryPlan = QueryPlan<MockCdrsTransport, MockConnectionManager<MockCdrsTransport>>;
+    type TestStrategy = LatencyAwareLoadBalancingStrategy<
+        RoundRobinLoadBalancingStrategy<
+            MockCdrsTransport,
+            MockConnectionManager<MockCdrsTransport>,
+        >,
+    >;
+
+    fn create_query_plan() -> TestQueryPlan {
+        local_nodes(MockConnectionManager::new(), &[addr(1), addr(2), addr(3)])
+    }
+
+    fn plan_addresses(query_plan: TestQueryPlan) -> Vec<SocketAddr> {
+        query_plan
+            .iter()
+            .map(|node| node.broadcast_rpc_address())
+            .collect()
+    }
+
+    // records given number of measurements for each node, 1ms apart
+    fn record_latencies(
+        strategy: &TestStrategy,
+        latencies: &[(SocketAddr, Duration)],
+        count: usize,
+        start: Instant,
+    ) -> Instant {
+        let mut now = start;
+        for _ in 0..count {
+            now += Duration::from_millis(1);
+            for (addr, latency) in latencies {
+                strategy.record_latency(*addr, *latency, now);
+            }
+        }
+
+        now
+    }
+
+    #[test]
+    fn should_move_slow_nodes_to_the_end() {
+        let strategy =
+            TestStrategy::new(RoundRobinLoadBalancingStrategy::new()).with_minimum_measurements(5);
+
+        let now = record_latencies(
+            &strategy,
+            &[
+                (addr(1), Duration::from_millis(50)),
+                (addr(2), Duration::from_millis(5)),
+                (addr(3), Duration::from_millis(6)),
+            ],
+            5,
+            Instant::now(),
+        );
+
+        let plan = plan_addresses(strategy.sort_query_plan(create_query_plan(), now));
+        assert_eq!(plan, vec![addr(2), addr(3), addr(1)]);
+    }
+
+    #[test]
+    fn should_not_judge_with_too_few_measurements() {
+        let strategy =
+            TestStrategy::new(RoundRobinLoadBalancingStrategy::new()).with_minimum_measurements(5);
+
+        let now = record_latencies(
+            &strategy,
+            &[
+                (addr(1), Duration::from_millis(50)),
+                (addr(2), Duration::from_millis(5)),
+            ],
+            4,
+            Instant::now(),
+        );
+
+        let plan = plan_addresses(strategy.sort_query_plan(create_query_plan(), now));
+        assert_eq!(plan, vec![addr(1), addr(2), addr(3)]);
+    }
+
+    #[test]
+    fn should_retry_excluded_nodes_after_retry_period() {
+        let strategy = TestStrategy::new(RoundRobinLoadBalancingStrategy::new())
+            .with_minimum_measurements(5)
+            .with_retry_period(Duration::from_secs(1));
+
+        let start = Instant::now();
+        let now = record_latencies(
+            &strategy,
+            &[
+                (addr(1), Duration::from_millis(50)),
+                (addr(2), Duration::from_millis(5)),
+            ],
+            5,
+            start,
+        );
+
+        // only the fast node keeps getting requests
+        let now = record_latencies(
+            &strategy,
+            &[(addr(2), Duration::from_millis(5))],
+            1,
+            now + Duration::from_secs(2),
+        );
+
+        let plan = plan_addresses(strategy.sort_query_plan(create_query_plan(), now));
+        assert_eq!(plan, vec![addr(1), addr(2), addr(3)]);
+    }
+
+    #[test]
+    fn should_decay_old_measurements() {
+        let strategy =
+            TestStrategy::new(RoundRobinLoadBalancingStrategy::new()).with_minimum_measurements(1);
+
+        let start = Instant::now();
+        strategy.record_latency(addr(1), Duration::from_millis(100), start);
+        strategy.record_latency(
+            addr(1),
+            Duration::from_millis(10),
+            start + Duration::from_secs(10),
+        );
+
+        let average = strategy.latencies.lock().unwrap()[&addr(1)].average;
+        assert!(average < Duration::from_millis(20).as_nanos() as f64);
+    }
+}
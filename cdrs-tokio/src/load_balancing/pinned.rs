@@ -0,0 +1,81 @@
+use derivative::Derivative;
+use std::marker::PhantomData;
+use std::net::SocketAddr;
+
+use crate::cluster::{ClusterMetadata, ConnectionManager};
+use crate::load_balancing::{LoadBalancingStrategy, QueryPlan, Request};
+use crate::transport::CdrsTransport;
+
+/// Load balancing sending all requests to the node with given broadcast RPC address. When the
+/// node is not part of the cluster, e.g. after being removed, query plans are empty and requests
+/// fail instead of going to other nodes. Useful in tests simulating a node disappearing.
+#[derive(Derivative)]
+#[derivative(Debug)]
+pub struct PinnedLoadBalancingStrategy<T: CdrsTransport, CM: ConnectionManager<T>> {
+    broadcast_rpc_address: SocketAddr,
+    #[derivative(Debug = "ignore")]
+    _transport: PhantomData<T>,
+    #[derivative(Debug = "ignore")]
+    _connection_manager: PhantomData<CM>,
+}
+
+impl<T: CdrsTransport, CM: ConnectionManager<T>> PinnedLoadBalancingStrategy<T, CM> {
+    pub fn new(broadcast_rpc_address: SocketAddr) -> Self {
+        PinnedLoadBalancingStrategy {
+            broadcast_rpc_address,
+            _transport: Default::default(),
+            _connection_manager: Default::default(),
+        }
+    }
+
+    /// Returns the address of the node receiving all requests.
+    #[inline]
+    pub fn broadcast_rpc_address(&self) -> SocketAddr {
+        self.broadcast_rpc_address
+    }
+}
+
+impl<T: CdrsTransport, CM: ConnectionManager<T>> LoadBalancingStrategy<T, CM>
+    for PinnedLoadBalancingStrategy<T, CM>
+{
+    fn query_plan(
+        &self,
+        _request: Option<Request>,
+        cluster: &ClusterMetadata<T, CM>,
+    ) -> QueryPlan<T, CM> {
+        cluster
+            .find_node_by_rpc_address(self.broadcast_rpc_address)
+            .into_iter()
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use fxhash::FxHashMap;
+    use uuid::Uuid;
+
+    use crate::cluster::connection_manager::MockConnectionManager;
+    use crate::cluster::test_nodes::{addr, local_nodes};
+    use crate::cluster::ClusterMetadata;
+    use crate::load_balancing::{LoadBalancingStrategy, PinnedLoadBalancingStrategy};
+
+    #[test]
+    fn should_select_only_pinned_node() {
+        let nodes: FxHashMap<_, _> = local_nodes(MockConnectionManager::new(), &[addr(1), addr(2)])
+            .into_iter()
+            .map(|node| (Uuid::new_v4(), node))
+            .collect();
+
+        let cluster = ClusterMetadata::new(nodes, Default::default());
+        let strategy = PinnedLoadBalancingStrategy::new(addr(2));
+
+        let query_plan = strategy.query_plan(None, &cluster);
+        assert_eq!(query_plan.len(), 1);
+        assert_eq!(query_plan[0].broadcast_rpc_address(), addr(2));
+
+        // the pinned node is gone
+        let cluster = cluster.clone_without_node(addr(2));
+        assert!(strategy.query_plan(None, &cluster).is_empty());
+    }
+}
@@ -6,6 +6,9 @@ use crate::cluster::Murmur3Token;
 /// A request executed by a `Session`.
 #[derive(Constructor, Clone, Debug)]
 pub struct Request<'a> {
+    /// Keyspace of the statement, if given in its parameters, or the current keyspace of the
+    /// session otherwise, e.g. one set by a `USE` statement. See
+    /// [`Session::keyspace_changes`](crate::cluster::session::Session::keyspace_changes).
     pub keyspace: Option<&'a str>,
     pub token: Option<Murmur3Token>,
     pub routing_key: Option<&'a [u8]>,
@@ -0,0 +1,77 @@
+use derivative::Derivative;
+use std::marker::PhantomData;
+
+use crate::cluster::{ClusterMetadata, ConnectionManager};
+use crate::load_balancing::{LoadBalancingStrategy, QueryPlan, Request};
+use crate::transport::CdrsTransport;
+
+/// Load balancing sending all requests to a single node - the one with the lowest address among
+/// nodes not ignored for load balancing. Useful in tests, which need requests to go through a
+/// known node. If that node gets removed, the next one takes over.
+#[derive(Default, Derivative)]
+#[derivative(Debug)]
+pub struct SingleNodeLoadBalancingStrategy<T: CdrsTransport, CM: ConnectionManager<T>> {
+    #[derivative(Debug = "ignore")]
+    _transport: PhantomData<T>,
+    #[derivative(Debug = "ignore")]
+    _connection_manager: PhantomData<CM>,
+}
+
+impl<T: CdrsTransport, CM: ConnectionManager<T>> SingleNodeLoadBalancingStrategy<T, CM> {
+    pub fn new() -> Self {
+        SingleNodeLoadBalancingStrategy {
+            _transport: Default::default(),
+            _connection_manager: Default::default(),
+        }
+    }
+}
+
+impl<T: CdrsTransport, CM: ConnectionManager<T>> LoadBalancingStrategy<T, CM>
+    for SingleNodeLoadBalancingStrategy<T, CM>
+{
+    fn query_plan(
+        &self,
+        _request: Option<Request>,
+        cluster: &ClusterMetadata<T, CM>,
+    ) -> QueryPlan<T, CM> {
+        // node map order is arbitrary, so the address decides which node is first
+        cluster
+            .unignored_nodes()
+            .into_iter()
+            .min_by_key(|node| node.broadcast_rpc_address())
+            .into_iter()
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use fxhash::FxHashMap;
+    use uuid::Uuid;
+
+    use crate::cluster::connection_manager::MockConnectionManager;
+    use crate::cluster::test_nodes::{addr, local_nodes};
+    use crate::cluster::ClusterMetadata;
+    use crate::load_balancing::{LoadBalancingStrategy, SingleNodeLoadBalancingStrategy};
+
+    #[test]
+    fn should_always_select_node_with_lowest_address() {
+        let nodes: FxHashMap<_, _> =
+            local_nodes(MockConnectionManager::new(), &[addr(3), addr(1), addr(2)])
+                .into_iter()
+                .map(|node| (Uuid::new_v4(), node))
+                .collect();
+
+        let cluster = ClusterMetadata::new(nodes, Default::default());
+        let strategy = SingleNodeLoadBalancingStrategy::new();
+
+        for _ in 0..3 {
+            let query_plan = strategy.query_plan(None, &cluster);
+            assert_eq!(query_plan.len(), 1);
+            assert_eq!(query_plan[0].broadcast_rpc_address(), addr(1));
+        }
+
+        let empty_cluster = ClusterMetadata::default();
+        assert!(strategy.query_plan(None, &empty_cluster).is_empty());
+    }
+}
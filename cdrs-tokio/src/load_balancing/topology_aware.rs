@@ -5,7 +5,6 @@ use crate::load_balancing::{LoadBalancingStrategy, QueryPlan, Request};
 use crate::transport::CdrsTransport;
 use cassandra_protocol::consistency::Consistency;
 use derivative::Derivative;
-use fxhash::{FxHashMap, FxHashSet};
 use itertools::Itertools;
 use rand::prelude::*;
 use rand::rng;
@@ -109,26 +108,26 @@ impl<T: CdrsTransport, CM: ConnectionManager<T>> TopologyAwareLoadBalancingStrat
         consistency: Option<Consistency>,
         cluster: &ClusterMetadata<T, CM>,
     ) -> QueryPlan<T, CM> {
+        let replicas = cluster.token_map().replicas(
+            token,
+            &keyspace.replication_strategy,
+            cluster.datacenters(),
+        );
+
         match &keyspace.replication_strategy {
-            ReplicationStrategy::SimpleStrategy { replication_factor } => {
-                self.simple_strategy_replicas(token, *replication_factor, cluster)
+            ReplicationStrategy::SimpleStrategy { .. } | ReplicationStrategy::Other { .. } => {
+                self.simple_strategy_replicas(replicas, cluster)
             }
-            ReplicationStrategy::NetworkTopologyStrategy {
-                datacenter_replication_factor,
-            } => self.network_topology_strategy_replicas(
-                token,
-                datacenter_replication_factor.clone(),
-                consistency,
-                cluster,
-            ),
-            ReplicationStrategy::Other => self.simple_strategy_replicas(token, 1, cluster),
+            ReplicationStrategy::NetworkTopologyStrategy { .. } => {
+                self.network_topology_strategy_replicas(replicas, consistency, cluster)
+            }
+            ReplicationStrategy::LocalStrategy => self.round_robin_unignored_local_nodes(cluster),
         }
     }
 
     fn network_topology_strategy_replicas(
         &self,
-        token: Murmur3Token,
-        mut datacenter_replication_factor: FxHashMap<String, usize>,
+        mut result: Vec<Arc<Node<T, CM>>>,
         consistency: Option<Consistency>,
         cluster: &ClusterMetadata<T, CM>,
     ) -> QueryPlan<T, CM> {
@@ -139,55 +138,6 @@ impl<T: CdrsTransport, CM: ConnectionManager<T>> TopologyAwareLoadBalancingStrat
         // 4. append round-robin unignored local non-replicas
         // 5. optionally, add shuffled remote unignored non-replicas
 
-        let replicas = cluster.token_map().nodes_for_token(token).collect_vec();
-
-        let desired_replica_count = datacenter_replication_factor.values().sum();
-        let mut same_rack_replicas: FxHashMap<String, usize> = datacenter_replication_factor
-            .iter()
-            .map(|(dc, replication_factor)| {
-                let rack_count = cluster.datacenter(dc).map(|dc| dc.rack_count).unwrap_or(0);
-                (dc.into(), replication_factor.saturating_sub(rack_count))
-            })
-            .collect();
-
-        let mut result = Vec::with_capacity(desired_replica_count);
-        let mut used_dc_racks: FxHashSet<(&str, &str)> = Default::default();
-
-        for replica in &replicas {
-            if let Some(datacenter_replication_factor) =
-                datacenter_replication_factor.get_mut(replica.datacenter())
-            {
-                if *datacenter_replication_factor == 0 {
-                    // found enough nodes in this datacenter
-                    continue;
-                }
-
-                let current_node_dc = replica.datacenter();
-                let current_node_rack = replica.rack();
-
-                if used_dc_racks.contains(&(current_node_dc, current_node_rack)) {
-                    // check if we need to put nodes from the same rack multiple times to meet
-                    // the replication factor
-                    if let Some(same_rack_replicas) = same_rack_replicas.get_mut(current_node_dc) {
-                        if *same_rack_replicas > 0 {
-                            *same_rack_replicas -= 1;
-                            *datacenter_replication_factor -= 1;
-                            result.push(replica.clone());
-                        }
-                    }
-                } else {
-                    *datacenter_replication_factor -= 1;
-
-                    used_dc_racks.insert((current_node_dc, current_node_rack));
-                    result.push(replica.clone());
-                }
-
-                if result.len() == desired_replica_count {
-                    break;
-                }
-            }
-        }
-
         // result now contains mixed local/remote and ignored/unignored nodes - put local in front
         result.sort_unstable_by(|a, b| {
             let a_distance = a.distance();
@@ -246,16 +196,10 @@ impl<T: CdrsTransport, CM: ConnectionManager<T>> TopologyAwareLoadBalancingStrat
 
     fn simple_strategy_replicas(
         &self,
-        token: Murmur3Token,
-        replica_count: usize,
+        mut replicas: Vec<Arc<Node<T, CM>>>,
         cluster: &ClusterMetadata<T, CM>,
     ) -> QueryPlan<T, CM> {
-        let mut replicas = cluster
-            .token_map()
-            .nodes_for_token_capped(token, replica_count)
-            .filter(|node| !node.is_ignored())
-            .collect_vec();
-
+        replicas.retain(|node| !node.is_ignored());
         replicas.shuffle(&mut rng());
 
         let unignored_nodes = self.round_robin_unignored_nodes(cluster);
@@ -462,7 +406,10 @@ mod tests {
         );
         keyspaces.insert(
             "k3".into(),
-            KeyspaceMetadata::new(ReplicationStrategy::Other),
+            KeyspaceMetadata::new(ReplicationStrategy::Other {
+                class: "org.apache.cassandra.locator.EverywhereStrategy".into(),
+                options: Default::default(),
+            }),
         );
         keyspaces.insert(
             "k4".into(),
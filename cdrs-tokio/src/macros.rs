@@ -1,19 +1,18 @@
 #[macro_export]
 
-/// Transforms arguments to values consumed by queries.
+/// Transforms arguments to values consumed by queries. Accepts anything convertible into
+/// [`Value`](crate::types::value::Value), including borrowed strings, byte slices (as blobs),
+/// references to other values and optional values, with `None` sent as `NULL`.
 macro_rules! query_values {
-    ($($value:expr),*) => {
+    ($($value:expr),* $(,)?) => {
         {
             use cdrs_tokio::types::value::Value;
             use cdrs_tokio::query::QueryValues;
-            let mut values: Vec<Value> = Vec::new();
-            $(
-                values.push($value.into());
-            )*
+            let values: Vec<Value> = vec![$($value.into()),*];
             QueryValues::SimpleValues(values)
         }
     };
-    ($($name:expr => $value:expr),*) => {
+    ($($name:expr => $value:expr),* $(,)?) => {
         {
             use cdrs_tokio::types::value::Value;
             use cdrs_tokio::query::QueryValues;
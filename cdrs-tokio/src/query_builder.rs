@@ -0,0 +1,273 @@
+//! Typed builders for common CQL statements. Builders never interpolate values into the resulting
+//! query - every value is sent as a bound value, and all identifiers are validated and quoted when
+//! needed. The result of building is a `(String, QueryValues)` pair, which can be used directly
+//! with [`Session::query_with_values`](crate::cluster::session::Session::query_with_values), or the
+//! query string can be prepared.
+//!
+//! Identifiers are treated as case-sensitive, i.e. they are used exactly as given. Lowercase names
+//! which are not reserved words are left unquoted, while other ones get quoted.
+//!
+//! ```
+//! use cdrs_tokio::query_builder::{Operator, SelectBuilder};
+//!
+//! let (query, values) = SelectBuilder::new("users")
+//!     .with_keyspace("app")
+//!     .add_column("name")
+//!     .add_where("id", Operator::Eq, 10)
+//!     .with_limit(1)
+//!     .build()
+//!     .unwrap();
+//!
+//! assert_eq!(query, "SELECT name FROM app.users WHERE id = ? LIMIT ?");
+//! assert_eq!(values.len(), 2);
+//! ```
+//!
+//! Statements which should not depend on the current keyspace (and therefore on `USE` statements)
+//! can be created with `qualified` constructors, e.g. [`SelectBuilder::qualified`]. Hand-written
+//! queries can use [`QualifiedTable`] to render validated table names.
+
+mod delete_builder;
+mod insert_builder;
+mod select_builder;
+mod update_builder;
+
+pub use self::delete_builder::DeleteBuilder;
+pub use self::insert_builder::InsertBuilder;
+pub use self::select_builder::SelectBuilder;
+pub use self::update_builder::UpdateBuilder;
+
+use cassandra_protocol::error::{Error, Result};
+use cassandra_protocol::query::utils::quote_identifier;
+use cassandra_protocol::types::value::Value;
+use derive_more::Display;
+use itertools::Itertools;
+use std::fmt::{Display, Formatter};
+
+/// Relation operator used in `WHERE` clauses.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Display)]
+pub enum Operator {
+    #[display("=")]
+    Eq,
+    #[display("<")]
+    Lt,
+    #[display("<=")]
+    Le,
+    #[display(">")]
+    Gt,
+    #[display(">=")]
+    Ge,
+    /// Expects the value to be a list of values.
+    #[display("IN")]
+    In,
+    #[display("CONTAINS")]
+    Contains,
+    #[display("CONTAINS KEY")]
+    ContainsKey,
+    #[display("LIKE")]
+    Like,
+}
+
+/// Clustering order used in `ORDER BY` clauses.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Display)]
+pub enum Order {
+    #[display("ASC")]
+    Asc,
+    #[display("DESC")]
+    Desc,
+}
+
+/// Validates given identifier and returns its representation suitable for concatenation in a CQL
+/// query. The identifier is treated as case-sensitive.
+pub fn identifier(name: &str) -> Result<String> {
+    if name.is_empty() {
+        return Err(Error::General("Identifiers cannot be empty!".into()));
+    }
+
+    if name.contains('\0') {
+        return Err(Error::General(format!(
+            "Identifier contains a NUL character: {name:?}"
+        )));
+    }
+
+    Ok(quote_identifier(name))
+}
+
+/// Table name qualified with a keyspace. Both identifiers are validated on creation and the table
+/// is displayed as `keyspace.table`, with identifiers quoted when needed.
+///
+/// ```
+/// use cdrs_tokio::query_builder::QualifiedTable;
+///
+/// let table = QualifiedTable::new("app", "Users").unwrap();
+/// assert_eq!(format!("SELECT * FROM {table}"), "SELECT * FROM app.\"Users\"");
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct QualifiedTable {
+    keyspace: String,
+    table: String,
+    name: String,
+}
+
+impl QualifiedTable {
+    pub fn new<K: Into<String>, T: Into<String>>(keyspace: K, table: T) -> Result<Self> {
+        let keyspace = keyspace.into();
+        let table = table.into();
+        let name = qualified_table_name(Some(&keyspace), &table)?;
+
+        Ok(QualifiedTable {
+            keyspace,
+            table,
+            name,
+        })
+    }
+
+    /// Returns the keyspace, as given.
+    #[inline]
+    pub fn keyspace(&self) -> &str {
+        &self.keyspace
+    }
+
+    /// Returns the table, as given.
+    #[inline]
+    pub fn table(&self) -> &str {
+        &self.table
+    }
+}
+
+impl Display for QualifiedTable {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.name)
+    }
+}
+
+fn qualified_table_name(keyspace: Option<&str>, table: &str) -> Result<String> {
+    let table = identifier(table)?;
+    match keyspace {
+        Some(keyspace) => Ok(format!("{}.{}", identifier(keyspace)?, table)),
+        None => Ok(table),
+    }
+}
+
+fn column_list(columns: &[String]) -> Result<String> {
+    columns
+        .iter()
+        .map(|column| identifier(column))
+        .collect::<Result<Vec<_>>>()
+        .map(|columns| columns.join(", "))
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Relation {
+    column: String,
+    operator: Operator,
+    value: Value,
+}
+
+/// `WHERE` clause shared by statements which support one.
+#[derive(Debug, Clone, Default, PartialEq)]
+struct WhereClause {
+    relations: Vec<Relation>,
+}
+
+impl WhereClause {
+    fn add(&mut self, column: String, operator: Operator, value: Value) {
+        self.relations.push(Relation {
+            column,
+            operator,
+            value,
+        });
+    }
+
+    fn write(self, query: &mut String, values: &mut Vec<Value>) -> Result<()> {
+        if self.relations.is_empty() {
+            return Ok(());
+        }
+
+        let relations = self
+            .relations
+            .into_iter()
+            .map(|relation| {
+                let column = identifier(&relation.column)?;
+                values.push(relation.value);
+                Ok(format!("{} {} ?", column, relation.operator))
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        query.push_str(" WHERE ");
+        query.push_str(&relations.iter().join(" AND "));
+
+        Ok(())
+    }
+}
+
+/// `USING` clause for statements which support TTL and/or timestamp.
+#[derive(Debug, Clone, Default, PartialEq)]
+struct UsingClause {
+    ttl: Option<Value>,
+    timestamp: Option<Value>,
+}
+
+impl UsingClause {
+    fn write(self, query: &mut String, values: &mut Vec<Value>) {
+        let mut parts = Vec::with_capacity(2);
+
+        if let Some(ttl) = self.ttl {
+            parts.push("TTL ?");
+            values.push(ttl);
+        }
+
+        if let Some(timestamp) = self.timestamp {
+            parts.push("TIMESTAMP ?");
+            values.push(timestamp);
+        }
+
+        if !parts.is_empty() {
+            query.push_str(" USING ");
+            query.push_str(&parts.join(" AND "));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{identifier, QualifiedTable, SelectBuilder};
+
+    #[test]
+    fn should_not_quote_plain_identifiers() {
+        assert_eq!(identifier("user_id").unwrap(), "user_id");
+        assert_eq!(identifier("col1").unwrap(), "col1");
+    }
+
+    #[test]
+    fn should_quote_case_sensitive_identifiers() {
+        assert_eq!(identifier("userId").unwrap(), "\"userId\"");
+        assert_eq!(identifier("1col").unwrap(), "\"1col\"");
+        assert_eq!(identifier("with space").unwrap(), "\"with space\"");
+        assert_eq!(identifier("a\"b").unwrap(), "\"a\"\"b\"");
+    }
+
+    #[test]
+    fn should_quote_reserved_keywords() {
+        assert_eq!(identifier("select").unwrap(), "\"select\"");
+        assert_eq!(identifier("token").unwrap(), "\"token\"");
+    }
+
+    #[test]
+    fn should_reject_invalid_identifiers() {
+        assert!(identifier("").is_err());
+        assert!(identifier("a\0b").is_err());
+    }
+
+    #[test]
+    fn should_qualify_tables() {
+        let table = QualifiedTable::new("Ks", "users").unwrap();
+        assert_eq!(table.to_string(), "\"Ks\".users");
+        assert_eq!(table.keyspace(), "Ks");
+        assert_eq!(table.table(), "users");
+
+        assert!(QualifiedTable::new("", "users").is_err());
+
+        let (query, _) = SelectBuilder::qualified("ks", "users").build().unwrap();
+        assert_eq!(query, "SELECT * FROM ks.users");
+    }
+}
@@ -0,0 +1,141 @@
+use cassandra_protocol::error::Result;
+use cassandra_protocol::query::QueryValues;
+use cassandra_protocol::types::value::Value;
+use cassandra_protocol::types::CLong;
+
+use crate::query_builder::{column_list, qualified_table_name, Operator, UsingClause, WhereClause};
+
+/// Builder for `DELETE` statements. If no columns are added, whole rows are deleted.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DeleteBuilder {
+    keyspace: Option<String>,
+    table: String,
+    columns: Vec<String>,
+    where_clause: WhereClause,
+    if_exists: bool,
+    using: UsingClause,
+}
+
+impl DeleteBuilder {
+    pub fn new<T: Into<String>>(table: T) -> Self {
+        DeleteBuilder {
+            keyspace: None,
+            table: table.into(),
+            columns: vec![],
+            where_clause: Default::default(),
+            if_exists: false,
+            using: Default::default(),
+        }
+    }
+
+    /// Creates a builder for a table qualified with a keyspace, so the statement doesn't depend
+    /// on the current keyspace.
+    pub fn qualified<K: Into<String>, T: Into<String>>(keyspace: K, table: T) -> Self {
+        Self::new(table).with_keyspace(keyspace)
+    }
+
+    /// Sets the keyspace of the table.
+    #[must_use]
+    pub fn with_keyspace<T: Into<String>>(mut self, keyspace: T) -> Self {
+        self.keyspace = Some(keyspace.into());
+        self
+    }
+
+    /// Adds a column to delete.
+    #[must_use]
+    pub fn add_column<T: Into<String>>(mut self, column: T) -> Self {
+        self.columns.push(column.into());
+        self
+    }
+
+    /// Adds a relation to the `WHERE` clause. The value is always bound.
+    #[must_use]
+    pub fn add_where<T: Into<String>, V: Into<Value>>(
+        mut self,
+        column: T,
+        operator: Operator,
+        value: V,
+    ) -> Self {
+        self.where_clause.add(column.into(), operator, value.into());
+        self
+    }
+
+    /// Sets the `IF EXISTS` flag, turning the delete into a lightweight transaction.
+    #[must_use]
+    pub fn with_if_exists(mut self, if_exists: bool) -> Self {
+        self.if_exists = if_exists;
+        self
+    }
+
+    /// Sets the write timestamp, in microseconds.
+    #[must_use]
+    pub fn with_timestamp(mut self, timestamp: CLong) -> Self {
+        self.using.timestamp = Some(timestamp.into());
+        self
+    }
+
+    /// Builds the query along with values to bind.
+    pub fn build(self) -> Result<(String, QueryValues)> {
+        let mut values = vec![];
+        let mut query = "DELETE".to_string();
+
+        if !self.columns.is_empty() {
+            query.push(' ');
+            query.push_str(&column_list(&self.columns)?);
+        }
+
+        query.push_str(" FROM ");
+        query.push_str(&qualified_table_name(
+            self.keyspace.as_deref(),
+            &self.table,
+        )?);
+
+        self.using.write(&mut query, &mut values);
+        self.where_clause.write(&mut query, &mut values)?;
+
+        if self.if_exists {
+            query.push_str(" IF EXISTS");
+        }
+
+        Ok((query, QueryValues::SimpleValues(values)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use cassandra_protocol::query::QueryValues;
+    use cassandra_protocol::types::value::Value;
+
+    use crate::query_builder::{DeleteBuilder, Operator};
+
+    #[test]
+    fn should_build_row_delete() {
+        let (query, values) = DeleteBuilder::new("users")
+            .add_where("id", Operator::In, vec![1, 2])
+            .build()
+            .unwrap();
+
+        assert_eq!(query, "DELETE FROM users WHERE id IN ?");
+        assert_eq!(values.len(), 1);
+    }
+
+    #[test]
+    fn should_build_column_delete() {
+        let (query, values) = DeleteBuilder::new("users")
+            .add_column("name")
+            .add_where("id", Operator::Eq, 1)
+            .with_timestamp(1000)
+            .with_if_exists(true)
+            .build()
+            .unwrap();
+
+        assert_eq!(
+            query,
+            "DELETE name FROM users USING TIMESTAMP ? WHERE id = ? IF EXISTS"
+        );
+        assert_eq!(
+            values,
+            QueryValues::SimpleValues(vec![Value::from(1000i64), Value::from(1)])
+        );
+    }
+}
@@ -0,0 +1,148 @@
+use cassandra_protocol::error::{Error, Result};
+use cassandra_protocol::query::QueryValues;
+use cassandra_protocol::types::value::Value;
+use cassandra_protocol::types::{CInt, CLong};
+use itertools::Itertools;
+
+use crate::query_builder::{column_list, qualified_table_name, UsingClause};
+
+/// Builder for `INSERT` statements.
+#[derive(Debug, Clone, PartialEq)]
+pub struct InsertBuilder {
+    keyspace: Option<String>,
+    table: String,
+    columns: Vec<String>,
+    values: Vec<Value>,
+    if_not_exists: bool,
+    using: UsingClause,
+}
+
+impl InsertBuilder {
+    pub fn new<T: Into<String>>(table: T) -> Self {
+        InsertBuilder {
+            keyspace: None,
+            table: table.into(),
+            columns: vec![],
+            values: vec![],
+            if_not_exists: false,
+            using: Default::default(),
+        }
+    }
+
+    /// Creates a builder for a table qualified with a keyspace, so the statement doesn't depend
+    /// on the current keyspace.
+    pub fn qualified<K: Into<String>, T: Into<String>>(keyspace: K, table: T) -> Self {
+        Self::new(table).with_keyspace(keyspace)
+    }
+
+    /// Sets the keyspace of the table.
+    #[must_use]
+    pub fn with_keyspace<T: Into<String>>(mut self, keyspace: T) -> Self {
+        self.keyspace = Some(keyspace.into());
+        self
+    }
+
+    /// Adds a column with a value to insert. The value is always bound.
+    #[must_use]
+    pub fn add_value<T: Into<String>, V: Into<Value>>(mut self, column: T, value: V) -> Self {
+        self.columns.push(column.into());
+        self.values.push(value.into());
+        self
+    }
+
+    /// Sets the `IF NOT EXISTS` flag, turning the insert into a lightweight transaction.
+    #[must_use]
+    pub fn with_if_not_exists(mut self, if_not_exists: bool) -> Self {
+        self.if_not_exists = if_not_exists;
+        self
+    }
+
+    /// Sets the time to live of inserted values, in seconds.
+    #[must_use]
+    pub fn with_ttl(mut self, ttl: CInt) -> Self {
+        self.using.ttl = Some(ttl.into());
+        self
+    }
+
+    /// Sets the write timestamp, in microseconds.
+    #[must_use]
+    pub fn with_timestamp(mut self, timestamp: CLong) -> Self {
+        self.using.timestamp = Some(timestamp.into());
+        self
+    }
+
+    /// Builds the query along with values to bind.
+    pub fn build(self) -> Result<(String, QueryValues)> {
+        if self.columns.is_empty() {
+            return Err(Error::General(
+                "Insert statements need at least one value!".into(),
+            ));
+        }
+
+        let mut query = format!(
+            "INSERT INTO {} ({}) VALUES ({})",
+            qualified_table_name(self.keyspace.as_deref(), &self.table)?,
+            column_list(&self.columns)?,
+            self.columns.iter().map(|_| "?").join(", ")
+        );
+
+        let mut values = self.values;
+
+        if self.if_not_exists {
+            query.push_str(" IF NOT EXISTS");
+        }
+
+        self.using.write(&mut query, &mut values);
+
+        Ok((query, QueryValues::SimpleValues(values)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use cassandra_protocol::query::QueryValues;
+    use cassandra_protocol::types::value::Value;
+
+    use crate::query_builder::InsertBuilder;
+
+    #[test]
+    fn should_build_insert() {
+        let (query, values) = InsertBuilder::new("users")
+            .add_value("id", 1)
+            .add_value("Name", "name")
+            .build()
+            .unwrap();
+
+        assert_eq!(query, "INSERT INTO users (id, \"Name\") VALUES (?, ?)");
+        assert_eq!(
+            values,
+            QueryValues::SimpleValues(vec![Value::from(1), Value::from("name")])
+        );
+    }
+
+    #[test]
+    fn should_build_insert_with_options() {
+        let (query, values) = InsertBuilder::new("users")
+            .with_keyspace("ks")
+            .add_value("id", 1)
+            .with_if_not_exists(true)
+            .with_ttl(60)
+            .with_timestamp(1000)
+            .build()
+            .unwrap();
+
+        assert_eq!(
+            query,
+            "INSERT INTO ks.users (id) VALUES (?) IF NOT EXISTS USING TTL ? AND TIMESTAMP ?"
+        );
+        assert_eq!(
+            values,
+            QueryValues::SimpleValues(vec![Value::from(1), Value::from(60), Value::from(1000i64)])
+        );
+    }
+
+    #[test]
+    fn should_reject_insert_without_values() {
+        assert!(InsertBuilder::new("users").build().is_err());
+    }
+}
@@ -0,0 +1,245 @@
+use cassandra_protocol::error::Result;
+use cassandra_protocol::query::QueryValues;
+use cassandra_protocol::types::value::Value;
+use cassandra_protocol::types::CInt;
+use itertools::Itertools;
+
+use crate::query_builder::{identifier, qualified_table_name, Operator, Order, WhereClause};
+
+#[derive(Debug, Clone, PartialEq)]
+enum Selector {
+    Column(String),
+    Ttl(String, Option<String>),
+    Writetime(String, Option<String>),
+}
+
+impl Selector {
+    fn write(&self) -> Result<String> {
+        match self {
+            Selector::Column(column) => identifier(column),
+            Selector::Ttl(column, alias) => Self::write_function("TTL", column, alias),
+            Selector::Writetime(column, alias) => Self::write_function("WRITETIME", column, alias),
+        }
+    }
+
+    fn write_function(function: &str, column: &str, alias: &Option<String>) -> Result<String> {
+        let selector = format!("{}({})", function, identifier(column)?);
+        match alias {
+            Some(alias) => Ok(format!("{} AS {}", selector, identifier(alias)?)),
+            None => Ok(selector),
+        }
+    }
+}
+
+/// Builder for `SELECT` statements. If no columns are added, all columns are selected.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SelectBuilder {
+    keyspace: Option<String>,
+    table: String,
+    columns: Vec<Selector>,
+    where_clause: WhereClause,
+    order_by: Vec<(String, Order)>,
+    limit: Option<CInt>,
+    allow_filtering: bool,
+}
+
+impl SelectBuilder {
+    pub fn new<T: Into<String>>(table: T) -> Self {
+        SelectBuilder {
+            keyspace: None,
+            table: table.into(),
+            columns: vec![],
+            where_clause: Default::default(),
+            order_by: vec![],
+            limit: None,
+            allow_filtering: false,
+        }
+    }
+
+    /// Creates a builder for a table qualified with a keyspace, so the statement doesn't depend
+    /// on the current keyspace.
+    pub fn qualified<K: Into<String>, T: Into<String>>(keyspace: K, table: T) -> Self {
+        Self::new(table).with_keyspace(keyspace)
+    }
+
+    /// Sets the keyspace of the table.
+    #[must_use]
+    pub fn with_keyspace<T: Into<String>>(mut self, keyspace: T) -> Self {
+        self.keyspace = Some(keyspace.into());
+        self
+    }
+
+    /// Adds a column to select.
+    #[must_use]
+    pub fn add_column<T: Into<String>>(mut self, column: T) -> Self {
+        self.columns.push(Selector::Column(column.into()));
+        self
+    }
+
+    /// Selects the time to live of given column. The result can be read with
+    /// [`Row::ttl_of`](crate::types::rows::Row::ttl_of), using the column name or the alias, if
+    /// present.
+    #[must_use]
+    pub fn add_ttl<T: Into<String>>(mut self, column: T, alias: Option<String>) -> Self {
+        self.columns.push(Selector::Ttl(column.into(), alias));
+        self
+    }
+
+    /// Selects the write time of given column. The result can be read with
+    /// [`Row::writetime_of`](crate::types::rows::Row::writetime_of), using the column name or the
+    /// alias, if present.
+    #[must_use]
+    pub fn add_writetime<T: Into<String>>(mut self, column: T, alias: Option<String>) -> Self {
+        self.columns.push(Selector::Writetime(column.into(), alias));
+        self
+    }
+
+    /// Adds a relation to the `WHERE` clause. The value is always bound.
+    #[must_use]
+    pub fn add_where<T: Into<String>, V: Into<Value>>(
+        mut self,
+        column: T,
+        operator: Operator,
+        value: V,
+    ) -> Self {
+        self.where_clause.add(column.into(), operator, value.into());
+        self
+    }
+
+    /// Adds a column to the `ORDER BY` clause.
+    #[must_use]
+    pub fn add_order_by<T: Into<String>>(mut self, column: T, order: Order) -> Self {
+        self.order_by.push((column.into(), order));
+        self
+    }
+
+    /// Sets the maximum number of returned rows.
+    #[must_use]
+    pub fn with_limit(mut self, limit: CInt) -> Self {
+        self.limit = Some(limit);
+        self
+    }
+
+    /// Sets the `ALLOW FILTERING` flag.
+    #[must_use]
+    pub fn with_allow_filtering(mut self, allow_filtering: bool) -> Self {
+        self.allow_filtering = allow_filtering;
+        self
+    }
+
+    /// Builds the query along with values to bind.
+    pub fn build(self) -> Result<(String, QueryValues)> {
+        let mut values = vec![];
+
+        let columns = if self.columns.is_empty() {
+            "*".to_string()
+        } else {
+            self.columns
+                .iter()
+                .map(Selector::write)
+                .collect::<Result<Vec<_>>>()?
+                .join(", ")
+        };
+
+        let mut query = format!(
+            "SELECT {} FROM {}",
+            columns,
+            qualified_table_name(self.keyspace.as_deref(), &self.table)?
+        );
+
+        self.where_clause.write(&mut query, &mut values)?;
+
+        if !self.order_by.is_empty() {
+            let order_by = self
+                .order_by
+                .iter()
+                .map(|(column, order)| identifier(column).map(|column| format!("{column} {order}")))
+                .collect::<Result<Vec<_>>>()?;
+
+            query.push_str(" ORDER BY ");
+            query.push_str(&order_by.iter().join(", "));
+        }
+
+        if let Some(limit) = self.limit {
+            query.push_str(" LIMIT ?");
+            values.push(limit.into());
+        }
+
+        if self.allow_filtering {
+            query.push_str(" ALLOW FILTERING");
+        }
+
+        Ok((query, QueryValues::SimpleValues(values)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use cassandra_protocol::query::QueryValues;
+    use cassandra_protocol::types::value::Value;
+
+    use crate::query_builder::{Operator, Order, SelectBuilder};
+
+    #[test]
+    fn should_select_all_columns() {
+        let (query, values) = SelectBuilder::new("users").build().unwrap();
+        assert_eq!(query, "SELECT * FROM users");
+        assert!(values.is_empty());
+    }
+
+    #[test]
+    fn should_build_full_select() {
+        let (query, values) = SelectBuilder::new("Users")
+            .with_keyspace("ks")
+            .add_column("id")
+            .add_column("from")
+            .add_where("id", Operator::Eq, 1)
+            .add_where("time", Operator::Gt, 2)
+            .add_order_by("time", Order::Desc)
+            .with_limit(10)
+            .with_allow_filtering(true)
+            .build()
+            .unwrap();
+
+        assert_eq!(
+            query,
+            "SELECT id, \"from\" FROM ks.\"Users\" WHERE id = ? AND time > ? ORDER BY time DESC LIMIT ? ALLOW FILTERING"
+        );
+        assert_eq!(
+            values,
+            QueryValues::SimpleValues(vec![Value::from(1), Value::from(2), Value::from(10)])
+        );
+    }
+
+    #[test]
+    fn should_not_interpolate_values() {
+        let (query, values) = SelectBuilder::new("users")
+            .add_where("name", Operator::Eq, "'; DROP TABLE users; --")
+            .build()
+            .unwrap();
+
+        assert_eq!(query, "SELECT * FROM users WHERE name = ?");
+        assert_eq!(values.len(), 1);
+    }
+
+    #[test]
+    fn should_select_ttl_and_writetime() {
+        let (query, _) = SelectBuilder::new("users")
+            .add_column("name")
+            .add_ttl("name", None)
+            .add_writetime("Name", Some("name_written".into()))
+            .build()
+            .unwrap();
+
+        assert_eq!(
+            query,
+            "SELECT name, TTL(name), WRITETIME(\"Name\") AS name_written FROM users"
+        );
+    }
+
+    #[test]
+    fn should_reject_empty_identifiers() {
+        assert!(SelectBuilder::new("").build().is_err());
+        assert!(SelectBuilder::new("users").add_column("").build().is_err());
+    }
+}
@@ -0,0 +1,164 @@
+use cassandra_protocol::error::{Error, Result};
+use cassandra_protocol::query::QueryValues;
+use cassandra_protocol::types::value::Value;
+use cassandra_protocol::types::{CInt, CLong};
+
+use crate::query_builder::{identifier, qualified_table_name, Operator, UsingClause, WhereClause};
+
+/// Builder for `UPDATE` statements.
+#[derive(Debug, Clone, PartialEq)]
+pub struct UpdateBuilder {
+    keyspace: Option<String>,
+    table: String,
+    assignments: Vec<(String, Value)>,
+    where_clause: WhereClause,
+    if_exists: bool,
+    using: UsingClause,
+}
+
+impl UpdateBuilder {
+    pub fn new<T: Into<String>>(table: T) -> Self {
+        UpdateBuilder {
+            keyspace: None,
+            table: table.into(),
+            assignments: vec![],
+            where_clause: Default::default(),
+            if_exists: false,
+            using: Default::default(),
+        }
+    }
+
+    /// Creates a builder for a table qualified with a keyspace, so the statement doesn't depend
+    /// on the current keyspace.
+    pub fn qualified<K: Into<String>, T: Into<String>>(keyspace: K, table: T) -> Self {
+        Self::new(table).with_keyspace(keyspace)
+    }
+
+    /// Sets the keyspace of the table.
+    #[must_use]
+    pub fn with_keyspace<T: Into<String>>(mut self, keyspace: T) -> Self {
+        self.keyspace = Some(keyspace.into());
+        self
+    }
+
+    /// Adds a column assignment. The value is always bound.
+    #[must_use]
+    pub fn add_set<T: Into<String>, V: Into<Value>>(mut self, column: T, value: V) -> Self {
+        self.assignments.push((column.into(), value.into()));
+        self
+    }
+
+    /// Adds a relation to the `WHERE` clause. The value is always bound.
+    #[must_use]
+    pub fn add_where<T: Into<String>, V: Into<Value>>(
+        mut self,
+        column: T,
+        operator: Operator,
+        value: V,
+    ) -> Self {
+        self.where_clause.add(column.into(), operator, value.into());
+        self
+    }
+
+    /// Sets the `IF EXISTS` flag, turning the update into a lightweight transaction.
+    #[must_use]
+    pub fn with_if_exists(mut self, if_exists: bool) -> Self {
+        self.if_exists = if_exists;
+        self
+    }
+
+    /// Sets the time to live of updated values, in seconds.
+    #[must_use]
+    pub fn with_ttl(mut self, ttl: CInt) -> Self {
+        self.using.ttl = Some(ttl.into());
+        self
+    }
+
+    /// Sets the write timestamp, in microseconds.
+    #[must_use]
+    pub fn with_timestamp(mut self, timestamp: CLong) -> Self {
+        self.using.timestamp = Some(timestamp.into());
+        self
+    }
+
+    /// Builds the query along with values to bind.
+    pub fn build(self) -> Result<(String, QueryValues)> {
+        if self.assignments.is_empty() {
+            return Err(Error::General(
+                "Update statements need at least one assignment!".into(),
+            ));
+        }
+
+        let mut values = Vec::with_capacity(self.assignments.len());
+        let mut query = format!(
+            "UPDATE {}",
+            qualified_table_name(self.keyspace.as_deref(), &self.table)?
+        );
+
+        self.using.write(&mut query, &mut values);
+
+        let assignments = self
+            .assignments
+            .into_iter()
+            .map(|(column, value)| {
+                let column = identifier(&column)?;
+                values.push(value);
+                Ok(format!("{column} = ?"))
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        query.push_str(" SET ");
+        query.push_str(&assignments.join(", "));
+
+        self.where_clause.write(&mut query, &mut values)?;
+
+        if self.if_exists {
+            query.push_str(" IF EXISTS");
+        }
+
+        Ok((query, QueryValues::SimpleValues(values)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use cassandra_protocol::query::QueryValues;
+    use cassandra_protocol::types::value::Value;
+
+    use crate::query_builder::{Operator, UpdateBuilder};
+
+    #[test]
+    fn should_build_update() {
+        let (query, values) = UpdateBuilder::new("users")
+            .with_keyspace("ks")
+            .add_set("name", "name")
+            .add_set("age", 30)
+            .add_where("id", Operator::Eq, 1)
+            .with_ttl(60)
+            .with_if_exists(true)
+            .build()
+            .unwrap();
+
+        assert_eq!(
+            query,
+            "UPDATE ks.users USING TTL ? SET name = ?, age = ? WHERE id = ? IF EXISTS"
+        );
+        assert_eq!(
+            values,
+            QueryValues::SimpleValues(vec![
+                Value::from(60),
+                Value::from("name"),
+                Value::from(30),
+                Value::from(1)
+            ])
+        );
+    }
+
+    #[test]
+    fn should_reject_update_without_assignments() {
+        assert!(UpdateBuilder::new("users")
+            .add_where("id", Operator::Eq, 1)
+            .build()
+            .is_err());
+    }
+}
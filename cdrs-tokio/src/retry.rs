@@ -1,5 +1,7 @@
 mod reconnection_policy;
+mod retry_budget;
 mod retry_policy;
 
 pub use reconnection_policy::*;
+pub use retry_budget::*;
 pub use retry_policy::*;
@@ -1,7 +1,9 @@
 use derive_more::Constructor;
 #[cfg(test)]
 use mockall::automock;
-use rand::{rng, Rng};
+use rand::rngs::StdRng;
+use rand::{rng, Rng, SeedableRng};
+use std::ops::RangeInclusive;
 use std::time::Duration;
 
 const DEFAULT_BASE_DELAY: Duration = Duration::from_secs(1);
@@ -11,6 +13,13 @@ const DEFAULT_MAX_DELAY: Duration = Duration::from_secs(60);
 pub trait ReconnectionSchedule {
     /// Returns next reconnect delay or `None` if not attempt should be made.
     fn next_delay(&mut self) -> Option<Duration>;
+
+    /// Returns how many delays have been returned so far, i.e. the index of the upcoming
+    /// reconnection attempt, starting from 1. Useful for logging. Schedules which don't count
+    /// attempts return 0.
+    fn attempt(&self) -> usize {
+        0
+    }
 }
 
 /// Creates reconnection schedules when trying to re-establish connections.
@@ -34,19 +43,26 @@ impl Default for ConstantReconnectionPolicy {
 
 impl ReconnectionPolicy for ConstantReconnectionPolicy {
     fn new_node_schedule(&self) -> Box<dyn ReconnectionSchedule + Send + Sync> {
-        Box::new(ConstantReconnectionSchedule::new(self.base_delay))
+        Box::new(ConstantReconnectionSchedule::new(self.base_delay, 0))
     }
 }
 
 #[derive(Constructor)]
 struct ConstantReconnectionSchedule {
     base_delay: Duration,
+    attempt: usize,
 }
 
 impl ReconnectionSchedule for ConstantReconnectionSchedule {
     fn next_delay(&mut self) -> Option<Duration> {
+        self.attempt = self.attempt.saturating_add(1);
         Some(self.base_delay)
     }
+
+    #[inline]
+    fn attempt(&self) -> usize {
+        self.attempt
+    }
 }
 
 /// Never schedules reconnections.
@@ -69,21 +85,56 @@ impl ReconnectionSchedule for NeverReconnectionSchedule {
 
 /// A reconnection policy that waits exponentially longer between each reconnection attempt (but
 /// keeps a constant delay once a maximum delay is reached). The delay will increase exponentially,
-/// with an added jitter.
-#[derive(Copy, Clone, Constructor, Debug, Ord, PartialOrd, Eq, PartialEq, Hash)]
+/// with an added jitter of +/-15% by default. Full jitter spreads reconnections of many clients
+/// better, e.g. after a node restart, by picking a random delay between zero and the exponential
+/// one.
+#[derive(Copy, Clone, Debug, Ord, PartialOrd, Eq, PartialEq, Hash)]
 pub struct ExponentialReconnectionPolicy {
     base_delay: Duration,
     max_delay: Duration,
     max_attempts: usize,
+    full_jitter: bool,
+    seed: Option<u64>,
+}
+
+impl ExponentialReconnectionPolicy {
+    pub fn new(base_delay: Duration, max_delay: Duration, max_attempts: usize) -> Self {
+        ExponentialReconnectionPolicy {
+            base_delay,
+            max_delay,
+            max_attempts,
+            full_jitter: false,
+            seed: None,
+        }
+    }
+
+    /// Picks delays uniformly between zero and the exponential delay, instead of applying a
+    /// +/-15% jitter.
+    #[must_use]
+    pub fn with_full_jitter(mut self, full_jitter: bool) -> Self {
+        self.full_jitter = full_jitter;
+        self
+    }
+
+    /// Makes jitter deterministic, e.g. for tests. Every schedule created by the policy returns
+    /// the same sequence of delays, so this shouldn't be used in production.
+    #[must_use]
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.seed = Some(seed);
+        self
+    }
 }
 
 impl ReconnectionPolicy for ExponentialReconnectionPolicy {
     fn new_node_schedule(&self) -> Box<dyn ReconnectionSchedule + Send + Sync> {
-        Box::new(ExponentialReconnectionSchedule::new(
-            self.base_delay,
-            self.max_delay,
-            self.max_attempts,
-        ))
+        Box::new(
+            ExponentialReconnectionSchedule::new(
+                self.base_delay,
+                self.max_delay,
+                self.max_attempts,
+            )
+            .with_jitter(self.full_jitter, self.seed),
+        )
     }
 }
 
@@ -104,23 +155,38 @@ struct ExponentialReconnectionSchedule {
     base_delay: Duration,
     max_delay: Duration,
     max_attempts: usize,
-    attempt: usize,
+    // stops growing after max attempts
+    exponent: usize,
+    attempts: usize,
+    full_jitter: bool,
+    // thread rng is used when not seeded
+    seeded_rng: Option<StdRng>,
 }
 
 impl ReconnectionSchedule for ExponentialReconnectionSchedule {
     fn next_delay(&mut self) -> Option<Duration> {
-        if self.attempt == self.max_attempts {
-            return Some(self.max_delay);
+        self.attempts = self.attempts.saturating_add(1);
+
+        if self.exponent == self.max_attempts {
+            return Some(if self.full_jitter {
+                self.full_jitter_delay(self.max_delay)
+            } else {
+                self.max_delay
+            });
         }
 
-        self.attempt += 1;
+        self.exponent += 1;
 
         let delay = self
             .base_delay
-            .saturating_mul(1u32.checked_shl(self.attempt as u32).unwrap_or(u32::MAX))
+            .saturating_mul(1u32.checked_shl(self.exponent as u32).unwrap_or(u32::MAX))
             .min(self.max_delay);
 
-        let jitter = rng().random_range(85..116);
+        if self.full_jitter {
+            return Some(self.full_jitter_delay(delay));
+        }
+
+        let jitter = self.random_range(85..=115) as u32;
 
         Some(
             (delay / 100)
@@ -128,6 +194,11 @@ impl ReconnectionSchedule for ExponentialReconnectionSchedule {
                 .clamp(self.base_delay, self.max_delay),
         )
     }
+
+    #[inline]
+    fn attempt(&self) -> usize {
+        self.attempts
+    }
 }
 
 impl ExponentialReconnectionSchedule {
@@ -136,25 +207,93 @@ impl ExponentialReconnectionSchedule {
             base_delay,
             max_delay,
             max_attempts,
-            attempt: 0,
+            exponent: 0,
+            attempts: 0,
+            full_jitter: false,
+            seeded_rng: None,
+        }
+    }
+
+    fn with_jitter(mut self, full_jitter: bool, seed: Option<u64>) -> Self {
+        self.full_jitter = full_jitter;
+        self.seeded_rng = seed.map(StdRng::seed_from_u64);
+        self
+    }
+
+    fn full_jitter_delay(&mut self, delay: Duration) -> Duration {
+        let millis = delay.as_millis().min(u64::MAX as u128) as u64;
+        Duration::from_millis(self.random_range(0..=millis))
+    }
+
+    fn random_range(&mut self, range: RangeInclusive<u64>) -> u64 {
+        match &mut self.seeded_rng {
+            Some(seeded_rng) => seeded_rng.random_range(range),
+            None => rng().random_range(range),
         }
     }
 }
 
 #[cfg(test)]
 mod tests {
+    use std::time::Duration;
+
     use crate::retry::reconnection_policy::ExponentialReconnectionSchedule;
-    use crate::retry::ReconnectionSchedule;
+    use crate::retry::{ExponentialReconnectionPolicy, ReconnectionPolicy, ReconnectionSchedule};
 
     #[test]
     fn should_reach_max_exponential_delay_without_panic() {
         let mut schedule = ExponentialReconnectionSchedule {
-            base_delay: Default::default(),
-            max_delay: Default::default(),
-            max_attempts: usize::MAX,
-            attempt: usize::MAX - 1,
+            exponent: usize::MAX - 1,
+            ..ExponentialReconnectionSchedule::new(
+                Default::default(),
+                Default::default(),
+                usize::MAX,
+            )
         };
 
         schedule.next_delay();
     }
+
+    fn delays(policy: &ExponentialReconnectionPolicy, count: usize) -> Vec<Duration> {
+        let mut schedule = policy.new_node_schedule();
+        (0..count).map(|_| schedule.next_delay().unwrap()).collect()
+    }
+
+    #[test]
+    fn should_apply_full_jitter() {
+        let policy = ExponentialReconnectionPolicy::new(
+            Duration::from_millis(100),
+            Duration::from_secs(10),
+            3,
+        )
+        .with_full_jitter(true);
+
+        let mut schedule = policy.new_node_schedule();
+        for attempt in 1..=5 {
+            let delay = schedule.next_delay().unwrap();
+            let max_delay = if attempt <= 3 {
+                Duration::from_millis(100 << attempt)
+            } else {
+                Duration::from_secs(10)
+            };
+
+            assert!(delay <= max_delay, "{:?} > {:?}", delay, max_delay);
+            assert_eq!(schedule.attempt(), attempt);
+        }
+    }
+
+    #[test]
+    fn should_repeat_seeded_delays() {
+        let policy = ExponentialReconnectionPolicy::new(
+            Duration::from_millis(100),
+            Duration::from_secs(60),
+            10,
+        )
+        .with_seed(7);
+
+        assert_eq!(delays(&policy, 8), delays(&policy, 8));
+
+        let policy = policy.with_full_jitter(true);
+        assert_eq!(delays(&policy, 8), delays(&policy, 8));
+    }
 }
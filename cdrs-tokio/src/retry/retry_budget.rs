@@ -0,0 +1,156 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Instant;
+
+// the balance is kept in thousandths of a retry, so fractional deposits are not lost
+const UNITS_PER_RETRY: u64 = 1000;
+
+const DEFAULT_BALANCE_SECONDS: u64 = 10;
+
+/// Session-wide limit of retries, protecting a struggling cluster from being overwhelmed by many
+/// requests retrying at once. Consulted only after the retry policy decides to retry - if the
+/// budget is exhausted, the request fails with
+/// [`Error::RetrySuppressed`](crate::error::Error::RetrySuppressed) wrapping the original error.
+///
+/// Works as a token bucket, with each retry taking one token. Tokens are added at a constant
+/// rate of `max_retries_per_second`, which allows some retries even when nothing succeeds, and
+/// with each successful request, which adds `retry_ratio` of a token, e.g. 0.1 permits one retry
+/// per ten successful requests. The bucket holds up to 10 seconds worth of constant rate tokens by
+/// default and starts full.
+#[derive(Debug)]
+pub struct RetryBudget {
+    balance: AtomicU64,
+    max_balance: u64,
+    refill_per_ms: u64,
+    deposit: u64,
+    created: Instant,
+    last_refill_ms: AtomicU64,
+    retries: AtomicU64,
+    suppressed_retries: AtomicU64,
+}
+
+impl RetryBudget {
+    pub fn new(max_retries_per_second: u32, retry_ratio: f64) -> Self {
+        let max_balance = max_retries_per_second as u64 * DEFAULT_BALANCE_SECONDS * UNITS_PER_RETRY;
+
+        RetryBudget {
+            balance: AtomicU64::new(max_balance),
+            max_balance,
+            // per second in thousandths is the same as per millisecond in whole retries
+            refill_per_ms: max_retries_per_second as u64,
+            deposit: (retry_ratio.max(0.0) * UNITS_PER_RETRY as f64) as u64,
+            created: Instant::now(),
+            last_refill_ms: AtomicU64::new(0),
+            retries: AtomicU64::new(0),
+            suppressed_retries: AtomicU64::new(0),
+        }
+    }
+
+    /// Sets the maximum number of retries which can be saved up, e.g. during a period without
+    /// failures.
+    #[must_use]
+    pub fn with_max_balance(mut self, max_balance: u32) -> Self {
+        self.max_balance = max_balance as u64 * UNITS_PER_RETRY;
+        self.balance = AtomicU64::new(self.max_balance);
+        self
+    }
+
+    /// Returns current state of the budget.
+    pub fn snapshot(&self) -> RetryBudgetSnapshot {
+        self.refill();
+
+        RetryBudgetSnapshot {
+            balance: self.balance.load(Ordering::Relaxed) as f64 / UNITS_PER_RETRY as f64,
+            retries: self.retries.load(Ordering::Relaxed),
+            suppressed_retries: self.suppressed_retries.load(Ordering::Relaxed),
+        }
+    }
+
+    /// Records a successful request.
+    pub(crate) fn deposit(&self) {
+        if self.deposit > 0 {
+            self.add(self.deposit);
+        }
+    }
+
+    /// Takes a retry from the budget, returning `false` if none are left.
+    pub(crate) fn try_withdraw(&self) -> bool {
+        self.refill();
+
+        let withdrawn = self
+            .balance
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |balance| {
+                balance.checked_sub(UNITS_PER_RETRY)
+            })
+            .is_ok();
+
+        if withdrawn {
+            self.retries.fetch_add(1, Ordering::Relaxed);
+        } else {
+            self.suppressed_retries.fetch_add(1, Ordering::Relaxed);
+        }
+
+        withdrawn
+    }
+
+    fn refill(&self) {
+        let now = self.created.elapsed().as_millis() as u64;
+        let last_refill = self.last_refill_ms.fetch_max(now, Ordering::Relaxed);
+
+        if now > last_refill {
+            self.add((now - last_refill).saturating_mul(self.refill_per_ms));
+        }
+    }
+
+    fn add(&self, amount: u64) {
+        let _ = self
+            .balance
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |balance| {
+                Some(balance.saturating_add(amount).min(self.max_balance))
+            });
+    }
+}
+
+/// State of a [`RetryBudget`] at some point in time.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct RetryBudgetSnapshot {
+    /// Number of retries currently available.
+    pub balance: f64,
+    /// Number of retries permitted by the budget.
+    pub retries: u64,
+    /// Number of retries suppressed due to exhausted budget.
+    pub suppressed_retries: u64,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::RetryBudget;
+
+    #[test]
+    fn should_suppress_retries_when_exhausted() {
+        let budget = RetryBudget::new(0, 0.5).with_max_balance(2);
+
+        assert!(budget.try_withdraw());
+        assert!(budget.try_withdraw());
+        assert!(!budget.try_withdraw());
+
+        // two successful requests earn another retry
+        budget.deposit();
+        assert!(!budget.try_withdraw());
+        budget.deposit();
+        assert!(budget.try_withdraw());
+
+        let snapshot = budget.snapshot();
+        assert_eq!(snapshot.balance, 0.0);
+        assert_eq!(snapshot.retries, 3);
+        assert_eq!(snapshot.suppressed_retries, 2);
+    }
+
+    #[test]
+    fn should_cap_balance() {
+        let budget = RetryBudget::new(0, 1.0).with_max_balance(1);
+
+        budget.deposit();
+        budget.deposit();
+        assert_eq!(budget.snapshot().balance, 1.0);
+    }
+}
@@ -1,6 +1,7 @@
 use derive_more::Display;
 
 use cassandra_protocol::error::Error;
+use cassandra_protocol::frame::message_batch::BatchType;
 use cassandra_protocol::frame::message_error::{
     ErrorBody, ErrorType, ReadTimeoutError, WriteTimeoutError, WriteType,
 };
@@ -15,7 +16,10 @@ pub enum RetryDecision {
 /// Information about a failed query.
 pub struct QueryInfo<'a> {
     pub error: &'a Error,
+    /// Idempotency of the request - for batches, of the batch as a whole.
     pub is_idempotent: bool,
+    /// Type of the batch, if the failed request was a batch.
+    pub batch_type: Option<BatchType>,
 }
 
 /// Query-specific information about current state of retrying.
@@ -24,12 +28,58 @@ pub trait RetrySession {
     fn decide(&mut self, query_info: QueryInfo) -> RetryDecision;
 }
 
+impl<R: RetrySession + ?Sized> RetrySession for &mut R {
+    #[inline]
+    fn decide(&mut self, query_info: QueryInfo) -> RetryDecision {
+        (**self).decide(query_info)
+    }
+}
+
+impl<R: RetrySession + ?Sized> RetrySession for Box<R> {
+    #[inline]
+    fn decide(&mut self, query_info: QueryInfo) -> RetryDecision {
+        (**self).decide(query_info)
+    }
+}
+
 /// Retry policy determines what to do in case of communication error.
 pub trait RetryPolicy {
-    /// Called for each new query, starts a session of deciding about retries.
+    /// Called for each new query, starts a session of deciding about retries. Sessions are
+    /// created lazily by the driver, on the first failure of a query.
     fn new_session(&self) -> Box<dyn RetrySession + Send + Sync>;
 }
 
+/// Retry session which starts a session of given policy only when the first decision is needed,
+/// so queries succeeding right away don't allocate one.
+pub struct LazyRetrySession<'a> {
+    retry_policy: &'a (dyn RetryPolicy + Send + Sync),
+    retry_session: Option<Box<dyn RetrySession + Send + Sync>>,
+}
+
+impl<'a> LazyRetrySession<'a> {
+    pub fn new(retry_policy: &'a (dyn RetryPolicy + Send + Sync)) -> Self {
+        LazyRetrySession {
+            retry_policy,
+            retry_session: None,
+        }
+    }
+
+    /// Checks if the underlying session has been started.
+    #[inline]
+    pub fn is_started(&self) -> bool {
+        self.retry_session.is_some()
+    }
+}
+
+impl RetrySession for LazyRetrySession<'_> {
+    fn decide(&mut self, query_info: QueryInfo) -> RetryDecision {
+        let retry_policy = self.retry_policy;
+        self.retry_session
+            .get_or_insert_with(|| retry_policy.new_session())
+            .decide(query_info)
+    }
+}
+
 /// Forwards all errors directly to the user, never retries
 #[derive(Default, Debug, Clone, Copy, Ord, PartialOrd, Eq, PartialEq, Hash)]
 pub struct FallthroughRetryPolicy;
@@ -51,6 +101,15 @@ impl RetrySession for FallthroughRetrySession {
 
 /// Default retry policy - retries when there is a high chance that a retry might help.  
 /// Behaviour based on [DataStax Java Driver](https://docs.datastax.com/en/developer/java-driver/4.10/manual/core/retries/)
+///
+/// Overloaded and bootstrapping coordinators reject requests before executing them, so these
+/// are always retried on the next node. Truncate errors are retried on the next node only for
+/// idempotent requests, while server, protocol and authentication errors are never retried.
+///
+/// Batches are handled according to their type: logged batch write timeouts are retried only
+/// when the batch log itself couldn't be written, unlogged batches are retried after possibly
+/// being executed only when idempotent, and counter batches are never retried once they reached
+/// a coordinator.
 #[derive(Default, Debug, Clone, Copy, Ord, PartialOrd, Eq, PartialEq, Hash)]
 pub struct DefaultRetryPolicy;
 
@@ -67,27 +126,52 @@ pub struct DefaultRetrySession {
     was_write_timeout_retry: bool,
 }
 
+impl DefaultRetrySession {
+    fn decide_write_timeout(
+        &mut self,
+        error: &WriteTimeoutError,
+        is_idempotent: bool,
+        batch_type: Option<BatchType>,
+    ) -> RetryDecision {
+        let should_retry = match batch_type {
+            // the coordinator failed to write the batch log, so the batch wasn't applied yet and
+            // won't be replayed
+            Some(BatchType::Logged) => error.write_type == WriteType::BatchLog,
+            // some statements might have been applied already
+            Some(BatchType::Unlogged) => {
+                is_idempotent && error.write_type == WriteType::UnloggedBatch
+            }
+            // counter batches are never retried, nor ones of unknown types
+            Some(_) => false,
+            None => is_idempotent && error.write_type == WriteType::BatchLog,
+        };
+
+        if !self.was_write_timeout_retry && should_retry {
+            self.was_write_timeout_retry = true;
+            RetryDecision::RetrySameNode
+        } else {
+            RetryDecision::DontRetry
+        }
+    }
+}
+
 impl RetrySession for DefaultRetrySession {
     fn decide(&mut self, query_info: QueryInfo) -> RetryDecision {
+        // counter updates are never idempotent, so counter batches which might have reached a
+        // coordinator are rethrown right away
+        if query_info.batch_type == Some(BatchType::Counter)
+            && !matches!(query_info.error, Error::RequestNotSent(_))
+        {
+            return RetryDecision::DontRetry;
+        }
+
         match query_info.error {
             Error::Io(_)
             | Error::General(_)
-            | Error::Server {
-                body:
-                    ErrorBody {
-                        ty: ErrorType::Overloaded,
-                        ..
-                    },
-                ..
-            }
-            | Error::Server {
-                body:
-                    ErrorBody {
-                        ty: ErrorType::Server,
-                        ..
-                    },
-                ..
-            }
+            | Error::ProtocolDesync(_)
+            | Error::UnexpectedFrame { .. }
+            | Error::HandshakeTimeout(_)
+            | Error::AuthenticationTimeout(_)
             | Error::Server {
                 body:
                     ErrorBody {
@@ -142,26 +226,183 @@ impl RetrySession for DefaultRetrySession {
                         ..
                     },
                 ..
-            } => {
-                if !self.was_write_timeout_retry
-                    && query_info.is_idempotent
-                    && error.write_type == WriteType::BatchLog
-                {
-                    self.was_write_timeout_retry = true;
-                    RetryDecision::RetrySameNode
-                } else {
-                    RetryDecision::DontRetry
-                }
-            }
+            } => self.decide_write_timeout(error, query_info.is_idempotent, query_info.batch_type),
+            // the coordinator rejected the request without executing it, so another one can
+            // take it regardless of idempotency
             Error::Server {
                 body:
                     ErrorBody {
-                        ty: ErrorType::IsBootstrapping,
+                        ty: ErrorType::Overloaded | ErrorType::IsBootstrapping,
                         ..
                     },
                 ..
             } => RetryDecision::RetryNextNode,
+            // the request might have been partially executed or is invalid for every node
+            Error::Server {
+                body:
+                    ErrorBody {
+                        ty: ErrorType::Server | ErrorType::Protocol | ErrorType::Authentication,
+                        ..
+                    },
+                ..
+            } => RetryDecision::DontRetry,
+            // the request never left the client, so it's always safe to send it elsewhere
+            Error::RequestNotSent(_) => RetryDecision::RetryNextNode,
             _ => RetryDecision::DontRetry,
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use cassandra_protocol::consistency::Consistency;
+    use cassandra_protocol::error::Error;
+    use cassandra_protocol::frame::message_batch::BatchType;
+    use cassandra_protocol::frame::message_error::{
+        ErrorBody, ErrorType, UnavailableError, WriteTimeoutError, WriteType,
+    };
+    use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+
+    use super::{DefaultRetryPolicy, QueryInfo, RetryDecision, RetryPolicy};
+
+    fn server_error(ty: ErrorType) -> Error {
+        Error::Server {
+            body: ErrorBody {
+                message: "error".into(),
+                ty,
+            },
+            addr: SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), 9042),
+        }
+    }
+
+    fn write_timeout(write_type: WriteType) -> Error {
+        server_error(ErrorType::WriteTimeout(WriteTimeoutError {
+            cl: Consistency::Quorum,
+            received: 1,
+            block_for: 2,
+            write_type,
+            contentions: None,
+        }))
+    }
+
+    fn decide(error: &Error, is_idempotent: bool, batch_type: Option<BatchType>) -> RetryDecision {
+        DefaultRetryPolicy.new_session().decide(QueryInfo {
+            error,
+            is_idempotent,
+            batch_type,
+        })
+    }
+
+    #[test]
+    fn should_retry_logged_batches_only_on_batch_log_timeouts() {
+        for is_idempotent in [false, true] {
+            assert_eq!(
+                decide(
+                    &write_timeout(WriteType::BatchLog),
+                    is_idempotent,
+                    Some(BatchType::Logged)
+                ),
+                RetryDecision::RetrySameNode
+            );
+            assert_eq!(
+                decide(
+                    &write_timeout(WriteType::Batch),
+                    is_idempotent,
+                    Some(BatchType::Logged)
+                ),
+                RetryDecision::DontRetry
+            );
+        }
+    }
+
+    #[test]
+    fn should_retry_unlogged_batches_only_when_idempotent() {
+        let error = write_timeout(WriteType::UnloggedBatch);
+        assert_eq!(
+            decide(&error, true, Some(BatchType::Unlogged)),
+            RetryDecision::RetrySameNode
+        );
+        assert_eq!(
+            decide(&error, false, Some(BatchType::Unlogged)),
+            RetryDecision::DontRetry
+        );
+
+        let error = Error::Io(std::io::ErrorKind::ConnectionReset.into());
+        assert_eq!(
+            decide(&error, true, Some(BatchType::Unlogged)),
+            RetryDecision::RetryNextNode
+        );
+        assert_eq!(
+            decide(&error, false, Some(BatchType::Unlogged)),
+            RetryDecision::DontRetry
+        );
+    }
+
+    #[test]
+    fn should_rethrow_counter_batches() {
+        let errors = [
+            write_timeout(WriteType::Counter),
+            write_timeout(WriteType::BatchLog),
+            server_error(ErrorType::Unavailable(UnavailableError {
+                cl: Consistency::Quorum,
+                required: 2,
+                alive: 1,
+            })),
+            server_error(ErrorType::Overloaded),
+            Error::Io(std::io::ErrorKind::ConnectionReset.into()),
+        ];
+
+        for error in &errors {
+            for is_idempotent in [false, true] {
+                assert_eq!(
+                    decide(error, is_idempotent, Some(BatchType::Counter)),
+                    RetryDecision::DontRetry,
+                    "{:?}",
+                    error
+                );
+            }
+        }
+
+        assert_eq!(
+            decide(
+                &Error::RequestNotSent(SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), 9042)),
+                false,
+                Some(BatchType::Counter)
+            ),
+            RetryDecision::RetryNextNode
+        );
+    }
+
+    #[test]
+    fn should_keep_non_batch_write_timeout_decisions() {
+        assert_eq!(
+            decide(&write_timeout(WriteType::BatchLog), true, None),
+            RetryDecision::RetrySameNode
+        );
+        assert_eq!(
+            decide(&write_timeout(WriteType::BatchLog), false, None),
+            RetryDecision::DontRetry
+        );
+        assert_eq!(
+            decide(&write_timeout(WriteType::Simple), true, None),
+            RetryDecision::DontRetry
+        );
+    }
+
+    #[test]
+    fn should_retry_write_timeouts_once() {
+        let error = write_timeout(WriteType::BatchLog);
+        let mut retry_session = DefaultRetryPolicy.new_session();
+
+        for expected in [RetryDecision::RetrySameNode, RetryDecision::DontRetry] {
+            assert_eq!(
+                retry_session.decide(QueryInfo {
+                    error: &error,
+                    is_idempotent: false,
+                    batch_type: Some(BatchType::Logged),
+                }),
+                expected
+            );
+        }
+    }
+}
@@ -0,0 +1,398 @@
+//! Isolates the async runtime used for spawning background tasks, timers and sockets. Tokio is used
+//! by default, while the `async-std` feature switches to async-std. Everything else, e.g. channels
+//! and locks, is runtime-agnostic.
+
+use futures::future::{abortable, select, AbortHandle, Either};
+use futures::FutureExt;
+use std::future::Future;
+use std::io;
+use std::net::SocketAddr;
+use std::time::Duration;
+use tokio::io::{AsyncRead, AsyncWrite};
+use tokio::sync::watch;
+use tracing::*;
+
+#[cfg(feature = "async-std")]
+pub(crate) use self::async_std_runtime::AsyncStdRuntime as DefaultRuntime;
+#[cfg(not(feature = "async-std"))]
+pub(crate) use self::tokio_runtime::TokioRuntime as DefaultRuntime;
+
+pub(crate) type TcpStream = <DefaultRuntime as Runtime>::TcpStream;
+
+/// Primitives which need to be provided by the runtime.
+pub(crate) trait Runtime {
+    type TcpStream: AsyncRead + AsyncWrite + Send + Sync + Unpin + 'static;
+
+    fn spawn(future: impl Future<Output = ()> + Send + 'static);
+
+    fn sleep(duration: Duration) -> impl Future<Output = ()> + Send;
+
+    fn connect(addr: SocketAddr) -> impl Future<Output = io::Result<Self::TcpStream>> + Send;
+
+    fn set_nodelay(stream: &Self::TcpStream, nodelay: bool) -> io::Result<()>;
+
+    fn lookup_host(host: &str) -> impl Future<Output = io::Result<Vec<SocketAddr>>> + Send;
+}
+
+/// Error returned when a [`timeout`] expires.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Elapsed;
+
+/// Spawns a background task, which can be aborted with the returned handle. Dropping the handle
+/// does not abort the task.
+pub(crate) fn spawn(future: impl Future<Output = ()> + Send + 'static) -> AbortHandle {
+    let (future, handle) = abortable(future);
+    DefaultRuntime::spawn(future.map(|_| ()));
+    handle
+}
+
+/// Tracks background tasks of a session, so they all get stopped when the session shuts down,
+/// instead of outliving it until the runtime exits.
+#[derive(Debug)]
+pub(crate) struct TaskTracker {
+    shutdown_sender: watch::Sender<bool>,
+}
+
+impl Default for TaskTracker {
+    fn default() -> Self {
+        TaskTracker {
+            shutdown_sender: watch::Sender::new(false),
+        }
+    }
+}
+
+impl TaskTracker {
+    /// Spawns a background task, which gets stopped on [`shutdown`](TaskTracker::shutdown) or
+    /// when the tracker is dropped.
+    pub(crate) fn spawn(&self, future: impl Future<Output = ()> + Send + 'static) -> AbortHandle {
+        let mut shutdown_receiver = self.shutdown_sender.subscribe();
+        spawn(async move {
+            // an error means the tracker is gone, which also stops the task
+            let shutdown = std::pin::pin!(shutdown_receiver.wait_for(|shutdown| *shutdown));
+            let future = std::pin::pin!(future);
+
+            // shutdown goes first, so tasks spawned after it never run
+            select(shutdown, future).await;
+        })
+    }
+
+    /// Stops all tracked tasks at their next suspension point. Tasks spawned afterwards are
+    /// stopped immediately.
+    pub(crate) fn shutdown(&self) {
+        self.shutdown_sender.send_replace(true);
+    }
+
+    #[inline]
+    pub(crate) fn is_shut_down(&self) -> bool {
+        *self.shutdown_sender.borrow()
+    }
+}
+
+#[inline]
+pub(crate) fn sleep(duration: Duration) -> impl Future<Output = ()> + Send {
+    DefaultRuntime::sleep(duration)
+}
+
+/// Waits for given future to complete for at most given duration.
+pub(crate) async fn timeout<F: Future>(
+    duration: Duration,
+    future: F,
+) -> Result<F::Output, Elapsed> {
+    let future = std::pin::pin!(future);
+    let sleep = std::pin::pin!(sleep(duration));
+
+    match select(future, sleep).await {
+        Either::Left((output, _)) => Ok(output),
+        Either::Right(_) => Err(Elapsed),
+    }
+}
+
+/// Connects to given address and applies socket options with [`apply_nodelay`].
+#[inline]
+pub(crate) async fn connect(
+    addr: SocketAddr,
+    nodelay: bool,
+    strict_socket_options: bool,
+) -> io::Result<TcpStream> {
+    let stream = DefaultRuntime::connect(addr).await?;
+    apply_nodelay(&stream, addr, nodelay, strict_socket_options)?;
+    Ok(stream)
+}
+
+#[inline]
+pub(crate) fn set_nodelay(stream: &TcpStream, nodelay: bool) -> io::Result<()> {
+    DefaultRuntime::set_nodelay(stream, nodelay)
+}
+
+/// Sets NODELAY on a socket connected to given node. Some environments, e.g. container runtimes,
+/// don't allow changing socket options, so failures are only logged, unless
+/// `strict_socket_options` is set. NODELAY is the only socket option set by the driver, e.g.
+/// keepalive is left to the OS defaults.
+pub(crate) fn apply_nodelay(
+    stream: &TcpStream,
+    addr: SocketAddr,
+    nodelay: bool,
+    strict_socket_options: bool,
+) -> io::Result<()> {
+    tolerate_socket_option_error(
+        set_nodelay(stream, nodelay),
+        "TCP_NODELAY",
+        addr,
+        strict_socket_options,
+    )
+}
+
+fn tolerate_socket_option_error(
+    result: io::Result<()>,
+    option: &str,
+    addr: SocketAddr,
+    strict_socket_options: bool,
+) -> io::Result<()> {
+    match result {
+        Err(error) if !strict_socket_options => {
+            warn!(%error, %addr, option, "Cannot set socket option! Continuing with the default.");
+            Ok(())
+        }
+        result => result,
+    }
+}
+
+/// Connects to the first reachable address given host resolves to, returning the last error if
+/// none of them are.
+#[cfg(feature = "http-proxy")]
+pub(crate) async fn connect_host(host: &str) -> io::Result<TcpStream> {
+    let mut last_error = None;
+    for addr in lookup_host(host).await? {
+        match DefaultRuntime::connect(addr).await {
+            Ok(stream) => return Ok(stream),
+            Err(error) => last_error = Some(error),
+        }
+    }
+
+    Err(last_error.unwrap_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("Could not resolve to any address: {host}"),
+        )
+    }))
+}
+
+#[inline]
+pub(crate) async fn lookup_host(host: &str) -> io::Result<Vec<SocketAddr>> {
+    DefaultRuntime::lookup_host(host).await
+}
+
+#[cfg(not(feature = "async-std"))]
+mod tokio_runtime {
+    use std::future::Future;
+    use std::io;
+    use std::net::SocketAddr;
+    use std::time::Duration;
+    use tokio::net::TcpStream;
+
+    use super::Runtime;
+
+    pub(crate) struct TokioRuntime;
+
+    impl Runtime for TokioRuntime {
+        type TcpStream = TcpStream;
+
+        #[inline]
+        fn spawn(future: impl Future<Output = ()> + Send + 'static) {
+            tokio::spawn(future);
+        }
+
+        #[inline]
+        fn sleep(duration: Duration) -> impl Future<Output = ()> + Send {
+            tokio::time::sleep(duration)
+        }
+
+        #[inline]
+        fn connect(addr: SocketAddr) -> impl Future<Output = io::Result<TcpStream>> + Send {
+            TcpStream::connect(addr)
+        }
+
+        #[inline]
+        fn set_nodelay(stream: &TcpStream, nodelay: bool) -> io::Result<()> {
+            stream.set_nodelay(nodelay)
+        }
+
+        async fn lookup_host(host: &str) -> io::Result<Vec<SocketAddr>> {
+            tokio::net::lookup_host(host)
+                .await
+                .map(|addrs| addrs.collect())
+        }
+    }
+}
+
+#[cfg(feature = "async-std")]
+mod async_std_runtime {
+    use async_std::net::ToSocketAddrs;
+    use std::future::Future;
+    use std::io;
+    use std::net::SocketAddr;
+    use std::pin::Pin;
+    use std::task::{ready, Context, Poll};
+    use std::time::Duration;
+    use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+
+    use super::Runtime;
+
+    pub(crate) struct AsyncStdRuntime;
+
+    impl Runtime for AsyncStdRuntime {
+        type TcpStream = TcpStream;
+
+        #[inline]
+        fn spawn(future: impl Future<Output = ()> + Send + 'static) {
+            async_std::task::spawn(future);
+        }
+
+        #[inline]
+        fn sleep(duration: Duration) -> impl Future<Output = ()> + Send {
+            async_std::task::sleep(duration)
+        }
+
+        async fn connect(addr: SocketAddr) -> io::Result<TcpStream> {
+            async_std::net::TcpStream::connect(addr)
+                .await
+                .map(TcpStream)
+        }
+
+        #[inline]
+        fn set_nodelay(stream: &TcpStream, nodelay: bool) -> io::Result<()> {
+            stream.0.set_nodelay(nodelay)
+        }
+
+        async fn lookup_host(host: &str) -> io::Result<Vec<SocketAddr>> {
+            host.to_socket_addrs().await.map(|addrs| addrs.collect())
+        }
+    }
+
+    /// async-std socket exposed through tokio I/O traits, which are used by the transports.
+    #[derive(Debug)]
+    pub(crate) struct TcpStream(async_std::net::TcpStream);
+
+    impl AsyncRead for TcpStream {
+        fn poll_read(
+            mut self: Pin<&mut Self>,
+            cx: &mut Context<'_>,
+            buf: &mut ReadBuf<'_>,
+        ) -> Poll<io::Result<()>> {
+            let read = ready!(async_std::io::Read::poll_read(
+                Pin::new(&mut self.0),
+                cx,
+                buf.initialize_unfilled()
+            ))?;
+
+            buf.advance(read);
+            Poll::Ready(Ok(()))
+        }
+    }
+
+    impl AsyncWrite for TcpStream {
+        #[inline]
+        fn poll_write(
+            mut self: Pin<&mut Self>,
+            cx: &mut Context<'_>,
+            buf: &[u8],
+        ) -> Poll<io::Result<usize>> {
+            async_std::io::Write::poll_write(Pin::new(&mut self.0), cx, buf)
+        }
+
+        #[inline]
+        fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+            async_std::io::Write::poll_flush(Pin::new(&mut self.0), cx)
+        }
+
+        #[inline]
+        fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+            async_std::io::Write::poll_close(Pin::new(&mut self.0), cx)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io;
+    use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+    use std::time::Duration;
+
+    use crate::runtime::{
+        connect, sleep, spawn, timeout, tolerate_socket_option_error, Elapsed, TaskTracker,
+    };
+
+    const ADDR: SocketAddr = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)), 9042);
+
+    fn permission_denied() -> io::Result<()> {
+        Err(io::Error::new(
+            io::ErrorKind::PermissionDenied,
+            "operation not permitted",
+        ))
+    }
+
+    #[test]
+    fn should_tolerate_socket_option_errors_unless_strict() {
+        assert!(
+            tolerate_socket_option_error(permission_denied(), "TCP_NODELAY", ADDR, false).is_ok()
+        );
+        assert_eq!(
+            tolerate_socket_option_error(permission_denied(), "TCP_NODELAY", ADDR, true)
+                .unwrap_err()
+                .kind(),
+            io::ErrorKind::PermissionDenied
+        );
+        assert!(tolerate_socket_option_error(Ok(()), "TCP_NODELAY", ADDR, true).is_ok());
+    }
+
+    #[tokio::test]
+    async fn should_connect_with_socket_options() {
+        let listener = std::net::TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        assert!(connect(addr, true, true).await.is_ok());
+        assert!(connect(addr, false, false).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn should_time_out_pending_futures() {
+        assert_eq!(
+            timeout(Duration::from_millis(10), futures::future::pending::<()>()).await,
+            Err(Elapsed)
+        );
+        assert_eq!(timeout(Duration::from_secs(1), async { 1 }).await, Ok(1));
+    }
+
+    #[tokio::test]
+    async fn should_abort_spawned_tasks() {
+        let (sender, mut receiver) = tokio::sync::mpsc::channel::<()>(1);
+        let handle = spawn(async move {
+            sleep(Duration::from_secs(10)).await;
+            let _ = sender.send(()).await;
+        });
+
+        handle.abort();
+        assert!(receiver.recv().await.is_none());
+    }
+
+    #[tokio::test]
+    async fn should_stop_tracked_tasks_on_shutdown() {
+        let tracker = TaskTracker::default();
+        let (sender, mut receiver) = tokio::sync::mpsc::channel::<()>(1);
+
+        let task_sender = sender.clone();
+        tracker.spawn(async move {
+            sleep(Duration::from_secs(10)).await;
+            let _ = task_sender.send(()).await;
+        });
+
+        tracker.shutdown();
+        assert!(tracker.is_shut_down());
+
+        tracker.spawn(async move {
+            let _ = sender.send(()).await;
+        });
+
+        assert!(receiver.recv().await.is_none());
+    }
+}
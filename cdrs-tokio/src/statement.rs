@@ -1,5 +1,11 @@
+mod keyspace_qualification;
+mod named_values;
+mod routing_analysis;
 mod statement_params;
 mod statement_params_builder;
 
+pub(crate) use keyspace_qualification::{find_unqualified_table, is_use_statement};
+pub(crate) use named_values::verify_named_values;
+pub use routing_analysis::StatementAnalysis;
 pub use statement_params::*;
 pub use statement_params_builder::*;
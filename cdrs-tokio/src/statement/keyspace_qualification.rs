@@ -0,0 +1,163 @@
+// keywords after which a table name follows, possibly after IF [NOT] EXISTS
+const TABLE_KEYWORDS: &[&str] = &["from", "into", "update", "table", "truncate"];
+const SKIPPED_KEYWORDS: &[&str] = &["if", "not", "exists", "table"];
+
+#[derive(Debug, PartialEq)]
+pub(super) enum Token<'a> {
+    Word(&'a str),
+    Quoted(&'a str),
+    Dot,
+    Symbol(char),
+    Other,
+}
+
+pub(super) fn tokenize(query: &str) -> Vec<Token<'_>> {
+    let mut tokens = vec![];
+    let mut chars = query.char_indices().peekable();
+
+    while let Some((start, c)) = chars.next() {
+        match c {
+            c if c.is_alphanumeric() || c == '_' => {
+                let mut end = start + c.len_utf8();
+                while let Some((index, c)) = chars.peek() {
+                    if !c.is_alphanumeric() && *c != '_' {
+                        break;
+                    }
+
+                    end = index + c.len_utf8();
+                    chars.next();
+                }
+
+                tokens.push(Token::Word(&query[start..end]));
+            }
+            '"' => {
+                // doubled quotes are escaped quotes inside the identifier
+                let mut end = query.len();
+                while let Some((index, c)) = chars.next() {
+                    if c == '"' {
+                        if chars.peek().map(|(_, c)| *c) == Some('"') {
+                            chars.next();
+                        } else {
+                            end = index;
+                            break;
+                        }
+                    }
+                }
+
+                tokens.push(Token::Quoted(&query[start + 1..end]));
+            }
+            '\'' => {
+                // string literals can't contain table names
+                while let Some((_, c)) = chars.next() {
+                    if c == '\'' {
+                        if chars.peek().map(|(_, c)| *c) == Some('\'') {
+                            chars.next();
+                        } else {
+                            break;
+                        }
+                    }
+                }
+
+                tokens.push(Token::Other);
+            }
+            '.' => tokens.push(Token::Dot),
+            c if c.is_whitespace() => {}
+            c => tokens.push(Token::Symbol(c)),
+        }
+    }
+
+    tokens
+}
+
+#[inline]
+pub(super) fn is_keyword(token: &Token, keywords: &[&str]) -> bool {
+    matches!(token, Token::Word(word) if keywords.iter().any(|keyword| word.eq_ignore_ascii_case(keyword)))
+}
+
+/// Looks for table references in given query, which are not qualified with a keyspace. Returns
+/// the first such table name. This is a heuristic, which only recognizes tables following `FROM`,
+/// `INTO`, `UPDATE`, `TABLE` and `TRUNCATE`.
+pub(crate) fn find_unqualified_table(query: &str) -> Option<&str> {
+    let tokens = tokenize(query);
+    let mut index = 0;
+
+    while index < tokens.len() {
+        if !is_keyword(&tokens[index], TABLE_KEYWORDS) {
+            index += 1;
+            continue;
+        }
+
+        index += 1;
+        while index < tokens.len() && is_keyword(&tokens[index], SKIPPED_KEYWORDS) {
+            index += 1;
+        }
+
+        let table = match tokens.get(index) {
+            Some(Token::Word(name)) | Some(Token::Quoted(name)) => *name,
+            _ => continue,
+        };
+
+        if tokens.get(index + 1) != Some(&Token::Dot) {
+            return Some(table);
+        }
+
+        index += 1;
+    }
+
+    None
+}
+
+/// Checks if given query is a `USE` statement, which changes the keyspace of its connection.
+pub(crate) fn is_use_statement(query: &str) -> bool {
+    tokenize(query)
+        .first()
+        .is_some_and(|token| is_keyword(token, &["use"]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{find_unqualified_table, is_use_statement};
+
+    #[test]
+    fn should_find_unqualified_tables() {
+        assert_eq!(find_unqualified_table("SELECT * FROM users"), Some("users"));
+        assert_eq!(
+            find_unqualified_table("insert into \"Users\" (id) values (?)"),
+            Some("Users")
+        );
+        assert_eq!(
+            find_unqualified_table("CREATE TABLE IF NOT EXISTS users (id int PRIMARY KEY)"),
+            Some("users")
+        );
+        assert_eq!(
+            find_unqualified_table(
+                "BEGIN BATCH INSERT INTO ks.a (id) VALUES (1); UPDATE b SET x = 1 WHERE id = 1; APPLY BATCH"
+            ),
+            Some("b")
+        );
+    }
+
+    #[test]
+    fn should_accept_qualified_tables() {
+        assert_eq!(find_unqualified_table("SELECT * FROM ks.users"), None);
+        assert_eq!(
+            find_unqualified_table("DELETE FROM \"Ks\".\"Users\" WHERE id = 'from x'"),
+            None
+        );
+        assert_eq!(find_unqualified_table("TRUNCATE TABLE ks.users"), None);
+        assert_eq!(
+            find_unqualified_table("SELECT \"from\" FROM ks.users WHERE \"into\" = ?"),
+            None
+        );
+        assert_eq!(find_unqualified_table("USE ks"), None);
+    }
+
+    #[test]
+    fn should_detect_use_statements() {
+        assert!(is_use_statement("USE ks"));
+        assert!(is_use_statement("  use \"Ks\";"));
+        assert!(!is_use_statement("SELECT * FROM ks.users WHERE use = 1"));
+        assert!(!is_use_statement("user"));
+        assert!(!is_use_statement(""));
+    }
+}
@@ -0,0 +1,285 @@
+use cassandra_protocol::query::QueryValues;
+use itertools::Itertools;
+use std::iter::Peekable;
+use std::str::CharIndices;
+
+use crate::error::{Error, Result};
+
+/// Named bind marker found in query text.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+struct NamedMarker<'a> {
+    name: &'a str,
+    // quoted names are case-sensitive
+    quoted: bool,
+}
+
+impl NamedMarker<'_> {
+    #[inline]
+    fn matches(&self, name: &str) -> bool {
+        if self.quoted {
+            self.name == name
+        } else {
+            self.name.eq_ignore_ascii_case(name)
+        }
+    }
+
+    fn to_name(self) -> String {
+        if self.quoted {
+            self.name.to_string()
+        } else {
+            self.name.to_ascii_lowercase()
+        }
+    }
+}
+
+#[inline]
+fn is_identifier_start(c: char) -> bool {
+    c.is_alphabetic() || c == '_'
+}
+
+#[inline]
+fn is_identifier_part(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+// skips characters until the closing quote, treating doubled quotes as escaped ones; returns the
+// index of the closing quote
+fn skip_quoted(chars: &mut Peekable<CharIndices>, quote: char, end: usize) -> usize {
+    while let Some((index, c)) = chars.next() {
+        if c == quote {
+            if chars.peek().map(|(_, c)| *c) == Some(quote) {
+                chars.next();
+            } else {
+                return index;
+            }
+        }
+    }
+
+    end
+}
+
+// skips characters until the given two-character terminator, e.g. the end of a block comment
+fn skip_until(chars: &mut Peekable<CharIndices>, first: char, second: char) {
+    let mut previous = None;
+    for (_, c) in chars.by_ref() {
+        if previous == Some(first) && c == second {
+            break;
+        }
+
+        previous = Some(c);
+    }
+}
+
+fn skip_identifier(chars: &mut Peekable<CharIndices>, start: usize) -> usize {
+    let mut end = start;
+    while let Some((index, c)) = chars.peek() {
+        if !is_identifier_part(*c) {
+            break;
+        }
+
+        end = index + c.len_utf8();
+        chars.next();
+    }
+
+    end
+}
+
+/// Extracts named bind markers from given query, skipping string literals, quoted identifiers
+/// and comments. A colon separating field names from values in map and UDT literals is not
+/// treated as a marker.
+fn find_named_markers(query: &str) -> Vec<NamedMarker<'_>> {
+    let mut markers = vec![];
+    let mut chars = query.char_indices().peekable();
+    let mut brace_depth = 0usize;
+    // whether the previous token can be followed by a literal separator
+    let mut after_value = false;
+
+    while let Some((start, c)) = chars.next() {
+        match c {
+            '\'' | '"' => {
+                skip_quoted(&mut chars, c, query.len());
+                after_value = true;
+            }
+            '$' if chars.peek().map(|(_, c)| *c) == Some('$') => {
+                chars.next();
+                skip_until(&mut chars, '$', '$');
+                after_value = true;
+            }
+            '-' | '/' if chars.peek().map(|(_, next)| *next) == Some(c) => {
+                while chars.next_if(|(_, c)| *c != '\n').is_some() {}
+            }
+            '/' if chars.peek().map(|(_, c)| *c) == Some('*') => {
+                chars.next();
+                skip_until(&mut chars, '*', '/');
+            }
+            ':' if !(brace_depth > 0 && after_value) => match chars.peek().copied() {
+                Some((_, '"')) => {
+                    chars.next();
+                    let end = skip_quoted(&mut chars, '"', query.len());
+                    markers.push(NamedMarker {
+                        name: &query[start + 2..end],
+                        quoted: true,
+                    });
+                    after_value = true;
+                }
+                Some((index, c)) if is_identifier_start(c) => {
+                    chars.next();
+                    let end = skip_identifier(&mut chars, index + c.len_utf8());
+                    markers.push(NamedMarker {
+                        name: &query[start + 1..end],
+                        quoted: false,
+                    });
+                    after_value = true;
+                }
+                _ => after_value = false,
+            },
+            c if is_identifier_part(c) => {
+                skip_identifier(&mut chars, start);
+                after_value = true;
+            }
+            c if c.is_whitespace() => {}
+            '{' => {
+                brace_depth += 1;
+                after_value = false;
+            }
+            '}' => {
+                brace_depth = brace_depth.saturating_sub(1);
+                after_value = true;
+            }
+            ')' | ']' => after_value = true,
+            _ => after_value = false,
+        }
+    }
+
+    markers
+}
+
+/// Checks if names of given values match named bind markers of given query. Positional values
+/// are not checked, since they can be bound to named markers.
+pub(crate) fn verify_named_values(
+    query: &str,
+    values: Option<&QueryValues>,
+    with_names: bool,
+) -> Result<()> {
+    let values = match values {
+        Some(QueryValues::NamedValues(values)) => values,
+        Some(QueryValues::SimpleValues(_)) | None => {
+            return if with_names && values.is_some_and(|values| !values.is_empty()) {
+                Err(Error::General(
+                    "Values are marked as named, but provided without names!".into(),
+                ))
+            } else {
+                Ok(())
+            };
+        }
+    };
+
+    if !with_names {
+        return Err(Error::General(
+            "Named values are provided, but not marked as named!".into(),
+        ));
+    }
+
+    let markers = find_named_markers(query);
+
+    let missing = markers
+        .iter()
+        .filter(|marker| !values.keys().any(|name| marker.matches(name)))
+        .map(|marker| marker.to_name())
+        .unique()
+        .sorted()
+        .collect_vec();
+
+    let extra = values
+        .keys()
+        .filter(|name| !markers.iter().any(|marker| marker.matches(name)))
+        .cloned()
+        .sorted()
+        .collect_vec();
+
+    if missing.is_empty() && extra.is_empty() {
+        Ok(())
+    } else {
+        Err(Error::NamedValuesMismatch { missing, extra })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use cassandra_protocol::query::QueryValues;
+    use std::collections::HashMap;
+
+    use super::{find_named_markers, verify_named_values, NamedMarker};
+    use crate::error::Error;
+
+    fn names(query: &str) -> Vec<&str> {
+        find_named_markers(query)
+            .into_iter()
+            .map(|marker| marker.name)
+            .collect()
+    }
+
+    fn named_values(names: &[&str]) -> QueryValues {
+        QueryValues::NamedValues(
+            names
+                .iter()
+                .map(|name| (name.to_string(), 1.into()))
+                .collect::<HashMap<_, _>>(),
+        )
+    }
+
+    #[test]
+    fn should_find_named_markers() {
+        assert_eq!(
+            names("SELECT * FROM ks.t WHERE id = :id AND c IN :cs LIMIT :limit"),
+            vec!["id", "cs", "limit"]
+        );
+        assert_eq!(
+            find_named_markers("INSERT INTO ks.t (id) VALUES (:\"Id\")"),
+            vec![NamedMarker {
+                name: "Id",
+                quoted: true,
+            }]
+        );
+        assert_eq!(
+            names("UPDATE ks.t USING TTL :ttl SET m = {'a': :a, :b: 1}, s = {:c} WHERE id = :id"),
+            vec!["ttl", "a", "b", "c", "id"]
+        );
+    }
+
+    #[test]
+    fn should_skip_literals_and_comments() {
+        assert_eq!(
+            names(
+                "SELECT \":x\" FROM ks.t -- :comment\n\
+                 WHERE a = ':literal' /* :block */ AND b = $$ :dollar $$ // :line\n\
+                 AND u = {f: true, g:false} AND c = :c"
+            ),
+            vec!["c"]
+        );
+    }
+
+    #[test]
+    fn should_verify_named_values() {
+        let query = "SELECT * FROM ks.t WHERE id = :id AND \"Ck\" = :\"Ck\" AND x = :id";
+
+        assert!(verify_named_values(query, Some(&named_values(&["ID", "Ck"])), true).is_ok());
+        assert!(
+            verify_named_values(query, Some(&QueryValues::SimpleValues(vec![])), false).is_ok()
+        );
+        assert!(verify_named_values(query, None, false).is_ok());
+
+        let result = verify_named_values(query, Some(&named_values(&["ck", "other"])), true);
+        assert!(
+            matches!(
+                &result,
+                Err(Error::NamedValuesMismatch { missing, extra })
+                    if missing == &["Ck", "id"] && extra == &["ck", "other"]
+            ),
+            "{:?}",
+            result
+        );
+
+        assert!(verify_named_values(query, Some(&named_values(&["id", "Ck"])), false).is_err());
+    }
+}
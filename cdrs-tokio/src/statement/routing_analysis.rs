@@ -0,0 +1,361 @@
+use std::borrow::Cow;
+
+use super::keyspace_qualification::{is_keyword, tokenize, Token};
+
+// keywords ending the WHERE clause
+const WHERE_END_KEYWORDS: &[&str] = &["if", "order", "group", "per", "limit", "allow"];
+
+/// Unprepared statement analyzed for token-aware routing. The server doesn't tell which bind
+/// markers of unprepared statements hold partition key values, so the statement text is matched
+/// against partition key columns of its table instead.
+///
+/// Only simple shapes are recognized: `INSERT` with a column list and `VALUES`, and `SELECT`,
+/// `UPDATE` and `DELETE` restricting each partition key column with `column = ?`. Anything else,
+/// e.g. batches, comments, JSON inserts, `IN` restrictions, `token()` or named bind markers, makes
+/// the analysis fail, so markers are never misidentified.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct StatementAnalysis<'a> {
+    keyspace: Option<Cow<'a, str>>,
+    table: Cow<'a, str>,
+    // columns bound to a single bind marker, along with its position
+    bound_columns: Vec<(Cow<'a, str>, usize)>,
+    // columns used in any other way, which makes them unusable for routing
+    other_columns: Vec<Cow<'a, str>>,
+}
+
+impl<'a> StatementAnalysis<'a> {
+    /// Analyzes given statement. Returns `None` if the statement is not recognized.
+    pub fn analyze(query: &'a str) -> Option<Self> {
+        let mut tokens = tokenize(query);
+        if tokens.last() == Some(&Token::Symbol(';')) {
+            tokens.pop();
+        }
+
+        if !is_supported(&tokens) {
+            return None;
+        }
+
+        let first = tokens.first()?;
+        if is_keyword(first, &["insert"]) {
+            analyze_insert(&tokens)
+        } else if is_keyword(first, &["select", "update", "delete"]) {
+            analyze_restrictions(&tokens)
+        } else {
+            None
+        }
+    }
+
+    /// Keyspace the statement is qualified with, if any.
+    #[inline]
+    pub fn keyspace(&self) -> Option<&str> {
+        self.keyspace.as_deref()
+    }
+
+    /// Table the statement refers to.
+    #[inline]
+    pub fn table(&self) -> &str {
+        &self.table
+    }
+
+    /// Returns positions of bind markers holding values of given partition key columns, in the
+    /// same order, or `None` if any of the columns is not bound to exactly one marker.
+    pub fn partition_key_markers<S: AsRef<str>>(&self, partition_key: &[S]) -> Option<Vec<usize>> {
+        if partition_key.is_empty() {
+            return None;
+        }
+
+        partition_key
+            .iter()
+            .map(|column| {
+                let column = column.as_ref();
+                if self.other_columns.iter().any(|other| other == column) {
+                    return None;
+                }
+
+                let mut markers = self
+                    .bound_columns
+                    .iter()
+                    .filter(|(bound, _)| bound == column)
+                    .map(|(_, marker)| *marker);
+
+                match (markers.next(), markers.next()) {
+                    (Some(marker), None) => Some(marker),
+                    _ => None,
+                }
+            })
+            .collect()
+    }
+}
+
+// rejects multiple statements, comments and function bodies, which the tokenizer doesn't
+// understand
+fn is_supported(tokens: &[Token]) -> bool {
+    !tokens.iter().zip(tokens.iter().skip(1)).any(|pair| {
+        matches!(
+            pair,
+            (Token::Symbol('-'), Token::Symbol('-'))
+                | (Token::Symbol('/'), Token::Symbol('/'))
+                | (Token::Symbol('/'), Token::Symbol('*'))
+        )
+    }) && !tokens
+        .iter()
+        .any(|token| matches!(token, Token::Symbol(';') | Token::Symbol('$')))
+}
+
+fn identifier<'a>(token: &Token<'a>) -> Option<Cow<'a, str>> {
+    match token {
+        Token::Word(word) if word.chars().any(|c| c.is_ascii_uppercase()) => {
+            Some(Cow::Owned(word.to_ascii_lowercase()))
+        }
+        Token::Word(word) => Some(Cow::Borrowed(word)),
+        Token::Quoted(name) if name.contains("\"\"") => {
+            Some(Cow::Owned(name.replace("\"\"", "\"")))
+        }
+        Token::Quoted(name) => Some(Cow::Borrowed(name)),
+        _ => None,
+    }
+}
+
+// returns the keyspace, table and index of the token following them
+#[allow(clippy::type_complexity)]
+fn table_at<'a>(
+    tokens: &[Token<'a>],
+    index: usize,
+) -> Option<(Option<Cow<'a, str>>, Cow<'a, str>, usize)> {
+    let name = identifier(tokens.get(index)?)?;
+    if tokens.get(index + 1) == Some(&Token::Dot) {
+        Some((Some(name), identifier(tokens.get(index + 2)?)?, index + 3))
+    } else {
+        Some((None, name, index + 1))
+    }
+}
+
+#[inline]
+fn marker_position(tokens: &[Token], index: usize) -> usize {
+    tokens[..index]
+        .iter()
+        .filter(|token| **token == Token::Symbol('?'))
+        .count()
+}
+
+// splits tokens on separators outside of parentheses, brackets and braces, stopping at an unopened
+// closing one or at a token for which `is_end` returns true - returns parts and the index of the
+// token which ended splitting
+fn split_top_level(
+    tokens: &[Token],
+    start: usize,
+    is_separator: impl Fn(&Token) -> bool,
+    is_end: impl Fn(&Token) -> bool,
+) -> (Vec<(usize, usize)>, usize) {
+    let mut parts = vec![];
+    let mut depth = 0usize;
+    let mut part_start = start;
+    let mut index = start;
+
+    while index < tokens.len() {
+        let token = &tokens[index];
+        match token {
+            Token::Symbol('(') | Token::Symbol('[') | Token::Symbol('{') => depth += 1,
+            Token::Symbol(')') | Token::Symbol(']') | Token::Symbol('}') if depth == 0 => break,
+            Token::Symbol(')') | Token::Symbol(']') | Token::Symbol('}') => depth -= 1,
+            _ if depth == 0 && is_end(token) => break,
+            _ if depth == 0 && is_separator(token) => {
+                parts.push((part_start, index));
+                part_start = index + 1;
+            }
+            _ => {}
+        }
+
+        index += 1;
+    }
+
+    parts.push((part_start, index));
+    (parts, index)
+}
+
+fn analyze_insert<'a>(tokens: &[Token<'a>]) -> Option<StatementAnalysis<'a>> {
+    if !is_keyword(tokens.get(1)?, &["into"]) {
+        return None;
+    }
+
+    let (keyspace, table, index) = table_at(tokens, 2)?;
+    if tokens.get(index) != Some(&Token::Symbol('(')) {
+        return None;
+    }
+
+    let (columns, index) = split_top_level(
+        tokens,
+        index + 1,
+        |token| *token == Token::Symbol(','),
+        |_| false,
+    );
+    let columns = columns
+        .into_iter()
+        .map(|(start, end)| match &tokens[start..end] {
+            [column] => identifier(column),
+            _ => None,
+        })
+        .collect::<Option<Vec<_>>>()?;
+
+    if tokens.get(index) != Some(&Token::Symbol(')'))
+        || !is_keyword(tokens.get(index + 1)?, &["values"])
+        || tokens.get(index + 2) != Some(&Token::Symbol('('))
+    {
+        return None;
+    }
+
+    let (values, index) = split_top_level(
+        tokens,
+        index + 3,
+        |token| *token == Token::Symbol(','),
+        |_| false,
+    );
+    if tokens.get(index) != Some(&Token::Symbol(')')) || values.len() != columns.len() {
+        return None;
+    }
+
+    let mut bound_columns = vec![];
+    let mut other_columns = vec![];
+
+    for (column, (start, end)) in columns.into_iter().zip(values) {
+        if tokens[start..end] == [Token::Symbol('?')] {
+            bound_columns.push((column, marker_position(tokens, start)));
+        } else {
+            other_columns.push(column);
+        }
+    }
+
+    Some(StatementAnalysis {
+        keyspace,
+        table,
+        bound_columns,
+        other_columns,
+    })
+}
+
+fn analyze_restrictions<'a>(tokens: &[Token<'a>]) -> Option<StatementAnalysis<'a>> {
+    let table_index = if is_keyword(&tokens[0], &["update"]) {
+        1
+    } else {
+        tokens
+            .iter()
+            .position(|token| is_keyword(token, &["from"]))?
+            + 1
+    };
+
+    let (keyspace, table, index) = table_at(tokens, table_index)?;
+    let where_index = index
+        + tokens[index..]
+            .iter()
+            .position(|token| is_keyword(token, &["where"]))?;
+
+    let (restrictions, _) = split_top_level(
+        tokens,
+        where_index + 1,
+        |token| is_keyword(token, &["and"]),
+        |token| is_keyword(token, WHERE_END_KEYWORDS),
+    );
+
+    let mut bound_columns = vec![];
+    let mut other_columns = vec![];
+
+    for (start, end) in restrictions {
+        match &tokens[start..end] {
+            [column, Token::Symbol('='), Token::Symbol('?')] if identifier(column).is_some() => {
+                bound_columns.push((identifier(column)?, marker_position(tokens, end - 1)));
+            }
+            restriction => other_columns.extend(restriction.iter().filter_map(identifier)),
+        }
+    }
+
+    Some(StatementAnalysis {
+        keyspace,
+        table,
+        bound_columns,
+        other_columns,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::StatementAnalysis;
+
+    fn markers(query: &str, partition_key: &[&str]) -> Option<Vec<usize>> {
+        StatementAnalysis::analyze(query)?.partition_key_markers(partition_key)
+    }
+
+    #[test]
+    fn should_find_insert_markers() {
+        let analysis = StatementAnalysis::analyze(
+            "INSERT INTO Ks.\"Users\" (name, \"Id\", bucket) VALUES (?, ?, ?) USING TTL ?;",
+        )
+        .unwrap();
+
+        assert_eq!(analysis.keyspace(), Some("ks"));
+        assert_eq!(analysis.table(), "Users");
+        assert_eq!(
+            analysis.partition_key_markers(&["Id", "bucket"]),
+            Some(vec![1, 2])
+        );
+        assert_eq!(analysis.partition_key_markers(&["id"]), None);
+
+        assert_eq!(
+            markers(
+                "INSERT INTO users (tags, id) VALUES ({?, 'a,b'}, ?)",
+                &["id"]
+            ),
+            Some(vec![1])
+        );
+        assert_eq!(
+            markers("INSERT INTO users (id, name) VALUES (1, ?)", &["id"]),
+            None
+        );
+        assert_eq!(markers("INSERT INTO users JSON ?", &["id"]), None);
+    }
+
+    #[test]
+    fn should_find_restriction_markers() {
+        assert_eq!(
+            markers(
+                "SELECT name FROM users WHERE ID = ? AND bucket = ? AND age > ? LIMIT ?",
+                &["id", "bucket"]
+            ),
+            Some(vec![0, 1])
+        );
+        assert_eq!(
+            markers(
+                "UPDATE users USING TTL ? SET name = ? WHERE id = ? IF name = ?",
+                &["id"]
+            ),
+            Some(vec![2])
+        );
+        assert_eq!(
+            markers("DELETE tags[?] FROM ks.users WHERE id = ?", &["id"]),
+            Some(vec![1])
+        );
+    }
+
+    #[test]
+    fn should_reject_unsupported_statements() {
+        let unsupported = [
+            "SELECT * FROM users WHERE id IN ?",
+            "SELECT * FROM users WHERE id IN (?, ?)",
+            "SELECT * FROM users WHERE token(id) = ?",
+            "SELECT * FROM users WHERE id = ? AND id = ?",
+            "SELECT * FROM users WHERE (id, bucket) = (?, ?)",
+            "SELECT * FROM users WHERE id = :id",
+            "SELECT * FROM users",
+            "SELECT * FROM users WHERE name = ? -- AND id = ?",
+            "SELECT * FROM users WHERE /* name = ? AND */ id = ?",
+            "SELECT * FROM users WHERE id = ?; SELECT * FROM users WHERE id = ?",
+            "BEGIN BATCH INSERT INTO users (id) VALUES (?); APPLY BATCH",
+            "TRUNCATE users",
+        ];
+
+        for query in unsupported {
+            assert_eq!(markers(query, &["id"]), None, "{:?}", query);
+        }
+
+        assert_eq!(markers("SELECT * FROM users WHERE id = ?", &[]), None);
+    }
+}
@@ -2,6 +2,7 @@ use cassandra_protocol::query::QueryParams;
 use cassandra_protocol::types::value::Value;
 use derivative::Derivative;
 use std::sync::Arc;
+use std::time::Duration;
 
 use crate::cluster::Murmur3Token;
 use crate::retry::RetryPolicy;
@@ -39,4 +40,11 @@ pub struct StatementParams {
     /// Enable beta protocol features. Server will respond with ERROR if protocol version is marked
     /// as beta on server and client does not provide this flag.
     pub beta_protocol: bool,
+    /// Skip checking if named values match named bind markers of a non-prepared query before
+    /// sending it. Useful for statements which the simple query text parser doesn't understand.
+    pub skip_named_values_validation: bool,
+    /// Client-side time limit for executing the statement, including retries. When it expires,
+    /// the statement fails with [`Error::Timeout`](cassandra_protocol::error::Error::Timeout),
+    /// which tells if the statement might have been applied.
+    pub timeout: Option<Duration>,
 }
@@ -4,6 +4,7 @@ use cassandra_protocol::types::value::Value;
 use cassandra_protocol::types::{CBytes, CInt, CLong};
 use derivative::Derivative;
 use std::sync::Arc;
+use std::time::Duration;
 
 use crate::cluster::Murmur3Token;
 use crate::retry::RetryPolicy;
@@ -33,6 +34,8 @@ pub struct StatementParamsBuilder {
     #[derivative(Debug = "ignore")]
     retry_policy: Option<Arc<dyn RetryPolicy + Send + Sync>>,
     beta_protocol: bool,
+    skip_named_values_validation: bool,
+    timeout: Option<Duration>,
 }
 
 impl StatementParamsBuilder {
@@ -109,7 +112,9 @@ impl StatementParamsBuilder {
         self
     }
 
-    /// Sets new keyspace.
+    /// Sets new keyspace, used for routing and sent with the statement. Sending a keyspace
+    /// requires protocol V5 - with older versions, requests fail instead of using the keyspace of
+    /// the connection.
     #[must_use]
     pub fn with_keyspace(mut self, keyspace: String) -> Self {
         self.keyspace = Some(keyspace);
@@ -161,13 +166,42 @@ impl StatementParamsBuilder {
         self
     }
 
-    /// Sets "now" in seconds.
+    /// Enables server-side tracing of the statement.
+    #[must_use]
+    pub fn with_tracing(mut self, tracing: bool) -> Self {
+        self.tracing = tracing;
+        self
+    }
+
+    /// Requests server warnings to be attached to the response.
+    #[must_use]
+    pub fn with_warnings(mut self, warnings: bool) -> Self {
+        self.warnings = warnings;
+        self
+    }
+
+    /// Disables checking if named values match named bind markers of a non-prepared query.
+    #[must_use]
+    pub fn skip_named_values_validation(mut self, value: bool) -> Self {
+        self.skip_named_values_validation = value;
+        self
+    }
+
+    /// Sets "now" in seconds. Requires protocol V5 - with older versions, requests fail.
     #[must_use]
     pub fn with_now_in_seconds(mut self, now_in_seconds: CInt) -> Self {
         self.now_in_seconds = Some(now_in_seconds);
         self
     }
 
+    /// Sets the time limit for executing the statement, including retries. If it expires, the
+    /// statement fails with a timeout error, which tells if the statement might have been applied.
+    #[must_use]
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
     #[must_use]
     pub fn build(self) -> StatementParams {
         StatementParams {
@@ -191,6 +225,8 @@ impl StatementParamsBuilder {
             speculative_execution_policy: self.speculative_execution_policy,
             retry_policy: self.retry_policy,
             beta_protocol: self.beta_protocol,
+            skip_named_values_validation: self.skip_named_values_validation,
+            timeout: self.timeout,
         }
     }
 }
@@ -10,28 +10,28 @@
 //! * [`TransportRustls`] is a transport which is used to establish SSL encrypted connection
 //!with Apache Cassandra server. **Note:** this option is available if and only if CDRS is imported
 //!with the `rust-tls` feature.
-use cassandra_protocol::compression::Compression;
+use cassandra_protocol::compression::{Compression, CompressionStats, CompressionStatsSnapshot};
 use cassandra_protocol::frame::frame_decoder::FrameDecoder;
 use cassandra_protocol::frame::frame_encoder::FrameEncoder;
 use cassandra_protocol::frame::message_result::ResultKind;
-use cassandra_protocol::frame::{Envelope, StreamId, MAX_FRAME_SIZE};
+use cassandra_protocol::frame::{Direction, Envelope, StreamId, PAYLOAD_SIZE_LIMIT};
 use cassandra_protocol::frame::{FromBytes, Opcode, EVENT_STREAM_ID};
 use cassandra_protocol::types::INT_LEN;
 use derive_more::Constructor;
+use futures::future::{join, AbortHandle};
 use futures::FutureExt;
 use fxhash::FxHashMap;
 use itertools::Itertools;
+use std::convert::TryFrom;
 use std::io;
 use std::net::SocketAddr;
-use std::sync::atomic::{AtomicBool, AtomicI16, Ordering};
-use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicU8, AtomicUsize, Ordering};
+use std::sync::{Arc, LazyLock, Mutex};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 use tokio::io::{
-    split, AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, BufReader, BufWriter, ReadHalf,
-    WriteHalf,
+    split, AsyncRead, AsyncWrite, AsyncWriteExt, BufReader, BufWriter, ReadHalf, WriteHalf,
 };
-use tokio::net::TcpStream;
 use tokio::sync::{mpsc, oneshot};
-use tokio::task::JoinHandle;
 #[cfg(feature = "rust-tls")]
 use tokio_rustls::rustls::{pki_types::ServerName, ClientConfig};
 #[cfg(feature = "rust-tls")]
@@ -42,12 +42,70 @@ use tracing::*;
 use mockall::*;
 
 use crate::cluster::KeyspaceHolder;
-use crate::envelope_parser::{convert_envelope_into_result, parse_envelope};
+use crate::envelope_parser::{convert_envelope_into_result, parse_raw_envelope};
+use crate::frame_recording::{ConnectionRecorder, FrameRecorder};
 use crate::future::BoxFuture;
+use crate::runtime;
 use crate::Error;
 use crate::Result;
 
-const INITIAL_STREAM_ID: i16 = 1;
+pub use self::read_buffer::{
+    read_buffer_memory, MAX_ADAPTIVE_READ_BUFFER_SIZE, MIN_ADAPTIVE_READ_BUFFER_SIZE,
+};
+pub use self::stream_id_pool::{StreamIdPool, INITIAL_STREAM_ID};
+pub use self::streamed_blob::{StreamedBlob, DEFAULT_BLOB_READ_TIMEOUT};
+
+use self::read_buffer::ReadBuffer;
+
+mod read_buffer;
+mod stream_id_pool;
+mod streamed_blob;
+
+// streamed blobs are written in chunks fitting a single frame
+const STREAMED_CHUNK_SIZE: usize = PAYLOAD_SIZE_LIMIT - 1;
+
+// streamed chunks read ahead of the one being written
+const STREAMED_CHUNK_QUEUE_SIZE: usize = 2;
+
+// handshake envelopes are small and later reads bypass the buffer, since they are bigger
+const HANDSHAKE_READ_BUFFER_SIZE: usize = 4096;
+
+static PROTOCOL_DESYNC_COUNT: AtomicUsize = AtomicUsize::new(0);
+
+/// Returns how many connections have been closed due to protocol desynchronization, i.e. receiving
+/// data which cannot be parsed.
+#[inline]
+pub fn protocol_desync_count() -> usize {
+    PROTOCOL_DESYNC_COUNT.load(Ordering::Relaxed)
+}
+
+static STREAM_ID_EXHAUSTION_COUNT: AtomicUsize = AtomicUsize::new(0);
+
+/// Returns how many requests have been rejected because all stream ids of their connection were in
+/// use.
+#[inline]
+pub fn stream_id_exhaustion_count() -> usize {
+    STREAM_ID_EXHAUSTION_COUNT.load(Ordering::Relaxed)
+}
+
+static ORPHAN_RESPONSE_COUNT: AtomicUsize = AtomicUsize::new(0);
+
+/// Returns how many responses have been received for stream ids without a waiting request. Such
+/// responses mean the connection is out of sync with the server, so it gets closed.
+#[inline]
+pub fn orphan_response_count() -> usize {
+    ORPHAN_RESPONSE_COUNT.load(Ordering::Relaxed)
+}
+
+static COMPRESSION_STATS: LazyLock<Arc<CompressionStats>> =
+    LazyLock::new(|| Arc::new(CompressionStats::new()));
+
+/// Returns compression statistics aggregated over all connections. Handshake envelopes are not
+/// included.
+#[inline]
+pub fn compression_stats() -> CompressionStatsSnapshot {
+    COMPRESSION_STATS.snapshot()
+}
 
 /// General CDRS transport trait.
 pub trait CdrsTransport: Send + Sync {
@@ -59,11 +117,52 @@ pub trait CdrsTransport: Send + Sync {
         handshake: bool,
     ) -> BoxFuture<'a, Result<Envelope>>;
 
+    /// Schedules data envelope for writing like [`write_envelope`](CdrsTransport::write_envelope),
+    /// and sets `written` if the envelope might have been written once the returned future
+    /// completes or is dropped. Envelopes dropped before being written are not sent at all. The
+    /// default implementation can't take envelopes back, so it sets `written` right away.
+    fn write_envelope_tracked<'a>(
+        &'a self,
+        envelope: &'a Envelope,
+        written: &'a AtomicBool,
+    ) -> BoxFuture<'a, Result<Envelope>> {
+        written.store(true, Ordering::Relaxed);
+        self.write_envelope(envelope, false)
+    }
+
+    /// Schedules data envelope for writing with given blob inserted into its body at given
+    /// offset, and waits for a response. The envelope body length is increased by the blob
+    /// length. The default implementation inserts the whole blob into a copy of the envelope,
+    /// while transports provided by the driver read it in chunks written as separate frames.
+    fn write_envelope_with_blob<'a>(
+        &'a self,
+        envelope: &'a Envelope,
+        offset: usize,
+        blob: StreamedBlob,
+    ) -> BoxFuture<'a, Result<Envelope>> {
+        async move {
+            let envelope = envelope_with_blob(envelope, offset, blob.read_to_vec().await?)?;
+            self.write_envelope(&envelope, false).await
+        }
+        .boxed()
+    }
+
     /// Checks if the connection is broken (e.g. after read or write errors).
     fn is_broken(&self) -> bool;
 
     /// Returns associated node address.
     fn address(&self) -> SocketAddr;
+
+    /// Returns how long the connection has been idle, i.e. the time since the last response has
+    /// been received. Transports which don't track activity are never considered idle.
+    fn idle_time(&self) -> Duration {
+        Duration::ZERO
+    }
+
+    /// Returns compression negotiated for the connection, if known.
+    fn compression(&self) -> Option<Compression> {
+        None
+    }
 }
 
 #[cfg(test)]
@@ -81,7 +180,45 @@ mock! {
         fn is_broken(&self) -> bool;
 
         fn address(&self) -> SocketAddr;
+
+        fn idle_time(&self) -> Duration;
+    }
+}
+
+// reads the next chunk of encoded envelope data with a blob inserted between the head and the
+// tail, fitting a single frame, or `None` when everything has been read
+async fn read_streamed_chunk(
+    head: &mut &[u8],
+    tail: &mut &[u8],
+    blob: &mut StreamedBlob,
+) -> Result<Option<Vec<u8>>> {
+    let mut chunk = Vec::with_capacity(STREAMED_CHUNK_SIZE);
+
+    let head_len = head.len().min(STREAMED_CHUNK_SIZE);
+    chunk.extend_from_slice(&head[..head_len]);
+    *head = &head[head_len..];
+
+    blob.read_into(&mut chunk, STREAMED_CHUNK_SIZE).await?;
+
+    if blob.remaining() == 0 {
+        let tail_len = tail.len().min(STREAMED_CHUNK_SIZE - chunk.len());
+        chunk.extend_from_slice(&tail[..tail_len]);
+        *tail = &tail[tail_len..];
     }
+
+    Ok(if chunk.is_empty() { None } else { Some(chunk) })
+}
+
+fn envelope_with_blob(envelope: &Envelope, offset: usize, blob: Vec<u8>) -> Result<Envelope> {
+    if offset > envelope.body.len() {
+        return Err(Error::General(format!(
+            "Blob offset {offset} exceeds envelope body length!"
+        )));
+    }
+
+    let mut envelope = envelope.clone();
+    envelope.body.splice(offset..offset, blob);
+    Ok(envelope)
 }
 
 /// Default Tcp transport.
@@ -98,25 +235,32 @@ impl TransportTcp {
         event_handler: Option<mpsc::Sender<Envelope>>,
         error_handler: Option<mpsc::Sender<Error>>,
         compression: Compression,
+        compression_threshold: usize,
         frame_encoder: Box<dyn FrameEncoder + Send + Sync>,
         frame_decoder: Box<dyn FrameDecoder + Send + Sync>,
         buffer_size: usize,
+        read_buffer_size: Option<usize>,
         tcp_nodelay: bool,
+        frame_recorder: Option<Arc<FrameRecorder>>,
     ) -> io::Result<TransportTcp> {
-        TcpStream::connect(addr).await.and_then(move |socket| {
-            socket.set_nodelay(tcp_nodelay)?;
-            Self::with_stream(
-                socket,
-                addr,
-                keyspace_holder,
-                event_handler,
-                error_handler,
-                compression,
-                frame_encoder,
-                frame_decoder,
-                buffer_size,
-            )
-        })
+        runtime::connect(addr, tcp_nodelay, true)
+            .await
+            .and_then(move |socket| {
+                Self::with_stream(
+                    socket,
+                    addr,
+                    keyspace_holder,
+                    event_handler,
+                    error_handler,
+                    compression,
+                    compression_threshold,
+                    frame_encoder,
+                    frame_decoder,
+                    buffer_size,
+                    read_buffer_size,
+                    frame_recorder,
+                )
+            })
     }
 
     #[allow(clippy::too_many_arguments)]
@@ -127,28 +271,48 @@ impl TransportTcp {
         event_handler: Option<mpsc::Sender<Envelope>>,
         error_handler: Option<mpsc::Sender<Error>>,
         compression: Compression,
+        compression_threshold: usize,
         frame_encoder: Box<dyn FrameEncoder + Send + Sync>,
         frame_decoder: Box<dyn FrameDecoder + Send + Sync>,
         buffer_size: usize,
+        read_buffer_size: Option<usize>,
+        frame_recorder: Option<Arc<FrameRecorder>>,
     ) -> io::Result<TransportTcp> {
         let (read_half, write_half) = split(stream);
         Ok(TransportTcp {
             inner: AsyncTransport::new(
                 addr,
                 compression,
+                compression_threshold,
                 frame_encoder,
                 frame_decoder,
                 buffer_size,
+                read_buffer_size,
                 read_half,
                 write_half,
                 event_handler,
                 error_handler,
                 keyspace_holder,
+                frame_recorder,
             ),
         })
     }
 }
 
+impl TransportTcp {
+    /// Returns compression statistics of this connection.
+    #[inline]
+    pub fn compression_stats(&self) -> CompressionStatsSnapshot {
+        self.inner.compression_stats()
+    }
+
+    /// Returns current size of the read buffer of this connection.
+    #[inline]
+    pub fn read_buffer_size(&self) -> usize {
+        self.inner.read_buffer_size.load(Ordering::Relaxed)
+    }
+}
+
 impl CdrsTransport for TransportTcp {
     //noinspection DuplicatedCode
     #[inline]
@@ -157,7 +321,30 @@ impl CdrsTransport for TransportTcp {
         envelope: &'a Envelope,
         handshake: bool,
     ) -> BoxFuture<'a, Result<Envelope>> {
-        self.inner.write_envelope(envelope, handshake).boxed()
+        self.inner.write_envelope(envelope, handshake, None).boxed()
+    }
+
+    #[inline]
+    fn write_envelope_tracked<'a>(
+        &'a self,
+        envelope: &'a Envelope,
+        written: &'a AtomicBool,
+    ) -> BoxFuture<'a, Result<Envelope>> {
+        self.inner
+            .write_envelope(envelope, false, Some(written))
+            .boxed()
+    }
+
+    #[inline]
+    fn write_envelope_with_blob<'a>(
+        &'a self,
+        envelope: &'a Envelope,
+        offset: usize,
+        blob: StreamedBlob,
+    ) -> BoxFuture<'a, Result<Envelope>> {
+        self.inner
+            .write_envelope_with_blob(envelope, offset, blob)
+            .boxed()
     }
 
     #[inline]
@@ -169,6 +356,16 @@ impl CdrsTransport for TransportTcp {
     fn address(&self) -> SocketAddr {
         self.inner.addr()
     }
+
+    #[inline]
+    fn idle_time(&self) -> Duration {
+        self.inner.idle_time()
+    }
+
+    #[inline]
+    fn compression(&self) -> Option<Compression> {
+        Some(self.inner.compression)
+    }
 }
 
 #[cfg(feature = "rust-tls")]
@@ -188,13 +385,15 @@ impl TransportRustls {
         event_handler: Option<mpsc::Sender<Envelope>>,
         error_handler: Option<mpsc::Sender<Error>>,
         compression: Compression,
+        compression_threshold: usize,
         frame_encoder: Box<dyn FrameEncoder + Send + Sync>,
         frame_decoder: Box<dyn FrameDecoder + Send + Sync>,
         buffer_size: usize,
+        read_buffer_size: Option<usize>,
         tcp_nodelay: bool,
+        frame_recorder: Option<Arc<FrameRecorder>>,
     ) -> io::Result<Self> {
-        let stream = TcpStream::connect(addr).await?;
-        stream.set_nodelay(tcp_nodelay)?;
+        let stream = runtime::connect(addr, tcp_nodelay, true).await?;
 
         Self::with_stream(
             stream,
@@ -205,9 +404,12 @@ impl TransportRustls {
             event_handler,
             error_handler,
             compression,
+            compression_threshold,
             frame_encoder,
             frame_decoder,
             buffer_size,
+            read_buffer_size,
+            frame_recorder,
         )
         .await
     }
@@ -222,9 +424,12 @@ impl TransportRustls {
         event_handler: Option<mpsc::Sender<Envelope>>,
         error_handler: Option<mpsc::Sender<Error>>,
         compression: Compression,
+        compression_threshold: usize,
         frame_encoder: Box<dyn FrameEncoder + Send + Sync>,
         frame_decoder: Box<dyn FrameDecoder + Send + Sync>,
         buffer_size: usize,
+        read_buffer_size: Option<usize>,
+        frame_recorder: Option<Arc<FrameRecorder>>,
     ) -> io::Result<Self> {
         let connector = RustlsConnector::from(config.clone());
         let stream = connector.connect(dns_name, stream).await?;
@@ -234,19 +439,37 @@ impl TransportRustls {
             inner: AsyncTransport::new(
                 addr,
                 compression,
+                compression_threshold,
                 frame_encoder,
                 frame_decoder,
                 buffer_size,
+                read_buffer_size,
                 read_half,
                 write_half,
                 event_handler,
                 error_handler,
                 keyspace_holder,
+                frame_recorder,
             ),
         })
     }
 }
 
+#[cfg(feature = "rust-tls")]
+impl TransportRustls {
+    /// Returns compression statistics of this connection.
+    #[inline]
+    pub fn compression_stats(&self) -> CompressionStatsSnapshot {
+        self.inner.compression_stats()
+    }
+
+    /// Returns current size of the read buffer of this connection.
+    #[inline]
+    pub fn read_buffer_size(&self) -> usize {
+        self.inner.read_buffer_size.load(Ordering::Relaxed)
+    }
+}
+
 #[cfg(feature = "rust-tls")]
 impl CdrsTransport for TransportRustls {
     //noinspection DuplicatedCode
@@ -256,7 +479,30 @@ impl CdrsTransport for TransportRustls {
         envelope: &'a Envelope,
         handshake: bool,
     ) -> BoxFuture<'a, Result<Envelope>> {
-        self.inner.write_envelope(envelope, handshake).boxed()
+        self.inner.write_envelope(envelope, handshake, None).boxed()
+    }
+
+    #[inline]
+    fn write_envelope_tracked<'a>(
+        &'a self,
+        envelope: &'a Envelope,
+        written: &'a AtomicBool,
+    ) -> BoxFuture<'a, Result<Envelope>> {
+        self.inner
+            .write_envelope(envelope, false, Some(written))
+            .boxed()
+    }
+
+    #[inline]
+    fn write_envelope_with_blob<'a>(
+        &'a self,
+        envelope: &'a Envelope,
+        offset: usize,
+        blob: StreamedBlob,
+    ) -> BoxFuture<'a, Result<Envelope>> {
+        self.inner
+            .write_envelope_with_blob(envelope, offset, blob)
+            .boxed()
     }
 
     #[inline]
@@ -268,15 +514,29 @@ impl CdrsTransport for TransportRustls {
     fn address(&self) -> SocketAddr {
         self.inner.addr()
     }
+
+    #[inline]
+    fn idle_time(&self) -> Duration {
+        self.inner.idle_time()
+    }
+
+    #[inline]
+    fn compression(&self) -> Option<Compression> {
+        Some(self.inner.compression)
+    }
 }
 
 #[derive(Debug)]
 struct AsyncTransport {
     addr: SocketAddr,
     compression: Compression,
+    compression_threshold: usize,
+    compression_stats: Arc<CompressionStats>,
+    read_buffer_size: Arc<AtomicUsize>,
     write_sender: mpsc::Sender<Request>,
     is_broken: Arc<AtomicBool>,
-    processing_handle: JoinHandle<()>,
+    last_activity_ms: AtomicU64,
+    processing_handle: AbortHandle,
 }
 
 impl Drop for AsyncTransport {
@@ -290,19 +550,32 @@ impl AsyncTransport {
     fn new<T: AsyncRead + AsyncWrite + Send + 'static>(
         addr: SocketAddr,
         compression: Compression,
-        frame_encoder: Box<dyn FrameEncoder + Send + Sync>,
-        frame_decoder: Box<dyn FrameDecoder + Send + Sync>,
+        compression_threshold: usize,
+        mut frame_encoder: Box<dyn FrameEncoder + Send + Sync>,
+        mut frame_decoder: Box<dyn FrameDecoder + Send + Sync>,
         buffer_size: usize,
+        read_buffer_size: Option<usize>,
         read_half: ReadHalf<T>,
         write_half: WriteHalf<T>,
         event_handler: Option<mpsc::Sender<Envelope>>,
         error_handler: Option<mpsc::Sender<Error>>,
         keyspace_holder: Arc<KeyspaceHolder>,
+        frame_recorder: Option<Arc<FrameRecorder>>,
     ) -> Self {
         let (write_sender, write_receiver) = mpsc::channel(buffer_size);
         let is_broken = Arc::new(AtomicBool::new(false));
 
-        let processing_handle = tokio::spawn(Self::start_processing(
+        let compression_stats = Arc::new(CompressionStats::with_parent(COMPRESSION_STATS.clone()));
+        frame_encoder.set_compression_stats(compression_stats.clone());
+        frame_decoder.set_compression_stats(compression_stats.clone());
+
+        let recorder =
+            frame_recorder.map(|recorder| ConnectionRecorder::new(recorder, compression));
+
+        let current_read_buffer_size = Arc::new(AtomicUsize::new(0));
+        let read_buffer = ReadBuffer::new(read_buffer_size, current_read_buffer_size.clone());
+
+        let processing_handle = runtime::spawn(Self::start_processing(
             write_receiver,
             event_handler,
             error_handler,
@@ -314,17 +587,40 @@ impl AsyncTransport {
             addr,
             frame_encoder,
             frame_decoder,
+            recorder,
+            read_buffer,
         ));
 
         AsyncTransport {
             addr,
             compression,
+            compression_threshold,
+            compression_stats,
+            read_buffer_size: current_read_buffer_size,
             write_sender,
             is_broken,
+            last_activity_ms: AtomicU64::new(Self::now_ms()),
             processing_handle,
         }
     }
 
+    // wall clock time is used on purpose - monotonic clocks usually stop while the system is
+    // suspended, which is exactly the case when connections go stale
+    #[inline]
+    fn now_ms() -> u64 {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|now| now.as_millis() as u64)
+            .unwrap_or_default()
+    }
+
+    #[inline]
+    fn idle_time(&self) -> Duration {
+        Duration::from_millis(
+            Self::now_ms().saturating_sub(self.last_activity_ms.load(Ordering::Relaxed)),
+        )
+    }
+
     #[inline]
     fn is_broken(&self) -> bool {
         self.is_broken.load(Ordering::Relaxed)
@@ -335,7 +631,21 @@ impl AsyncTransport {
         self.addr
     }
 
-    async fn write_envelope(&self, envelope: &Envelope, handshake: bool) -> Result<Envelope> {
+    #[inline]
+    fn compression_stats(&self) -> CompressionStatsSnapshot {
+        self.compression_stats.snapshot()
+    }
+
+    async fn write_envelope(
+        &self,
+        envelope: &Envelope,
+        handshake: bool,
+        written: Option<&AtomicBool>,
+    ) -> Result<Envelope> {
+        if self.is_broken() {
+            return Err(Error::RequestNotSent(self.addr));
+        }
+
         let (sender, receiver) = oneshot::channel();
 
         // leave stream id empty for now and generate it later
@@ -344,17 +654,131 @@ impl AsyncTransport {
         let data = if handshake {
             envelope.encode_with(Compression::None)?
         } else {
-            envelope.encode_with(self.compression)?
+            envelope.encode_with_stats(
+                self.compression,
+                self.compression_threshold,
+                Some(&self.compression_stats),
+            )?
         };
 
+        // the tracker is dropped along with this future, so it reports the request as written
+        // or cancels it, whichever happens first
+        let tracker = written.map(WriteTracker::new);
+
         self.write_sender
-            .send(Request::new(data, sender, handshake))
+            .send(Request::new(
+                data,
+                sender,
+                handshake,
+                None,
+                tracker.as_ref().map(|tracker| tracker.state.clone()),
+            ))
             .await
-            .map_err(|_| Error::General("Connection closed when writing data!".into()))?;
+            .map_err(|_| Error::RequestNotSent(self.addr))?;
+
+        self.wait_for_response(receiver).await
+    }
+
+    async fn write_envelope_with_blob(
+        &self,
+        envelope: &Envelope,
+        offset: usize,
+        mut blob: StreamedBlob,
+    ) -> Result<Envelope> {
+        if self.is_broken() {
+            return Err(Error::RequestNotSent(self.addr));
+        }
+
+        // small envelopes fit in a single frame, so there's nothing to gain from streaming
+        if envelope.body.len() + blob.len() < PAYLOAD_SIZE_LIMIT {
+            let envelope = envelope_with_blob(envelope, offset, blob.read_to_vec().await?)?;
+            return self.write_envelope(&envelope, false, None).await;
+        }
+
+        if offset > envelope.body.len() {
+            return Err(Error::General(format!(
+                "Blob offset {offset} exceeds envelope body length!"
+            )));
+        }
+
+        // the body is not available as a whole, so it can't be compressed before V5 - the
+        // compression flag is set per envelope, so it's sent uncompressed instead; since V5,
+        // frames are compressed as they are written
+        let mut data = envelope.encode_with(Compression::None)?;
+
+        let body_len =
+            i32::from_be_bytes([data[5], data[6], data[7], data[8]]) as usize + blob.len();
+        let body_len = i32::try_from(body_len).map_err(|_| {
+            Error::General(format!(
+                "Envelope body length {body_len} exceeds maximum size!"
+            ))
+        })?;
 
-        receiver
+        data[5..9].copy_from_slice(&body_len.to_be_bytes());
+
+        // flags might have added data before the body
+        let offset = data.len() - envelope.body.len() + offset;
+        let len = data.len() + blob.len();
+
+        // the first chunk starts with the envelope header, which gets the stream id - it's read
+        // before the request is queued, so a reader failing right away only fails this request
+        let (mut head, mut tail) = data.split_at(offset);
+        let first_chunk = read_streamed_chunk(&mut head, &mut tail, &mut blob)
+            .await?
+            .unwrap_or_default();
+
+        let (sender, receiver) = oneshot::channel();
+        let (chunk_sender, chunk_receiver) = mpsc::channel(STREAMED_CHUNK_QUEUE_SIZE);
+
+        self.write_sender
+            .send(Request::new(
+                first_chunk,
+                sender,
+                false,
+                Some(StreamedChunks {
+                    blob_offset: offset,
+                    len,
+                    chunks: chunk_receiver,
+                }),
+                None,
+            ))
             .await
-            .map_err(|_| Error::General("Connection closed while waiting for response!".into()))?
+            .map_err(|_| Error::RequestNotSent(self.addr))?;
+
+        // following chunks are read while previous ones are being written, so only a few of them
+        // are kept in memory at a time
+        let read_chunks = async move {
+            while let Some(chunk) = read_streamed_chunk(&mut head, &mut tail, &mut blob)
+                .await
+                .transpose()
+            {
+                let failed = chunk.is_err();
+                if chunk_sender.send(chunk).await.is_err() || failed {
+                    break;
+                }
+            }
+        };
+
+        let (_, response) = join(read_chunks, self.wait_for_response(receiver)).await;
+        response
+    }
+
+    async fn wait_for_response(
+        &self,
+        receiver: oneshot::Receiver<Result<Envelope>>,
+    ) -> Result<Envelope> {
+        // once a request gets a stream id, its handler is always notified about errors, so a
+        // dropped handler means the request was still waiting in the queue when the connection died
+        let response = receiver
+            .await
+            .map_err(|_| Error::RequestNotSent(self.addr))?;
+
+        if response.is_ok() {
+            self.last_activity_ms
+                .store(Self::now_ms(), Ordering::Relaxed);
+        }
+
+        response
     }
 
     #[allow(clippy::too_many_arguments)]
@@ -370,6 +794,8 @@ impl AsyncTransport {
         addr: SocketAddr,
         frame_encoder: Box<dyn FrameEncoder + Send + Sync>,
         frame_decoder: Box<dyn FrameDecoder + Send + Sync>,
+        recorder: Option<ConnectionRecorder>,
+        read_buffer: ReadBuffer,
     ) {
         let response_handler_map = ResponseHandlerMap::new();
 
@@ -378,22 +804,31 @@ impl AsyncTransport {
             BufWriter::new(write_half),
             &response_handler_map,
             frame_encoder,
+            recorder.as_ref(),
         );
 
         let reader = Self::start_reading_handshake_frames(
-            BufReader::with_capacity(MAX_FRAME_SIZE, read_half),
+            BufReader::with_capacity(HANDSHAKE_READ_BUFFER_SIZE, read_half),
             event_handler,
             compression,
             addr,
             keyspace_holder,
             &response_handler_map,
             frame_decoder,
+            recorder.as_ref(),
+            read_buffer,
         );
 
         let result = tokio::try_join!(writer, reader);
         if let Err(error) = result {
             error!(%error, "Transport error!");
 
+            if let Error::ProtocolDesync(_) | Error::UnexpectedFrame { .. } = error {
+                // we can't tell which response is which anymore, so the only safe option is to fail
+                // everything in flight and let the socket close
+                PROTOCOL_DESYNC_COUNT.fetch_add(1, Ordering::Relaxed);
+            }
+
             is_broken.store(true, Ordering::Relaxed);
             response_handler_map.signal_general_error(&error);
 
@@ -403,6 +838,7 @@ impl AsyncTransport {
         }
     }
 
+    #[allow(clippy::too_many_arguments)]
     async fn start_reading_handshake_frames(
         mut read_half: impl AsyncRead + Unpin,
         event_handler: Option<mpsc::Sender<Envelope>>,
@@ -411,10 +847,27 @@ impl AsyncTransport {
         keyspace_holder: Arc<KeyspaceHolder>,
         response_handler_map: &ResponseHandlerMap,
         frame_decoder: Box<dyn FrameDecoder + Send + Sync>,
+        recorder: Option<&ConnectionRecorder>,
+        read_buffer: ReadBuffer,
     ) -> Result<()> {
         // before Authenticate or Ready, envelopes are unframed
         loop {
-            let result = parse_envelope(&mut read_half, compression, addr).await;
+            let result = parse_raw_envelope(&mut read_half, compression)
+                .await
+                .and_then(|envelope| {
+                    if let Some(recorder) = recorder {
+                        recorder.record_received(&envelope);
+                    }
+
+                    // servers report unsupported versions using one they support, which is
+                    // needed to detect protocol version mismatches
+                    if envelope.opcode != Opcode::Error {
+                        response_handler_map.verify_header(&envelope)?;
+                    }
+
+                    convert_envelope_into_result(envelope, addr)
+                });
+
             match result {
                 Ok(envelope) => {
                     if envelope.stream_id >= 0 {
@@ -431,14 +884,13 @@ impl AsyncTransport {
                                 keyspace_holder,
                                 response_handler_map,
                                 frame_decoder,
+                                recorder,
+                                read_buffer,
                             )
                             .await;
                         }
                     } else if envelope.stream_id == EVENT_STREAM_ID {
-                        // server event
-                        if let Some(event_handler) = &event_handler {
-                            let _ = event_handler.send(envelope).await;
-                        }
+                        Self::forward_event(event_handler.as_ref(), envelope).await;
                     }
                 }
                 Err(error) => return Err(error),
@@ -446,6 +898,18 @@ impl AsyncTransport {
         }
     }
 
+    // only the control connection has an event handler - data connections don't register for
+    // events, so anything received there is unexpected and can be dropped
+    async fn forward_event(event_handler: Option<&mpsc::Sender<Envelope>>, envelope: Envelope) {
+        match event_handler {
+            Some(event_handler) => {
+                let _ = event_handler.send(envelope).await;
+            }
+            None => debug!("Dropping server event received on a data connection."),
+        }
+    }
+
+    #[allow(clippy::too_many_arguments)]
     async fn start_reading_normal_frames(
         mut read_half: impl AsyncRead + Unpin,
         event_handler: Option<mpsc::Sender<Envelope>>,
@@ -454,10 +918,11 @@ impl AsyncTransport {
         keyspace_holder: Arc<KeyspaceHolder>,
         response_handler_map: &ResponseHandlerMap,
         mut frame_decoder: Box<dyn FrameDecoder + Send + Sync>,
+        recorder: Option<&ConnectionRecorder>,
+        mut read_buffer: ReadBuffer,
     ) -> Result<()> {
-        let mut buffer = Vec::with_capacity(MAX_FRAME_SIZE);
         loop {
-            let num_read = read_half.read_buf(&mut buffer).await?;
+            let num_read = read_buffer.read(&mut read_half).await?;
             if num_read == 0 {
                 break Err(Error::Io(io::Error::new(
                     io::ErrorKind::UnexpectedEof,
@@ -465,13 +930,29 @@ impl AsyncTransport {
                 )));
             }
 
-            let envelopes = frame_decoder.consume(&mut buffer, compression)?;
+            let envelopes = frame_decoder
+                .consume(read_buffer.data(), compression)
+                .map_err(|error| Error::ProtocolDesync(error.to_string()))?;
             for envelope in envelopes {
+                if let Some(recorder) = recorder {
+                    recorder.record_received(&envelope);
+                }
+
+                response_handler_map.verify_header(&envelope)?;
+
                 if envelope.stream_id >= 0 {
                     // in case we get a SetKeyspace result, we need to store current keyspace
                     // checks are done manually for speed
                     if envelope.opcode == Opcode::Result {
-                        let result_kind = ResultKind::from_bytes(&envelope.body[..INT_LEN])?;
+                        let result_kind =
+                            match envelope.body.get(..INT_LEN).map(ResultKind::from_bytes) {
+                                Some(Ok(result_kind)) => result_kind,
+                                _ => {
+                                    return Err(Error::ProtocolDesync(
+                                        "Invalid result kind in response!".into(),
+                                    ))
+                                }
+                            };
                         if result_kind == ResultKind::SetKeyspace {
                             let response_body = envelope.response_body()?;
                             let set_keyspace =
@@ -491,10 +972,7 @@ impl AsyncTransport {
                         convert_envelope_into_result(envelope, addr),
                     )?;
                 } else if envelope.stream_id == EVENT_STREAM_ID {
-                    // server event
-                    if let Some(event_handler) = &event_handler {
-                        let _ = event_handler.send(envelope).await;
-                    }
+                    Self::forward_event(event_handler.as_ref(), envelope).await;
                 }
             }
         }
@@ -505,6 +983,7 @@ impl AsyncTransport {
         mut write_half: impl AsyncWrite + Unpin,
         response_handler_map: &ResponseHandlerMap,
         mut frame_encoder: Box<dyn FrameEncoder + Send + Sync>,
+        recorder: Option<&ConnectionRecorder>,
     ) -> Result<()> {
         let mut frame_stream_ids = Vec::with_capacity(1);
 
@@ -512,29 +991,29 @@ impl AsyncTransport {
             frame_stream_ids.clear();
 
             loop {
-                let stream_id = response_handler_map.next_stream_id();
-                frame_stream_ids.push(stream_id);
+                if !request.start_writing() {
+                    // the sender stopped waiting before the request got written, so it's skipped
+                } else if let Some(stream_id) = response_handler_map.next_stream_id() {
+                    frame_stream_ids.push(stream_id);
 
-                request.set_stream_id(stream_id);
-                response_handler_map.add_handler(stream_id, request.handler);
+                    request.set_stream_id(stream_id);
+                    response_handler_map.set_version_byte(request.data[0]);
 
-                if request.handshake {
-                    // handshake messages are not framed, so let's just write them directly
-                    if let Err(error) = write_half.write_all(&request.data).await {
-                        response_handler_map.send_response(stream_id, Err(error.into()))?;
-                        return Err(Error::General("Write channel failure!".into()));
+                    if let Some(recorder) = recorder {
+                        request.record(recorder);
                     }
-                } else {
-                    // post-handshake messages can be aggregated in frames by the encoder
-                    loop {
-                        if frame_encoder.can_fit(request.data.len()) {
-                            frame_encoder.add_envelope(request.data);
-                            break;
-                        }
 
-                        // flush previous frame or create a non-self-contained one
+                    response_handler_map.add_handler(stream_id, request.handler);
+
+                    if request.handshake {
+                        // handshake messages are not framed, so let's just write them directly
+                        if let Err(error) = write_half.write_all(&request.data).await {
+                            response_handler_map.send_response(stream_id, Err(error.into()))?;
+                            return Err(Error::General("Write channel failure!".into()));
+                        }
+                    } else if let Some(streamed) = request.streamed.take() {
+                        // streamed envelopes are too big to share frames with others
                         if frame_encoder.has_envelopes() {
-                            // we have some envelopes => flush current frame
                             Self::write_self_contained_frame(
                                 &mut write_half,
                                 response_handler_map,
@@ -542,31 +1021,77 @@ impl AsyncTransport {
                                 frame_encoder.as_mut(),
                             )
                             .await?;
-                        } else {
-                            // non-self-contained
-                            let data_len = request.data.len();
-                            let mut data_start = 0;
-
-                            while data_start < data_len {
-                                let (data_start_offset, frame) = frame_encoder
-                                    .finalize_non_self_contained(&request.data[data_start..]);
+                        }
 
-                                data_start += data_start_offset;
+                        Self::write_streamed_envelope(
+                            &mut write_half,
+                            response_handler_map,
+                            &mut frame_stream_ids,
+                            frame_encoder.as_mut(),
+                            &request.data,
+                            streamed,
+                            stream_id,
+                        )
+                        .await?;
+                    } else {
+                        // post-handshake messages can be aggregated in frames by the encoder
+                        loop {
+                            if frame_encoder.can_fit(request.data.len()) {
+                                frame_encoder.add_envelope(request.data);
+                                break;
+                            }
 
-                                Self::write_frame(
+                            // flush previous frame or create a non-self-contained one
+                            if frame_encoder.has_envelopes() {
+                                // we have some envelopes => flush current frame
+                                Self::write_self_contained_frame(
                                     &mut write_half,
                                     response_handler_map,
                                     &mut frame_stream_ids,
-                                    frame,
+                                    frame_encoder.as_mut(),
                                 )
                                 .await?;
+                            } else {
+                                // non-self-contained
+                                let data_len = request.data.len();
+                                let mut data_start = 0;
+
+                                while data_start < data_len {
+                                    let (data_start_offset, frame) = frame_encoder
+                                        .finalize_non_self_contained(&request.data[data_start..]);
+
+                                    data_start += data_start_offset;
+
+                                    Self::write_frame(
+                                        &mut write_half,
+                                        response_handler_map,
+                                        &mut frame_stream_ids,
+                                        frame,
+                                    )
+                                    .await?;
 
-                                frame_encoder.reset();
-                            }
+                                    frame_encoder.reset();
+                                }
 
-                            break;
+                                break;
+                            }
                         }
                     }
+                } else {
+                    STREAM_ID_EXHAUSTION_COUNT.fetch_add(1, Ordering::Relaxed);
+
+                    // abandoned requests might never get responses, so the connection is recycled
+                    // instead of waiting for their ids - the rejected request was not sent
+                    if response_handler_map.has_too_many_abandoned() {
+                        return Err(Error::General(
+                            "Stream ids exhausted by abandoned requests!".into(),
+                        ));
+                    }
+
+                    // keep the connection alive - in-flight requests will free their ids
+                    let _ = request
+                        .handler
+                        .send(Err(Error::General("No stream ids available!".into())));
                 }
 
                 request = match write_receiver.try_recv() {
@@ -618,6 +1143,88 @@ impl AsyncTransport {
         Ok(())
     }
 
+    // writes the envelope as non-self-contained frames, one per chunk, as chunks are read - a
+    // partially written envelope can't be taken back, so a failing reader breaks the connection
+    #[allow(clippy::too_many_arguments)]
+    async fn write_streamed_envelope(
+        write_half: &mut (impl AsyncWrite + Unpin),
+        response_handler_map: &ResponseHandlerMap,
+        frame_stream_ids: &mut Vec<StreamId>,
+        frame_encoder: &mut (dyn FrameEncoder + Send + Sync),
+        data: &[u8],
+        mut streamed: StreamedChunks,
+        stream_id: StreamId,
+    ) -> Result<()> {
+        Self::write_streamed_chunk(
+            write_half,
+            response_handler_map,
+            frame_stream_ids,
+            frame_encoder,
+            data,
+        )
+        .await?;
+
+        let mut written = data.len();
+        while written < streamed.len {
+            let chunk = match streamed.chunks.recv().await {
+                Some(Ok(chunk)) => chunk,
+                Some(Err(error)) => {
+                    response_handler_map.send_response(stream_id, Err(error))?;
+                    return Err(Error::General(
+                        "Streamed blob failed after being partially written!".into(),
+                    ));
+                }
+                None => {
+                    response_handler_map.send_response(
+                        stream_id,
+                        Err(Error::General("Streamed blob request cancelled!".into())),
+                    )?;
+                    return Err(Error::General(
+                        "Streamed blob cancelled after being partially written!".into(),
+                    ));
+                }
+            };
+
+            written += chunk.len();
+
+            Self::write_streamed_chunk(
+                write_half,
+                response_handler_map,
+                frame_stream_ids,
+                frame_encoder,
+                &chunk,
+            )
+            .await?;
+        }
+
+        Ok(())
+    }
+
+    async fn write_streamed_chunk(
+        write_half: &mut (impl AsyncWrite + Unpin),
+        response_handler_map: &ResponseHandlerMap,
+        frame_stream_ids: &mut Vec<StreamId>,
+        frame_encoder: &mut (dyn FrameEncoder + Send + Sync),
+        chunk: &[u8],
+    ) -> Result<()> {
+        let mut chunk_start = 0;
+        while chunk_start < chunk.len() {
+            let (chunk_start_offset, frame) =
+                frame_encoder.finalize_non_self_contained(&chunk[chunk_start..]);
+
+            chunk_start += chunk_start_offset;
+
+            if let Err(error) = write_half.write_all(frame).await {
+                Self::notify_error_handlers(response_handler_map, frame_stream_ids, error.into())?;
+                return Err(Error::General("Write channel failure!".into()));
+            }
+
+            frame_encoder.reset();
+        }
+
+        Ok(())
+    }
+
     async fn write_frame(
         write_half: &mut (impl AsyncWrite + Unpin),
         response_handler_map: &ResponseHandlerMap,
@@ -647,15 +1254,50 @@ type ResponseHandler = oneshot::Sender<Result<Envelope>>;
 
 struct ResponseHandlerMap {
     stream_handlers: Mutex<FxHashMap<StreamId, ResponseHandler>>,
-    available_stream_id: AtomicI16,
+    stream_ids: StreamIdPool,
+    // version byte of sent requests, or 0 before the first one
+    version_byte: AtomicU8,
 }
 
 impl ResponseHandlerMap {
     #[inline]
     pub fn new() -> Self {
+        Self::with_stream_ids(StreamIdPool::new().with_strict_checks(true))
+    }
+
+    fn with_stream_ids(stream_ids: StreamIdPool) -> Self {
         ResponseHandlerMap {
             stream_handlers: Default::default(),
-            available_stream_id: AtomicI16::new(INITIAL_STREAM_ID),
+            stream_ids,
+            version_byte: AtomicU8::new(0),
+        }
+    }
+
+    #[inline]
+    pub fn set_version_byte(&self, version_byte: u8) {
+        self.version_byte.store(version_byte, Ordering::Relaxed);
+    }
+
+    // all envelopes on a connection use the same protocol version, so any other version or a
+    // request direction means something in between misbehaves
+    pub fn verify_header(&self, envelope: &Envelope) -> Result<()> {
+        let version_byte = self.version_byte.load(Ordering::Relaxed);
+        if version_byte == 0 {
+            return Ok(());
+        }
+
+        let expected = version_byte | u8::from(Direction::Response);
+        let actual = u8::from(envelope.version) | u8::from(envelope.direction);
+
+        if actual == expected {
+            Ok(())
+        } else {
+            Err(Error::UnexpectedFrame {
+                expected,
+                actual,
+                opcode: envelope.opcode,
+                stream: envelope.stream_id,
+            })
         }
     }
 
@@ -670,36 +1312,91 @@ impl ResponseHandlerMap {
     pub fn send_response(&self, stream_id: StreamId, response: Result<Envelope>) -> Result<()> {
         match self.stream_handlers.lock().unwrap().remove(&stream_id) {
             Some(handler) => {
+                self.stream_ids.release(stream_id);
                 let _ = handler.send(response);
                 Ok(())
             }
-            // unmatched stream - probably a bug somewhere
-            None => Err(Error::General(format!("Unmatched stream id: {stream_id}"))),
+            // unmatched stream - either a bug somewhere or we're out of sync with the server
+            None => {
+                ORPHAN_RESPONSE_COUNT.fetch_add(1, Ordering::Relaxed);
+                Err(Error::ProtocolDesync(format!(
+                    "Unmatched stream id: {stream_id}"
+                )))
+            }
         }
     }
 
     pub fn signal_general_error(&self, error: &Error) {
-        for (_, handler) in self.stream_handlers.lock().unwrap().drain() {
+        let mut stream_handlers = self.stream_handlers.lock().unwrap();
+        for (_, handler) in stream_handlers.drain() {
             let _ = handler.send(Err(error.clone()));
         }
+
+        self.stream_ids.release_all();
     }
 
-    pub fn next_stream_id(&self) -> StreamId {
-        loop {
-            let stream = self.available_stream_id.fetch_add(1, Ordering::Relaxed);
-            if stream < 0 {
-                match self.available_stream_id.compare_exchange(
-                    stream,
-                    INITIAL_STREAM_ID,
-                    Ordering::Relaxed,
-                    Ordering::Relaxed,
-                ) {
-                    Ok(_) => return INITIAL_STREAM_ID,
-                    Err(_) => continue,
-                }
-            }
+    #[inline]
+    pub fn next_stream_id(&self) -> Option<StreamId> {
+        self.stream_ids.acquire()
+    }
 
-            return stream;
+    // ids of requests whose senders stopped waiting stay quarantined until their responses
+    // arrive, since reusing them earlier would match late responses with other requests - checks
+    // if they hold at least half of all ids
+    pub fn has_too_many_abandoned(&self) -> bool {
+        let abandoned = self
+            .stream_handlers
+            .lock()
+            .unwrap()
+            .values()
+            .filter(|handler| handler.is_closed())
+            .count();
+
+        abandoned * 2 >= self.stream_ids.capacity()
+    }
+}
+
+struct StreamedChunks {
+    // offset of the blob in the first chunk
+    blob_offset: usize,
+    // length of the whole envelope
+    len: usize,
+    // chunks following the first one, which is kept as request data, read as they're written
+    chunks: mpsc::Receiver<Result<Vec<u8>>>,
+}
+
+const REQUEST_QUEUED: u8 = 0;
+const REQUEST_WRITTEN: u8 = 1;
+const REQUEST_CANCELLED: u8 = 2;
+
+// tracks if a queued request has been written, cancelling it if dropped before
+struct WriteTracker<'a> {
+    state: Arc<AtomicU8>,
+    written: &'a AtomicBool,
+}
+
+impl<'a> WriteTracker<'a> {
+    fn new(written: &'a AtomicBool) -> Self {
+        WriteTracker {
+            state: Arc::new(AtomicU8::new(REQUEST_QUEUED)),
+            written,
+        }
+    }
+}
+
+impl Drop for WriteTracker<'_> {
+    fn drop(&mut self) {
+        if self
+            .state
+            .compare_exchange(
+                REQUEST_QUEUED,
+                REQUEST_CANCELLED,
+                Ordering::AcqRel,
+                Ordering::Acquire,
+            )
+            .is_err()
+        {
+            self.written.store(true, Ordering::Relaxed);
         }
     }
 }
@@ -709,11 +1406,851 @@ struct Request {
     data: Vec<u8>,
     handler: ResponseHandler,
     handshake: bool,
+    streamed: Option<StreamedChunks>,
+    // shared with the write tracker of the sender, if any
+    state: Option<Arc<AtomicU8>>,
 }
 
 impl Request {
+    // marks the request as written, unless the sender has cancelled it already
+    #[inline]
+    fn start_writing(&self) -> bool {
+        self.state.as_ref().is_none_or(|state| {
+            state
+                .compare_exchange(
+                    REQUEST_QUEUED,
+                    REQUEST_WRITTEN,
+                    Ordering::AcqRel,
+                    Ordering::Acquire,
+                )
+                .is_ok()
+        })
+    }
+
     #[inline]
     fn set_stream_id(&mut self, stream_d: StreamId) {
         self.data[2..4].copy_from_slice(&stream_d.to_be_bytes());
     }
+
+    // streamed blobs are split into chunks, so only data preceding them is recorded
+    fn record(&self, recorder: &ConnectionRecorder) {
+        match &self.streamed {
+            Some(streamed) => recorder.record_sent(
+                &self.data[..streamed.blob_offset.min(self.data.len())],
+                streamed.len,
+            ),
+            None => recorder.record_sent(&self.data, self.data.len()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use cassandra_protocol::compression::{Compression, CompressionMode};
+    use cassandra_protocol::crc::crc32;
+    use cassandra_protocol::frame::frame_decoder::{
+        FrameDecoder, LegacyFrameDecoder, Lz4FrameDecoder,
+    };
+    use cassandra_protocol::frame::frame_encoder::{
+        FrameEncoder, LegacyFrameEncoder, Lz4FrameEncoder,
+    };
+    use cassandra_protocol::frame::{
+        Direction, Envelope, Flags, Opcode, StreamId, Version, EVENT_STREAM_ID,
+    };
+    use std::convert::TryInto;
+    use std::io;
+    use std::net::SocketAddr;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::Arc;
+    use std::time::Duration;
+    use tokio::io::{duplex, AsyncReadExt, AsyncWriteExt, DuplexStream};
+    use tokio::sync::{mpsc, oneshot, watch};
+    use tokio::time::{sleep, timeout};
+
+    use crate::cluster::KeyspaceHolder;
+    use crate::frame_recording::{read_recording, FrameRecorder, ReplayTransport};
+    use crate::transport::{
+        orphan_response_count, protocol_desync_count, CdrsTransport, ResponseHandlerMap,
+        StreamIdPool, StreamedBlob, TransportTcp,
+    };
+    use crate::Error;
+
+    const RESPONSE_TIMEOUT: Duration = Duration::from_secs(5);
+
+    fn create_transport() -> (TransportTcp, DuplexStream, mpsc::Receiver<Error>) {
+        create_transport_with_compression(Compression::None, 0)
+    }
+
+    fn create_transport_with_compression(
+        compression: Compression,
+        compression_threshold: usize,
+    ) -> (TransportTcp, DuplexStream, mpsc::Receiver<Error>) {
+        create_transport_with_framing(
+            compression,
+            compression_threshold,
+            Box::<LegacyFrameEncoder>::default(),
+            Box::<LegacyFrameDecoder>::default(),
+        )
+    }
+
+    fn create_transport_with_framing(
+        compression: Compression,
+        compression_threshold: usize,
+        frame_encoder: Box<dyn FrameEncoder + Send + Sync>,
+        frame_decoder: Box<dyn FrameDecoder + Send + Sync>,
+    ) -> (TransportTcp, DuplexStream, mpsc::Receiver<Error>) {
+        let (client, server) = duplex(1024);
+        let (error_sender, error_receiver) = mpsc::channel(1);
+        let (keyspace_sender, _) = watch::channel(None);
+
+        let transport = TransportTcp::with_stream(
+            client,
+            SocketAddr::from(([127, 0, 0, 1], 9042)),
+            Arc::new(KeyspaceHolder::new(keyspace_sender)),
+            None,
+            Some(error_sender),
+            compression,
+            compression_threshold,
+            frame_encoder,
+            frame_decoder,
+            16,
+            None,
+            None,
+        )
+        .unwrap();
+
+        (transport, server, error_receiver)
+    }
+
+    async fn read_request_stream_id(server: &mut DuplexStream) -> StreamId {
+        let mut header = [0; 9];
+        server.read_exact(&mut header).await.unwrap();
+
+        let mut body = vec![0; i32::from_be_bytes(header[5..9].try_into().unwrap()) as usize];
+        server.read_exact(&mut body).await.unwrap();
+
+        StreamId::from_be_bytes(header[2..4].try_into().unwrap())
+    }
+
+    async fn complete_handshake(transport: &TransportTcp, server: &mut DuplexStream) {
+        let startup = Envelope::new_req_startup(None, Version::V4).unwrap();
+        let (response, _) = tokio::join!(
+            timeout(RESPONSE_TIMEOUT, transport.write_envelope(&startup, true)),
+            async {
+                let ready = Envelope::new(
+                    Version::V4,
+                    Direction::Response,
+                    Flags::empty(),
+                    Opcode::Ready,
+                    read_request_stream_id(server).await,
+                    vec![],
+                    None,
+                    vec![],
+                );
+
+                server
+                    .write_all(&ready.encode_with(Compression::None).unwrap())
+                    .await
+                    .unwrap();
+            }
+        );
+
+        assert_eq!(response.unwrap().unwrap().opcode, Opcode::Ready);
+    }
+
+    #[tokio::test]
+    async fn should_fail_handshake_on_invalid_version() {
+        let (transport, mut server, mut error_receiver) = create_transport();
+        let desync_count = protocol_desync_count();
+
+        let startup = Envelope::new_req_startup(None, Version::V4).unwrap();
+        let (response, stream_id) = tokio::join!(
+            timeout(RESPONSE_TIMEOUT, transport.write_envelope(&startup, true)),
+            async {
+                let stream_id = read_request_stream_id(&mut server).await;
+                let stream_id_bytes = stream_id.to_be_bytes();
+                server
+                    .write_all(&[
+                        0x8f,
+                        0,
+                        stream_id_bytes[0],
+                        stream_id_bytes[1],
+                        0x02,
+                        0,
+                        0,
+                        0,
+                        0,
+                    ])
+                    .await
+                    .unwrap();
+                stream_id
+            }
+        );
+
+        assert!(stream_id > 0);
+        assert!(matches!(response.unwrap(), Err(Error::ProtocolDesync(_))));
+        assert!(matches!(
+            error_receiver.recv().await,
+            Some(Error::ProtocolDesync(_))
+        ));
+        assert!(transport.is_broken());
+        assert!(protocol_desync_count() > desync_count);
+    }
+
+    #[tokio::test]
+    async fn should_fail_all_pending_requests_on_corrupted_frame() {
+        let (transport, mut server, mut error_receiver) = create_transport();
+        complete_handshake(&transport, &mut server).await;
+
+        let options = Envelope::new_req_options(Version::V4);
+        let (first, second, _) = tokio::join!(
+            timeout(RESPONSE_TIMEOUT, transport.write_envelope(&options, false)),
+            timeout(RESPONSE_TIMEOUT, transport.write_envelope(&options, false)),
+            async {
+                let stream_id = read_request_stream_id(&mut server).await;
+                read_request_stream_id(&mut server).await;
+
+                // a result envelope with a body too short to contain the result kind
+                let stream_id_bytes = stream_id.to_be_bytes();
+                server
+                    .write_all(&[
+                        0x84,
+                        0,
+                        stream_id_bytes[0],
+                        stream_id_bytes[1],
+                        0x08,
+                        0,
+                        0,
+                        0,
+                        2,
+                        0,
+                        0,
+                    ])
+                    .await
+                    .unwrap();
+            }
+        );
+
+        assert!(matches!(first.unwrap(), Err(Error::ProtocolDesync(_))));
+        assert!(matches!(second.unwrap(), Err(Error::ProtocolDesync(_))));
+        assert!(matches!(
+            error_receiver.recv().await,
+            Some(Error::ProtocolDesync(_))
+        ));
+        assert!(transport.is_broken());
+
+        // the socket should be closed
+        let mut buffer = [0; 1];
+        assert_eq!(server.read(&mut buffer).await.unwrap(), 0);
+    }
+
+    #[test]
+    fn should_detect_stream_ids_held_by_abandoned_requests() {
+        let response_handler_map =
+            ResponseHandlerMap::with_stream_ids(StreamIdPool::with_max_stream_id(4));
+
+        let mut receivers: Vec<_> = (0..4)
+            .map(|_| {
+                let (sender, receiver) = oneshot::channel();
+                response_handler_map
+                    .add_handler(response_handler_map.next_stream_id().unwrap(), sender);
+                receiver
+            })
+            .collect();
+        assert_eq!(response_handler_map.next_stream_id(), None);
+
+        receivers.truncate(3);
+        assert!(!response_handler_map.has_too_many_abandoned());
+
+        receivers.truncate(2);
+        assert!(response_handler_map.has_too_many_abandoned());
+
+        // a late response returns the quarantined id
+        response_handler_map
+            .send_response(4, Err(Error::General("late".into())))
+            .unwrap();
+        assert!(!response_handler_map.has_too_many_abandoned());
+        assert_eq!(response_handler_map.next_stream_id(), Some(4));
+    }
+
+    #[tokio::test]
+    async fn should_report_written_tracked_requests() {
+        let (transport, mut server, _error_receiver) = create_transport();
+        complete_handshake(&transport, &mut server).await;
+
+        let options = Envelope::new_req_options(Version::V4);
+        let written = AtomicBool::new(false);
+        let (response, _) = tokio::join!(
+            timeout(
+                Duration::from_millis(100),
+                transport.write_envelope_tracked(&options, &written)
+            ),
+            read_request_stream_id(&mut server)
+        );
+
+        assert!(response.is_err());
+        assert!(written.load(Ordering::Relaxed));
+    }
+
+    #[tokio::test]
+    async fn should_not_write_tracked_requests_cancelled_while_queued() {
+        let (transport, mut server, _error_receiver) = create_transport();
+        complete_handshake(&transport, &mut server).await;
+
+        // a request bigger than the connection buffer blocks the writer until the server reads it
+        let blocking = Envelope::new(
+            Version::V4,
+            Direction::Request,
+            Flags::empty(),
+            Opcode::Options,
+            0,
+            vec![0; 4096],
+            None,
+            vec![],
+        );
+        assert!(timeout(
+            Duration::from_millis(50),
+            transport.write_envelope(&blocking, false)
+        )
+        .await
+        .is_err());
+
+        let startup = Envelope::new_req_startup(None, Version::V4).unwrap();
+        let written = AtomicBool::new(false);
+        assert!(timeout(
+            Duration::from_millis(50),
+            transport.write_envelope_tracked(&startup, &written)
+        )
+        .await
+        .is_err());
+        assert!(!written.load(Ordering::Relaxed));
+
+        read_request_stream_id(&mut server).await;
+
+        let options = Envelope::new_req_options(Version::V4);
+        let (_, opcode) = tokio::join!(
+            timeout(
+                Duration::from_millis(100),
+                transport.write_envelope(&options, false)
+            ),
+            async {
+                let mut header = [0; 9];
+                server.read_exact(&mut header).await.unwrap();
+                header[4]
+            }
+        );
+
+        assert_eq!(opcode, u8::from(Opcode::Options));
+    }
+
+    #[tokio::test]
+    async fn should_drop_events_on_data_connections() {
+        let (transport, mut server, _error_receiver) = create_transport();
+        complete_handshake(&transport, &mut server).await;
+
+        let options = Envelope::new_req_options(Version::V4);
+        let (response, _) = tokio::join!(
+            timeout(RESPONSE_TIMEOUT, transport.write_envelope(&options, false)),
+            async {
+                let stream_id = read_request_stream_id(&mut server).await;
+
+                let event = Envelope::new(
+                    Version::V4,
+                    Direction::Response,
+                    Flags::empty(),
+                    Opcode::Event,
+                    EVENT_STREAM_ID,
+                    vec![],
+                    None,
+                    vec![],
+                );
+                let ready = Envelope::new(
+                    Version::V4,
+                    Direction::Response,
+                    Flags::empty(),
+                    Opcode::Ready,
+                    stream_id,
+                    vec![],
+                    None,
+                    vec![],
+                );
+
+                for envelope in &[event, ready] {
+                    server
+                        .write_all(&envelope.encode_with(Compression::None).unwrap())
+                        .await
+                        .unwrap();
+                }
+            }
+        );
+
+        assert_eq!(response.unwrap().unwrap().opcode, Opcode::Ready);
+        assert!(!transport.is_broken());
+    }
+
+    #[tokio::test]
+    async fn should_break_connection_on_unexpected_version() {
+        let (transport, mut server, mut error_receiver) = create_transport();
+        complete_handshake(&transport, &mut server).await;
+
+        let options = Envelope::new_req_options(Version::V4);
+        let (response, stream_id) = tokio::join!(
+            timeout(RESPONSE_TIMEOUT, transport.write_envelope(&options, false)),
+            async {
+                let stream_id = read_request_stream_id(&mut server).await;
+                let supported = Envelope::new(
+                    Version::V5,
+                    Direction::Response,
+                    Flags::empty(),
+                    Opcode::Supported,
+                    stream_id,
+                    vec![0, 0],
+                    None,
+                    vec![],
+                );
+
+                server
+                    .write_all(&supported.encode_with(Compression::None).unwrap())
+                    .await
+                    .unwrap();
+
+                stream_id
+            }
+        );
+
+        let error = response.unwrap().unwrap_err();
+        assert!(
+            matches!(
+                error,
+                Error::UnexpectedFrame {
+                    expected: 0x84,
+                    actual: 0x85,
+                    opcode: Opcode::Supported,
+                    stream,
+                } if stream == stream_id
+            ),
+            "{:?}",
+            error
+        );
+        assert!(matches!(
+            error_receiver.recv().await,
+            Some(Error::UnexpectedFrame { .. })
+        ));
+        assert!(transport.is_broken());
+    }
+
+    #[tokio::test]
+    async fn should_count_orphan_responses() {
+        let (transport, mut server, mut error_receiver) = create_transport();
+        complete_handshake(&transport, &mut server).await;
+        let orphan_count = orphan_response_count();
+
+        let supported = Envelope::new(
+            Version::V4,
+            Direction::Response,
+            Flags::empty(),
+            Opcode::Supported,
+            100,
+            vec![0, 0],
+            None,
+            vec![],
+        );
+
+        server
+            .write_all(&supported.encode_with(Compression::None).unwrap())
+            .await
+            .unwrap();
+
+        assert!(matches!(
+            error_receiver.recv().await,
+            Some(Error::ProtocolDesync(_))
+        ));
+        assert!(transport.is_broken());
+        assert!(orphan_response_count() > orphan_count);
+    }
+
+    #[tokio::test]
+    async fn should_reset_idle_time_on_response() {
+        let (transport, mut server, _error_receiver) = create_transport();
+
+        sleep(Duration::from_millis(50)).await;
+        assert!(transport.idle_time() >= Duration::from_millis(50));
+
+        complete_handshake(&transport, &mut server).await;
+        assert!(transport.idle_time() < Duration::from_millis(50));
+    }
+
+    #[tokio::test]
+    async fn should_not_send_requests_on_broken_connection() {
+        let (transport, server, mut error_receiver) = create_transport();
+
+        drop(server);
+        assert!(matches!(error_receiver.recv().await, Some(Error::Io(_))));
+
+        let options = Envelope::new_req_options(Version::V4);
+        let response = timeout(RESPONSE_TIMEOUT, transport.write_envelope(&options, true)).await;
+
+        assert!(matches!(response.unwrap(), Err(Error::RequestNotSent(_))));
+    }
+
+    fn blob_data(len: usize) -> Vec<u8> {
+        (0..len)
+            .map(|index| (index * 7 + index / 251) as u8)
+            .collect()
+    }
+
+    fn blob_request(version: Version) -> Envelope {
+        Envelope::new(
+            version,
+            Direction::Request,
+            Flags::empty(),
+            Opcode::Options,
+            0,
+            vec![1, 2, 3, 4],
+            None,
+            vec![],
+        )
+    }
+
+    fn blob_response(version: Version, stream_id: StreamId) -> Envelope {
+        Envelope::new(
+            version,
+            Direction::Response,
+            Flags::empty(),
+            Opcode::Ready,
+            stream_id,
+            vec![],
+            None,
+            vec![],
+        )
+    }
+
+    // writes a blob in the middle of a request and verifies its checksum on the server side
+    async fn verify_legacy_blob_request(compression: Compression, blob_len: usize) {
+        let (transport, mut server, _error_receiver) =
+            create_transport_with_compression(compression, 0);
+        complete_handshake(&transport, &mut server).await;
+
+        let data = blob_data(blob_len);
+        let checksum = crc32(&data);
+
+        let request = blob_request(Version::V4);
+        let blob = StreamedBlob::new(io::Cursor::new(data), blob_len).unwrap();
+
+        let (response, _) = tokio::join!(
+            timeout(
+                RESPONSE_TIMEOUT,
+                transport.write_envelope_with_blob(&request, 2, blob)
+            ),
+            async {
+                let mut header = [0; 9];
+                server.read_exact(&mut header).await.unwrap();
+
+                let mut body =
+                    vec![0; i32::from_be_bytes(header[5..9].try_into().unwrap()) as usize];
+                server.read_exact(&mut body).await.unwrap();
+
+                // streamed envelopes are never compressed, while small ones follow the session
+                let compressed = Flags::from_bits_truncate(header[1]).contains(Flags::COMPRESSION);
+                if compressed {
+                    body = compression.decode(body).unwrap();
+                }
+
+                assert_eq!(
+                    compressed,
+                    compression.is_compressed() && blob_len < 1 << 17
+                );
+                assert_eq!(body.len(), blob_len + 4);
+                assert_eq!(&body[..2], &[1, 2]);
+                assert_eq!(crc32(&body[2..(blob_len + 2)]), checksum);
+                assert_eq!(&body[(blob_len + 2)..], &[3, 4]);
+
+                let response = blob_response(
+                    Version::V4,
+                    StreamId::from_be_bytes(header[2..4].try_into().unwrap()),
+                );
+
+                server
+                    .write_all(&response.encode_with(Compression::None).unwrap())
+                    .await
+                    .unwrap();
+            }
+        );
+
+        assert_eq!(response.unwrap().unwrap().opcode, Opcode::Ready);
+        assert!(!transport.is_broken());
+    }
+
+    #[tokio::test]
+    async fn should_stream_blob_uncompressed() {
+        verify_legacy_blob_request(Compression::None, 1000).await;
+        verify_legacy_blob_request(Compression::None, 300_000).await;
+    }
+
+    #[tokio::test]
+    async fn should_stream_blob_uncompressed_in_compressed_session() {
+        verify_legacy_blob_request(Compression::Lz4, 1000).await;
+        verify_legacy_blob_request(Compression::Lz4, 300_000).await;
+    }
+
+    #[tokio::test]
+    async fn should_stream_blob_in_compressed_frames() {
+        let (transport, mut server, _error_receiver) = create_transport_with_framing(
+            Compression::None,
+            0,
+            Box::<Lz4FrameEncoder>::default(),
+            Box::<Lz4FrameDecoder>::default(),
+        );
+        complete_handshake(&transport, &mut server).await;
+
+        let blob_len = 300_000;
+        let data = blob_data(blob_len);
+        let checksum = crc32(&data);
+
+        let request = blob_request(Version::V5);
+        let blob = StreamedBlob::new(io::Cursor::new(data), blob_len).unwrap();
+
+        let (response, _) = tokio::join!(
+            timeout(
+                RESPONSE_TIMEOUT,
+                transport.write_envelope_with_blob(&request, 2, blob)
+            ),
+            async {
+                let mut decoder = Lz4FrameDecoder::default();
+                let mut buffer = vec![];
+                let mut frames_len = 0;
+
+                let envelope = loop {
+                    frames_len += server.read_buf(&mut buffer).await.unwrap();
+
+                    let mut envelopes = decoder.consume(&mut buffer, Compression::None).unwrap();
+                    if let Some(envelope) = envelopes.pop() {
+                        break envelope;
+                    }
+                };
+
+                // the blob is compressible, so frames should be smaller than the envelope
+                assert!(frames_len < blob_len);
+
+                let body = &envelope.body;
+                assert_eq!(body.len(), blob_len + 4);
+                assert_eq!(&body[..2], &[1, 2]);
+                assert_eq!(crc32(&body[2..(blob_len + 2)]), checksum);
+                assert_eq!(&body[(blob_len + 2)..], &[3, 4]);
+
+                let mut encoder = Lz4FrameEncoder::default();
+                encoder.add_envelope(
+                    blob_response(Version::V5, envelope.stream_id)
+                        .encode_with(Compression::None)
+                        .unwrap(),
+                );
+
+                server
+                    .write_all(encoder.finalize_self_contained())
+                    .await
+                    .unwrap();
+            }
+        );
+
+        assert_eq!(response.unwrap().unwrap().opcode, Opcode::Ready);
+        assert!(!transport.is_broken());
+    }
+
+    #[tokio::test]
+    async fn should_only_fail_request_on_short_blob() {
+        let (transport, mut server, mut error_receiver) = create_transport();
+        complete_handshake(&transport, &mut server).await;
+
+        let request = blob_request(Version::V4);
+        let blob = StreamedBlob::new(io::Cursor::new(blob_data(1000)), 300_000).unwrap();
+
+        let response = timeout(
+            RESPONSE_TIMEOUT,
+            transport.write_envelope_with_blob(&request, 2, blob),
+        )
+        .await;
+
+        assert!(matches!(response.unwrap(), Err(Error::Io(_))));
+        assert!(error_receiver.try_recv().is_err());
+        assert!(!transport.is_broken());
+
+        // the first chunk failed, so nothing has been written and the connection can still be used
+        let (response, _) = tokio::join!(
+            timeout(RESPONSE_TIMEOUT, transport.write_envelope(&request, false)),
+            async {
+                let stream_id = read_request_stream_id(&mut server).await;
+                server
+                    .write_all(
+                        &blob_response(Version::V4, stream_id)
+                            .encode_with(Compression::None)
+                            .unwrap(),
+                    )
+                    .await
+                    .unwrap();
+            }
+        );
+
+        assert_eq!(response.unwrap().unwrap().opcode, Opcode::Ready);
+        assert!(!transport.is_broken());
+    }
+
+    #[tokio::test]
+    async fn should_break_connection_on_blob_failing_mid_stream() {
+        let (transport, mut server, mut error_receiver) = create_transport();
+        complete_handshake(&transport, &mut server).await;
+
+        let request = blob_request(Version::V4);
+        let blob = StreamedBlob::new(io::Cursor::new(blob_data(200_000)), 300_000).unwrap();
+
+        let (response, _) = tokio::join!(
+            timeout(
+                RESPONSE_TIMEOUT,
+                transport.write_envelope_with_blob(&request, 2, blob)
+            ),
+            async {
+                // the first chunk has already been sent when the reader fails
+                let mut data = vec![];
+                let _ = server.read_to_end(&mut data).await;
+                assert!(!data.is_empty());
+            }
+        );
+
+        assert!(matches!(response.unwrap(), Err(Error::Io(_))));
+        assert!(error_receiver.recv().await.is_some());
+        assert!(transport.is_broken());
+    }
+
+    #[tokio::test]
+    async fn should_only_compress_responses_in_responses_only_mode() {
+        let (transport, mut server, _error_receiver) = create_transport_with_compression(
+            Compression::Lz4,
+            CompressionMode::ResponsesOnly.request_compression_threshold(0),
+        );
+        complete_handshake(&transport, &mut server).await;
+
+        let request = Envelope::new(
+            Version::V4,
+            Direction::Request,
+            Flags::empty(),
+            Opcode::Options,
+            0,
+            vec![0; 2048],
+            None,
+            vec![],
+        );
+
+        let (response, _) = tokio::join!(
+            timeout(RESPONSE_TIMEOUT, transport.write_envelope(&request, false)),
+            async {
+                let mut header = [0; 9];
+                server.read_exact(&mut header).await.unwrap();
+                assert!(!Flags::from_bits_truncate(header[1]).contains(Flags::COMPRESSION));
+
+                let mut body =
+                    vec![0; i32::from_be_bytes(header[5..9].try_into().unwrap()) as usize];
+                server.read_exact(&mut body).await.unwrap();
+                assert_eq!(body, vec![0; 2048]);
+
+                let response = Envelope::new(
+                    Version::V4,
+                    Direction::Response,
+                    Flags::empty(),
+                    Opcode::Result,
+                    StreamId::from_be_bytes(header[2..4].try_into().unwrap()),
+                    vec![0, 0, 0, 1],
+                    None,
+                    vec![],
+                );
+
+                let response = response.encode_with(Compression::Lz4).unwrap();
+                assert!(Flags::from_bits_truncate(response[1]).contains(Flags::COMPRESSION));
+                server.write_all(&response).await.unwrap();
+            }
+        );
+
+        let response = response.unwrap().unwrap();
+        assert_eq!(response.opcode, Opcode::Result);
+        assert_eq!(response.body, vec![0, 0, 0, 1]);
+    }
+
+    #[tokio::test]
+    async fn should_record_and_replay_exchanged_envelopes() {
+        let path =
+            std::env::temp_dir().join(format!("cdrs-frame-recording-{}.bin", std::process::id()));
+        let recorder = Arc::new(FrameRecorder::to_file(&path).unwrap());
+
+        let (client, mut server) = duplex(1024);
+        let (keyspace_sender, _) = watch::channel(None);
+        let transport = TransportTcp::with_stream(
+            client,
+            SocketAddr::from(([127, 0, 0, 1], 9042)),
+            Arc::new(KeyspaceHolder::new(keyspace_sender)),
+            None,
+            None,
+            Compression::None,
+            0,
+            Box::<LegacyFrameEncoder>::default(),
+            Box::<LegacyFrameDecoder>::default(),
+            16,
+            None,
+            Some(recorder.clone()),
+        )
+        .unwrap();
+
+        complete_handshake(&transport, &mut server).await;
+
+        let options = Envelope::new_req_options(Version::V4);
+        let (response, _) = tokio::join!(
+            timeout(RESPONSE_TIMEOUT, transport.write_envelope(&options, false)),
+            async {
+                let supported = Envelope::new(
+                    Version::V4,
+                    Direction::Response,
+                    Flags::empty(),
+                    Opcode::Supported,
+                    read_request_stream_id(&mut server).await,
+                    vec![0, 0],
+                    None,
+                    vec![],
+                );
+
+                server
+                    .write_all(&supported.encode_with(Compression::None).unwrap())
+                    .await
+                    .unwrap();
+            }
+        );
+
+        assert_eq!(response.unwrap().unwrap().opcode, Opcode::Supported);
+
+        recorder.flush().unwrap();
+        let frames = read_recording(std::fs::File::open(&path).unwrap()).unwrap();
+        let _ = std::fs::remove_file(&path);
+
+        assert_eq!(
+            frames
+                .iter()
+                .map(|frame| (frame.direction, frame.opcode()))
+                .collect::<Vec<_>>(),
+            vec![
+                (Direction::Request, Some(Opcode::Startup)),
+                (Direction::Response, Some(Opcode::Ready)),
+                (Direction::Request, Some(Opcode::Options)),
+                (Direction::Response, Some(Opcode::Supported)),
+            ]
+        );
+
+        let replay = ReplayTransport::new(&frames, frames[0].connection_id, transport.address());
+        let startup = Envelope::new_req_startup(None, Version::V4).unwrap();
+
+        assert_eq!(
+            replay.write_envelope(&startup, true).await.unwrap().opcode,
+            Opcode::Ready
+        );
+        assert_eq!(
+            replay.write_envelope(&options, false).await.unwrap().opcode,
+            Opcode::Supported
+        );
+        assert_eq!(replay.remaining(), 0);
+    }
 }
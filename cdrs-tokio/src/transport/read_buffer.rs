@@ -0,0 +1,244 @@
+use std::io;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::{AsyncRead, AsyncReadExt};
+
+use crate::runtime;
+
+/// Initial size of adaptive read buffers.
+pub const MIN_ADAPTIVE_READ_BUFFER_SIZE: usize = 16 * 1024;
+/// Size adaptive read buffers can grow to.
+pub const MAX_ADAPTIVE_READ_BUFFER_SIZE: usize = 1024 * 1024;
+
+// a single full read can be a coincidence, so growing waits for a few in a row
+const FULL_READS_BEFORE_GROWTH: usize = 2;
+const IDLE_TIME_BEFORE_SHRINKING: Duration = Duration::from_secs(30);
+
+static READ_BUFFER_MEMORY: AtomicUsize = AtomicUsize::new(0);
+
+/// Returns the total size of read buffers of all connections.
+#[inline]
+pub fn read_buffer_memory() -> usize {
+    READ_BUFFER_MEMORY.load(Ordering::Relaxed)
+}
+
+/// Buffer for data read from a connection. Adaptive buffers start small and double their size
+/// when reads keep filling them, since that means frames need multiple reads. They shrink back
+/// after the connection is idle for a while. Fixed buffers never change their size.
+pub(crate) struct ReadBuffer {
+    buffer: Vec<u8>,
+    // frame decoders can replace the buffer, e.g. when splitting off consumed frames, so its
+    // capacity is restored to this size before each read
+    capacity: usize,
+    adaptive: bool,
+    full_reads: usize,
+    size: Arc<AtomicUsize>,
+}
+
+impl ReadBuffer {
+    /// Creates a buffer with given fixed size or an adaptive one, reporting its current size to
+    /// given counter.
+    pub(crate) fn new(fixed_size: Option<usize>, size: Arc<AtomicUsize>) -> Self {
+        let mut buffer = ReadBuffer {
+            buffer: vec![],
+            capacity: 0,
+            adaptive: fixed_size.is_none(),
+            full_reads: 0,
+            size,
+        };
+
+        buffer.resize(fixed_size.unwrap_or(MIN_ADAPTIVE_READ_BUFFER_SIZE));
+        buffer
+    }
+
+    /// Reads available data into the buffer, which needs to be empty.
+    pub(crate) async fn read(
+        &mut self,
+        reader: &mut (impl AsyncRead + Unpin),
+    ) -> io::Result<usize> {
+        debug_assert!(self.buffer.is_empty());
+        self.buffer.reserve_exact(self.capacity);
+
+        let num_read = if self.adaptive && self.capacity() > MIN_ADAPTIVE_READ_BUFFER_SIZE {
+            match runtime::timeout(
+                IDLE_TIME_BEFORE_SHRINKING,
+                reader.read_buf(&mut self.buffer),
+            )
+            .await
+            {
+                Ok(result) => result?,
+                Err(_) => {
+                    // reading is cancel safe, so nothing is lost by starting over
+                    self.resize(MIN_ADAPTIVE_READ_BUFFER_SIZE);
+                    reader.read_buf(&mut self.buffer).await?
+                }
+            }
+        } else {
+            reader.read_buf(&mut self.buffer).await?
+        };
+
+        self.record_read(num_read);
+        Ok(num_read)
+    }
+
+    #[inline]
+    pub(crate) fn data(&mut self) -> &mut Vec<u8> {
+        &mut self.buffer
+    }
+
+    #[inline]
+    fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    fn record_read(&mut self, num_read: usize) {
+        if !self.adaptive {
+            return;
+        }
+
+        if num_read < self.capacity() {
+            self.full_reads = 0;
+            return;
+        }
+
+        self.full_reads += 1;
+        if self.full_reads >= FULL_READS_BEFORE_GROWTH
+            && self.capacity() < MAX_ADAPTIVE_READ_BUFFER_SIZE
+        {
+            self.full_reads = 0;
+            self.resize((self.capacity() * 2).min(MAX_ADAPTIVE_READ_BUFFER_SIZE));
+        }
+    }
+
+    fn resize(&mut self, size: usize) {
+        let previous_size = self.capacity;
+
+        if size < self.buffer.capacity() {
+            let mut buffer = Vec::with_capacity(size);
+            buffer.append(&mut self.buffer);
+            self.buffer = buffer;
+        } else {
+            self.buffer.reserve_exact(size - self.buffer.len());
+        }
+
+        self.capacity = size;
+        self.size.store(size, Ordering::Relaxed);
+
+        READ_BUFFER_MEMORY.fetch_add(size, Ordering::Relaxed);
+        READ_BUFFER_MEMORY.fetch_sub(previous_size, Ordering::Relaxed);
+    }
+}
+
+impl Drop for ReadBuffer {
+    fn drop(&mut self) {
+        READ_BUFFER_MEMORY.fetch_sub(self.capacity, Ordering::Relaxed);
+        self.size.store(0, Ordering::Relaxed);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use cassandra_protocol::compression::Compression;
+    use cassandra_protocol::frame::frame_decoder::{FrameDecoder, UncompressedFrameDecoder};
+    use cassandra_protocol::frame::frame_encoder::{FrameEncoder, UncompressedFrameEncoder};
+    use cassandra_protocol::frame::{Envelope, Version};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+    use tokio::io::{duplex, AsyncWriteExt};
+
+    use super::*;
+
+    #[tokio::test]
+    async fn should_grow_adaptive_buffer_when_reads_fill_it() {
+        let size = Arc::new(AtomicUsize::new(0));
+        let mut buffer = ReadBuffer::new(None, size.clone());
+        let initial_size = size.load(Ordering::Relaxed);
+        assert!(initial_size >= MIN_ADAPTIVE_READ_BUFFER_SIZE);
+
+        let (mut client, mut server) = duplex(MAX_ADAPTIVE_READ_BUFFER_SIZE * 4);
+        server
+            .write_all(&vec![0; MAX_ADAPTIVE_READ_BUFFER_SIZE * 4])
+            .await
+            .unwrap();
+        drop(server);
+
+        for _ in 0..FULL_READS_BEFORE_GROWTH {
+            buffer.read(&mut client).await.unwrap();
+            buffer.data().clear();
+        }
+
+        assert!(size.load(Ordering::Relaxed) >= initial_size * 2);
+
+        for _ in 0..32 {
+            buffer.read(&mut client).await.unwrap();
+            buffer.data().clear();
+        }
+
+        assert_eq!(size.load(Ordering::Relaxed), MAX_ADAPTIVE_READ_BUFFER_SIZE);
+    }
+
+    #[tokio::test]
+    async fn should_keep_fixed_buffer_size() {
+        let size = Arc::new(AtomicUsize::new(0));
+        let mut buffer = ReadBuffer::new(Some(1024), size.clone());
+
+        let (mut client, mut server) = duplex(8192);
+        server.write_all(&[0; 8192]).await.unwrap();
+
+        for _ in 0..FULL_READS_BEFORE_GROWTH * 2 {
+            assert_eq!(buffer.read(&mut client).await.unwrap(), 1024);
+            buffer.data().clear();
+        }
+
+        assert_eq!(size.load(Ordering::Relaxed), 1024);
+
+        drop(buffer);
+        assert_eq!(size.load(Ordering::Relaxed), 0);
+    }
+
+    #[tokio::test]
+    async fn should_keep_buffer_size_with_v5_decoder() {
+        let size = Arc::new(AtomicUsize::new(0));
+        let mut buffer = ReadBuffer::new(Some(1024), size.clone());
+        let mut decoder = UncompressedFrameDecoder::default();
+
+        let mut encoder = UncompressedFrameEncoder::default();
+        let mut data = vec![];
+        for _ in 0..1024 {
+            encoder.reset();
+            encoder.add_envelope(
+                Envelope::new_req_options(Version::V5)
+                    .encode_with(Compression::None)
+                    .unwrap(),
+            );
+            data.extend_from_slice(encoder.finalize_self_contained());
+        }
+
+        let (mut client, mut server) = duplex(data.len());
+        server.write_all(&data).await.unwrap();
+        drop(server);
+
+        let mut envelopes = 0;
+        let mut full_reads = 0;
+        loop {
+            let num_read = buffer.read(&mut client).await.unwrap();
+            if num_read == 0 {
+                break;
+            }
+
+            if num_read == 1024 {
+                full_reads += 1;
+            }
+
+            envelopes += decoder
+                .consume(buffer.data(), Compression::None)
+                .unwrap()
+                .len();
+        }
+
+        assert_eq!(envelopes, 1024);
+        assert_eq!(full_reads, data.len() / 1024);
+        assert_eq!(size.load(Ordering::Relaxed), 1024);
+    }
+}
@@ -0,0 +1,279 @@
+use cassandra_protocol::frame::StreamId;
+use std::sync::Mutex;
+
+/// First stream id handed out by a pool. Stream id 0 is not used, and negative ids are reserved for
+/// server events.
+pub const INITIAL_STREAM_ID: StreamId = 1;
+
+const WORD_BITS: usize = u64::BITS as usize;
+
+#[derive(Debug)]
+struct StreamIdPoolState {
+    in_use: Vec<u64>,
+    in_use_count: usize,
+    next: StreamId,
+    high_watermark: usize,
+}
+
+impl StreamIdPoolState {
+    #[inline]
+    fn is_in_use(&self, stream_id: StreamId) -> bool {
+        let index = stream_id as usize;
+        self.in_use[index / WORD_BITS] & (1 << (index % WORD_BITS)) != 0
+    }
+
+    #[inline]
+    fn set_in_use(&mut self, stream_id: StreamId, in_use: bool) {
+        let index = stream_id as usize;
+        if in_use {
+            self.in_use[index / WORD_BITS] |= 1 << (index % WORD_BITS);
+        } else {
+            self.in_use[index / WORD_BITS] &= !(1 << (index % WORD_BITS));
+        }
+    }
+}
+
+/// Pool of stream ids used to match responses with requests on a single connection. Ids are handed
+/// out in ascending order, skipping ones still in use, and wrap around to [`INITIAL_STREAM_ID`]
+/// after the maximum id, so allocation is deterministic for a given sequence of calls.
+///
+/// In strict mode, releasing an id which is not in use (e.g. releasing twice) fails a debug
+/// assertion.
+#[derive(Debug)]
+pub struct StreamIdPool {
+    state: Mutex<StreamIdPoolState>,
+    max_stream_id: StreamId,
+    strict: bool,
+}
+
+impl Default for StreamIdPool {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl StreamIdPool {
+    /// Creates a pool of all positive stream ids.
+    #[inline]
+    pub fn new() -> Self {
+        Self::with_max_stream_id(StreamId::MAX)
+    }
+
+    /// Creates a pool of stream ids from [`INITIAL_STREAM_ID`] up to `max_stream_id`, inclusive.
+    pub fn with_max_stream_id(max_stream_id: StreamId) -> Self {
+        assert!(
+            max_stream_id >= INITIAL_STREAM_ID,
+            "Maximum stream id must be at least {}, got {}",
+            INITIAL_STREAM_ID,
+            max_stream_id
+        );
+
+        StreamIdPool {
+            state: Mutex::new(StreamIdPoolState {
+                in_use: vec![0; max_stream_id as usize / WORD_BITS + 1],
+                in_use_count: 0,
+                next: INITIAL_STREAM_ID,
+                high_watermark: 0,
+            }),
+            max_stream_id,
+            strict: false,
+        }
+    }
+
+    /// Enables strict mode, which detects releasing ids which are not in use in debug builds.
+    #[must_use]
+    pub fn with_strict_checks(mut self, strict: bool) -> Self {
+        self.strict = strict;
+        self
+    }
+
+    /// Total number of stream ids in the pool.
+    #[inline]
+    pub fn capacity(&self) -> usize {
+        (self.max_stream_id - INITIAL_STREAM_ID) as usize + 1
+    }
+
+    /// Acquires a free stream id, or returns `None` if all are in use.
+    pub fn acquire(&self) -> Option<StreamId> {
+        let mut state = self.state.lock().unwrap();
+        if state.in_use_count == self.capacity() {
+            return None;
+        }
+
+        let mut stream_id = state.next;
+        while state.is_in_use(stream_id) {
+            stream_id = self.following(stream_id);
+        }
+
+        state.set_in_use(stream_id, true);
+        state.in_use_count += 1;
+        state.high_watermark = state.high_watermark.max(state.in_use_count);
+        state.next = self.following(stream_id);
+
+        Some(stream_id)
+    }
+
+    /// Returns given stream id to the pool. Returns `false` if the id was not in use.
+    pub fn release(&self, stream_id: StreamId) -> bool {
+        let mut state = self.state.lock().unwrap();
+
+        let in_use = (INITIAL_STREAM_ID..=self.max_stream_id).contains(&stream_id)
+            && state.is_in_use(stream_id);
+        if in_use {
+            state.set_in_use(stream_id, false);
+            state.in_use_count -= 1;
+        } else {
+            debug_assert!(
+                !self.strict,
+                "Releasing stream id {}, which is not in use!",
+                stream_id
+            );
+        }
+
+        in_use
+    }
+
+    /// Checks if given stream id is currently in use.
+    pub fn is_in_use(&self, stream_id: StreamId) -> bool {
+        (INITIAL_STREAM_ID..=self.max_stream_id).contains(&stream_id)
+            && self.state.lock().unwrap().is_in_use(stream_id)
+    }
+
+    /// Releases all stream ids, e.g. after failing all requests in flight.
+    pub fn release_all(&self) {
+        let mut state = self.state.lock().unwrap();
+        state.in_use.iter_mut().for_each(|word| *word = 0);
+        state.in_use_count = 0;
+    }
+
+    /// Number of stream ids currently in use.
+    #[inline]
+    pub fn in_use_count(&self) -> usize {
+        self.state.lock().unwrap().in_use_count
+    }
+
+    /// Highest number of stream ids in use at the same time.
+    #[inline]
+    pub fn high_watermark(&self) -> usize {
+        self.state.lock().unwrap().high_watermark
+    }
+
+    #[inline]
+    fn following(&self, stream_id: StreamId) -> StreamId {
+        if stream_id >= self.max_stream_id {
+            INITIAL_STREAM_ID
+        } else {
+            stream_id + 1
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use cassandra_protocol::frame::StreamId;
+    use std::collections::HashSet;
+    use std::sync::Arc;
+
+    use super::{StreamIdPool, INITIAL_STREAM_ID};
+
+    #[test]
+    fn should_acquire_in_order_and_wrap_around() {
+        let pool = StreamIdPool::with_max_stream_id(4);
+        assert_eq!(pool.capacity(), 4);
+
+        let acquired: Vec<_> = (0..4).map(|_| pool.acquire().unwrap()).collect();
+        assert_eq!(acquired, vec![1, 2, 3, 4]);
+        assert_eq!(pool.acquire(), None);
+
+        assert!(pool.release(2));
+        assert!(pool.release(3));
+        assert_eq!(pool.acquire(), Some(2));
+
+        // wraps around, skipping ids in use
+        assert!(pool.release(1));
+        assert_eq!(pool.acquire(), Some(3));
+        assert_eq!(pool.acquire(), Some(1));
+        assert_eq!(pool.acquire(), None);
+
+        assert_eq!(pool.in_use_count(), 4);
+        assert_eq!(pool.high_watermark(), 4);
+    }
+
+    #[test]
+    fn should_track_high_watermark() {
+        let pool = StreamIdPool::new();
+        assert_eq!(pool.capacity(), StreamId::MAX as usize);
+
+        let first = pool.acquire().unwrap();
+        let second = pool.acquire().unwrap();
+        assert_eq!(first, INITIAL_STREAM_ID);
+        assert!(pool.release(first));
+        assert!(pool.release(second));
+        pool.acquire().unwrap();
+
+        assert_eq!(pool.in_use_count(), 1);
+        assert_eq!(pool.high_watermark(), 2);
+
+        pool.release_all();
+        assert_eq!(pool.in_use_count(), 0);
+        assert!(!pool.is_in_use(3));
+    }
+
+    #[test]
+    fn should_detect_invalid_release() {
+        let pool = StreamIdPool::with_max_stream_id(4);
+
+        let stream_id = pool.acquire().unwrap();
+        assert!(pool.is_in_use(stream_id));
+        assert!(pool.release(stream_id));
+        assert!(!pool.release(stream_id));
+        assert!(!pool.release(-1));
+        assert!(!pool.release(5));
+        assert_eq!(pool.in_use_count(), 0);
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic(expected = "Releasing stream id 1, which is not in use!")]
+    fn should_fail_double_release_in_strict_mode() {
+        let pool = StreamIdPool::with_max_stream_id(4).with_strict_checks(true);
+
+        let stream_id = pool.acquire().unwrap();
+        pool.release(stream_id);
+        pool.release(stream_id);
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn should_acquire_unique_ids_concurrently() {
+        let pool = Arc::new(StreamIdPool::with_max_stream_id(1000));
+
+        let tasks: Vec<_> = (0..10)
+            .map(|_| {
+                let pool = pool.clone();
+                tokio::spawn(async move {
+                    let mut acquired = vec![];
+                    for _ in 0..100 {
+                        acquired.push(pool.acquire().unwrap());
+                        tokio::task::yield_now().await;
+                    }
+
+                    acquired
+                })
+            })
+            .collect();
+
+        let mut all = HashSet::new();
+        for task in tasks {
+            for stream_id in task.await.unwrap() {
+                assert!(
+                    all.insert(stream_id),
+                    "Stream id {} acquired twice",
+                    stream_id
+                );
+            }
+        }
+
+        assert_eq!(all.len(), 1000);
+        assert_eq!(pool.acquire(), None);
+    }
+}
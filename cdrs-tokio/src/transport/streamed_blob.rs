@@ -0,0 +1,168 @@
+use std::fmt::{Debug, Formatter};
+use std::io;
+use std::pin::Pin;
+use std::time::Duration;
+use tokio::io::{AsyncRead, AsyncReadExt};
+
+use crate::runtime::timeout;
+use crate::Error;
+use crate::Result;
+
+/// Default time to wait for each read of blob data.
+pub const DEFAULT_BLOB_READ_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Blob value read from an [`AsyncRead`] in chunks fitting a single frame, instead of being copied
+/// into a serialized request as a whole. Chunks are written as they are read, with only a few
+/// buffered at a time. A reader failing or stalling before its first chunk has been read only fails
+/// its own request - afterwards, the partially written request can't be taken back, so the
+/// connection is closed as well. The reader needs to provide at least the declared number of
+/// bytes - any remaining ones are ignored.
+pub struct StreamedBlob {
+    reader: Pin<Box<dyn AsyncRead + Send>>,
+    len: usize,
+    remaining: usize,
+    read_timeout: Duration,
+}
+
+impl Debug for StreamedBlob {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("StreamedBlob")
+            .field("len", &self.len)
+            .field("remaining", &self.remaining)
+            .field("read_timeout", &self.read_timeout)
+            .finish()
+    }
+}
+
+impl StreamedBlob {
+    /// Creates a blob of given length, read from given reader. Fails if the length exceeds the
+    /// maximum value size.
+    pub fn new<R: AsyncRead + Send + 'static>(reader: R, len: usize) -> Result<Self> {
+        if len > i32::MAX as usize {
+            return Err(Error::General(format!(
+                "Blob length {len} exceeds maximum value size!"
+            )));
+        }
+
+        Ok(StreamedBlob {
+            reader: Box::pin(reader),
+            len,
+            remaining: len,
+            read_timeout: DEFAULT_BLOB_READ_TIMEOUT,
+        })
+    }
+
+    /// Sets how long to wait for each read of blob data, before failing the request.
+    #[must_use]
+    pub fn with_read_timeout(mut self, read_timeout: Duration) -> Self {
+        self.read_timeout = read_timeout;
+        self
+    }
+
+    /// Returns the declared blob length.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Checks if the blob is empty.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Returns how many bytes still need to be read.
+    #[inline]
+    pub(crate) fn remaining(&self) -> usize {
+        self.remaining
+    }
+
+    /// Appends blob data to given buffer, until either the buffer reaches `limit` bytes or the
+    /// whole blob has been read.
+    pub(crate) async fn read_into(&mut self, buffer: &mut Vec<u8>, limit: usize) -> Result<()> {
+        while buffer.len() < limit && self.remaining > 0 {
+            let to_read = (limit - buffer.len()).min(self.remaining);
+            let read = timeout(
+                self.read_timeout,
+                (&mut self.reader).take(to_read as u64).read_buf(buffer),
+            )
+            .await
+            .map_err(|_| Error::Timeout {
+                message: format!(
+                    "Blob read stalled after {} of {} bytes!",
+                    self.len - self.remaining,
+                    self.len
+                ),
+                possibly_applied: false,
+            })??;
+
+            if read == 0 {
+                return Err(Error::Io(io::Error::new(
+                    io::ErrorKind::UnexpectedEof,
+                    format!(
+                        "Blob ended after {} of {} bytes!",
+                        self.len - self.remaining,
+                        self.len
+                    ),
+                )));
+            }
+
+            self.remaining -= read;
+        }
+
+        Ok(())
+    }
+
+    /// Reads the whole blob into memory.
+    pub(crate) async fn read_to_vec(mut self) -> Result<Vec<u8>> {
+        let mut buffer = Vec::with_capacity(self.remaining);
+        self.read_into(&mut buffer, self.remaining).await?;
+        Ok(buffer)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::transport::StreamedBlob;
+    use crate::Error;
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn should_read_declared_length() {
+        let data: &[u8] = &[1, 2, 3, 4, 5];
+        let mut blob = StreamedBlob::new(data, 4).unwrap();
+
+        let mut buffer = vec![];
+        blob.read_into(&mut buffer, 3).await.unwrap();
+        assert_eq!(buffer, vec![1, 2, 3]);
+        assert_eq!(blob.remaining(), 1);
+
+        blob.read_into(&mut buffer, 16).await.unwrap();
+        assert_eq!(buffer, vec![1, 2, 3, 4]);
+        assert_eq!(blob.remaining(), 0);
+    }
+
+    #[tokio::test]
+    async fn should_fail_on_short_reader() {
+        let data: &[u8] = &[1, 2, 3];
+        let blob = StreamedBlob::new(data, 4).unwrap();
+
+        assert!(matches!(blob.read_to_vec().await, Err(Error::Io(_))));
+    }
+
+    #[tokio::test]
+    async fn should_time_out_on_stalled_reader() {
+        let (reader, _writer) = tokio::io::duplex(16);
+        let blob = StreamedBlob::new(reader, 4)
+            .unwrap()
+            .with_read_timeout(Duration::from_millis(10));
+
+        assert!(matches!(
+            blob.read_to_vec().await,
+            Err(Error::Timeout {
+                possibly_applied: false,
+                ..
+            })
+        ));
+    }
+}
@@ -5,18 +5,25 @@ mod common;
 use cassandra_protocol::frame::Version;
 #[cfg(feature = "e2e-tests")]
 use cdrs_tokio::consistency::Consistency;
+use cdrs_tokio::error::Error;
+use cdrs_tokio::frame::message_result::{
+    CUdt, ColSpec, ColType, ColTypeOption, ColTypeOptionValue,
+};
 #[cfg(feature = "e2e-tests")]
 use cdrs_tokio::frame::TryFromRow;
-#[cfg(feature = "e2e-tests")]
-use cdrs_tokio::query::QueryValues;
+use cdrs_tokio::frame::Version as ProtocolVersion;
+use cdrs_tokio::helpers::RowValues;
+use cdrs_tokio::query::{convert_bound_values, QueryValues};
 #[cfg(feature = "e2e-tests")]
 use cdrs_tokio::query_values;
 #[cfg(feature = "e2e-tests")]
 use cdrs_tokio::statement::StatementParamsBuilder;
 #[cfg(feature = "e2e-tests")]
 use cdrs_tokio::types::blob::Blob;
-#[cfg(feature = "e2e-tests")]
+use cdrs_tokio::types::udt_value::UdtValue;
+use cdrs_tokio::types::value::Value;
 use cdrs_tokio::IntoCdrsValue;
+use cdrs_tokio::IntoQueryValues;
 #[cfg(feature = "e2e-tests")]
 use cdrs_tokio::{TryFromRow, TryFromUdt};
 #[cfg(feature = "e2e-tests")]
@@ -32,6 +39,182 @@ use time::PrimitiveDateTime;
 #[cfg(feature = "e2e-tests")]
 use uuid::Uuid;
 
+#[derive(IntoQueryValues)]
+struct BoundRow {
+    id: i64,
+    #[cdrs(rename = "value")]
+    text: String,
+    score: Option<f64>,
+    #[cdrs(none = "unset")]
+    tag: Option<String>,
+    #[cdrs(skip)]
+    #[allow(dead_code)]
+    cached: bool,
+}
+
+fn col_spec(name: &str, id: ColType) -> ColSpec {
+    ColSpec {
+        table_spec: None,
+        name: name.into(),
+        col_type: ColTypeOption { id, value: None },
+    }
+}
+
+fn bound_row_col_specs() -> Vec<ColSpec> {
+    vec![
+        col_spec("id", ColType::Int),
+        col_spec("value", ColType::Varchar),
+        col_spec("score", ColType::Float),
+        col_spec("tag", ColType::Varchar),
+    ]
+}
+
+#[test]
+fn derived_values_against_prepared_metadata() {
+    let values: QueryValues = BoundRow {
+        id: 5,
+        text: "text".into(),
+        score: None,
+        tag: None,
+        cached: true,
+    }
+    .into();
+
+    let values = convert_bound_values(&values, &bound_row_col_specs())
+        .expect("convert values")
+        .into_owned();
+
+    match values {
+        QueryValues::NamedValues(values) => {
+            assert_eq!(values.len(), 4);
+            assert_eq!(values["id"], Value::from(5i32));
+            assert_eq!(values["value"], Value::from("text"));
+            assert_eq!(values["score"], Value::Null);
+            assert_eq!(values["tag"], Value::NotSet);
+        }
+        QueryValues::SimpleValues(_) => panic!("expected named values"),
+    }
+}
+
+#[test]
+fn derived_row_values() {
+    let row = BoundRow {
+        id: 5,
+        text: "text".into(),
+        score: Some(0.5),
+        tag: None,
+        cached: true,
+    };
+
+    assert_eq!(BoundRow::columns(), &["id", "value", "score", "tag"]);
+    assert_eq!(
+        row.row_values(),
+        vec![
+            Value::from(5i64),
+            Value::from("text"),
+            Value::from(0.5f64),
+            Value::NotSet
+        ]
+    );
+}
+
+#[test]
+fn derived_values_out_of_range() {
+    let values: QueryValues = BoundRow {
+        id: i64::MAX,
+        text: "text".into(),
+        score: Some(0.5),
+        tag: Some("tag".into()),
+        cached: false,
+    }
+    .into();
+
+    assert!(matches!(
+        convert_bound_values(&values, &bound_row_col_specs()),
+        Err(Error::InvalidBoundValue { name, .. }) if name == "id"
+    ));
+}
+
+#[derive(Clone, IntoCdrsValue)]
+struct Address {
+    number: i32,
+    street: String,
+}
+
+#[derive(Clone, IntoCdrsValue)]
+struct Person {
+    #[cdrs(udt)]
+    home: Address,
+    #[cdrs(rename = "name")]
+    full_name: Option<String>,
+}
+
+fn udt_type(name: &str, descriptions: Vec<(String, ColTypeOption)>) -> ColTypeOption {
+    ColTypeOption {
+        id: ColType::Udt,
+        value: Some(ColTypeOptionValue::UdtType(CUdt {
+            ks: "ks".into(),
+            udt_name: name.into(),
+            descriptions,
+        })),
+    }
+}
+
+fn udt_field(name: &str, id: ColType) -> (String, ColTypeOption) {
+    (name.into(), ColTypeOption { id, value: None })
+}
+
+#[test]
+fn derived_udt_values_against_prepared_metadata() {
+    // declared in a different order than the struct fields
+    let person_type = udt_type(
+        "person",
+        vec![
+            udt_field("name", ColType::Varchar),
+            (
+                "home".into(),
+                udt_type(
+                    "address",
+                    vec![
+                        udt_field("street", ColType::Varchar),
+                        udt_field("number", ColType::Int),
+                    ],
+                ),
+            ),
+        ],
+    );
+    let col_spec = ColSpec {
+        table_spec: None,
+        name: "person".into(),
+        col_type: person_type,
+    };
+
+    let person = Person {
+        home: Address {
+            number: 1,
+            street: "A".into(),
+        },
+        full_name: None,
+    };
+
+    assert_eq!(
+        UdtValue::from(person.clone())
+            .bind(&col_spec, ProtocolVersion::V4)
+            .unwrap(),
+        Value::Some(vec![
+            255, 255, 255, 255, // name
+            0, 0, 0, 13, // home
+            0, 0, 0, 1, b'A', 0, 0, 0, 4, 0, 0, 0, 1,
+        ])
+    );
+
+    let extra_field = UdtValue::from(person).with_field("age", 30);
+    assert!(matches!(
+        extra_field.bind(&col_spec, ProtocolVersion::V4),
+        Err(Error::InvalidBoundValue { name, .. }) if name == "person.age"
+    ));
+}
+
 #[tokio::test]
 #[cfg(feature = "e2e-tests")]
 async fn simple_udt_v4() {
@@ -3,6 +3,8 @@ use std::collections::HashMap;
 #[cfg(feature = "e2e-tests")]
 use std::sync::Arc;
 
+#[cfg(feature = "e2e-tests")]
+use cassandra_protocol::frame::Version;
 #[cfg(feature = "e2e-tests")]
 use cdrs_tokio::authenticators::NoneAuthenticatorProvider;
 #[cfg(feature = "e2e-tests")]
@@ -226,3 +228,56 @@ async fn drop_keyspace() {
     let keyspace_dropped = session.query(drop_query).await.is_ok();
     assert!(keyspace_dropped, "Should drop new keyspace without errors");
 }
+
+#[tokio::test]
+#[cfg(feature = "e2e-tests")]
+async fn keyspace_scoped_session() {
+    let cluster_config = NodeTcpConfigBuilder::new()
+        .with_contact_point("127.0.0.1:9042".into())
+        .with_authenticator_provider(Arc::new(NoneAuthenticatorProvider))
+        .with_version(Version::V5)
+        .build()
+        .await
+        .unwrap();
+    let lb = RoundRobinLoadBalancingStrategy::new();
+    let session = TcpSessionBuilder::new(lb, cluster_config)
+        .with_reconnection_policy(Arc::new(NeverReconnectionPolicy))
+        .build()
+        .await
+        .unwrap();
+
+    let system = session.with_keyspace("system").expect("keyspace session");
+    assert_eq!(system.keyspace(), "system");
+
+    let rows = system
+        .query("SELECT key FROM local")
+        .await
+        .expect("query in keyspace")
+        .response_body()
+        .expect("get body")
+        .into_rows()
+        .expect("into rows");
+    assert_eq!(rows.len(), 1);
+
+    let prepared = system
+        .prepare("SELECT key FROM local")
+        .await
+        .expect("prepare in keyspace");
+    let cached = session
+        .with_keyspace("system")
+        .expect("keyspace session")
+        .prepare("SELECT key FROM local")
+        .await
+        .expect("prepare in keyspace");
+    assert_eq!(prepared.id, cached.id);
+
+    system.exec(&prepared).await.expect("exec in keyspace");
+
+    // the same query in another keyspace is a different statement
+    assert!(session
+        .with_keyspace("system_schema")
+        .expect("keyspace session")
+        .prepare("SELECT key FROM local")
+        .await
+        .is_err());
+}
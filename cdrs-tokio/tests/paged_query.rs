@@ -55,17 +55,19 @@ async fn paged_query() {
     // This returns always false the first time
     assert!(!query_pager.has_more());
 
-    let rows = query_pager.next().await.expect("pager next");
-    assert_eq!(3, rows.len());
+    let page = query_pager.next_page().await.expect("pager next");
+    assert_eq!(3, page.row_count());
     assert!(query_pager.has_more());
-    let rows = query_pager.next().await.expect("pager next");
-    assert_eq!(3, rows.len());
+    let page = query_pager.next_page().await.expect("pager next");
+    assert_eq!(3, page.row_count());
     assert!(query_pager.has_more());
-    let rows = query_pager.next().await.expect("pager next");
-    assert_eq!(3, rows.len());
+    let page = query_pager.next_page().await.expect("pager next");
+    assert_eq!(3, page.row_count());
     assert!(query_pager.has_more());
-    let rows = query_pager.next().await.expect("pager next");
-    assert_eq!(1, rows.len());
+    let page = query_pager.next_page().await.expect("pager next");
+    assert_eq!(1, page.row_count());
+    assert_eq!(10, page.total_row_count());
+    assert!(!page.has_more());
 
     assert!(!query_pager.has_more());
 }
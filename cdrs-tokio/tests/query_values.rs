@@ -2,13 +2,86 @@ mod common;
 
 #[cfg(feature = "e2e-tests")]
 use cassandra_protocol::frame::Version;
-#[cfg(feature = "e2e-tests")]
 use cdrs_tokio::query_values;
 #[cfg(feature = "e2e-tests")]
 use cdrs_tokio::types::IntoRustByName;
 #[cfg(feature = "e2e-tests")]
 use common::*;
 
+use cdrs_tokio::query::QueryValues;
+use cdrs_tokio::types::blob::Blob;
+use cdrs_tokio::types::value::Value;
+use std::net::{IpAddr, Ipv4Addr};
+use uuid::Uuid;
+
+#[test]
+fn query_values_accept_borrowed_and_optional_values() {
+    let text = "text".to_string();
+    let bytes = vec![1u8, 2, 3];
+    let blob = Blob::new(bytes.clone());
+    let uuid = Uuid::nil();
+    let ip = IpAddr::V4(Ipv4Addr::LOCALHOST);
+    let missing: Option<String> = None;
+    let present = Some(42i64);
+
+    let borrowed = query_values!(
+        text.as_str(),
+        &text,
+        bytes.as_slice(),
+        &blob,
+        &uuid,
+        &ip,
+        &1i32,
+        &true,
+        &2.5f64,
+        Some(text.as_str()),
+        None::<&str>,
+        Some(&uuid),
+        &missing,
+        &present,
+    );
+
+    let owned = query_values!(
+        text.clone(),
+        text.clone(),
+        blob.clone(),
+        blob,
+        uuid,
+        ip,
+        1i32,
+        true,
+        2.5f64,
+        text.clone(),
+        Value::Null,
+        uuid,
+        Value::Null,
+        42i64
+    );
+
+    assert_eq!(borrowed, owned);
+}
+
+#[test]
+fn named_query_values_accept_borrowed_and_optional_values() {
+    let text = "text".to_string();
+    let missing: Option<Uuid> = None;
+
+    let values = query_values!(
+        "text" => &text,
+        "missing" => &missing,
+        "slice" => &b"abc"[..],
+    );
+
+    match values {
+        QueryValues::NamedValues(values) => {
+            assert_eq!(values["text"], Value::from(text));
+            assert_eq!(values["missing"], Value::Null);
+            assert_eq!(values["slice"], Value::Some(b"abc".to_vec()));
+        }
+        values => panic!("Unexpected values: {:?}", values),
+    }
+}
+
 #[tokio::test]
 #[cfg(feature = "e2e-tests")]
 async fn query_values_in_v4() {
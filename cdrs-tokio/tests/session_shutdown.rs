@@ -0,0 +1,89 @@
+#[cfg(feature = "e2e-tests")]
+use cdrs_tokio::authenticators::NoneAuthenticatorProvider;
+#[cfg(feature = "e2e-tests")]
+use cdrs_tokio::cluster::session::{SessionBuilder, TcpSessionBuilder};
+#[cfg(feature = "e2e-tests")]
+use cdrs_tokio::cluster::NodeTcpConfigBuilder;
+#[cfg(feature = "e2e-tests")]
+use cdrs_tokio::load_balancing::RoundRobinLoadBalancingStrategy;
+#[cfg(feature = "e2e-tests")]
+use cdrs_tokio::retry::NeverReconnectionPolicy;
+#[cfg(feature = "e2e-tests")]
+use std::sync::Arc;
+#[cfg(feature = "e2e-tests")]
+use std::time::Duration;
+#[cfg(feature = "e2e-tests")]
+use tokio::runtime::Handle;
+
+#[cfg(feature = "e2e-tests")]
+async fn wait_for_task_count(expected: usize) -> usize {
+    let metrics = Handle::current().metrics();
+
+    // stopped tasks need to be polled once more to finish
+    for _ in 0..50 {
+        if metrics.num_alive_tasks() <= expected {
+            break;
+        }
+
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+
+    metrics.num_alive_tasks()
+}
+
+#[tokio::test]
+#[cfg(feature = "e2e-tests")]
+async fn dropped_sessions_should_stop_background_tasks() {
+    let baseline = Handle::current().metrics().num_alive_tasks();
+
+    for _ in 0..100 {
+        let cluster_config = NodeTcpConfigBuilder::new()
+            .with_contact_point("127.0.0.1:9042".into())
+            .with_authenticator_provider(Arc::new(NoneAuthenticatorProvider))
+            .build()
+            .await
+            .unwrap();
+        let session =
+            TcpSessionBuilder::new(RoundRobinLoadBalancingStrategy::new(), cluster_config)
+                .with_reconnection_policy(Arc::new(NeverReconnectionPolicy))
+                .build()
+                .await
+                .unwrap();
+
+        session
+            .query("SELECT * FROM system.local")
+            .await
+            .expect("Query error");
+    }
+
+    assert_eq!(wait_for_task_count(baseline).await, baseline);
+}
+
+#[tokio::test]
+#[cfg(feature = "e2e-tests")]
+async fn shut_down_session_should_stop_background_tasks() {
+    let baseline = Handle::current().metrics().num_alive_tasks();
+
+    let cluster_config = NodeTcpConfigBuilder::new()
+        .with_contact_point("127.0.0.1:9042".into())
+        .with_authenticator_provider(Arc::new(NoneAuthenticatorProvider))
+        .build()
+        .await
+        .unwrap();
+    let session = TcpSessionBuilder::new(RoundRobinLoadBalancingStrategy::new(), cluster_config)
+        .with_reconnection_policy(Arc::new(NeverReconnectionPolicy))
+        .build()
+        .await
+        .unwrap();
+
+    let running = Handle::current().metrics().num_alive_tasks();
+    assert!(running > baseline);
+
+    // background tasks stop even though the session is still alive
+    session.shutdown();
+    assert!(session.is_shut_down());
+    assert!(wait_for_task_count(running - 1).await < running);
+
+    drop(session);
+    assert_eq!(wait_for_task_count(baseline).await, baseline);
+}